@@ -0,0 +1,1034 @@
+/*!
+ * Fill Plan Acceptance
+ *
+ * Between the review dialog and the fill command, a plan can drift: the
+ * vault is edited, thresholds change, the form is replanned. Accepting a plan
+ * freezes exactly what the user approved — the chosen fields, their values
+ * resolved at that instant, with the user's overrides applied — into an
+ * immutable `AcceptedPlanJson`. Fill commands are built from an acceptance,
+ * never from a live plan, and the audit entry records the acceptance id and
+ * its content hash.
+ *
+ * While the dialog is open the user can edit a kept plan one field at a
+ * time — type a value, exclude or re-include a field, or reset it — instead
+ * of regenerating it. Each change bumps the plan's revision; an acceptance
+ * that names the revision it reviewed is refused once the plan has moved on.
+ *
+ * A kept plan's id is random, so regenerating the same plan mints a new one.
+ * Its `planHash` is not: it covers the form fingerprint, each matched field
+ * with its vault key and value hash, the unmatched fields and the
+ * disposition thresholds, independent of field order. Acceptances, the
+ * fill commands built from them and their audit entries carry it, and
+ * `find_by_hash` recognizes a plan as one seen before.
+ */
+
+use asterisk_vault::VaultItem;
+use chrono::{DateTime, Duration, Utc};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::matching::{FillPlanJson, MatchTier, REVIEW_THRESHOLD, SAFE_AUTO_THRESHOLD};
+use crate::redact::value_hash;
+use crate::{Disposition, FieldFillJson, FieldNodeJson, FillCommandJson};
+
+/// Generated plans kept for acceptance, most recent last
+const MAX_PLANS: usize = 20;
+
+/// Acceptances kept so audit entries and commands can refer back to them
+const MAX_ACCEPTED: usize = 100;
+
+/// Lifetime of a fill command built from an acceptance
+const COMMAND_MINUTES: i64 = 5;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// A user's change to a field before accepting the plan
+///
+/// A `value` is used as is; otherwise `vault_key` picks the vault item whose
+/// value is used. Overrides may also add fields the plan left unmatched.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct PlanOverrideJson {
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    #[serde(rename = "vaultKey", skip_serializing_if = "Option::is_none", default)]
+    pub vault_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub value: Option<String>,
+}
+
+/// One field as approved, with its value resolved at acceptance
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AcceptedFieldJson {
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    #[serde(rename = "vaultKey", skip_serializing_if = "Option::is_none")]
+    pub vault_key: Option<String>,
+    pub value: String,
+    /// Tier of the plan's recommendation; absent for fields the user set
+    #[serde(rename = "matchTier", skip_serializing_if = "Option::is_none")]
+    pub match_tier: Option<MatchTier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    /// Whether a user override decided this field
+    pub overridden: bool,
+}
+
+/// Who decided a plan field's value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlanFieldSource {
+    /// The plan's recommendation (or no value, for unmatched fields)
+    Plan,
+    /// A value the user typed
+    Manual,
+}
+
+/// One field of a kept plan with the user's edits applied
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlanFieldJson {
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    #[serde(rename = "vaultKey", skip_serializing_if = "Option::is_none")]
+    pub vault_key: Option<String>,
+    /// A typed or composed value; otherwise the value of `vault_key`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    #[serde(rename = "matchTier", skip_serializing_if = "Option::is_none")]
+    pub match_tier: Option<MatchTier>,
+    pub disposition: Disposition,
+    pub source: PlanFieldSource,
+    pub overridden: bool,
+    pub excluded: bool,
+    /// maxLength and pattern problems with a typed value
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub warnings: Vec<String>,
+    /// The plan's revision after this change
+    #[serde(rename = "planRevision")]
+    pub plan_revision: u64,
+}
+
+/// An immutable record of what the user approved
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AcceptedPlanJson {
+    pub id: String,
+    #[serde(rename = "planId")]
+    pub plan_id: String,
+    /// Content hash of the plan (see `plan_hash`)
+    #[serde(rename = "planHash")]
+    pub plan_hash: String,
+    pub domain: String,
+    pub url: String,
+    #[serde(rename = "formFingerprint")]
+    pub form_fingerprint: String,
+    pub fields: Vec<AcceptedFieldJson>,
+    #[serde(rename = "acceptedAt")]
+    pub accepted_at: String,
+    /// Hex SHA-256 over the fields above
+    #[serde(rename = "contentHash")]
+    pub content_hash: String,
+}
+
+/// The content an acceptance hash covers
+#[derive(Serialize)]
+struct HashedContent<'a> {
+    id: &'a str,
+    plan_id: &'a str,
+    plan_hash: &'a str,
+    domain: &'a str,
+    url: &'a str,
+    form_fingerprint: &'a str,
+    fields: &'a [AcceptedFieldJson],
+    accepted_at: &'a str,
+}
+
+impl AcceptedPlanJson {
+    fn compute_hash(&self) -> String {
+        let content = HashedContent {
+            id: &self.id,
+            plan_id: &self.plan_id,
+            plan_hash: &self.plan_hash,
+            domain: &self.domain,
+            url: &self.url,
+            form_fingerprint: &self.form_fingerprint,
+            fields: &self.fields,
+            accepted_at: &self.accepted_at,
+        };
+        let bytes = serde_json::to_vec(&content).unwrap_or_default();
+        Sha256::digest(&bytes)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+}
+
+/// The content a plan hash covers
+#[derive(Serialize)]
+struct PlanContent<'a> {
+    form_fingerprint: &'a str,
+    /// Field id, vault key and value hash, sorted by field id
+    fields: Vec<(&'a str, &'a str, Option<String>)>,
+    unmatched_fields: Vec<&'a str>,
+    safe_auto_threshold: f64,
+    review_threshold: f64,
+}
+
+/// Hex SHA-256 identifying `plan`'s content, stable across regenerations
+///
+/// Values enter only as `value_hash`es, resolved from `vault_items` like
+/// acceptance does; a key missing from the vault hashes as no value.
+/// Confidences and reasons are left out, so a plan re-scored to the same
+/// matches keeps its hash.
+pub fn plan_hash(plan: &FillPlanJson, vault_items: &[VaultItem]) -> String {
+    let mut fields: Vec<(&str, &str, Option<String>)> = plan
+        .recommendations
+        .iter()
+        .map(|r| {
+            let value = r.value.as_deref().or_else(|| {
+                vault_items
+                    .iter()
+                    .find(|item| item.key == r.vault_key)
+                    .map(|item| item.value.as_str())
+            });
+            (
+                r.field_id.as_str(),
+                r.vault_key.as_str(),
+                value.map(value_hash),
+            )
+        })
+        .collect();
+    fields.sort();
+    let mut unmatched_fields: Vec<&str> =
+        plan.unmatched_fields.iter().map(String::as_str).collect();
+    unmatched_fields.sort_unstable();
+    let content = PlanContent {
+        form_fingerprint: &plan.form_fingerprint,
+        fields,
+        unmatched_fields,
+        safe_auto_threshold: SAFE_AUTO_THRESHOLD,
+        review_threshold: REVIEW_THRESHOLD,
+    };
+    let bytes = serde_json::to_vec(&content).unwrap_or_default();
+    Sha256::digest(&bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn random_id(prefix: &str) -> String {
+    let mut buf = [0u8; 8];
+    OsRng.fill_bytes(&mut buf);
+    let hex: String = buf.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}", prefix, hex)
+}
+
+/// The user's edits to one field of a kept plan
+#[derive(Debug, Clone, Default, PartialEq)]
+struct FieldEdit {
+    value: Option<String>,
+    excluded: bool,
+}
+
+struct StoredPlan {
+    plan: FillPlanJson,
+    domain: String,
+    /// The snapshot's fields, for checking typed values
+    fields: Vec<FieldNodeJson>,
+    /// Bumped by every edit; new plans start at 0
+    revision: u64,
+    edits: HashMap<String, FieldEdit>,
+}
+
+impl StoredPlan {
+    fn has_field(&self, field_id: &str) -> bool {
+        self.plan
+            .recommendations
+            .iter()
+            .any(|r| r.field_id == field_id)
+            || self.plan.unmatched_fields.iter().any(|id| id == field_id)
+    }
+
+    fn field_json(&self, field_id: &str) -> PlanFieldJson {
+        let edit = self.edits.get(field_id).cloned().unwrap_or_default();
+        let recommendation = self
+            .plan
+            .recommendations
+            .iter()
+            .find(|r| r.field_id == field_id);
+        let mut json = PlanFieldJson {
+            field_id: field_id.to_string(),
+            vault_key: None,
+            value: None,
+            confidence: None,
+            match_tier: None,
+            disposition: Disposition::Blocked,
+            source: PlanFieldSource::Plan,
+            overridden: false,
+            excluded: edit.excluded,
+            warnings: Vec::new(),
+            plan_revision: self.revision,
+        };
+        match (edit.value, recommendation) {
+            (Some(value), _) => {
+                if let Some(field) = self.fields.iter().find(|f| f.id == field_id) {
+                    json.warnings = crate::fill::check_manual_value(field, &value)
+                        .iter()
+                        .map(crate::i18n::Message::render)
+                        .collect();
+                }
+                json.value = Some(value);
+                json.disposition = Disposition::Review;
+                json.source = PlanFieldSource::Manual;
+                json.overridden = true;
+            }
+            (None, Some(r)) => {
+                json.vault_key = Some(r.vault_key.clone());
+                json.value = r.value.clone();
+                json.confidence = Some(r.confidence);
+                json.match_tier = Some(r.match_tier);
+                json.disposition = Disposition::for_confidence(r.confidence);
+            }
+            (None, None) => {}
+        }
+        json
+    }
+}
+
+struct Acceptance {
+    plan: AcceptedPlanJson,
+    /// The fill command built from it, once there is one
+    command_id: Option<String>,
+}
+
+// ============================================================================
+// Acceptance Store
+// ============================================================================
+
+/// Recently generated plans and the acceptances made from them
+#[derive(Default)]
+pub struct AcceptanceStore {
+    plans: VecDeque<(String, StoredPlan)>,
+    accepted: VecDeque<Acceptance>,
+}
+
+impl AcceptanceStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep a generated plan for `domain` so it can be edited and accepted;
+    /// returns the plan with its new id and hash set
+    ///
+    /// `fields` are the planned snapshot's fields, used to check values the
+    /// user types; `vault_items` supply the values the hash covers.
+    pub fn register_plan(
+        &mut self,
+        mut plan: FillPlanJson,
+        domain: &str,
+        fields: &[FieldNodeJson],
+        vault_items: &[VaultItem],
+    ) -> FillPlanJson {
+        let id = random_id("plan");
+        plan.plan_id = Some(id.clone());
+        plan.plan_hash = Some(plan_hash(&plan, vault_items));
+        self.plans.push_back((
+            id,
+            StoredPlan {
+                plan: plan.clone(),
+                domain: domain.to_string(),
+                fields: fields.to_vec(),
+                revision: 0,
+                edits: HashMap::new(),
+            },
+        ));
+        while self.plans.len() > MAX_PLANS {
+            self.plans.pop_front();
+        }
+        plan
+    }
+
+    /// Kept plans with content hash `hash`, oldest first
+    pub fn find_by_hash(&self, hash: &str) -> Vec<FillPlanJson> {
+        self.plans
+            .iter()
+            .filter(|(_, stored)| stored.plan.plan_hash.as_deref() == Some(hash))
+            .map(|(_, stored)| stored.plan.clone())
+            .collect()
+    }
+
+    fn stored(&self, plan_id: &str) -> Result<&StoredPlan, String> {
+        self.plans
+            .iter()
+            .find(|(id, _)| id == plan_id)
+            .map(|(_, stored)| stored)
+            .ok_or_else(|| format!("Fill plan {} not found; generate a new plan", plan_id))
+    }
+
+    /// Apply `change` to one field's edits, bumping the plan's revision if
+    /// anything changed; returns the field as it now stands
+    fn edit_field(
+        &mut self,
+        plan_id: &str,
+        field_id: &str,
+        change: impl FnOnce(&mut FieldEdit),
+    ) -> Result<PlanFieldJson, String> {
+        let (_, stored) = self
+            .plans
+            .iter_mut()
+            .find(|(id, _)| id == plan_id)
+            .ok_or_else(|| format!("Fill plan {} not found; generate a new plan", plan_id))?;
+        if !stored.has_field(field_id) {
+            return Err(format!("Field {} is not in plan {}", field_id, plan_id));
+        }
+        let edit = stored.edits.entry(field_id.to_string()).or_default();
+        let before = edit.clone();
+        change(edit);
+        if *edit != before {
+            stored.revision += 1;
+        }
+        if *edit == FieldEdit::default() {
+            stored.edits.remove(field_id);
+        }
+        Ok(stored.field_json(field_id))
+    }
+
+    /// Fill `field_id` with a typed value instead of the plan's match
+    ///
+    /// The value is kept even if it breaks the field's maxLength or
+    /// pattern; the returned field carries those warnings.
+    pub fn set_field_value(
+        &mut self,
+        plan_id: &str,
+        field_id: &str,
+        value: &str,
+    ) -> Result<PlanFieldJson, String> {
+        self.edit_field(plan_id, field_id, |edit| {
+            edit.value = Some(value.to_string())
+        })
+    }
+
+    /// Leave `field_id` out of the fill
+    pub fn exclude_field(
+        &mut self,
+        plan_id: &str,
+        field_id: &str,
+    ) -> Result<PlanFieldJson, String> {
+        self.edit_field(plan_id, field_id, |edit| edit.excluded = true)
+    }
+
+    /// Undo `exclude_field`, keeping any typed value
+    pub fn include_field(
+        &mut self,
+        plan_id: &str,
+        field_id: &str,
+    ) -> Result<PlanFieldJson, String> {
+        self.edit_field(plan_id, field_id, |edit| edit.excluded = false)
+    }
+
+    /// Drop every edit to `field_id`, back to the plan's recommendation
+    pub fn reset_field(&mut self, plan_id: &str, field_id: &str) -> Result<PlanFieldJson, String> {
+        self.edit_field(plan_id, field_id, |edit| *edit = FieldEdit::default())
+    }
+
+    /// Freeze the fields of `plan_id` the user kept, with overrides applied
+    ///
+    /// Values are resolved from `vault_items` now; later vault edits don't
+    /// reach the acceptance. Every accepted field must be in the plan or
+    /// have an override that gives it a value. Values typed with
+    /// `set_field_value` count as overrides, below any in `overrides`, and
+    /// excluded fields can't be accepted. With `reviewed_revision`, the plan
+    /// must not have been edited since that revision.
+    pub fn accept(
+        &mut self,
+        plan_id: &str,
+        reviewed_revision: Option<u64>,
+        accepted_field_ids: &[String],
+        overrides: &[PlanOverrideJson],
+        vault_items: &[VaultItem],
+        now: DateTime<Utc>,
+    ) -> Result<AcceptedPlanJson, String> {
+        let stored = self.stored(plan_id)?;
+        if let Some(reviewed) = reviewed_revision.filter(|r| *r != stored.revision) {
+            return Err(format!(
+                "Fill plan {} changed since revision {} (now {}); review it again",
+                plan_id, reviewed, stored.revision
+            ));
+        }
+        if accepted_field_ids.is_empty() {
+            return Err("No fields were accepted".to_string());
+        }
+        let typed: Vec<PlanOverrideJson> = stored
+            .edits
+            .iter()
+            .filter_map(|(field_id, edit)| {
+                Some(PlanOverrideJson {
+                    field_id: field_id.clone(),
+                    vault_key: None,
+                    value: Some(edit.value.clone()?),
+                })
+            })
+            .collect();
+
+        let vault_value = |key: &str| {
+            vault_items
+                .iter()
+                .find(|item| item.key == key)
+                .map(|item| item.value.clone())
+                .ok_or_else(|| format!("Vault item {} not found", key))
+        };
+
+        let mut seen = HashSet::new();
+        let mut fields = Vec::new();
+        for field_id in accepted_field_ids {
+            if !seen.insert(field_id.as_str()) {
+                continue;
+            }
+            if stored.edits.get(field_id).is_some_and(|edit| edit.excluded) {
+                return Err(format!(
+                    "Field {} was excluded from plan {}",
+                    field_id, plan_id
+                ));
+            }
+            let recommendation = stored
+                .plan
+                .recommendations
+                .iter()
+                .find(|r| &r.field_id == field_id);
+            let field_override = overrides
+                .iter()
+                .chain(&typed)
+                .find(|o| &o.field_id == field_id);
+            let field = match field_override {
+                Some(o) => {
+                    // A typed value only keeps a vault key the user named
+                    let vault_key = match &o.value {
+                        Some(_) => o.vault_key.clone(),
+                        None => o
+                            .vault_key
+                            .clone()
+                            .or_else(|| recommendation.map(|r| r.vault_key.clone())),
+                    };
+                    let value = match (&o.value, &vault_key) {
+                        (Some(value), _) => value.clone(),
+                        (None, Some(key)) => vault_value(key)?,
+                        (None, None) => {
+                            return Err(format!("Override for {} has no value", field_id))
+                        }
+                    };
+                    AcceptedFieldJson {
+                        field_id: field_id.clone(),
+                        vault_key,
+                        value,
+                        match_tier: None,
+                        confidence: None,
+                        overridden: true,
+                    }
+                }
+                None => {
+                    let r = recommendation
+                        .ok_or_else(|| format!("Field {} is not in plan {}", field_id, plan_id))?;
+                    let value = match &r.value {
+                        Some(value) => value.clone(),
+                        None => vault_value(&r.vault_key)?,
+                    };
+                    AcceptedFieldJson {
+                        field_id: field_id.clone(),
+                        vault_key: Some(r.vault_key.clone()),
+                        value,
+                        match_tier: Some(r.match_tier),
+                        confidence: Some(r.confidence),
+                        overridden: false,
+                    }
+                }
+            };
+            fields.push(field);
+        }
+
+        let mut accepted = AcceptedPlanJson {
+            id: random_id("acc"),
+            plan_id: plan_id.to_string(),
+            plan_hash: stored.plan.plan_hash.clone().unwrap_or_default(),
+            domain: stored.domain.clone(),
+            url: stored.plan.form_id.clone(),
+            form_fingerprint: stored.plan.form_fingerprint.clone(),
+            fields,
+            accepted_at: now.to_rfc3339(),
+            content_hash: String::new(),
+        };
+        accepted.content_hash = accepted.compute_hash();
+
+        self.accepted.push_back(Acceptance {
+            plan: accepted.clone(),
+            command_id: None,
+        });
+        while self.accepted.len() > MAX_ACCEPTED {
+            self.accepted.pop_front();
+        }
+        Ok(accepted)
+    }
+
+    /// An acceptance by id
+    pub fn get(&self, acceptance_id: &str) -> Option<&AcceptedPlanJson> {
+        self.find(acceptance_id).map(|a| &a.plan)
+    }
+
+    /// Id of the fill command built from an acceptance, if any
+    pub fn command_id(&self, acceptance_id: &str) -> Option<&str> {
+        self.find(acceptance_id)?.command_id.as_deref()
+    }
+
+    fn find(&self, acceptance_id: &str) -> Option<&Acceptance> {
+        self.accepted.iter().find(|a| a.plan.id == acceptance_id)
+    }
+
+    /// Build the fill command for an acceptance
+    ///
+    /// Each acceptance yields at most one command, filled with exactly the
+    /// values frozen at acceptance.
+    pub fn create_command(
+        &mut self,
+        acceptance_id: &str,
+        capture_undo: bool,
+        now: DateTime<Utc>,
+    ) -> Result<FillCommandJson, String> {
+        let acceptance = self
+            .accepted
+            .iter_mut()
+            .find(|a| a.plan.id == acceptance_id)
+            .ok_or_else(|| format!("Acceptance {} not found", acceptance_id))?;
+        if let Some(command_id) = &acceptance.command_id {
+            return Err(format!(
+                "Acceptance {} was already used by fill command {}",
+                acceptance_id, command_id
+            ));
+        }
+
+        let plan = &acceptance.plan;
+        let command = FillCommandJson {
+            id: format!("fill-{}", plan.id),
+            target_domain: plan.domain.clone(),
+            target_url: Some(plan.url.clone()).filter(|url| !url.is_empty()),
+            fills: plan
+                .fields
+                .iter()
+                .map(|f| FieldFillJson {
+                    field_id: f.field_id.clone(),
+                    value: f.value.clone(),
+                })
+                .collect(),
+            created_at: now.to_rfc3339(),
+            expires_at: (now + Duration::minutes(COMMAND_MINUTES)).to_rfc3339(),
+            capture_undo,
+            plan_hash: Some(plan.plan_hash.clone()),
+        };
+        acceptance.command_id = Some(command.id.clone());
+        Ok(command)
+    }
+
+    /// Check an audit entry's claim to an acceptance; returns its hash
+    pub fn verify(
+        &self,
+        acceptance_id: &str,
+        claimed_hash: Option<&str>,
+    ) -> Result<String, String> {
+        let plan = self
+            .get(acceptance_id)
+            .ok_or_else(|| format!("Acceptance {} not found", acceptance_id))?;
+        match claimed_hash {
+            Some(hash) if hash != plan.content_hash => Err(format!(
+                "Content hash does not match acceptance {}",
+                acceptance_id
+            )),
+            _ => Ok(plan.content_hash.clone()),
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::FillRecommendationJson;
+    use asterisk_vault::{test_util, VaultCategory};
+
+    fn item(key: &str, value: &str) -> VaultItem {
+        test_util::item(key, value, VaultCategory::Contact)
+    }
+
+    fn recommendation(field_id: &str, vault_key: &str) -> FillRecommendationJson {
+        FillRecommendationJson {
+            field_id: field_id.to_string(),
+            vault_key: vault_key.to_string(),
+            confidence: 0.95,
+            reason: "test".to_string(),
+            reason_message: None,
+            required: false,
+            match_tier: MatchTier::Autocomplete,
+            label_source: None,
+            value: None,
+        }
+    }
+
+    fn plan() -> FillPlanJson {
+        FillPlanJson {
+            plan_id: None,
+            plan_hash: None,
+            form_fingerprint: "fp".to_string(),
+            form_id: "https://example.com/signup".to_string(),
+            recommendations: vec![
+                recommendation("email", "email"),
+                recommendation("name", "firstName"),
+                recommendation("phone", "phone"),
+            ],
+            unmatched_fields: vec!["company".to_string()],
+            overall_confidence: 0.95,
+            generated_at: "2026-01-01T00:00:00Z".to_string(),
+            required_fields_covered: 0,
+            total_required_fields: 0,
+            warnings: vec![],
+        }
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        "2026-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::minutes(minutes)
+    }
+
+    fn vault() -> Vec<VaultItem> {
+        vec![
+            item("email", "ada@example.com"),
+            item("firstName", "Ada"),
+            item("phone", "555-0100"),
+            item("company", "Analytical Engines"),
+        ]
+    }
+
+    #[test]
+    fn test_accept_freezes_chosen_fields_and_overrides() {
+        let mut store = AcceptanceStore::new();
+        let plan = store.register_plan(plan(), "example.com", &[], &vault());
+        let plan_id = plan.plan_id.unwrap();
+
+        let accepted = store
+            .accept(
+                &plan_id,
+                None,
+                &ids(&["email", "name", "company"]),
+                &[
+                    PlanOverrideJson {
+                        field_id: "name".to_string(),
+                        value: Some("Augusta".to_string()),
+                        ..Default::default()
+                    },
+                    PlanOverrideJson {
+                        field_id: "company".to_string(),
+                        vault_key: Some("company".to_string()),
+                        ..Default::default()
+                    },
+                ],
+                &vault(),
+                at(0),
+            )
+            .unwrap();
+
+        let fields: Vec<(&str, Option<&str>, &str, bool)> = accepted
+            .fields
+            .iter()
+            .map(|f| {
+                (
+                    f.field_id.as_str(),
+                    f.vault_key.as_deref(),
+                    f.value.as_str(),
+                    f.overridden,
+                )
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("email", Some("email"), "ada@example.com", false),
+                ("name", None, "Augusta", true),
+                ("company", Some("company"), "Analytical Engines", true),
+            ]
+        );
+        assert_eq!(accepted.domain, "example.com");
+        assert_eq!(accepted.content_hash, accepted.compute_hash());
+        assert_eq!(
+            store.verify(&accepted.id, None).unwrap(),
+            accepted.content_hash
+        );
+        assert!(store.verify(&accepted.id, Some("bogus")).is_err());
+    }
+
+    #[test]
+    fn test_plan_hash_is_stable_across_regeneration_and_order() {
+        let mut store = AcceptanceStore::new();
+        let first = store.register_plan(plan(), "example.com", &[], &vault());
+        let mut shuffled = plan();
+        shuffled.recommendations.reverse();
+        shuffled.recommendations[0].confidence = 0.5;
+        shuffled.generated_at = "2026-02-01T00:00:00Z".to_string();
+        let second = store.register_plan(shuffled, "example.com", &[], &vault());
+
+        assert_ne!(first.plan_id, second.plan_id);
+        assert_eq!(first.plan_hash, second.plan_hash);
+        let hash = first.plan_hash.clone().unwrap();
+        assert_eq!(hash.len(), 64);
+        assert!(!hash.contains("ada"));
+
+        let found = store.find_by_hash(&hash);
+        let found_ids: Vec<_> = found.iter().map(|p| p.plan_id.clone()).collect();
+        assert_eq!(found_ids, vec![first.plan_id.clone(), second.plan_id]);
+        assert!(store.find_by_hash("nope").is_empty());
+
+        // The hash travels with the acceptance and its fill command
+        let accepted = store
+            .accept(
+                first.plan_id.as_deref().unwrap(),
+                None,
+                &ids(&["email"]),
+                &[],
+                &vault(),
+                at(0),
+            )
+            .unwrap();
+        assert_eq!(accepted.plan_hash, hash);
+        let command = store.create_command(&accepted.id, false, at(0)).unwrap();
+        assert_eq!(command.plan_hash, Some(hash));
+    }
+
+    #[test]
+    fn test_near_identical_plans_hash_differently() {
+        let base = plan_hash(&plan(), &vault());
+
+        // A vault value edited since
+        let mut edited = vault();
+        edited[0].value = "ada@example.org".to_string();
+        assert_ne!(plan_hash(&plan(), &edited), base);
+
+        // Two fields' keys swapped
+        let mut swapped = plan();
+        swapped.recommendations[0].vault_key = "phone".to_string();
+        swapped.recommendations[2].vault_key = "email".to_string();
+        assert_ne!(plan_hash(&swapped, &vault()), base);
+
+        // A field moved to unmatched
+        let mut fewer = plan();
+        let phone = fewer.recommendations.pop().unwrap();
+        fewer.unmatched_fields.push(phone.field_id);
+        assert_ne!(plan_hash(&fewer, &vault()), base);
+
+        // A composed value instead of the vault item's
+        let mut composed = plan();
+        composed.recommendations[1].value = Some("Ada".to_string());
+        assert_eq!(plan_hash(&composed, &vault()), base);
+        composed.recommendations[1].value = Some("Ada L".to_string());
+        assert_ne!(plan_hash(&composed, &vault()), base);
+
+        // Another form
+        let mut other_form = plan();
+        other_form.form_fingerprint = "fp2".to_string();
+        assert_ne!(plan_hash(&other_form, &vault()), base);
+    }
+
+    #[test]
+    fn test_acceptance_is_immutable_under_later_changes() {
+        let mut store = AcceptanceStore::new();
+        let plan_id = store
+            .register_plan(plan(), "example.com", &[], &vault())
+            .plan_id
+            .unwrap();
+        let mut vault_items = vault();
+        let accepted = store
+            .accept(
+                &plan_id,
+                None,
+                &ids(&["email", "phone"]),
+                &[],
+                &vault_items,
+                at(0),
+            )
+            .unwrap();
+
+        // The vault is edited and the form replanned after acceptance
+        vault_items[0].update_value("new@example.com");
+        vault_items.retain(|item| item.key != "phone");
+        let mut replanned = plan();
+        replanned.recommendations[0].vault_key = "phone".to_string();
+        store.register_plan(replanned, "example.com", &[], &vault());
+        store
+            .accept(&plan_id, None, &ids(&["email"]), &[], &vault_items, at(1))
+            .unwrap();
+
+        assert_eq!(store.get(&accepted.id), Some(&accepted));
+        let command = store.create_command(&accepted.id, false, at(2)).unwrap();
+        let fills: Vec<(&str, &str)> = command
+            .fills
+            .iter()
+            .map(|f| (f.field_id.as_str(), f.value.as_str()))
+            .collect();
+        assert_eq!(
+            fills,
+            vec![("email", "ada@example.com"), ("phone", "555-0100")]
+        );
+        assert_eq!(command.target_domain, "example.com");
+        assert!(crate::fill::validate_command(&command).is_ok());
+        assert_eq!(
+            store
+                .verify(&accepted.id, Some(&accepted.content_hash))
+                .unwrap(),
+            accepted.content_hash
+        );
+
+        // One command per acceptance
+        assert!(store.create_command(&accepted.id, false, at(3)).is_err());
+        assert_eq!(store.command_id(&accepted.id), Some(command.id.as_str()));
+    }
+
+    #[test]
+    fn test_accept_rejects_fields_outside_plan() {
+        let mut store = AcceptanceStore::new();
+        let plan_id = store
+            .register_plan(plan(), "example.com", &[], &vault())
+            .plan_id
+            .unwrap();
+
+        assert!(store
+            .accept(&plan_id, None, &ids(&["company"]), &[], &vault(), at(0))
+            .is_err());
+        assert!(store
+            .accept(&plan_id, None, &[], &[], &vault(), at(0))
+            .is_err());
+        assert!(store
+            .accept("plan-unknown", None, &ids(&["email"]), &[], &vault(), at(0))
+            .is_err());
+        // A plan whose vault item has since been deleted can't be accepted
+        assert!(store
+            .accept(&plan_id, None, &ids(&["email"]), &[], &[], at(0))
+            .is_err());
+    }
+
+    #[test]
+    fn test_field_edits_override_then_reset() {
+        let mut store = AcceptanceStore::new();
+        let mut zip = FieldNodeJson {
+            id: "name".to_string(),
+            max_length: Some(4),
+            ..Default::default()
+        };
+        zip.validation = Some("[A-Za-z]+".to_string());
+        let plan_id = store
+            .register_plan(plan(), "example.com", &[zip], &vault())
+            .plan_id
+            .unwrap();
+
+        let typed = store.set_field_value(&plan_id, "name", "Augusta").unwrap();
+        assert_eq!(typed.value.as_deref(), Some("Augusta"));
+        assert_eq!(typed.source, PlanFieldSource::Manual);
+        assert_eq!(typed.disposition, Disposition::Review);
+        assert!(typed.overridden);
+        assert_eq!(typed.vault_key, None);
+        assert_eq!(typed.warnings.len(), 1);
+        assert_eq!(typed.plan_revision, 1);
+
+        let excluded = store.exclude_field(&plan_id, "name").unwrap();
+        assert!(excluded.excluded);
+        assert_eq!(excluded.value.as_deref(), Some("Augusta"));
+        assert_eq!(excluded.plan_revision, 2);
+        // Excluding again changes nothing
+        assert_eq!(
+            store.exclude_field(&plan_id, "name").unwrap().plan_revision,
+            2
+        );
+
+        let reset = store.reset_field(&plan_id, "name").unwrap();
+        assert_eq!(reset.source, PlanFieldSource::Plan);
+        assert_eq!(reset.vault_key.as_deref(), Some("firstName"));
+        assert_eq!(reset.match_tier, Some(MatchTier::Autocomplete));
+        assert!(!reset.overridden && !reset.excluded);
+        assert!(reset.warnings.is_empty());
+        assert_eq!(reset.plan_revision, 3);
+
+        // Unmatched fields can be given a value; unknown ones can't be edited
+        let company = store.set_field_value(&plan_id, "company", "ACME").unwrap();
+        assert_eq!(company.source, PlanFieldSource::Manual);
+        assert!(store.exclude_field(&plan_id, "nope").is_err());
+        assert!(store.include_field("plan-unknown", "name").is_err());
+    }
+
+    #[test]
+    fn test_accept_against_edited_plan() {
+        let mut store = AcceptanceStore::new();
+        let plan_id = store
+            .register_plan(plan(), "example.com", &[], &vault())
+            .plan_id
+            .unwrap();
+        store.set_field_value(&plan_id, "name", "Augusta").unwrap();
+        let revision = store
+            .exclude_field(&plan_id, "phone")
+            .unwrap()
+            .plan_revision;
+
+        // Reviewed before the edits
+        let stale = store.accept(&plan_id, Some(0), &ids(&["email"]), &[], &vault(), at(0));
+        assert!(stale.unwrap_err().contains("changed since revision 0"));
+
+        // Excluded fields can't be accepted until included again
+        assert!(store
+            .accept(
+                &plan_id,
+                Some(revision),
+                &ids(&["phone"]),
+                &[],
+                &vault(),
+                at(0)
+            )
+            .is_err());
+
+        let accepted = store
+            .accept(
+                &plan_id,
+                Some(revision),
+                &ids(&["email", "name"]),
+                &[],
+                &vault(),
+                at(0),
+            )
+            .unwrap();
+        let values: Vec<(&str, &str, bool)> = accepted
+            .fields
+            .iter()
+            .map(|f| (f.field_id.as_str(), f.value.as_str(), f.overridden))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("email", "ada@example.com", false),
+                ("name", "Augusta", true)
+            ]
+        );
+
+        let included = store.include_field(&plan_id, "phone").unwrap();
+        assert!(store
+            .accept(
+                &plan_id,
+                Some(included.plan_revision),
+                &ids(&["phone"]),
+                &[],
+                &vault(),
+                at(1),
+            )
+            .is_ok());
+    }
+}
@@ -0,0 +1,156 @@
+/*!
+ * Vault Access Log
+ *
+ * Opt-in record of reads of sensitive vault items. Each record carries the
+ * key, when it was read, and who asked — never the value.
+ *
+ * Off by default: most users don't need it, and the log itself reveals which
+ * sensitive items exist and when they were used.
+ */
+
+use asterisk_vault::VaultItem;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A single read of a sensitive vault item
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AccessRecordJson {
+    /// The vault key that was read
+    pub key: String,
+    /// ISO timestamp of the read
+    #[serde(rename = "accessedAt")]
+    pub accessed_at: String,
+    /// Caller context (e.g. "vault_get", "bridge:GET /v1/vault")
+    pub context: String,
+}
+
+/// Append-only JSONL log of sensitive reads
+#[derive(Debug)]
+pub struct AccessLog {
+    path: PathBuf,
+    enabled: AtomicBool,
+}
+
+impl AccessLog {
+    /// Create a disabled access log writing to `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            enabled: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Record a read of `item` if logging is on and the item is sensitive
+    pub fn record_read(&self, item: &VaultItem, context: &str) -> Result<(), String> {
+        if !self.is_enabled() || !item.sensitive {
+            return Ok(());
+        }
+
+        let record = AccessRecordJson {
+            key: item.key.clone(),
+            accessed_at: chrono::Utc::now().to_rfc3339(),
+            context: context.to_string(),
+        };
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create access log directory: {}", e))?;
+        }
+        let line = serde_json::to_string(&record)
+            .map_err(|e| format!("Failed to serialize access record: {}", e))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("Failed to open access log: {}", e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Failed to write access record: {}", e))
+    }
+
+    /// All access records, oldest first
+    pub fn list(&self) -> Result<Vec<AccessRecordJson>, String> {
+        let file = match fs::File::open(&self.path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to open access log: {}", e)),
+        };
+
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(record) => records.push(record),
+                Err(e) => eprintln!("[Asterisk Access] Skipping malformed record: {}", e),
+            }
+        }
+        Ok(records)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asterisk_vault::{test_util, VaultCategory};
+
+    fn item(key: &str, sensitive: bool) -> VaultItem {
+        let mut item = test_util::item(key, "secret-value", VaultCategory::Financial);
+        item.sensitive = sensitive;
+        item
+    }
+
+    fn log(name: &str) -> AccessLog {
+        let path = std::env::temp_dir()
+            .join(format!("asterisk-access-{}-{}", name, std::process::id()))
+            .join("access.jsonl");
+        let _ = fs::remove_file(&path);
+        AccessLog::new(path)
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let log = log("disabled");
+        assert!(!log.is_enabled());
+        log.record_read(&item("ssn", true), "vault_get").unwrap();
+        assert!(log.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sensitive_read_is_recorded() {
+        let log = log("sensitive");
+        log.set_enabled(true);
+        log.record_read(&item("ssn", true), "vault_get").unwrap();
+
+        let records = log.list().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].key, "ssn");
+        assert_eq!(records[0].context, "vault_get");
+
+        let raw = fs::read_to_string(&log.path).unwrap();
+        assert!(!raw.contains("secret-value"));
+    }
+
+    #[test]
+    fn test_non_sensitive_read_is_not_recorded() {
+        let log = log("plain");
+        log.set_enabled(true);
+        log.record_read(&item("email", false), "vault_get").unwrap();
+        assert!(log.list().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,394 @@
+/*!
+ * Locale-Aware Address Formatting
+ *
+ * Some forms ask for the whole address in one field, but the vault stores
+ * it as components (street, city, zip, ...). The order those components go
+ * in depends on the country: US addresses put the postal code after the
+ * state, German and French ones put it before the city, Japanese ones go
+ * from postal code down to street.
+ *
+ * Each template is a list of lines; a line is a list of components with the
+ * separator that goes before each one. Missing components are skipped along
+ * with their separator, and lines left empty are dropped, so there are never
+ * dangling commas.
+ */
+
+use asterisk_vault::{VaultCategory, VaultItem};
+
+/// The pieces of an address, as stored in the vault
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AddressComponents {
+    pub street: Option<String>,
+    pub street2: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Part {
+    Street,
+    Street2,
+    City,
+    State,
+    PostalCode,
+    Country,
+}
+
+impl AddressComponents {
+    fn get(&self, part: Part) -> Option<&str> {
+        let value = match part {
+            Part::Street => &self.street,
+            Part::Street2 => &self.street2,
+            Part::City => &self.city,
+            Part::State => &self.state,
+            Part::PostalCode => &self.postal_code,
+            Part::Country => &self.country,
+        };
+        value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+    }
+}
+
+/// A template line: each component with the separator placed before it
+/// when something precedes it on the line
+type Line = &'static [(&'static str, Part)];
+
+const US: &[Line] = &[
+    &[("", Part::Street)],
+    &[("", Part::Street2)],
+    &[
+        ("", Part::City),
+        (", ", Part::State),
+        (" ", Part::PostalCode),
+    ],
+    &[("", Part::Country)],
+];
+
+const UK: &[Line] = &[
+    &[("", Part::Street)],
+    &[("", Part::Street2)],
+    &[("", Part::City)],
+    &[("", Part::State)],
+    &[("", Part::PostalCode)],
+    &[("", Part::Country)],
+];
+
+/// Germany and France: postal code before the city
+const POSTAL_CITY: &[Line] = &[
+    &[("", Part::Street)],
+    &[("", Part::Street2)],
+    &[("", Part::PostalCode), (" ", Part::City)],
+    &[("", Part::Country)],
+];
+
+/// Japan: largest unit first
+const JP: &[Line] = &[
+    &[("〒", Part::PostalCode)],
+    &[("", Part::State), (" ", Part::City)],
+    &[("", Part::Street)],
+    &[("", Part::Street2)],
+    &[("", Part::Country)],
+];
+
+const GENERIC: &[Line] = &[
+    &[("", Part::Street)],
+    &[("", Part::Street2)],
+    &[("", Part::City), (", ", Part::State)],
+    &[("", Part::PostalCode)],
+    &[("", Part::Country)],
+];
+
+/// ISO code for a country given as a code or a common name
+pub fn country_code(country: &str) -> Option<&'static str> {
+    let normalized: String = country
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter(|c| *c != '.')
+        .collect();
+    let code = match normalized.as_str() {
+        "us" | "usa" | "united states" | "united states of america" | "america" => "US",
+        "gb" | "uk" | "united kingdom" | "great britain" | "england" | "scotland" | "wales" => "GB",
+        "de" | "deu" | "germany" | "deutschland" => "DE",
+        "fr" | "fra" | "france" => "FR",
+        "jp" | "jpn" | "japan" | "日本" => "JP",
+        _ => return None,
+    };
+    Some(code)
+}
+
+fn template(country: &str) -> &'static [Line] {
+    match country_code(country) {
+        Some("US") => US,
+        Some("GB") => UK,
+        Some("DE") | Some("FR") => POSTAL_CITY,
+        Some("JP") => JP,
+        _ => GENERIC,
+    }
+}
+
+fn is_marker(separator: &str) -> bool {
+    separator.chars().all(|c| c != ',' && !c.is_whitespace())
+}
+
+/// The address as lines, ordered for `country`; empty lines are dropped
+pub fn address_lines(components: &AddressComponents, country: &str) -> Vec<String> {
+    template(country)
+        .iter()
+        .filter_map(|line| {
+            let mut rendered = String::new();
+            for (separator, part) in line.iter() {
+                let Some(value) = components.get(*part) else {
+                    continue;
+                };
+                // Separators join components; only a marker like 〒 may lead
+                if !rendered.is_empty() || is_marker(separator) {
+                    rendered.push_str(separator);
+                }
+                rendered.push_str(value);
+            }
+            (!rendered.is_empty()).then_some(rendered)
+        })
+        .collect()
+}
+
+/// The address on a single line, ordered for `country`
+pub fn format_address(components: &AddressComponents, country: &str) -> String {
+    address_lines(components, country).join(", ")
+}
+
+// ============================================================================
+// Vault Components
+// ============================================================================
+
+/// Key patterns for each component, most specific first
+const STREET_KEYS: &[&str] = &["street", "address1", "addressLine1"];
+const STREET2_KEYS: &[&str] = &["street2", "address2", "addressLine2"];
+const CITY_KEYS: &[&str] = &["city"];
+const STATE_KEYS: &[&str] = &["state", "province", "region"];
+const POSTAL_KEYS: &[&str] = &["zip", "postalCode", "postcode"];
+const COUNTRY_KEYS: &[&str] = &["country"];
+/// Keys of an item that already holds a whole address
+const FULL_ADDRESS_KEYS: &[&str] = &["fullAddress", "address"];
+
+/// Find an address item: exact key first, then a key containing the pattern
+fn find_component<'a>(items: &'a [VaultItem], patterns: &[&str]) -> Option<&'a VaultItem> {
+    let address = || {
+        items
+            .iter()
+            .filter(|i| i.category == VaultCategory::Address)
+    };
+    patterns
+        .iter()
+        .find_map(|p| address().find(|i| i.key.eq_ignore_ascii_case(p)))
+        .or_else(|| {
+            patterns.iter().find_map(|p| {
+                let p = p.to_lowercase();
+                address().find(|i| {
+                    let key = i.key.to_lowercase();
+                    // "street" must not pick up "street2"
+                    key.contains(&p) && !(p == "street" && key.contains("street2"))
+                })
+            })
+        })
+}
+
+/// Address components found in the vault, plus the key of the street item
+///
+/// Returns None when the vault doesn't store an address as components: no
+/// street, nothing to place it (city or postal code), or an item that
+/// already holds the whole address.
+pub fn vault_components(items: &[VaultItem]) -> Option<(String, AddressComponents)> {
+    let has_full_address = items.iter().any(|i| {
+        i.category == VaultCategory::Address
+            && FULL_ADDRESS_KEYS
+                .iter()
+                .any(|k| i.key.eq_ignore_ascii_case(k))
+    });
+    if has_full_address {
+        return None;
+    }
+
+    let street = find_component(items, STREET_KEYS)?;
+    let value = |patterns: &[&str]| find_component(items, patterns).map(|i| i.value.clone());
+    let components = AddressComponents {
+        street: Some(street.value.clone()),
+        street2: value(STREET2_KEYS),
+        city: value(CITY_KEYS),
+        state: value(STATE_KEYS),
+        postal_code: value(POSTAL_KEYS),
+        country: value(COUNTRY_KEYS),
+    };
+    if components.city.is_none() && components.postal_code.is_none() {
+        return None;
+    }
+    Some((street.key.clone(), components))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full() -> AddressComponents {
+        AddressComponents {
+            street: Some("1 Main St".to_string()),
+            street2: Some("Apt 2".to_string()),
+            city: Some("Springfield".to_string()),
+            state: Some("IL".to_string()),
+            postal_code: Some("62701".to_string()),
+            country: Some("United States".to_string()),
+        }
+    }
+
+    fn only(parts: &[(Part, &str)]) -> AddressComponents {
+        let mut c = AddressComponents::default();
+        for (part, value) in parts {
+            let value = Some(value.to_string());
+            match part {
+                Part::Street => c.street = value,
+                Part::Street2 => c.street2 = value,
+                Part::City => c.city = value,
+                Part::State => c.state = value,
+                Part::PostalCode => c.postal_code = value,
+                Part::Country => c.country = value,
+            }
+        }
+        c
+    }
+
+    #[test]
+    fn test_format_table() {
+        use Part::*;
+        let cases: Vec<(&str, AddressComponents, &str)> = vec![
+            (
+                "US",
+                full(),
+                "1 Main St, Apt 2, Springfield, IL 62701, United States",
+            ),
+            (
+                "usa",
+                only(&[
+                    (Street, "1 Main St"),
+                    (City, "Springfield"),
+                    (PostalCode, "62701"),
+                ]),
+                "1 Main St, Springfield 62701",
+            ),
+            (
+                "US",
+                only(&[(Street, "1 Main St"), (State, "IL"), (PostalCode, "62701")]),
+                "1 Main St, IL 62701",
+            ),
+            (
+                "United Kingdom",
+                only(&[
+                    (Street, "10 Downing St"),
+                    (City, "London"),
+                    (PostalCode, "SW1A 2AA"),
+                    (Country, "United Kingdom"),
+                ]),
+                "10 Downing St, London, SW1A 2AA, United Kingdom",
+            ),
+            (
+                "GB",
+                only(&[
+                    (Street, "1 High St"),
+                    (State, "Kent"),
+                    (PostalCode, "CT1 1AA"),
+                ]),
+                "1 High St, Kent, CT1 1AA",
+            ),
+            (
+                "DE",
+                only(&[
+                    (Street, "Hauptstr. 5"),
+                    (City, "Berlin"),
+                    (PostalCode, "10115"),
+                    (Country, "Deutschland"),
+                ]),
+                "Hauptstr. 5, 10115 Berlin, Deutschland",
+            ),
+            (
+                "Germany",
+                only(&[(Street, "Hauptstr. 5"), (City, "Berlin")]),
+                "Hauptstr. 5, Berlin",
+            ),
+            (
+                "fr",
+                only(&[
+                    (Street, "12 rue de Rivoli"),
+                    (Street2, "Bât. B"),
+                    (City, "Paris"),
+                    (PostalCode, "75001"),
+                ]),
+                "12 rue de Rivoli, Bât. B, 75001 Paris",
+            ),
+            (
+                "Japan",
+                only(&[
+                    (Street, "1-1 Chiyoda"),
+                    (City, "Chiyoda-ku"),
+                    (State, "Tokyo"),
+                    (PostalCode, "100-0001"),
+                ]),
+                "〒100-0001, Tokyo Chiyoda-ku, 1-1 Chiyoda",
+            ),
+            (
+                "JP",
+                only(&[(Street, "1-1 Chiyoda"), (City, "Chiyoda-ku")]),
+                "Chiyoda-ku, 1-1 Chiyoda",
+            ),
+            (
+                "Narnia",
+                full(),
+                "1 Main St, Apt 2, Springfield, IL, 62701, United States",
+            ),
+            (
+                "",
+                only(&[(Street, "1 Main St"), (State, "IL")]),
+                "1 Main St, IL",
+            ),
+            (
+                "US",
+                only(&[(Street, "  "), (City, "Springfield"), (State, " ")]),
+                "Springfield",
+            ),
+            ("US", AddressComponents::default(), ""),
+        ];
+
+        for (country, components, expected) in cases {
+            assert_eq!(
+                format_address(&components, country),
+                expected,
+                "country {:?}, components {:?}",
+                country,
+                components
+            );
+        }
+    }
+
+    #[test]
+    fn test_address_lines_have_no_dangling_separators() {
+        let components = only(&[(Part::State, "IL"), (Part::PostalCode, "62701")]);
+        for country in ["US", "GB", "DE", "FR", "JP", "other"] {
+            for line in address_lines(&components, country) {
+                assert!(!line.starts_with(',') && !line.starts_with(' '), "{}", line);
+                assert!(!line.ends_with(',') && !line.ends_with(' '), "{}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn test_country_codes() {
+        assert_eq!(country_code("U.S."), Some("US"));
+        assert_eq!(country_code(" england "), Some("GB"));
+        assert_eq!(country_code("DEUTSCHLAND"), Some("DE"));
+        assert_eq!(country_code("日本"), Some("JP"));
+        assert_eq!(country_code("Narnia"), None);
+    }
+}
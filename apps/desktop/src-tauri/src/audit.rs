@@ -0,0 +1,1323 @@
+/*!
+ * Audit Log Storage
+ *
+ * Fill history is partitioned by profile so different contexts don't mix:
+ * the default profile keeps the original `audit.jsonl`, any other profile
+ * writes `audit-<profile>.jsonl` next to it. Switching profile switches
+ * which log is read and written.
+ *
+ * When no data directory is writable the logs are kept in memory instead,
+ * still one per profile.
+ *
+ * Appends are idempotent by entry id: a retried append whose first attempt
+ * was written but never acknowledged is skipped rather than logged twice.
+ * The ids already in each profile's log are indexed on first append and
+ * reloaded after the log is cleared or pruned.
+ *
+ * Heavy users can keep less per fill with the detail level (`AuditDetail`,
+ * `ASTERISK_AUDIT_DETAIL` at startup): `summary` drops the per-field items,
+ * `minimal` keeps one compact line of when, where and how many. Entries are
+ * cut down as they are appended and record their level, so a log may mix
+ * levels; per-field figures (heatmap sources, never-filled keys) come from
+ * full entries only, and stats say how many of those there are.
+ */
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::maintenance::{self, MaintenanceReportJson};
+use crate::AuditEntryJson;
+
+/// Profile used until the user switches
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Log file name for a profile
+pub fn log_file_name(profile: &str) -> String {
+    if profile == DEFAULT_PROFILE {
+        "audit.jsonl".to_string()
+    } else {
+        format!("audit-{}.jsonl", profile)
+    }
+}
+
+/// Profile names end up in file names, so keep them to a safe alphabet
+pub fn validate_profile(profile: &str) -> Result<(), String> {
+    if profile.is_empty() || profile.len() > 64 {
+        return Err("Profile name must be 1-64 characters".to_string());
+    }
+    if !profile
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid profile name \"{}\": use letters, digits, '-' or '_'",
+            profile
+        ));
+    }
+    Ok(())
+}
+
+/// Environment variable setting the detail level at startup
+pub const DETAIL_ENV: &str = "ASTERISK_AUDIT_DETAIL";
+
+/// How much of each fill the audit log keeps, most first
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditDetail {
+    /// Everything reported, including every field item
+    #[default]
+    Full,
+    /// The entry without its field items
+    Summary,
+    /// Time, domain and counts only (plus the fill command, for undo)
+    Minimal,
+}
+
+impl AuditDetail {
+    pub fn is_full(&self) -> bool {
+        *self == AuditDetail::Full
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "full" => Ok(AuditDetail::Full),
+            "summary" => Ok(AuditDetail::Summary),
+            "minimal" => Ok(AuditDetail::Minimal),
+            other => Err(format!(
+                "Unknown audit detail level \"{}\": use full, summary or minimal",
+                other
+            )),
+        }
+    }
+
+    /// The level set in `DETAIL_ENV`; full when unset
+    pub fn from_env() -> Result<Self, String> {
+        std::env::var(DETAIL_ENV).map_or(Ok(AuditDetail::Full), |value| Self::parse(&value))
+    }
+
+    /// `entry` cut down to this level
+    ///
+    /// An entry that already holds less keeps its own level; nothing is
+    /// ever added back.
+    pub fn reduce(self, mut entry: AuditEntryJson) -> AuditEntryJson {
+        let detail = self.max(entry.detail);
+        if detail >= AuditDetail::Summary {
+            entry.items = Vec::new();
+        }
+        if detail == AuditDetail::Minimal {
+            entry.url = String::new();
+            entry.fingerprint = String::new();
+            entry.acceptance_id = None;
+            entry.acceptance_hash = None;
+            entry.plan_hash = None;
+        }
+        entry.detail = detail;
+        entry
+    }
+}
+
+/// How many entries were kept at each detail level
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DetailCoverageJson {
+    pub full: usize,
+    pub summary: usize,
+    pub minimal: usize,
+}
+
+impl DetailCoverageJson {
+    pub fn of<'a>(entries: impl IntoIterator<Item = &'a AuditEntryJson>) -> Self {
+        let mut coverage = Self::default();
+        for entry in entries {
+            coverage.add(entry.detail);
+        }
+        coverage
+    }
+
+    pub fn add(&mut self, detail: AuditDetail) {
+        match detail {
+            AuditDetail::Full => self.full += 1,
+            AuditDetail::Summary => self.summary += 1,
+            AuditDetail::Minimal => self.minimal += 1,
+        }
+    }
+}
+
+/// Totals over the active profile's log
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AuditStatsJson {
+    pub profile: String,
+    pub entries: usize,
+    pub planned: u64,
+    pub applied: u64,
+    pub blocked: u64,
+    pub reviewed: u64,
+    /// Distinct domains filled
+    pub domains: usize,
+    #[serde(rename = "lastEntryAt")]
+    pub last_entry_at: Option<String>,
+    /// Entries at each detail level; only full ones record fields
+    pub coverage: DetailCoverageJson,
+}
+
+/// A domain that appears in the audit log
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditDomainJson {
+    pub domain: String,
+    /// Audit entries (fills) recorded for the domain
+    pub fills: usize,
+    #[serde(rename = "lastFillAt")]
+    pub last_fill_at: String,
+}
+
+/// Vault keys listed per heatmap bucket
+pub const HEATMAP_TOP_SOURCES: usize = 5;
+
+/// Most buckets one heatmap request may produce (about ten years of days)
+pub const MAX_HEATMAP_BUCKETS: i64 = 3660;
+
+/// Width of a heatmap bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeatmapBucket {
+    Day,
+    /// Weeks start on Monday
+    Week,
+}
+
+/// How often a vault key supplied an applied field
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SourceCountJson {
+    pub key: String,
+    pub count: u64,
+}
+
+/// Fill activity in one heatmap bucket
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HeatmapBucketJson {
+    /// First local day of the bucket (YYYY-MM-DD)
+    pub start: String,
+    pub entries: usize,
+    /// Applied fields, summed over the bucket's entries
+    pub applied: u64,
+    /// Distinct domains filled
+    pub domains: usize,
+    /// Entries with field items, the ones `top_sources` is counted over
+    #[serde(rename = "fullEntries")]
+    pub full_entries: usize,
+    /// Vault keys behind the applied fields, most used first
+    #[serde(rename = "topSources")]
+    pub top_sources: Vec<SourceCountJson>,
+}
+
+fn bucket_start(day: NaiveDate, bucket: HeatmapBucket) -> NaiveDate {
+    match bucket {
+        HeatmapBucket::Day => day,
+        HeatmapBucket::Week => {
+            day - Duration::days(i64::from(day.weekday().num_days_from_monday()))
+        }
+    }
+}
+
+#[derive(Default)]
+struct BucketTally<'a> {
+    entries: usize,
+    full_entries: usize,
+    applied: u64,
+    domains: HashSet<&'a str>,
+    sources: HashMap<&'a str, u64>,
+}
+
+/// Bucket audit entries by local day or week, `from` to `to` inclusive
+///
+/// Entry times are UTC; `offset_minutes` (east of UTC) shifts them to the
+/// viewer's wall clock before bucketing. It's a fixed offset, so a range
+/// spanning a DST change is bucketed with one offset throughout. Every
+/// bucket in the range is returned, including empty ones. Entries with an
+/// unparsable time are skipped. Top sources come from full-detail entries
+/// only; each bucket says how many it had.
+pub fn usage_heatmap(
+    entries: &[AuditEntryJson],
+    from: NaiveDate,
+    to: NaiveDate,
+    bucket: HeatmapBucket,
+    offset_minutes: i32,
+) -> Result<Vec<HeatmapBucketJson>, String> {
+    if from > to {
+        return Err(format!(
+            "Heatmap range starts after it ends: {} > {}",
+            from, to
+        ));
+    }
+    let offset = offset_minutes
+        .checked_mul(60)
+        .and_then(FixedOffset::east_opt)
+        .ok_or_else(|| format!("Invalid timezone offset: {} minutes", offset_minutes))?;
+
+    let step = match bucket {
+        HeatmapBucket::Day => 1,
+        HeatmapBucket::Week => 7,
+    };
+    let first = bucket_start(from, bucket);
+    let last = bucket_start(to, bucket);
+    let count = (last - first).num_days() / step + 1;
+    if count > MAX_HEATMAP_BUCKETS {
+        return Err(format!(
+            "Heatmap range too large: {} buckets (max {})",
+            count, MAX_HEATMAP_BUCKETS
+        ));
+    }
+
+    let mut tallies: Vec<BucketTally> = (0..count).map(|_| BucketTally::default()).collect();
+    for entry in entries {
+        let Ok(at) = DateTime::parse_from_rfc3339(&entry.created_at) else {
+            continue;
+        };
+        let day = at.with_timezone(&offset).date_naive();
+        if day < from || day > to {
+            continue;
+        }
+        let index = ((bucket_start(day, bucket) - first).num_days() / step) as usize;
+        let tally = &mut tallies[index];
+        tally.entries += 1;
+        tally.applied += u64::from(entry.summary.applied_count);
+        tally.domains.insert(entry.domain.as_str());
+        if entry.detail.is_full() {
+            tally.full_entries += 1;
+        }
+        for item in entry
+            .items
+            .iter()
+            .filter(|i| i.applied && !i.source.is_empty())
+        {
+            *tally.sources.entry(item.source.as_str()).or_default() += 1;
+        }
+    }
+
+    Ok(tallies
+        .into_iter()
+        .enumerate()
+        .map(|(i, tally)| {
+            let mut top_sources: Vec<SourceCountJson> = tally
+                .sources
+                .into_iter()
+                .map(|(key, count)| SourceCountJson {
+                    key: key.to_string(),
+                    count,
+                })
+                .collect();
+            top_sources.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+            top_sources.truncate(HEATMAP_TOP_SOURCES);
+            HeatmapBucketJson {
+                start: (first + Duration::days(i as i64 * step)).to_string(),
+                entries: tally.entries,
+                applied: tally.applied,
+                domains: tally.domains.len(),
+                full_entries: tally.full_entries,
+                top_sources,
+            }
+        })
+        .collect())
+}
+
+/// Per-profile audit logs
+#[derive(Debug)]
+pub struct AuditLog {
+    dir: PathBuf,
+    profile: Mutex<String>,
+    /// Set when entries are kept in memory, keyed by profile
+    memory: Option<Mutex<HashMap<String, Vec<AuditEntryJson>>>>,
+    /// Ids already logged, keyed by profile; loaded from the log on demand
+    ids: Mutex<HashMap<String, HashSet<String>>>,
+    /// How much of each appended entry is kept
+    detail: Mutex<AuditDetail>,
+}
+
+impl AuditLog {
+    /// Logs written as JSONL files in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            profile: Mutex::new(DEFAULT_PROFILE.to_string()),
+            memory: None,
+            ids: Mutex::new(HashMap::new()),
+            detail: Mutex::new(AuditDetail::Full),
+        }
+    }
+
+    /// Logs kept in memory only
+    pub fn in_memory() -> Self {
+        Self {
+            dir: PathBuf::new(),
+            profile: Mutex::new(DEFAULT_PROFILE.to_string()),
+            memory: Some(Mutex::new(HashMap::new())),
+            ids: Mutex::new(HashMap::new()),
+            detail: Mutex::new(AuditDetail::Full),
+        }
+    }
+
+    pub fn is_in_memory(&self) -> bool {
+        self.memory.is_some()
+    }
+
+    pub fn active_profile(&self) -> Result<String, String> {
+        Ok(self.profile.lock().map_err(|e| e.to_string())?.clone())
+    }
+
+    /// Make `profile` the active profile; later reads and writes use its log
+    pub fn switch_profile(&self, profile: &str) -> Result<(), String> {
+        validate_profile(profile)?;
+        *self.profile.lock().map_err(|e| e.to_string())? = profile.to_string();
+        Ok(())
+    }
+
+    pub fn detail(&self) -> Result<AuditDetail, String> {
+        Ok(*self.detail.lock().map_err(|e| e.to_string())?)
+    }
+
+    /// Keep `detail` of entries appended from now on; logged ones are
+    /// left as they are
+    pub fn set_detail(&self, detail: AuditDetail) -> Result<(), String> {
+        *self.detail.lock().map_err(|e| e.to_string())? = detail;
+        Ok(())
+    }
+
+    /// Path of the active profile's log (None in memory mode)
+    pub fn path(&self) -> Result<Option<PathBuf>, String> {
+        if self.is_in_memory() {
+            return Ok(None);
+        }
+        self.file_path().map(Some)
+    }
+
+    fn file_path(&self) -> Result<PathBuf, String> {
+        Ok(self.dir.join(log_file_name(&self.active_profile()?)))
+    }
+
+    /// Whether the active profile's log already holds an entry with `id`
+    pub fn contains(&self, id: &str) -> Result<bool, String> {
+        let profile = self.active_profile()?;
+        let mut ids = self.ids.lock().map_err(|e| e.to_string())?;
+        Ok(self.logged_ids(&mut ids, &profile)?.contains(id))
+    }
+
+    /// Append an entry to the active profile's log
+    ///
+    /// The entry is first cut down to the log's detail level. Returns false,
+    /// writing nothing, when an entry with the same id is already logged, so
+    /// a retried append is safe.
+    pub fn append(&self, entry: AuditEntryJson) -> Result<bool, String> {
+        let profile = self.active_profile()?;
+        // Held until the write is done so concurrent retries can't both pass
+        let mut ids = self.ids.lock().map_err(|e| e.to_string())?;
+        if self.logged_ids(&mut ids, &profile)?.contains(&entry.id) {
+            println!(
+                "[Asterisk Audit] Entry {} already logged, skipping",
+                entry.id
+            );
+            return Ok(false);
+        }
+        let id = entry.id.clone();
+        let entry = self.detail()?.reduce(entry);
+        self.write_entry(&profile, entry)?;
+        self.logged_ids(&mut ids, &profile)?.insert(id);
+        Ok(true)
+    }
+
+    /// The id index for `profile`, read from its log if not loaded yet
+    fn logged_ids<'a>(
+        &self,
+        ids: &'a mut HashMap<String, HashSet<String>>,
+        profile: &str,
+    ) -> Result<&'a mut HashSet<String>, String> {
+        if !ids.contains_key(profile) {
+            let mut logged = HashSet::new();
+            self.for_each_entry_in(profile, |entry| {
+                logged.insert(entry.id);
+            })?;
+            ids.insert(profile.to_string(), logged);
+        }
+        Ok(ids.get_mut(profile).expect("index was just loaded"))
+    }
+
+    fn write_entry(&self, profile: &str, entry: AuditEntryJson) -> Result<(), String> {
+        if let Some(memory) = &self.memory {
+            memory
+                .lock()
+                .map_err(|e| e.to_string())?
+                .entry(profile.to_string())
+                .or_default()
+                .push(entry);
+            return Ok(());
+        }
+        let path = self.dir.join(log_file_name(profile));
+
+        // Ensure parent directory exists
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create audit directory: {}", e))?;
+        }
+
+        // Serialize to JSON line
+        let json_line = serde_json::to_string(&entry)
+            .map_err(|e| format!("Failed to serialize entry: {}", e))?;
+
+        // Append to file
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to open audit log: {}", e))?;
+
+        writeln!(file, "{}", json_line)
+            .map_err(|e| format!("Failed to write audit entry: {}", e))?;
+
+        println!(
+            "[Asterisk Audit] Logged entry {} for {}",
+            entry.id, entry.domain
+        );
+        Ok(())
+    }
+
+    /// Every entry in the active profile's log, in file order
+    pub fn entries(&self) -> Result<Vec<AuditEntryJson>, String> {
+        let mut entries: Vec<AuditEntryJson> = Vec::new();
+        self.for_each_entry(|entry| entries.push(entry))?;
+        Ok(entries)
+    }
+
+    /// The active profile's entry with `id`
+    ///
+    /// The id index rules out unknown ids without reading the log; a known
+    /// one is read up to its line.
+    pub fn get(&self, id: &str) -> Result<Option<AuditEntryJson>, String> {
+        if !self.contains(id)? {
+            return Ok(None);
+        }
+        let mut found = None;
+        self.try_for_each_entry_in(&self.active_profile()?, |entry| {
+            if entry.id == id {
+                found = Some(entry);
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })?;
+        Ok(found)
+    }
+
+    /// Visit each entry of the active profile's log in file order, reading
+    /// the file a line at a time rather than collecting it
+    pub fn for_each_entry(&self, visit: impl FnMut(AuditEntryJson)) -> Result<(), String> {
+        self.for_each_entry_in(&self.active_profile()?, visit)
+    }
+
+    fn for_each_entry_in(
+        &self,
+        profile: &str,
+        mut visit: impl FnMut(AuditEntryJson),
+    ) -> Result<(), String> {
+        self.try_for_each_entry_in(profile, |entry| {
+            visit(entry);
+            ControlFlow::Continue(())
+        })
+    }
+
+    /// `for_each_entry_in`, stopping once `visit` breaks
+    fn try_for_each_entry_in(
+        &self,
+        profile: &str,
+        mut visit: impl FnMut(AuditEntryJson) -> ControlFlow<()>,
+    ) -> Result<(), String> {
+        if let Some(memory) = &self.memory {
+            let memory = memory.lock().map_err(|e| e.to_string())?;
+            for entry in memory.get(profile).into_iter().flatten() {
+                if visit(entry.clone()).is_break() {
+                    break;
+                }
+            }
+            return Ok(());
+        }
+        let path = self.dir.join(log_file_name(profile));
+
+        let file = match fs::File::open(&path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                // No audit log yet
+                return Ok(());
+            }
+            Err(e) => return Err(format!("Failed to open audit log: {}", e)),
+        };
+
+        let reader = BufReader::new(file);
+        for line in reader.lines() {
+            let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<AuditEntryJson>(&line) {
+                Ok(entry) => {
+                    if visit(entry).is_break() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("[Asterisk Audit] Skipping malformed entry: {}", e);
+                    continue;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Each distinct domain in the active profile's log with its fill count
+    /// and last fill, most recently filled first
+    ///
+    /// Entries whose time can't be parsed still count as fills but don't
+    /// move the domain's last fill.
+    pub fn domains(&self) -> Result<Vec<AuditDomainJson>, String> {
+        let mut tally: HashMap<String, (usize, Option<DateTime<Utc>>, String)> = HashMap::new();
+        self.for_each_entry(|entry| {
+            let at = DateTime::parse_from_rfc3339(&entry.created_at)
+                .ok()
+                .map(|t| t.with_timezone(&Utc));
+            let (fills, last, last_raw) = tally.entry(entry.domain).or_default();
+            *fills += 1;
+            let newer = match at {
+                Some(_) => at > *last,
+                None => last_raw.is_empty(),
+            };
+            if newer {
+                *last = at.or(*last);
+                *last_raw = entry.created_at;
+            }
+        })?;
+
+        let mut domains: Vec<(AuditDomainJson, Option<DateTime<Utc>>)> = tally
+            .into_iter()
+            .map(|(domain, (fills, last, last_fill_at))| {
+                (
+                    AuditDomainJson {
+                        domain,
+                        fills,
+                        last_fill_at,
+                    },
+                    last,
+                )
+            })
+            .collect();
+        domains.sort_by(|(a, a_last), (b, b_last)| {
+            b_last.cmp(a_last).then_with(|| a.domain.cmp(&b.domain))
+        });
+        Ok(domains.into_iter().map(|(domain, _)| domain).collect())
+    }
+
+    /// The `keys` no applied field in the active profile's log came from,
+    /// sorted
+    ///
+    /// Planned fields that were never applied don't count as fills, and
+    /// only full-detail entries record fields: a key filled only while the
+    /// log kept less is listed too.
+    pub fn never_filled(&self, keys: Vec<String>) -> Result<Vec<String>, String> {
+        let mut filled = HashSet::new();
+        self.for_each_entry(|entry| {
+            filled.extend(
+                entry
+                    .items
+                    .into_iter()
+                    .filter(|item| item.applied)
+                    .map(|item| item.source),
+            );
+        })?;
+        let mut unused: Vec<String> = keys
+            .into_iter()
+            .filter(|key| !filled.contains(key))
+            .collect();
+        unused.sort();
+        Ok(unused)
+    }
+
+    /// Totals for the active profile (counts only, no field values)
+    pub fn stats(&self) -> Result<AuditStatsJson, String> {
+        let entries = self.entries()?;
+        let mut stats = AuditStatsJson {
+            profile: self.active_profile()?,
+            entries: entries.len(),
+            ..Default::default()
+        };
+        let mut domains = HashSet::new();
+        for entry in &entries {
+            stats.planned += u64::from(entry.summary.planned_count);
+            stats.applied += u64::from(entry.summary.applied_count);
+            stats.blocked += u64::from(entry.summary.blocked_count);
+            stats.reviewed += u64::from(entry.summary.reviewed_count);
+            domains.insert(entry.domain.as_str());
+            stats.coverage.add(entry.detail);
+            if stats
+                .last_entry_at
+                .as_deref()
+                .is_none_or(|last| entry.created_at.as_str() > last)
+            {
+                stats.last_entry_at = Some(entry.created_at.clone());
+            }
+        }
+        stats.domains = domains.len();
+        Ok(stats)
+    }
+
+    /// Fill activity for the active profile in time buckets; see
+    /// [`usage_heatmap`]
+    pub fn usage_heatmap(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        bucket: HeatmapBucket,
+        offset_minutes: i32,
+    ) -> Result<Vec<HeatmapBucketJson>, String> {
+        usage_heatmap(&self.entries()?, from, to, bucket, offset_minutes)
+    }
+
+    /// Clear the active profile's log (deletes the file)
+    pub fn clear(&self) -> Result<(), String> {
+        let profile = self.active_profile()?;
+        let mut ids = self.ids.lock().map_err(|e| e.to_string())?;
+        ids.remove(&profile);
+        if let Some(memory) = &self.memory {
+            memory.lock().map_err(|e| e.to_string())?.remove(&profile);
+            return Ok(());
+        }
+        let path = self.dir.join(log_file_name(&profile));
+
+        match fs::remove_file(&path) {
+            Ok(_) => {
+                println!("[Asterisk Audit] Audit log cleared");
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                // File doesn't exist, that's fine
+                Ok(())
+            }
+            Err(e) => Err(format!("Failed to clear audit log: {}", e)),
+        }
+    }
+
+    /// Remove the active profile's entries created before `cutoff`
+    pub fn prune(
+        &self,
+        cutoff: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<MaintenanceReportJson, String> {
+        let profile = self.active_profile()?;
+        let mut ids = self.ids.lock().map_err(|e| e.to_string())?;
+        if !dry_run {
+            // Reloaded from what's left on the next append
+            ids.remove(&profile);
+        }
+        if let Some(memory) = &self.memory {
+            let mut memory = memory.lock().map_err(|e| e.to_string())?;
+            let entries = memory.entry(profile).or_default();
+            return Ok(maintenance::prune_audit_entries(entries, cutoff, dry_run));
+        }
+        maintenance::prune_audit_log(&self.dir.join(log_file_name(&profile)), cutoff, dry_run)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuditItemJson, AuditSummaryJson, Disposition, RedactionLevel};
+
+    fn entry(id: &str) -> AuditEntryJson {
+        AuditEntryJson {
+            id: id.to_string(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            url: "https://example.com/form".to_string(),
+            domain: "example.com".to_string(),
+            fingerprint: "fp".to_string(),
+            summary: AuditSummaryJson {
+                planned_count: 1,
+                applied_count: 1,
+                blocked_count: 0,
+                reviewed_count: 0,
+            },
+            items: vec![],
+            recall_recorded: false,
+            fill_command_id: None,
+            acceptance_id: None,
+            acceptance_hash: None,
+            plan_hash: None,
+            detail: Default::default(),
+        }
+    }
+
+    fn ids(log: &AuditLog) -> Vec<String> {
+        log.entries().unwrap().into_iter().map(|e| e.id).collect()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("asterisk-audit-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_log_file_names() {
+        assert_eq!(log_file_name(DEFAULT_PROFILE), "audit.jsonl");
+        assert_eq!(log_file_name("work"), "audit-work.jsonl");
+    }
+
+    #[test]
+    fn test_profile_names_validated() {
+        assert!(validate_profile("work_2").is_ok());
+        assert!(validate_profile("").is_err());
+        assert!(validate_profile("../etc").is_err());
+    }
+
+    fn check_profiles_isolated(log: &AuditLog) {
+        log.append(entry("personal-1")).unwrap();
+
+        log.switch_profile("work").unwrap();
+        assert!(ids(log).is_empty());
+        log.append(entry("work-1")).unwrap();
+        assert_eq!(ids(log), vec!["work-1"]);
+
+        // Switching back restores the original log
+        log.switch_profile(DEFAULT_PROFILE).unwrap();
+        assert_eq!(ids(log), vec!["personal-1"]);
+    }
+
+    #[test]
+    fn test_profiles_are_isolated_on_disk() {
+        let dir = temp_dir("profiles");
+        let log = AuditLog::new(&dir);
+        check_profiles_isolated(&log);
+        assert!(dir.join("audit.jsonl").exists());
+        assert!(dir.join("audit-work.jsonl").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_profiles_are_isolated_in_memory() {
+        let log = AuditLog::in_memory();
+        check_profiles_isolated(&log);
+        assert!(log.path().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_stats() {
+        let log = AuditLog::in_memory();
+        assert_eq!(log.stats().unwrap().entries, 0);
+
+        let mut second = entry("b");
+        second.domain = "other.org".to_string();
+        second.created_at = "2026-02-01T00:00:00Z".to_string();
+        log.append(entry("a")).unwrap();
+        log.append(second).unwrap();
+        log.append(entry("c")).unwrap();
+
+        let stats = log.stats().unwrap();
+        assert_eq!(stats.profile, DEFAULT_PROFILE);
+        assert_eq!(stats.entries, 3);
+        assert_eq!(stats.applied, 3);
+        assert_eq!(stats.domains, 2);
+        assert_eq!(stats.last_entry_at.as_deref(), Some("2026-02-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_detail_levels_reduce_entries() {
+        let mut original = filled("a", "2026-01-01T00:00:00Z", "example.com", &["email"]);
+        original.fill_command_id = Some("cmd-1".to_string());
+        original.acceptance_id = Some("acc-1".to_string());
+        original.plan_hash = Some("plan".to_string());
+
+        let full = AuditDetail::Full.reduce(original.clone());
+        assert_eq!(full.items.len(), 1);
+        assert_eq!(full.detail, AuditDetail::Full);
+        assert!(!serde_json::to_string(&full).unwrap().contains("\"detail\""));
+
+        let summary = AuditDetail::Summary.reduce(original.clone());
+        assert!(summary.items.is_empty());
+        assert_eq!(summary.url, original.url);
+        assert_eq!(summary.plan_hash.as_deref(), Some("plan"));
+        assert_eq!(summary.summary.applied_count, 1);
+        assert_eq!(summary.detail, AuditDetail::Summary);
+
+        let minimal = AuditDetail::Minimal.reduce(original.clone());
+        assert!(minimal.items.is_empty());
+        assert_eq!(
+            (minimal.url.as_str(), minimal.fingerprint.as_str()),
+            ("", "")
+        );
+        assert_eq!(minimal.acceptance_id, None);
+        assert_eq!(minimal.plan_hash, None);
+        assert_eq!(minimal.domain, "example.com");
+        assert_eq!(minimal.created_at, original.created_at);
+        assert_eq!(minimal.summary.applied_count, 1);
+        // Kept so the fill can still be undone
+        assert_eq!(minimal.fill_command_id.as_deref(), Some("cmd-1"));
+        assert!(serde_json::to_string(&minimal)
+            .unwrap()
+            .contains("\"detail\":\"minimal\""));
+
+        // A reduced entry is never promoted back
+        assert_eq!(
+            AuditDetail::Full.reduce(minimal).detail,
+            AuditDetail::Minimal
+        );
+
+        assert_eq!(
+            AuditDetail::parse(" Summary ").unwrap(),
+            AuditDetail::Summary
+        );
+        assert!(AuditDetail::parse("verbose").is_err());
+    }
+
+    #[test]
+    fn test_mixed_detail_log_stats() {
+        let log = AuditLog::in_memory();
+        log.append(filled(
+            "a",
+            "2026-01-01T09:00:00Z",
+            "example.com",
+            &["email", "name"],
+        ))
+        .unwrap();
+        log.set_detail(AuditDetail::Summary).unwrap();
+        log.append(filled("b", "2026-01-01T10:00:00Z", "other.org", &["phone"]))
+            .unwrap();
+        log.set_detail(AuditDetail::Minimal).unwrap();
+        log.append(filled("c", "2026-01-01T11:00:00Z", "third.net", &["zip"]))
+            .unwrap();
+
+        let levels: Vec<AuditDetail> = log.entries().unwrap().iter().map(|e| e.detail).collect();
+        assert_eq!(
+            levels,
+            vec![
+                AuditDetail::Full,
+                AuditDetail::Summary,
+                AuditDetail::Minimal
+            ]
+        );
+
+        // Counts cover every entry, whatever its level
+        let stats = log.stats().unwrap();
+        assert_eq!((stats.entries, stats.applied, stats.domains), (3, 4, 3));
+        assert_eq!(
+            stats.coverage,
+            DetailCoverageJson {
+                full: 1,
+                summary: 1,
+                minimal: 1
+            }
+        );
+
+        // Field-level figures only see the full entry
+        let d = day("2026-01-01");
+        let bucket = &log.usage_heatmap(d, d, HeatmapBucket::Day, 0).unwrap()[0];
+        assert_eq!((bucket.entries, bucket.full_entries), (3, 1));
+        let sources: Vec<&str> = bucket.top_sources.iter().map(|s| s.key.as_str()).collect();
+        assert_eq!(sources, vec!["email", "name"]);
+        assert_eq!(
+            log.never_filled(vec!["email".to_string(), "zip".to_string()])
+                .unwrap(),
+            vec!["zip"]
+        );
+    }
+
+    #[test]
+    fn test_never_filled_keys() {
+        let log = AuditLog::in_memory();
+        let mut planned_only = filled("b", "2026-01-02T00:00:00Z", "example.com", &["phone"]);
+        planned_only.items[0].applied = false;
+        log.append(filled(
+            "a",
+            "2026-01-01T00:00:00Z",
+            "example.com",
+            &["email"],
+        ))
+        .unwrap();
+        log.append(planned_only).unwrap();
+
+        let keys = ["phone", "email", "company"].map(String::from).to_vec();
+        assert_eq!(log.never_filled(keys).unwrap(), vec!["company", "phone"]);
+
+        // Fills are per profile
+        log.switch_profile("work").unwrap();
+        assert_eq!(
+            log.never_filled(vec!["email".to_string()]).unwrap(),
+            vec!["email"]
+        );
+    }
+
+    #[test]
+    fn test_domains_distinct_counted_and_newest_first() {
+        let dir = temp_dir("domains");
+        let log = AuditLog::new(&dir);
+        assert!(log.domains().unwrap().is_empty());
+
+        for (id, domain, created_at) in [
+            ("1", "example.com", "2026-01-01T00:00:00Z"),
+            ("2", "shop.test", "2026-03-01T00:00:00Z"),
+            ("3", "example.com", "2026-02-15T00:00:00Z"),
+            ("4", "bank.test", "2026-02-01T00:00:00Z"),
+            ("5", "example.com", "2026-01-20T00:00:00Z"),
+            // Same instant as shop.test's last fill, written with an offset
+            ("6", "apply.test", "2026-03-01T01:00:00+01:00"),
+        ] {
+            let mut e = entry(id);
+            e.domain = domain.to_string();
+            e.created_at = created_at.to_string();
+            log.append(e).unwrap();
+        }
+
+        let domains: Vec<(String, usize, String)> = log
+            .domains()
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.domain, d.fills, d.last_fill_at))
+            .collect();
+        assert_eq!(
+            domains,
+            vec![
+                (
+                    "apply.test".to_string(),
+                    1,
+                    "2026-03-01T01:00:00+01:00".to_string()
+                ),
+                (
+                    "shop.test".to_string(),
+                    1,
+                    "2026-03-01T00:00:00Z".to_string()
+                ),
+                (
+                    "example.com".to_string(),
+                    3,
+                    "2026-02-15T00:00:00Z".to_string()
+                ),
+                (
+                    "bank.test".to_string(),
+                    1,
+                    "2026-02-01T00:00:00Z".to_string()
+                ),
+            ]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    fn filled(id: &str, created_at: &str, domain: &str, sources: &[&str]) -> AuditEntryJson {
+        let mut e = entry(id);
+        e.created_at = created_at.to_string();
+        e.domain = domain.to_string();
+        e.summary.applied_count = sources.len() as u32;
+        e.items = sources
+            .iter()
+            .enumerate()
+            .map(|(i, source)| AuditItemJson {
+                field_id: format!("f{}", i),
+                label: source.to_string(),
+                kind: "text".to_string(),
+                confidence: 1.0,
+                disposition: Disposition::Safe,
+                applied: true,
+                source: source.to_string(),
+                old_value_redacted: String::new(),
+                new_value_redacted: "••••".to_string(),
+                redaction: RedactionLevel::Masked,
+                user_confirmed: false,
+                notes: None,
+            })
+            .collect();
+        e
+    }
+
+    fn day(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    /// (start, entries) per bucket
+    fn shape(buckets: &[HeatmapBucketJson]) -> Vec<(&str, usize)> {
+        buckets
+            .iter()
+            .map(|b| (b.start.as_str(), b.entries))
+            .collect()
+    }
+
+    #[test]
+    fn test_heatmap_includes_empty_buckets() {
+        let entries = vec![filled(
+            "a",
+            "2026-01-02T12:00:00Z",
+            "example.com",
+            &["email"],
+        )];
+        let buckets = usage_heatmap(
+            &entries,
+            day("2026-01-01"),
+            day("2026-01-04"),
+            HeatmapBucket::Day,
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(
+            shape(&buckets),
+            vec![
+                ("2026-01-01", 0),
+                ("2026-01-02", 1),
+                ("2026-01-03", 0),
+                ("2026-01-04", 0)
+            ]
+        );
+        assert_eq!(buckets[0].applied, 0);
+        assert!(buckets[0].top_sources.is_empty());
+        assert_eq!(buckets[1].applied, 1);
+    }
+
+    #[test]
+    fn test_heatmap_weeks_start_on_monday() {
+        let entries = vec![
+            // Monday of the first week, but before the range starts
+            filled("a", "2025-12-29T12:00:00Z", "example.com", &["email"]),
+            filled("b", "2026-01-01T12:00:00Z", "example.com", &["email"]),
+            filled("c", "2026-01-11T23:59:59Z", "example.com", &["email"]),
+            filled("d", "2026-01-12T00:00:00Z", "example.com", &["email"]),
+        ];
+        let buckets = usage_heatmap(
+            &entries,
+            day("2026-01-01"),
+            day("2026-01-14"),
+            HeatmapBucket::Week,
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(
+            shape(&buckets),
+            vec![("2025-12-29", 1), ("2026-01-05", 1), ("2026-01-12", 1)]
+        );
+    }
+
+    #[test]
+    fn test_heatmap_offset_moves_entries_across_midnight() {
+        let entries = vec![filled(
+            "a",
+            "2026-03-10T23:30:00Z",
+            "example.com",
+            &["email"],
+        )];
+        let bucket_of = |offset| {
+            usage_heatmap(
+                &entries,
+                day("2026-03-09"),
+                day("2026-03-12"),
+                HeatmapBucket::Day,
+                offset,
+            )
+            .unwrap()
+            .into_iter()
+            .find(|b| b.entries == 1)
+            .map(|b| b.start)
+        };
+
+        assert_eq!(bucket_of(0).as_deref(), Some("2026-03-10"));
+        assert_eq!(bucket_of(60).as_deref(), Some("2026-03-11"));
+        assert_eq!(bucket_of(-300).as_deref(), Some("2026-03-10"));
+        // Half-hour zones are fine too
+        assert_eq!(bucket_of(30).as_deref(), Some("2026-03-11"));
+        assert_eq!(bucket_of(29).as_deref(), Some("2026-03-10"));
+    }
+
+    #[test]
+    fn test_heatmap_across_dst_change() {
+        // Central Europe moves from +01:00 to +02:00 at 2026-03-29T01:00Z
+        let entries = vec![
+            filled("a", "2026-03-28T23:30:00Z", "example.com", &["email"]),
+            filled("b", "2026-03-29T22:30:00Z", "example.com", &["email"]),
+        ];
+        let range = |offset| {
+            usage_heatmap(
+                &entries,
+                day("2026-03-28"),
+                day("2026-03-30"),
+                HeatmapBucket::Day,
+                offset,
+            )
+            .unwrap()
+        };
+
+        // The short local day neither drops nor repeats a bucket
+        assert_eq!(
+            shape(&range(60)),
+            vec![("2026-03-28", 0), ("2026-03-29", 2), ("2026-03-30", 0)]
+        );
+        assert_eq!(
+            shape(&range(120)),
+            vec![("2026-03-28", 0), ("2026-03-29", 1), ("2026-03-30", 1)]
+        );
+
+        // US clocks spring forward on 2026-03-08; weeks either side stay 7 days
+        let weeks = usage_heatmap(
+            &[],
+            day("2026-03-01"),
+            day("2026-03-15"),
+            HeatmapBucket::Week,
+            -300,
+        )
+        .unwrap();
+        assert_eq!(
+            shape(&weeks),
+            vec![("2026-02-23", 0), ("2026-03-02", 0), ("2026-03-09", 0)]
+        );
+    }
+
+    #[test]
+    fn test_heatmap_domains_and_top_sources() {
+        let mut entries = vec![
+            filled(
+                "a",
+                "2026-01-01T09:00:00Z",
+                "example.com",
+                &["email", "name"],
+            ),
+            filled(
+                "b",
+                "2026-01-01T10:00:00Z",
+                "other.org",
+                &["email", "phone"],
+            ),
+            filled(
+                "c",
+                "2026-01-01T11:00:00Z",
+                "example.com",
+                &["email", "a", "b", "c"],
+            ),
+        ];
+        let mut unapplied = filled("d", "2026-01-01T12:00:00Z", "third.net", &["zip"]);
+        unapplied.items[0].applied = false;
+        unapplied.summary.applied_count = 0;
+        entries.push(unapplied);
+
+        let buckets = usage_heatmap(
+            &entries,
+            day("2026-01-01"),
+            day("2026-01-01"),
+            HeatmapBucket::Day,
+            0,
+        )
+        .unwrap();
+        let bucket = &buckets[0];
+
+        assert_eq!(bucket.entries, 4);
+        assert_eq!(bucket.applied, 8);
+        assert_eq!(bucket.domains, 3);
+        let top: Vec<(&str, u64)> = bucket
+            .top_sources
+            .iter()
+            .map(|s| (s.key.as_str(), s.count))
+            .collect();
+        assert_eq!(top.len(), HEATMAP_TOP_SOURCES);
+        assert_eq!(top[0], ("email", 3));
+        // Ties break by key, so the list is stable
+        assert_eq!(&top[1..], &[("a", 1), ("b", 1), ("c", 1), ("name", 1)]);
+    }
+
+    #[test]
+    fn test_heatmap_rejects_bad_ranges() {
+        let d = day("2026-01-01");
+        assert!(usage_heatmap(&[], d, d - Duration::days(1), HeatmapBucket::Day, 0).is_err());
+        assert!(usage_heatmap(&[], d, d, HeatmapBucket::Day, 24 * 60).is_err());
+        assert!(usage_heatmap(
+            &[],
+            d,
+            d + Duration::days(MAX_HEATMAP_BUCKETS),
+            HeatmapBucket::Day,
+            0
+        )
+        .is_err());
+        assert!(usage_heatmap(
+            &[],
+            d,
+            d + Duration::days(MAX_HEATMAP_BUCKETS),
+            HeatmapBucket::Week,
+            0
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_clear_only_affects_active_profile() {
+        let log = AuditLog::in_memory();
+        log.append(entry("a")).unwrap();
+        log.switch_profile("work").unwrap();
+        log.append(entry("b")).unwrap();
+        log.clear().unwrap();
+        assert!(ids(&log).is_empty());
+
+        log.switch_profile(DEFAULT_PROFILE).unwrap();
+        assert_eq!(ids(&log), vec!["a"]);
+    }
+
+    #[test]
+    fn test_appending_same_id_twice_stores_it_once() {
+        let log = AuditLog::in_memory();
+        assert!(log.append(entry("a")).unwrap());
+        assert!(!log.append(entry("a")).unwrap());
+        assert!(log.contains("a").unwrap());
+        assert_eq!(ids(&log), vec!["a"]);
+
+        // Ids are per profile
+        log.switch_profile("work").unwrap();
+        assert!(!log.contains("a").unwrap());
+        assert!(log.append(entry("a")).unwrap());
+    }
+
+    #[test]
+    fn test_get_finds_entry_by_id() {
+        let dir = temp_dir("get");
+        let log = AuditLog::new(&dir);
+        for log in [&log, &AuditLog::in_memory()] {
+            log.append(entry("a")).unwrap();
+            log.append(entry("b")).unwrap();
+            assert_eq!(log.get("b").unwrap().map(|e| e.id).as_deref(), Some("b"));
+            assert!(log.get("c").unwrap().is_none());
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_distinct_ids_both_store() {
+        let log = AuditLog::in_memory();
+        assert!(log.append(entry("a")).unwrap());
+        assert!(log.append(entry("b")).unwrap());
+        assert_eq!(ids(&log), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_dedupe_survives_restart_and_resets_on_clear() {
+        let dir = temp_dir("dedupe");
+        AuditLog::new(&dir).append(entry("a")).unwrap();
+
+        // A fresh log indexes what's already on disk
+        let log = AuditLog::new(&dir);
+        assert!(!log.append(entry("a")).unwrap());
+        assert_eq!(ids(&log), vec!["a"]);
+
+        log.clear().unwrap();
+        assert!(log.append(entry("a")).unwrap());
+        assert_eq!(ids(&log), vec!["a"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
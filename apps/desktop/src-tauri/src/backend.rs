@@ -0,0 +1,555 @@
+/*!
+ * Vault Backend Switching
+ *
+ * Replaces the active `VaultStore` at runtime, for testing and migration:
+ * the new backend is opened, every item of the current one is copied into it
+ * (`asterisk_vault::migrate`), and it takes the old one's place inside the
+ * shared vault.
+ *
+ * The vault's lock is held from the copy to the replacement, so a command
+ * or bridge request running alongside sees either the old store or the new
+ * one, never a half-migrated one. If opening or copying fails, the old
+ * store stays active untouched.
+ *
+ * At startup the app opens the encrypted vault under a master key kept in
+ * the OS keychain (`open_keychain_vault`), creating the key on first run.
+ * It has its own file, so switching to the passphrase-encrypted backend
+ * never tries a passphrase on it. Developers can start with the plain JSON
+ * file store instead by setting `JSON_VAULT_ENV`, to keep test data across
+ * restarts without the encrypted backend.
+ */
+
+use asterisk_vault::{
+    EncryptedFileStore, InMemoryStore, JournaledFileStore, JsonFileStore, KeyringBackend,
+    LockableStore, MasterKey, Result as VaultResult, VaultCategory, VaultItem, VaultStore,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::shared::SharedVault;
+
+/// File name of the journaled vault in the data directory
+pub const VAULT_FILE: &str = "vault.json";
+
+/// File name of the passphrase-encrypted vault in the data directory
+pub const ENCRYPTED_VAULT_FILE: &str = "vault.enc";
+
+/// File name of the keychain-encrypted vault opened at startup
+///
+/// Kept apart from `ENCRYPTED_VAULT_FILE`, which no passphrase could open
+/// if it held the keychain vault.
+pub const KEYCHAIN_VAULT_FILE: &str = "vault.keychain.enc";
+
+/// File name of the development JSON vault in the data directory
+pub const JSON_VAULT_FILE: &str = "vault.dev.json";
+
+/// Environment variable that starts the app on the JSON file store
+pub const JSON_VAULT_ENV: &str = "ASTERISK_JSON_VAULT";
+
+/// Whether `JSON_VAULT_ENV` asks for the JSON file store
+pub fn json_vault_from_env() -> bool {
+    json_vault_from_setting(std::env::var(JSON_VAULT_ENV).ok().as_deref())
+}
+
+/// "1", "true", "yes" or "on" enable the JSON file store; anything else
+/// keeps the in-memory one
+pub fn json_vault_from_setting(value: Option<&str>) -> bool {
+    matches!(
+        value.map(|v| v.trim().to_ascii_lowercase()).as_deref(),
+        Some("1" | "true" | "yes" | "on")
+    )
+}
+
+/// The vault backends that can be switched to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultBackendKind {
+    /// Volatile, for development and tests
+    Memory,
+    /// Plain JSON file rewritten on every change, for development
+    /// (`JsonFileStore`)
+    Json,
+    /// JSON file with a crash-safe journal (`JournaledFileStore`)
+    Journaled,
+    /// Passphrase-encrypted file that `vault_lock` can lock until the
+    /// passphrase is given again (`LockableStore`)
+    Encrypted,
+}
+
+/// Backend-specific options for `vault_switch_backend`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VaultBackendOptionsJson {
+    /// Vault file for file-backed stores; defaults to `VAULT_FILE` (or
+    /// `JSON_VAULT_FILE`, `ENCRYPTED_VAULT_FILE`) in the data directory
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Passphrase the encrypted store's key is derived from
+    #[serde(default)]
+    pub passphrase: Option<String>,
+}
+
+/// Outcome of a backend switch
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VaultBackendSwitchJson {
+    pub kind: VaultBackendKind,
+    /// Items copied from the previous backend
+    pub migrated: usize,
+    /// Items in the new backend afterwards, including any it already held
+    pub items: usize,
+}
+
+/// The file a store of `kind` would open, or None for the in-memory store
+///
+/// `data_dir` is None when running without a writable data directory, in
+/// which case file-backed stores need an explicit path.
+pub fn backend_file(
+    kind: VaultBackendKind,
+    options: &VaultBackendOptionsJson,
+    data_dir: Option<&Path>,
+) -> Result<Option<PathBuf>, String> {
+    let default_name = match kind {
+        VaultBackendKind::Memory => return Ok(None),
+        VaultBackendKind::Json => JSON_VAULT_FILE,
+        VaultBackendKind::Journaled => VAULT_FILE,
+        VaultBackendKind::Encrypted => ENCRYPTED_VAULT_FILE,
+    };
+    match (&options.path, data_dir) {
+        (Some(path), _) => Ok(Some(PathBuf::from(path))),
+        (None, Some(dir)) => Ok(Some(dir.join(default_name))),
+        (None, None) => {
+            Err("No data directory is available; give the vault file a path".to_string())
+        }
+    }
+}
+
+/// Open a fresh store of `kind`, from the file `backend_file` names
+pub fn open_backend(
+    kind: VaultBackendKind,
+    options: &VaultBackendOptionsJson,
+    data_dir: Option<&Path>,
+) -> Result<Box<dyn VaultStore>, String> {
+    let Some(path) = backend_file(kind, options, data_dir)? else {
+        return Ok(Box::new(InMemoryStore::new()));
+    };
+    match kind {
+        VaultBackendKind::Memory => Ok(Box::new(InMemoryStore::new())),
+        VaultBackendKind::Json => {
+            let store = JsonFileStore::open(path).map_err(|e| e.to_string())?;
+            Ok(Box::new(store))
+        }
+        VaultBackendKind::Journaled => Ok(Box::new(LoggedJournal::open(path)?)),
+        VaultBackendKind::Encrypted => {
+            let passphrase = options
+                .passphrase
+                .as_deref()
+                .filter(|p| !p.is_empty())
+                .ok_or("The encrypted vault needs a passphrase")?;
+            let store = open_encrypted(path, passphrase)?;
+            Ok(Box::new(LockableStore::new(store)))
+        }
+    }
+}
+
+/// The journaled store, logging what the vault crate leaves to its caller:
+/// a corrupt journal tail cut off on open, and checkpoints that failed
+/// behind a successful write
+#[derive(Debug)]
+struct LoggedJournal(JournaledFileStore);
+
+impl LoggedJournal {
+    fn open(path: PathBuf) -> Result<Self, String> {
+        let store = JournaledFileStore::open(&path).map_err(|e| e.to_string())?;
+        if store.truncated_bytes() > 0 {
+            eprintln!(
+                "[Asterisk Vault] Cut {} corrupt bytes off the journal of {}",
+                store.truncated_bytes(),
+                path.display()
+            );
+        }
+        Ok(Self(store))
+    }
+
+    /// Pass a write's result through, logging a checkpoint it set off that
+    /// failed
+    fn logged(&mut self, result: VaultResult<()>) -> VaultResult<()> {
+        if let Some(e) = self.0.take_checkpoint_error() {
+            eprintln!("[Asterisk Vault] Checkpoint failed, journal kept: {}", e);
+        }
+        result
+    }
+}
+
+impl VaultStore for LoggedJournal {
+    fn set(&mut self, key: String, item: VaultItem) -> VaultResult<()> {
+        let result = self.0.set(key, item);
+        self.logged(result)
+    }
+
+    fn set_many(&mut self, items: Vec<(String, VaultItem)>) -> VaultResult<()> {
+        let result = self.0.set_many(items);
+        self.logged(result)
+    }
+
+    fn get(&self, key: &str) -> VaultResult<Option<VaultItem>> {
+        self.0.get(key)
+    }
+
+    fn list(&self) -> VaultResult<Vec<VaultItem>> {
+        self.0.list()
+    }
+
+    fn list_by_category(&self, category: VaultCategory) -> VaultResult<Vec<VaultItem>> {
+        self.0.list_by_category(category)
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&VaultItem) -> VaultResult<()>) -> VaultResult<()> {
+        self.0.for_each(visit)
+    }
+
+    fn delete(&mut self, key: &str) -> VaultResult<()> {
+        let result = self.0.delete(key);
+        self.logged(result)
+    }
+
+    fn clear(&mut self) -> VaultResult<()> {
+        let result = self.0.clear();
+        self.logged(result)
+    }
+}
+
+/// The encrypted vault at `path`, created if there is none
+fn open_encrypted(path: PathBuf, passphrase: &str) -> Result<EncryptedFileStore, String> {
+    let store = if path.exists() {
+        EncryptedFileStore::open(path, passphrase)
+    } else {
+        EncryptedFileStore::create(path, passphrase)
+    };
+    store.map_err(|e| e.to_string())
+}
+
+/// Result of `vault_key_status`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VaultKeyStatusJson {
+    /// A master key is stored in the keychain
+    pub persisted: bool,
+    /// Why the keychain couldn't be read, if it couldn't
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Whether `keyring` holds a master key
+pub fn key_status(keyring: &dyn KeyringBackend) -> VaultKeyStatusJson {
+    match asterisk_vault::load_master_key(keyring) {
+        Ok(key) => VaultKeyStatusJson {
+            persisted: key.is_some(),
+            error: None,
+        },
+        Err(e) => VaultKeyStatusJson {
+            persisted: false,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// The keychain vault in `data_dir`, under the master key from `keyring`
+///
+/// On first run (no key and no vault file) a key is generated and stored;
+/// the flag is true when that happened. Fails, leaving the caller on its
+/// current store, when the keychain can't be read or written, or the vault
+/// file exists without its key or doesn't open under it.
+///
+/// The master key is the file key itself, so no key derivation runs.
+/// Locking the store forgets the key and the decrypted items; unlocking
+/// reads the key from `keyring` again, with no passphrase.
+pub fn open_keychain_vault(
+    keyring: Arc<dyn KeyringBackend>,
+    data_dir: &Path,
+) -> Result<(Box<dyn VaultStore>, bool), String> {
+    let unavailable = |e: asterisk_vault::VaultError| format!("Master key unavailable: {}", e);
+    let loaded = asterisk_vault::load_master_key(keyring.as_ref()).map_err(unavailable)?;
+    let (key, created) = match loaded {
+        Some(key) => (key, false),
+        None if data_dir.join(KEYCHAIN_VAULT_FILE).exists() => {
+            return Err(format!(
+                "{} exists but the keychain holds no master key for it",
+                KEYCHAIN_VAULT_FILE
+            ))
+        }
+        None => {
+            let key = MasterKey::generate();
+            asterisk_vault::store_master_key(keyring.as_ref(), &key).map_err(unavailable)?;
+            (key, true)
+        }
+    };
+    let path = data_dir.join(KEYCHAIN_VAULT_FILE);
+    let store = if path.exists() {
+        EncryptedFileStore::open_with_key(path, &key)
+    } else {
+        EncryptedFileStore::create_with_key(path, &key)
+    };
+    let store = store.map_err(|e| e.to_string())?;
+    Ok((
+        Box::new(LockableStore::with_keychain(store, keyring)),
+        created,
+    ))
+}
+
+/// Copy the current vault into `next` and make `next` the active store
+pub fn swap_in(
+    vault: &SharedVault,
+    kind: VaultBackendKind,
+    mut next: Box<dyn VaultStore>,
+) -> Result<VaultBackendSwitchJson, String> {
+    let mut active = vault.lock()?;
+    let migrated = asterisk_vault::migrate(active.as_ref(), next.as_mut())
+        .map_err(|e| format!("Failed to migrate vault: {}", e))?;
+    let items = next.len();
+    // The previous store is dropped here, which lets file stores flush
+    *active = next;
+    Ok(VaultBackendSwitchJson {
+        kind,
+        migrated,
+        items,
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asterisk_vault::test_util;
+    use std::sync::Barrier;
+    use std::thread;
+
+    fn item(key: &str) -> VaultItem {
+        test_util::item(key, &format!("{} value", key), VaultCategory::Contact)
+    }
+
+    fn memory_vault(keys: &[&str]) -> SharedVault {
+        let store: Box<dyn VaultStore> = Box::new(InMemoryStore::with_items(
+            keys.iter().map(|k| item(k)).collect(),
+        ));
+        SharedVault::new("vault", store)
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("asterisk-backend-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_data_survives_swap_and_new_backend_is_active() {
+        let dir = scratch("swap");
+        let vault = memory_vault(&["email", "phone"]);
+        vault.lock().unwrap().touch("email").unwrap();
+
+        let next =
+            open_backend(VaultBackendKind::Journaled, &Default::default(), Some(&dir)).unwrap();
+        let summary = swap_in(&vault, VaultBackendKind::Journaled, next).unwrap();
+        assert_eq!(summary.migrated, 2);
+        assert_eq!(summary.items, 2);
+
+        // Writes now go to the journaled file
+        vault
+            .lock()
+            .unwrap()
+            .set("city".to_string(), item("city"))
+            .unwrap();
+        let email = vault.lock().unwrap().get("email").unwrap().unwrap();
+        assert_eq!(email.metadata.usage_count, 1);
+
+        // Drop the active store so it checkpoints, then reopen the file
+        let next: Box<dyn VaultStore> = Box::new(InMemoryStore::new());
+        swap_in(&vault, VaultBackendKind::Memory, next).unwrap();
+        let reopened = JournaledFileStore::open(dir.join(VAULT_FILE)).unwrap();
+        assert_eq!(reopened.len(), 3);
+        assert!(reopened.exists("city"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_failed_migration_keeps_old_store() {
+        /// Store that refuses every write
+        struct ReadOnly;
+        impl VaultStore for ReadOnly {
+            fn set(&mut self, _: String, _: VaultItem) -> asterisk_vault::Result<()> {
+                Err(asterisk_vault::VaultError::StorageError(
+                    "read-only".to_string(),
+                ))
+            }
+            fn get(&self, _: &str) -> asterisk_vault::Result<Option<VaultItem>> {
+                Ok(None)
+            }
+            fn list(&self) -> asterisk_vault::Result<Vec<VaultItem>> {
+                Ok(Vec::new())
+            }
+            fn delete(&mut self, _: &str) -> asterisk_vault::Result<()> {
+                Ok(())
+            }
+            fn clear(&mut self) -> asterisk_vault::Result<()> {
+                Ok(())
+            }
+        }
+
+        let vault = memory_vault(&["email"]);
+        let err = swap_in(&vault, VaultBackendKind::Memory, Box::new(ReadOnly)).unwrap_err();
+        assert!(err.contains("read-only"), "{}", err);
+        assert!(vault.lock().unwrap().exists("email"));
+    }
+
+    #[test]
+    fn test_json_backend_persists_and_is_opt_in() {
+        let dir = scratch("json");
+        let vault = memory_vault(&["email"]);
+        let next = open_backend(VaultBackendKind::Json, &Default::default(), Some(&dir)).unwrap();
+        swap_in(&vault, VaultBackendKind::Json, next).unwrap();
+        vault
+            .lock()
+            .unwrap()
+            .set("city".to_string(), item("city"))
+            .unwrap();
+
+        let reopened = JsonFileStore::open(dir.join(JSON_VAULT_FILE)).unwrap();
+        assert_eq!(reopened.len(), 2);
+
+        assert!(json_vault_from_setting(Some(" On ")));
+        assert!(!json_vault_from_setting(Some("0")));
+        assert!(!json_vault_from_setting(None));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_journaled_needs_a_path_without_data_dir() {
+        assert!(open_backend(VaultBackendKind::Journaled, &Default::default(), None).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_needs_a_passphrase() {
+        let dir = scratch("encrypted");
+        let err = open_backend(VaultBackendKind::Encrypted, &Default::default(), Some(&dir))
+            .err()
+            .unwrap();
+        assert!(err.contains("passphrase"), "{}", err);
+        assert!(!dir.join(ENCRYPTED_VAULT_FILE).exists());
+    }
+
+    #[test]
+    fn test_encrypted_backend_locks_behind_its_passphrase() {
+        let dir = scratch("lockable");
+        let options = VaultBackendOptionsJson {
+            path: None,
+            passphrase: Some("correct horse".to_string()),
+        };
+        let store = open_backend(VaultBackendKind::Encrypted, &options, Some(&dir)).unwrap();
+        let vault = memory_vault(&["email"]);
+        swap_in(&vault, VaultBackendKind::Encrypted, store).unwrap();
+
+        let mut active = vault.lock().unwrap();
+        assert!(active.is_lockable());
+        active.lock().unwrap();
+        assert!(matches!(
+            active.list(),
+            Err(asterisk_vault::VaultError::Locked)
+        ));
+        assert!(active.unlock("battery staple").is_err());
+        assert!(active.is_locked());
+        active.unlock("correct horse").unwrap();
+        assert!(active.exists("email"));
+        drop(active);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_keychain_vault_creates_key_once_and_reopens() {
+        let dir = scratch("keychain");
+        let keyring: Arc<dyn KeyringBackend> =
+            Arc::new(asterisk_vault::FileKeyring::new(dir.join("keyring.json")));
+
+        let (mut store, created) = open_keychain_vault(keyring.clone(), &dir).unwrap();
+        assert!(created);
+        store.set("email".to_string(), item("email")).unwrap();
+        drop(store);
+
+        let (mut store, created) = open_keychain_vault(keyring.clone(), &dir).unwrap();
+        assert!(!created);
+        assert!(store.exists("email"));
+        assert!(key_status(keyring.as_ref()).persisted);
+
+        // Locking forgets the key; unlocking needs no passphrase
+        assert!(store.is_lockable());
+        assert!(!store.unlock_needs_passphrase());
+        store.lock().unwrap();
+        assert!(matches!(
+            store.list(),
+            Err(asterisk_vault::VaultError::Locked)
+        ));
+        store.unlock("").unwrap();
+        assert!(store.exists("email"));
+
+        // The passphrase backend's default file is a separate vault
+        let options = VaultBackendOptionsJson {
+            path: None,
+            passphrase: Some("correct horse".to_string()),
+        };
+        let passphrase_vault =
+            open_backend(VaultBackendKind::Encrypted, &options, Some(&dir)).unwrap();
+        assert!(passphrase_vault.is_empty());
+        drop(passphrase_vault);
+
+        // Without its key the vault is left alone and no new key is made
+        asterisk_vault::delete_master_key(keyring.as_ref()).unwrap();
+        let err = open_keychain_vault(keyring.clone(), &dir).err().unwrap();
+        assert!(err.contains("no master key"), "{}", err);
+        assert_eq!(
+            asterisk_vault::load_master_key(keyring.as_ref()).unwrap(),
+            None
+        );
+        assert!(dir.join(KEYCHAIN_VAULT_FILE).exists());
+
+        // A keychain that can't be read is an error, not a new key
+        let unreadable: Arc<dyn KeyringBackend> = Arc::new(asterisk_vault::FileKeyring::new(&dir));
+        let err = open_keychain_vault(unreadable.clone(), &dir).err().unwrap();
+        assert!(err.contains("Master key unavailable"), "{}", err);
+        let status = key_status(unreadable.as_ref());
+        assert!(!status.persisted && status.error.is_some());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_concurrent_readers_see_old_or_new_store() {
+        let keys: Vec<String> = (0..50).map(|i| format!("key{}", i)).collect();
+        let refs: Vec<&str> = keys.iter().map(String::as_str).collect();
+        let vault = memory_vault(&refs);
+        let start = Arc::new(Barrier::new(5));
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let vault = vault.clone();
+                let start = Arc::clone(&start);
+                thread::spawn(move || {
+                    start.wait();
+                    for _ in 0..200 {
+                        // Never empty or partly copied
+                        assert_eq!(vault.lock().unwrap().len(), 50);
+                    }
+                })
+            })
+            .collect();
+
+        start.wait();
+        for _ in 0..20 {
+            let next: Box<dyn VaultStore> = Box::new(InMemoryStore::new());
+            swap_in(&vault, VaultBackendKind::Memory, next).unwrap();
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,225 @@
+/*!
+ * Matching Benchmark
+ *
+ * Times the local matching pipeline (template, composed address,
+ * autocomplete and pattern tiers) over a snapshot, with no network calls,
+ * so regressions show up as numbers rather than as a sluggish review screen.
+ *
+ * Each iteration runs every fillable field once; the iteration time is the
+ * sum of its field times. Timings use the monotonic `Instant` clock, and all
+ * sample storage is allocated before the loop starts.
+ */
+
+use asterisk_vault::VaultItem;
+use serde::{Deserialize, Serialize};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crate::matching;
+use crate::templates::FormTemplateJson;
+use crate::FormSnapshotJson;
+
+/// Upper bound on iterations, so a typo can't hang the app
+pub const MAX_ITERATIONS: u32 = 100_000;
+
+/// Average time spent on one field
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FieldTimingJson {
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    #[serde(rename = "meanMicros")]
+    pub mean_micros: f64,
+}
+
+/// Timing summary for a benchmark run; all times in microseconds
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BenchmarkReportJson {
+    pub iterations: u32,
+    /// Fields timed per iteration (unfillable types are skipped)
+    pub fields: usize,
+    #[serde(rename = "p50Micros")]
+    pub p50_micros: f64,
+    #[serde(rename = "p95Micros")]
+    pub p95_micros: f64,
+    #[serde(rename = "p99Micros")]
+    pub p99_micros: f64,
+    #[serde(rename = "meanMicros")]
+    pub mean_micros: f64,
+    #[serde(rename = "perField")]
+    pub per_field: Vec<FieldTimingJson>,
+}
+
+/// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[Duration], quantile: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (quantile * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+/// Run local matching over `snapshot` `iterations` times (clamped to
+/// 1..=`MAX_ITERATIONS`) and summarize the timings
+pub fn benchmark_matching(
+    snapshot: &FormSnapshotJson,
+    vault_items: &[VaultItem],
+    template: Option<&FormTemplateJson>,
+    iterations: u32,
+) -> BenchmarkReportJson {
+    let iterations = iterations.clamp(1, MAX_ITERATIONS);
+    let fields: Vec<_> = snapshot
+        .fields
+        .iter()
+        .filter(|f| matching::is_fillable(f))
+        .collect();
+
+    let mut samples = Vec::with_capacity(iterations as usize);
+    let mut field_totals = vec![Duration::ZERO; fields.len()];
+    for _ in 0..iterations {
+        let mut iteration = Duration::ZERO;
+        for (field, total) in fields.iter().zip(field_totals.iter_mut()) {
+            let start = Instant::now();
+            black_box(matching::match_field(
+                field,
+                snapshot,
+                vault_items,
+                template,
+            ));
+            let elapsed = start.elapsed();
+            *total += elapsed;
+            iteration += elapsed;
+        }
+        samples.push(iteration);
+    }
+
+    samples.sort_unstable();
+    let total: Duration = samples.iter().sum();
+    BenchmarkReportJson {
+        iterations,
+        fields: fields.len(),
+        p50_micros: micros(percentile(&samples, 0.50)),
+        p95_micros: micros(percentile(&samples, 0.95)),
+        p99_micros: micros(percentile(&samples, 0.99)),
+        mean_micros: micros(total) / iterations as f64,
+        per_field: fields
+            .iter()
+            .zip(&field_totals)
+            .map(|(field, total)| FieldTimingJson {
+                field_id: field.id.clone(),
+                mean_micros: micros(*total) / iterations as f64,
+            })
+            .collect(),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FieldNodeJson, FormFingerprintJson};
+    use asterisk_vault::{Provenance, ProvenanceSource, VaultCategory};
+    use chrono::Utc;
+
+    fn field(id: &str, field_type: &str, label: &str) -> FieldNodeJson {
+        FieldNodeJson {
+            id: id.to_string(),
+            name: id.to_string(),
+            label: label.to_string(),
+            field_type: field_type.to_string(),
+            semantic: "unknown".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn snapshot() -> FormSnapshotJson {
+        FormSnapshotJson {
+            url: "https://example.com/signup".to_string(),
+            domain: "example.com".to_string(),
+            title: "Sign up".to_string(),
+            captured_at: "2026-01-01T00:00:00Z".to_string(),
+            fingerprint: FormFingerprintJson {
+                field_count: 3,
+                field_types: vec![],
+                required_count: 0,
+                hash: "fp".to_string(),
+            },
+            fields: vec![
+                field("email", "email", "Email address"),
+                field("first", "text", "First name"),
+                field("password", "password", "Password"),
+            ],
+            session: Default::default(),
+        }
+    }
+
+    fn vault() -> Vec<VaultItem> {
+        ["email", "firstName"]
+            .iter()
+            .map(|key| {
+                VaultItem::new(
+                    *key,
+                    "value",
+                    *key,
+                    VaultCategory::Contact,
+                    Provenance {
+                        source: ProvenanceSource::UserEntered,
+                        timestamp: Utc::now(),
+                        confidence: 1.0,
+                        origin: None,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_percentiles_are_monotonic() {
+        let report = benchmark_matching(&snapshot(), &vault(), None, 200);
+
+        assert_eq!(report.iterations, 200);
+        assert!(report.p50_micros > 0.0);
+        assert!(report.p50_micros <= report.p95_micros);
+        assert!(report.p95_micros <= report.p99_micros);
+        assert!(report.mean_micros > 0.0);
+    }
+
+    #[test]
+    fn test_per_field_covers_fillable_fields_only() {
+        let report = benchmark_matching(&snapshot(), &vault(), None, 10);
+
+        assert_eq!(report.fields, 2);
+        let ids: Vec<&str> = report
+            .per_field
+            .iter()
+            .map(|f| f.field_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["email", "first"]);
+        // Per-field means add up to the overall mean
+        let sum: f64 = report.per_field.iter().map(|f| f.mean_micros).sum();
+        assert!((sum - report.mean_micros).abs() < 1e-6 * report.mean_micros.max(1.0));
+    }
+
+    #[test]
+    fn test_iterations_clamped() {
+        assert_eq!(
+            benchmark_matching(&snapshot(), &vault(), None, 0).iterations,
+            1
+        );
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let samples: Vec<Duration> = (1..=100).map(Duration::from_micros).collect();
+        assert_eq!(percentile(&samples, 0.50), Duration::from_micros(50));
+        assert_eq!(percentile(&samples, 0.95), Duration::from_micros(95));
+        assert_eq!(percentile(&samples, 0.99), Duration::from_micros(99));
+        assert_eq!(percentile(&[], 0.5), Duration::ZERO);
+    }
+}
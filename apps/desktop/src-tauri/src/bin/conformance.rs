@@ -0,0 +1,34 @@
+//! Run the bridge conformance suite against a running Asterisk instance.
+//!
+//! Usage: conformance [--url http://127.0.0.1:17373] [--token TOKEN]
+
+use asterisk_desktop_lib::conformance;
+
+fn main() {
+    let mut url = "http://127.0.0.1:17373".to_string();
+    let mut token = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--url" => url = args.next().unwrap_or(url),
+            "--token" => token = args.next(),
+            "-h" | "--help" => {
+                println!("Usage: conformance [--url URL] [--token TOKEN]");
+                return;
+            }
+            other => {
+                eprintln!("Unknown argument: {}", other);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    println!("Running bridge conformance suite against {}", url);
+    let report = conformance::run_suite(&url, token.as_deref());
+    println!("{}", report);
+
+    if report.failed() > 0 {
+        std::process::exit(1);
+    }
+}
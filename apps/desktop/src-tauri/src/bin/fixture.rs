@@ -0,0 +1,68 @@
+//! Start a matching-quality fixture from a captured form snapshot.
+//!
+//! Usage: fixture SNAPSHOT.json NAME [--vault PERSONA] [--dir DIR]
+//!
+//! Writes DIR/NAME.json (default DIR is the crate's fixtures/forms) with
+//! expectations taken from today's local plan. Review every expectation by
+//! hand before committing; see the `scenarios` module for the format.
+
+use asterisk_desktop_lib::scenarios;
+use asterisk_desktop_lib::FormSnapshotJson;
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: fixture SNAPSHOT.json NAME [--vault PERSONA] [--dir DIR]";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut positional = Vec::new();
+    let mut persona = "us-person".to_string();
+    let mut dir = scenarios::corpus_dir();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--vault" => persona = args.next().unwrap_or_else(|| fail(USAGE)),
+            "--dir" => dir = PathBuf::from(args.next().unwrap_or_else(|| fail(USAGE))),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            other if other.starts_with("--") => fail(&format!("Unknown argument: {}", other)),
+            _ => positional.push(arg),
+        }
+    }
+    let [snapshot_path, name] = positional.as_slice() else {
+        fail(USAGE);
+    };
+
+    let text = std::fs::read_to_string(snapshot_path)
+        .unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", snapshot_path, e)));
+    let snapshot: FormSnapshotJson = serde_json::from_str(&text)
+        .unwrap_or_else(|e| fail(&format!("Invalid snapshot {}: {}", snapshot_path, e)));
+    let vault = scenarios::load_vault(&dir, &persona).unwrap_or_else(|e| fail(&e));
+
+    let path = dir.join(format!("{}.json", name));
+    if path.exists() {
+        fail(&format!("{} already exists", path.display()));
+    }
+    let fixture = scenarios::fixture_from_snapshot(name, &persona, snapshot, &vault);
+    let json = serde_json::to_string_pretty(&fixture).unwrap_or_else(|e| fail(&e.to_string()));
+    std::fs::write(&path, json + "\n")
+        .unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", path.display(), e)));
+
+    let unmatched = fixture
+        .expected
+        .iter()
+        .filter(|e| e.vault_key.is_none())
+        .count();
+    println!(
+        "Wrote {} ({} fields, {} left empty by the local tiers). Review the expectations before committing.",
+        path.display(),
+        fixture.expected.len(),
+        unmatched
+    );
+}
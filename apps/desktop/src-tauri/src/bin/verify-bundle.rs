@@ -0,0 +1,50 @@
+//! Check a signed audit evidence bundle.
+//!
+//! Usage: verify-bundle BUNDLE.zip PUBLIC_KEY
+//!
+//! PUBLIC_KEY is the base64 key from the bundle's manifest, or a file that
+//! holds it. Verifies the manifest signature against that key and the hash
+//! chain against the bundled entries; exits 1 if either check fails.
+
+use asterisk_desktop_lib::evidence;
+
+const USAGE: &str = "Usage: verify-bundle BUNDLE.zip PUBLIC_KEY";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(2);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    let [bundle_path, key] = args.as_slice() else {
+        fail(USAGE);
+    };
+
+    // A key is short base64; anything naming a file is read from it
+    let key = match std::fs::read_to_string(key) {
+        Ok(contents) => contents,
+        Err(_) => key.clone(),
+    };
+    let bundle = std::fs::File::open(bundle_path)
+        .unwrap_or_else(|e| fail(&format!("Failed to open {}: {}", bundle_path, e)));
+
+    match evidence::verify_bundle(bundle, &key) {
+        Ok(manifest) => println!(
+            "OK: {} entries from {} to {}, exported {} by Asterisk {}",
+            manifest.entry_count,
+            manifest.from,
+            manifest.to,
+            manifest.exported_at,
+            manifest.app_version
+        ),
+        Err(e) => {
+            eprintln!("FAILED: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+/*!
+ * Bridge Port
+ *
+ * The bridge listens on 127.0.0.1, port 17373 unless `ASTERISK_BRIDGE_PORT`
+ * says otherwise. When another process already holds the port, the next
+ * `FALLBACK_PORTS` ports are tried in turn. The port actually bound is
+ * written to `PORT_FILE` in the data directory for local tools, and is
+ * reported to the desktop UI by the `bridge_port` command. The extension
+ * can't read either, so it finds the bridge by probing `/health` across the
+ * same range.
+ */
+
+use std::fs;
+use std::path::Path;
+use tiny_http::Server;
+
+/// Port the bridge listens on by default
+pub const DEFAULT_PORT: u16 = 17373;
+
+/// Environment variable overriding the bridge port
+pub const PORT_ENV: &str = "ASTERISK_BRIDGE_PORT";
+
+/// File in the data directory holding the port the bridge bound
+pub const PORT_FILE: &str = "bridge-port";
+
+/// How many ports after the configured one are tried when it is taken
+pub const FALLBACK_PORTS: u16 = 10;
+
+/// The configured port: `PORT_ENV` if set, else `DEFAULT_PORT`
+pub fn port_from_env() -> Result<u16, String> {
+    match std::env::var(PORT_ENV) {
+        Err(_) => Ok(DEFAULT_PORT),
+        Ok(port) => match port.trim().parse::<u16>() {
+            Ok(port) if port > 0 => Ok(port),
+            _ => Err(format!("Invalid {}: {}", PORT_ENV, port)),
+        },
+    }
+}
+
+/// Bind the first free port of `port` and the `FALLBACK_PORTS` after it
+pub fn bind(port: u16) -> Result<(Server, u16), String> {
+    let mut last_error = String::new();
+    for candidate in port..=port.saturating_add(FALLBACK_PORTS) {
+        match Server::http(("127.0.0.1", candidate)) {
+            Ok(server) => {
+                // Port 0 asks the OS for any free port
+                let bound = server
+                    .server_addr()
+                    .to_ip()
+                    .map_or(candidate, |addr| addr.port());
+                if candidate != port {
+                    println!(
+                        "[Asterisk HTTP] Port {} is in use, using {} instead",
+                        port, bound
+                    );
+                }
+                return Ok((server, bound));
+            }
+            Err(e) => last_error = format!("127.0.0.1:{}: {}", candidate, e),
+        }
+    }
+    Err(format!(
+        "No free port in {}..={} ({})",
+        port,
+        port.saturating_add(FALLBACK_PORTS),
+        last_error
+    ))
+}
+
+/// Record the bound port in `data_dir`, or remove a stale record when the
+/// bridge isn't running
+pub fn write_port_file(data_dir: &Path, port: Option<u16>) -> Result<(), String> {
+    let path = data_dir.join(PORT_FILE);
+    match port {
+        Some(port) => fs::write(&path, format!("{}\n", port))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e)),
+        None => match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("Failed to remove {}: {}", path.display(), e))
+            }
+            _ => Ok(()),
+        },
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_taken_port_falls_through_to_the_next() {
+        // Any free port to start from
+        let (_first, port) = bind(0).unwrap();
+        assert_ne!(port, 0);
+
+        let (_second, bound) = bind(port).unwrap();
+        assert_ne!(bound, port);
+        assert!(bound > port && bound <= port.saturating_add(FALLBACK_PORTS));
+    }
+
+    #[test]
+    fn test_port_file_written_and_removed() {
+        let dir = std::env::temp_dir().join(format!("asterisk-bridge-port-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        write_port_file(&dir, Some(17374)).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join(PORT_FILE)).unwrap().trim(),
+            "17374"
+        );
+        write_port_file(&dir, None).unwrap();
+        assert!(!dir.join(PORT_FILE).exists());
+        write_port_file(&dir, None).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
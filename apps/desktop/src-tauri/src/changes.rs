@@ -0,0 +1,447 @@
+/*!
+ * Vault Change Journal
+ *
+ * Lets the extension keep its cache of vault keys current without
+ * re-fetching the whole list: `GET /v1/vault/changes?since=N` answers with
+ * the keys created, updated or deleted after revision N of the shared
+ * vault.
+ *
+ * `JournaledStore` wraps the active `VaultStore` and notes each key it
+ * writes. Writes only happen under the vault's lock, and the revision is
+ * bumped when that lock is released, so a noted change can't know its
+ * revision yet; it waits as pending. The next read of the journal, again
+ * under the lock, stamps everything pending with the revision current at
+ * that point. That revision is never older than the write, so a change is
+ * at worst reported once more than needed, never missed.
+ *
+ * The journal keeps only the latest change per key and at most
+ * `JOURNAL_CAPACITY` keys. Dropping the oldest raises the journal's floor;
+ * a client asking for changes since before the floor, or since a revision
+ * the vault hasn't reached (it restarted), is told to resync in full. So
+ * is every client after `reset`, used when the whole vault changes under
+ * them: a backend switch or a profile switch.
+ */
+
+use asterisk_vault::{Result as VaultResult, VaultCategory, VaultItem, VaultStore};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::shared::SharedVault;
+
+/// Most keys the journal remembers before compacting
+pub const JOURNAL_CAPACITY: usize = 1000;
+
+/// What happened to a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// One key's latest change
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VaultChangeJson {
+    pub key: String,
+    pub change: ChangeKind,
+    pub revision: u64,
+}
+
+/// Answer to a changes request
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VaultChangesJson {
+    /// The vault's revision; pass it as `since` next time
+    pub revision: u64,
+    /// The journal can't answer: re-fetch the full list instead
+    pub resync: bool,
+    /// Changed keys, oldest change first; empty when `resync` is set
+    pub changes: Vec<VaultChangeJson>,
+}
+
+/// Bounded record of changed keys by revision
+pub struct ChangeJournal {
+    inner: Mutex<JournalInner>,
+}
+
+struct JournalInner {
+    capacity: usize,
+    /// Oldest `since` the entries fully cover
+    floor: u64,
+    /// Stamped changes in revision order, one per key
+    entries: VecDeque<VaultChangeJson>,
+    /// Changes not yet stamped with a revision
+    pending: Vec<(String, ChangeKind)>,
+    /// Pending changes were dropped; the next stamp raises the floor
+    overflowed: bool,
+}
+
+impl Default for ChangeJournal {
+    fn default() -> Self {
+        ChangeJournal::new(JOURNAL_CAPACITY)
+    }
+}
+
+impl ChangeJournal {
+    pub fn new(capacity: usize) -> Self {
+        ChangeJournal {
+            inner: Mutex::new(JournalInner {
+                capacity,
+                floor: 0,
+                entries: VecDeque::new(),
+                pending: Vec::new(),
+                overflowed: false,
+            }),
+        }
+    }
+
+    /// Note a change to `key`, to be stamped on the next read
+    pub fn record(&self, key: &str, change: ChangeKind) {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        if inner.pending.len() >= inner.capacity {
+            inner.pending.clear();
+            inner.overflowed = true;
+        }
+        inner.pending.push((key.to_string(), change));
+    }
+
+    /// Changes after revision `since`, or a request to resync
+    pub fn since(&self, vault: &SharedVault, since: u64) -> Result<VaultChangesJson, String> {
+        // Holding the vault keeps writes, and so new pending changes, out
+        let guard = vault.lock()?;
+        let revision = vault.revision();
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner.stamp(revision);
+        drop(guard);
+
+        if since < inner.floor || since > revision {
+            return Ok(VaultChangesJson {
+                revision,
+                resync: true,
+                changes: Vec::new(),
+            });
+        }
+        let changes = inner
+            .entries
+            .iter()
+            .filter(|entry| entry.revision > since)
+            .cloned()
+            .collect();
+        Ok(VaultChangesJson {
+            revision,
+            resync: false,
+            changes,
+        })
+    }
+
+    /// Forget every change and make all current clients resync
+    ///
+    /// Bumps the vault's revision, so a client that fetches the full list
+    /// afterwards isn't sent straight back to resync.
+    pub fn reset(&self, vault: &SharedVault) -> Result<(), String> {
+        let mut guard = vault.lock()?;
+        guard.mark_written();
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner.entries.clear();
+        inner.pending.clear();
+        inner.overflowed = false;
+        // The guard bumps the revision to this when it drops
+        inner.floor = vault.revision() + 1;
+        Ok(())
+    }
+}
+
+impl JournalInner {
+    /// Move pending changes into the entries at `revision`, compacting
+    fn stamp(&mut self, revision: u64) {
+        if self.overflowed {
+            self.entries.clear();
+            self.floor = revision;
+            self.overflowed = false;
+        }
+        for (key, change) in std::mem::take(&mut self.pending) {
+            let previous = self
+                .entries
+                .iter()
+                .position(|entry| entry.key == key)
+                .and_then(|index| self.entries.remove(index));
+            // Created and updated within one revision is still new to every
+            // client; across revisions the latest change wins
+            let change = match (previous, change) {
+                (Some(previous), ChangeKind::Updated)
+                    if previous.revision == revision && previous.change == ChangeKind::Created =>
+                {
+                    ChangeKind::Created
+                }
+                (_, change) => change,
+            };
+            self.entries.push_back(VaultChangeJson {
+                key,
+                change,
+                revision,
+            });
+        }
+        while self.entries.len() > self.capacity {
+            if let Some(dropped) = self.entries.pop_front() {
+                self.floor = self.floor.max(dropped.revision);
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Journaled Store
+// ============================================================================
+
+/// A vault store that notes every key it writes in a `ChangeJournal`
+pub struct JournaledStore {
+    inner: Box<dyn VaultStore>,
+    journal: Arc<ChangeJournal>,
+}
+
+impl JournaledStore {
+    pub fn new(inner: Box<dyn VaultStore>, journal: Arc<ChangeJournal>) -> Self {
+        JournaledStore { inner, journal }
+    }
+}
+
+impl VaultStore for JournaledStore {
+    fn set(&mut self, key: String, item: VaultItem) -> VaultResult<()> {
+        let change = if self.inner.exists(&key) {
+            ChangeKind::Updated
+        } else {
+            ChangeKind::Created
+        };
+        self.inner.set(key.clone(), item)?;
+        self.journal.record(&key, change);
+        Ok(())
+    }
+
+    fn set_many(&mut self, items: Vec<(String, VaultItem)>) -> VaultResult<()> {
+        let changes: Vec<(String, ChangeKind)> = items
+            .iter()
+            .map(|(key, _)| {
+                let change = if self.inner.exists(key) {
+                    ChangeKind::Updated
+                } else {
+                    ChangeKind::Created
+                };
+                (key.clone(), change)
+            })
+            .collect();
+        self.inner.set_many(items)?;
+        for (key, change) in changes {
+            self.journal.record(&key, change);
+        }
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> VaultResult<Option<VaultItem>> {
+        self.inner.get(key)
+    }
+
+    fn get_many(&self, keys: &[&str]) -> VaultResult<HashMap<String, VaultItem>> {
+        self.inner.get_many(keys)
+    }
+
+    fn list(&self) -> VaultResult<Vec<VaultItem>> {
+        self.inner.list()
+    }
+
+    fn list_by_category(&self, category: VaultCategory) -> VaultResult<Vec<VaultItem>> {
+        self.inner.list_by_category(category)
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&VaultItem) -> VaultResult<()>) -> VaultResult<()> {
+        self.inner.for_each(visit)
+    }
+
+    fn delete(&mut self, key: &str) -> VaultResult<()> {
+        self.inner.delete(key)?;
+        self.journal.record(key, ChangeKind::Deleted);
+        Ok(())
+    }
+
+    fn get_and_touch(&mut self, key: &str) -> VaultResult<Option<VaultItem>> {
+        let touched = self.inner.get_and_touch(key)?;
+        if touched.is_some() {
+            self.journal.record(key, ChangeKind::Updated);
+        }
+        Ok(touched)
+    }
+
+    fn exists(&self, key: &str) -> bool {
+        self.inner.exists(key)
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn clear(&mut self) -> VaultResult<()> {
+        let mut keys = Vec::new();
+        self.inner.for_each(&mut |item| {
+            keys.push(item.key.clone());
+            Ok(())
+        })?;
+        self.inner.clear()?;
+        for key in keys {
+            self.journal.record(&key, ChangeKind::Deleted);
+        }
+        Ok(())
+    }
+
+    fn is_lockable(&self) -> bool {
+        self.inner.is_lockable()
+    }
+
+    fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+
+    fn unlock_needs_passphrase(&self) -> bool {
+        self.inner.unlock_needs_passphrase()
+    }
+
+    fn lock(&mut self) -> VaultResult<()> {
+        self.inner.lock()
+    }
+
+    fn unlock(&mut self, passphrase: &str) -> VaultResult<()> {
+        self.inner.unlock(passphrase)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asterisk_vault::{test_util, InMemoryStore, VaultCategory};
+
+    fn item(key: &str) -> VaultItem {
+        test_util::item(key, "value", VaultCategory::Custom)
+    }
+
+    fn journaled(capacity: usize) -> (SharedVault, Arc<ChangeJournal>) {
+        let journal = Arc::new(ChangeJournal::new(capacity));
+        let store = JournaledStore::new(Box::new(InMemoryStore::new()), Arc::clone(&journal));
+        let store: Box<dyn VaultStore> = Box::new(store);
+        (SharedVault::new("vault", store), journal)
+    }
+
+    fn set(vault: &SharedVault, key: &str) {
+        vault
+            .lock()
+            .unwrap()
+            .set(key.to_string(), item(key))
+            .unwrap();
+    }
+
+    fn keys(changes: &VaultChangesJson) -> Vec<(&str, ChangeKind)> {
+        changes
+            .changes
+            .iter()
+            .map(|c| (c.key.as_str(), c.change))
+            .collect()
+    }
+
+    #[test]
+    fn test_changes_since_revision() {
+        let (vault, journal) = journaled(10);
+        set(&vault, "email");
+        set(&vault, "phone");
+        let first = journal.since(&vault, 0).unwrap();
+        assert!(!first.resync);
+        assert_eq!(first.revision, 2);
+        assert_eq!(
+            keys(&first),
+            vec![
+                ("email", ChangeKind::Created),
+                ("phone", ChangeKind::Created)
+            ]
+        );
+
+        set(&vault, "email");
+        vault.lock().unwrap().delete("phone").unwrap();
+        let next = journal.since(&vault, first.revision).unwrap();
+        assert_eq!(
+            keys(&next),
+            vec![
+                ("email", ChangeKind::Updated),
+                ("phone", ChangeKind::Deleted)
+            ]
+        );
+        // Nothing new since the latest revision
+        let idle = journal.since(&vault, next.revision).unwrap();
+        assert!(!idle.resync);
+        assert!(idle.changes.is_empty());
+    }
+
+    #[test]
+    fn test_compaction_keeps_latest_change_per_key() {
+        let (vault, journal) = journaled(10);
+        set(&vault, "email");
+        set(&vault, "email");
+        vault.lock().unwrap().touch("email").unwrap();
+        let changes = journal.since(&vault, 0).unwrap();
+        assert_eq!(keys(&changes), vec![("email", ChangeKind::Created)]);
+    }
+
+    #[test]
+    fn test_too_old_revision_asks_for_resync() {
+        let (vault, journal) = journaled(2);
+        set(&vault, "a");
+        let early = journal.since(&vault, 0).unwrap();
+        set(&vault, "b");
+        journal.since(&vault, 0).unwrap();
+        set(&vault, "c");
+
+        // "a" fell out of the journal, so only later revisions are covered
+        let late = journal.since(&vault, early.revision).unwrap();
+        assert!(!late.resync);
+        assert_eq!(
+            keys(&late),
+            vec![("b", ChangeKind::Created), ("c", ChangeKind::Created)]
+        );
+        let stale = journal.since(&vault, 0).unwrap();
+        assert!(stale.resync);
+        assert!(stale.changes.is_empty());
+
+        // A revision the vault never reached (an earlier run's) resyncs too
+        assert!(journal.since(&vault, 99).unwrap().resync);
+    }
+
+    #[test]
+    fn test_pending_overflow_asks_for_resync() {
+        let (vault, journal) = journaled(2);
+        for key in ["a", "b", "c"] {
+            set(&vault, key);
+        }
+        let changes = journal.since(&vault, 0).unwrap();
+        assert!(changes.resync);
+        assert!(!journal.since(&vault, changes.revision).unwrap().resync);
+    }
+
+    #[test]
+    fn test_reset_forces_resync() {
+        let (vault, journal) = journaled(10);
+        set(&vault, "email");
+        let before = journal.since(&vault, 0).unwrap();
+        journal.reset(&vault).unwrap();
+
+        let after = journal.since(&vault, before.revision).unwrap();
+        assert!(after.resync);
+        // A client that resynced at the new revision is served normally
+        let fresh = journal.since(&vault, after.revision).unwrap();
+        assert!(!fresh.resync);
+        set(&vault, "phone");
+        assert_eq!(
+            keys(&journal.since(&vault, after.revision).unwrap()),
+            vec![("phone", ChangeKind::Created)]
+        );
+    }
+}
@@ -0,0 +1,271 @@
+/*!
+ * Clock Sanity Check
+ *
+ * Fill command expiry, snapshot freshness and history ordering all compare
+ * RFC3339 timestamps from the local clock. A clock that is minutes off makes
+ * those features misbehave without any error, so `time_sanity_check`
+ * compares it against a trusted reference: the HTTP `Date` header of the
+ * LLM endpoint, or another URL the user configures.
+ *
+ * The `Date` header has one-second resolution and arrives after a network
+ * round trip, so the measured skew carries an uncertainty of half the round
+ * trip plus half a second. Only skew beyond the threshold *and* that
+ * uncertainty produces a warning.
+ *
+ * Code whose behavior depends on elapsed time takes its time from a `Clock`
+ * rather than `Utc::now()`, so tests can move it forward instead of
+ * sleeping.
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// Default reference: the endpoint the LLM features talk to anyway
+pub const DEFAULT_REFERENCE_URL: &str = "https://api.anthropic.com";
+
+/// Skew tolerated before warning; fill commands expire after minutes
+pub const DEFAULT_MAX_SKEW_SECS: i64 = 60;
+
+/// Give up on the reference after this long
+const REQUEST_TIMEOUT_SECS: u64 = 10;
+
+/// The system clock, plus however far tests have moved it forward
+#[derive(Debug, Default)]
+pub struct Clock {
+    offset_ms: AtomicI64,
+}
+
+impl Clock {
+    pub fn system() -> Self {
+        Self::default()
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        Utc::now() + Duration::milliseconds(self.offset_ms.load(Ordering::SeqCst))
+    }
+
+    /// Move this clock forward by `by`
+    pub fn advance(&self, by: Duration) {
+        self.offset_ms
+            .fetch_add(by.num_milliseconds(), Ordering::SeqCst);
+    }
+}
+
+/// Result of comparing the local clock with a reference
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TimeSanityJson {
+    /// Where the reference time came from
+    pub reference: String,
+    #[serde(rename = "referenceTime")]
+    pub reference_time: String,
+    #[serde(rename = "localTime")]
+    pub local_time: String,
+    /// Local clock minus reference; positive when the local clock is ahead
+    #[serde(rename = "skewMs")]
+    pub skew_ms: i64,
+    /// How far off the measurement itself may be
+    #[serde(rename = "uncertaintyMs")]
+    pub uncertainty_ms: i64,
+    #[serde(rename = "maxSkewSecs")]
+    pub max_skew_secs: i64,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+/// Parse an HTTP `Date` header ("Tue, 15 Oct 2026 08:49:37 GMT")
+pub fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+/// Compare the local clock with a reference reading
+///
+/// `sent` is the local time the request left and `round_trip` how long the
+/// response took; `reference` is the server's `Date` header.
+pub fn evaluate(
+    reference_source: &str,
+    reference: DateTime<Utc>,
+    sent: DateTime<Utc>,
+    round_trip: Duration,
+    max_skew: Duration,
+) -> TimeSanityJson {
+    // The server stamped its reply somewhere in the round trip, and somewhere
+    // within the second its header names; compare the midpoints
+    let local = sent + round_trip / 2;
+    let reference_mid = reference + Duration::milliseconds(500);
+    let skew = local - reference_mid;
+    let uncertainty = round_trip / 2 + Duration::milliseconds(500);
+
+    let ok = skew.abs() <= max_skew + uncertainty;
+    let warning = (!ok).then(|| {
+        format!(
+            "Your clock is about {} {} {}. Expiry times and the order of recent changes may be wrong until it is corrected.",
+            describe(skew.abs()),
+            if skew > Duration::zero() {
+                "ahead of"
+            } else {
+                "behind"
+            },
+            reference_source
+        )
+    });
+
+    TimeSanityJson {
+        reference: reference_source.to_string(),
+        reference_time: reference.to_rfc3339(),
+        local_time: local.to_rfc3339(),
+        skew_ms: skew.num_milliseconds(),
+        uncertainty_ms: uncertainty.num_milliseconds(),
+        max_skew_secs: max_skew.num_seconds(),
+        ok,
+        warning,
+    }
+}
+
+fn describe(skew: Duration) -> String {
+    if skew.num_hours() >= 2 {
+        format!("{} hours", skew.num_hours())
+    } else if skew.num_minutes() >= 2 {
+        format!("{} minutes", skew.num_minutes())
+    } else {
+        format!("{} seconds", skew.num_seconds())
+    }
+}
+
+/// Read the `Date` header of `url` and compare it with the local clock
+pub async fn check(url: &str, max_skew: Duration) -> Result<TimeSanityJson, String> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let sent = Utc::now();
+    let started = std::time::Instant::now();
+    let response = client
+        .head(url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach {}: {}", url, e))?;
+    let round_trip = Duration::from_std(started.elapsed()).unwrap_or_else(|_| Duration::zero());
+
+    // Any status will do; only the header matters
+    let date = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| format!("{} sent no Date header", url))?;
+    let reference = parse_http_date(date)
+        .ok_or_else(|| format!("{} sent an unreadable Date: {}", url, date))?;
+
+    Ok(evaluate(url, reference, sent, round_trip, max_skew))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn max_skew() -> Duration {
+        Duration::seconds(DEFAULT_MAX_SKEW_SECS)
+    }
+
+    #[test]
+    fn test_advanced_clock_runs_ahead() {
+        let clock = Clock::system();
+        let before = Utc::now();
+        assert!(clock.now() - before < Duration::seconds(5));
+        clock.advance(Duration::hours(1));
+        clock.advance(Duration::minutes(30));
+        let ahead = clock.now() - Utc::now();
+        assert!(ahead > Duration::minutes(89) && ahead <= Duration::minutes(90));
+    }
+
+    #[test]
+    fn test_parse_http_date() {
+        let parsed = parse_http_date("Thu, 15 Oct 2026 08:49:37 GMT").unwrap();
+        assert_eq!(parsed, at("2026-10-15T08:49:37Z"));
+        assert!(parse_http_date("yesterday").is_none());
+    }
+
+    #[test]
+    fn test_synchronised_clock_is_ok() {
+        let reference = at("2026-10-15T08:49:37Z");
+        // Sent 200ms before the server's second started, 1s round trip
+        let sent = at("2026-10-15T08:49:36.800Z");
+        let result = evaluate("ref", reference, sent, Duration::seconds(1), max_skew());
+        assert_eq!(result.skew_ms, -200);
+        assert_eq!(result.uncertainty_ms, 1_000);
+        assert!(result.ok);
+        assert!(result.warning.is_none());
+    }
+
+    #[test]
+    fn test_clock_ahead_beyond_threshold_warns() {
+        let reference = at("2026-10-15T08:49:37Z");
+        let sent = at("2026-10-15T08:54:37.400Z");
+        let result = evaluate(
+            "ref",
+            reference,
+            sent,
+            Duration::milliseconds(200),
+            max_skew(),
+        );
+        assert_eq!(result.skew_ms, 5 * 60 * 1000);
+        assert!(!result.ok);
+        let warning = result.warning.unwrap();
+        assert!(warning.contains("5 minutes ahead"), "{}", warning);
+    }
+
+    #[test]
+    fn test_clock_behind_beyond_threshold_warns() {
+        let reference = at("2026-10-15T08:49:37Z");
+        let sent = at("2026-10-15T05:49:37Z");
+        let result = evaluate("ref", reference, sent, Duration::zero(), max_skew());
+        assert!(result.skew_ms < 0);
+        assert!(result.warning.unwrap().contains("3 hours behind"));
+    }
+
+    #[test]
+    fn test_slow_round_trip_widens_tolerance() {
+        let reference = at("2026-10-15T08:49:37Z");
+        // 70s apart, but a 30s round trip could explain 15s of it
+        let sent = at("2026-10-15T08:50:32.500Z");
+        let slow = evaluate("ref", reference, sent, Duration::seconds(30), max_skew());
+        assert_eq!(slow.skew_ms, 70_000);
+        assert!(slow.ok);
+
+        let sent = at("2026-10-15T08:50:47.500Z");
+        let fast = evaluate("ref", reference, sent, Duration::zero(), max_skew());
+        assert_eq!(fast.skew_ms, 70_000);
+        assert!(!fast.ok);
+    }
+
+    #[tokio::test]
+    async fn test_check_reads_date_header() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        std::thread::spawn(move || {
+            if let Ok(request) = server.recv() {
+                let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+                let header = tiny_http::Header::from_bytes(&b"Date"[..], date.as_bytes()).unwrap();
+                let _ = request.respond(tiny_http::Response::empty(200).with_header(header));
+            }
+        });
+
+        let result = check(&url, max_skew()).await.unwrap();
+        assert!(result.ok, "{:?}", result);
+        assert_eq!(result.reference, url);
+    }
+}
@@ -0,0 +1,287 @@
+/*!
+ * Extension Version Compatibility
+ *
+ * The extension identifies itself on every bridge request with an
+ * `X-Asterisk-Client: asterisk-extension/<version>` header. Before routing,
+ * the bridge compares that version against the supported range:
+ *
+ * - below `minimum`: refused with 426 Upgrade Required and a JSON body
+ *   saying which version is needed (except on exempt routes, so an old
+ *   extension can still see the desktop is up)
+ * - at or above `minimum` but below `current`: served, with `Deprecation`
+ *   and `Sunset` headers announcing when support ends
+ * - anything else, including requests without the header: served normally
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// Request header carrying the client name and version
+pub const CLIENT_HEADER: &str = "X-Asterisk-Client";
+
+/// Routes served to every client regardless of version
+const EXEMPT_PATHS: &[&str] = &["/", "/health", "/capabilities", "/v1/capabilities"];
+
+/// A dotted version, compared numerically (pre-release suffixes ignored)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(pub u32, pub u32, pub u32);
+
+impl Version {
+    /// Parse "1.2.3", "1.2" or "v1.2.3-beta"; None if not a version
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().trim_start_matches('v');
+        let core = text.split(['-', '+']).next()?;
+        let mut parts = core.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next().map_or(Some(0), |p| p.parse().ok())?;
+        let patch = parts.next().map_or(Some(0), |p| p.parse().ok())?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self(major, minor, patch))
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+/// Which client versions the bridge serves
+#[derive(Debug, Clone, Copy)]
+pub struct VersionPolicy {
+    /// Oldest version served at all
+    pub minimum: Version,
+    /// Versions below this are served but marked deprecated
+    pub current: Version,
+    /// When deprecated versions stop being served, as an HTTP-date
+    pub sunset: &'static str,
+}
+
+/// Policy for the shipped extension; bump when the wire format changes
+///
+/// 0.2.0: `GET /v1/fill-commands` returns `{pollHintMs, commands,
+/// recaptureRequests}` instead of a bare array.
+pub const POLICY: VersionPolicy = VersionPolicy {
+    minimum: Version(0, 2, 0),
+    current: Version(0, 2, 0),
+    sunset: "Wed, 01 Jul 2026 00:00:00 GMT",
+};
+
+/// How the bridge treats a request's client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compatibility {
+    /// No header or an unparseable version
+    Unknown,
+    Supported,
+    Deprecated,
+    Unsupported,
+}
+
+/// Split a client header into its name and version
+///
+/// Accepts "name/1.2.3" or a bare "1.2.3".
+pub fn parse_client(header: &str) -> (Option<&str>, Option<Version>) {
+    let header = header.trim();
+    match header.rsplit_once('/') {
+        Some((name, version)) => (Some(name.trim()), Version::parse(version)),
+        None => (None, Version::parse(header)),
+    }
+}
+
+/// Classify a request's `X-Asterisk-Client` header
+pub fn evaluate(policy: &VersionPolicy, header: Option<&str>) -> Compatibility {
+    let Some(version) = header.and_then(|h| parse_client(h).1) else {
+        return Compatibility::Unknown;
+    };
+    if version < policy.minimum {
+        Compatibility::Unsupported
+    } else if version < policy.current {
+        Compatibility::Deprecated
+    } else {
+        Compatibility::Supported
+    }
+}
+
+/// Whether `url` is served even to unsupported clients
+pub fn is_exempt(url: &str) -> bool {
+    let path = url.split('?').next().unwrap_or(url);
+    EXEMPT_PATHS.contains(&path)
+}
+
+/// Extra response headers for a client (name, value)
+pub fn response_headers(
+    policy: &VersionPolicy,
+    compatibility: Compatibility,
+) -> Vec<(&'static str, String)> {
+    match compatibility {
+        Compatibility::Deprecated => vec![
+            ("Deprecation", "true".to_string()),
+            ("Sunset", policy.sunset.to_string()),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+/// Body of the 426 response sent to unsupported clients
+pub fn upgrade_required_body(policy: &VersionPolicy, header: &str) -> serde_json::Value {
+    let version = parse_client(header).1.map(|v| v.to_string());
+    serde_json::json!({
+        "error": "Client upgrade required",
+        "clientVersion": version,
+        "minimumVersion": policy.minimum.to_string(),
+        "currentVersion": policy.current.to_string(),
+        "message": format!(
+            "This version of the Asterisk extension is no longer supported. Update it to {} or later.",
+            policy.minimum
+        ),
+    })
+}
+
+// ============================================================================
+// Client Tracking
+// ============================================================================
+
+/// The most recent client that identified itself to the bridge
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SeenClientJson {
+    /// Client name from the header, e.g. "asterisk-extension"
+    pub client: Option<String>,
+    pub version: String,
+    pub compatibility: Compatibility,
+    #[serde(rename = "seenAt")]
+    pub seen_at: String,
+}
+
+/// Remembers the last identified client (health checks keep it fresh)
+#[derive(Debug, Default)]
+pub struct ClientTracker {
+    last: Mutex<Option<SeenClientJson>>,
+}
+
+impl ClientTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a request's client header; requests without one are ignored
+    pub fn observe(&self, header: &str, compatibility: Compatibility, now: DateTime<Utc>) {
+        let (client, version) = parse_client(header);
+        let Some(version) = version else {
+            return;
+        };
+        if let Ok(mut last) = self.last.lock() {
+            *last = Some(SeenClientJson {
+                client: client.map(str::to_string),
+                version: version.to_string(),
+                compatibility,
+                seen_at: now.to_rfc3339(),
+            });
+        }
+    }
+
+    pub fn last(&self) -> Option<SeenClientJson> {
+        self.last.lock().ok().and_then(|last| last.clone())
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_POLICY: VersionPolicy = VersionPolicy {
+        minimum: Version(0, 3, 0),
+        current: Version(0, 5, 0),
+        sunset: "Thu, 01 Oct 2026 00:00:00 GMT",
+    };
+
+    #[test]
+    fn test_version_parsing() {
+        assert_eq!(Version::parse("1.2.3"), Some(Version(1, 2, 3)));
+        assert_eq!(Version::parse("v0.4"), Some(Version(0, 4, 0)));
+        assert_eq!(Version::parse("2"), Some(Version(2, 0, 0)));
+        assert_eq!(Version::parse("0.5.0-beta.1"), Some(Version(0, 5, 0)));
+        assert_eq!(Version::parse("1.2.3.4"), None);
+        assert_eq!(Version::parse("latest"), None);
+        assert_eq!(Version::parse(""), None);
+    }
+
+    #[test]
+    fn test_versions_compare_numerically() {
+        assert!(Version(0, 10, 0) > Version(0, 9, 9));
+        assert!(Version(1, 0, 0) > Version(0, 99, 99));
+    }
+
+    #[test]
+    fn test_band_boundaries() {
+        let cases = [
+            ("asterisk-extension/0.2.9", Compatibility::Unsupported),
+            ("asterisk-extension/0.3.0", Compatibility::Deprecated),
+            ("asterisk-extension/0.4.9", Compatibility::Deprecated),
+            ("asterisk-extension/0.5.0", Compatibility::Supported),
+            ("asterisk-extension/1.0.0", Compatibility::Supported),
+            ("0.2.0", Compatibility::Unsupported),
+            ("asterisk-extension/dev", Compatibility::Unknown),
+        ];
+        for (header, expected) in cases {
+            assert_eq!(evaluate(&TEST_POLICY, Some(header)), expected, "{}", header);
+        }
+    }
+
+    #[test]
+    fn test_missing_header_is_unknown_and_served_plainly() {
+        let compatibility = evaluate(&TEST_POLICY, None);
+        assert_eq!(compatibility, Compatibility::Unknown);
+        assert!(response_headers(&TEST_POLICY, compatibility).is_empty());
+    }
+
+    #[test]
+    fn test_deprecated_clients_get_sunset_headers() {
+        let headers = response_headers(&TEST_POLICY, Compatibility::Deprecated);
+        assert_eq!(
+            headers,
+            vec![
+                ("Deprecation", "true".to_string()),
+                ("Sunset", "Thu, 01 Oct 2026 00:00:00 GMT".to_string()),
+            ]
+        );
+        assert!(response_headers(&TEST_POLICY, Compatibility::Supported).is_empty());
+    }
+
+    #[test]
+    fn test_exempt_paths() {
+        assert!(is_exempt("/"));
+        assert!(is_exempt("/health"));
+        assert!(is_exempt("/capabilities?x=1"));
+        assert!(!is_exempt("/v1/fill-commands"));
+        assert!(!is_exempt("/healthz"));
+    }
+
+    #[test]
+    fn test_upgrade_body() {
+        let body = upgrade_required_body(&TEST_POLICY, "asterisk-extension/0.2.1");
+        assert_eq!(body["clientVersion"], "0.2.1");
+        assert_eq!(body["minimumVersion"], "0.3.0");
+        assert!(body["message"].as_str().unwrap().contains("0.3.0"));
+    }
+
+    #[test]
+    fn test_tracker_records_last_client() {
+        let tracker = ClientTracker::new();
+        let now = Utc::now();
+        tracker.observe("asterisk-extension/0.4.0", Compatibility::Deprecated, now);
+        tracker.observe("garbage", Compatibility::Unknown, now);
+
+        let last = tracker.last().unwrap();
+        assert_eq!(last.client.as_deref(), Some("asterisk-extension"));
+        assert_eq!(last.version, "0.4.0");
+        assert_eq!(last.compatibility, Compatibility::Deprecated);
+    }
+}
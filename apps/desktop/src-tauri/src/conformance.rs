@@ -0,0 +1,1167 @@
+/*!
+ * Bridge Conformance Suite
+ *
+ * An executable contract for the extension bridge. Runs an ordered scenario
+ * against a bridge URL and reports pass/fail per check with expected vs
+ * actual, so the extension and desktop can evolve separately without
+ * drifting.
+ *
+ * Run against a live instance with the `conformance` binary (dev-tools
+ * feature); the tests below run the same suite against an in-process bridge.
+ *
+ * The scenario writes a probe snapshot, vault item and fill command for the
+ * `conformance.test` domain and removes the vault item and command again.
+ */
+
+use serde_json::{json, Value};
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// Domain used for every probe the suite writes
+pub const PROBE_DOMAIN: &str = "conformance.test";
+const PROBE_KEY: &str = "conformance-probe";
+const PROBE_COMMAND_ID: &str = "conformance-command";
+
+/// Header carrying the bridge token, when one is configured
+pub use crate::tokens::TOKEN_HEADER;
+
+// ============================================================================
+// Report
+// ============================================================================
+
+/// Result of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    Fail,
+    /// The bridge doesn't provide what the check needs
+    Skip,
+}
+
+/// One line of the conformance report
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub outcome: Outcome,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Full conformance report, in scenario order
+#[derive(Debug, Default)]
+pub struct ConformanceReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl ConformanceReport {
+    pub fn passed(&self) -> usize {
+        self.count(Outcome::Pass)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(Outcome::Fail)
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.count(Outcome::Skip)
+    }
+
+    fn count(&self, outcome: Outcome) -> usize {
+        self.checks.iter().filter(|c| c.outcome == outcome).count()
+    }
+
+    fn check(&mut self, name: &'static str, ok: bool, expected: impl Into<String>, actual: String) {
+        self.checks.push(CheckResult {
+            name,
+            outcome: if ok { Outcome::Pass } else { Outcome::Fail },
+            expected: expected.into(),
+            actual,
+        });
+    }
+
+    fn skip(&mut self, name: &'static str, reason: &str) {
+        self.checks.push(CheckResult {
+            name,
+            outcome: Outcome::Skip,
+            expected: String::new(),
+            actual: reason.to_string(),
+        });
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            match check.outcome {
+                Outcome::Pass => writeln!(f, "PASS  {}", check.name)?,
+                Outcome::Skip => writeln!(f, "SKIP  {} ({})", check.name, check.actual)?,
+                Outcome::Fail => writeln!(
+                    f,
+                    "FAIL  {}\n      expected: {}\n      actual:   {}",
+                    check.name, check.expected, check.actual
+                )?,
+            }
+        }
+        write!(
+            f,
+            "{} passed, {} failed, {} skipped",
+            self.passed(),
+            self.failed(),
+            self.skipped()
+        )
+    }
+}
+
+// ============================================================================
+// Minimal HTTP Client
+// ============================================================================
+
+/// A response from the bridge
+#[derive(Debug)]
+struct HttpResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl HttpResponse {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap_or(Value::Null)
+    }
+
+    fn summary(&self) -> String {
+        let body: String = self.body.chars().take(200).collect();
+        format!("{} {}", self.status, body)
+    }
+}
+
+/// Blocking HTTP/1.1 client for a plain `http://host:port` bridge
+struct BridgeClient {
+    host: String,
+    token: Option<String>,
+    /// Sent as `X-Asterisk-Client` when set
+    client: Option<String>,
+    /// Sent as `Origin` when set, as a browser page would
+    origin: Option<String>,
+    /// Sent as `Accept` when set
+    accept: Option<String>,
+}
+
+impl BridgeClient {
+    fn new(base_url: &str, token: Option<&str>) -> Result<Self, String> {
+        let host = base_url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("Only http:// bridge URLs are supported: {}", base_url))?
+            .trim_end_matches('/')
+            .to_string();
+        Ok(Self {
+            host,
+            token: token.map(str::to_string),
+            client: None,
+            origin: None,
+            accept: None,
+        })
+    }
+
+    fn send(&self, method: &str, path: &str, body: Option<&str>) -> Result<HttpResponse, String> {
+        let mut stream =
+            TcpStream::connect(&self.host).map_err(|e| format!("connect failed: {}", e))?;
+        stream
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .map_err(|e| e.to_string())?;
+
+        let body = body.unwrap_or("");
+        let mut request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+            method,
+            path,
+            self.host,
+            body.len()
+        );
+        if !body.is_empty() {
+            request.push_str("Content-Type: application/json\r\n");
+        }
+        if let Some(token) = &self.token {
+            request.push_str(&format!("{}: {}\r\n", TOKEN_HEADER, token));
+        }
+        if let Some(client) = &self.client {
+            request.push_str(&format!("{}: {}\r\n", crate::compat::CLIENT_HEADER, client));
+        }
+        if let Some(origin) = &self.origin {
+            request.push_str(&format!("Origin: {}\r\n", origin));
+        }
+        if let Some(accept) = &self.accept {
+            request.push_str(&format!("Accept: {}\r\n", accept));
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|e| format!("write failed: {}", e))?;
+        let mut raw = Vec::new();
+        stream
+            .read_to_end(&mut raw)
+            .map_err(|e| format!("read failed: {}", e))?;
+        parse_response(&String::from_utf8_lossy(&raw))
+    }
+}
+
+fn parse_response(raw: &str) -> Result<HttpResponse, String> {
+    let (head, body) = raw
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| "malformed HTTP response".to_string())?;
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| "missing HTTP status".to_string())?;
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect();
+
+    let chunked = headers.iter().any(|(k, v)| {
+        k.eq_ignore_ascii_case("transfer-encoding") && v.eq_ignore_ascii_case("chunked")
+    });
+    let body = if chunked {
+        decode_chunked(body)
+    } else {
+        body.to_string()
+    };
+    Ok(HttpResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+fn decode_chunked(mut body: &str) -> String {
+    let mut decoded = String::new();
+    while let Some((size, rest)) = body.split_once("\r\n") {
+        let Ok(size) = usize::from_str_radix(size.trim(), 16) else {
+            break;
+        };
+        if size == 0 || rest.len() < size {
+            break;
+        }
+        decoded.push_str(&rest[..size]);
+        body = rest[size..].trim_start_matches("\r\n");
+    }
+    decoded
+}
+
+// ============================================================================
+// Scenario
+// ============================================================================
+
+fn probe_snapshot() -> Value {
+    json!({
+        "url": format!("https://{}/signup", PROBE_DOMAIN),
+        "domain": PROBE_DOMAIN,
+        "title": "Conformance probe",
+        "capturedAt": chrono::Utc::now().to_rfc3339(),
+        "fingerprint": {
+            "fieldCount": 1,
+            "fieldTypes": ["email"],
+            "requiredCount": 1,
+            "hash": "conformance"
+        },
+        "fields": [{
+            "id": "email",
+            "name": "email",
+            "label": "Email",
+            "type": "email",
+            "semantic": "email",
+            "required": true,
+            "maxLength": 64
+        }]
+    })
+}
+
+fn probe_item() -> Value {
+    let now = chrono::Utc::now().to_rfc3339();
+    json!({
+        "key": PROBE_KEY,
+        "value": "probe@conformance.test",
+        "label": "Conformance probe",
+        "category": "custom",
+        "provenance": {
+            "source": "user_entered",
+            "timestamp": now,
+            "confidence": 1.0,
+            "origin": null
+        },
+        "metadata": {
+            "created": now,
+            "updated": now,
+            "last_used": null,
+            "usage_count": 0
+        }
+    })
+}
+
+fn probe_command(fills: Value) -> Value {
+    let now = chrono::Utc::now();
+    json!({
+        "id": PROBE_COMMAND_ID,
+        "targetDomain": PROBE_DOMAIN,
+        "fills": fills,
+        "createdAt": now.to_rfc3339(),
+        "expiresAt": (now + chrono::Duration::minutes(5)).to_rfc3339()
+    })
+}
+
+fn contains_key(list: &Value, field: &str, value: &str) -> bool {
+    // Fill command listings wrap the array as {"commands": [...], ...}
+    let list = list.get("commands").unwrap_or(list);
+    list.as_array()
+        .is_some_and(|items| items.iter().any(|i| i[field] == value))
+}
+
+/// Run the full scenario against `base_url`
+pub fn run_suite(base_url: &str, token: Option<&str>) -> ConformanceReport {
+    let mut report = ConformanceReport::default();
+    let client = match BridgeClient::new(base_url, token) {
+        Ok(client) => client,
+        Err(e) => {
+            report.check("bridge_url", false, "http://host:port", e);
+            return report;
+        }
+    };
+
+    // Every check issues at most a few requests; a transport error is a failure
+    macro_rules! send {
+        ($name:expr, $method:expr, $path:expr, $body:expr) => {
+            match client.send($method, $path, $body) {
+                Ok(response) => response,
+                Err(e) => {
+                    report.check($name, false, "a response", e);
+                    return report;
+                }
+            }
+        };
+    }
+
+    // Liveness and CORS
+    let r = send!("health", "GET", "/health", None);
+    report.check("health", r.status == 200, "200 OK", r.summary());
+    report.check(
+        "health_poll_hint",
+        r.header("X-Poll-Hint-Ms")
+            .is_some_and(|h| h.parse::<u64>().is_ok()),
+        "X-Poll-Hint-Ms header with a number",
+        r.summary(),
+    );
+
+    let r = send!("cors_preflight", "OPTIONS", "/v1/vault", None);
+    report.check(
+        "cors_preflight",
+        r.status == 204 && r.header("Access-Control-Allow-Origin").is_some(),
+        "204 with Access-Control-Allow-Origin",
+        format!(
+            "{} allow-origin={:?}",
+            r.status,
+            r.header("Access-Control-Allow-Origin")
+        ),
+    );
+
+    // Pairing and capability discovery
+    report.skip("pairing", "no pairing route on this bridge");
+    let r = send!("capabilities", "GET", "/v1/capabilities", None);
+    report.check(
+        "capabilities",
+        r.status == 200 && r.json()["features"].is_array(),
+        r#"200 {"features":[...]}"#,
+        r.summary(),
+    );
+
+    // Snapshot lifecycle
+    let body = probe_snapshot().to_string();
+    let r = send!("snapshot_post", "POST", "/v1/form-snapshots", Some(&body));
+    report.check(
+        "snapshot_post",
+        r.status == 200 && r.json()["status"] == "ok",
+        r#"200 {"status":"ok"}"#,
+        r.summary(),
+    );
+
+    let r = send!("snapshot_get", "GET", "/v1/form-snapshots", None);
+    report.check(
+        "snapshot_get",
+        r.status == 200 && r.json()["domain"] == PROBE_DOMAIN,
+        format!("200 with the {} snapshot", PROBE_DOMAIN),
+        r.summary(),
+    );
+
+    // Vault CRUD
+    let body = probe_item().to_string();
+    let r = send!("vault_post", "POST", "/v1/vault", Some(&body));
+    report.check(
+        "vault_post",
+        r.status == 200 && r.json()["status"] == "ok",
+        r#"200 {"status":"ok"}"#,
+        r.summary(),
+    );
+
+    let r = send!("vault_list", "GET", "/v1/vault", None);
+    report.check(
+        "vault_list",
+        r.status == 200 && contains_key(&r.json(), "key", PROBE_KEY),
+        format!("200 listing {}", PROBE_KEY),
+        r.summary(),
+    );
+    report.skip("vault_etags", "no ETag support on this bridge");
+
+    let path = format!("/v1/vault?key={}", PROBE_KEY);
+    let r = send!("vault_delete", "DELETE", &path, None);
+    let listed = send!("vault_delete", "GET", "/v1/vault", None);
+    report.check(
+        "vault_delete",
+        r.status == 200 && !contains_key(&listed.json(), "key", PROBE_KEY),
+        format!("200 and {} no longer listed", PROBE_KEY),
+        format!(
+            "{} listed={}",
+            r.status,
+            contains_key(&listed.json(), "key", PROBE_KEY)
+        ),
+    );
+
+    // Fill-command lifecycle
+    let r = send!(
+        "fill_command_invalid",
+        "POST",
+        "/v1/fill-commands",
+        Some("not json")
+    );
+    report.check(
+        "fill_command_invalid",
+        r.status == 400 && r.json()["error"].is_string(),
+        r#"400 {"error":"..."}"#,
+        r.summary(),
+    );
+
+    let body = probe_command(json!([{ "fieldId": "email", "value": "x".repeat(65) }])).to_string();
+    let r = send!(
+        "fill_command_max_length",
+        "POST",
+        "/v1/fill-commands",
+        Some(&body)
+    );
+    report.check(
+        "fill_command_max_length",
+        (r.status == 422 && r.json()["problems"].is_array()) || r.json()["warnings"].is_array(),
+        r#"422 {"error":"...","problems":[...]} (or "warnings" under the flag policy)"#,
+        r.summary(),
+    );
+
+    let body = probe_command(json!([{ "fieldId": "email", "value": "probe@conformance.test" }]))
+        .to_string();
+    let r = send!(
+        "fill_command_post",
+        "POST",
+        "/v1/fill-commands",
+        Some(&body)
+    );
+    let status = r.json()["status"].as_str().unwrap_or_default().to_string();
+    report.check(
+        "fill_command_post",
+        (r.status == 200 && status == "ok") || (r.status == 202 && status == "awaiting_consent"),
+        r#"200 {"status":"ok"} or 202 {"status":"awaiting_consent"}"#,
+        r.summary(),
+    );
+
+    let path = format!("/v1/fill-commands?domain={}", PROBE_DOMAIN);
+    let r = send!("fill_command_served", "GET", &path, None);
+    let served = contains_key(&r.json(), "id", PROBE_COMMAND_ID);
+    report.check(
+        "fill_command_poll_hint",
+        r.json()["pollHintMs"].is_u64(),
+        r#"{"commands":[...],"pollHintMs":N}"#,
+        r.summary(),
+    );
+    report.check(
+        "fill_command_served",
+        r.status == 200 && served == (status == "ok"),
+        if status == "ok" {
+            "200 listing the command"
+        } else {
+            "200 without the command until consent is given"
+        },
+        format!("{} served={}", r.status, served),
+    );
+    report.skip(
+        "fill_command_long_poll",
+        "no long-poll support on this bridge",
+    );
+    report.skip("fill_result_report", "no fill result route on this bridge");
+
+    let path = format!("/v1/fill-commands?id={}", PROBE_COMMAND_ID);
+    let r = send!("fill_command_ack", "DELETE", &path, None);
+    let path = format!("/v1/fill-commands?domain={}", PROBE_DOMAIN);
+    let listed = send!("fill_command_ack", "GET", &path, None);
+    report.check(
+        "fill_command_ack",
+        r.status == 200 && !contains_key(&listed.json(), "id", PROBE_COMMAND_ID),
+        "200 and the command no longer served",
+        format!("{} {}", r.status, listed.summary()),
+    );
+
+    // Site info and error shapes
+    let path = format!("/v1/site-info?domain={}", PROBE_DOMAIN);
+    let r = send!("site_info", "GET", &path, None);
+    report.check(
+        "site_info",
+        r.status == 200 && r.json()["consent"].is_object(),
+        r#"200 {"domain":...,"consent":{...}}"#,
+        r.summary(),
+    );
+    let r = send!("audit_read_back", "GET", "/v1/audit/stats", None);
+    report.check(
+        "audit_read_back",
+        r.status == 200 && r.json()["entries"].is_u64(),
+        r#"200 {"entries":N,...}"#,
+        r.summary(),
+    );
+
+    let r = send!("unknown_route", "GET", "/v1/conformance-unknown", None);
+    report.check("unknown_route", r.status == 404, "404", r.summary());
+
+    report
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        access, audit, changes, clock, compat, consent, cors, fill, groups, history, onboarding,
+        pause, recall, recapture, sessions, shared, tokens, undo, vault_lock, BridgeContext,
+    };
+    use asterisk_vault::{InMemoryStore, VaultStore};
+    use std::sync::{Arc, Mutex};
+
+    /// Start an in-process bridge on an ephemeral port
+    fn start_harness(consent_granted: bool) -> String {
+        start_harness_with_tokens(consent_granted, tokens::TokenStore::in_memory())
+    }
+
+    fn start_harness_with_tokens(consent_granted: bool, token_store: tokens::TokenStore) -> String {
+        start_harness_with_stores(
+            consent_granted,
+            token_store,
+            shared::SnapshotStore::new("form snapshot", None),
+            cors::CorsPolicy::Permissive,
+            Arc::new(clock::Clock::system()),
+        )
+    }
+
+    fn start_harness_with_stores(
+        consent_granted: bool,
+        token_store: tokens::TokenStore,
+        snapshot_store: shared::SnapshotStore,
+        cors: cors::CorsPolicy,
+        fill_clock: Arc<clock::Clock>,
+    ) -> String {
+        let scratch = std::env::temp_dir().join(format!(
+            "asterisk-conformance-{}-{}",
+            consent_granted,
+            std::process::id()
+        ));
+        let mut consent_store = consent::ConsentStore::in_memory();
+        if consent_granted {
+            consent_store
+                .grant(PROBE_DOMAIN, consent::ConsentScope::Always)
+                .unwrap();
+        }
+
+        let vault: Box<dyn VaultStore> = Box::new(InMemoryStore::new());
+        let context = BridgeContext {
+            snapshot_store,
+            form_sessions: sessions::FormSessionStore::new("form session", Default::default()),
+            plan_activity: Arc::new(Mutex::new(None)),
+            recapture_queue: Arc::new(Mutex::new(recapture::RecaptureQueue::default())),
+            vault_store: shared::SharedVault::new("vault", vault),
+            vault_history: Arc::new(history::VaultHistory::new(scratch.join("history.jsonl"))),
+            vault_changes: Arc::new(changes::ChangeJournal::default()),
+            fill_command_store: shared::FillCommandStore::new("fill command", Vec::new()),
+            fill_clock,
+            fill_groups: Arc::new(groups::FillCommandGroups::new()),
+            max_length_policy: Arc::new(Mutex::new(fill::MaxLengthPolicy::default())),
+            access_log: Arc::new(access::AccessLog::new(scratch.join("access.jsonl"))),
+            consent_store: consent::SharedConsentStore::new("consent", consent_store),
+            recall_store: Arc::new(Mutex::new(recall::RecallStore::in_memory())),
+            undo_store: Arc::new(Mutex::new(undo::UndoStore::new())),
+            client_tracker: Arc::new(compat::ClientTracker::new()),
+            audit_log: Arc::new(audit::AuditLog::in_memory()),
+            token_store: Arc::new(Mutex::new(token_store)),
+            bridge_pause: Arc::new(pause::BridgePause::new()),
+            onboarding: onboarding::OnboardingStore::new(
+                "onboarding",
+                onboarding::Onboarding::in_memory(),
+            ),
+            cors,
+            vault_lock: Arc::new(vault_lock::VaultLock::new(
+                Default::default(),
+                chrono::Utc::now(),
+                Arc::new(|_, _| {}),
+            )),
+            events: Arc::new(|_, _| {}),
+            snapshot_hook: Arc::new(|_, _| {}),
+        };
+
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        crate::spawn_bridge(server, context);
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn test_suite_passes_against_in_process_bridge() {
+        let report = run_suite(&start_harness(true), None);
+        assert_eq!(report.failed(), 0, "\n{}", report);
+        assert!(report.passed() >= 13);
+    }
+
+    #[test]
+    fn test_suite_accepts_consent_hold() {
+        let report = run_suite(&start_harness(false), None);
+        assert_eq!(report.failed(), 0, "\n{}", report);
+    }
+
+    fn batch_command(id: &str, fills: Value) -> Value {
+        let mut command = probe_command(fills);
+        command["id"] = json!(id);
+        command
+    }
+
+    fn served_ids(client: &BridgeClient) -> Vec<String> {
+        let path = format!("/v1/fill-commands?domain={}", PROBE_DOMAIN);
+        let listed = client.send("GET", &path, None).unwrap().json();
+        listed["commands"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["id"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_batch_with_invalid_command_commits_none() {
+        let client = BridgeClient::new(&start_harness(true), None).unwrap();
+        let fill = json!([{ "fieldId": "email", "value": "a@b.c" }]);
+        let batch = json!([
+            batch_command("batch-ok", fill.clone()),
+            batch_command("batch-empty", json!([])),
+        ]);
+
+        let r = client
+            .send("POST", "/v1/fill-commands/batch", Some(&batch.to_string()))
+            .unwrap();
+        assert_eq!(r.status, 422, "{}", r.summary());
+        assert_eq!(r.json()["problems"][0]["id"], "batch-empty");
+        assert!(served_ids(&client).is_empty());
+    }
+
+    #[test]
+    fn test_valid_batch_commits_all() {
+        let client = BridgeClient::new(&start_harness(true), None).unwrap();
+        let fill = json!([{ "fieldId": "email", "value": "a@b.c" }]);
+        let batch = json!([
+            batch_command("batch-1", fill.clone()),
+            batch_command("batch-2", fill),
+        ]);
+
+        let r = client
+            .send("POST", "/v1/fill-commands/batch", Some(&batch.to_string()))
+            .unwrap();
+        assert_eq!(r.status, 200, "{}", r.summary());
+        assert_eq!(r.json()["ids"], json!(["batch-1", "batch-2"]));
+        assert_eq!(served_ids(&client), vec!["batch-1", "batch-2"]);
+    }
+
+    #[test]
+    fn test_advanced_clock_expires_commands_without_sleeping() {
+        let fill_clock = Arc::new(clock::Clock::system());
+        let base_url = start_harness_with_stores(
+            true,
+            tokens::TokenStore::in_memory(),
+            shared::SnapshotStore::new("form snapshot", None),
+            cors::CorsPolicy::Permissive,
+            Arc::clone(&fill_clock),
+        );
+        let client = BridgeClient::new(&base_url, None).unwrap();
+        // probe_command expires five minutes after it was made
+        let command = batch_command(
+            "expiring",
+            json!([{ "fieldId": "email", "value": "a@b.c" }]),
+        );
+        let r = client
+            .send("POST", "/v1/fill-commands", Some(&command.to_string()))
+            .unwrap();
+        assert_eq!(r.status, 200, "{}", r.summary());
+
+        fill_clock.advance(chrono::Duration::minutes(4));
+        assert_eq!(served_ids(&client), vec!["expiring"]);
+        fill_clock.advance(chrono::Duration::minutes(2));
+        assert!(served_ids(&client).is_empty());
+    }
+
+    #[test]
+    fn test_bridge_serves_snapshot_after_writer_panics() {
+        let snapshot_store = shared::SnapshotStore::new("form snapshot", None);
+        let base_url = start_harness_with_stores(
+            true,
+            tokens::TokenStore::in_memory(),
+            snapshot_store.clone(),
+            cors::CorsPolicy::Permissive,
+            Arc::new(clock::Clock::system()),
+        );
+        let client = BridgeClient::new(&base_url, None).unwrap();
+        let r = client
+            .send(
+                "POST",
+                "/v1/form-snapshots",
+                Some(&probe_snapshot().to_string()),
+            )
+            .unwrap();
+        assert_eq!(r.status, 200, "{}", r.summary());
+
+        // A command thread panics while holding the snapshot lock
+        let writer = snapshot_store.clone();
+        let panicked = std::thread::spawn(move || {
+            let _guard = writer.lock().unwrap();
+            panic!("writer failed");
+        })
+        .join();
+        assert!(panicked.is_err());
+
+        let r = client.send("GET", "/v1/form-snapshots", None).unwrap();
+        assert_eq!(r.status, 200, "{}", r.summary());
+        assert_eq!(r.json()["domain"], PROBE_DOMAIN);
+    }
+
+    fn client_as(base_url: &str, version: &str) -> BridgeClient {
+        let mut client = BridgeClient::new(base_url, None).unwrap();
+        client.client = Some(format!("asterisk-extension/{}", version));
+        client
+    }
+
+    #[test]
+    fn test_unsupported_client_gets_426_except_health() {
+        let base_url = start_harness(true);
+        // 0.1.0 expects the fill command poll to return a bare array
+        let old = client_as(&base_url, "0.1.0");
+
+        let r = old.send("GET", "/v1/fill-commands", None).unwrap();
+        assert_eq!(r.status, 426, "{}", r.summary());
+        assert_eq!(
+            r.json()["minimumVersion"],
+            compat::POLICY.minimum.to_string()
+        );
+
+        let r = old.send("GET", "/health", None).unwrap();
+        assert_eq!(r.status, 200);
+    }
+
+    #[test]
+    fn test_root_serves_status_page_or_json() {
+        let base_url = start_harness(true);
+        let mut browser = client_as(&base_url, &compat::POLICY.current.to_string());
+        let item = probe_item();
+        let r = browser
+            .send("POST", "/v1/vault", Some(&item.to_string()))
+            .unwrap();
+        assert_eq!(r.status, 200, "{}", r.summary());
+
+        browser.client = None;
+        browser.accept = Some("text/html,application/xhtml+xml,*/*;q=0.8".to_string());
+        let r = browser.send("GET", "/", None).unwrap();
+        assert_eq!(r.status, 200, "{}", r.summary());
+        assert!(r.header("Content-Type").unwrap().starts_with("text/html"));
+        assert!(r.body.contains("Asterisk is running"));
+        assert!(r.body.contains("Connected recently"));
+        // Nothing about what the bridge holds
+        for secret in [PROBE_KEY, item["value"].as_str().unwrap(), PROBE_DOMAIN] {
+            assert!(!r.body.contains(secret), "{}", secret);
+        }
+        for counter in ["items", "pending", "audit", "vault"] {
+            assert!(!r.body.to_lowercase().contains(counter), "{}", counter);
+        }
+
+        browser.accept = Some("application/json".to_string());
+        let r = browser.send("GET", "/", None).unwrap();
+        assert_eq!(r.status, 200, "{}", r.summary());
+        assert_eq!(
+            r.json(),
+            json!({
+                "status": "ok",
+                "app": "Asterisk",
+                "version": env!("CARGO_PKG_VERSION"),
+                "extensionConnected": true,
+            })
+        );
+
+        // Other routes keep their JSON behavior
+        browser.accept = None;
+        let r = browser.send("GET", "/v1/capabilities", None).unwrap();
+        assert_eq!(r.header("Content-Type"), Some("application/json"));
+        assert!(r.json()["features"].is_array());
+    }
+
+    #[test]
+    fn test_vault_list_filters_by_category() {
+        let client = BridgeClient::new(&start_harness(true), None).unwrap();
+        let mut card = probe_item();
+        card["key"] = json!("conformance-card");
+        card["category"] = json!("financial");
+        for item in [probe_item(), card] {
+            let r = client
+                .send("POST", "/v1/vault", Some(&item.to_string()))
+                .unwrap();
+            assert_eq!(r.status, 200, "{}", r.summary());
+        }
+
+        let all = client.send("GET", "/v1/vault", None).unwrap();
+        assert_eq!(all.status, 200, "{}", all.summary());
+        assert_eq!(all.json().as_array().unwrap().len(), 2);
+
+        let financial = client
+            .send("GET", "/v1/vault?category=financial", None)
+            .unwrap();
+        assert_eq!(financial.status, 200, "{}", financial.summary());
+        let keys: Vec<String> = financial
+            .json()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["key"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(keys, ["conformance-card"]);
+
+        let r = client.send("GET", "/v1/vault?category=pets", None).unwrap();
+        assert_eq!(r.status, 400, "{}", r.summary());
+        assert!(r.json()["error"].as_str().unwrap().contains("pets"));
+    }
+
+    #[test]
+    fn test_vault_keys_never_include_values() {
+        let client = BridgeClient::new(&start_harness(true), None).unwrap();
+        let mut card = probe_item();
+        card["key"] = json!("conformance-card");
+        card["label"] = json!("Work card");
+        card["category"] = json!("financial");
+        card["value"] = json!("4111 1111 1111 1111");
+        let mut rejected = probe_item();
+        rejected["key"] = json!("conformance-rejected");
+        rejected["review_status"] = json!("rejected");
+        for item in [probe_item(), card.clone(), rejected] {
+            let r = client
+                .send("POST", "/v1/vault", Some(&item.to_string()))
+                .unwrap();
+            assert_eq!(r.status, 200, "{}", r.summary());
+        }
+
+        let r = client.send("GET", "/v1/vault/keys", None).unwrap();
+        assert_eq!(r.status, 200, "{}", r.summary());
+        let mut keys = r.json().as_array().unwrap().clone();
+        keys.sort_by_key(|key| key["key"].as_str().unwrap().to_string());
+        assert_eq!(
+            keys,
+            [
+                json!({ "key": "conformance-card", "label": "Work card", "category": "financial" }),
+                json!({ "key": PROBE_KEY, "label": "Conformance probe", "category": "custom" }),
+            ]
+        );
+        for value in [&probe_item()["value"], &card["value"]] {
+            assert!(!r.body.contains(value.as_str().unwrap()));
+        }
+        assert!(!r.body.contains("\"value\""));
+    }
+
+    #[test]
+    fn test_vault_list_never_returns_rejected_items() {
+        let client = BridgeClient::new(&start_harness(true), None).unwrap();
+        let mut rejected = probe_item();
+        rejected["key"] = json!("conformance-rejected");
+        rejected["value"] = json!("rejected-value");
+        rejected["review_status"] = json!("rejected");
+        let mut unreviewed = probe_item();
+        unreviewed["key"] = json!("conformance-unreviewed");
+        unreviewed["review_status"] = json!("unreviewed");
+        for item in [probe_item(), rejected, unreviewed] {
+            let r = client
+                .send("POST", "/v1/vault", Some(&item.to_string()))
+                .unwrap();
+            assert_eq!(r.status, 200, "{}", r.summary());
+        }
+
+        for path in ["/v1/vault", "/v1/vault?category=custom"] {
+            let r = client.send("GET", path, None).unwrap();
+            assert_eq!(r.status, 200, "{}", r.summary());
+            let mut keys: Vec<String> = r
+                .json()
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["key"].as_str().unwrap().to_string())
+                .collect();
+            keys.sort();
+            // Unreviewed items are still offered, for the client to cap at Review
+            assert_eq!(keys, [PROBE_KEY, "conformance-unreviewed"], "{}", path);
+            assert!(!r.body.contains("rejected-value"), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_current_and_anonymous_clients_served_without_deprecation() {
+        let base_url = start_harness(true);
+        let current = client_as(&base_url, &compat::POLICY.current.to_string());
+        let anonymous = BridgeClient::new(&base_url, None).unwrap();
+        for client in [current, anonymous] {
+            let r = client.send("GET", "/v1/fill-commands", None).unwrap();
+            assert_eq!(r.status, 200);
+            assert!(r.header("Deprecation").is_none());
+            assert!(r.header("Sunset").is_none());
+        }
+    }
+
+    #[test]
+    fn test_vault_write_returns_revision_for_consistent_reads() {
+        let client = BridgeClient::new(&start_harness(true), None).unwrap();
+        let r = client
+            .send("POST", "/v1/vault", Some(&probe_item().to_string()))
+            .unwrap();
+        assert_eq!(r.status, 200, "{}", r.summary());
+        let revision = r.json()["revision"].as_u64().unwrap();
+        assert!(revision >= 1);
+
+        let path = format!("/v1/vault?minRevision={}", revision);
+        let r = client.send("GET", &path, None).unwrap();
+        assert_eq!(r.status, 200, "{}", r.summary());
+        assert_eq!(r.json().as_array().unwrap().len(), 1);
+
+        let r = client
+            .send("GET", "/v1/vault?minRevision=latest", None)
+            .unwrap();
+        assert_eq!(r.status, 400, "{}", r.summary());
+    }
+
+    #[test]
+    fn test_requests_are_served_while_another_waits_for_a_revision() {
+        let base_url = start_harness(true);
+        let waiting = {
+            let base_url = base_url.clone();
+            std::thread::spawn(move || {
+                let client = BridgeClient::new(&base_url, None).unwrap();
+                let started = std::time::Instant::now();
+                let r = client
+                    .send("GET", "/v1/vault?minRevision=1000", None)
+                    .unwrap();
+                (r.status, started.elapsed())
+            })
+        };
+        std::thread::sleep(Duration::from_millis(200));
+
+        let client = BridgeClient::new(&base_url, None).unwrap();
+        let started = std::time::Instant::now();
+        assert_eq!(served_ids(&client), Vec::<String>::new());
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        // The revision never comes, so the wait runs out
+        let (status, elapsed) = waiting.join().unwrap();
+        assert_eq!(status, 503);
+        assert!(elapsed >= shared::MAX_REVISION_WAIT);
+    }
+
+    fn start_harness_without_cors() -> String {
+        start_harness_with_stores(
+            true,
+            tokens::TokenStore::in_memory(),
+            shared::SnapshotStore::new("form snapshot", None),
+            cors::CorsPolicy::Disabled,
+            Arc::new(clock::Clock::system()),
+        )
+    }
+
+    #[test]
+    fn test_disabled_cors_rejects_preflight_and_omits_headers() {
+        let client = BridgeClient::new(&start_harness_without_cors(), None).unwrap();
+
+        let r = client.send("OPTIONS", "/v1/vault", None).unwrap();
+        assert_eq!(r.status, 403, "{}", r.summary());
+
+        for (method, path) in [
+            ("OPTIONS", "/v1/vault"),
+            ("GET", "/health"),
+            ("GET", "/v1/vault"),
+        ] {
+            let r = client.send(method, path, None).unwrap();
+            assert!(
+                r.headers
+                    .iter()
+                    .all(|(name, _)| !name.to_ascii_lowercase().starts_with("access-control-")),
+                "{} {}: {:?}",
+                method,
+                path,
+                r.headers
+            );
+        }
+        let r = client.send("GET", "/health", None).unwrap();
+        assert_eq!(r.status, 200);
+    }
+
+    #[test]
+    fn test_disabled_cors_refuses_other_origins() {
+        let base_url = start_harness_without_cors();
+        let mut page = BridgeClient::new(&base_url, None).unwrap();
+        page.origin = Some("https://evil.example".to_string());
+        let r = page.send("GET", "/v1/vault", None).unwrap();
+        assert_eq!(r.status, 403, "{}", r.summary());
+
+        page.origin = Some(base_url.clone());
+        let r = page.send("GET", "/v1/vault", None).unwrap();
+        assert_eq!(r.status, 200, "{}", r.summary());
+    }
+
+    #[test]
+    fn test_permissive_cors_serves_any_origin() {
+        let mut page = BridgeClient::new(&start_harness(true), None).unwrap();
+        page.origin = Some("chrome-extension://asterisk".to_string());
+        let r = page.send("GET", "/v1/vault", None).unwrap();
+        assert_eq!(r.status, 200, "{}", r.summary());
+        assert_eq!(r.header("Access-Control-Allow-Origin"), Some("*"));
+    }
+
+    #[test]
+    fn test_scoped_token_enforced_per_route() {
+        let mut token_store = tokens::TokenStore::in_memory();
+        let token = token_store
+            .create("stats script", &[tokens::Scope::AuditRead])
+            .unwrap()
+            .token;
+        let base_url = start_harness_with_tokens(true, token_store);
+        let script = BridgeClient::new(&base_url, Some(&token)).unwrap();
+
+        let r = script.send("GET", "/v1/audit/stats", None).unwrap();
+        assert_eq!(r.status, 200, "{}", r.summary());
+        assert_eq!(r.json()["entries"], 0);
+        let r = script.send("GET", "/health", None).unwrap();
+        assert_eq!(r.status, 200);
+
+        let item = probe_item().to_string();
+        let command = probe_command(json!([{ "fieldId": "email", "value": "x" }])).to_string();
+        for (method, path, body) in [
+            ("GET", "/v1/vault", None),
+            ("POST", "/v1/vault", Some(item.as_str())),
+            ("POST", "/v1/fill-commands", Some(command.as_str())),
+            ("GET", "/v1/fill-commands", None),
+        ] {
+            let r = script.send(method, path, body).unwrap();
+            assert_eq!(r.status, 403, "{} {}: {}", method, path, r.summary());
+            assert!(r.json()["requiredScope"].is_string());
+        }
+
+        // Nothing was written
+        let listed = BridgeClient::new(&base_url, None)
+            .unwrap()
+            .send("GET", "/v1/vault", None)
+            .unwrap();
+        assert!(!contains_key(&listed.json(), "key", PROBE_KEY));
+
+        let stranger = BridgeClient::new(&base_url, Some("ast_unknown")).unwrap();
+        let r = stranger.send("GET", "/v1/audit/stats", None).unwrap();
+        assert_eq!(r.status, 401);
+    }
+
+    #[test]
+    fn test_paired_bridge_requires_token_on_v1_routes() {
+        let mut token_store = tokens::TokenStore::in_memory();
+        token_store.set_pairing_token("ast_paired");
+        let base_url = start_harness_with_tokens(true, token_store);
+
+        let page = BridgeClient::new(&base_url, None).unwrap();
+        let stranger = BridgeClient::new(&base_url, Some("ast_guess")).unwrap();
+        for path in ["/v1/vault", "/v1/fill-commands", "/v1/capabilities"] {
+            let r = page.send("GET", path, None).unwrap();
+            assert_eq!(r.status, 401, "{}: {}", path, r.summary());
+            assert_eq!(r.json()["error"], "Missing bridge token");
+            let r = stranger.send("GET", path, None).unwrap();
+            assert_eq!(r.status, 401, "{}: {}", path, r.summary());
+            assert_eq!(r.json()["error"], "Unknown bridge token");
+        }
+
+        // Health checks and preflight still work without it
+        assert_eq!(page.send("GET", "/health", None).unwrap().status, 200);
+        assert_eq!(page.send("GET", "/", None).unwrap().status, 200);
+        let r = page.send("OPTIONS", "/v1/vault", None).unwrap();
+        assert_eq!(r.status, 204, "{}", r.summary());
+
+        let report = run_suite(&base_url, Some("ast_paired"));
+        assert_eq!(report.failed(), 0, "\n{}", report);
+    }
+
+    #[test]
+    fn test_configured_read_only_token_rejected_from_writes() {
+        let mut token_store = tokens::TokenStore::in_memory();
+        token_store.configure(vec![tokens::ConfiguredToken {
+            name: "cli".to_string(),
+            token: "ast_read_only".to_string(),
+            scopes: vec![tokens::Scope::VaultRead, tokens::Scope::FillRead],
+        }]);
+        let base_url = start_harness_with_tokens(true, token_store);
+        let cli = BridgeClient::new(&base_url, Some("ast_read_only")).unwrap();
+
+        for path in ["/v1/vault", "/v1/fill-commands"] {
+            let r = cli.send("GET", path, None).unwrap();
+            assert_eq!(r.status, 200, "GET {}: {}", path, r.summary());
+        }
+        let item = probe_item().to_string();
+        let command = probe_command(json!([{ "fieldId": "email", "value": "x" }])).to_string();
+        for (method, path, body, scope) in [
+            ("POST", "/v1/vault", Some(item.as_str()), "vault:write"),
+            (
+                "POST",
+                "/v1/fill-commands",
+                Some(command.as_str()),
+                "fill:write",
+            ),
+            ("GET", "/v1/audit/stats", None, "audit:read"),
+        ] {
+            let r = cli.send(method, path, body).unwrap();
+            assert_eq!(r.status, 403, "{} {}: {}", method, path, r.summary());
+            assert_eq!(r.json()["requiredScope"], scope);
+        }
+
+        let listed = cli.send("GET", "/v1/vault", None).unwrap();
+        assert!(!contains_key(&listed.json(), "key", PROBE_KEY));
+    }
+
+    #[test]
+    fn test_unreachable_bridge_fails() {
+        let report = run_suite("http://127.0.0.1:1", None);
+        assert_eq!(report.failed(), 1);
+        assert!(report.to_string().contains("FAIL  health"));
+    }
+
+    #[test]
+    fn test_decode_chunked() {
+        assert_eq!(
+            decode_chunked("5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n"),
+            "hello world"
+        );
+    }
+}
@@ -0,0 +1,333 @@
+/*!
+ * Per-Domain Fill Consent
+ *
+ * No value leaves the desktop for a domain until the user has allowed
+ * Asterisk to fill forms there. Fill commands for a domain without consent
+ * are held back as pending until the user grants or denies it.
+ *
+ * "Always" grants are persisted; "once" grants cover a single release and
+ * are consumed by it.
+ */
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::fill::canonicalize_domain;
+use crate::shared::SharedStore;
+use crate::FillCommandJson;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// How long a consent grant lasts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConsentScope {
+    /// Covers the next fill only
+    Once,
+    /// Covers every fill until revoked
+    Always,
+}
+
+/// A user's consent for fills on one domain
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConsentGrantJson {
+    pub domain: String,
+    #[serde(rename = "grantedAt")]
+    pub granted_at: String,
+    pub scope: ConsentScope,
+}
+
+/// Consent status for a domain, as reported to the extension and UI
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConsentStatusJson {
+    pub domain: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grant: Option<ConsentGrantJson>,
+    /// Fill commands waiting on a consent decision
+    #[serde(rename = "pendingCommands")]
+    pub pending_commands: usize,
+}
+
+/// Payload of the `consent-required` event raised for the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentRequestJson {
+    pub domain: String,
+    #[serde(rename = "commandId")]
+    pub command_id: String,
+}
+
+/// The consent store, as shared by commands and the bridge
+pub type SharedConsentStore = SharedStore<ConsentStore>;
+
+/// Name of the Tauri event asking the UI for consent
+pub const CONSENT_REQUIRED_EVENT: &str = "consent-required";
+
+// ============================================================================
+// Consent Store
+// ============================================================================
+
+/// Consent grants plus the fill commands held back awaiting consent
+#[derive(Debug, Default)]
+pub struct ConsentStore {
+    path: Option<PathBuf>,
+    grants: Vec<ConsentGrantJson>,
+    pending: Vec<FillCommandJson>,
+}
+
+impl ConsentStore {
+    /// Create a store whose grants are never written to disk
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Load persisted grants from `path`; a missing file means no grants
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let grants = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("Failed to parse consent grants: {}", e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("Failed to read consent grants: {}", e)),
+        };
+        Ok(Self {
+            path: Some(path),
+            grants,
+            pending: Vec::new(),
+        })
+    }
+
+    pub fn grants(&self) -> &[ConsentGrantJson] {
+        &self.grants
+    }
+
+    /// Decide whether a command for `domain` may be served right away
+    ///
+    /// A "once" grant is consumed by this call.
+    pub fn authorize(&mut self, domain: &str) -> bool {
+        let domain = canonicalize_domain(domain);
+        let Some(index) = self.grants.iter().position(|g| g.domain == domain) else {
+            return false;
+        };
+        if self.grants[index].scope == ConsentScope::Once {
+            self.grants.remove(index);
+        }
+        true
+    }
+
+    /// Hold a command back until consent is decided for its domain
+    pub fn hold(&mut self, command: FillCommandJson) {
+        self.pending.retain(|c| c.id != command.id);
+        self.pending.push(command);
+    }
+
+    /// Grant consent for a domain and release its pending commands in the
+    /// order they were created
+    ///
+    /// A "once" grant is spent on the released commands if there are any;
+    /// otherwise it is kept for the next command.
+    pub fn grant(
+        &mut self,
+        domain: &str,
+        scope: ConsentScope,
+    ) -> Result<Vec<FillCommandJson>, String> {
+        let domain = canonicalize_domain(domain);
+        let has_pending = self
+            .pending
+            .iter()
+            .any(|c| canonicalize_domain(&c.target_domain) == domain);
+
+        // Commands are only released once the grant is saved, so a failed
+        // save leaves them held rather than lost
+        let previous = self.grants.clone();
+        self.grants.retain(|g| g.domain != domain);
+        if scope == ConsentScope::Always || !has_pending {
+            self.grants.push(ConsentGrantJson {
+                domain: domain.clone(),
+                granted_at: chrono::Utc::now().to_rfc3339(),
+                scope,
+            });
+        }
+        if let Err(e) = self.persist() {
+            self.grants = previous;
+            return Err(e);
+        }
+        Ok(self.take_pending(&domain))
+    }
+
+    /// Deny consent for a domain, cancelling its pending commands
+    pub fn deny(&mut self, domain: &str) -> Vec<FillCommandJson> {
+        self.take_pending(&canonicalize_domain(domain))
+    }
+
+    /// Drop held commands by id, whatever their domain; returns how many
+    pub fn cancel(&mut self, command_ids: &[String]) -> usize {
+        let before = self.pending.len();
+        self.pending.retain(|c| !command_ids.contains(&c.id));
+        before - self.pending.len()
+    }
+
+    /// Remove any grant for a domain
+    pub fn revoke(&mut self, domain: &str) -> Result<(), String> {
+        let domain = canonicalize_domain(domain);
+        self.grants.retain(|g| g.domain != domain);
+        self.persist()
+    }
+
+    /// Consent status for a domain
+    pub fn status(&self, domain: &str) -> ConsentStatusJson {
+        let domain = canonicalize_domain(domain);
+        ConsentStatusJson {
+            grant: self.grants.iter().find(|g| g.domain == domain).cloned(),
+            pending_commands: self
+                .pending
+                .iter()
+                .filter(|c| canonicalize_domain(&c.target_domain) == domain)
+                .count(),
+            domain,
+        }
+    }
+
+    fn take_pending(&mut self, domain: &str) -> Vec<FillCommandJson> {
+        let (taken, kept) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|c| canonicalize_domain(&c.target_domain) == domain);
+        self.pending = kept;
+        taken
+    }
+
+    /// Only "always" grants survive a restart
+    fn persist(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create consent directory: {}", e))?;
+        }
+
+        let always: Vec<&ConsentGrantJson> = self
+            .grants
+            .iter()
+            .filter(|g| g.scope == ConsentScope::Always)
+            .collect();
+        let json = serde_json::to_string_pretty(&always)
+            .map_err(|e| format!("Failed to serialize consent grants: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to write consent grants: {}", e))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(id: &str, domain: &str) -> FillCommandJson {
+        FillCommandJson {
+            id: id.to_string(),
+            target_domain: domain.to_string(),
+            target_url: None,
+            fills: vec![],
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            expires_at: "2026-01-01T00:05:00Z".to_string(),
+            capture_undo: false,
+            plan_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_no_consent_by_default() {
+        let mut store = ConsentStore::in_memory();
+        assert!(!store.authorize("example.com"));
+    }
+
+    #[test]
+    fn test_always_scope_persists_across_fills() {
+        let mut store = ConsentStore::in_memory();
+        store.grant("Example.com", ConsentScope::Always).unwrap();
+        assert!(store.authorize("example.com"));
+        assert!(store.authorize("example.com"));
+    }
+
+    #[test]
+    fn test_once_scope_is_consumed() {
+        let mut store = ConsentStore::in_memory();
+        store.grant("example.com", ConsentScope::Once).unwrap();
+        assert!(store.authorize("example.com"));
+        assert!(!store.authorize("example.com"));
+    }
+
+    #[test]
+    fn test_once_grant_spent_on_pending_release() {
+        let mut store = ConsentStore::in_memory();
+        store.hold(command("a", "example.com"));
+
+        let released = store.grant("example.com", ConsentScope::Once).unwrap();
+        assert_eq!(released.len(), 1);
+        assert!(!store.authorize("example.com"));
+    }
+
+    #[test]
+    fn test_release_preserves_creation_order() {
+        let mut store = ConsentStore::in_memory();
+        store.hold(command("first", "example.com"));
+        store.hold(command("other", "other.org"));
+        store.hold(command("second", "example.com"));
+        store.hold(command("third", "example.com"));
+
+        let released = store.grant("example.com", ConsentScope::Always).unwrap();
+        let ids: Vec<&str> = released.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["first", "second", "third"]);
+        assert_eq!(store.status("other.org").pending_commands, 1);
+        assert_eq!(store.status("example.com").pending_commands, 0);
+    }
+
+    #[test]
+    fn test_deny_cancels_pending() {
+        let mut store = ConsentStore::in_memory();
+        store.hold(command("a", "example.com"));
+
+        let cancelled = store.deny("example.com");
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(store.status("example.com").pending_commands, 0);
+        assert!(store.status("example.com").grant.is_none());
+    }
+
+    #[test]
+    fn test_only_always_grants_persist() {
+        let dir = std::env::temp_dir().join(format!("asterisk-consent-{}", std::process::id()));
+        let path = dir.join("consent.json");
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut store = ConsentStore::load(&path).unwrap();
+        store.grant("always.com", ConsentScope::Always).unwrap();
+        store.grant("once.com", ConsentScope::Once).unwrap();
+
+        let mut reloaded = ConsentStore::load(&path).unwrap();
+        assert!(reloaded.authorize("always.com"));
+        assert!(!reloaded.authorize("once.com"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_failed_save_keeps_commands_held() {
+        let dir =
+            std::env::temp_dir().join(format!("asterisk-consent-fail-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut store = ConsentStore::load(dir.join("blocker").join("consent.json")).unwrap();
+        // A file where the grants' directory should be makes the save fail
+        fs::write(dir.join("blocker"), "").unwrap();
+        store.hold(command("cmd-1", "example.com"));
+
+        assert!(store.grant("example.com", ConsentScope::Always).is_err());
+        assert!(!store.authorize("example.com"));
+        assert_eq!(store.status("example.com").pending_commands, 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,112 @@
+/*!
+ * Bridge CORS Policy
+ *
+ * The browser extension calls the bridge cross-origin, so by default every
+ * response carries permissive `Access-Control-*` headers and `OPTIONS`
+ * preflights are answered. Users who only run the native UI can set
+ * `ASTERISK_DISABLE_CORS=1` to turn that surface off: no CORS headers are
+ * sent, preflights are refused, and requests whose `Origin` is not the
+ * bridge itself are refused before routing. Callers that send no `Origin`
+ * (native tools, scripts) are served as before.
+ */
+
+use tiny_http::Header;
+
+/// Environment variable that disables CORS when set to a truthy value
+pub const DISABLE_ENV: &str = "ASTERISK_DISABLE_CORS";
+
+/// Whether the bridge serves cross-origin callers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CorsPolicy {
+    /// Any origin may call the bridge (needed by the extension)
+    #[default]
+    Permissive,
+    /// Same-origin and native callers only
+    Disabled,
+}
+
+impl CorsPolicy {
+    /// Read the policy from `ASTERISK_DISABLE_CORS`
+    pub fn from_env() -> Self {
+        Self::from_setting(std::env::var(DISABLE_ENV).ok().as_deref())
+    }
+
+    /// "1", "true", "yes" or "on" disable CORS; anything else keeps it
+    pub fn from_setting(value: Option<&str>) -> Self {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            Some("1" | "true" | "yes" | "on") => Self::Disabled,
+            _ => Self::Permissive,
+        }
+    }
+
+    /// Headers added to every bridge response
+    pub fn headers(self) -> Vec<Header> {
+        match self {
+            Self::Disabled => Vec::new(),
+            Self::Permissive => vec![
+                Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap(),
+                Header::from_bytes(
+                    &b"Access-Control-Allow-Methods"[..],
+                    &b"GET, POST, DELETE, OPTIONS"[..],
+                )
+                .unwrap(),
+                Header::from_bytes(
+                    &b"Access-Control-Allow-Headers"[..],
+                    &b"Content-Type, X-Asterisk-Client, X-Asterisk-Token, X-Asterisk-Incognito"[..],
+                )
+                .unwrap(),
+            ],
+        }
+    }
+
+    /// Whether a request with this `Origin` and `Host` may be served
+    ///
+    /// Only a disabled policy refuses anything, and then only requests from
+    /// a page on another origin.
+    pub fn allows(self, origin: Option<&str>, host: Option<&str>) -> bool {
+        match (self, origin) {
+            (Self::Permissive, _) | (Self::Disabled, None) => true,
+            (Self::Disabled, Some(origin)) => {
+                host.is_some_and(|host| origin.eq_ignore_ascii_case(&format!("http://{}", host)))
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_from_setting() {
+        assert_eq!(CorsPolicy::from_setting(None), CorsPolicy::Permissive);
+        assert_eq!(CorsPolicy::from_setting(Some("0")), CorsPolicy::Permissive);
+        assert_eq!(CorsPolicy::from_setting(Some("")), CorsPolicy::Permissive);
+        assert_eq!(CorsPolicy::from_setting(Some("1")), CorsPolicy::Disabled);
+        assert_eq!(
+            CorsPolicy::from_setting(Some(" TRUE ")),
+            CorsPolicy::Disabled
+        );
+    }
+
+    #[test]
+    fn test_disabled_policy_sends_no_headers() {
+        assert_eq!(CorsPolicy::Permissive.headers().len(), 3);
+        assert!(CorsPolicy::Disabled.headers().is_empty());
+    }
+
+    #[test]
+    fn test_disabled_policy_refuses_other_origins() {
+        let host = Some("127.0.0.1:17373");
+        let page = Some("https://evil.example");
+        assert!(CorsPolicy::Permissive.allows(page, host));
+        assert!(!CorsPolicy::Disabled.allows(page, host));
+        assert!(!CorsPolicy::Disabled.allows(Some("chrome-extension://abc"), host));
+        assert!(CorsPolicy::Disabled.allows(None, host));
+        assert!(CorsPolicy::Disabled.allows(Some("http://127.0.0.1:17373"), host));
+    }
+}
@@ -0,0 +1,612 @@
+/*!
+ * Disk Usage
+ *
+ * `storage_report` walks the data directory and attributes every file to
+ * the artifact that wrote it (the file names are the ones `run()` opens),
+ * with item counts and configured caps where the artifact has them.
+ * Anything unrecognised is reported as `other` rather than hidden.
+ *
+ * `storage_trim` runs an artifact's own pruning mechanism (audit retention,
+ * LLM cache eviction) and reports the bytes reclaimed, measured on disk
+ * before and after; audit pruning is recorded in the maintenance ledger.
+ * Artifacts without a pruning mechanism are refused rather than deleted
+ * behind their owner's back.
+ *
+ * `storage_footprint` is the short answer for support and quotas: bytes
+ * under the data directory in a handful of categories (audit, vault,
+ * caches, templates, backups, other) that always add up to the total.
+ * Missing files and a missing data directory count as zero.
+ *
+ * The walker never follows symlinks, so a link inside the data directory
+ * can't make it report (or be pointed at) files elsewhere.
+ */
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::audit::AuditLog;
+use crate::llm::AnalysisCache;
+use crate::maintenance::MaintenanceLedger;
+
+/// Deepest directory level walked below the data directory
+const MAX_DEPTH: usize = 8;
+
+/// Default audit retention for a trim, in days
+pub const DEFAULT_AUDIT_RETENTION_DAYS: u32 = 90;
+
+/// A kind of data Asterisk keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageArtifact {
+    /// File-backed vault and its journal
+    Vault,
+    /// Audit logs of every profile
+    Audit,
+    AccessLog,
+    VaultHistory,
+    Telemetry,
+    MaintenanceLedger,
+    Templates,
+    FieldOverrides,
+    Consent,
+    Recall,
+    Tokens,
+    /// Cached LLM answers (held in memory)
+    LlmCache,
+    /// Files in the data directory that no artifact claims
+    Other,
+}
+
+/// The artifact that owns a file, from its path relative to the data dir
+fn artifact_for(relative: &Path) -> StorageArtifact {
+    // Everything Asterisk writes lives at the top level
+    if relative.components().count() != 1 {
+        return StorageArtifact::Other;
+    }
+    let name = relative.to_string_lossy();
+    let name = name.strip_suffix(".tmp").unwrap_or(&name);
+    match name {
+        // "vault" is `vault.tmp` mid-checkpoint
+        "vault.json" | "vault.journal" | "vault" | "vault.enc" | "vault.keychain.enc"
+        | "vault.dev.json" => StorageArtifact::Vault,
+        "access.jsonl" => StorageArtifact::AccessLog,
+        "vault-history.jsonl" => StorageArtifact::VaultHistory,
+        "matching.jsonl" | "telemetry.salt" => StorageArtifact::Telemetry,
+        "maintenance.jsonl" => StorageArtifact::MaintenanceLedger,
+        "templates.json" => StorageArtifact::Templates,
+        "field-overrides.json" => StorageArtifact::FieldOverrides,
+        "consent.json" => StorageArtifact::Consent,
+        "recall.json" | "recall.key" => StorageArtifact::Recall,
+        "tokens.json" => StorageArtifact::Tokens,
+        // The key that signs audit evidence bundles
+        "evidence.key" => StorageArtifact::Audit,
+        _ if name.starts_with("audit") && name.ends_with(".jsonl") => StorageArtifact::Audit,
+        _ => StorageArtifact::Other,
+    }
+}
+
+/// Disk use of one artifact
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ArtifactUsageJson {
+    pub artifact: StorageArtifact,
+    pub bytes: u64,
+    pub files: usize,
+    /// Records held, where the artifact counts them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<usize>,
+    /// Configured limit on `items`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cap: Option<usize>,
+    /// Whether `storage_trim` can prune it
+    pub trimmable: bool,
+}
+
+/// Disk use of the whole data directory
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StorageReportJson {
+    /// Absent when nothing is written to disk
+    #[serde(rename = "dataDir", skip_serializing_if = "Option::is_none")]
+    pub data_dir: Option<String>,
+    #[serde(rename = "totalBytes")]
+    pub total_bytes: u64,
+    pub artifacts: Vec<ArtifactUsageJson>,
+}
+
+/// Bytes reclaimed from one trimmed artifact
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StorageTrimJson {
+    pub target: StorageArtifact,
+    #[serde(rename = "reclaimedBytes")]
+    pub reclaimed_bytes: u64,
+    /// Records removed (audit entries, cached answers)
+    #[serde(rename = "removedCount")]
+    pub removed_count: usize,
+}
+
+/// Live stores the report counts and the trim delegates to
+pub struct StorageContext<'a> {
+    pub audit: &'a AuditLog,
+    pub llm_cache: &'a AnalysisCache,
+    /// Records audit pruning like any other destructive maintenance
+    pub ledger: &'a MaintenanceLedger,
+    /// Items in the active vault
+    pub vault_items: usize,
+    pub templates: usize,
+}
+
+// ============================================================================
+// Walking
+// ============================================================================
+
+/// Regular files under `dir` with their sizes, relative to `dir`
+///
+/// Symlinks are skipped, not followed; unreadable entries are skipped.
+fn walk(dir: &Path) -> Vec<(PathBuf, u64)> {
+    let mut files = Vec::new();
+    let mut pending = vec![(dir.to_path_buf(), 0)];
+    while let Some((current, depth)) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&current) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            // symlink_metadata describes the link itself
+            let Ok(metadata) = fs::symlink_metadata(&path) else {
+                continue;
+            };
+            if metadata.file_type().is_symlink() {
+                continue;
+            }
+            if metadata.is_dir() {
+                if depth < MAX_DEPTH {
+                    pending.push((path, depth + 1));
+                }
+            } else if metadata.is_file() {
+                if let Ok(relative) = path.strip_prefix(dir) {
+                    files.push((relative.to_path_buf(), metadata.len()));
+                }
+            }
+        }
+    }
+    files
+}
+
+/// Bytes on disk of one artifact
+fn artifact_bytes(dir: &Path, artifact: StorageArtifact) -> u64 {
+    walk(dir)
+        .into_iter()
+        .filter(|(path, _)| artifact_for(path) == artifact)
+        .map(|(_, size)| size)
+        .sum()
+}
+
+// ============================================================================
+// Report and Trim
+// ============================================================================
+
+const ARTIFACTS: &[StorageArtifact] = &[
+    StorageArtifact::Vault,
+    StorageArtifact::Audit,
+    StorageArtifact::AccessLog,
+    StorageArtifact::VaultHistory,
+    StorageArtifact::Telemetry,
+    StorageArtifact::MaintenanceLedger,
+    StorageArtifact::Templates,
+    StorageArtifact::FieldOverrides,
+    StorageArtifact::Consent,
+    StorageArtifact::Recall,
+    StorageArtifact::Tokens,
+    StorageArtifact::LlmCache,
+    StorageArtifact::Other,
+];
+
+fn is_trimmable(artifact: StorageArtifact) -> bool {
+    matches!(artifact, StorageArtifact::Audit | StorageArtifact::LlmCache)
+}
+
+/// Report disk use of `data_dir` (None in memory mode) by artifact
+pub fn report(
+    data_dir: Option<&Path>,
+    context: &StorageContext<'_>,
+) -> Result<StorageReportJson, String> {
+    let files = data_dir.map(walk).unwrap_or_default();
+
+    let mut artifacts: Vec<ArtifactUsageJson> = ARTIFACTS
+        .iter()
+        .map(|&artifact| ArtifactUsageJson {
+            artifact,
+            bytes: 0,
+            files: 0,
+            items: None,
+            cap: None,
+            trimmable: is_trimmable(artifact),
+        })
+        .collect();
+    for (path, size) in &files {
+        let artifact = artifact_for(path);
+        if let Some(usage) = artifacts.iter_mut().find(|u| u.artifact == artifact) {
+            usage.bytes += size;
+            usage.files += 1;
+        }
+    }
+
+    let audit_entries = context.audit.entries()?.len();
+    for usage in &mut artifacts {
+        match usage.artifact {
+            StorageArtifact::Vault => usage.items = Some(context.vault_items),
+            StorageArtifact::Audit => usage.items = Some(audit_entries),
+            StorageArtifact::Templates => usage.items = Some(context.templates),
+            StorageArtifact::LlmCache => {
+                usage.items = Some(context.llm_cache.stats().entries);
+                usage.cap = Some(context.llm_cache.capacity());
+            }
+            _ => {}
+        }
+    }
+
+    Ok(StorageReportJson {
+        data_dir: data_dir.map(|d| d.display().to_string()),
+        total_bytes: files.iter().map(|(_, size)| size).sum(),
+        artifacts,
+    })
+}
+
+/// Prune each target with its own mechanism
+///
+/// Audit entries older than `audit_retention_days` are removed from the
+/// active profile's log; the LLM cache is emptied. Fails before touching
+/// anything if a target has no pruning mechanism.
+pub fn trim(
+    data_dir: Option<&Path>,
+    targets: &[StorageArtifact],
+    audit_retention_days: u32,
+    context: &StorageContext<'_>,
+) -> Result<Vec<StorageTrimJson>, String> {
+    if let Some(target) = targets.iter().find(|t| !is_trimmable(**t)) {
+        return Err(format!("{:?} has no pruning mechanism", target));
+    }
+
+    let mut results = Vec::new();
+    for &target in targets {
+        let before = data_dir.map_or(0, |dir| artifact_bytes(dir, target));
+        let removed_count = match target {
+            StorageArtifact::Audit => {
+                let cutoff =
+                    chrono::Utc::now() - chrono::Duration::days(i64::from(audit_retention_days));
+                let report = context.audit.prune(cutoff, false)?;
+                context.ledger.record(&report)?;
+                report.removed_count
+            }
+            StorageArtifact::LlmCache => context.llm_cache.evict_all(),
+            _ => unreachable!("checked above"),
+        };
+        let after = data_dir.map_or(0, |dir| artifact_bytes(dir, target));
+        results.push(StorageTrimJson {
+            target,
+            reclaimed_bytes: before.saturating_sub(after),
+            removed_count,
+        });
+    }
+    Ok(results)
+}
+
+// ============================================================================
+// Footprint
+// ============================================================================
+
+/// Bytes under the data directory by category; the categories add up to
+/// `total_bytes`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct StorageFootprintJson {
+    /// Absent when nothing is written to disk
+    #[serde(rename = "dataDir", skip_serializing_if = "Option::is_none")]
+    pub data_dir: Option<String>,
+    #[serde(rename = "totalBytes")]
+    pub total_bytes: u64,
+    /// Audit logs, the access log and the evidence signing key
+    pub audit: u64,
+    /// The vault file and its journal, when the vault is file-backed
+    pub vault: u64,
+    /// Webview and other cache directories; the LLM answer cache is held in
+    /// memory and takes nothing here
+    pub caches: u64,
+    pub templates: u64,
+    /// Corrupt files moved aside at startup, and `.bak` copies
+    pub backups: u64,
+    pub other: u64,
+}
+
+/// Whether a file is a copy kept aside rather than live data
+fn is_backup(relative: &Path) -> bool {
+    let name = relative.to_string_lossy();
+    name.contains(".corrupt-") || name.ends_with(".bak")
+}
+
+/// Whether a file sits in a cache directory (WebView2 keeps its profile,
+/// caches included, in the data directory on Windows)
+fn is_cache(relative: &Path) -> bool {
+    let mut dirs = relative.parent().into_iter().flat_map(Path::components);
+    dirs.any(|dir| {
+        let dir = dir.as_os_str().to_string_lossy().to_ascii_lowercase();
+        dir == "ebwebview" || dir.contains("cache")
+    })
+}
+
+/// Sum the files under `data_dir` (None in memory mode) by category
+pub fn footprint(data_dir: Option<&Path>) -> StorageFootprintJson {
+    let mut footprint = StorageFootprintJson {
+        data_dir: data_dir.map(|d| d.display().to_string()),
+        ..Default::default()
+    };
+    for (path, size) in data_dir.map(walk).unwrap_or_default() {
+        footprint.total_bytes += size;
+        let category = if is_backup(&path) {
+            &mut footprint.backups
+        } else if is_cache(&path) {
+            &mut footprint.caches
+        } else {
+            match artifact_for(&path) {
+                StorageArtifact::Audit | StorageArtifact::AccessLog => &mut footprint.audit,
+                StorageArtifact::Vault => &mut footprint.vault,
+                StorageArtifact::Templates => &mut footprint.templates,
+                StorageArtifact::LlmCache => &mut footprint.caches,
+                _ => &mut footprint.other,
+            }
+        };
+        *category += size;
+    }
+    footprint
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuditEntryJson, AuditSummaryJson};
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "asterisk-disk-usage-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(dir: &Path, name: &str, bytes: usize) {
+        fs::write(dir.join(name), vec![b'x'; bytes]).unwrap();
+    }
+
+    fn audit_entry(id: &str, created_at: &str) -> AuditEntryJson {
+        AuditEntryJson {
+            id: id.to_string(),
+            created_at: created_at.to_string(),
+            url: "https://example.com/form".to_string(),
+            domain: "example.com".to_string(),
+            fingerprint: "fp".to_string(),
+            summary: AuditSummaryJson {
+                planned_count: 1,
+                applied_count: 1,
+                blocked_count: 0,
+                reviewed_count: 0,
+            },
+            items: vec![],
+            recall_recorded: false,
+            fill_command_id: None,
+            acceptance_id: None,
+            acceptance_hash: None,
+            plan_hash: None,
+            detail: Default::default(),
+        }
+    }
+
+    fn usage(report: &StorageReportJson, artifact: StorageArtifact) -> &ArtifactUsageJson {
+        report
+            .artifacts
+            .iter()
+            .find(|u| u.artifact == artifact)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_report_attributes_files_to_artifacts() {
+        let dir = scratch("report");
+        write(&dir, "vault.json", 100);
+        write(&dir, "vault.journal", 20);
+        write(&dir, "templates.json", 300);
+        write(&dir, "recall.json", 40);
+        write(&dir, "recall.key", 32);
+        write(&dir, "audit-work.jsonl", 7);
+        write(&dir, "notes.txt", 5);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        write(&dir.join("nested"), "vault.json", 11);
+
+        let audit = AuditLog::new(&dir);
+        audit
+            .append(audit_entry("a1", "2026-01-01T00:00:00Z"))
+            .unwrap();
+        let audit_bytes = fs::metadata(dir.join("audit.jsonl")).unwrap().len();
+        let cache = AnalysisCache::with_capacity(64);
+        let ledger = MaintenanceLedger::in_memory();
+        let context = StorageContext {
+            audit: &audit,
+            llm_cache: &cache,
+            ledger: &ledger,
+            vault_items: 3,
+            templates: 2,
+        };
+
+        let report = report(Some(&dir), &context).unwrap();
+        let vault = usage(&report, StorageArtifact::Vault);
+        assert_eq!((vault.bytes, vault.files, vault.items), (120, 2, Some(3)));
+        assert_eq!(usage(&report, StorageArtifact::Templates).items, Some(2));
+        assert_eq!(usage(&report, StorageArtifact::Recall).bytes, 72);
+        let audit_usage = usage(&report, StorageArtifact::Audit);
+        assert_eq!(audit_usage.bytes, audit_bytes + 7);
+        assert_eq!(audit_usage.items, Some(1));
+        assert!(audit_usage.trimmable);
+        // Files in subdirectories aren't ours, whatever their name
+        assert_eq!(usage(&report, StorageArtifact::Other).bytes, 16);
+        let cache_usage = usage(&report, StorageArtifact::LlmCache);
+        assert_eq!((cache_usage.bytes, cache_usage.cap), (0, Some(64)));
+        assert_eq!(report.total_bytes, 120 + 300 + 72 + audit_bytes + 7 + 16);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walker_does_not_follow_symlinks() {
+        let dir = scratch("symlink");
+        let outside = scratch("symlink-outside");
+        write(&outside, "big.bin", 10_000);
+        write(&dir, "consent.json", 10);
+        std::os::unix::fs::symlink(&outside, dir.join("linked-dir")).unwrap();
+        std::os::unix::fs::symlink(outside.join("big.bin"), dir.join("templates.json")).unwrap();
+
+        let audit = AuditLog::in_memory();
+        let cache = AnalysisCache::new();
+        let ledger = MaintenanceLedger::in_memory();
+        let context = StorageContext {
+            audit: &audit,
+            llm_cache: &cache,
+            ledger: &ledger,
+            vault_items: 0,
+            templates: 0,
+        };
+        let report = report(Some(&dir), &context).unwrap();
+        assert_eq!(report.total_bytes, 10);
+        assert_eq!(usage(&report, StorageArtifact::Templates).files, 0);
+
+        let _ = fs::remove_dir_all(&dir);
+        let _ = fs::remove_dir_all(&outside);
+    }
+
+    #[test]
+    fn test_trim_delegates_to_audit_retention_and_cache_eviction() {
+        let dir = scratch("trim");
+        let audit = AuditLog::new(&dir);
+        audit
+            .append(audit_entry("old", "2020-01-01T00:00:00Z"))
+            .unwrap();
+        let recent = chrono::Utc::now().to_rfc3339();
+        audit.append(audit_entry("new", &recent)).unwrap();
+        let before = fs::metadata(dir.join("audit.jsonl")).unwrap().len();
+
+        let ledger = MaintenanceLedger::in_memory();
+        let cache = AnalysisCache::new();
+        cache.store(
+            &crate::llm::AnalyzeFieldRequest {
+                label: "Email".to_string(),
+                name: "email".to_string(),
+                field_type: "email".to_string(),
+                placeholder: None,
+                semantic: None,
+                available_keys: vec!["email".to_string()],
+                aria_label: None,
+                described_by: None,
+                section_heading: None,
+                nearby_text: None,
+            },
+            &crate::llm::AnalyzeFieldResponse {
+                vault_key: Some("email".to_string()),
+                confidence: 0.9,
+                reasoning: String::new(),
+                provider: None,
+            },
+        );
+        let context = StorageContext {
+            audit: &audit,
+            llm_cache: &cache,
+            ledger: &ledger,
+            vault_items: 0,
+            templates: 0,
+        };
+
+        let results = trim(
+            Some(&dir),
+            &[StorageArtifact::Audit, StorageArtifact::LlmCache],
+            DEFAULT_AUDIT_RETENTION_DAYS,
+            &context,
+        )
+        .unwrap();
+        let after = fs::metadata(dir.join("audit.jsonl")).unwrap().len();
+        assert_eq!(results[0].target, StorageArtifact::Audit);
+        assert_eq!(results[0].removed_count, 1);
+        assert_eq!(results[0].reclaimed_bytes, before - after);
+        assert!(results[0].reclaimed_bytes > 0);
+        assert_eq!(audit.entries().unwrap()[0].id, "new");
+        assert_eq!(ledger.list().unwrap()[0].removed_ids, vec!["old"]);
+        assert_eq!(results[1].removed_count, 1);
+        assert_eq!(cache.stats().entries, 0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_footprint_totals_by_category() {
+        let dir = scratch("footprint");
+        write(&dir, "vault.enc", 100);
+        write(&dir, "vault.journal", 20);
+        write(&dir, "audit.jsonl", 50);
+        write(&dir, "audit-work.jsonl", 7);
+        write(&dir, "access.jsonl", 3);
+        write(&dir, "templates.json", 300);
+        write(&dir, "consent.json.corrupt-1767225600", 40);
+        write(&dir, "templates.json.bak", 9);
+        write(&dir, "tokens.json", 5);
+        fs::create_dir_all(dir.join("EBWebView").join("Default")).unwrap();
+        write(&dir.join("EBWebView").join("Default"), "Cookies", 60);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        write(&dir.join("nested"), "vault.json", 11);
+
+        let footprint = footprint(Some(&dir));
+        assert_eq!(footprint.vault, 120);
+        assert_eq!(footprint.audit, 60);
+        assert_eq!(footprint.templates, 300);
+        assert_eq!(footprint.backups, 49);
+        assert_eq!(footprint.caches, 60);
+        assert_eq!(footprint.other, 16);
+        assert_eq!(footprint.total_bytes, 120 + 60 + 300 + 49 + 60 + 16);
+
+        // Nothing on disk yet is all zeros, not an error
+        let _ = fs::remove_dir_all(&dir);
+        let missing = super::footprint(Some(&dir));
+        assert_eq!(missing.total_bytes, 0);
+        assert_eq!(missing.vault, 0);
+        assert_eq!(super::footprint(None), StorageFootprintJson::default());
+    }
+
+    #[test]
+    fn test_trim_refuses_artifacts_without_pruning() {
+        let dir = scratch("refuse");
+        write(&dir, "tokens.json", 10);
+        let audit = AuditLog::in_memory();
+        let cache = AnalysisCache::new();
+        let ledger = MaintenanceLedger::in_memory();
+        let context = StorageContext {
+            audit: &audit,
+            llm_cache: &cache,
+            ledger: &ledger,
+            vault_items: 0,
+            templates: 0,
+        };
+        let err = trim(
+            Some(&dir),
+            &[StorageArtifact::LlmCache, StorageArtifact::Tokens],
+            DEFAULT_AUDIT_RETENTION_DAYS,
+            &context,
+        )
+        .unwrap_err();
+        assert!(err.contains("Tokens"), "{}", err);
+        assert!(dir.join("tokens.json").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
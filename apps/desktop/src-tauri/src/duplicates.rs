@@ -0,0 +1,396 @@
+/*!
+ * Duplicate Field Resolution
+ *
+ * Checkout and application forms often ask the same thing twice in
+ * different sections: a postal code for shipping and one for billing, an
+ * email for the applicant and one for their employer. Matched field by
+ * field, both get the same vault item, which is wrong whenever the second
+ * section is meant for a different address or person.
+ *
+ * After matching, recommendations that share a vault key but sit in
+ * different sections (by `sectionHeading`, or by the section tokens of the
+ * autocomplete attribute) are treated as a duplicate group:
+ * - A field whose section names a variant ("Billing", "Work", ...) takes the
+ *   matching variant from the vault when there is one (`billingZip` for a
+ *   billing postal code)
+ * - Any field still sharing a key with an earlier section is capped to
+ *   review, with a reason naming both sections (fields already below the
+ *   review threshold stay blocked)
+ */
+
+use asterisk_vault::VaultItem;
+
+use crate::matching::{FillRecommendationJson, REVIEW_THRESHOLD};
+use crate::FieldNodeJson;
+
+/// What a section heading says the fields under it are for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variant {
+    Shipping,
+    Billing,
+    Work,
+    Personal,
+}
+
+impl Variant {
+    /// Words that name the variant in headings, autocomplete tokens and
+    /// vault keys
+    fn keywords(self) -> &'static [&'static str] {
+        match self {
+            Variant::Shipping => &["shipping", "delivery"],
+            Variant::Billing => &["billing", "invoice", "payment"],
+            Variant::Work => &["work", "employer", "business", "office"],
+            Variant::Personal => &["personal", "home"],
+        }
+    }
+
+    fn all() -> [Variant; 4] {
+        [
+            Variant::Shipping,
+            Variant::Billing,
+            Variant::Work,
+            Variant::Personal,
+        ]
+    }
+
+    fn from_text(text: &str) -> Option<Variant> {
+        let text = text.to_lowercase();
+        Variant::all()
+            .into_iter()
+            .find(|v| v.keywords().iter().any(|k| text.contains(k)))
+    }
+}
+
+/// The section a field sits in
+#[derive(Debug, Clone, PartialEq)]
+struct Section {
+    /// Shown in explanations: the heading, or the autocomplete section
+    name: String,
+    variant: Option<Variant>,
+}
+
+/// Infer a field's section from its heading, then its autocomplete tokens
+fn section_of(field: &FieldNodeJson) -> Option<Section> {
+    if let Some(heading) = field
+        .section_heading
+        .as_deref()
+        .map(str::trim)
+        .filter(|h| !h.is_empty())
+    {
+        return Some(Section {
+            name: heading.to_string(),
+            variant: Variant::from_text(heading),
+        });
+    }
+
+    // "section-x shipping postal-code": every token but the field name
+    let autocomplete = field.autocomplete.as_deref()?;
+    let tokens: Vec<&str> = autocomplete.split_whitespace().collect();
+    let (_, qualifiers) = tokens.split_last()?;
+    let grouping: Vec<&str> = qualifiers
+        .iter()
+        .copied()
+        .filter(|t| t.starts_with("section-") || Variant::from_text(t).is_some())
+        .collect();
+    if grouping.is_empty() {
+        return None;
+    }
+    Some(Section {
+        name: grouping.join(" "),
+        variant: grouping.iter().find_map(|t| Variant::from_text(t)),
+    })
+}
+
+/// Lowercased vault key with variant words removed ("billingZip" -> "zip")
+fn key_stem(key: &str) -> String {
+    let mut stem = key.to_lowercase();
+    for variant in Variant::all() {
+        for keyword in variant.keywords() {
+            stem = stem.replace(keyword, "");
+        }
+    }
+    stem.replace(['_', '-', '.', ' '], "")
+}
+
+/// The vault item for `variant` of the same data as `base`, if the vault
+/// has one
+fn find_variant<'a>(
+    vault_items: &'a [VaultItem],
+    base: &VaultItem,
+    variant: Variant,
+) -> Option<&'a VaultItem> {
+    let stem = key_stem(&base.key);
+    if stem.is_empty() {
+        return None;
+    }
+    let same_data =
+        |item: &&VaultItem| item.category == base.category && key_stem(&item.key) == stem;
+    let names_variant = |item: &&VaultItem| {
+        let text = format!("{} {}", item.key, item.label).to_lowercase();
+        variant.keywords().iter().any(|k| text.contains(k))
+    };
+
+    vault_items
+        .iter()
+        .filter(same_data)
+        .find(names_variant)
+        .or_else(|| {
+            // Personal details are usually stored without a qualifier
+            (variant == Variant::Personal).then(|| {
+                vault_items
+                    .iter()
+                    .filter(same_data)
+                    .find(|item| Variant::from_text(&item.key).is_none())
+            })?
+        })
+}
+
+/// Resolve recommendations that share a vault key across sections
+///
+/// `fields` are the snapshot's fields, in form order; recommendations are
+/// updated in place.
+pub fn resolve_duplicates(
+    fields: &[FieldNodeJson],
+    vault_items: &[VaultItem],
+    recommendations: &mut [FillRecommendationJson],
+) {
+    let position = |field_id: &str| fields.iter().position(|f| f.id == field_id);
+    let mut order: Vec<usize> = (0..recommendations.len()).collect();
+    order.sort_by_key(|&i| position(&recommendations[i].field_id));
+
+    let sections: Vec<Option<Section>> = recommendations
+        .iter()
+        .map(|r| {
+            position(&r.field_id)
+                .map(|p| &fields[p])
+                .and_then(section_of)
+        })
+        .collect();
+
+    // Only keys asked for in more than one section form a group
+    let in_group = |i: usize| {
+        let Some(section) = &sections[i] else {
+            return false;
+        };
+        order.iter().any(|&j| {
+            j != i
+                && recommendations[j].vault_key == recommendations[i].vault_key
+                && sections[j].as_ref().is_some_and(|s| s.name != section.name)
+        })
+    };
+    let grouped: Vec<bool> = (0..recommendations.len()).map(in_group).collect();
+
+    // Prefer the section's own variant of the data
+    for &i in &order {
+        if !grouped[i] {
+            continue;
+        }
+        let Some(variant) = sections[i].as_ref().and_then(|s| s.variant) else {
+            continue;
+        };
+        let Some(base) = vault_items
+            .iter()
+            .find(|item| item.key == recommendations[i].vault_key)
+        else {
+            continue;
+        };
+        if let Some(item) = find_variant(vault_items, base, variant) {
+            if item.key != base.key {
+                let section = sections[i].as_ref().map_or("", |s| s.name.as_str());
+                let rec = &mut recommendations[i];
+                rec.reason = format!("{}; \"{}\" section uses {}", rec.reason, section, item.key);
+                rec.vault_key = item.key.clone();
+            }
+        }
+    }
+
+    // Whatever still repeats an earlier section's value needs a look
+    for (n, &i) in order.iter().enumerate() {
+        if !grouped[i] {
+            continue;
+        }
+        let Some(section) = &sections[i] else {
+            continue;
+        };
+        let first = order[..n].iter().find(|&&j| {
+            recommendations[j].vault_key == recommendations[i].vault_key
+                && sections[j].as_ref().is_some_and(|s| s.name != section.name)
+        });
+        let Some(&first) = first else {
+            continue;
+        };
+        let earlier = sections[first].as_ref().map_or("", |s| s.name.as_str());
+        let rec = &mut recommendations[i];
+        rec.confidence = rec.confidence.min(REVIEW_THRESHOLD);
+        rec.reason = format!(
+            "{}; the \"{}\" section asks for this again after \"{}\", check it should be the same value",
+            rec.reason, section.name, earlier
+        );
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::{generate_fill_plan, MatchTier};
+    use crate::{Disposition, FormFingerprintJson, FormSnapshotJson};
+    use asterisk_vault::{test_util, VaultCategory};
+
+    fn item(key: &str, category: VaultCategory) -> VaultItem {
+        test_util::item(key, &format!("{} value", key), category)
+    }
+
+    fn field(id: &str, field_type: &str, label: &str, heading: &str) -> FieldNodeJson {
+        FieldNodeJson {
+            id: id.to_string(),
+            name: id.to_string(),
+            label: label.to_string(),
+            field_type: field_type.to_string(),
+            semantic: "unknown".to_string(),
+            section_heading: Some(heading.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn form(fields: Vec<FieldNodeJson>) -> FormSnapshotJson {
+        FormSnapshotJson {
+            url: "https://shop.example/checkout".to_string(),
+            domain: "shop.example".to_string(),
+            title: "Checkout".to_string(),
+            captured_at: chrono::Utc::now().to_rfc3339(),
+            fingerprint: FormFingerprintJson {
+                field_count: fields.len() as u32,
+                field_types: vec![],
+                required_count: 0,
+                hash: "checkout".to_string(),
+            },
+            fields,
+            session: Default::default(),
+        }
+    }
+
+    /// Shipping and billing sections, each asking for a postal code
+    fn checkout() -> FormSnapshotJson {
+        form(vec![
+            field("ship-city", "text", "City", "Shipping address"),
+            field("ship-zip", "text", "Postal code", "Shipping address"),
+            field("bill-zip", "text", "Postal code", "Billing address"),
+        ])
+    }
+
+    fn rec<'a>(plan: &'a crate::matching::FillPlanJson, id: &str) -> &'a FillRecommendationJson {
+        plan.recommendations
+            .iter()
+            .find(|r| r.field_id == id)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_billing_variant_is_used_when_stored() {
+        let vault = vec![
+            item("city", VaultCategory::Address),
+            item("zip", VaultCategory::Address),
+            item("billingZip", VaultCategory::Address),
+        ];
+        let plan = generate_fill_plan(&checkout(), &vault, None);
+        assert_eq!(rec(&plan, "ship-zip").vault_key, "zip");
+        let billing = rec(&plan, "bill-zip");
+        assert_eq!(billing.vault_key, "billingZip");
+        assert_eq!(billing.confidence, 0.85);
+        assert!(billing
+            .reason
+            .contains("\"Billing address\" section uses billingZip"));
+    }
+
+    #[test]
+    fn test_ambiguous_second_occurrence_goes_to_review() {
+        let vault = vec![
+            item("city", VaultCategory::Address),
+            item("zip", VaultCategory::Address),
+        ];
+        let mut snapshot = checkout();
+        for f in &mut snapshot.fields {
+            f.autocomplete = Some("postal-code".to_string());
+        }
+        snapshot.fields[0].autocomplete = None;
+
+        let plan = generate_fill_plan(&snapshot, &vault, None);
+        let shipping = rec(&plan, "ship-zip");
+        assert_eq!(shipping.vault_key, "zip");
+        assert_eq!(shipping.confidence, 0.95);
+
+        let billing = rec(&plan, "bill-zip");
+        assert_eq!(billing.vault_key, "zip");
+        assert_eq!(billing.match_tier, MatchTier::Autocomplete);
+        assert_eq!(
+            Disposition::for_confidence(billing.confidence),
+            Disposition::Review
+        );
+        assert!(
+            billing.reason.contains("\"Billing address\""),
+            "{}",
+            billing.reason
+        );
+        assert!(
+            billing.reason.contains("\"Shipping address\""),
+            "{}",
+            billing.reason
+        );
+    }
+
+    #[test]
+    fn test_employer_and_personal_emails_resolve_to_variants() {
+        let vault = vec![
+            item("email", VaultCategory::Contact),
+            item("workEmail", VaultCategory::Contact),
+        ];
+        let snapshot = form(vec![
+            field("me-email", "email", "Email", "Personal details"),
+            field("boss-email", "email", "Email", "Employer contact"),
+        ]);
+        let plan = generate_fill_plan(&snapshot, &vault, None);
+        assert_eq!(rec(&plan, "me-email").vault_key, "email");
+        let employer = rec(&plan, "boss-email");
+        assert_eq!(employer.vault_key, "workEmail");
+        assert!(employer.confidence >= REVIEW_THRESHOLD);
+        assert!(!employer.reason.contains("check it"));
+    }
+
+    #[test]
+    fn test_autocomplete_sections_group_fields() {
+        let vault = vec![
+            item("zip", VaultCategory::Address),
+            item("shippingZip", VaultCategory::Address),
+        ];
+        let mut ship = field("a", "text", "Postcode", "");
+        ship.section_heading = None;
+        ship.autocomplete = Some("shipping postal-code".to_string());
+        let mut bill = ship.clone();
+        bill.id = "b".to_string();
+        bill.autocomplete = Some("billing postal-code".to_string());
+
+        let plan = generate_fill_plan(&form(vec![ship, bill]), &vault, None);
+        assert_eq!(rec(&plan, "a").vault_key, "shippingZip");
+        let billing = rec(&plan, "b");
+        assert_eq!(billing.vault_key, "zip");
+        // Different keys now, so nothing left to review
+        assert!(!billing.reason.contains("check it"));
+    }
+
+    #[test]
+    fn test_repeats_within_one_section_are_left_alone() {
+        let vault = vec![item("email", VaultCategory::Contact)];
+        let snapshot = form(vec![
+            field("email", "email", "Email", "Account"),
+            field("confirm", "email", "Confirm email", "Account"),
+        ]);
+        let plan = generate_fill_plan(&snapshot, &vault, None);
+        assert_eq!(rec(&plan, "confirm").vault_key, "email");
+        assert_eq!(rec(&plan, "confirm").confidence, 0.90);
+        assert!(!rec(&plan, "confirm").reason.contains("check it"));
+    }
+}
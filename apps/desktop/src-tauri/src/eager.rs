@@ -0,0 +1,415 @@
+/*!
+ * Eager Analysis
+ *
+ * With eager analysis on, a snapshot arriving at the bridge starts plan
+ * generation in the background, before the user opens the desktop window.
+ * The local plan is kept keyed to the snapshot's form fingerprint, and the
+ * fields it leaves open are sent for LLM analysis so their answers are
+ * already in the analysis cache. `generate_fill_plan` then serves the kept
+ * plan instead of matching again, and `llm_analyze_snapshot` answers from
+ * the cache.
+ *
+ * Only domains the user has filled before or saved a template for are
+ * analyzed, never snapshots from a private (incognito) window, and nothing
+ * starts while the vault is locked or the LLM spend budget is used up. The
+ * setting is off by default and lives in memory.
+ *
+ * Background work gives way to the user: a newer snapshot supersedes the
+ * running job, and an explicit plan or analysis request cancels it. LLM
+ * answers it already got stay cached; its plan is dropped.
+ */
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use chrono::{DateTime, Utc};
+
+use crate::llm::{AnalysisSession, AnalysisSessions, CancellationToken, SessionKey};
+use crate::matching::FillPlanJson;
+use crate::FormSnapshotJson;
+
+/// Request header the extension sets on snapshots from a private window
+pub const INCOGNITO_HEADER: &str = "X-Asterisk-Incognito";
+
+/// Plans kept before the oldest are dropped
+const PLAN_CAPACITY: usize = 8;
+
+/// Called by the bridge with each stored snapshot and whether it came from
+/// a private window
+pub type SnapshotHook = Arc<dyn Fn(&FormSnapshotJson, bool) + Send + Sync>;
+
+/// Why a snapshot wasn't analyzed eagerly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EagerSkip {
+    Disabled,
+    Incognito,
+    VaultLocked,
+    OverBudget,
+    /// No fill history or saved template for the domain
+    UnknownDomain,
+}
+
+/// What eligibility is decided from
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EagerInputs {
+    pub enabled: bool,
+    pub incognito: bool,
+    pub vault_locked: bool,
+    pub over_budget: bool,
+    pub has_fill_history: bool,
+    pub has_template: bool,
+}
+
+/// Whether a snapshot may be analyzed in the background
+pub fn eligibility(inputs: &EagerInputs) -> Result<(), EagerSkip> {
+    if !inputs.enabled {
+        Err(EagerSkip::Disabled)
+    } else if inputs.incognito {
+        Err(EagerSkip::Incognito)
+    } else if inputs.vault_locked {
+        Err(EagerSkip::VaultLocked)
+    } else if inputs.over_budget {
+        Err(EagerSkip::OverBudget)
+    } else if !inputs.has_fill_history && !inputs.has_template {
+        Err(EagerSkip::UnknownDomain)
+    } else {
+        Ok(())
+    }
+}
+
+/// Eager analysis setting and counters, for the settings view
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EagerStatusJson {
+    pub enabled: bool,
+    /// Background jobs started
+    pub started: u64,
+    /// Jobs whose plan was kept
+    pub completed: u64,
+    /// Jobs superseded by a newer snapshot or a user request
+    pub cancelled: u64,
+    /// Snapshots not analyzed, because of an `EagerSkip`
+    pub skipped: u64,
+    /// Plan requests served from a kept plan
+    pub hits: u64,
+    /// Plans kept right now
+    pub plans: usize,
+}
+
+/// A plan generated in the background
+#[derive(Debug, Clone)]
+struct KeptPlan {
+    domain: String,
+    fingerprint: String,
+    /// Vault revision the plan was matched against
+    vault_revision: u64,
+    plan: FillPlanJson,
+}
+
+#[derive(Debug, Default)]
+struct EagerState {
+    /// Session id of the running job and what cancels it
+    running: Option<(u64, CancellationToken)>,
+    plans: VecDeque<KeptPlan>,
+    status: EagerStatusJson,
+}
+
+/// Background plan generation and the plans it kept
+#[derive(Debug, Default)]
+pub struct EagerAnalysis {
+    enabled: AtomicBool,
+    /// One key for every job, so each supersedes the one before
+    sessions: AnalysisSessions,
+    state: Mutex<EagerState>,
+}
+
+impl EagerAnalysis {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, EagerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn session_key() -> SessionKey {
+        SessionKey {
+            domain: String::new(),
+            tab_id: Some("eager".to_string()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Turn eager analysis on or off; turning it off cancels the running
+    /// job and drops the kept plans
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+        if !enabled {
+            self.cancel();
+            self.invalidate();
+        }
+    }
+
+    pub fn status(&self) -> EagerStatusJson {
+        let state = self.state();
+        EagerStatusJson {
+            enabled: self.is_enabled(),
+            plans: state.plans.len(),
+            ..state.status.clone()
+        }
+    }
+
+    /// Count a snapshot that wasn't analyzed
+    pub fn skipped(&self, reason: EagerSkip) {
+        println!("[Eager] Skipped snapshot: {:?}", reason);
+        self.state().status.skipped += 1;
+    }
+
+    /// Start a job, superseding the running one
+    pub fn begin(&self, now: DateTime<Utc>) -> AnalysisSession {
+        let session = self.sessions.begin(Self::session_key(), now);
+        let mut state = self.state();
+        if state
+            .running
+            .replace((session.id(), session.token().clone()))
+            .is_some()
+        {
+            state.status.cancelled += 1;
+        }
+        state.status.started += 1;
+        session
+    }
+
+    /// Cancel the running job so an explicit request goes first
+    pub fn cancel(&self) {
+        let mut state = self.state();
+        if let Some((_, token)) = state.running.take() {
+            token.cancel();
+            state.status.cancelled += 1;
+        }
+    }
+
+    /// End `session`, keeping its plan if nothing superseded or cancelled
+    /// it; returns whether the plan was kept
+    pub fn finish(
+        &self,
+        session: &AnalysisSession,
+        snapshot: &FormSnapshotJson,
+        vault_revision: u64,
+        plan: FillPlanJson,
+    ) -> bool {
+        if !self.sessions.finish(session) || session.is_superseded() {
+            return false;
+        }
+        let mut state = self.state();
+        state.running = None;
+        state.plans.retain(|kept| {
+            kept.domain != snapshot.domain || kept.fingerprint != snapshot.fingerprint.hash
+        });
+        if state.plans.len() >= PLAN_CAPACITY {
+            state.plans.pop_front();
+        }
+        state.plans.push_back(KeptPlan {
+            domain: snapshot.domain.clone(),
+            fingerprint: snapshot.fingerprint.hash.clone(),
+            vault_revision,
+            plan,
+        });
+        state.status.completed += 1;
+        true
+    }
+
+    /// End `session` without a plan, e.g. after it failed
+    pub fn abandon(&self, session: &AnalysisSession) {
+        self.sessions.finish(session);
+        let mut state = self.state();
+        if state
+            .running
+            .as_ref()
+            .is_some_and(|(id, _)| *id == session.id())
+        {
+            state.running = None;
+        }
+    }
+
+    /// The kept plan for `snapshot`'s form, if it was matched against this
+    /// vault revision
+    pub fn lookup(&self, snapshot: &FormSnapshotJson, vault_revision: u64) -> Option<FillPlanJson> {
+        let mut state = self.state();
+        let plan = state
+            .plans
+            .iter()
+            .find(|kept| {
+                kept.domain == snapshot.domain
+                    && kept.fingerprint == snapshot.fingerprint.hash
+                    && kept.vault_revision == vault_revision
+            })
+            .map(|kept| kept.plan.clone())?;
+        state.status.hits += 1;
+        Some(plan)
+    }
+
+    /// Drop the kept plans, e.g. after templates or overrides changed
+    pub fn invalidate(&self) {
+        self.state().plans.clear();
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(domain: &str, fingerprint: &str) -> FormSnapshotJson {
+        FormSnapshotJson {
+            url: format!("https://{}/signup", domain),
+            domain: domain.to_string(),
+            title: String::new(),
+            captured_at: Utc::now().to_rfc3339(),
+            fingerprint: crate::FormFingerprintJson {
+                field_count: 0,
+                field_types: vec![],
+                required_count: 0,
+                hash: fingerprint.to_string(),
+            },
+            fields: vec![],
+            session: Default::default(),
+        }
+    }
+
+    fn plan(fingerprint: &str) -> FillPlanJson {
+        FillPlanJson {
+            plan_id: None,
+            plan_hash: None,
+            form_fingerprint: fingerprint.to_string(),
+            form_id: String::new(),
+            recommendations: vec![],
+            unmatched_fields: vec![],
+            overall_confidence: 0.0,
+            generated_at: Utc::now().to_rfc3339(),
+            required_fields_covered: 0,
+            total_required_fields: 0,
+            warnings: vec![],
+        }
+    }
+
+    #[test]
+    fn test_eligibility_rules() {
+        let known = EagerInputs {
+            enabled: true,
+            has_fill_history: true,
+            ..Default::default()
+        };
+        assert_eq!(eligibility(&known), Ok(()));
+        let templated = EagerInputs {
+            enabled: true,
+            has_template: true,
+            ..Default::default()
+        };
+        assert_eq!(eligibility(&templated), Ok(()));
+
+        for (inputs, skip) in [
+            (
+                EagerInputs {
+                    enabled: false,
+                    ..known
+                },
+                EagerSkip::Disabled,
+            ),
+            (
+                EagerInputs {
+                    incognito: true,
+                    ..known
+                },
+                EagerSkip::Incognito,
+            ),
+            (
+                EagerInputs {
+                    vault_locked: true,
+                    ..known
+                },
+                EagerSkip::VaultLocked,
+            ),
+            (
+                EagerInputs {
+                    over_budget: true,
+                    ..templated
+                },
+                EagerSkip::OverBudget,
+            ),
+            (
+                EagerInputs {
+                    enabled: true,
+                    ..Default::default()
+                },
+                EagerSkip::UnknownDomain,
+            ),
+        ] {
+            assert_eq!(eligibility(&inputs), Err(skip));
+        }
+    }
+
+    #[test]
+    fn test_kept_plan_served_for_same_form_and_vault() {
+        let eager = EagerAnalysis::new();
+        eager.set_enabled(true);
+        let page = snapshot("example.com", "fp1");
+
+        let session = eager.begin(Utc::now());
+        assert!(eager.finish(&session, &page, 3, plan("fp1")));
+        let hit = eager.lookup(&page, 3).unwrap();
+        assert_eq!(hit.form_fingerprint, "fp1");
+
+        // Another form, or a vault that changed since, misses
+        assert!(eager.lookup(&snapshot("example.com", "fp2"), 3).is_none());
+        assert!(eager.lookup(&snapshot("other.com", "fp1"), 3).is_none());
+        assert!(eager.lookup(&page, 4).is_none());
+
+        eager.invalidate();
+        assert!(eager.lookup(&page, 3).is_none());
+        let status = eager.status();
+        assert_eq!((status.started, status.completed, status.hits), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_superseding_snapshot_cancels_job() {
+        let eager = EagerAnalysis::new();
+        let first = eager.begin(Utc::now());
+        let second = eager.begin(Utc::now());
+        assert!(first.is_superseded());
+        assert!(!eager.finish(&first, &snapshot("a.com", "fp1"), 0, plan("fp1")));
+        assert!(eager.lookup(&snapshot("a.com", "fp1"), 0).is_none());
+
+        assert!(eager.finish(&second, &snapshot("a.com", "fp2"), 0, plan("fp2")));
+        assert!(eager.lookup(&snapshot("a.com", "fp2"), 0).is_some());
+        assert_eq!(eager.status().cancelled, 1);
+    }
+
+    #[test]
+    fn test_user_request_cancels_job() {
+        let eager = EagerAnalysis::new();
+        let job = eager.begin(Utc::now());
+        eager.cancel();
+        assert!(job.is_superseded());
+        assert!(!eager.finish(&job, &snapshot("a.com", "fp1"), 0, plan("fp1")));
+        assert!(eager.lookup(&snapshot("a.com", "fp1"), 0).is_none());
+
+        // Nothing running: cancelling again is a no-op
+        eager.cancel();
+        assert_eq!(eager.status().cancelled, 1);
+
+        // A failed job leaves nothing running for the next to cancel
+        let failed = eager.begin(Utc::now());
+        eager.abandon(&failed);
+        eager.begin(Utc::now());
+        assert_eq!(eager.status().cancelled, 1);
+    }
+}
@@ -0,0 +1,444 @@
+/*!
+ * Signed Audit Evidence Bundles
+ *
+ * An evidence bundle proves what was filled and when, to someone who only
+ * trusts a public key. It is a zip of:
+ *
+ * - `entries.jsonl`: the exported audit entries, one per line, in log order
+ * - `chain.json`: a SHA-256 hash chain over those lines, each link hashing
+ *   the previous link and the line, so any edit, removal or reordering
+ *   breaks every link after it
+ * - `manifest.json`: app version, export time, date range, entry count and
+ *   the chain head
+ * - `manifest.sig`: an Ed25519 signature over `manifest.json`
+ * - `public-key.txt`: the key to verify with
+ *
+ * The audit log itself isn't chained, so the chain covers the exported
+ * selection: it shows the bundle is intact, not that the log had no other
+ * entries. The signing key is generated on first use and kept in its own
+ * file, like the recall key. Keys and signatures are base64, hashes hex.
+ */
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, NaiveDate, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::audit::DetailCoverageJson;
+use crate::AuditEntryJson;
+
+/// Current bundle format
+pub const BUNDLE_FORMAT: u32 = 1;
+
+/// Link before the first entry
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+const ENTRIES_FILE: &str = "entries.jsonl";
+const CHAIN_FILE: &str = "chain.json";
+const MANIFEST_FILE: &str = "manifest.json";
+const SIGNATURE_FILE: &str = "manifest.sig";
+const PUBLIC_KEY_FILE: &str = "public-key.txt";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// What a bundle contains; this is what gets signed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EvidenceManifestJson {
+    pub format: u32,
+    #[serde(rename = "appVersion")]
+    pub app_version: String,
+    #[serde(rename = "exportedAt")]
+    pub exported_at: String,
+    /// First day of the exported range (YYYY-MM-DD, UTC)
+    pub from: String,
+    /// Last day of the exported range, inclusive
+    pub to: String,
+    #[serde(rename = "entryCount")]
+    pub entry_count: usize,
+    /// Entries at each audit detail level; only full ones list fields
+    #[serde(default)]
+    pub coverage: DetailCoverageJson,
+    /// Hash of the last chain link (the genesis link for no entries)
+    #[serde(rename = "chainHead")]
+    pub chain_head: String,
+    #[serde(rename = "publicKey")]
+    pub public_key: String,
+}
+
+/// One link of the hash chain
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChainLinkJson {
+    /// Id of the entry this link covers
+    pub id: String,
+    pub hash: String,
+}
+
+// ============================================================================
+// Keys
+// ============================================================================
+
+/// Read the signing key, generating and saving one on first use
+pub fn load_or_create_key(key_path: &Path) -> Result<SigningKey, String> {
+    match fs::read(key_path) {
+        Ok(bytes) => {
+            let seed: [u8; 32] = bytes
+                .try_into()
+                .map_err(|_| "Evidence key file is corrupt".to_string())?;
+            return Ok(SigningKey::from_bytes(&seed));
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to read evidence key: {}", e)),
+    }
+
+    if let Some(parent) = key_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create key directory: {}", e))?;
+    }
+    let mut seed = [0u8; 32];
+    OsRng.fill_bytes(&mut seed);
+    crate::recall::write_private(key_path, &seed)
+        .map_err(|e| format!("Failed to write evidence key: {}", e))?;
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+/// The public half of `key`, as shipped in bundles
+pub fn public_key(key: &SigningKey) -> String {
+    BASE64.encode(key.verifying_key().as_bytes())
+}
+
+fn parse_public_key(encoded: &str) -> Result<VerifyingKey, String> {
+    let bytes: [u8; 32] = BASE64
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| "Invalid public key".to_string())?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| "Invalid public key".to_string())
+}
+
+// ============================================================================
+// Export
+// ============================================================================
+
+/// Entries created on a UTC day from `from` to `to` inclusive, in log order
+///
+/// Entries with an unparsable time are left out.
+pub fn entries_in_range(
+    entries: Vec<AuditEntryJson>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<AuditEntryJson> {
+    entries
+        .into_iter()
+        .filter(|entry| {
+            DateTime::parse_from_rfc3339(&entry.created_at)
+                .map(|at| (from..=to).contains(&at.with_timezone(&Utc).date_naive()))
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+fn link(previous: &str, line: &str) -> String {
+    Sha256::new()
+        .chain_update(previous.as_bytes())
+        .chain_update(line.as_bytes())
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Write a signed bundle of `entries` to `writer`; returns its manifest
+pub fn write_bundle(
+    writer: impl Write + Seek,
+    entries: &[AuditEntryJson],
+    (from, to): (NaiveDate, NaiveDate),
+    key: &SigningKey,
+    now: DateTime<Utc>,
+) -> Result<EvidenceManifestJson, String> {
+    let mut lines = String::new();
+    let mut chain = Vec::with_capacity(entries.len());
+    let mut head = GENESIS.to_string();
+    for entry in entries {
+        let line = serde_json::to_string(entry)
+            .map_err(|e| format!("Failed to serialize entry: {}", e))?;
+        head = link(&head, &line);
+        chain.push(ChainLinkJson {
+            id: entry.id.clone(),
+            hash: head.clone(),
+        });
+        lines.push_str(&line);
+        lines.push('\n');
+    }
+
+    let manifest = EvidenceManifestJson {
+        format: BUNDLE_FORMAT,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: now.to_rfc3339(),
+        from: from.to_string(),
+        to: to.to_string(),
+        entry_count: entries.len(),
+        coverage: DetailCoverageJson::of(entries),
+        chain_head: head,
+        public_key: public_key(key),
+    };
+    let manifest_bytes = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    let signature = BASE64.encode(key.sign(&manifest_bytes).to_bytes());
+    let chain_bytes = serde_json::to_vec_pretty(&chain)
+        .map_err(|e| format!("Failed to serialize chain: {}", e))?;
+
+    let mut zip = ZipWriter::new(writer);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let files: [(&str, &[u8]); 5] = [
+        (ENTRIES_FILE, lines.as_bytes()),
+        (CHAIN_FILE, &chain_bytes),
+        (MANIFEST_FILE, &manifest_bytes),
+        (SIGNATURE_FILE, signature.as_bytes()),
+        (PUBLIC_KEY_FILE, manifest.public_key.as_bytes()),
+    ];
+    for (name, bytes) in files {
+        zip.start_file(name, options)
+            .and_then(|_| zip.write_all(bytes).map_err(Into::into))
+            .map_err(|e| format!("Failed to write {}: {}", name, e))?;
+    }
+    zip.finish()
+        .map_err(|e| format!("Failed to write bundle: {}", e))?;
+    Ok(manifest)
+}
+
+// ============================================================================
+// Verification
+// ============================================================================
+
+fn read_file(archive: &mut ZipArchive<impl Read + Seek>, name: &str) -> Result<Vec<u8>, String> {
+    let mut file = archive
+        .by_name(name)
+        .map_err(|_| format!("Bundle has no {}", name))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+    Ok(bytes)
+}
+
+/// Check a bundle's signature against `public_key` and its hash chain
+/// against its entries; returns the verified manifest
+pub fn verify_bundle(
+    reader: impl Read + Seek,
+    public_key: &str,
+) -> Result<EvidenceManifestJson, String> {
+    let key = parse_public_key(public_key)?;
+    let mut archive = ZipArchive::new(reader).map_err(|e| format!("Not a bundle: {}", e))?;
+
+    let manifest_bytes = read_file(&mut archive, MANIFEST_FILE)?;
+    let signature: [u8; 64] = BASE64
+        .decode(read_file(&mut archive, SIGNATURE_FILE)?.trim_ascii())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| "Malformed manifest signature".to_string())?;
+    key.verify_strict(&manifest_bytes, &Signature::from_bytes(&signature))
+        .map_err(|_| "Manifest signature does not match the public key".to_string())?;
+
+    let manifest: EvidenceManifestJson =
+        serde_json::from_slice(&manifest_bytes).map_err(|e| format!("Invalid manifest: {}", e))?;
+    if manifest.format != BUNDLE_FORMAT {
+        return Err(format!("Unsupported bundle format {}", manifest.format));
+    }
+    let chain: Vec<ChainLinkJson> = serde_json::from_slice(&read_file(&mut archive, CHAIN_FILE)?)
+        .map_err(|e| format!("Invalid chain: {}", e))?;
+    let entries = String::from_utf8(read_file(&mut archive, ENTRIES_FILE)?)
+        .map_err(|_| "Entries are not UTF-8".to_string())?;
+
+    let lines: Vec<&str> = entries.lines().filter(|l| !l.trim().is_empty()).collect();
+    if lines.len() != manifest.entry_count || chain.len() != manifest.entry_count {
+        return Err(format!(
+            "Manifest lists {} entries but the bundle has {} entries and {} chain links",
+            manifest.entry_count,
+            lines.len(),
+            chain.len()
+        ));
+    }
+    let mut head = GENESIS.to_string();
+    for (index, (line, expected)) in lines.iter().zip(&chain).enumerate() {
+        head = link(&head, line);
+        let id = serde_json::from_str::<AuditEntryJson>(line)
+            .map(|entry| entry.id)
+            .map_err(|e| format!("Entry {} is not an audit entry: {}", index + 1, e))?;
+        if head != expected.hash || id != expected.id {
+            return Err(format!(
+                "Entry {} ({}) breaks the hash chain",
+                index + 1,
+                id
+            ));
+        }
+    }
+    if head != manifest.chain_head {
+        return Err("Hash chain does not end at the signed chain head".to_string());
+    }
+    Ok(manifest)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AuditSummaryJson;
+    use std::io::Cursor;
+
+    fn entry(id: &str, created_at: &str) -> AuditEntryJson {
+        AuditEntryJson {
+            id: id.to_string(),
+            created_at: created_at.to_string(),
+            url: "https://vendor.example.com/expenses".to_string(),
+            domain: "vendor.example.com".to_string(),
+            fingerprint: "fp".to_string(),
+            summary: AuditSummaryJson {
+                planned_count: 2,
+                applied_count: 2,
+                blocked_count: 0,
+                reviewed_count: 0,
+            },
+            items: vec![],
+            recall_recorded: false,
+            fill_command_id: None,
+            acceptance_id: None,
+            acceptance_hash: None,
+            plan_hash: None,
+            detail: Default::default(),
+        }
+    }
+
+    fn day(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn bundle(key: &SigningKey) -> Vec<u8> {
+        let entries = entries_in_range(
+            vec![
+                entry("a", "2026-03-01T09:00:00Z"),
+                entry("b", "2026-03-02T10:00:00Z"),
+                entry("c", "2026-03-03T11:00:00Z"),
+                entry("late", "2026-04-01T00:00:00Z"),
+            ],
+            day("2026-03-01"),
+            day("2026-03-31"),
+        );
+        let mut out = Cursor::new(Vec::new());
+        write_bundle(
+            &mut out,
+            &entries,
+            (day("2026-03-01"), day("2026-03-31")),
+            key,
+            "2026-04-02T00:00:00Z".parse().unwrap(),
+        )
+        .unwrap();
+        out.into_inner()
+    }
+
+    /// Copy of `bundle` with one file's content changed
+    fn rewrite(bundle: &[u8], name: &str, change: impl Fn(String) -> String) -> Vec<u8> {
+        let mut archive = ZipArchive::new(Cursor::new(bundle)).unwrap();
+        let mut out = ZipWriter::new(Cursor::new(Vec::new()));
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index).unwrap();
+            let file_name = file.name().to_string();
+            let mut content = String::new();
+            file.read_to_string(&mut content).unwrap();
+            if file_name == name {
+                content = change(content);
+            }
+            out.start_file(file_name, SimpleFileOptions::default())
+                .unwrap();
+            out.write_all(content.as_bytes()).unwrap();
+        }
+        out.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let signer = key(1);
+        let manifest = verify_bundle(Cursor::new(bundle(&signer)), &public_key(&signer)).unwrap();
+        assert_eq!(manifest.entry_count, 3);
+        assert_eq!(manifest.from, "2026-03-01");
+        assert_eq!(manifest.exported_at, "2026-04-02T00:00:00+00:00");
+        assert_eq!(manifest.public_key, public_key(&signer));
+    }
+
+    #[test]
+    fn test_tampered_entry_breaks_the_chain() {
+        let signer = key(1);
+        let tampered = rewrite(&bundle(&signer), ENTRIES_FILE, |entries| {
+            entries.replacen("\"appliedCount\":2", "\"appliedCount\":9", 1)
+        });
+        let error = verify_bundle(Cursor::new(tampered), &public_key(&signer)).unwrap_err();
+        assert_eq!(error, "Entry 1 (a) breaks the hash chain");
+
+        // Dropping an entry no longer matches the signed count
+        let original = bundle(&signer);
+        let dropped = rewrite(&original, ENTRIES_FILE, |entries| {
+            entries
+                .lines()
+                .skip(1)
+                .map(|l| format!("{}\n", l))
+                .collect()
+        });
+        assert!(verify_bundle(Cursor::new(dropped), &public_key(&signer))
+            .unwrap_err()
+            .contains("Manifest lists 3 entries"));
+    }
+
+    #[test]
+    fn test_tampered_manifest_fails_signature() {
+        let signer = key(1);
+        let tampered = rewrite(&bundle(&signer), MANIFEST_FILE, |manifest| {
+            manifest.replace("\"entryCount\": 3", "\"entryCount\": 2")
+        });
+        assert_eq!(
+            verify_bundle(Cursor::new(tampered), &public_key(&signer)).unwrap_err(),
+            "Manifest signature does not match the public key"
+        );
+    }
+
+    #[test]
+    fn test_wrong_key_is_rejected() {
+        let bundle = bundle(&key(1));
+        assert_eq!(
+            verify_bundle(Cursor::new(&bundle), &public_key(&key(2))).unwrap_err(),
+            "Manifest signature does not match the public key"
+        );
+        assert_eq!(
+            verify_bundle(Cursor::new(&bundle), "not a key").unwrap_err(),
+            "Invalid public key"
+        );
+    }
+
+    #[test]
+    fn test_key_is_created_once() {
+        let dir = std::env::temp_dir().join(format!("asterisk-evidence-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("evidence.key");
+
+        let first = load_or_create_key(&path).unwrap();
+        let second = load_or_create_key(&path).unwrap();
+        assert_eq!(public_key(&first), public_key(&second));
+
+        fs::write(&path, b"short").unwrap();
+        assert!(load_or_create_key(&path).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,253 @@
+/*!
+ * Match Explanations
+ *
+ * Every field in a plan gets one plain-language sentence saying why it was
+ * (or wasn't) matched, whichever tier decided it. Local tiers already put
+ * that sentence in the recommendation's `reason` ("Matched by
+ * autocomplete=email", `The name attribute "phone" contains "phone"`); LLM
+ * answers carry the model's own `reasoning`, which is shown as the model's
+ * and not as a rule Asterisk applied. `explain_plan` merges both into one
+ * list in form order, so the UI has a single place to read rationale from.
+ */
+
+use serde::{Deserialize, Serialize};
+
+use crate::llm::{FieldAnalysisJson, FieldAnalysisStatus};
+use crate::matching::{FillPlanJson, MatchTier};
+use crate::FormSnapshotJson;
+
+/// Who decided a field's match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExplanationSource {
+    /// A local rule, override, template or earlier step
+    Local,
+    /// The LLM's own reasoning
+    Llm,
+}
+
+/// Why one field was matched, or why it wasn't
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MatchExplanationJson {
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    /// Absent when the field is unmatched
+    #[serde(rename = "vaultKey", skip_serializing_if = "Option::is_none")]
+    pub vault_key: Option<String>,
+    pub confidence: f64,
+    #[serde(rename = "matchTier", skip_serializing_if = "Option::is_none")]
+    pub match_tier: Option<MatchTier>,
+    pub source: ExplanationSource,
+    pub explanation: String,
+}
+
+/// Explain every fillable field of `snapshot`, in form order
+///
+/// Locally matched fields are explained by `plan`; fields it left unmatched
+/// by their entry in `analyses`, if the LLM looked at them.
+pub fn explain_plan(
+    snapshot: &FormSnapshotJson,
+    plan: &FillPlanJson,
+    analyses: &[FieldAnalysisJson],
+) -> Vec<MatchExplanationJson> {
+    snapshot
+        .fields
+        .iter()
+        .filter_map(|field| {
+            if let Some(rec) = plan.recommendations.iter().find(|r| r.field_id == field.id) {
+                return Some(MatchExplanationJson {
+                    field_id: field.id.clone(),
+                    vault_key: Some(rec.vault_key.clone()),
+                    confidence: rec.confidence,
+                    match_tier: Some(rec.match_tier),
+                    source: ExplanationSource::Local,
+                    explanation: rec.reason.clone(),
+                });
+            }
+            if !plan.unmatched_fields.contains(&field.id) {
+                // Not fillable (passwords, checkboxes)
+                return None;
+            }
+            let analysis = analyses.iter().find(|a| a.field_id == field.id);
+            Some(match analysis {
+                Some(analysis) => explain_analysis(analysis),
+                None => MatchExplanationJson {
+                    field_id: field.id.clone(),
+                    vault_key: None,
+                    confidence: 0.0,
+                    match_tier: None,
+                    source: ExplanationSource::Local,
+                    explanation: "No local rule recognised this field".to_string(),
+                },
+            })
+        })
+        .collect()
+}
+
+/// Explain one LLM analysis outcome
+fn explain_analysis(analysis: &FieldAnalysisJson) -> MatchExplanationJson {
+    let mut json = MatchExplanationJson {
+        field_id: analysis.field_id.clone(),
+        vault_key: None,
+        confidence: 0.0,
+        match_tier: None,
+        source: ExplanationSource::Llm,
+        explanation: String::new(),
+    };
+    match (analysis.status, &analysis.result) {
+        (FieldAnalysisStatus::Analyzed, Some(result)) => {
+            let reasoning = result.reasoning.trim();
+            json.vault_key = result.vault_key.clone();
+            json.confidence = result.confidence;
+            json.match_tier = result.vault_key.is_some().then_some(MatchTier::Llm);
+            json.explanation = match (&result.vault_key, reasoning.is_empty()) {
+                (Some(_), true) => "The AI matched this field without giving a reason".to_string(),
+                (None, true) => "The AI found no matching vault item".to_string(),
+                (_, false) => format!("AI: {}", reasoning),
+            };
+        }
+        (FieldAnalysisStatus::SkippedByUser, _) => {
+            json.source = ExplanationSource::Local;
+            json.explanation = "Not sent for AI analysis; you deselected it".to_string();
+        }
+        (FieldAnalysisStatus::DeadlineExceeded, _) => {
+            json.explanation = "The AI didn't answer before the deadline".to_string();
+        }
+        (FieldAnalysisStatus::BudgetExceeded, _) => {
+            json.source = ExplanationSource::Local;
+            json.explanation = "Not sent for AI analysis; the AI budget is used up".to_string();
+        }
+        _ => {
+            json.explanation = format!(
+                "AI analysis failed: {}",
+                analysis.error.as_deref().unwrap_or("no answer")
+            );
+        }
+    }
+    json
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::AnalyzeFieldResponse;
+    use crate::matching::generate_fill_plan;
+    use crate::{FieldNodeJson, FormFingerprintJson};
+    use asterisk_vault::{test_util, VaultCategory, VaultItem};
+    use chrono::Utc;
+
+    fn field(id: &str, label: &str, field_type: &str) -> FieldNodeJson {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": id,
+            "label": label,
+            "type": field_type,
+            "semantic": "",
+            "required": false,
+        }))
+        .unwrap()
+    }
+
+    fn item(key: &str, category: VaultCategory) -> VaultItem {
+        test_util::item(key, "value", category)
+    }
+
+    fn snapshot(fields: Vec<FieldNodeJson>) -> FormSnapshotJson {
+        FormSnapshotJson {
+            url: "https://example.com/form".to_string(),
+            domain: "example.com".to_string(),
+            title: "Form".to_string(),
+            captured_at: Utc::now().to_rfc3339(),
+            fingerprint: FormFingerprintJson {
+                field_count: fields.len() as u32,
+                field_types: vec![],
+                required_count: 0,
+                hash: "h".to_string(),
+            },
+            fields,
+            session: Default::default(),
+        }
+    }
+
+    fn analysis(field_id: &str, result: Option<(&str, &str)>) -> FieldAnalysisJson {
+        FieldAnalysisJson {
+            field_id: field_id.to_string(),
+            status: FieldAnalysisStatus::Analyzed,
+            result: result.map(|(key, reasoning)| AnalyzeFieldResponse {
+                vault_key: (!key.is_empty()).then(|| key.to_string()),
+                confidence: 0.8,
+                reasoning: reasoning.to_string(),
+                provider: None,
+            }),
+            error: None,
+            cached: false,
+            cache_source: None,
+            revalidating: false,
+        }
+    }
+
+    #[test]
+    fn test_local_and_llm_explanations_merge_in_form_order() {
+        let items = vec![
+            item("email", VaultCategory::Contact),
+            item("employer", VaultCategory::Custom),
+        ];
+        let form = snapshot(vec![
+            field("work", "Where do you work?", "text"),
+            field("mail", "Email address", "email"),
+            field("pw", "Password", "password"),
+            field("pet", "Pet's name", "text"),
+            field("motto", "Motto", "text"),
+        ]);
+        let plan = generate_fill_plan(&form, &items, None);
+        let mut skipped = analysis("motto", None);
+        skipped.status = FieldAnalysisStatus::SkippedByUser;
+        let analyses = vec![
+            analysis("work", Some(("employer", "Asks for the employer"))),
+            analysis("pet", Some(("", ""))),
+            skipped,
+        ];
+
+        let explained = explain_plan(&form, &plan, &analyses);
+        let ids: Vec<&str> = explained.iter().map(|e| e.field_id.as_str()).collect();
+        assert_eq!(ids, vec!["work", "mail", "pet", "motto"]);
+
+        assert_eq!(explained[0].source, ExplanationSource::Llm);
+        assert_eq!(explained[0].explanation, "AI: Asks for the employer");
+        assert_eq!(explained[0].match_tier, Some(MatchTier::Llm));
+
+        assert_eq!(explained[1].source, ExplanationSource::Local);
+        assert_eq!(
+            explained[1].explanation,
+            "The label \"Email address\" contains \"email\""
+        );
+
+        assert_eq!(explained[2].vault_key, None);
+        assert_eq!(
+            explained[2].explanation,
+            "The AI found no matching vault item"
+        );
+        assert!(explained[3].explanation.contains("deselected"));
+    }
+
+    #[test]
+    fn test_unanalyzed_and_failed_fields_are_explained() {
+        let form = snapshot(vec![field("a", "Motto", "text"), field("b", "Pet", "text")]);
+        let plan = generate_fill_plan(&form, &[], None);
+        let mut failed = analysis("b", None);
+        failed.status = FieldAnalysisStatus::Failed;
+        failed.error = Some("timeout".to_string());
+
+        let explained = explain_plan(&form, &plan, &[failed]);
+        assert_eq!(
+            explained[0].explanation,
+            "No local rule recognised this field"
+        );
+        assert_eq!(explained[1].explanation, "AI analysis failed: timeout");
+        assert_eq!(explained[1].source, ExplanationSource::Llm);
+    }
+}
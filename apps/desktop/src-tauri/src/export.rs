@@ -0,0 +1,719 @@
+/*!
+ * Streaming Vault Export/Import
+ *
+ * Writes the vault as JSON lines (one `VaultItemJson` per line) through a
+ * fixed-size buffer, visiting items in place via `VaultStore::for_each`, so
+ * memory stays flat however large the vault is. The importer reads the same
+ * format a line at a time.
+ *
+ * A fill profile is the vault's structure without its values — each item's
+ * key, label and category — for sharing a consistent schema across a team.
+ * Importing one creates empty items for the keys the vault lacks.
+ *
+ * The simplest import of all is a flat JSON object of key to value, with
+ * each item's category guessed from its key.
+ *
+ * Both value imports trim keys, and keys of one import that differ only by
+ * case (`Email`, `email`, `email `) are merged into a single item under the
+ * first spelling rather than becoming duplicates. Each merge is reported.
+ * They stage every item before writing any and store them with one
+ * `set_many`, so an import that fails to save leaves the vault untouched.
+ */
+
+use asterisk_vault::{
+    Provenance, ProvenanceSource, ReviewStatus, VaultCategory, VaultError, VaultItem, VaultStore,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufWriter, Write};
+
+use crate::history::VaultHistory;
+use crate::{TryFromLossy, VaultItemJson};
+
+/// Capacity of the export write buffer
+pub const EXPORT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Outcome of a streamed import
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ImportSummaryJson {
+    pub imported: usize,
+    /// Lines that could not be imported, as "line N: reason"
+    pub errors: Vec<String>,
+    /// Imported lines with fields that fell back to a default, as
+    /// "line N: reason"
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Imported items that wait in the review queue before they are
+    /// auto-filled
+    #[serde(rename = "queuedForReview", default)]
+    pub queued_for_review: usize,
+    /// Entries merged into an earlier entry whose key differs only by case
+    /// or whitespace, as "line N: ..." (or the key, for simple imports)
+    #[serde(default)]
+    pub merged: Vec<String>,
+}
+
+/// The form keys are compared in when merging import entries
+pub fn canonical_key(key: &str) -> String {
+    key.trim().to_lowercase()
+}
+
+/// Whether `incoming` should replace `kept` when their keys merge: a value
+/// beats an empty one, then the higher provenance confidence wins; on a tie
+/// the earlier entry stays
+fn prefer_incoming(kept: &VaultItem, incoming: &VaultItem) -> bool {
+    match (
+        kept.value.trim().is_empty(),
+        incoming.value.trim().is_empty(),
+    ) {
+        (true, false) => true,
+        (false, true) => false,
+        _ => incoming.provenance.confidence > kept.provenance.confidence,
+    }
+}
+
+/// Items staged by one import, indexed by canonical key
+#[derive(Default)]
+struct ImportedKeys {
+    staged: Vec<VaultItem>,
+    /// Whether each staged item's key was already in the vault
+    existed: Vec<bool>,
+    by_canonical: HashMap<String, usize>,
+}
+
+impl ImportedKeys {
+    /// Stage `item`, read from `place`, or merge it into the item an
+    /// earlier entry staged under an equivalent key
+    fn stage(
+        &mut self,
+        mut item: VaultItem,
+        place: &str,
+        store: &dyn VaultStore,
+        summary: &mut ImportSummaryJson,
+    ) {
+        let spelled = std::mem::take(&mut item.key);
+        item.key = spelled.trim().to_string();
+        if let Err(e) = asterisk_vault::check_key(&item.key) {
+            summary.errors.push(format!("{}: {}", place, e));
+            return;
+        }
+        let canonical = canonical_key(&item.key);
+
+        if let Some(&index) = self.by_canonical.get(&canonical) {
+            let kept = &mut self.staged[index];
+            let replace = prefer_incoming(kept, &item);
+            summary.merged.push(format!(
+                "{}: {:?} merged into {:?}, keeping the {} value",
+                place,
+                spelled,
+                kept.key,
+                if replace { "new" } else { "earlier" }
+            ));
+            if !replace {
+                return;
+            }
+            summary.queued_for_review -=
+                usize::from(kept.review_status == ReviewStatus::Unreviewed);
+            summary.queued_for_review +=
+                usize::from(item.review_status == ReviewStatus::Unreviewed);
+            item.key = std::mem::take(&mut kept.key);
+            *kept = item;
+            return;
+        }
+
+        summary.imported += 1;
+        if item.review_status == ReviewStatus::Unreviewed {
+            summary.queued_for_review += 1;
+        }
+        self.by_canonical.insert(canonical, self.staged.len());
+        self.existed.push(store.exists(&item.key));
+        self.staged.push(item);
+    }
+
+    /// Store every staged item in one batch, so a failed write leaves the
+    /// vault as it was, then record them in the history
+    fn commit(self, store: &mut dyn VaultStore, history: &VaultHistory) -> Result<(), String> {
+        store
+            .set_many(
+                self.staged
+                    .iter()
+                    .map(|item| (item.key.clone(), item.clone()))
+                    .collect(),
+            )
+            .map_err(|e| format!("Failed to import: {}", e))?;
+        for (item, existed) in self.staged.iter().zip(self.existed) {
+            history.record_set(item, existed, "import")?;
+        }
+        Ok(())
+    }
+}
+
+/// Write every vault item to `writer` as JSON lines; returns the item count
+pub fn export_jsonl(store: &dyn VaultStore, writer: impl Write) -> Result<usize, String> {
+    let mut writer = BufWriter::with_capacity(EXPORT_BUFFER_SIZE, writer);
+    let mut count = 0;
+
+    store
+        .for_each(&mut |item| {
+            serde_json::to_writer(&mut writer, &VaultItemJson::from(item.clone()))
+                .map_err(|e| VaultError::SerializationError(e.to_string()))?;
+            writer
+                .write_all(b"\n")
+                .map_err(|e| VaultError::StorageError(e.to_string()))?;
+            count += 1;
+            Ok(())
+        })
+        .map_err(|e| format!("Failed to export vault: {}", e))?;
+
+    writer
+        .flush()
+        .map_err(|e| format!("Failed to export vault: {}", e))?;
+    Ok(count)
+}
+
+/// Read JSON lines from `reader` into the vault
+///
+/// Bad lines are reported in the summary and skipped; IO errors and a
+/// failed save abort, with nothing written.
+/// Malformed timestamps don't skip a line: they fall back to defaults and
+/// are reported as warnings. Items not entered by the user and below the
+/// review confidence threshold are queued for review. Lines whose keys
+/// differ only by case or whitespace merge into one item.
+pub fn import_jsonl(
+    mut reader: impl BufRead,
+    store: &mut dyn VaultStore,
+    history: &VaultHistory,
+) -> Result<ImportSummaryJson, String> {
+    let mut summary = ImportSummaryJson::default();
+    let mut keys = ImportedKeys::default();
+    let mut line = String::new();
+    let mut line_number = 0;
+
+    loop {
+        line.clear();
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|e| format!("Failed to read import: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        line_number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let item = serde_json::from_str::<VaultItemJson>(&line)
+            .map_err(|e| e.to_string())
+            .and_then(VaultItem::try_from_lossy);
+        let mut item = match item {
+            Ok((item, warnings)) => {
+                summary.warnings.extend(
+                    warnings
+                        .into_iter()
+                        .map(|w| format!("line {}: {}", line_number, w)),
+                );
+                item
+            }
+            Err(e) => {
+                summary.errors.push(format!("line {}: {}", line_number, e));
+                continue;
+            }
+        };
+
+        if item.review_status == ReviewStatus::Approved {
+            item.review_status = ReviewStatus::initial(&item.provenance);
+        }
+
+        let place = format!("line {}", line_number);
+        keys.stage(item, &place, store, &mut summary);
+    }
+    keys.commit(store, history)?;
+    Ok(summary)
+}
+
+// ============================================================================
+// Fill Profiles
+// ============================================================================
+
+/// Current fill profile format
+pub const PROFILE_VERSION: u32 = 1;
+
+/// One vault item's structure, without its value
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProfileItemJson {
+    pub key: String,
+    pub label: String,
+    pub category: VaultCategory,
+}
+
+/// The shareable structure of a vault
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FillProfileJson {
+    pub version: u32,
+    pub items: Vec<ProfileItemJson>,
+}
+
+/// Keys, labels and categories of every vault item, sorted by key
+pub fn export_profile(store: &dyn VaultStore) -> Result<FillProfileJson, String> {
+    let mut items = Vec::new();
+    store
+        .for_each(&mut |item| {
+            items.push(ProfileItemJson {
+                key: item.key.clone(),
+                label: item.label.clone(),
+                category: item.category.clone(),
+            });
+            Ok(())
+        })
+        .map_err(|e| format!("Failed to export profile: {}", e))?;
+    items.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(FillProfileJson {
+        version: PROFILE_VERSION,
+        items,
+    })
+}
+
+/// Create an empty item for each profile key the vault doesn't have
+///
+/// Existing items are left untouched and reported as warnings, so a
+/// profile never overwrites a value. The rest are stored in one batch: if
+/// any of them can't be, none is.
+pub fn import_profile(
+    profile: &FillProfileJson,
+    store: &mut dyn VaultStore,
+    history: &VaultHistory,
+) -> Result<ImportSummaryJson, String> {
+    if profile.version != PROFILE_VERSION {
+        return Err(format!(
+            "Unsupported fill profile version {} (expected {})",
+            profile.version, PROFILE_VERSION
+        ));
+    }
+    let mut summary = ImportSummaryJson::default();
+    let mut items = Vec::new();
+    for entry in &profile.items {
+        if store.exists(&entry.key) {
+            summary
+                .warnings
+                .push(format!("{}: already in the vault, left as is", entry.key));
+            continue;
+        }
+        let item = VaultItem::new(
+            entry.key.clone(),
+            String::new(),
+            entry.label.clone(),
+            entry.category.clone(),
+            Provenance {
+                source: ProvenanceSource::Imported,
+                timestamp: chrono::Utc::now(),
+                confidence: 1.0,
+                origin: Some("profile".to_string()),
+            },
+        );
+        items.push(item);
+    }
+    // All or nothing, so a bad entry can't leave half a profile behind
+    store
+        .set_many(
+            items
+                .iter()
+                .map(|item| (item.key.clone(), item.clone()))
+                .collect(),
+        )
+        .map_err(|e| format!("Failed to import fill profile: {}", e))?;
+    for item in &items {
+        history.record_set(item, false, "profile")?;
+    }
+    summary.imported = items.len();
+    Ok(summary)
+}
+
+// ============================================================================
+// Simple Key-Value Import
+// ============================================================================
+
+/// The category a key name suggests; `Custom` when nothing fits
+///
+/// Matches whole words of the key, so `home_email` and `postalCode` are
+/// recognised but `username` is not a name.
+pub fn infer_category(key: &str) -> VaultCategory {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for c in key.chars() {
+        let boundary = !c.is_alphanumeric() || c.is_uppercase();
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    let has = |candidates: &[&str]| words.iter().any(|w| candidates.contains(&w.as_str()));
+
+    if has(&["email", "mail", "phone", "mobile", "tel", "telephone"]) {
+        VaultCategory::Contact
+    } else if has(&[
+        "street", "address", "city", "town", "zip", "postcode", "postal", "state", "country",
+    ]) {
+        VaultCategory::Address
+    } else if has(&["name", "firstname", "lastname", "surname", "fullname"]) {
+        VaultCategory::Identity
+    } else {
+        VaultCategory::Custom
+    }
+}
+
+/// Create an item for each entry of a flat `{"key": "value"}` map
+///
+/// Categories come from `infer_category`. Existing keys are overwritten,
+/// as with a JSON lines import, and keys differing only by case or
+/// whitespace merge; entries whose value isn't a string are reported as
+/// errors and skipped.
+pub fn import_simple(
+    map: &BTreeMap<String, serde_json::Value>,
+    store: &mut dyn VaultStore,
+    history: &VaultHistory,
+) -> Result<ImportSummaryJson, String> {
+    let mut summary = ImportSummaryJson::default();
+    let mut keys = ImportedKeys::default();
+    for (key, value) in map {
+        let Some(value) = value.as_str() else {
+            summary
+                .errors
+                .push(format!("{}: value is not a string", key));
+            continue;
+        };
+        let item = VaultItem::new(
+            key.clone(),
+            value.to_string(),
+            key.clone(),
+            infer_category(key),
+            Provenance {
+                source: ProvenanceSource::Imported,
+                timestamp: chrono::Utc::now(),
+                confidence: 1.0,
+                origin: Some("simple".to_string()),
+            },
+        );
+        keys.stage(item, key, store, &mut summary);
+    }
+    keys.commit(store, history)?;
+    Ok(summary)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asterisk_vault::{test_util, InMemoryStore};
+
+    fn item(i: usize) -> VaultItem {
+        VaultItem::new(
+            format!("key-{}", i),
+            format!("value number {}", i),
+            format!("Label {}", i),
+            VaultCategory::Custom,
+            test_util::user_entered(),
+        )
+    }
+
+    fn disabled_history() -> VaultHistory {
+        let history = VaultHistory::new(std::env::temp_dir().join("asterisk-export-unused.jsonl"));
+        history.set_enabled(false);
+        history
+    }
+
+    /// Records the largest single write it receives
+    #[derive(Default)]
+    struct PeakWriter {
+        bytes: Vec<u8>,
+        peak_write: usize,
+    }
+
+    impl Write for PeakWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.peak_write = self.peak_write.max(buf.len());
+            self.bytes.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_streamed_round_trip() {
+        let source = InMemoryStore::with_items((0..5_000).map(item).collect());
+
+        let mut out = Vec::new();
+        assert_eq!(export_jsonl(&source, &mut out).unwrap(), 5_000);
+
+        let mut target = InMemoryStore::new();
+        let summary = import_jsonl(out.as_slice(), &mut target, &disabled_history()).unwrap();
+        assert_eq!(summary.imported, 5_000);
+        assert!(summary.errors.is_empty());
+        assert_eq!(
+            target.get("key-4321").unwrap().unwrap().value,
+            "value number 4321"
+        );
+    }
+
+    #[test]
+    fn test_export_buffer_is_bounded() {
+        for count in [100, 20_000] {
+            let store = InMemoryStore::with_items((0..count).map(item).collect());
+            let mut writer = PeakWriter::default();
+            export_jsonl(&store, &mut writer).unwrap();
+
+            // Output grows with the vault, the largest buffered chunk doesn't
+            assert!(writer.peak_write <= EXPORT_BUFFER_SIZE);
+            if count == 20_000 {
+                assert!(writer.bytes.len() > 10 * EXPORT_BUFFER_SIZE);
+            }
+        }
+    }
+
+    #[test]
+    fn test_import_reports_bad_lines() {
+        let mut out = Vec::new();
+        export_jsonl(&InMemoryStore::with_items(vec![item(1)]), &mut out).unwrap();
+        out.extend_from_slice(b"{not json}\n\n");
+
+        let mut target = InMemoryStore::new();
+        let summary = import_jsonl(out.as_slice(), &mut target, &disabled_history()).unwrap();
+        assert_eq!(summary.imported, 1);
+        assert_eq!(summary.errors.len(), 1);
+        assert!(summary.errors[0].starts_with("line 2:"));
+    }
+
+    /// Takes single writes but refuses every batch
+    struct FailingBatches(InMemoryStore);
+
+    impl VaultStore for FailingBatches {
+        fn set(&mut self, key: String, item: VaultItem) -> asterisk_vault::Result<()> {
+            self.0.set(key, item)
+        }
+        fn set_many(&mut self, _: Vec<(String, VaultItem)>) -> asterisk_vault::Result<()> {
+            Err(VaultError::StorageError("disk full".to_string()))
+        }
+        fn get(&self, key: &str) -> asterisk_vault::Result<Option<VaultItem>> {
+            self.0.get(key)
+        }
+        fn list(&self) -> asterisk_vault::Result<Vec<VaultItem>> {
+            self.0.list()
+        }
+        fn delete(&mut self, key: &str) -> asterisk_vault::Result<()> {
+            self.0.delete(key)
+        }
+        fn clear(&mut self) -> asterisk_vault::Result<()> {
+            self.0.clear()
+        }
+    }
+
+    #[test]
+    fn test_failed_import_writes_nothing() {
+        let mut out = Vec::new();
+        export_jsonl(
+            &InMemoryStore::with_items((0..3).map(item).collect()),
+            &mut out,
+        )
+        .unwrap();
+        let mut target = FailingBatches(InMemoryStore::new());
+        let err = import_jsonl(out.as_slice(), &mut target, &disabled_history()).unwrap_err();
+        assert!(err.contains("disk full"), "{}", err);
+        assert!(target.list().unwrap().is_empty());
+
+        let map: BTreeMap<String, serde_json::Value> =
+            serde_json::from_str(r#"{"email": "a@example.com", "city": "Berlin"}"#).unwrap();
+        let err = import_simple(&map, &mut target, &disabled_history()).unwrap_err();
+        assert!(err.contains("disk full"), "{}", err);
+        assert!(target.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_import_reports_blank_keys_and_keeps_the_rest() {
+        let map: BTreeMap<String, serde_json::Value> =
+            serde_json::from_str(r#"{" ": "lost", "email": "a@example.com"}"#).unwrap();
+        let mut store = InMemoryStore::new();
+        let summary = import_simple(&map, &mut store, &disabled_history()).unwrap();
+        assert_eq!(summary.imported, 1);
+        assert_eq!(summary.errors.len(), 1);
+        assert!(summary.errors[0].starts_with(" : "), "{:?}", summary.errors);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_import_tolerates_bad_timestamps() {
+        let mut json = VaultItemJson::from(item(1));
+        json.metadata.created = "last tuesday".to_string();
+        let line = serde_json::to_string(&json).unwrap() + "\n";
+
+        let mut target = InMemoryStore::new();
+        let summary = import_jsonl(line.as_bytes(), &mut target, &disabled_history()).unwrap();
+        assert_eq!(summary.imported, 1);
+        assert!(summary.errors.is_empty());
+        assert_eq!(summary.warnings.len(), 1);
+        assert!(summary.warnings[0].starts_with("line 1: key-1: invalid created"));
+        assert!(target.exists("key-1"));
+    }
+
+    #[test]
+    fn test_low_confidence_imports_are_queued_for_review() {
+        let mut unsure = item(2);
+        unsure.provenance.source = ProvenanceSource::Imported;
+        unsure.provenance.confidence = 0.5;
+        let mut out = Vec::new();
+        export_jsonl(&InMemoryStore::with_items(vec![item(1), unsure]), &mut out).unwrap();
+
+        let mut target = InMemoryStore::new();
+        let summary = import_jsonl(out.as_slice(), &mut target, &disabled_history()).unwrap();
+        assert_eq!(summary.imported, 2);
+        assert_eq!(summary.queued_for_review, 1);
+        let queued = target.get("key-2").unwrap().unwrap();
+        assert_eq!(queued.review_status, ReviewStatus::Unreviewed);
+    }
+
+    #[test]
+    fn test_profile_export_contains_no_values() {
+        let store = InMemoryStore::with_items((0..3).map(item).collect());
+        let profile = export_profile(&store).unwrap();
+        assert_eq!(profile.items.len(), 3);
+        assert_eq!(profile.items[0].key, "key-0");
+        assert_eq!(profile.items[0].label, "Label 0");
+        assert_eq!(profile.items[0].category, VaultCategory::Custom);
+
+        let json = serde_json::to_string(&profile).unwrap();
+        assert!(!json.contains("value number"));
+        assert!(!json.contains("\"value\""));
+    }
+
+    #[test]
+    fn test_profile_import_creates_empty_keyed_items() {
+        let profile =
+            export_profile(&InMemoryStore::with_items((0..3).map(item).collect())).unwrap();
+        let mut existing = item(1);
+        existing.value = "mine".to_string();
+        let mut target = InMemoryStore::with_items(vec![existing]);
+
+        let summary = import_profile(&profile, &mut target, &disabled_history()).unwrap();
+        assert_eq!(summary.imported, 2);
+        assert_eq!(
+            summary.warnings,
+            vec!["key-1: already in the vault, left as is"]
+        );
+
+        let created = target.get("key-2").unwrap().unwrap();
+        assert_eq!(created.value, "");
+        assert_eq!(created.label, "Label 2");
+        assert_eq!(created.category, VaultCategory::Custom);
+        assert_eq!(created.provenance.source, ProvenanceSource::Imported);
+        assert_eq!(target.get("key-1").unwrap().unwrap().value, "mine");
+
+        let mut future = profile;
+        future.version = 2;
+        assert!(import_profile(&future, &mut target, &disabled_history()).is_err());
+    }
+
+    #[test]
+    fn test_profile_import_with_a_bad_key_imports_nothing() {
+        let mut profile =
+            export_profile(&InMemoryStore::with_items((0..3).map(item).collect())).unwrap();
+        profile.items[1].key = String::new();
+        let mut target = InMemoryStore::new();
+
+        let err = import_profile(&profile, &mut target, &disabled_history()).unwrap_err();
+        assert!(err.contains("Key cannot be empty"), "{}", err);
+        assert!(target.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_simple_import_infers_categories() {
+        assert_eq!(infer_category("email"), VaultCategory::Contact);
+        assert_eq!(infer_category("work_phone"), VaultCategory::Contact);
+        assert_eq!(infer_category("firstName"), VaultCategory::Identity);
+        assert_eq!(infer_category("name"), VaultCategory::Identity);
+        assert_eq!(infer_category("street"), VaultCategory::Address);
+        assert_eq!(infer_category("city"), VaultCategory::Address);
+        assert_eq!(infer_category("zip"), VaultCategory::Address);
+        assert_eq!(infer_category("username"), VaultCategory::Custom);
+        assert_eq!(infer_category("favourite_colour"), VaultCategory::Custom);
+
+        let map: BTreeMap<String, serde_json::Value> = serde_json::from_str(
+            r#"{"email": "a@example.com", "city": "Berlin", "shoe_size": "42", "age": 30}"#,
+        )
+        .unwrap();
+        let mut store = InMemoryStore::new();
+        let summary = import_simple(&map, &mut store, &disabled_history()).unwrap();
+        assert_eq!(summary.imported, 3);
+        assert_eq!(summary.errors, vec!["age: value is not a string"]);
+
+        let email = store.get("email").unwrap().unwrap();
+        assert_eq!(email.value, "a@example.com");
+        assert_eq!(email.category, VaultCategory::Contact);
+        assert_eq!(email.provenance.source, ProvenanceSource::Imported);
+        assert_eq!(email.review_status, ReviewStatus::Approved);
+        assert_eq!(
+            store.get("city").unwrap().unwrap().category,
+            VaultCategory::Address
+        );
+        assert_eq!(
+            store.get("shoe_size").unwrap().unwrap().category,
+            VaultCategory::Custom
+        );
+    }
+
+    #[test]
+    fn test_case_and_whitespace_variant_keys_merge() {
+        let variant = |key: &str, value: &str, confidence: f64| {
+            let mut it = item(0);
+            it.key = key.to_string();
+            it.value = value.to_string();
+            it.provenance.confidence = confidence;
+            serde_json::to_string(&VaultItemJson::from(it)).unwrap() + "\n"
+        };
+        let lines = [
+            variant("Email", "", 1.0),
+            variant("email", "old@example.com", 0.95),
+            variant("email ", "ada@example.com", 1.0),
+            variant("phone", "555", 1.0),
+        ]
+        .concat();
+
+        let mut target = InMemoryStore::new();
+        let summary = import_jsonl(lines.as_bytes(), &mut target, &disabled_history()).unwrap();
+        assert_eq!(summary.imported, 2);
+        assert!(summary.errors.is_empty());
+        assert_eq!(
+            summary.merged,
+            vec![
+                r#"line 2: "email" merged into "Email", keeping the new value"#,
+                r#"line 3: "email " merged into "Email", keeping the new value"#,
+            ]
+        );
+        assert_eq!(target.len(), 2);
+        let merged = target.get("Email").unwrap().unwrap();
+        assert_eq!(merged.key, "Email");
+        assert_eq!(merged.value, "ada@example.com");
+
+        // A value never gives way to an empty one, and ties keep the first
+        let map: BTreeMap<String, serde_json::Value> =
+            serde_json::from_str(r#"{"City": "Berlin", "city": "", "city ": "Paris"}"#).unwrap();
+        let mut store = InMemoryStore::new();
+        let summary = import_simple(&map, &mut store, &disabled_history()).unwrap();
+        assert_eq!(summary.imported, 1);
+        assert_eq!(summary.merged.len(), 2);
+        assert!(summary
+            .merged
+            .iter()
+            .all(|m| m.ends_with("merged into \"City\", keeping the earlier value")));
+        assert_eq!(store.get("City").unwrap().unwrap().value, "Berlin");
+        assert_eq!(canonical_key(" CITY "), "city");
+    }
+}
@@ -0,0 +1,158 @@
+/*!
+ * Optional Features
+ *
+ * Cargo features that can be compiled out are listed once, in `FEATURES`,
+ * generated by `feature_registry!` from the feature names. The registry is
+ * what `features_available` and the bridge's `/v1/capabilities` report, so
+ * the UI and extension can tell "compiled out" from "this version doesn't
+ * have it".
+ *
+ * Commands behind a feature are registered either way. When the feature is
+ * compiled out they return `CommandError::FeatureDisabled` naming it,
+ * instead of Tauri's generic "command not found".
+ *
+ * Registering a name here that isn't a Cargo feature trips the
+ * `unexpected_cfgs` lint, so the registry can't drift from Cargo.toml.
+ */
+
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::{ids, Message, MessageJson};
+
+/// A compile-time optional feature and whether this build has it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FeatureJson {
+    pub name: &'static str,
+    pub enabled: bool,
+    pub description: &'static str,
+}
+
+/// Build `FEATURES` from `"cargo-feature" => "description"` pairs
+macro_rules! feature_registry {
+    ($($name:literal => $description:literal),* $(,)?) => {
+        /// Every optional feature, with whether it is compiled in
+        pub const FEATURES: &[FeatureJson] = &[
+            $(FeatureJson {
+                name: $name,
+                enabled: cfg!(feature = $name),
+                description: $description,
+            }),*
+        ];
+    };
+}
+
+feature_registry! {
+    "dev-tools" => "Bridge conformance runner and fixture authoring",
+    "test-helpers" => "Commands that let tests skip ahead in time",
+}
+
+/// Whether `name` is a registered feature compiled into this build
+pub fn is_enabled(name: &str) -> bool {
+    FEATURES.iter().any(|f| f.name == name && f.enabled)
+}
+
+// ============================================================================
+// Command Errors
+// ============================================================================
+
+/// Error returned by feature-gated commands
+///
+/// Both variants carry a catalog message (`messageId`, `params`, `message`)
+/// flattened next to `code`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum CommandError {
+    /// The command needs a feature this build doesn't have
+    FeatureDisabled {
+        feature: String,
+        #[serde(flatten)]
+        message: MessageJson,
+    },
+    /// Any other failure
+    Failed {
+        #[serde(flatten)]
+        message: MessageJson,
+    },
+}
+
+impl CommandError {
+    pub fn feature_disabled(feature: &str) -> Self {
+        CommandError::FeatureDisabled {
+            feature: feature.to_string(),
+            message: Message::new(ids::FEATURE_DISABLED)
+                .with("feature", feature)
+                .to_json(),
+        }
+    }
+}
+
+/// Errors not yet in the catalog pass through as the `detail` param
+impl From<String> for CommandError {
+    fn from(detail: String) -> Self {
+        CommandError::Failed {
+            message: Message::new(ids::COMMAND_FAILED)
+                .with("detail", detail)
+                .to_json(),
+        }
+    }
+}
+
+/// Fail with `FeatureDisabled` unless `feature` is compiled in
+pub fn require(feature: &str) -> Result<(), CommandError> {
+    if is_enabled(feature) {
+        Ok(())
+    } else {
+        Err(CommandError::feature_disabled(feature))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // These hold with and without `--features dev-tools`; CI runs both
+
+    #[test]
+    fn test_registry_reflects_build() {
+        let dev_tools = FEATURES.iter().find(|f| f.name == "dev-tools").unwrap();
+        assert_eq!(dev_tools.enabled, cfg!(feature = "dev-tools"));
+        assert!(!is_enabled("not-a-feature"));
+    }
+
+    #[test]
+    fn test_require_reports_missing_feature() {
+        let result = require("dev-tools");
+        assert_eq!(result.is_ok(), cfg!(feature = "dev-tools"));
+        if let Err(e) = result {
+            let json = serde_json::to_value(&e).unwrap();
+            assert_eq!(json["code"], "feature_disabled");
+            assert_eq!(json["feature"], "dev-tools");
+            assert_eq!(json["messageId"], "feature-disabled");
+            assert_eq!(json["params"]["feature"], "dev-tools");
+            assert!(json["message"].as_str().unwrap().contains("dev-tools"));
+            assert_eq!(serde_json::from_value::<CommandError>(json).unwrap(), e);
+        }
+        assert_eq!(
+            require("not-a-feature"),
+            Err(CommandError::feature_disabled("not-a-feature"))
+        );
+    }
+
+    #[test]
+    fn test_other_errors_keep_message() {
+        let json = serde_json::to_value(CommandError::from("boom".to_string())).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "code": "failed",
+                "messageId": "command-failed",
+                "params": { "detail": "boom" },
+                "message": "boom",
+            })
+        );
+    }
+}
@@ -0,0 +1,844 @@
+/*!
+ * Fill Command Checks
+ *
+ * Validates fill commands against the captured form snapshot before they
+ * are handed to the extension, and planned fills against the input type of
+ * the field they target, and drops commands once they expire.
+ */
+
+use asterisk_vault::{VaultCategory, VaultItem};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::clock::Clock;
+use crate::i18n::{ids, Message, MessageJson};
+use crate::matching::FillRecommendationJson;
+use crate::shared::FillCommandStore;
+use crate::{FieldNodeJson, FillCommandJson, FormSnapshotJson};
+
+// ============================================================================
+// Command Validation
+// ============================================================================
+
+/// Canonical form of a domain for matching commands, snapshots and consent
+///
+/// Lowercases and strips any scheme, path, port and trailing dot, so
+/// "HTTPS://Example.com:443/login" and "example.com" compare equal.
+pub fn canonicalize_domain(domain: &str) -> String {
+    let domain = domain.trim();
+    let domain = domain.split_once("://").map_or(domain, |(_, rest)| rest);
+    let host = domain.split(['/', '?', '#']).next().unwrap_or("");
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    host.trim_end_matches('.').to_lowercase()
+}
+
+/// Check a fill command is well-formed before it is stored
+///
+/// Returns every problem found, not just the first.
+pub fn validate_command(command: &FillCommandJson) -> Result<(), Vec<Message>> {
+    let mut problems = Vec::new();
+
+    if command.id.trim().is_empty() {
+        problems.push(Message::new(ids::COMMAND_ID_EMPTY));
+    }
+    if canonicalize_domain(&command.target_domain).is_empty() {
+        problems.push(Message::new(ids::COMMAND_DOMAIN_EMPTY));
+    }
+    if command.fills.is_empty() {
+        problems.push(Message::new(ids::COMMAND_NO_FILLS));
+    }
+
+    let mut seen = HashSet::new();
+    for fill in &command.fills {
+        if !seen.insert(fill.field_id.as_str()) {
+            problems.push(Message::new(ids::COMMAND_DUPLICATE_FILL).with("field", &fill.field_id));
+        }
+    }
+
+    let created = chrono::DateTime::parse_from_rfc3339(&command.created_at);
+    let expires = chrono::DateTime::parse_from_rfc3339(&command.expires_at);
+    match (created, expires) {
+        (Ok(created), Ok(expires)) if expires <= created => {
+            problems.push(Message::new(ids::COMMAND_EXPIRY_ORDER));
+        }
+        (Ok(_), Ok(_)) => {}
+        (created, expires) => {
+            if created.is_err() {
+                problems.push(
+                    Message::new(ids::COMMAND_INVALID_CREATED_AT)
+                        .with("value", &command.created_at),
+                );
+            }
+            if expires.is_err() {
+                problems.push(
+                    Message::new(ids::COMMAND_INVALID_EXPIRES_AT)
+                        .with("value", &command.expires_at),
+                );
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems)
+    }
+}
+
+// ============================================================================
+// maxLength Checks
+// ============================================================================
+
+/// How to treat a fill value longer than its field's maxLength
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MaxLengthPolicy {
+    /// Refuse the fill (the browser would silently truncate it)
+    #[default]
+    Reject,
+    /// Allow the fill but flag it as "will be truncated"
+    Flag,
+}
+
+/// A fill value that exceeds its target field's maxLength
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MaxLengthIssueJson {
+    /// The field ID the value was meant for
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    /// The field's maxLength attribute
+    #[serde(rename = "maxLength")]
+    pub max_length: u32,
+    /// Length of the fill value, counted the way the browser does (UTF-16 units)
+    #[serde(rename = "valueLength")]
+    pub value_length: u32,
+    /// Whether the fill is rejected (false means it will be truncated)
+    pub rejected: bool,
+    /// Human-readable explanation
+    pub reason: String,
+    /// `reason` as a catalog message
+    #[serde(
+        rename = "reasonMessage",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub reason_message: Option<MessageJson>,
+}
+
+/// Check each fill value against the target field's maxLength
+///
+/// Fields missing from the snapshot or without a maxLength are not checked.
+pub fn check_max_lengths(
+    command: &FillCommandJson,
+    fields: &[FieldNodeJson],
+    policy: MaxLengthPolicy,
+) -> Vec<MaxLengthIssueJson> {
+    command
+        .fills
+        .iter()
+        .filter_map(|fill| {
+            let field = fields.iter().find(|f| f.id == fill.field_id)?;
+            let max_length = field.max_length?;
+            let value_length = fill.value.encode_utf16().count() as u32;
+            if value_length <= max_length {
+                return None;
+            }
+
+            let rejected = policy == MaxLengthPolicy::Reject;
+            let reason = Message::new(if rejected {
+                ids::VALUE_TOO_LONG
+            } else {
+                ids::VALUE_TRUNCATED
+            })
+            .with("length", value_length)
+            .with("max", max_length)
+            .to_json();
+
+            Some(MaxLengthIssueJson {
+                field_id: fill.field_id.clone(),
+                max_length,
+                value_length,
+                rejected,
+                reason: reason.message.clone(),
+                reason_message: Some(reason),
+            })
+        })
+        .collect()
+}
+
+/// Compiled size bound for a field's `pattern`, as in find/replace
+const PATTERN_SIZE_LIMIT: usize = 1 << 16;
+
+/// Warnings for a value the user typed into a field
+///
+/// Applies the same maxLength rule as fill commands plus the field's HTML
+/// `pattern`, which must match the whole value. A pattern the regex engine
+/// can't compile (JavaScript syntax it lacks) is not checked.
+pub fn check_manual_value(field: &FieldNodeJson, value: &str) -> Vec<Message> {
+    let mut warnings = Vec::new();
+    let value_length = value.encode_utf16().count() as u32;
+    if let Some(max_length) = field.max_length.filter(|max| value_length > *max) {
+        warnings.push(
+            Message::new(ids::VALUE_TOO_LONG)
+                .with("length", value_length)
+                .with("max", max_length),
+        );
+    }
+    let pattern = field.validation.as_deref().filter(|p| !p.is_empty());
+    if let Some(pattern) = pattern {
+        let matcher = regex::RegexBuilder::new(&format!("^(?:{})$", pattern))
+            .size_limit(PATTERN_SIZE_LIMIT)
+            .build();
+        if matcher.is_ok_and(|matcher| !matcher.is_match(value)) {
+            warnings.push(Message::new(ids::VALUE_PATTERN_MISMATCH).with("pattern", pattern));
+        }
+    }
+    warnings
+}
+
+// ============================================================================
+// Type Reconciliation
+// ============================================================================
+
+/// Whether a `category` vault value fits an input of `field_type`
+///
+/// Typed inputs (email, tel, number, date and friends, url, color) need a
+/// value of that shape; a tel field also refuses address and financial
+/// items, whose postal codes and card numbers are digits too. Text-like and
+/// unknown types take anything, and an empty value fits everywhere.
+pub fn type_compatible(field_type: &str, category: &VaultCategory, value: &str) -> bool {
+    let value = value.trim();
+    if value.is_empty() {
+        return true;
+    }
+    match field_type.trim().to_ascii_lowercase().as_str() {
+        "email" => looks_like_email(value),
+        "tel" => {
+            !matches!(category, VaultCategory::Address | VaultCategory::Financial)
+                && looks_like_phone(value)
+        }
+        "number" | "range" => value.replacen(',', ".", 1).parse::<f64>().is_ok(),
+        "date" => parse_date(value).is_some(),
+        "month" => NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d").is_ok(),
+        "time" => ["%H:%M", "%H:%M:%S"]
+            .iter()
+            .any(|format| NaiveTime::parse_from_str(value, format).is_ok()),
+        "datetime-local" => ["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"]
+            .iter()
+            .any(|format| NaiveDateTime::parse_from_str(value, format).is_ok()),
+        "url" => {
+            !value.contains(char::is_whitespace)
+                && (value.contains("://") || (value.contains('.') && !value.contains('@')))
+        }
+        "color" => {
+            value.len() == 7
+                && value.starts_with('#')
+                && value[1..].chars().all(|c| c.is_ascii_hexdigit())
+        }
+        _ => true,
+    }
+}
+
+fn looks_like_email(value: &str) -> bool {
+    match value.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.contains('@')
+                && !value.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+/// Digits with the usual separators, and not a date written with dashes
+fn looks_like_phone(value: &str) -> bool {
+    let digits = value.chars().filter(char::is_ascii_digit).count();
+    (5..=15).contains(&digits)
+        && value
+            .chars()
+            .all(|c| c.is_ascii_digit() || " +-()./".contains(c))
+        && parse_date(value).is_none()
+}
+
+/// A date in ISO or one of the common day/month orders
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    ["%Y-%m-%d", "%d.%m.%Y", "%m/%d/%Y", "%d/%m/%Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+}
+
+/// A planned fill whose value doesn't fit the input type of its field
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TypeMismatchJson {
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    #[serde(rename = "vaultKey")]
+    pub vault_key: String,
+    #[serde(rename = "fieldType")]
+    pub field_type: String,
+    /// Human-readable explanation
+    pub reason: String,
+    /// `reason` as a catalog message
+    #[serde(rename = "reasonMessage")]
+    pub reason_message: MessageJson,
+}
+
+/// Check each planned fill against its field's input type
+///
+/// Composed values are checked as composed. Recommendations whose field or
+/// vault item is gone are skipped.
+pub fn reconcile_types(
+    recommendations: &[FillRecommendationJson],
+    fields: &[FieldNodeJson],
+    items: &[VaultItem],
+) -> Vec<TypeMismatchJson> {
+    recommendations
+        .iter()
+        .filter_map(|rec| {
+            let field = fields.iter().find(|f| f.id == rec.field_id)?;
+            let item = items.iter().find(|i| i.key == rec.vault_key)?;
+            let value = rec.value.as_deref().unwrap_or(&item.value);
+            if type_compatible(&field.field_type, &item.category, value) {
+                return None;
+            }
+            let reason = Message::new(ids::VALUE_TYPE_MISMATCH)
+                .with("key", &item.key)
+                .with("type", &field.field_type)
+                .to_json();
+            Some(TypeMismatchJson {
+                field_id: field.id.clone(),
+                vault_key: item.key.clone(),
+                field_type: field.field_type.clone(),
+                reason: reason.message.clone(),
+                reason_message: reason,
+            })
+        })
+        .collect()
+}
+
+// ============================================================================
+// Batches
+// ============================================================================
+
+/// Why one command of a batch was refused
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BatchProblemJson {
+    /// Position of the command in the batch
+    pub index: usize,
+    pub id: String,
+    pub problems: Vec<String>,
+    /// `problems` as catalog messages, in the same order
+    #[serde(default)]
+    pub messages: Vec<MessageJson>,
+}
+
+impl BatchProblemJson {
+    fn new(index: usize, id: String, problems: Vec<Message>) -> Self {
+        let messages: Vec<MessageJson> = problems.into_iter().map(MessageJson::from).collect();
+        BatchProblemJson {
+            index,
+            id,
+            problems: messages.iter().map(|m| m.message.clone()).collect(),
+            messages,
+        }
+    }
+}
+
+/// Outcome for one command of an accepted batch
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BatchResultJson {
+    pub id: String,
+    /// "ok", or "awaiting_consent" when held for the user's consent
+    pub status: String,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub warnings: Vec<MaxLengthIssueJson>,
+}
+
+/// Check every command of a batch before any of it is stored
+///
+/// Target domains are canonicalized in place. On success returns the
+/// (non-rejecting) maxLength warnings for each command; if any command is
+/// invalid, returns the problems of every invalid command and the whole
+/// batch must be refused.
+pub fn validate_batch(
+    commands: &mut [FillCommandJson],
+    snapshot: Option<&FormSnapshotJson>,
+    policy: MaxLengthPolicy,
+) -> Result<Vec<Vec<MaxLengthIssueJson>>, Vec<BatchProblemJson>> {
+    if commands.is_empty() {
+        return Err(vec![BatchProblemJson::new(
+            0,
+            String::new(),
+            vec![Message::new(ids::BATCH_EMPTY)],
+        )]);
+    }
+
+    let mut seen = HashSet::new();
+    let mut warnings = Vec::with_capacity(commands.len());
+    let mut refused = Vec::new();
+    for (index, command) in commands.iter_mut().enumerate() {
+        command.target_domain = canonicalize_domain(&command.target_domain);
+        let mut problems = validate_command(command).err().unwrap_or_default();
+        if !command.id.trim().is_empty() && !seen.insert(command.id.clone()) {
+            problems.push(Message::new(ids::BATCH_DUPLICATE_ID).with("id", &command.id));
+        }
+
+        let issues = match snapshot {
+            Some(snapshot) if canonicalize_domain(&snapshot.domain) == command.target_domain => {
+                check_max_lengths(command, &snapshot.fields, policy)
+            }
+            _ => Vec::new(),
+        };
+        problems.extend(issues.iter().filter(|issue| issue.rejected).map(|issue| {
+            Message::new(ids::FIELD_PROBLEM)
+                .with("field", &issue.field_id)
+                .with("problem", &issue.reason)
+        }));
+
+        if problems.is_empty() {
+            warnings.push(issues);
+        } else {
+            refused.push(BatchProblemJson::new(index, command.id.clone(), problems));
+        }
+    }
+
+    if refused.is_empty() {
+        Ok(warnings)
+    } else {
+        Err(refused)
+    }
+}
+
+// ============================================================================
+// Expiry
+// ============================================================================
+
+/// How often the pruner thread clears expired commands from the store
+pub const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Whether `command` has expired at `now`
+///
+/// Expiry is compared as a point in time, so offsets like `+02:00` and `Z`
+/// order correctly. A command whose `expiresAt` doesn't parse could never
+/// be delivered, so it counts as expired.
+pub fn is_expired(command: &FillCommandJson, now: DateTime<Utc>) -> bool {
+    !DateTime::parse_from_rfc3339(&command.expires_at).is_ok_and(|expires| expires > now)
+}
+
+/// Drop the commands that have expired at `now`
+pub fn prune_expired(commands: &mut Vec<FillCommandJson>, now: DateTime<Utc>) {
+    commands.retain(|command| !is_expired(command, now));
+}
+
+/// Prune `store` if it holds expired commands, leaving its revision alone
+/// otherwise
+pub fn prune_store(store: &FillCommandStore, now: DateTime<Utc>) {
+    match store.lock() {
+        Ok(mut commands) => {
+            if commands.iter().any(|command| is_expired(command, now)) {
+                prune_expired(&mut commands, now);
+            }
+        }
+        Err(e) => eprintln!("[Asterisk] ERROR: Failed to prune fill commands: {}", e),
+    }
+}
+
+/// Start the thread that prunes `store` every `PRUNE_INTERVAL`, going by
+/// `clock`
+pub fn spawn_pruner(store: FillCommandStore, clock: Arc<Clock>) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        thread::sleep(PRUNE_INTERVAL);
+        prune_store(&store, clock.now());
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::Locale;
+    use crate::FieldFillJson;
+
+    fn field(id: &str, max_length: Option<u32>) -> FieldNodeJson {
+        FieldNodeJson {
+            id: id.to_string(),
+            name: id.to_string(),
+            label: id.to_string(),
+            field_type: "text".to_string(),
+            semantic: "unknown".to_string(),
+            required: false,
+            max_length,
+            ..Default::default()
+        }
+    }
+
+    fn command(fills: &[(&str, &str)]) -> FillCommandJson {
+        FillCommandJson {
+            id: "cmd-1".to_string(),
+            target_domain: "example.com".to_string(),
+            target_url: None,
+            fills: fills
+                .iter()
+                .map(|(id, value)| FieldFillJson {
+                    field_id: id.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            expires_at: "2026-01-01T00:05:00Z".to_string(),
+            capture_undo: false,
+            plan_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_valid_command() {
+        assert!(validate_command(&command(&[("email", "a@b.c")])).is_ok());
+    }
+
+    #[test]
+    fn test_empty_fills_rejected() {
+        let problems = validate_command(&command(&[])).unwrap_err();
+        assert_eq!(problems, vec![Message::new(ids::COMMAND_NO_FILLS)]);
+    }
+
+    #[test]
+    fn test_duplicate_field_ids_rejected() {
+        let problems = validate_command(&command(&[("email", "a"), ("name", "b"), ("email", "c")]))
+            .unwrap_err();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].render_in(Locale::En).contains("email"));
+    }
+
+    #[test]
+    fn test_inverted_timestamps_rejected() {
+        let mut cmd = command(&[("email", "a")]);
+        cmd.expires_at = "2025-12-31T23:59:00Z".to_string();
+        let problems = validate_command(&cmd).unwrap_err();
+        assert_eq!(problems, vec![Message::new(ids::COMMAND_EXPIRY_ORDER)]);
+
+        cmd.expires_at = "tomorrow".to_string();
+        let problems = validate_command(&cmd).unwrap_err();
+        assert_eq!(problems[0].id, ids::COMMAND_INVALID_EXPIRES_AT);
+        assert!(problems[0].render_in(Locale::En).contains("tomorrow"));
+    }
+
+    #[test]
+    fn test_prune_expired_compares_instants_not_strings() {
+        let now = DateTime::parse_from_rfc3339("2026-01-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let expiring = |id: &str, expires_at: &str| {
+            let mut cmd = command(&[("email", "a")]);
+            cmd.id = id.to_string();
+            cmd.expires_at = expires_at.to_string();
+            cmd
+        };
+        let mut commands = vec![
+            expiring("past", "2026-01-01T11:59:00Z"),
+            // Later than `now` as a string, but 10:30 UTC
+            expiring("past-offset", "2026-01-01T12:30:00+02:00"),
+            // Earlier than `now` as a string, but 13:00 UTC
+            expiring("future-offset", "2026-01-01T08:00:00-05:00"),
+            expiring("future", "2026-01-01T12:05:00Z"),
+            expiring("at-now", "2026-01-01T12:00:00Z"),
+            expiring("garbled", "tomorrow"),
+        ];
+        prune_expired(&mut commands, now);
+        let kept: Vec<&str> = commands.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(kept, vec!["future-offset", "future"]);
+    }
+
+    #[test]
+    fn test_prune_store_without_expired_commands_keeps_revision() {
+        let store = FillCommandStore::new("fill command", vec![command(&[("email", "a")])]);
+        let before = DateTime::parse_from_rfc3339("2026-01-01T00:01:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        prune_store(&store, before);
+        assert_eq!(store.revision(), 0);
+        assert_eq!(store.lock().unwrap().len(), 1);
+
+        prune_store(&store, before + chrono::Duration::minutes(10));
+        assert_eq!(store.revision(), 1);
+        assert!(store.lock().unwrap().is_empty());
+    }
+
+    fn batch_command(id: &str, domain: &str) -> FillCommandJson {
+        let mut cmd = command(&[("email", "a@b.c")]);
+        cmd.id = id.to_string();
+        cmd.target_domain = domain.to_string();
+        cmd
+    }
+
+    #[test]
+    fn test_valid_batch() {
+        let mut batch = vec![
+            batch_command("a", "HTTPS://Example.com/login"),
+            batch_command("b", "other.org"),
+        ];
+        let warnings = validate_batch(&mut batch, None, MaxLengthPolicy::Reject).unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(batch[0].target_domain, "example.com");
+    }
+
+    #[test]
+    fn test_batch_reports_every_invalid_command() {
+        let mut no_fills = batch_command("b", "example.com");
+        no_fills.fills.clear();
+        let mut batch = vec![
+            batch_command("a", "example.com"),
+            no_fills,
+            batch_command("a", "example.com"),
+        ];
+        let refused = validate_batch(&mut batch, None, MaxLengthPolicy::Reject).unwrap_err();
+        assert_eq!(refused.len(), 2);
+        assert_eq!(refused[0].index, 1);
+        assert_eq!(
+            refused[0].problems,
+            vec!["Command has no fills".to_string()]
+        );
+        assert_eq!(refused[1].index, 2);
+        assert!(refused[1].problems[0].contains("Duplicate command id"));
+        assert_eq!(refused[1].messages[0].message_id, ids::BATCH_DUPLICATE_ID);
+        assert_eq!(refused[1].messages[0].params["id"], "a");
+    }
+
+    #[test]
+    fn test_batch_checks_max_length_against_snapshot() {
+        let snapshot = FormSnapshotJson {
+            url: "https://example.com/".to_string(),
+            domain: "Example.com".to_string(),
+            title: String::new(),
+            captured_at: String::new(),
+            fingerprint: crate::FormFingerprintJson {
+                field_count: 1,
+                field_types: vec![],
+                required_count: 0,
+                hash: String::new(),
+            },
+            fields: vec![field("email", Some(3))],
+            session: Default::default(),
+        };
+        let mut batch = vec![
+            batch_command("a", "other.org"),
+            batch_command("b", "example.com"),
+        ];
+        let refused =
+            validate_batch(&mut batch, Some(&snapshot), MaxLengthPolicy::Reject).unwrap_err();
+        assert_eq!(refused.len(), 1);
+        assert_eq!(refused[0].id, "b");
+
+        let warnings = validate_batch(&mut batch, Some(&snapshot), MaxLengthPolicy::Flag).unwrap();
+        assert!(warnings[0].is_empty());
+        assert_eq!(warnings[1].len(), 1);
+    }
+
+    #[test]
+    fn test_empty_batch_rejected() {
+        assert!(validate_batch(&mut [], None, MaxLengthPolicy::Reject).is_err());
+    }
+
+    #[test]
+    fn test_canonicalize_domain() {
+        assert_eq!(canonicalize_domain("Example.COM"), "example.com");
+        assert_eq!(
+            canonicalize_domain(" https://Example.com:8443/login?x=1 "),
+            "example.com"
+        );
+        assert_eq!(canonicalize_domain("example.com."), "example.com");
+        assert_eq!(canonicalize_domain("www.example.com"), "www.example.com");
+    }
+
+    #[test]
+    fn test_value_under_max_length() {
+        let fields = vec![field("zip", Some(5))];
+        let issues = check_max_lengths(
+            &command(&[("zip", "1234")]),
+            &fields,
+            MaxLengthPolicy::Reject,
+        );
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn test_value_equal_to_max_length() {
+        let fields = vec![field("zip", Some(5))];
+        let issues = check_max_lengths(
+            &command(&[("zip", "12345")]),
+            &fields,
+            MaxLengthPolicy::Reject,
+        );
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn test_value_over_max_length_rejected() {
+        let fields = vec![field("zip", Some(5))];
+        let issues = check_max_lengths(
+            &command(&[("zip", "12345-6789")]),
+            &fields,
+            MaxLengthPolicy::Reject,
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field_id, "zip");
+        assert_eq!(issues[0].value_length, 10);
+        assert!(issues[0].rejected);
+    }
+
+    #[test]
+    fn test_value_over_max_length_flagged() {
+        let fields = vec![field("zip", Some(5))];
+        let issues = check_max_lengths(
+            &command(&[("zip", "12345-6789")]),
+            &fields,
+            MaxLengthPolicy::Flag,
+        );
+        assert_eq!(issues.len(), 1);
+        assert!(!issues[0].rejected);
+        assert!(issues[0].reason.contains("truncated"));
+    }
+
+    #[test]
+    fn test_length_counts_utf16_units() {
+        // "é" is one UTF-16 unit even though it is two UTF-8 bytes
+        let fields = vec![field("name", Some(4))];
+        let issues = check_max_lengths(
+            &command(&[("name", "José")]),
+            &fields,
+            MaxLengthPolicy::Reject,
+        );
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_or_unbounded_fields_skipped() {
+        let fields = vec![field("notes", None)];
+        let issues = check_max_lengths(
+            &command(&[("notes", "a long value"), ("missing", "another long value")]),
+            &fields,
+            MaxLengthPolicy::Reject,
+        );
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn test_manual_value_checks_max_length_and_pattern() {
+        let mut zip = field("zip", Some(5));
+        zip.validation = Some("[0-9]{5}".to_string());
+        assert!(check_manual_value(&zip, "12345").is_empty());
+        assert_eq!(check_manual_value(&zip, "1234").len(), 1);
+        assert_eq!(check_manual_value(&zip, "123456").len(), 2);
+
+        // Unsupported syntax is left to the browser
+        zip.validation = Some("(?<=x)".to_string());
+        assert!(check_manual_value(&zip, "abc").is_empty());
+    }
+
+    #[test]
+    fn test_compatible_types() {
+        use VaultCategory::*;
+        assert!(type_compatible("email", &Contact, "ada@example.com"));
+        assert!(type_compatible("tel", &Contact, "+49 (30) 123-4567"));
+        assert!(type_compatible("number", &Custom, "42,5"));
+        assert!(type_compatible("date", &Identity, "1990-04-01"));
+        assert!(type_compatible("date", &Identity, "01.04.1990"));
+        assert!(type_compatible("month", &Financial, "2027-12"));
+        assert!(type_compatible("time", &Custom, "09:30"));
+        assert!(type_compatible("url", &Contact, "example.com/ada"));
+        assert!(type_compatible("color", &Custom, "#A0b1C2"));
+        // Text-like and unknown inputs take anything; empty fits everywhere
+        assert!(type_compatible("text", &Financial, "4111 1111 1111 1111"));
+        assert!(type_compatible(
+            "textarea",
+            &Address,
+            "1 Main St\nSpringfield"
+        ));
+        assert!(type_compatible("TEL", &Contact, " "));
+    }
+
+    #[test]
+    fn test_incompatible_types() {
+        use VaultCategory::*;
+        assert!(!type_compatible("tel", &Contact, "ada@example.com"));
+        assert!(!type_compatible("email", &Contact, "+49 30 1234567"));
+        assert!(!type_compatible("email", &Contact, "ada@localhost"));
+        assert!(!type_compatible("date", &Contact, "+49 30 1234567"));
+        assert!(!type_compatible("tel", &Identity, "1990-04-01"));
+        // Digits, but a postal code or card number isn't a phone number
+        assert!(!type_compatible("tel", &Address, "10115"));
+        assert!(!type_compatible("tel", &Financial, "4111111111111111"));
+        assert!(!type_compatible("number", &Identity, "Ada"));
+        assert!(!type_compatible("url", &Contact, "ada@example.com"));
+        assert!(!type_compatible("color", &Custom, "red"));
+    }
+
+    #[test]
+    fn test_reconcile_flags_incompatible_fills() {
+        use crate::matching::MatchTier;
+        use asterisk_vault::{Provenance, ProvenanceSource};
+
+        let item = |key: &str, value: &str| {
+            VaultItem::new(
+                key,
+                value,
+                key,
+                VaultCategory::Contact,
+                Provenance {
+                    source: ProvenanceSource::UserEntered,
+                    timestamp: chrono::Utc::now(),
+                    confidence: 1.0,
+                    origin: None,
+                },
+            )
+        };
+        let rec = |field_id: &str, vault_key: &str| FillRecommendationJson {
+            field_id: field_id.to_string(),
+            vault_key: vault_key.to_string(),
+            confidence: 0.95,
+            reason: String::new(),
+            reason_message: None,
+            required: false,
+            match_tier: MatchTier::Pattern,
+            label_source: None,
+            value: None,
+        };
+        let mut phone = field("phone", None);
+        phone.field_type = "tel".to_string();
+        let mut mail = field("mail", None);
+        mail.field_type = "email".to_string();
+        let items = vec![
+            item("email", "ada@example.com"),
+            item("phone", "030 1234567"),
+        ];
+
+        let mismatches = reconcile_types(
+            &[
+                rec("phone", "email"),
+                rec("mail", "email"),
+                rec("gone", "phone"),
+            ],
+            &[phone, mail],
+            &items,
+        );
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].field_id, "phone");
+        assert_eq!(mismatches[0].field_type, "tel");
+        assert_eq!(
+            mismatches[0].reason_message.render_in(Locale::En),
+            "The value of \"email\" doesn't look like input of type tel"
+        );
+    }
+}
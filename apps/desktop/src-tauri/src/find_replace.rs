@@ -0,0 +1,510 @@
+/*!
+ * Vault-Wide Find and Replace
+ *
+ * Two-step: `preview` computes per-item diffs and stashes the planned
+ * replacements under a single-use token; `apply` performs exactly those
+ * replacements in one batch, skipping any item edited since the preview.
+ * Only the latest few previews are kept.
+ *
+ * Regexes are bounded (pattern length, compiled size, nesting) so a pasted
+ * pattern can't stall the app. Values that are JSON objects or arrays only
+ * have their string leaves rewritten, never keys or structure.
+ */
+
+use asterisk_vault::{VaultItem, VaultStore};
+use regex::{NoExpand, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::history::VaultHistory;
+use crate::redact::redact_value;
+
+/// Longest accepted find pattern, in bytes
+const MAX_PATTERN_LEN: usize = 256;
+/// Compiled program size limit for regex patterns
+const REGEX_SIZE_LIMIT: usize = 1 << 16;
+/// Maximum nesting depth for regex patterns
+const REGEX_NEST_LIMIT: u32 = 16;
+/// Previews kept awaiting apply; older ones are dropped past this
+const MAX_PREVIEWS: usize = 16;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Options controlling how `find` is interpreted
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FindReplaceOptions {
+    /// Treat `find` as a regular expression (replacement may use `$1`)
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(rename = "caseInsensitive", default)]
+    pub case_insensitive: bool,
+}
+
+/// How one item would change
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ItemDiffJson {
+    pub key: String,
+    pub label: String,
+    /// Current value (masked for sensitive items)
+    pub before: String,
+    /// Value after replacement (masked for sensitive items)
+    pub after: String,
+    #[serde(rename = "matchCount")]
+    pub match_count: usize,
+}
+
+/// Result of a preview, redeemable once via `apply`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FindReplacePreviewJson {
+    /// Empty when nothing matched
+    pub token: String,
+    pub diffs: Vec<ItemDiffJson>,
+    #[serde(rename = "totalMatches")]
+    pub total_matches: usize,
+}
+
+/// Outcome of applying a preview
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FindReplaceResultJson {
+    /// Keys that were rewritten
+    pub applied: Vec<String>,
+    /// Keys left alone because they changed after the preview
+    pub skipped: Vec<String>,
+}
+
+/// A replacement computed at preview time
+#[derive(Debug, Clone)]
+struct PlannedReplacement {
+    key: String,
+    old_value: String,
+    new_value: String,
+}
+
+// ============================================================================
+// Matching
+// ============================================================================
+
+/// Compile `find` into a bounded regex
+fn build_matcher(find: &str, options: &FindReplaceOptions) -> Result<Regex, String> {
+    if find.is_empty() {
+        return Err("Find text cannot be empty".to_string());
+    }
+    if find.len() > MAX_PATTERN_LEN {
+        return Err(format!(
+            "Find pattern is too long ({} > {} bytes)",
+            find.len(),
+            MAX_PATTERN_LEN
+        ));
+    }
+
+    let pattern = if options.regex {
+        find.to_string()
+    } else {
+        regex::escape(find)
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(options.case_insensitive)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_SIZE_LIMIT)
+        .nest_limit(REGEX_NEST_LIMIT)
+        .build()
+        .map_err(|e| format!("Invalid find pattern: {}", e))
+}
+
+/// Replace within a plain string, returning the new text and match count
+fn replace_text(text: &str, matcher: &Regex, replace: &str, expand: bool) -> (String, usize) {
+    let count = matcher.find_iter(text).count();
+    if count == 0 {
+        return (text.to_string(), 0);
+    }
+    let replaced = if expand {
+        matcher.replace_all(text, replace)
+    } else {
+        matcher.replace_all(text, NoExpand(replace))
+    };
+    (replaced.into_owned(), count)
+}
+
+/// Replace within string leaves of a JSON value
+fn replace_leaves(
+    value: &mut serde_json::Value,
+    matcher: &Regex,
+    replace: &str,
+    expand: bool,
+) -> usize {
+    match value {
+        serde_json::Value::String(text) => {
+            let (replaced, count) = replace_text(text, matcher, replace, expand);
+            *text = replaced;
+            count
+        }
+        serde_json::Value::Array(items) => items
+            .iter_mut()
+            .map(|v| replace_leaves(v, matcher, replace, expand))
+            .sum(),
+        serde_json::Value::Object(map) => map
+            .values_mut()
+            .map(|v| replace_leaves(v, matcher, replace, expand))
+            .sum(),
+        _ => 0,
+    }
+}
+
+/// Compute the replaced value for an item, if anything matches
+fn replace_value(
+    value: &str,
+    matcher: &Regex,
+    replace: &str,
+    expand: bool,
+) -> Option<(String, usize)> {
+    let trimmed = value.trim_start();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        if let Ok(mut json) = serde_json::from_str::<serde_json::Value>(value) {
+            let count = replace_leaves(&mut json, matcher, replace, expand);
+            return (count > 0).then(|| (json.to_string(), count));
+        }
+    }
+
+    let (replaced, count) = replace_text(value, matcher, replace, expand);
+    (count > 0).then_some((replaced, count))
+}
+
+fn display_value(value: &str, sensitive: bool) -> String {
+    if sensitive {
+        redact_value(value, true).0
+    } else {
+        value.to_string()
+    }
+}
+
+// ============================================================================
+// Preview Store
+// ============================================================================
+
+/// Outstanding previews awaiting apply, oldest first
+#[derive(Debug, Default)]
+pub struct FindReplaceStore {
+    previews: Vec<(String, Vec<PlannedReplacement>)>,
+    next_id: u64,
+}
+
+impl FindReplaceStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compute the diffs for a find/replace over `items`
+    pub fn preview(
+        &mut self,
+        items: &[VaultItem],
+        find: &str,
+        replace: &str,
+        options: &FindReplaceOptions,
+    ) -> Result<FindReplacePreviewJson, String> {
+        let matcher = build_matcher(find, options)?;
+
+        let mut diffs = Vec::new();
+        let mut planned = Vec::new();
+        for item in items {
+            let Some((new_value, match_count)) =
+                replace_value(&item.value, &matcher, replace, options.regex)
+            else {
+                continue;
+            };
+            diffs.push(ItemDiffJson {
+                key: item.key.clone(),
+                label: item.label.clone(),
+                before: display_value(&item.value, item.sensitive),
+                after: display_value(&new_value, item.sensitive),
+                match_count,
+            });
+            planned.push(PlannedReplacement {
+                key: item.key.clone(),
+                old_value: item.value.clone(),
+                new_value,
+            });
+        }
+        diffs.sort_by(|a, b| a.key.cmp(&b.key));
+
+        let total_matches = diffs.iter().map(|d| d.match_count).sum();
+        let token = if planned.is_empty() {
+            String::new()
+        } else {
+            self.next_id += 1;
+            let token = format!(
+                "fr-{}-{}",
+                chrono::Utc::now().timestamp_millis(),
+                self.next_id
+            );
+            self.previews.push((token.clone(), planned));
+            if self.previews.len() > MAX_PREVIEWS {
+                self.previews.remove(0);
+            }
+            token
+        };
+
+        Ok(FindReplacePreviewJson {
+            token,
+            diffs,
+            total_matches,
+        })
+    }
+
+    /// Apply a previewed find/replace; the token is consumed
+    ///
+    /// The replacements are written in one batch. If that fails nothing is
+    /// changed and the token stays valid, so the apply can be retried.
+    pub fn apply(
+        &mut self,
+        token: &str,
+        vault: &mut dyn VaultStore,
+        history: &VaultHistory,
+    ) -> Result<FindReplaceResultJson, String> {
+        let index = self
+            .previews
+            .iter()
+            .position(|(t, _)| t == token)
+            .ok_or_else(|| "Unknown or already used preview token".to_string())?;
+        let (token, planned) = self.previews.remove(index);
+
+        let staged = stage(&planned, vault);
+        let (items, result) = match staged {
+            Ok(staged) => staged,
+            Err(e) => {
+                self.previews.insert(index, (token, planned));
+                return Err(e);
+            }
+        };
+        let batch = items
+            .iter()
+            .map(|item| (item.key.clone(), item.clone()))
+            .collect();
+        if let Err(e) = vault.set_many(batch) {
+            self.previews.insert(index, (token, planned));
+            return Err(e.to_string());
+        }
+        for item in &items {
+            history.record_set(item, true, "find_replace")?;
+        }
+        Ok(result)
+    }
+}
+
+/// The updated items for the replacements still current in `vault`, and
+/// which keys were applied or skipped as edited since the preview
+fn stage(
+    planned: &[PlannedReplacement],
+    vault: &dyn VaultStore,
+) -> Result<(Vec<VaultItem>, FindReplaceResultJson), String> {
+    let keys: Vec<&str> = planned.iter().map(|r| r.key.as_str()).collect();
+    let mut current = vault.get_many(&keys).map_err(|e| e.to_string())?;
+    let mut items = Vec::new();
+    let mut result = FindReplaceResultJson {
+        applied: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for replacement in planned {
+        let Some(mut item) = current
+            .remove(&replacement.key)
+            .filter(|i| i.value == replacement.old_value)
+        else {
+            result.skipped.push(replacement.key.clone());
+            continue;
+        };
+        item.update_value(replacement.new_value.clone());
+        items.push(item);
+        result.applied.push(replacement.key.clone());
+    }
+    Ok((items, result))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asterisk_vault::{test_util, InMemoryStore, VaultCategory};
+
+    fn item(key: &str, value: &str) -> VaultItem {
+        test_util::item(key, value, VaultCategory::Custom)
+    }
+
+    fn history() -> VaultHistory {
+        let history = VaultHistory::new(std::env::temp_dir().join("unused-history.jsonl"));
+        history.set_enabled(false);
+        history
+    }
+
+    fn literal() -> FindReplaceOptions {
+        FindReplaceOptions::default()
+    }
+
+    #[test]
+    fn test_regex_limits_enforced() {
+        let mut store = FindReplaceStore::new();
+        let items = vec![item("company", "Acme Corp")];
+        let regex = FindReplaceOptions {
+            regex: true,
+            ..Default::default()
+        };
+
+        let too_long = "a".repeat(MAX_PATTERN_LEN + 1);
+        assert!(store.preview(&items, &too_long, "", &regex).is_err());
+        assert!(store.preview(&items, "(unclosed", "", &regex).is_err());
+        assert!(store
+            .preview(&items, "(a{1000}){1000}", "", &regex)
+            .is_err());
+        assert!(store.preview(&items, "", "x", &literal()).is_err());
+    }
+
+    #[test]
+    fn test_literal_is_not_a_regex() {
+        let mut store = FindReplaceStore::new();
+        let items = vec![item("note", "a.c abc")];
+        let preview = store.preview(&items, "a.c", "X", &literal()).unwrap();
+        assert_eq!(preview.diffs[0].after, "X abc");
+        assert_eq!(preview.total_matches, 1);
+    }
+
+    #[test]
+    fn test_multi_item_apply() {
+        let mut vault = InMemoryStore::with_items(vec![
+            item("company", "Acme Corp"),
+            item("employer", "Acme Corp (since 2019)"),
+            item("email", "me@acme.example"),
+            item("city", "Springfield"),
+        ]);
+        let mut store = FindReplaceStore::new();
+        let options = FindReplaceOptions {
+            regex: true,
+            case_insensitive: true,
+        };
+
+        let preview = store
+            .preview(&vault.list().unwrap(), r"\bacme\b", "Globex", &options)
+            .unwrap();
+        let keys: Vec<&str> = preview.diffs.iter().map(|d| d.key.as_str()).collect();
+        assert_eq!(keys, vec!["company", "email", "employer"]);
+
+        let result = store.apply(&preview.token, &mut vault, &history()).unwrap();
+        assert_eq!(result.applied.len(), 3);
+        assert_eq!(vault.get("company").unwrap().unwrap().value, "Globex Corp");
+        assert_eq!(
+            vault.get("email").unwrap().unwrap().value,
+            "me@Globex.example"
+        );
+        assert_eq!(vault.get("city").unwrap().unwrap().value, "Springfield");
+    }
+
+    #[test]
+    fn test_token_is_single_use_and_skips_stale_items() {
+        let mut vault = InMemoryStore::with_items(vec![item("a", "old"), item("b", "old")]);
+        let mut store = FindReplaceStore::new();
+        let preview = store
+            .preview(&vault.list().unwrap(), "old", "new", &literal())
+            .unwrap();
+
+        // Edited between preview and apply
+        vault
+            .set("b".to_string(), item("b", "old but edited"))
+            .unwrap();
+
+        let result = store.apply(&preview.token, &mut vault, &history()).unwrap();
+        assert_eq!(result.applied, vec!["a".to_string()]);
+        assert_eq!(result.skipped, vec!["b".to_string()]);
+        assert!(store.apply(&preview.token, &mut vault, &history()).is_err());
+    }
+
+    /// A vault whose batch writes fail
+    struct FailingWrites(InMemoryStore);
+
+    impl VaultStore for FailingWrites {
+        fn set(&mut self, key: String, item: VaultItem) -> asterisk_vault::Result<()> {
+            self.0.set(key, item)
+        }
+        fn set_many(&mut self, _: Vec<(String, VaultItem)>) -> asterisk_vault::Result<()> {
+            Err(asterisk_vault::VaultError::StorageError(
+                "disk full".to_string(),
+            ))
+        }
+        fn get(&self, key: &str) -> asterisk_vault::Result<Option<VaultItem>> {
+            self.0.get(key)
+        }
+        fn list(&self) -> asterisk_vault::Result<Vec<VaultItem>> {
+            self.0.list()
+        }
+        fn delete(&mut self, key: &str) -> asterisk_vault::Result<()> {
+            self.0.delete(key)
+        }
+        fn clear(&mut self) -> asterisk_vault::Result<()> {
+            self.0.clear()
+        }
+    }
+
+    #[test]
+    fn test_failed_apply_changes_nothing_and_keeps_token() {
+        let items = vec![item("a", "old"), item("b", "old")];
+        let mut vault = FailingWrites(InMemoryStore::with_items(items.clone()));
+        let mut store = FindReplaceStore::new();
+        let preview = store
+            .preview(&vault.list().unwrap(), "old", "new", &literal())
+            .unwrap();
+
+        let err = store
+            .apply(&preview.token, &mut vault, &history())
+            .unwrap_err();
+        assert!(err.contains("disk full"), "{}", err);
+        assert_eq!(vault.get("a").unwrap().unwrap().value, "old");
+        assert_eq!(vault.get("b").unwrap().unwrap().value, "old");
+
+        // Retried against a working vault, the same token applies
+        let mut vault = InMemoryStore::with_items(items);
+        let result = store.apply(&preview.token, &mut vault, &history()).unwrap();
+        assert_eq!(result.applied.len(), 2);
+        assert_eq!(vault.get("b").unwrap().unwrap().value, "new");
+    }
+
+    #[test]
+    fn test_oldest_previews_are_dropped() {
+        let vault = InMemoryStore::with_items(vec![item("a", "old")]);
+        let mut store = FindReplaceStore::new();
+        let tokens: Vec<String> = (0..=MAX_PREVIEWS)
+            .map(|_| {
+                store
+                    .preview(&vault.list().unwrap(), "old", "new", &literal())
+                    .unwrap()
+                    .token
+            })
+            .collect();
+        assert_eq!(store.previews.len(), MAX_PREVIEWS);
+        assert!(store.previews.iter().all(|(token, _)| *token != tokens[0]));
+        assert_eq!(store.previews.last().unwrap().0, tokens[MAX_PREVIEWS]);
+    }
+
+    #[test]
+    fn test_json_values_replace_leaves_only() {
+        let mut store = FindReplaceStore::new();
+        let items = vec![item("profile", r#"{"name":"name","tags":["name","x"]}"#)];
+        let preview = store.preview(&items, "name", "title", &literal()).unwrap();
+
+        let after: serde_json::Value = serde_json::from_str(&preview.diffs[0].after).unwrap();
+        assert_eq!(
+            after,
+            serde_json::json!({"name": "title", "tags": ["title", "x"]})
+        );
+        assert_eq!(preview.total_matches, 2);
+    }
+
+    #[test]
+    fn test_sensitive_diffs_are_masked() {
+        let mut store = FindReplaceStore::new();
+        let mut secret = item("ssn", "123-45-6789");
+        secret.sensitive = true;
+        let preview = store.preview(&[secret], "123", "999", &literal()).unwrap();
+        assert_eq!(preview.diffs[0].before, "••••••");
+        assert_eq!(preview.diffs[0].after, "••••••");
+    }
+}
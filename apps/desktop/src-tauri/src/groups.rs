@@ -0,0 +1,414 @@
+/*!
+ * Fill Command Groups
+ *
+ * Filling near-identical forms on several sites (one vendor onboarding form
+ * per supplier portal) means one fill command per site. A group ties such
+ * commands together under one `groupId` so the UI can follow their combined
+ * progress and cancel what is left in one go.
+ *
+ * Grouping is a desktop-side notion only: each member is queued, delivered
+ * and acknowledged by the extension like any other command. The bridge
+ * reports deliveries (a poll returned the command) and acknowledgements
+ * (the extension deleted it after filling), and a member's state is
+ * combined from those with its expiry:
+ * - `applied` once acknowledged, whatever else happened
+ * - `cancelled` if its group was cancelled before then
+ * - `expired` once its `expiresAt` has passed
+ * - `delivered` once a poll has returned it
+ * - `pending` otherwise, including while it awaits consent
+ *
+ * The group expires with its last member. Groups are kept in memory and
+ * forgotten `GROUP_RETENTION` after they expire.
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::fill::BatchResultJson;
+use crate::FillCommandJson;
+
+/// How long a group's status stays available after it expires
+pub const GROUP_RETENTION: Duration = Duration::hours(1);
+
+/// Where one member of a group is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemberState {
+    Pending,
+    Delivered,
+    Applied,
+    Expired,
+    Cancelled,
+}
+
+/// Where a group as a whole is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupState {
+    /// Some member may still be filled
+    InProgress,
+    /// Every member was applied
+    Complete,
+    /// Nothing left to fill, but not every member was applied
+    Expired,
+    Cancelled,
+}
+
+/// One member of a group and its state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupMemberJson {
+    #[serde(rename = "commandId")]
+    pub command_id: String,
+    #[serde(rename = "targetDomain")]
+    pub target_domain: String,
+    pub state: MemberState,
+}
+
+/// Result of `fill_command_group_status`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupStatusJson {
+    #[serde(rename = "groupId")]
+    pub group_id: String,
+    pub state: GroupState,
+    /// When the last member expires (ISO 8601)
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+    pub members: Vec<GroupMemberJson>,
+    pub pending: usize,
+    pub delivered: usize,
+    pub applied: usize,
+    pub expired: usize,
+    pub cancelled: usize,
+}
+
+/// Result of `fill_command_create_group`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupCreatedJson {
+    #[serde(rename = "groupId")]
+    pub group_id: String,
+    /// One result per member, in the order given
+    pub results: Vec<BatchResultJson>,
+}
+
+#[derive(Debug)]
+struct Member {
+    command_id: String,
+    target_domain: String,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+struct Group {
+    members: Vec<Member>,
+    cancelled: bool,
+}
+
+impl Group {
+    /// When the last member expires; `None` if none has a valid expiry
+    fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.members.iter().filter_map(|m| m.expires_at).max()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Groups {
+    groups: HashMap<String, Group>,
+    /// Group of each member command
+    member_of: HashMap<String, String>,
+    delivered: HashSet<String>,
+    applied: HashSet<String>,
+    next_id: u64,
+}
+
+/// Every live group and what the bridge has seen of its members
+#[derive(Debug, Default)]
+pub struct FillCommandGroups {
+    inner: Mutex<Groups>,
+}
+
+impl FillCommandGroups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn inner(&self) -> MutexGuard<'_, Groups> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Start a group of `commands`; returns its id
+    ///
+    /// Also forgets groups that expired more than `GROUP_RETENTION` ago.
+    pub fn create(&self, commands: &[FillCommandJson], now: DateTime<Utc>) -> String {
+        let mut inner = self.inner();
+        inner.forget_expired(now);
+
+        inner.next_id += 1;
+        let group_id = format!("grp-{}-{}", now.timestamp_millis(), inner.next_id);
+        let members: Vec<Member> = commands
+            .iter()
+            .map(|c| Member {
+                command_id: c.id.clone(),
+                target_domain: c.target_domain.clone(),
+                expires_at: DateTime::parse_from_rfc3339(&c.expires_at)
+                    .ok()
+                    .map(|t| t.with_timezone(&Utc)),
+            })
+            .collect();
+        for member in &members {
+            inner
+                .member_of
+                .insert(member.command_id.clone(), group_id.clone());
+        }
+        inner.groups.insert(
+            group_id.clone(),
+            Group {
+                members,
+                cancelled: false,
+            },
+        );
+        group_id
+    }
+
+    /// Note that a poll returned these commands; others are ignored
+    pub fn mark_delivered<'a>(&self, command_ids: impl IntoIterator<Item = &'a str>) {
+        let mut inner = self.inner();
+        for id in command_ids {
+            if inner.member_of.contains_key(id) {
+                inner.delivered.insert(id.to_string());
+            }
+        }
+    }
+
+    /// Note that the extension acknowledged filling this command
+    pub fn mark_applied(&self, command_id: &str) {
+        let mut inner = self.inner();
+        if inner.member_of.contains_key(command_id) {
+            inner.applied.insert(command_id.to_string());
+        }
+    }
+
+    /// Cancel a group; returns the members not yet applied, which the
+    /// caller removes from the fill command queue and consent hold
+    pub fn cancel(&self, group_id: &str) -> Result<Vec<String>, String> {
+        let mut inner = self.inner();
+        let Groups {
+            groups, applied, ..
+        } = &mut *inner;
+        let group = groups
+            .get_mut(group_id)
+            .ok_or_else(|| format!("Fill command group {} not found", group_id))?;
+        group.cancelled = true;
+        Ok(group
+            .members
+            .iter()
+            .filter(|m| !applied.contains(&m.command_id))
+            .map(|m| m.command_id.clone())
+            .collect())
+    }
+
+    /// Every member's state and the group's, as of `now`
+    pub fn status(&self, group_id: &str, now: DateTime<Utc>) -> Result<GroupStatusJson, String> {
+        let inner = self.inner();
+        let group = inner
+            .groups
+            .get(group_id)
+            .ok_or_else(|| format!("Fill command group {} not found", group_id))?;
+
+        let members: Vec<GroupMemberJson> = group
+            .members
+            .iter()
+            .map(|m| GroupMemberJson {
+                command_id: m.command_id.clone(),
+                target_domain: m.target_domain.clone(),
+                state: inner.member_state(group, m, now),
+            })
+            .collect();
+        let count = |state: MemberState| members.iter().filter(|m| m.state == state).count();
+        let (pending, delivered, applied) = (
+            count(MemberState::Pending),
+            count(MemberState::Delivered),
+            count(MemberState::Applied),
+        );
+
+        let state = if applied == members.len() {
+            GroupState::Complete
+        } else if group.cancelled {
+            GroupState::Cancelled
+        } else if pending + delivered > 0 {
+            GroupState::InProgress
+        } else {
+            GroupState::Expired
+        };
+
+        Ok(GroupStatusJson {
+            group_id: group_id.to_string(),
+            state,
+            expires_at: group
+                .expires_at()
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default(),
+            pending,
+            delivered,
+            applied,
+            expired: count(MemberState::Expired),
+            cancelled: count(MemberState::Cancelled),
+            members,
+        })
+    }
+}
+
+impl Groups {
+    fn member_state(&self, group: &Group, member: &Member, now: DateTime<Utc>) -> MemberState {
+        if self.applied.contains(&member.command_id) {
+            MemberState::Applied
+        } else if group.cancelled {
+            MemberState::Cancelled
+        } else if member.expires_at.is_none_or(|t| t <= now) {
+            MemberState::Expired
+        } else if self.delivered.contains(&member.command_id) {
+            MemberState::Delivered
+        } else {
+            MemberState::Pending
+        }
+    }
+
+    fn forget_expired(&mut self, now: DateTime<Utc>) {
+        let stale: Vec<String> = self
+            .groups
+            .iter()
+            .filter(|(_, g)| g.expires_at().is_none_or(|t| t + GROUP_RETENTION <= now))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in stale {
+            let Some(group) = self.groups.remove(&id) else {
+                continue;
+            };
+            for member in group.members {
+                self.member_of.remove(&member.command_id);
+                self.delivered.remove(&member.command_id);
+                self.applied.remove(&member.command_id);
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(id: &str, domain: &str, expires_at: DateTime<Utc>) -> FillCommandJson {
+        FillCommandJson {
+            id: id.to_string(),
+            target_domain: domain.to_string(),
+            target_url: None,
+            fills: Vec::new(),
+            created_at: Utc::now().to_rfc3339(),
+            expires_at: expires_at.to_rfc3339(),
+            capture_undo: false,
+            plan_hash: None,
+        }
+    }
+
+    fn states(status: &GroupStatusJson) -> Vec<MemberState> {
+        status.members.iter().map(|m| m.state).collect()
+    }
+
+    #[test]
+    fn test_partially_completed_group() {
+        let now = Utc::now();
+        let groups = FillCommandGroups::new();
+        let id = groups.create(
+            &[
+                command("a", "one.example", now + Duration::minutes(5)),
+                command("b", "two.example", now + Duration::minutes(5)),
+                command("c", "three.example", now + Duration::minutes(10)),
+            ],
+            now,
+        );
+
+        groups.mark_delivered(["a", "b", "unrelated"]);
+        groups.mark_applied("a");
+        let status = groups.status(&id, now).unwrap();
+        assert_eq!(status.state, GroupState::InProgress);
+        assert_eq!(
+            states(&status),
+            [
+                MemberState::Applied,
+                MemberState::Delivered,
+                MemberState::Pending
+            ]
+        );
+        assert_eq!(
+            (status.pending, status.delivered, status.applied),
+            (1, 1, 1)
+        );
+        assert_eq!(
+            status.expires_at,
+            (now + Duration::minutes(10)).to_rfc3339()
+        );
+
+        groups.mark_applied("b");
+        groups.mark_applied("c");
+        assert_eq!(groups.status(&id, now).unwrap().state, GroupState::Complete);
+        assert!(groups.status("grp-missing", now).is_err());
+    }
+
+    #[test]
+    fn test_group_expires_with_its_last_member() {
+        let now = Utc::now();
+        let groups = FillCommandGroups::new();
+        let id = groups.create(
+            &[
+                command("a", "one.example", now + Duration::minutes(5)),
+                command("b", "two.example", now + Duration::minutes(10)),
+            ],
+            now,
+        );
+        groups.mark_applied("a");
+
+        // One member left in time, so the group is still going
+        let later = now + Duration::minutes(7);
+        let status = groups.status(&id, later).unwrap();
+        assert_eq!(status.state, GroupState::InProgress);
+
+        let status = groups.status(&id, now + Duration::minutes(10)).unwrap();
+        assert_eq!(status.state, GroupState::Expired);
+        assert_eq!(
+            states(&status),
+            [MemberState::Applied, MemberState::Expired]
+        );
+
+        // Forgotten once the retention window after expiry has passed
+        groups.create(&[], now + Duration::minutes(10) + GROUP_RETENTION);
+        assert!(groups.status(&id, now).is_err());
+    }
+
+    #[test]
+    fn test_cancel_returns_unapplied_members() {
+        let now = Utc::now();
+        let groups = FillCommandGroups::new();
+        let id = groups.create(
+            &[
+                command("a", "one.example", now + Duration::minutes(5)),
+                command("b", "two.example", now + Duration::minutes(5)),
+            ],
+            now,
+        );
+        groups.mark_applied("a");
+        assert_eq!(groups.cancel(&id).unwrap(), ["b"]);
+
+        let status = groups.status(&id, now).unwrap();
+        assert_eq!(status.state, GroupState::Cancelled);
+        assert_eq!(
+            states(&status),
+            [MemberState::Applied, MemberState::Cancelled]
+        );
+        assert!(groups.cancel("grp-missing").is_err());
+    }
+}
@@ -0,0 +1,221 @@
+/*!
+ * Vault Change History
+ *
+ * The audit log records fills, not edits. This log records vault mutations
+ * (create/update/delete) to `vault-history.jsonl` so "when did I last change
+ * my address?" has an answer. Values are stored redacted only.
+ */
+
+use asterisk_vault::VaultItem;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::redact::redact_value;
+
+/// Kind of vault mutation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeAction {
+    Create,
+    Update,
+    Delete,
+}
+
+/// A single change to a vault item
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VaultChangeJson {
+    pub key: String,
+    pub action: ChangeAction,
+    /// ISO timestamp of the change
+    #[serde(rename = "changedAt")]
+    pub changed_at: String,
+    /// Redacted value after the change (absent for deletes)
+    #[serde(rename = "valueRedacted", skip_serializing_if = "Option::is_none")]
+    pub value_redacted: Option<String>,
+    /// Where the change came from (e.g. "vault_set", "bridge")
+    pub source: String,
+}
+
+/// Append-only JSONL log of vault mutations
+#[derive(Debug)]
+pub struct VaultHistory {
+    path: PathBuf,
+    enabled: AtomicBool,
+}
+
+impl VaultHistory {
+    /// Create an enabled history log writing to `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            enabled: AtomicBool::new(true),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Record that `item` was written; `existed` picks create vs update
+    pub fn record_set(&self, item: &VaultItem, existed: bool, source: &str) -> Result<(), String> {
+        let action = if existed {
+            ChangeAction::Update
+        } else {
+            ChangeAction::Create
+        };
+        let (value_redacted, _) = redact_value(&item.value, item.sensitive);
+        self.append(VaultChangeJson {
+            key: item.key.clone(),
+            action,
+            changed_at: chrono::Utc::now().to_rfc3339(),
+            value_redacted: Some(value_redacted),
+            source: source.to_string(),
+        })
+    }
+
+    /// Record that `key` was deleted
+    pub fn record_delete(&self, key: &str, source: &str) -> Result<(), String> {
+        self.append(VaultChangeJson {
+            key: key.to_string(),
+            action: ChangeAction::Delete,
+            changed_at: chrono::Utc::now().to_rfc3339(),
+            value_redacted: None,
+            source: source.to_string(),
+        })
+    }
+
+    /// Change events for `key`, oldest first
+    pub fn for_key(&self, key: &str) -> Result<Vec<VaultChangeJson>, String> {
+        let file = match fs::File::open(&self.path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to open vault history: {}", e)),
+        };
+
+        let mut events = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<VaultChangeJson>(&line) {
+                Ok(event) if event.key == key => events.push(event),
+                Ok(_) => {}
+                Err(e) => eprintln!("[Asterisk History] Skipping malformed record: {}", e),
+            }
+        }
+        Ok(events)
+    }
+
+    fn append(&self, event: VaultChangeJson) -> Result<(), String> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create history directory: {}", e))?;
+        }
+        let line = serde_json::to_string(&event)
+            .map_err(|e| format!("Failed to serialize change: {}", e))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("Failed to open vault history: {}", e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Failed to write change: {}", e))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asterisk_vault::{test_util, VaultCategory};
+
+    fn item(key: &str, value: &str) -> VaultItem {
+        test_util::item(key, value, VaultCategory::Address)
+    }
+
+    fn history(name: &str) -> VaultHistory {
+        let path = std::env::temp_dir()
+            .join(format!("asterisk-history-{}-{}", name, std::process::id()))
+            .join("vault-history.jsonl");
+        let _ = fs::remove_file(&path);
+        VaultHistory::new(path)
+    }
+
+    #[test]
+    fn test_updates_recorded_in_order() {
+        let history = history("updates");
+        history
+            .record_set(&item("street", "1 Old Road"), false, "vault_set")
+            .unwrap();
+        history
+            .record_set(&item("street", "22 New Street"), true, "vault_set")
+            .unwrap();
+        history
+            .record_set(&item("street", "333 Final Avenue"), true, "vault_set")
+            .unwrap();
+        history
+            .record_set(&item("city", "Springfield"), false, "vault_set")
+            .unwrap();
+
+        let events = history.for_key("street").unwrap();
+        let actions: Vec<ChangeAction> = events.iter().map(|e| e.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                ChangeAction::Create,
+                ChangeAction::Update,
+                ChangeAction::Update
+            ]
+        );
+        assert_eq!(
+            events[2].value_redacted.as_deref(),
+            Some("33••••••••••••ue")
+        );
+        assert!(events[1].changed_at <= events[2].changed_at);
+    }
+
+    #[test]
+    fn test_delete_recorded() {
+        let history = history("delete");
+        history
+            .record_set(&item("street", "1 Old Road"), false, "vault_set")
+            .unwrap();
+        history.record_delete("street", "vault_delete").unwrap();
+
+        let events = history.for_key("street").unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].action, ChangeAction::Delete);
+        assert!(events[1].value_redacted.is_none());
+    }
+
+    #[test]
+    fn test_values_never_stored_in_clear() {
+        let history = history("redacted");
+        history
+            .record_set(&item("street", "1 Old Road"), false, "vault_set")
+            .unwrap();
+        let raw = fs::read_to_string(&history.path).unwrap();
+        assert!(!raw.contains("Old Road"));
+    }
+
+    #[test]
+    fn test_disabled_history_records_nothing() {
+        let history = history("disabled");
+        history.set_enabled(false);
+        history.record_delete("street", "vault_delete").unwrap();
+        assert!(history.for_key("street").unwrap().is_empty());
+    }
+}
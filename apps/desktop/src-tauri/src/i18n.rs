@@ -0,0 +1,367 @@
+/*!
+ * Message Catalog
+ *
+ * User-facing strings the backend generates (command errors, match reasons,
+ * validation problems) are built as a `Message`: a stable id plus named
+ * params. The text lives in per-locale catalogs under `src/locales/`, one
+ * `id = text` line per message with `{ $param }` placeholders, a single-line
+ * subset of Fluent so the files can move to a full Fluent runtime later.
+ *
+ * Responses carry `MessageJson`: the id and params, so a client can render
+ * the text itself, and `message`, the text rendered in the configured
+ * locale for clients that don't. A message missing from the locale's
+ * catalog falls back to English, then to its id.
+ *
+ * The locale is process-wide. It starts from `ASTERISK_LOCALE` ("de",
+ * "de-DE", "de_DE.UTF-8" all select German) and can be changed at runtime
+ * with `locale_set`; anything unrecognised means English.
+ */
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+/// Environment variable selecting the initial locale
+pub const LOCALE_ENV: &str = "ASTERISK_LOCALE";
+
+/// A locale with a bundled catalog
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+}
+
+impl Locale {
+    /// Every bundled locale, English (the fallback) first
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::De];
+
+    /// Pick a locale from a language tag ("de-DE", "de_DE.UTF-8", "EN")
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag
+            .trim()
+            .split(['-', '_', '.'])
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        match language.as_str() {
+            "en" => Some(Locale::En),
+            "de" => Some(Locale::De),
+            _ => None,
+        }
+    }
+
+    fn catalog_source(self) -> &'static str {
+        match self {
+            Locale::En => include_str!("locales/en.ftl"),
+            Locale::De => include_str!("locales/de.ftl"),
+        }
+    }
+
+    fn catalog(self) -> &'static HashMap<&'static str, &'static str> {
+        static EN: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
+        static DE: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
+        let cell = match self {
+            Locale::En => &EN,
+            Locale::De => &DE,
+        };
+        cell.get_or_init(|| parse_catalog(self.catalog_source()))
+    }
+}
+
+/// Parse `id = text` lines, skipping blanks and `#` comments
+fn parse_catalog(source: &'static str) -> HashMap<&'static str, &'static str> {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(id, text)| (id.trim(), text.trim()))
+        .collect()
+}
+
+// ============================================================================
+// Current Locale
+// ============================================================================
+
+/// 0 until first read, then 1 + the index of the locale in `Locale::ALL`
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+/// The locale messages are rendered in
+pub fn current_locale() -> Locale {
+    match CURRENT.load(Ordering::Relaxed) {
+        0 => {
+            let locale = std::env::var(LOCALE_ENV)
+                .ok()
+                .and_then(|tag| Locale::from_tag(&tag))
+                .unwrap_or_default();
+            set_locale(locale);
+            locale
+        }
+        n => Locale::ALL[usize::from(n - 1)],
+    }
+}
+
+/// Render messages in `locale` from now on
+pub fn set_locale(locale: Locale) {
+    let index = Locale::ALL.iter().position(|l| *l == locale).unwrap_or(0);
+    CURRENT.store(index as u8 + 1, Ordering::Relaxed);
+}
+
+// ============================================================================
+// Messages
+// ============================================================================
+
+/// Declare the message ids the backend uses, and `ALL_IDS` listing them
+macro_rules! message_ids {
+    ($($name:ident => $id:literal),* $(,)?) => {
+        $(pub const $name: &str = $id;)*
+
+        /// Every message id referenced from code
+        #[cfg(test)]
+        pub const ALL_IDS: &[&str] = &[$($id),*];
+    };
+}
+
+pub mod ids {
+    message_ids! {
+        COMMAND_FAILED => "command-failed",
+        FEATURE_DISABLED => "feature-disabled",
+
+        MATCH_AUTOCOMPLETE => "match-autocomplete",
+        MATCH_LABEL => "match-label",
+        MATCH_ARIA_LABEL => "match-aria-label",
+        MATCH_PLACEHOLDER => "match-placeholder",
+        MATCH_DESCRIBED_BY => "match-described-by",
+        MATCH_NEARBY_TEXT => "match-nearby-text",
+        MATCH_NAME => "match-name",
+        MATCH_OVERRIDE => "match-override",
+        MATCH_TEMPLATE => "match-template",
+        MATCH_COMPOSED_ADDRESS => "match-composed-address",
+        MATCH_SESSION_STEP => "match-session-step",
+        MATCH_SESSION => "match-session",
+
+        COMMAND_ID_EMPTY => "command-id-empty",
+        COMMAND_DOMAIN_EMPTY => "command-domain-empty",
+        COMMAND_NO_FILLS => "command-no-fills",
+        COMMAND_DUPLICATE_FILL => "command-duplicate-fill",
+        COMMAND_EXPIRY_ORDER => "command-expiry-order",
+        COMMAND_INVALID_CREATED_AT => "command-invalid-created-at",
+        COMMAND_INVALID_EXPIRES_AT => "command-invalid-expires-at",
+        BATCH_EMPTY => "batch-empty",
+        BATCH_DUPLICATE_ID => "batch-duplicate-id",
+        FIELD_PROBLEM => "field-problem",
+        VALUE_TOO_LONG => "value-too-long",
+        VALUE_TRUNCATED => "value-truncated",
+        VALUE_PATTERN_MISMATCH => "value-pattern-mismatch",
+        VALUE_TYPE_MISMATCH => "value-type-mismatch",
+    }
+}
+
+/// A user-facing message: a catalog id and its params
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub id: &'static str,
+    pub params: Vec<(&'static str, String)>,
+}
+
+impl Message {
+    pub fn new(id: &'static str) -> Self {
+        Message {
+            id,
+            params: Vec::new(),
+        }
+    }
+
+    /// Add a param substituted for `{ $name }`
+    pub fn with(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.params.push((name, value.to_string()));
+        self
+    }
+
+    /// Render in `locale`, falling back to English and then to the id
+    ///
+    /// A placeholder without a matching param is left in place.
+    pub fn render_in(&self, locale: Locale) -> String {
+        render(self.id, locale, |name| {
+            self.params
+                .iter()
+                .find(|(param, _)| *param == name)
+                .map(|(_, value)| value.as_str())
+        })
+    }
+
+    /// Render in the current locale
+    pub fn render(&self) -> String {
+        self.render_in(current_locale())
+    }
+
+    pub fn to_json(&self) -> MessageJson {
+        MessageJson {
+            message_id: self.id.to_string(),
+            params: self
+                .params
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+            message: self.render(),
+        }
+    }
+}
+
+impl std::fmt::Display for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render())
+    }
+}
+
+/// Look up `id` in `locale` (then English) and fill in its placeholders
+fn render<'a>(id: &str, locale: Locale, lookup: impl Fn(&str) -> Option<&'a str>) -> String {
+    let template = locale
+        .catalog()
+        .get(id)
+        .or_else(|| Locale::En.catalog().get(id))
+        .copied()
+        .unwrap_or(id);
+    substitute(template, lookup)
+}
+
+/// Replace each `{ $name }` in `template` with `lookup(name)`
+fn substitute<'a>(template: &str, mut lookup: impl FnMut(&str) -> Option<&'a str>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let placeholder = &rest[start..=start + len];
+        let name = placeholder[1..placeholder.len() - 1].trim();
+        match name.strip_prefix('$').and_then(|name| lookup(name.trim())) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(placeholder),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// A message as sent to clients
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageJson {
+    #[serde(rename = "messageId")]
+    pub message_id: String,
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+    /// Rendered in the configured locale
+    pub message: String,
+}
+
+impl MessageJson {
+    /// Render again in another locale, from the id and params
+    pub fn render_in(&self, locale: Locale) -> String {
+        render(&self.message_id, locale, |name| {
+            self.params.get(name).map(String::as_str)
+        })
+    }
+}
+
+impl From<Message> for MessageJson {
+    fn from(message: Message) -> Self {
+        message.to_json()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Placeholder names used by a catalog entry
+    fn placeholders(text: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        substitute(text, |name| {
+            names.push(name.to_string());
+            None
+        });
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_every_referenced_id_is_in_the_english_catalog() {
+        let en = Locale::En.catalog();
+        for id in ids::ALL_IDS {
+            assert!(en.contains_key(id), "{} missing from en.ftl", id);
+        }
+    }
+
+    #[test]
+    fn test_translations_use_the_english_placeholders() {
+        let en = Locale::En.catalog();
+        for locale in Locale::ALL {
+            for (id, text) in locale.catalog() {
+                let english = en.get(id);
+                assert!(
+                    english.is_some(),
+                    "{:?} has {} but en.ftl doesn't",
+                    locale,
+                    id
+                );
+                assert_eq!(placeholders(text), placeholders(english.unwrap()), "{}", id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_params_round_trip() {
+        let message = Message::new(ids::VALUE_TOO_LONG)
+            .with("length", 12)
+            .with("max", 10);
+        assert_eq!(
+            message.render_in(Locale::En),
+            "Value is 12 characters but the field allows at most 10"
+        );
+        assert_eq!(
+            message.render_in(Locale::De),
+            "Der Wert hat 12 Zeichen, das Feld erlaubt höchstens 10"
+        );
+
+        let json = serde_json::to_string(&message.to_json()).unwrap();
+        let json: MessageJson = serde_json::from_str(&json).unwrap();
+        assert_eq!(json.message_id, "value-too-long");
+        assert_eq!(json.params["length"], "12");
+        assert_eq!(json.message, message.render());
+        for locale in Locale::ALL {
+            assert_eq!(json.render_in(locale), message.render_in(locale));
+        }
+    }
+
+    #[test]
+    fn test_missing_entries_and_params_fall_back() {
+        assert_eq!(
+            Message::new("no-such-message").render_in(Locale::De),
+            "no-such-message"
+        );
+        assert_eq!(
+            substitute("at most { $max } of { $total }", |name| (name == "max")
+                .then_some("3")),
+            "at most 3 of { $total }"
+        );
+    }
+
+    #[test]
+    fn test_locale_from_tag() {
+        assert_eq!(Locale::from_tag("de"), Some(Locale::De));
+        assert_eq!(Locale::from_tag("de_DE.UTF-8"), Some(Locale::De));
+        assert_eq!(Locale::from_tag(" EN-gb "), Some(Locale::En));
+        assert_eq!(Locale::from_tag("fr"), None);
+        assert_eq!(Locale::from_tag(""), None);
+    }
+}
@@ -0,0 +1,582 @@
+/*!
+ * CSV Import Column Analysis
+ *
+ * Backs the import mapping wizard: reads a CSV file's header and a sample of
+ * its rows and suggests, for each column, which vault keys it could map to.
+ * Suggestions combine the matcher's name rules applied to the header (see
+ * `matching::keys_for_name`) with the shape of the sampled values (emails,
+ * phone numbers, postal codes, dates). Columns whose values look like
+ * sensitive data (Luhn-valid card numbers, SSNs, IBANs; see `pii`) are
+ * flagged so the wizard leaves them out unless the user opts in.
+ *
+ * Only the first `MAX_SAMPLE_ROWS` rows are read, and each record is capped
+ * at `MAX_RECORD_BYTES`, so memory stays bounded however large the file is.
+ * No cell values are returned.
+ */
+
+use asterisk_vault::VaultCategory;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, ErrorKind};
+
+use crate::matching;
+use crate::pii;
+
+/// Rows sampled after the header
+pub const MAX_SAMPLE_ROWS: usize = 200;
+
+/// Bytes kept per record; the rest of an oversized record is skipped
+pub const MAX_RECORD_BYTES: usize = 64 * 1024;
+
+/// Share of a column's non-empty sampled cells that must have a shape for
+/// the column to be given that shape
+const SHAPE_THRESHOLD: f64 = 0.8;
+
+/// Confidence of a key suggested by value shape alone, for a column where
+/// every sampled cell has the shape
+const SHAPE_CONFIDENCE: f64 = 0.75;
+
+/// Added when the header and the value shape suggest the same key
+const AGREEMENT_BONUS: f64 = 0.1;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// What a cell value looks like
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueShape {
+    Email,
+    Phone,
+    PostalCode,
+    Date,
+    CardNumber,
+}
+
+impl ValueShape {
+    fn description(self) -> &'static str {
+        match self {
+            ValueShape::Email => "email addresses",
+            ValueShape::Phone => "phone numbers",
+            ValueShape::PostalCode => "postal codes",
+            ValueShape::Date => "dates",
+            ValueShape::CardNumber => "card numbers",
+        }
+    }
+
+    /// The vault key a column of this shape most likely holds
+    fn suggested_key(self) -> (&'static str, VaultCategory) {
+        match self {
+            ValueShape::Email => ("email", VaultCategory::Contact),
+            ValueShape::Phone => ("phone", VaultCategory::Contact),
+            ValueShape::PostalCode => ("zip", VaultCategory::Address),
+            ValueShape::Date => ("birthday", VaultCategory::Identity),
+            ValueShape::CardNumber => ("cardNumber", VaultCategory::Financial),
+        }
+    }
+}
+
+/// A vault key a column could map to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeyCandidateJson {
+    pub key: String,
+    pub category: VaultCategory,
+    pub confidence: f64,
+    /// Why the key was suggested
+    pub reason: String,
+}
+
+/// Mapping suggestions for one column
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ColumnAnalysisJson {
+    pub index: usize,
+    pub header: String,
+    /// Best first
+    pub candidates: Vec<KeyCandidateJson>,
+    /// Shape shared by most of the sampled values, if any
+    pub shape: Option<ValueShape>,
+    /// What sensitive data the sampled values look like, if any
+    #[serde(rename = "sensitiveKind", skip_serializing_if = "Option::is_none")]
+    pub sensitive_kind: Option<pii::PiiKind>,
+    /// Leave the column out of the import unless the user opts in
+    #[serde(rename = "excludeByDefault")]
+    pub exclude_by_default: bool,
+    /// Non-empty cells sampled
+    #[serde(rename = "sampledValues")]
+    pub sampled_values: usize,
+}
+
+/// Result of `analyze_columns`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ColumnsAnalysisJson {
+    pub columns: Vec<ColumnAnalysisJson>,
+    #[serde(rename = "sampledRows")]
+    pub sampled_rows: usize,
+    /// The file has more rows than were sampled
+    pub truncated: bool,
+}
+
+// ============================================================================
+// Value Shapes
+// ============================================================================
+
+fn is_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !value.chars().any(char::is_whitespace)
+        && !domain.contains('@')
+        && domain
+            .split_once('.')
+            .is_some_and(|(host, tld)| !host.is_empty() && tld.len() >= 2 && !tld.ends_with('.'))
+}
+
+/// 7-15 digits, optionally with a leading +, spaces, dashes, dots and
+/// parentheses
+fn is_phone(value: &str) -> bool {
+    let value = value.strip_prefix('+').unwrap_or(value);
+    let mut digits = 0;
+    for c in value.chars() {
+        match c {
+            '0'..='9' => digits += 1,
+            ' ' | '-' | '.' | '(' | ')' => {}
+            _ => return false,
+        }
+    }
+    (7..=15).contains(&digits)
+}
+
+/// US ZIP (12345, 12345-6789), UK postcode (SW1A 1AA) or Canadian postal
+/// code (K1A 0B1)
+fn is_postal_code(value: &str) -> bool {
+    let upper = value.to_ascii_uppercase();
+    let bytes = upper.as_bytes();
+    let digits = |s: &[u8]| s.iter().all(u8::is_ascii_digit);
+
+    let us = match bytes.len() {
+        5 => digits(bytes),
+        10 => digits(&bytes[..5]) && bytes[5] == b'-' && digits(&bytes[6..]),
+        _ => false,
+    };
+    let canadian = {
+        let compact: Vec<u8> = bytes.iter().copied().filter(|b| *b != b' ').collect();
+        compact.len() == 6
+            && compact.iter().enumerate().all(|(i, b)| {
+                if i % 2 == 0 {
+                    b.is_ascii_alphabetic()
+                } else {
+                    b.is_ascii_digit()
+                }
+            })
+    };
+    let uk = upper.split_once(' ').is_some_and(|(outward, inward)| {
+        let outward = outward.as_bytes();
+        let inward = inward.as_bytes();
+        (2..=4).contains(&outward.len())
+            && outward[0].is_ascii_alphabetic()
+            && outward.iter().all(u8::is_ascii_alphanumeric)
+            && outward.iter().any(u8::is_ascii_digit)
+            && inward.len() == 3
+            && inward[0].is_ascii_digit()
+            && inward[1..].iter().all(u8::is_ascii_alphabetic)
+    });
+    us || canadian || uk
+}
+
+fn is_date(value: &str) -> bool {
+    ["%Y-%m-%d", "%m/%d/%Y", "%d.%m.%Y", "%Y/%m/%d"]
+        .iter()
+        .any(|format| chrono::NaiveDate::parse_from_str(value, format).is_ok())
+}
+
+/// The shape of one value, if it has one
+///
+/// Card numbers are checked first: a 16-digit card number also passes as a
+/// digit string, and it matters more.
+pub fn detect_shape(value: &str) -> Option<ValueShape> {
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else if pii::detect(value) == Some(pii::PiiKind::CreditCard) {
+        Some(ValueShape::CardNumber)
+    } else if is_email(value) {
+        Some(ValueShape::Email)
+    } else if is_date(value) {
+        Some(ValueShape::Date)
+    } else if is_postal_code(value) {
+        Some(ValueShape::PostalCode)
+    } else if is_phone(value) {
+        Some(ValueShape::Phone)
+    } else {
+        None
+    }
+}
+
+// ============================================================================
+// CSV Sampling
+// ============================================================================
+
+/// Read one CSV record (RFC 4180 quoting, embedded newlines allowed)
+///
+/// At most `MAX_RECORD_BYTES` of the record are kept; the rest is read and
+/// dropped. Returns None at end of input.
+fn read_record(reader: &mut impl BufRead) -> Result<Option<Vec<String>>, String> {
+    let mut fields = Vec::new();
+    let mut field = Vec::new();
+    let mut kept = 0usize;
+    let mut in_quotes = false;
+    let mut quote_pending = false;
+    let mut read_any = false;
+
+    loop {
+        let buf = match reader.fill_buf() {
+            Ok(buf) => buf,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(format!("Failed to read import: {}", e)),
+        };
+        if buf.is_empty() {
+            break;
+        }
+        read_any = true;
+
+        let mut consumed = 0;
+        let mut done = false;
+        for &b in buf {
+            consumed += 1;
+            if quote_pending {
+                quote_pending = false;
+                if b == b'"' {
+                    // Doubled quote inside a quoted field
+                    if kept < MAX_RECORD_BYTES {
+                        field.push(b);
+                        kept += 1;
+                    }
+                    continue;
+                }
+                in_quotes = false;
+            }
+            match b {
+                b'"' if in_quotes => quote_pending = true,
+                b'"' if field.is_empty() => in_quotes = true,
+                b',' if !in_quotes => {
+                    fields.push(String::from_utf8_lossy(&field).into_owned());
+                    field.clear();
+                }
+                b'\n' if !in_quotes => {
+                    done = true;
+                    break;
+                }
+                b'\r' if !in_quotes => {}
+                _ if kept < MAX_RECORD_BYTES => {
+                    field.push(b);
+                    kept += 1;
+                }
+                _ => {}
+            }
+        }
+        reader.consume(consumed);
+        if done {
+            break;
+        }
+    }
+
+    if !read_any && fields.is_empty() && field.is_empty() {
+        return Ok(None);
+    }
+    fields.push(String::from_utf8_lossy(&field).into_owned());
+    Ok(Some(fields))
+}
+
+/// Per-column tallies over the sampled rows
+#[derive(Default)]
+struct ColumnSample {
+    values: usize,
+    shapes: Vec<(ValueShape, usize)>,
+    sensitive: Vec<(pii::PiiKind, usize)>,
+}
+
+fn tally<T: PartialEq>(counts: &mut Vec<(T, usize)>, value: T) {
+    match counts.iter_mut().find(|(v, _)| *v == value) {
+        Some((_, count)) => *count += 1,
+        None => counts.push((value, 1)),
+    }
+}
+
+impl ColumnSample {
+    fn observe(&mut self, value: &str) {
+        let value = value.trim();
+        if value.is_empty() {
+            return;
+        }
+        self.values += 1;
+        if let Some(shape) = detect_shape(value) {
+            tally(&mut self.shapes, shape);
+        }
+        if let Some(kind) = pii::detect(value) {
+            tally(&mut self.sensitive, kind);
+        }
+    }
+
+    /// The most common shape and the share of values that have it, if that
+    /// share clears `SHAPE_THRESHOLD`
+    fn shape(&self) -> Option<(ValueShape, f64)> {
+        let (shape, count) = self.shapes.iter().max_by_key(|(_, count)| *count)?;
+        let ratio = *count as f64 / self.values as f64;
+        (ratio >= SHAPE_THRESHOLD).then_some((*shape, ratio))
+    }
+
+    /// Any sensitive value at all flags the column
+    fn sensitive_kind(&self) -> Option<pii::PiiKind> {
+        self.sensitive
+            .iter()
+            .max_by_key(|(_, count)| *count)
+            .map(|(kind, _)| *kind)
+    }
+}
+
+/// Candidates for a column from its header and sampled values, best first
+fn candidates(header: &str, shape: Option<(ValueShape, f64)>) -> Vec<KeyCandidateJson> {
+    let mut candidates: Vec<KeyCandidateJson> = matching::keys_for_name(header)
+        .into_iter()
+        .map(|(key, category, confidence)| KeyCandidateJson {
+            key: key.to_string(),
+            category,
+            confidence,
+            reason: format!("Header \"{}\"", header.trim()),
+        })
+        .collect();
+
+    if let Some((shape, ratio)) = shape {
+        let (key, category) = shape.suggested_key();
+        let reason = format!(
+            "{:.0}% of sampled values look like {}",
+            ratio * 100.0,
+            shape.description()
+        );
+        match candidates.iter_mut().find(|c| c.key == key) {
+            Some(candidate) => {
+                candidate.confidence = (candidate.confidence + AGREEMENT_BONUS).min(0.99);
+                candidate.reason = format!("{}; {}", candidate.reason, reason);
+            }
+            None => candidates.push(KeyCandidateJson {
+                key: key.to_string(),
+                category,
+                confidence: SHAPE_CONFIDENCE * ratio,
+                reason,
+            }),
+        }
+    }
+
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    candidates
+}
+
+/// Analyze the columns of a CSV file read from `reader`
+pub fn analyze_columns(mut reader: impl BufRead) -> Result<ColumnsAnalysisJson, String> {
+    let header = read_record(&mut reader)?.ok_or_else(|| "Import file is empty".to_string())?;
+    let mut samples: Vec<ColumnSample> = header.iter().map(|_| ColumnSample::default()).collect();
+
+    let mut sampled_rows = 0;
+    let mut truncated = false;
+    while let Some(row) = read_record(&mut reader)? {
+        if row.iter().all(|cell| cell.trim().is_empty()) {
+            continue;
+        }
+        if sampled_rows == MAX_SAMPLE_ROWS {
+            truncated = true;
+            break;
+        }
+        sampled_rows += 1;
+        for (sample, cell) in samples.iter_mut().zip(&row) {
+            sample.observe(cell);
+        }
+    }
+
+    let columns = header
+        .iter()
+        .zip(&samples)
+        .enumerate()
+        .map(|(index, (header, sample))| {
+            let header = header.trim_start_matches('\u{feff}');
+            let shape = sample.shape();
+            let sensitive_kind = sample.sensitive_kind();
+            ColumnAnalysisJson {
+                index,
+                header: header.to_string(),
+                candidates: candidates(header, shape),
+                shape: shape.map(|(shape, _)| shape),
+                sensitive_kind,
+                exclude_by_default: sensitive_kind.is_some(),
+                sampled_values: sample.values,
+            }
+        })
+        .collect();
+
+    Ok(ColumnsAnalysisJson {
+        columns,
+        sampled_rows,
+        truncated,
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Cursor, Read};
+
+    #[test]
+    fn test_email_shape() {
+        assert!(is_email("ada@example.com"));
+        assert!(is_email("a.b+tag@mail.example.co.uk"));
+        assert!(!is_email("ada@example"));
+        assert!(!is_email("ada example@example.com"));
+        assert!(!is_email("@example.com"));
+        assert!(!is_email("a@b@example.com"));
+    }
+
+    #[test]
+    fn test_phone_shape() {
+        assert!(is_phone("+1 (555) 010-2030"));
+        assert!(is_phone("030 1234567"));
+        assert!(!is_phone("12345"));
+        assert!(!is_phone("555-CALL-NOW"));
+        assert!(!is_phone("1234567890123456"));
+    }
+
+    #[test]
+    fn test_postal_code_shape() {
+        assert!(is_postal_code("94103"));
+        assert!(is_postal_code("94103-1234"));
+        assert!(is_postal_code("SW1A 1AA"));
+        assert!(is_postal_code("k1a 0b1"));
+        assert!(!is_postal_code("9410"));
+        assert!(!is_postal_code("Main St"));
+    }
+
+    #[test]
+    fn test_date_shape() {
+        assert!(is_date("1990-04-01"));
+        assert!(is_date("04/01/1990"));
+        assert!(is_date("01.04.1990"));
+        assert!(!is_date("1990-13-01"));
+        assert!(!is_date("April 1st"));
+    }
+
+    #[test]
+    fn test_card_numbers_win_over_other_shapes() {
+        assert_eq!(
+            detect_shape("4111 1111 1111 1111"),
+            Some(ValueShape::CardNumber)
+        );
+        // Same length, fails Luhn: not a card
+        assert_eq!(detect_shape("4111 1111 1111 1112"), None);
+        assert_eq!(detect_shape("555 010 2030"), Some(ValueShape::Phone));
+        assert_eq!(detect_shape("94103"), Some(ValueShape::PostalCode));
+    }
+
+    #[test]
+    fn test_header_heuristics() {
+        let keys = |header: &str| -> Vec<&str> {
+            matching::keys_for_name(header)
+                .into_iter()
+                .map(|(key, _, _)| key)
+                .collect()
+        };
+        assert_eq!(keys("First Name"), vec!["firstName"]);
+        assert_eq!(keys("postal_code"), vec!["zip"]);
+        assert_eq!(keys("Email Address"), vec!["email"]);
+        assert_eq!(keys("given-name")[0], "firstName");
+        assert!(keys("Notes").is_empty());
+    }
+
+    #[test]
+    fn test_quoted_fields() {
+        let mut reader =
+            Cursor::new("a,\"b, with comma\",\"say \"\"hi\"\"\"\r\n\"multi\nline\",x,\n");
+        assert_eq!(
+            read_record(&mut reader).unwrap().unwrap(),
+            vec!["a", "b, with comma", "say \"hi\""]
+        );
+        assert_eq!(
+            read_record(&mut reader).unwrap().unwrap(),
+            vec!["multi\nline", "x", ""]
+        );
+        assert!(read_record(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_analyze_suggests_keys_and_excludes_cards() {
+        let csv = "\u{feff}Work Email,Contact,Postal,Card,Notes\n\
+                   ada@example.com,+1 555 010 2030,94103,4111 1111 1111 1111,hi\n\
+                   bob@example.com,+1 555 010 9999,10115,5555 5555 5555 4444,\n\
+                   cy@example.org,555-010-1234,SW1A 1AA,4012 8888 8888 1881,ok\n";
+        let analysis = analyze_columns(Cursor::new(csv)).unwrap();
+        assert_eq!(analysis.sampled_rows, 3);
+        assert!(!analysis.truncated);
+
+        let [email, phone, postal, card, notes] = analysis.columns.as_slice() else {
+            panic!("expected five columns: {:?}", analysis.columns);
+        };
+
+        assert_eq!(email.header, "Work Email");
+        assert_eq!(email.shape, Some(ValueShape::Email));
+        assert_eq!(email.candidates[0].key, "email");
+        // Header and values agree
+        assert!(email.candidates[0].confidence > 0.9);
+
+        // Header says nothing; the values do
+        assert_eq!(phone.shape, Some(ValueShape::Phone));
+        assert_eq!(phone.candidates[0].key, "phone");
+        assert!(!phone.exclude_by_default);
+
+        assert_eq!(postal.candidates[0].key, "zip");
+
+        assert_eq!(card.shape, Some(ValueShape::CardNumber));
+        assert_eq!(card.sensitive_kind, Some(pii::PiiKind::CreditCard));
+        assert!(card.exclude_by_default);
+
+        assert!(notes.candidates.is_empty());
+        assert_eq!(notes.sampled_values, 2);
+    }
+
+    /// Endless rows, counting how many bytes were read
+    struct Rows {
+        served: usize,
+    }
+
+    impl Read for Rows {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let row = b"ada@example.com,94103\n";
+            let n = row.len().min(buf.len());
+            buf[..n].copy_from_slice(&row[..n]);
+            self.served += n;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_sampling_stops_on_huge_files() {
+        let mut rows = Rows { served: 0 };
+        let analysis = analyze_columns(BufReader::new(&mut rows)).unwrap();
+        assert_eq!(analysis.sampled_rows, MAX_SAMPLE_ROWS);
+        assert!(analysis.truncated);
+        assert!(rows.served < 64 * 1024);
+    }
+
+    #[test]
+    fn test_oversized_record_is_capped() {
+        let mut csv = String::from("notes\n");
+        csv.push_str(&"x".repeat(MAX_RECORD_BYTES * 3));
+        csv.push_str("\nshort\n");
+        let mut reader = Cursor::new(csv);
+        read_record(&mut reader).unwrap();
+        let long = read_record(&mut reader).unwrap().unwrap();
+        assert_eq!(long[0].len(), MAX_RECORD_BYTES);
+        assert_eq!(read_record(&mut reader).unwrap().unwrap(), vec!["short"]);
+    }
+}
@@ -1,16 +1,73 @@
+mod acceptance;
+mod access;
+mod address;
+mod audit;
+mod backend;
+mod benchmark;
+mod bridge_port;
+mod changes;
+mod clock;
+mod compat;
+#[cfg(any(test, feature = "dev-tools"))]
+pub mod conformance;
+mod consent;
+mod cors;
+mod disk_usage;
+mod duplicates;
+mod eager;
+pub mod evidence;
+mod explain;
+mod export;
+mod features;
+mod fill;
+mod find_replace;
+mod groups;
+mod history;
+mod i18n;
+mod import_columns;
 mod llm;
+mod maintenance;
+mod match_stream;
+mod matching;
+mod onboarding;
+mod overrides;
+mod pause;
+mod pii;
+mod polling;
+mod power;
+mod recall;
+mod recapture;
+mod redact;
+mod review;
+mod rules;
+pub mod scenarios;
+mod semantic;
+mod sessions;
+mod shared;
+mod startup;
+mod status_page;
+mod storage;
+mod telemetry;
+mod templates;
+mod tokens;
+mod undo;
+mod vault_lock;
 
 use asterisk_vault::{
-    InMemoryStore, Provenance, ProvenanceSource, VaultCategory, VaultItem, VaultStore,
+    InMemoryStore, Provenance, ProvenanceSource, ReviewStatus, VaultCategory, VaultItem,
+    VaultStore, SEARCH_LIMIT,
 };
 use serde::{Deserialize, Serialize};
-use std::fs::{self, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
+use std::collections::HashMap;
+use std::fs;
+use std::io::BufReader;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
 use std::thread;
-use tauri::State;
-use tiny_http::{Header, Response, Server};
+use std::time::Duration;
+use tauri::{Emitter, Manager, State};
+use tiny_http::{Header, Request, Response, Server};
 
 // ============================================================================
 // State Management
@@ -18,22 +75,133 @@ use tiny_http::{Header, Response, Server};
 
 /// Application state holding the vault store
 pub struct AppState {
-    pub vault: Arc<Mutex<Box<dyn VaultStore>>>,
+    pub vault: shared::SharedVault,
+    pub history: Arc<history::VaultHistory>,
+    /// Keys changed by revision, for the extension's differential listing
+    pub changes: Arc<changes::ChangeJournal>,
+}
+
+/// Where switched-to file backends keep the vault by default
+pub struct VaultBackendState {
+    /// None when running without a writable data directory
+    pub data_dir: Option<PathBuf>,
+}
+
+/// Outstanding vault find/replace previews
+pub struct FindReplaceState {
+    pub previews: Mutex<find_replace::FindReplaceStore>,
 }
 
 /// Separate state for form snapshots (NOT part of vault)
 pub struct FormSnapshotState {
-    pub latest: Arc<Mutex<Option<FormSnapshotJson>>>,
+    pub latest: shared::SnapshotStore,
+    /// Steps of recent multi-step forms
+    pub sessions: sessions::FormSessionStore,
+    /// Last generated plan not yet applied, for extension poll hints
+    pub plan_activity: Arc<Mutex<Option<polling::PlanActivity>>>,
+    /// Pages the extension is asked to scrape again
+    pub recapture: Arc<Mutex<recapture::RecaptureQueue>>,
 }
 
 /// State for pending fill commands (desktop → extension)
 pub struct FillCommandState {
-    pub commands: Arc<Mutex<Vec<FillCommandJson>>>,
+    pub commands: shared::FillCommandStore,
+    /// Time that command creation and expiry go by
+    pub clock: Arc<clock::Clock>,
+    pub max_length_policy: Arc<Mutex<fill::MaxLengthPolicy>>,
+    /// Commands queued together and followed as one
+    pub groups: Arc<groups::FillCommandGroups>,
+}
+
+/// Generated fill plans and the acceptances made from them
+pub struct AcceptanceState {
+    pub store: Mutex<acceptance::AcceptanceStore>,
+}
+
+/// State for per-domain fill consent
+pub struct ConsentState {
+    pub store: consent::SharedConsentStore,
 }
 
+/// Sink for events the bridge raises for the UI (emitted as Tauri events)
+pub type EventSink = Arc<dyn Fn(&str, serde_json::Value) + Send + Sync>;
+
 /// State for audit log storage
 pub struct AuditState {
-    pub log_path: PathBuf,
+    /// Fill history, one log per profile
+    pub log: Arc<audit::AuditLog>,
+    pub access_log: Arc<access::AccessLog>,
+    /// Opt-in, value-free matching outcomes
+    pub telemetry: Arc<telemetry::MatchingTelemetry>,
+    /// Signing key for evidence bundles, created on first export
+    pub evidence_key: PathBuf,
+}
+
+/// Ledger of destructive maintenance runs
+pub struct MaintenanceState {
+    pub ledger: maintenance::MaintenanceLedger,
+}
+
+/// State for the opt-in recall store of filled values
+pub struct RecallState {
+    pub store: Arc<Mutex<recall::RecallStore>>,
+}
+
+/// Encrypted prior values of recent fills, for undo
+pub struct UndoState {
+    pub store: Arc<Mutex<undo::UndoStore>>,
+}
+
+/// Which extension version last talked to the bridge
+pub struct BridgeClientState {
+    pub tracker: Arc<compat::ClientTracker>,
+}
+
+/// First-run onboarding progress
+pub struct OnboardingState {
+    pub store: onboarding::OnboardingStore,
+}
+
+/// Named, scoped tokens for local bridge integrations
+pub struct BridgeTokenState {
+    pub store: Arc<Mutex<tokens::TokenStore>>,
+    /// Full-scope token handed to the extension when pairing
+    pub pairing_token: String,
+}
+
+/// Port the bridge is listening on; None if it failed to start
+pub struct BridgePortState {
+    pub port: Option<u16>,
+}
+
+/// Bridge capabilities the user has paused, shared with the bridge
+pub struct BridgePauseState {
+    pub pause: Arc<pause::BridgePause>,
+}
+
+/// Outcome of the startup data directory probe
+pub struct StorageState {
+    pub status: storage::StorageStatusJson,
+}
+
+/// Data files that failed to load at startup, and what was disabled
+pub struct StartupState {
+    pub plan: Mutex<startup::StartupPlanJson>,
+}
+
+/// State for saved form templates
+pub struct TemplateState {
+    pub store: Arc<Mutex<templates::TemplateStore>>,
+}
+
+/// State for per-field overrides the user has saved
+pub struct OverrideState {
+    pub store: Mutex<overrides::OverrideStore>,
+}
+
+/// User-defined fill rules, loaded at startup
+pub struct FillRulesState {
+    pub rules: Vec<rules::FillRuleJson>,
 }
 
 /// State for API key storage (in-memory for now, should use keychain in future)
@@ -41,6 +209,43 @@ pub struct ApiKeyState {
     pub claude_api_key: Arc<Mutex<Option<String>>>,
 }
 
+/// Extra tokens to redact from LLM prompts
+pub struct LlmRedactionState {
+    pub tokens: Arc<Mutex<Vec<String>>>,
+}
+
+/// Cached LLM field answers, and the running snapshot analysis per domain
+/// and tab that shares them
+pub struct LlmCacheState {
+    pub cache: Arc<llm::AnalysisCache>,
+    pub sessions: Arc<llm::AnalysisSessions>,
+}
+
+/// LLM token and dollar usage against the configured budget
+pub struct LlmBudgetState {
+    pub usage: Arc<llm::LlmUsageStats>,
+}
+
+/// Plans generated in the background as snapshots arrive
+pub struct EagerAnalysisState {
+    pub analysis: Arc<eager::EagerAnalysis>,
+}
+
+/// Whether the vault is locked, shared with the bridge
+pub struct VaultLockState {
+    pub lock: Arc<vault_lock::VaultLock>,
+}
+
+impl VaultLockState {
+    /// Record a vault access in the app, restarting the idle lock timer;
+    /// refused while the vault is locked, so nothing is read from it
+    fn accessed(&self) -> Result<(), String> {
+        self.lock
+            .touch(chrono::Utc::now())
+            .map_err(|reason| format!("Vault is locked ({:?})", reason))
+    }
+}
+
 // ============================================================================
 // Vault Serializable Types for IPC
 // ============================================================================
@@ -55,6 +260,13 @@ pub struct VaultItemJson {
     pub category: String,
     pub provenance: ProvenanceJson,
     pub metadata: VaultMetadataJson,
+    #[serde(default)]
+    pub sensitive: bool,
+    #[serde(default)]
+    pub review_status: ReviewStatus,
+    /// When the user should check the value again (ISO 8601)
+    #[serde(default)]
+    pub review_after: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -73,6 +285,24 @@ pub struct VaultMetadataJson {
     pub usage_count: u32,
 }
 
+/// A vault item without its value, for pickers that only need to name it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultKeyJson {
+    pub key: String,
+    pub label: String,
+    pub category: VaultCategory,
+}
+
+impl From<VaultItem> for VaultKeyJson {
+    fn from(item: VaultItem) -> Self {
+        Self {
+            key: item.key,
+            label: item.label,
+            category: item.category,
+        }
+    }
+}
+
 // ============================================================================
 // Form Snapshot Types (mirrors TypeScript FormSnapshot)
 // ============================================================================
@@ -83,7 +313,7 @@ pub struct SelectOptionJson {
     pub label: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct FieldNodeJson {
     pub id: String,
     pub name: String,
@@ -106,6 +336,18 @@ pub struct FieldNodeJson {
     pub input_mode: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<Vec<SelectOptionJson>>,
+    /// Fallback label context for fields without a proper <label>
+    #[serde(rename = "ariaLabel", default, skip_serializing_if = "Option::is_none")]
+    pub aria_label: Option<String>,
+    #[serde(rename = "describedBy", default, skip_serializing_if = "Option::is_none")]
+    pub described_by: Option<String>,
+    #[serde(rename = "sectionHeading", default, skip_serializing_if = "Option::is_none")]
+    pub section_heading: Option<String>,
+    #[serde(rename = "nearbyText", default, skip_serializing_if = "Option::is_none")]
+    pub nearby_text: Option<String>,
+    /// Option already chosen in a country select (never captured for other fields)
+    #[serde(rename = "selectedValue", default, skip_serializing_if = "Option::is_none")]
+    pub selected_value: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -128,6 +370,9 @@ pub struct FormSnapshotJson {
     pub captured_at: String,
     pub fingerprint: FormFingerprintJson,
     pub fields: Vec<FieldNodeJson>,
+    /// Position in a multi-step form, if the page is one
+    #[serde(flatten)]
+    pub session: sessions::FormStepJson,
 }
 
 // ============================================================================
@@ -135,7 +380,7 @@ pub struct FormSnapshotJson {
 // ============================================================================
 
 /// A single field fill instruction
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FieldFillJson {
     /// The field ID to fill (matches FieldNode.id)
     #[serde(rename = "fieldId")]
@@ -163,6 +408,13 @@ pub struct FillCommandJson {
     /// Command expires after this time (ISO 8601)
     #[serde(rename = "expiresAt")]
     pub expires_at: String,
+    /// Ask the extension to report each field's prior value so the fill can
+    /// be undone (see the `undo` module)
+    #[serde(rename = "captureUndo", default)]
+    pub capture_undo: bool,
+    /// Content hash of the plan the command was accepted from
+    #[serde(rename = "planHash", default, skip_serializing_if = "Option::is_none")]
+    pub plan_hash: Option<String>,
 }
 
 // ============================================================================
@@ -179,7 +431,7 @@ pub enum RedactionLevel {
 }
 
 /// Disposition category for a fill recommendation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Disposition {
     Safe,
@@ -187,6 +439,19 @@ pub enum Disposition {
     Blocked,
 }
 
+impl Disposition {
+    /// How the review UI treats a recommendation with this confidence
+    pub fn for_confidence(confidence: f64) -> Self {
+        if confidence >= matching::SAFE_AUTO_THRESHOLD {
+            Disposition::Safe
+        } else if confidence >= matching::REVIEW_THRESHOLD {
+            Disposition::Review
+        } else {
+            Disposition::Blocked
+        }
+    }
+}
+
 /// A single field in an audit entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditItemJson {
@@ -256,6 +521,25 @@ pub struct AuditEntryJson {
     pub summary: AuditSummaryJson,
     /// Individual field items
     pub items: Vec<AuditItemJson>,
+    /// Whether recall was on for the domain, so the filled values were kept
+    #[serde(rename = "recallRecorded", default)]
+    pub recall_recorded: bool,
+    /// The fill command that performed this fill, if known; needed for undo
+    #[serde(rename = "fillCommandId", default, skip_serializing_if = "Option::is_none")]
+    pub fill_command_id: Option<String>,
+    /// The plan acceptance the fill was built from
+    #[serde(rename = "acceptanceId", default, skip_serializing_if = "Option::is_none")]
+    pub acceptance_id: Option<String>,
+    /// Content hash of that acceptance, filled in by the backend
+    #[serde(rename = "acceptanceHash", default, skip_serializing_if = "Option::is_none")]
+    pub acceptance_hash: Option<String>,
+    /// Content hash of the accepted plan, filled in by the backend
+    #[serde(rename = "planHash", default, skip_serializing_if = "Option::is_none")]
+    pub plan_hash: Option<String>,
+    /// How much of the fill was kept; set by the backend when the entry is
+    /// logged, absent (full) in logs written before detail levels existed
+    #[serde(default, skip_serializing_if = "audit::AuditDetail::is_full")]
+    pub detail: audit::AuditDetail,
 }
 
 /// Response from audit_list command with pagination support
@@ -268,6 +552,16 @@ pub struct AuditListResponse {
     pub next_cursor: Option<u32>,
 }
 
+/// Response from vault_list command with pagination support
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultListResponse {
+    /// Vault items on this page
+    pub items: Vec<VaultItemJson>,
+    /// Cursor for next page, if more items match
+    #[serde(rename = "nextCursor", skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<u32>,
+}
+
 // ============================================================================
 // Type Conversions (Vault)
 // ============================================================================
@@ -301,200 +595,352 @@ impl From<VaultItem> for VaultItemJson {
                 last_used: item.metadata.last_used.map(|dt| dt.to_rfc3339()),
                 usage_count: item.metadata.usage_count,
             },
+            sensitive: item.sensitive,
+            review_status: item.review_status,
+            review_after: item.review_after.map(|dt| dt.to_rfc3339()),
         }
     }
 }
 
+/// How `vault_item_from_json` treats an unparsable timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimestampMode {
+    /// Fail the whole conversion
+    Strict,
+    /// Substitute a fallback and record a warning
+    Lossy,
+}
+
+/// Conversion that tolerates bad timestamps, for import paths
+///
+/// A hand-edited import shouldn't lose an item over one malformed date, but
+/// the UI (`vault_set`) should still fail loudly, so this sits beside
+/// `TryFrom` rather than replacing it.
+pub trait TryFromLossy<T>: Sized {
+    /// Convert `value`, returning the item plus a warning per field that had
+    /// to fall back
+    fn try_from_lossy(value: T) -> Result<(Self, Vec<String>), String>;
+}
+
+impl TryFromLossy<VaultItemJson> for VaultItem {
+    /// Unparsable `provenance.timestamp`, `created` and `updated` become now;
+    /// an unparsable `last_used` becomes None. Invalid categories and
+    /// sources are still errors.
+    fn try_from_lossy(json: VaultItemJson) -> Result<(Self, Vec<String>), String> {
+        vault_item_from_json(json, TimestampMode::Lossy)
+    }
+}
+
 impl TryFrom<VaultItemJson> for VaultItem {
     type Error = String;
 
     fn try_from(json: VaultItemJson) -> Result<Self, Self::Error> {
-        use chrono::DateTime;
-
-        let category = match json.category.as_str() {
-            "identity" => VaultCategory::Identity,
-            "contact" => VaultCategory::Contact,
-            "address" => VaultCategory::Address,
-            "financial" => VaultCategory::Financial,
-            "custom" => VaultCategory::Custom,
-            _ => return Err(format!("Invalid category: {}", json.category)),
-        };
-
-        let source = match json.provenance.source.as_str() {
-            "user_entered" => ProvenanceSource::UserEntered,
-            "imported" => ProvenanceSource::Imported,
-            "autofilled" => ProvenanceSource::Autofilled,
-            _ => return Err(format!("Invalid source: {}", json.provenance.source)),
-        };
+        vault_item_from_json(json, TimestampMode::Strict).map(|(item, _)| item)
+    }
+}
 
-        let timestamp = DateTime::parse_from_rfc3339(&json.provenance.timestamp)
-            .map_err(|e| format!("Invalid timestamp: {}", e))?
-            .with_timezone(&chrono::Utc);
-
-        let created = DateTime::parse_from_rfc3339(&json.metadata.created)
-            .map_err(|e| format!("Invalid created timestamp: {}", e))?
-            .with_timezone(&chrono::Utc);
-
-        let updated = DateTime::parse_from_rfc3339(&json.metadata.updated)
-            .map_err(|e| format!("Invalid updated timestamp: {}", e))?
-            .with_timezone(&chrono::Utc);
-
-        let last_used = json
-            .metadata
-            .last_used
-            .map(|s| {
-                DateTime::parse_from_rfc3339(&s)
-                    .map(|dt| dt.with_timezone(&chrono::Utc))
-                    .map_err(|e| format!("Invalid last_used timestamp: {}", e))
-            })
-            .transpose()?;
-
-        Ok(VaultItem {
-            key: json.key,
-            value: json.value,
-            label: json.label,
-            category,
-            provenance: Provenance {
-                source,
-                timestamp,
-                confidence: json.provenance.confidence,
-                origin: json.provenance.origin,
-            },
-            metadata: asterisk_vault::VaultMetadata {
-                created,
-                updated,
-                last_used,
-                usage_count: json.metadata.usage_count,
-            },
-        })
+/// The category named by its `VaultItemJson` string
+fn parse_category(category: &str) -> Result<VaultCategory, String> {
+    match category {
+        "identity" => Ok(VaultCategory::Identity),
+        "contact" => Ok(VaultCategory::Contact),
+        "address" => Ok(VaultCategory::Address),
+        "financial" => Ok(VaultCategory::Financial),
+        "custom" => Ok(VaultCategory::Custom),
+        _ => Err(format!("Invalid category: {}", category)),
     }
 }
 
+fn vault_item_from_json(
+    json: VaultItemJson,
+    mode: TimestampMode,
+) -> Result<(VaultItem, Vec<String>), String> {
+    use chrono::{DateTime, Utc};
+
+    let category = parse_category(&json.category)?;
+
+    let source = match json.provenance.source.as_str() {
+        "user_entered" => ProvenanceSource::UserEntered,
+        "imported" => ProvenanceSource::Imported,
+        "autofilled" => ProvenanceSource::Autofilled,
+        _ => return Err(format!("Invalid source: {}", json.provenance.source)),
+    };
+
+    let mut warnings = Vec::new();
+    let mut parse = |name: &str, value: &str| -> Result<Option<DateTime<Utc>>, String> {
+        match DateTime::parse_from_rfc3339(value) {
+            Ok(dt) => Ok(Some(dt.with_timezone(&Utc))),
+            Err(e) if mode == TimestampMode::Lossy => {
+                warnings.push(format!(
+                    "{}: invalid {} timestamp {:?} ({})",
+                    json.key, name, value, e
+                ));
+                Ok(None)
+            }
+            Err(e) => Err(format!("Invalid {} timestamp: {}", name, e)),
+        }
+    };
+
+    let now = Utc::now();
+    let timestamp = parse("provenance", &json.provenance.timestamp)?.unwrap_or(now);
+    let created = parse("created", &json.metadata.created)?.unwrap_or(now);
+    let updated = parse("updated", &json.metadata.updated)?.unwrap_or(now);
+    let last_used = match &json.metadata.last_used {
+        Some(s) => parse("last_used", s)?,
+        None => None,
+    };
+    let review_after = match &json.review_after {
+        Some(s) => parse("review_after", s)?,
+        None => None,
+    };
+
+    let item = VaultItem {
+        key: json.key,
+        value: json.value,
+        label: json.label,
+        category,
+        provenance: Provenance {
+            source,
+            timestamp,
+            confidence: json.provenance.confidence,
+            origin: json.provenance.origin,
+        },
+        metadata: asterisk_vault::VaultMetadata {
+            created,
+            updated,
+            last_used,
+            usage_count: json.metadata.usage_count,
+        },
+        sensitive: json.sensitive,
+        review_status: json.review_status,
+        review_after,
+    };
+    Ok((item, warnings))
+}
+
 // ============================================================================
 // Tauri Commands - Vault
 // ============================================================================
 
+/// Store a vault item; returns the vault revision that includes it
 #[tauri::command]
-fn vault_set(key: String, item: VaultItemJson, state: State<AppState>) -> Result<(), String> {
-    let vault_item = VaultItem::try_from(item)?;
+fn vault_set(
+    key: String,
+    item: VaultItemJson,
+    state: State<AppState>,
+) -> Result<shared::Revised<()>, String> {
+    let mut vault_item = VaultItem::try_from(item)?;
     let mut vault = state.vault.lock().map_err(|e| e.to_string())?;
-    vault.set(key, vault_item).map_err(|e| e.to_string())
+    // Usage is only bumped by touch; don't overwrite it with the UI's copy
+    let stored = vault.get(&key).map_err(|e| e.to_string())?;
+    let existed = stored.is_some();
+    if let Some(stored) = &stored {
+        vault_item.keep_usage_of(stored);
+    }
+    vault
+        .set(key, vault_item.clone())
+        .map_err(|e| e.to_string())?;
+    drop(vault);
+    state.history.record_set(&vault_item, existed, "vault_set")?;
+    Ok(shared::Revised::new((), &state.vault))
 }
 
+/// Store several vault items, each under its own key, all or nothing: if
+/// any item or key is invalid, none is stored
 #[tauri::command]
-fn vault_get(key: String, state: State<AppState>) -> Result<Option<VaultItemJson>, String> {
-    let vault = state.vault.lock().map_err(|e| e.to_string())?;
+fn vault_set_many(
+    items: Vec<VaultItemJson>,
+    state: State<AppState>,
+) -> Result<shared::Revised<()>, String> {
+    let mut items = items
+        .into_iter()
+        .map(VaultItem::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut vault = state.vault.lock().map_err(|e| e.to_string())?;
+    let keys: Vec<&str> = items.iter().map(|item| item.key.as_str()).collect();
+    let stored = vault.get_many(&keys).map_err(|e| e.to_string())?;
+    let existed: Vec<bool> = items
+        .iter_mut()
+        .map(|item| match stored.get(&item.key) {
+            // As in vault_set, usage is left to touch
+            Some(stored) => {
+                item.keep_usage_of(stored);
+                true
+            }
+            None => false,
+        })
+        .collect();
     vault
-        .get(&key)
-        .map(|opt| opt.map(VaultItemJson::from))
-        .map_err(|e| e.to_string())
+        .set_many(
+            items
+                .iter()
+                .map(|item| (item.key.clone(), item.clone()))
+                .collect(),
+        )
+        .map_err(|e| e.to_string())?;
+    drop(vault);
+    for (item, existed) in items.iter().zip(existed) {
+        state.history.record_set(item, existed, "vault_set_many")?;
+    }
+    Ok(shared::Revised::new((), &state.vault))
 }
 
+/// Get a vault item, once the vault has reached `min_revision` if given
 #[tauri::command]
-fn vault_list(state: State<AppState>) -> Result<Vec<VaultItemJson>, String> {
-    let vault = state.vault.lock().map_err(|e| e.to_string())?;
-    vault
-        .list()
-        .map(|items| items.into_iter().map(VaultItemJson::from).collect())
-        .map_err(|e| e.to_string())
+fn vault_get(
+    key: String,
+    min_revision: Option<u64>,
+    state: State<AppState>,
+    audit_state: State<AuditState>,
+    lock_state: State<VaultLockState>,
+) -> Result<Option<VaultItemJson>, String> {
+    lock_state.accessed()?;
+    let vault = state.vault.lock_at(min_revision)?;
+    let item = vault.get(&key).map_err(|e| e.to_string())?;
+    if let Some(item) = &item {
+        audit_state.access_log.record_read(item, "vault_get")?;
+    }
+    Ok(item.map(VaultItemJson::from))
 }
 
+/// The vault items under `keys`, by key; keys with no item are absent from
+/// the map, which tells a missing item apart from an empty value
 #[tauri::command]
-fn vault_delete(key: String, state: State<AppState>) -> Result<(), String> {
-    let mut vault = state.vault.lock().map_err(|e| e.to_string())?;
-    vault.delete(&key).map_err(|e| e.to_string())
+fn vault_get_many(
+    keys: Vec<String>,
+    state: State<AppState>,
+    audit_state: State<AuditState>,
+    lock_state: State<VaultLockState>,
+) -> Result<HashMap<String, VaultItemJson>, String> {
+    lock_state.accessed()?;
+    let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+    let items = state
+        .vault
+        .lock()?
+        .get_many(&keys)
+        .map_err(|e| e.to_string())?;
+    for item in items.values() {
+        audit_state.access_log.record_read(item, "vault_get_many")?;
+    }
+    Ok(items
+        .into_iter()
+        .map(|(key, item)| (key, VaultItemJson::from(item)))
+        .collect())
 }
 
-// ============================================================================
-// Tauri Commands - Form Snapshots
-// ============================================================================
-
+/// Every vault item in `category` (`identity`, `contact`, `address`,
+/// `financial` or `custom`)
 #[tauri::command]
-fn get_latest_form_snapshot(
-    state: State<FormSnapshotState>,
-) -> Result<Option<FormSnapshotJson>, String> {
-    let latest = state.latest.lock().map_err(|e| e.to_string())?;
-    Ok(latest.clone())
+fn vault_list_by_category(
+    category: String,
+    state: State<AppState>,
+    lock_state: State<VaultLockState>,
+) -> Result<Vec<VaultItemJson>, String> {
+    lock_state.accessed()?;
+    let category = parse_category(&category)?;
+    let items = state
+        .vault
+        .lock()?
+        .list_by_category(category)
+        .map_err(|e| e.to_string())?;
+    Ok(items.into_iter().map(VaultItemJson::from).collect())
 }
 
-// ============================================================================
-// Tauri Commands - Audit Log
-// ============================================================================
-
-/// Append a new audit entry to the log file
+/// Vault items whose review date has passed, soonest due first
+///
+/// Unlike the review queue (`review_status`), which holds imports waiting
+/// for the user to vouch for them, a review date only flags a value that
+/// has likely gone stale, like an expired card or an old address. Flagged
+/// items are still matched and filled.
 #[tauri::command]
-fn audit_append(entry: AuditEntryJson, state: State<AuditState>) -> Result<(), String> {
-    // Ensure parent directory exists
-    if let Some(parent) = state.log_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| format!("Failed to create audit directory: {}", e))?;
-    }
-
-    // Serialize to JSON line
-    let json_line =
-        serde_json::to_string(&entry).map_err(|e| format!("Failed to serialize entry: {}", e))?;
-
-    // Append to file
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&state.log_path)
-        .map_err(|e| format!("Failed to open audit log: {}", e))?;
+fn vault_needs_review(
+    state: State<AppState>,
+    lock_state: State<VaultLockState>,
+) -> Result<Vec<VaultItemJson>, String> {
+    lock_state.accessed()?;
+    let now = chrono::Utc::now();
+    let mut items = state.vault.lock()?.list().map_err(|e| e.to_string())?;
+    items.retain(|item| item.needs_review(now));
+    items.sort_by_key(|item| item.review_after);
+    Ok(items.into_iter().map(VaultItemJson::from).collect())
+}
 
-    writeln!(file, "{}", json_line).map_err(|e| format!("Failed to write audit entry: {}", e))?;
+/// Vault items whose key, label or category match `query`, best first
+/// (see `VaultStore::search`); values are never searched
+///
+/// Returns at most `limit` items, and never more than `SEARCH_LIMIT`.
+#[tauri::command]
+fn vault_search(
+    query: String,
+    limit: Option<u32>,
+    state: State<AppState>,
+    lock_state: State<VaultLockState>,
+) -> Result<Vec<VaultItemJson>, String> {
+    lock_state.accessed()?;
+    let mut items = state
+        .vault
+        .lock()?
+        .search(&query)
+        .map_err(|e| e.to_string())?;
+    items.truncate(limit.map_or(SEARCH_LIMIT, |limit| limit as usize));
+    Ok(items.into_iter().map(VaultItemJson::from).collect())
+}
 
-    println!(
-        "[Asterisk Audit] Logged entry {} for {}",
-        entry.id, entry.domain
-    );
-    Ok(())
+/// Items in `category` whose key, label or value contains `search`
+/// (ignoring case), sorted by key so cursors stay stable between pages
+fn filter_vault_items(
+    items: Vec<VaultItem>,
+    category: Option<&VaultCategory>,
+    search: Option<&str>,
+) -> Vec<VaultItem> {
+    let search = search.map(str::to_lowercase).filter(|s| !s.is_empty());
+    let mut items: Vec<VaultItem> = items
+        .into_iter()
+        .filter(|item| category.is_none_or(|c| item.category == *c))
+        .filter(|item| {
+            search.as_deref().is_none_or(|needle| {
+                [&item.key, &item.label, &item.value]
+                    .iter()
+                    .any(|field| field.to_lowercase().contains(needle))
+            })
+        })
+        .collect();
+    items.sort_by(|a, b| a.key.cmp(&b.key));
+    items
 }
 
-/// List audit entries with optional pagination
+/// List vault items, filtered and paginated, once the vault has reached
+/// `min_revision` if given; without `limit` every match is returned
 #[tauri::command]
-fn audit_list(
+fn vault_list(
+    category: Option<String>,
+    search: Option<String>,
     limit: Option<u32>,
     cursor: Option<u32>,
-    state: State<AuditState>,
-) -> Result<AuditListResponse, String> {
-    let limit = limit.unwrap_or(50).min(100) as usize;
-    let start = cursor.unwrap_or(0) as usize;
-
-    // Read all entries from file
-    let file = match fs::File::open(&state.log_path) {
-        Ok(f) => f,
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-            // No audit log yet, return empty
-            return Ok(AuditListResponse {
-                items: vec![],
-                next_cursor: None,
-            });
-        }
-        Err(e) => return Err(format!("Failed to open audit log: {}", e)),
-    };
-
-    let reader = BufReader::new(file);
-    let mut entries: Vec<AuditEntryJson> = Vec::new();
-
-    for line in reader.lines() {
-        let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
-        if line.trim().is_empty() {
-            continue;
-        }
-        match serde_json::from_str::<AuditEntryJson>(&line) {
-            Ok(entry) => entries.push(entry),
-            Err(e) => {
-                eprintln!("[Asterisk Audit] Skipping malformed entry: {}", e);
-                continue;
-            }
-        }
+    min_revision: Option<u64>,
+    state: State<AppState>,
+    lock_state: State<VaultLockState>,
+) -> Result<VaultListResponse, String> {
+    lock_state.accessed()?;
+    let category = category.as_deref().map(parse_category).transpose()?;
+    let vault = state.vault.lock_at(min_revision)?;
+    let items = match &category {
+        Some(category) => vault.list_by_category(category.clone()),
+        None => vault.list(),
     }
+    .map_err(|e| e.to_string())?;
+    drop(vault);
+    let matches = filter_vault_items(items, category.as_ref(), search.as_deref());
 
-    // Sort by createdAt descending (newest first)
-    entries.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-
-    // Apply pagination
-    let total = entries.len();
-    let page: Vec<AuditEntryJson> = entries.into_iter().skip(start).take(limit).collect();
+    let start = cursor.unwrap_or(0) as usize;
+    let limit = limit.map_or(usize::MAX, |limit| limit as usize);
+    let total = matches.len();
+    let page: Vec<VaultItemJson> = matches
+        .into_iter()
+        .skip(start)
+        .take(limit)
+        .map(VaultItemJson::from)
+        .collect();
 
     let next_cursor = if start + page.len() < total {
         Some((start + page.len()) as u32)
@@ -502,168 +948,2776 @@ fn audit_list(
         None
     };
 
-    Ok(AuditListResponse {
+    Ok(VaultListResponse {
         items: page,
         next_cursor,
     })
 }
 
-/// Get a single audit entry by ID
+/// Delete a vault item; returns the vault revision without it
 #[tauri::command]
-fn audit_get(id: String, state: State<AuditState>) -> Result<Option<AuditEntryJson>, String> {
-    let file = match fs::File::open(&state.log_path) {
-        Ok(f) => f,
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-            return Ok(None);
-        }
-        Err(e) => return Err(format!("Failed to open audit log: {}", e)),
-    };
-
-    let reader = BufReader::new(file);
-
-    for line in reader.lines() {
-        let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
-        if line.trim().is_empty() {
-            continue;
-        }
-        if let Ok(entry) = serde_json::from_str::<AuditEntryJson>(&line) {
-            if entry.id == id {
-                return Ok(Some(entry));
-            }
-        }
-    }
-
-    Ok(None)
+fn vault_delete(key: String, state: State<AppState>) -> Result<shared::Revised<()>, String> {
+    let mut vault = state.vault.lock().map_err(|e| e.to_string())?;
+    vault.delete(&key).map_err(|e| e.to_string())?;
+    drop(vault);
+    state.history.record_delete(&key, "vault_delete")?;
+    Ok(shared::Revised::new((), &state.vault))
 }
 
-/// Clear all audit log entries (deletes the file)
+/// Chronological change events (with redacted values) for a vault key
 #[tauri::command]
-fn audit_clear(state: State<AuditState>) -> Result<(), String> {
-    match fs::remove_file(&state.log_path) {
-        Ok(_) => {
-            println!("[Asterisk Audit] Audit log cleared");
-            Ok(())
-        }
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-            // File doesn't exist, that's fine
-            Ok(())
-        }
-        Err(e) => Err(format!("Failed to clear audit log: {}", e)),
-    }
+fn vault_history(
+    key: String,
+    state: State<AppState>,
+) -> Result<Vec<history::VaultChangeJson>, String> {
+    state.history.for_key(&key)
 }
 
-/// Get the file path of the audit log
+/// Preview a vault-wide find and replace without changing anything
 #[tauri::command]
-fn audit_path(state: State<AuditState>) -> Result<String, String> {
-    state
-        .log_path
-        .to_str()
-        .map(|s| s.to_string())
-        .ok_or_else(|| "Invalid audit path".to_string())
+fn vault_find_replace_preview(
+    find: String,
+    replace: String,
+    options: Option<find_replace::FindReplaceOptions>,
+    state: State<AppState>,
+    find_replace_state: State<FindReplaceState>,
+) -> Result<find_replace::FindReplacePreviewJson, String> {
+    let items = state
+        .vault
+        .lock()
+        .map_err(|e| e.to_string())?
+        .list()
+        .map_err(|e| e.to_string())?;
+    let mut previews = find_replace_state
+        .previews
+        .lock()
+        .map_err(|e| e.to_string())?;
+    previews.preview(&items, &find, &replace, &options.unwrap_or_default())
 }
 
-// ============================================================================
-// LLM Integration Commands
-// ============================================================================
-
-/// Analyze a field using LLM (Claude API)
+/// Apply a previously previewed find and replace
 #[tauri::command]
-async fn llm_analyze_field(
-    request: llm::AnalyzeFieldRequest,
-    api_key_state: State<'_, ApiKeyState>,
-) -> Result<llm::AnalyzeFieldResponse, String> {
-    // Get API key from state
-    let api_key = api_key_state
-        .claude_api_key
+fn vault_find_replace_apply(
+    preview_token: String,
+    state: State<AppState>,
+    find_replace_state: State<FindReplaceState>,
+) -> Result<shared::Revised<find_replace::FindReplaceResultJson>, String> {
+    let mut previews = find_replace_state
+        .previews
         .lock()
-        .map_err(|e| format!("Failed to lock API key: {}", e))?
-        .clone()
-        .ok_or_else(|| "No API key configured. Please set your Claude API key in Settings.".to_string())?;
+        .map_err(|e| e.to_string())?;
+    let mut vault = state.vault.lock().map_err(|e| e.to_string())?;
+    let result = previews.apply(&preview_token, vault.as_mut(), &state.history)?;
+    drop(vault);
+    Ok(shared::Revised::new(result, &state.vault))
+}
 
-    // Call LLM analysis
-    llm::analyze_field_with_llm(request, &api_key).await
+/// Stream the vault to `path` as JSON lines; returns the number of items
+#[tauri::command]
+fn vault_export_jsonl(path: String, state: State<AppState>) -> Result<usize, String> {
+    let file = fs::File::create(&path).map_err(|e| format!("Failed to create export: {}", e))?;
+    let vault = state.vault.lock().map_err(|e| e.to_string())?;
+    export::export_jsonl(vault.as_ref(), file)
 }
 
-/// Set the Claude API key
+/// Suggest vault keys for each column of the CSV file at `path`
+///
+/// Reads the header and a bounded sample of rows. Columns that look like
+/// card numbers or other sensitive data are flagged for exclusion.
 #[tauri::command]
-fn set_api_key(
-    api_key: String,
-    state: State<ApiKeyState>,
-) -> Result<(), String> {
-    let mut key_store = state
-        .claude_api_key
-        .lock()
-        .map_err(|e| format!("Failed to lock API key: {}", e))?;
+fn import_analyze_columns(path: String) -> Result<import_columns::ColumnsAnalysisJson, String> {
+    let file = fs::File::open(&path).map_err(|e| format!("Failed to open import: {}", e))?;
+    import_columns::analyze_columns(BufReader::new(file))
+}
 
-    *key_store = Some(api_key);
-    Ok(())
+/// Switch the active vault backend, copying every item into the new one
+///
+/// Requests running meanwhile see the old store or the new one, never a
+/// half-migrated one; on failure the old store stays active. The default
+/// vault file is off limits while startup found it corrupt.
+#[tauri::command]
+fn vault_switch_backend(
+    kind: backend::VaultBackendKind,
+    options: Option<backend::VaultBackendOptionsJson>,
+    state: State<AppState>,
+    backend_state: State<VaultBackendState>,
+    startup_state: State<StartupState>,
+) -> Result<shared::Revised<backend::VaultBackendSwitchJson>, String> {
+    let options = options.unwrap_or_default();
+    let file = backend::backend_file(kind, &options, backend_state.data_dir.as_deref())?;
+    let plan = startup_state.plan.lock().map_err(|e| e.to_string())?;
+    if let Some(file) = file.filter(|file| plan.has_problem(file)) {
+        return Err(format!(
+            "{} failed to load at startup; see startup_problems",
+            file.display()
+        ));
+    }
+    drop(plan);
+    let next = backend::open_backend(kind, &options, backend_state.data_dir.as_deref())?;
+    let next = Box::new(changes::JournaledStore::new(next, Arc::clone(&state.changes)));
+    let summary = backend::swap_in(&state.vault, kind, next)?;
+    state.changes.reset(&state.vault)?;
+    println!(
+        "[Vault] Switched backend to {:?}: {} items migrated",
+        summary.kind, summary.migrated
+    );
+    Ok(shared::Revised::new(summary, &state.vault))
 }
 
-/// Check if API key is configured
+/// Whether the OS keychain holds the vault master key, for setup state
 #[tauri::command]
-fn has_api_key(state: State<ApiKeyState>) -> Result<bool, String> {
-    let key_store = state
-        .claude_api_key
-        .lock()
-        .map_err(|e| format!("Failed to lock API key: {}", e))?;
+fn vault_key_status() -> backend::VaultKeyStatusJson {
+    backend::key_status(&asterisk_vault::OsKeyring)
+}
 
-    Ok(key_store.is_some())
+/// Compare the local clock with a trusted reference time
+///
+/// Reads the HTTP `Date` header of `reference_url` (the LLM endpoint by
+/// default). The result carries a warning when the skew is large enough
+/// for expiry and ordering to go wrong.
+#[tauri::command]
+async fn time_sanity_check(
+    reference_url: Option<String>,
+    max_skew_secs: Option<i64>,
+) -> Result<clock::TimeSanityJson, String> {
+    let url = reference_url.unwrap_or_else(|| clock::DEFAULT_REFERENCE_URL.to_string());
+    let max_skew = chrono::Duration::seconds(max_skew_secs.unwrap_or(clock::DEFAULT_MAX_SKEW_SECS));
+    let result = clock::check(&url, max_skew).await?;
+    if let Some(warning) = &result.warning {
+        eprintln!("[Clock] {}", warning);
+    }
+    Ok(result)
 }
 
-/// Clear the API key
+/// Optional features and whether this build includes them
 #[tauri::command]
-fn clear_api_key(state: State<ApiKeyState>) -> Result<(), String> {
-    let mut key_store = state
-        .claude_api_key
-        .lock()
-        .map_err(|e| format!("Failed to lock API key: {}", e))?;
+fn features_available() -> Vec<features::FeatureJson> {
+    features::FEATURES.to_vec()
+}
 
-    *key_store = None;
-    Ok(())
+/// Locale server-generated messages are rendered in
+#[tauri::command]
+fn locale_get() -> i18n::Locale {
+    i18n::current_locale()
 }
 
-// ============================================================================
-// HTTP Server for Extension Bridge
-// ============================================================================
+/// Render server-generated messages in another locale
+///
+/// Takes a language tag ("de", "de-DE"); unsupported languages are refused.
+#[tauri::command]
+fn locale_set(locale: String) -> Result<i18n::Locale, String> {
+    let parsed = i18n::Locale::from_tag(&locale)
+        .ok_or_else(|| format!("Unsupported locale: {}", locale))?;
+    i18n::set_locale(parsed);
+    Ok(parsed)
+}
 
-fn start_http_server(
-    snapshot_store: Arc<Mutex<Option<FormSnapshotJson>>>,
-    vault_store: Arc<Mutex<Box<dyn VaultStore>>>,
-    fill_command_store: Arc<Mutex<Vec<FillCommandJson>>>,
-) {
-    thread::spawn(move || {
-        let server = match Server::http("127.0.0.1:17373") {
-            Ok(s) => s,
+/// Port the extension bridge is listening on
+#[tauri::command]
+fn bridge_port(port_state: State<BridgePortState>) -> Result<u16, String> {
+    port_state
+        .port
+        .ok_or_else(|| "The bridge is not running".to_string())
+}
+
+/// Run the bridge conformance suite against this app's own bridge
+///
+/// Needs the dev-tools feature. Writes and removes probe data for the
+/// conformance test domain, and returns the formatted report.
+#[tauri::command]
+async fn bridge_conformance_run(
+    token: Option<String>,
+    port_state: State<'_, BridgePortState>,
+) -> Result<String, features::CommandError> {
+    #[cfg(feature = "dev-tools")]
+    {
+        let port = port_state
+            .port
+            .ok_or_else(|| "The bridge is not running".to_string())?;
+        let report = tauri::async_runtime::spawn_blocking(move || {
+            conformance::run_suite(&format!("http://127.0.0.1:{}", port), token.as_deref())
+        })
+        .await
+        .map_err(|e| format!("Conformance run failed: {}", e))?;
+        Ok(report.to_string())
+    }
+    #[cfg(not(feature = "dev-tools"))]
+    {
+        let _ = (token, port_state);
+        Err(features::CommandError::feature_disabled("dev-tools"))
+    }
+}
+
+/// Import a JSON lines export from `path`, one item at a time
+#[tauri::command]
+fn vault_import_jsonl(
+    path: String,
+    state: State<AppState>,
+) -> Result<shared::Revised<export::ImportSummaryJson>, String> {
+    let file = fs::File::open(&path).map_err(|e| format!("Failed to open import: {}", e))?;
+    let mut vault = state.vault.lock().map_err(|e| e.to_string())?;
+    let summary = export::import_jsonl(BufReader::new(file), vault.as_mut(), &state.history)?;
+    drop(vault);
+    Ok(shared::Revised::new(summary, &state.vault))
+}
+
+/// Import a flat JSON object of key to value from `path`, guessing each
+/// item's category from its key
+#[tauri::command]
+fn vault_import_simple(
+    path: String,
+    state: State<AppState>,
+) -> Result<shared::Revised<export::ImportSummaryJson>, String> {
+    let raw = fs::read_to_string(&path).map_err(|e| format!("Failed to open import: {}", e))?;
+    let map: std::collections::BTreeMap<String, serde_json::Value> =
+        serde_json::from_str(&raw).map_err(|e| format!("Invalid key-value import: {}", e))?;
+    let mut vault = state.vault.lock().map_err(|e| e.to_string())?;
+    let summary = export::import_simple(&map, vault.as_mut(), &state.history)?;
+    drop(vault);
+    Ok(shared::Revised::new(summary, &state.vault))
+}
+
+/// The vault's keys, labels and categories without any values, for
+/// sharing a consistent schema with a teammate
+#[tauri::command]
+fn vault_export_profile(state: State<AppState>) -> Result<export::FillProfileJson, String> {
+    let vault = state.vault.lock().map_err(|e| e.to_string())?;
+    export::export_profile(vault.as_ref())
+}
+
+/// Create empty items from the fill profile at `path`; keys already in
+/// the vault are left as they are
+#[tauri::command]
+fn vault_import_profile(
+    path: String,
+    state: State<AppState>,
+) -> Result<shared::Revised<export::ImportSummaryJson>, String> {
+    let raw = fs::read_to_string(&path).map_err(|e| format!("Failed to open profile: {}", e))?;
+    let profile: export::FillProfileJson =
+        serde_json::from_str(&raw).map_err(|e| format!("Invalid fill profile: {}", e))?;
+    let mut vault = state.vault.lock().map_err(|e| e.to_string())?;
+    let summary = export::import_profile(&profile, vault.as_mut(), &state.history)?;
+    drop(vault);
+    Ok(shared::Revised::new(summary, &state.vault))
+}
+
+/// Vault items waiting for review, oldest first
+///
+/// Low-confidence imports start here; until approved they are never
+/// auto-filled.
+#[tauri::command]
+fn vault_review_queue(state: State<AppState>) -> Result<Vec<VaultItemJson>, String> {
+    let vault = state.vault.lock().map_err(|e| e.to_string())?;
+    let items = review::queue(vault.as_ref())?;
+    Ok(items.into_iter().map(VaultItemJson::from).collect())
+}
+
+/// Approve a queued item (its confidence becomes 1.0) or reject it so it
+/// is never matched
+#[tauri::command]
+fn vault_review_resolve(
+    key: String,
+    decision: review::ReviewDecision,
+    state: State<AppState>,
+) -> Result<shared::Revised<VaultItemJson>, String> {
+    let mut vault = state.vault.lock().map_err(|e| e.to_string())?;
+    let item = review::resolve(vault.as_mut(), &state.history, &key, decision)?;
+    drop(vault);
+    Ok(shared::Revised::new(VaultItemJson::from(item), &state.vault))
+}
+
+/// Whether the vault is locked, and its lock settings
+#[tauri::command]
+fn vault_lock_status(lock_state: State<VaultLockState>) -> vault_lock::VaultLockStatusJson {
+    lock_state.lock.status(chrono::Utc::now())
+}
+
+/// Change when the vault locks by itself (on sleep, after idling)
+#[tauri::command]
+fn vault_lock_configure(
+    settings: vault_lock::LockSettingsJson,
+    lock_state: State<VaultLockState>,
+) -> vault_lock::VaultLockStatusJson {
+    lock_state.lock.configure(settings.into());
+    lock_state.lock.status(chrono::Utc::now())
+}
+
+/// Lock the vault now; the bridge refuses requests until it is unlocked,
+/// and a store that can be locked forgets its key until `vault_unlock`
+/// (with the passphrase, if it has one)
+#[tauri::command]
+fn vault_lock(
+    state: State<AppState>,
+    lock_state: State<VaultLockState>,
+) -> Result<vault_lock::VaultStatusJson, String> {
+    let now = chrono::Utc::now();
+    let mut vault = state.vault.lock()?;
+    if vault.is_lockable() {
+        vault.lock().map_err(|e| e.to_string())?;
+    }
+    lock_state.lock.lock(vault_lock::LockReason::Manual, now);
+    Ok(vault_lock::VaultStatusJson::new(
+        lock_state.lock.status(now),
+        vault.as_ref(),
+    ))
+}
+
+/// Unlock the vault; a vault with a passphrase needs it, and stays locked
+/// if it's wrong
+///
+/// The passphrase is checked even if the store itself hasn't locked yet
+/// (the idle lock thread reaches it a moment after the timer runs out).
+#[tauri::command]
+fn vault_unlock(
+    passphrase: Option<String>,
+    state: State<AppState>,
+    lock_state: State<VaultLockState>,
+) -> Result<vault_lock::VaultStatusJson, String> {
+    let mut vault = state.vault.lock()?;
+    if vault.unlock_needs_passphrase() {
+        let passphrase = passphrase
+            .as_deref()
+            .ok_or("The vault's passphrase is needed to unlock it")?;
+        // Locked first, so a wrong passphrase leaves the store locked too
+        if !vault.is_locked() {
+            vault.lock().map_err(|e| e.to_string())?;
+        }
+        vault.unlock(passphrase).map_err(|e| e.to_string())?;
+    } else if vault.is_locked() {
+        vault.unlock("").map_err(|e| e.to_string())?;
+    }
+    Ok(vault_lock::VaultStatusJson::new(
+        lock_state.lock.unlock(chrono::Utc::now()),
+        vault.as_ref(),
+    ))
+}
+
+/// Whether the vault is locked, either way, and whether its store can be
+/// locked and needs a passphrase to unlock
+#[tauri::command]
+fn vault_status(
+    state: State<AppState>,
+    lock_state: State<VaultLockState>,
+) -> Result<vault_lock::VaultStatusJson, String> {
+    let vault = state.vault.lock()?;
+    Ok(vault_lock::VaultStatusJson::new(
+        lock_state.lock.status(chrono::Utc::now()),
+        vault.as_ref(),
+    ))
+}
+
+/// Report user activity in the app, resetting the idle lock timer
+///
+/// Fails when the vault is locked, including when the timer ran out
+/// before this call.
+#[tauri::command]
+fn vault_activity(lock_state: State<VaultLockState>) -> Result<(), String> {
+    lock_state.accessed()
+}
+
+/// Change an app setting; takes effect at once
+///
+/// `autoLockMinutes` sets the vault lock's idle timeout: the vault locks
+/// after that many minutes without vault access (0 never locks).
+#[tauri::command]
+fn settings_set(
+    name: String,
+    value: serde_json::Value,
+    lock_state: State<VaultLockState>,
+) -> Result<(), String> {
+    match name.as_str() {
+        "autoLockMinutes" => {
+            let minutes = value
+                .as_u64()
+                .and_then(|minutes| u32::try_from(minutes).ok())
+                .ok_or_else(|| format!("autoLockMinutes must be a whole number, got {}", value))?;
+            lock_state.lock.set_idle_timeout(
+                (minutes > 0).then(|| std::time::Duration::from_secs(u64::from(minutes) * 60)),
+            );
+            Ok(())
+        }
+        _ => Err(format!("Unknown setting: {}", name)),
+    }
+}
+
+/// Turn recording of vault change history on or off
+#[tauri::command]
+fn vault_history_set_enabled(enabled: bool, state: State<AppState>) -> Result<(), String> {
+    state.history.set_enabled(enabled);
+    Ok(())
+}
+
+/// Flag vault items outside Financial whose values look like SSNs, card
+/// numbers or IBANs (keys and detected type only, never the values)
+#[tauri::command]
+fn vault_scan_pii(state: State<AppState>) -> Result<Vec<pii::PiiFindingJson>, String> {
+    let vault = state.vault.lock().map_err(|e| e.to_string())?;
+    let items = vault.list().map_err(|e| e.to_string())?;
+    Ok(pii::scan(&items))
+}
+
+// ============================================================================
+// Tauri Commands - Form Snapshots
+// ============================================================================
+
+#[tauri::command]
+fn get_latest_form_snapshot(
+    state: State<FormSnapshotState>,
+) -> Result<Option<FormSnapshotJson>, String> {
+    let latest = state.latest.lock().map_err(|e| e.to_string())?;
+    Ok(latest.clone())
+}
+
+/// Ask the extension to scrape `domain` again
+///
+/// Returns the pending request, which is the existing one if the domain was
+/// already asked for.
+#[tauri::command]
+fn request_recapture(
+    domain: String,
+    snapshot_state: State<FormSnapshotState>,
+) -> Result<recapture::RecaptureRequestJson, String> {
+    let (request, _) = snapshot_state
+        .recapture
+        .lock()
+        .map_err(|e| e.to_string())?
+        .request(
+            &domain,
+            None,
+            None,
+            recapture::RecaptureReason::Requested,
+            chrono::Utc::now(),
+        );
+    Ok(request)
+}
+
+/// Local fill plan for `snapshot` with its template and the user's overrides
+///
+/// For a step of a multi-step form, fields are also matched against the
+/// plans of the session's earlier steps.
+fn local_fill_plan(
+    snapshot: &FormSnapshotJson,
+    items: &[VaultItem],
+    sessions: &sessions::FormSessionStore,
+    template_state: &TemplateState,
+    override_state: &OverrideState,
+) -> Result<matching::FillPlanJson, String> {
+    let plan_for = |snapshot: &FormSnapshotJson, earlier: &[matching::EarlierStep<'_>]| {
+        let template = template_state
+            .store
+            .lock()
+            .map_err(|e| e.to_string())?
+            .find(&snapshot.domain, &snapshot.fingerprint.hash)
+            .cloned();
+        let field_overrides = override_state
+            .store
+            .lock()
+            .map_err(|e| e.to_string())?
+            .for_form(&snapshot.domain, &snapshot.fingerprint.hash);
+        Ok::<_, String>(matching::generate_session_fill_plan(
+            snapshot,
+            items,
+            template.as_ref(),
+            &field_overrides,
+            earlier,
+        ))
+    };
+
+    let earlier_snapshots = sessions.lock()?.earlier_steps(snapshot);
+    let earlier_plans = earlier_snapshots
+        .iter()
+        .map(|earlier| plan_for(earlier, &[]))
+        .collect::<Result<Vec<_>, _>>()?;
+    let earlier: Vec<matching::EarlierStep<'_>> = earlier_snapshots
+        .iter()
+        .zip(&earlier_plans)
+        .map(|(snapshot, plan)| matching::EarlierStep { snapshot, plan })
+        .collect();
+    plan_for(snapshot, &earlier)
+}
+
+/// Generate a fill plan for the latest snapshot using the local matching tiers
+///
+/// A plan eager analysis already made for the same form and vault is used
+/// as is; a background job still running is cancelled instead.
+#[tauri::command]
+fn generate_fill_plan(
+    snapshot_state: State<FormSnapshotState>,
+    state: State<AppState>,
+    template_state: State<TemplateState>,
+    override_state: State<OverrideState>,
+    acceptance_state: State<AcceptanceState>,
+    eager_state: State<EagerAnalysisState>,
+    lock_state: State<VaultLockState>,
+) -> Result<Option<matching::FillPlanJson>, String> {
+    lock_state.accessed()?;
+    let snapshot = snapshot_state
+        .latest
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clone();
+    let Some(snapshot) = snapshot else {
+        return Ok(None);
+    };
+
+    // A plan means a fill command is likely on its way; poll faster
+    let now = chrono::Utc::now();
+    *snapshot_state
+        .plan_activity
+        .lock()
+        .map_err(|e| e.to_string())? = Some(polling::PlanActivity {
+        domain: fill::canonicalize_domain(&snapshot.domain),
+        generated_at: now,
+    });
+
+    // Plan from what we have, but ask for a fresh look at an old page
+    if !polling::snapshot_is_fresh(&snapshot.captured_at, now) {
+        snapshot_state
+            .recapture
+            .lock()
+            .map_err(|e| e.to_string())?
+            .request(
+                &snapshot.domain,
+                Some(&snapshot.url),
+                Some(&snapshot.fingerprint.hash),
+                recapture::RecaptureReason::StaleSnapshot,
+                now,
+            );
+    }
+
+    let (vault_revision, items) = {
+        let vault = state.vault.lock().map_err(|e| e.to_string())?;
+        (
+            state.vault.revision(),
+            vault.list().map_err(|e| e.to_string())?,
+        )
+    };
+    eager_state.analysis.cancel();
+    let mut plan = match eager_state.analysis.lookup(&snapshot, vault_revision) {
+        Some(plan) => plan,
+        None => local_fill_plan(
+            &snapshot,
+            &items,
+            &snapshot_state.sessions,
+            &template_state,
+            &override_state,
+        )?,
+    };
+    // Warn about fills the field's input type would refuse or mangle
+    let mismatches = fill::reconcile_types(&plan.recommendations, &snapshot.fields, &items);
+    plan.warnings
+        .extend(mismatches.into_iter().map(|m| format!("{}: {}", m.field_id, m.reason)));
+    let mut acceptances = acceptance_state.store.lock().map_err(|e| e.to_string())?;
+    Ok(Some(acceptances.register_plan(
+        plan,
+        &fill::canonicalize_domain(&snapshot.domain),
+        &snapshot.fields,
+        &items,
+    )))
+}
+
+/// The vault keys a plan fills from
+fn plan_vault_keys(plan: &matching::FillPlanJson) -> Vec<&str> {
+    plan.recommendations
+        .iter()
+        .map(|rec| rec.vault_key.as_str())
+        .collect()
+}
+
+/// Check a plan's fills against the input types of the latest snapshot
+///
+/// Returns the fills whose vault value doesn't fit its field (an email
+/// address planned for a `tel` input, a phone number for a `date` one).
+#[tauri::command]
+fn reconcile_field_types(
+    plan: matching::FillPlanJson,
+    snapshot_state: State<FormSnapshotState>,
+    state: State<AppState>,
+) -> Result<Vec<fill::TypeMismatchJson>, String> {
+    let Some(snapshot) = snapshot_state.latest.latest()? else {
+        return Err("No form snapshot to reconcile against".to_string());
+    };
+    let items: Vec<VaultItem> = state
+        .vault
+        .lock()?
+        .get_many(&plan_vault_keys(&plan))
+        .map_err(|e| e.to_string())?
+        .into_values()
+        .collect();
+    Ok(fill::reconcile_types(&plan.recommendations, &snapshot.fields, &items))
+}
+
+/// Check a plan's fills against the user's fill rules for the page of the
+/// latest snapshot
+///
+/// Returns a verdict for every fill: allowed, blocked, or needing
+/// confirmation, with the rule that decided it.
+#[tauri::command]
+fn check_fill_rules(
+    plan: matching::FillPlanJson,
+    snapshot_state: State<FormSnapshotState>,
+    state: State<AppState>,
+    rules_state: State<FillRulesState>,
+) -> Result<Vec<rules::FieldVerdictJson>, String> {
+    let Some(snapshot) = snapshot_state.latest.latest()? else {
+        return Err("No form snapshot to check against".to_string());
+    };
+    let items = state
+        .vault
+        .lock()?
+        .get_many(&plan_vault_keys(&plan))
+        .map_err(|e| e.to_string())?;
+    Ok(rules::check_plan(&rules_state.rules, &plan, &snapshot, |key| {
+        items.get(key).map(|item| item.category.clone())
+    }))
+}
+
+/// Explain in plain language why each field of the latest snapshot was or
+/// wasn't matched
+///
+/// Local matches are explained by the rule that made them; pass the results
+/// of `llm_analyze_snapshot` as `analyses` to include the LLM's reasoning
+/// for the fields the local tiers left open.
+#[tauri::command]
+fn match_explanations(
+    analyses: Option<Vec<llm::FieldAnalysisJson>>,
+    snapshot_state: State<FormSnapshotState>,
+    state: State<AppState>,
+    template_state: State<TemplateState>,
+    override_state: State<OverrideState>,
+) -> Result<Vec<explain::MatchExplanationJson>, String> {
+    let Some(snapshot) = snapshot_state.latest.latest()? else {
+        return Ok(Vec::new());
+    };
+    let items = state
+        .vault
+        .lock()
+        .map_err(|e| e.to_string())?
+        .list()
+        .map_err(|e| e.to_string())?;
+    let plan = local_fill_plan(
+        &snapshot,
+        &items,
+        &snapshot_state.sessions,
+        &template_state,
+        &override_state,
+    )?;
+    Ok(explain::explain_plan(
+        &snapshot,
+        &plan,
+        &analyses.unwrap_or_default(),
+    ))
+}
+
+/// Kept plans whose content hash is `hash`, oldest first
+///
+/// Regenerating an identical plan gives it a new `planId` but the same
+/// `planHash`, so this finds earlier copies of the same plan.
+#[tauri::command]
+fn fill_plan_find_by_hash(
+    hash: String,
+    acceptance_state: State<AcceptanceState>,
+) -> Result<Vec<matching::FillPlanJson>, String> {
+    Ok(acceptance_state
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .find_by_hash(&hash))
+}
+
+/// Fill one field of a generated plan with a typed value
+///
+/// The field is marked overridden and goes to review; maxLength and
+/// pattern problems come back as warnings rather than errors.
+#[tauri::command]
+fn fill_plan_set_value(
+    plan_id: String,
+    field_id: String,
+    literal_value: String,
+    acceptance_state: State<AcceptanceState>,
+) -> Result<acceptance::PlanFieldJson, String> {
+    acceptance_state
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .set_field_value(&plan_id, &field_id, &literal_value)
+}
+
+/// Leave one field of a generated plan out of the fill
+#[tauri::command]
+fn fill_plan_exclude(
+    plan_id: String,
+    field_id: String,
+    acceptance_state: State<AcceptanceState>,
+) -> Result<acceptance::PlanFieldJson, String> {
+    acceptance_state
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .exclude_field(&plan_id, &field_id)
+}
+
+/// Put an excluded field of a generated plan back into the fill
+#[tauri::command]
+fn fill_plan_include(
+    plan_id: String,
+    field_id: String,
+    acceptance_state: State<AcceptanceState>,
+) -> Result<acceptance::PlanFieldJson, String> {
+    acceptance_state
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .include_field(&plan_id, &field_id)
+}
+
+/// Undo every edit to one field of a generated plan
+#[tauri::command]
+fn fill_plan_reset_field(
+    plan_id: String,
+    field_id: String,
+    acceptance_state: State<AcceptanceState>,
+) -> Result<acceptance::PlanFieldJson, String> {
+    acceptance_state
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .reset_field(&plan_id, &field_id)
+}
+
+/// Freeze the fields the user approved from a generated plan
+///
+/// Values are resolved from the vault now, with `overrides` and the plan's
+/// field edits applied; the returned acceptance is what
+/// `fill_command_create` fills, whatever changes afterwards. Pass the
+/// `planRevision` the dialog last showed to refuse a plan edited since.
+#[tauri::command]
+fn fill_plan_accept(
+    plan_id: String,
+    plan_revision: Option<u64>,
+    accepted_field_ids: Vec<String>,
+    overrides: Option<Vec<acceptance::PlanOverrideJson>>,
+    state: State<AppState>,
+    acceptance_state: State<AcceptanceState>,
+) -> Result<acceptance::AcceptedPlanJson, String> {
+    let items = state
+        .vault
+        .lock()
+        .map_err(|e| e.to_string())?
+        .list()
+        .map_err(|e| e.to_string())?;
+    acceptance_state
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .accept(
+            &plan_id,
+            plan_revision,
+            &accepted_field_ids,
+            &overrides.unwrap_or_default(),
+            &items,
+            chrono::Utc::now(),
+        )
+}
+
+/// Time the local matching pipeline over `snapshot`, `iterations` times.
+/// Runs offline; nothing is filled or recorded.
+#[tauri::command]
+fn benchmark_matching(
+    snapshot: FormSnapshotJson,
+    iterations: u32,
+    state: State<AppState>,
+    template_state: State<TemplateState>,
+) -> Result<benchmark::BenchmarkReportJson, String> {
+    let template = template_state
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .find(&snapshot.domain, &snapshot.fingerprint.hash)
+        .cloned();
+    let items = state
+        .vault
+        .lock()
+        .map_err(|e| e.to_string())?
+        .list()
+        .map_err(|e| e.to_string())?;
+    Ok(benchmark::benchmark_matching(
+        &snapshot,
+        &items,
+        template.as_ref(),
+        iterations,
+    ))
+}
+
+// ============================================================================
+// Tauri Commands - Form Templates
+// ============================================================================
+
+/// Save the field mappings for a form as a template
+#[tauri::command]
+fn template_save(
+    domain: String,
+    fingerprint: String,
+    mappings: Vec<templates::TemplateMappingJson>,
+    state: State<TemplateState>,
+    eager_state: State<EagerAnalysisState>,
+) -> Result<templates::FormTemplateJson, String> {
+    let mut store = state.store.lock().map_err(|e| e.to_string())?;
+    eager_state.analysis.invalidate();
+    store.save(&domain, &fingerprint, mappings)
+}
+
+/// List all saved templates
+#[tauri::command]
+fn template_list(state: State<TemplateState>) -> Result<Vec<templates::FormTemplateJson>, String> {
+    let store = state.store.lock().map_err(|e| e.to_string())?;
+    Ok(store.list().to_vec())
+}
+
+/// Report per-field fill results so failing template mappings can heal
+#[tauri::command]
+fn template_report_fill_result(
+    template_id: String,
+    results: Vec<templates::FieldFillResultJson>,
+    state: State<TemplateState>,
+    audit_state: State<AuditState>,
+    eager_state: State<EagerAnalysisState>,
+) -> Result<templates::TemplateHealthJson, String> {
+    let mut store = state.store.lock().map_err(|e| e.to_string())?;
+    eager_state.analysis.invalidate();
+    if let Some(template) = store.get(&template_id) {
+        if let Err(e) =
+            audit_state
+                .telemetry
+                .record_fill_results(&template.domain, &template.fingerprint, &results)
+        {
+            eprintln!("[Asterisk Telemetry] {}", e);
+        }
+    }
+    store.apply_fill_results(&template_id, &results)
+}
+
+/// Get the heal count and suspect mappings for a template
+#[tauri::command]
+fn template_health(
+    template_id: String,
+    state: State<TemplateState>,
+) -> Result<Option<templates::TemplateHealthJson>, String> {
+    let store = state.store.lock().map_err(|e| e.to_string())?;
+    Ok(store.health(&template_id))
+}
+
+/// Templates whose mappings point at vault keys that no longer exist
+#[tauri::command]
+fn template_verify(
+    state: State<TemplateState>,
+    app_state: State<AppState>,
+) -> Result<Vec<templates::TemplateProblemJson>, String> {
+    let items = app_state
+        .vault
+        .lock()
+        .map_err(|e| e.to_string())?
+        .list()
+        .map_err(|e| e.to_string())?;
+    let keys = items.iter().map(|item| item.key.as_str()).collect();
+    let store = state.store.lock().map_err(|e| e.to_string())?;
+    Ok(store.verify(&keys))
+}
+
+/// Drop template mappings to deleted vault keys, and templates left empty
+///
+/// Returns what was changed.
+#[tauri::command]
+fn template_repair(
+    state: State<TemplateState>,
+    app_state: State<AppState>,
+    eager_state: State<EagerAnalysisState>,
+) -> Result<Vec<templates::TemplateProblemJson>, String> {
+    let items = app_state
+        .vault
+        .lock()
+        .map_err(|e| e.to_string())?
+        .list()
+        .map_err(|e| e.to_string())?;
+    let keys = items.iter().map(|item| item.key.as_str()).collect();
+    let mut store = state.store.lock().map_err(|e| e.to_string())?;
+    eager_state.analysis.invalidate();
+    store.repair(&keys)
+}
+
+// ============================================================================
+// Tauri Commands - Field Overrides
+// ============================================================================
+
+/// Always fill `field_id` on this form from `vault_key`, ahead of every
+/// matching tier
+#[tauri::command]
+fn set_field_override(
+    domain: String,
+    fingerprint: String,
+    field_id: String,
+    vault_key: String,
+    state: State<OverrideState>,
+    eager_state: State<EagerAnalysisState>,
+) -> Result<overrides::FieldOverrideJson, String> {
+    let mut store = state.store.lock().map_err(|e| e.to_string())?;
+    eager_state.analysis.invalidate();
+    store.set(&domain, &fingerprint, &field_id, &vault_key)
+}
+
+/// Remove a field override; returns whether there was one
+#[tauri::command]
+fn remove_field_override(
+    domain: String,
+    fingerprint: String,
+    field_id: String,
+    state: State<OverrideState>,
+    eager_state: State<EagerAnalysisState>,
+) -> Result<bool, String> {
+    let mut store = state.store.lock().map_err(|e| e.to_string())?;
+    eager_state.analysis.invalidate();
+    store.remove(&domain, &fingerprint, &field_id)
+}
+
+/// List all saved field overrides
+#[tauri::command]
+fn list_field_overrides(
+    state: State<OverrideState>,
+) -> Result<Vec<overrides::FieldOverrideJson>, String> {
+    let store = state.store.lock().map_err(|e| e.to_string())?;
+    Ok(store.list().to_vec())
+}
+
+// ============================================================================
+// Tauri Commands - Fill Commands
+// ============================================================================
+
+/// Queue a checked fill command for the extension, or hold it until the user
+/// consents to its domain; returns whether it was queued
+fn queue_fill_command(
+    command: FillCommandJson,
+    consent_store: &consent::SharedConsentStore,
+    fill_command_store: &shared::FillCommandStore,
+    events: &(dyn Fn(&str, serde_json::Value) + Send + Sync),
+) -> Result<bool, String> {
+    let queued = queue_fill_commands(
+        std::slice::from_ref(&command),
+        consent_store,
+        fill_command_store,
+        events,
+    )?;
+    Ok(queued[0])
+}
+
+/// Queue several checked fill commands, holding back those whose domain
+/// needs consent; returns whether each one was queued
+///
+/// Both stores are locked once for the whole batch, so a poll never sees
+/// part of it, and consent is decided once per domain, so a "once" grant
+/// covers every command for that domain.
+fn queue_fill_commands(
+    commands: &[FillCommandJson],
+    consent_store: &consent::SharedConsentStore,
+    fill_command_store: &shared::FillCommandStore,
+    events: &(dyn Fn(&str, serde_json::Value) + Send + Sync),
+) -> Result<Vec<bool>, String> {
+    // One consent guard for the check and the hold, so a grant can't land
+    // between them and miss a command
+    let mut consent = consent_store.lock()?;
+    let mut store = fill_command_store.lock()?;
+    let mut authorized_domains = HashMap::new();
+    let mut queued = Vec::with_capacity(commands.len());
+    let mut requests = Vec::new();
+    for command in commands {
+        let authorized = *authorized_domains
+            .entry(command.target_domain.clone())
+            .or_insert_with(|| consent.authorize(&command.target_domain));
+        if authorized {
+            // Remove any existing command with same ID
+            store.retain(|c| c.id != command.id);
+            store.push(command.clone());
+        } else {
+            println!(
+                "[Asterisk HTTP] Awaiting consent for {}: {}",
+                command.target_domain, command.id
+            );
+            requests.push(consent::ConsentRequestJson {
+                domain: command.target_domain.clone(),
+                command_id: command.id.clone(),
+            });
+            consent.hold(command.clone());
+        }
+        queued.push(authorized);
+    }
+    drop(store);
+    drop(consent);
+
+    for request_event in requests {
+        events(
+            consent::CONSENT_REQUIRED_EVENT,
+            serde_json::to_value(&request_event).unwrap_or_default(),
+        );
+    }
+    Ok(queued)
+}
+
+/// Result of `fill_command_create`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FillCommandCreatedJson {
+    pub command: FillCommandJson,
+    /// "ok" when queued, "awaiting_consent" when held for the user
+    pub status: String,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub warnings: Vec<fill::MaxLengthIssueJson>,
+}
+
+/// Create the fill command for a plan acceptance
+///
+/// The command carries exactly the values frozen by `fill_plan_accept`;
+/// vault or plan changes since then don't reach it. Each acceptance yields
+/// one command.
+#[tauri::command]
+fn fill_command_create(
+    acceptance_id: String,
+    capture_undo: Option<bool>,
+    acceptance_state: State<AcceptanceState>,
+    snapshot_state: State<FormSnapshotState>,
+    fill_state: State<FillCommandState>,
+    consent_state: State<ConsentState>,
+    app: tauri::AppHandle,
+) -> Result<shared::Revised<FillCommandCreatedJson>, String> {
+    let command = acceptance_state
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .create_command(
+            &acceptance_id,
+            capture_undo.unwrap_or(false),
+            fill_state.clock.now(),
+        )?;
+    fill::validate_command(&command).map_err(|problems| {
+        problems.iter().map(i18n::Message::render).collect::<Vec<_>>().join("; ")
+    })?;
+
+    let policy = *fill_state
+        .max_length_policy
+        .lock()
+        .map_err(|e| e.to_string())?;
+    let warnings = match &*snapshot_state.latest.lock().map_err(|e| e.to_string())? {
+        Some(snapshot) if fill::canonicalize_domain(&snapshot.domain) == command.target_domain => {
+            fill::check_max_lengths(&command, &snapshot.fields, policy)
+        }
+        _ => Vec::new(),
+    };
+    if warnings.iter().any(|issue| issue.rejected) {
+        return Err("Fill values exceed field maxLength".to_string());
+    }
+
+    let emit = move |event: &str, payload: serde_json::Value| {
+        let _ = app.emit(event, payload);
+    };
+    let authorized = queue_fill_command(
+        command.clone(),
+        &consent_state.store,
+        &fill_state.commands,
+        &emit,
+    )?;
+    let created = FillCommandCreatedJson {
+        command,
+        status: if authorized { "ok" } else { "awaiting_consent" }.to_string(),
+        warnings,
+    };
+    Ok(shared::Revised::new(created, &fill_state.commands))
+}
+
+/// Check a fill command's values against the latest snapshot's maxLength limits
+///
+/// Uses the configured policy unless one is given explicitly.
+#[tauri::command]
+fn fill_command_check_lengths(
+    command: FillCommandJson,
+    policy: Option<fill::MaxLengthPolicy>,
+    snapshot_state: State<FormSnapshotState>,
+    fill_state: State<FillCommandState>,
+) -> Result<Vec<fill::MaxLengthIssueJson>, String> {
+    let policy = match policy {
+        Some(policy) => policy,
+        None => *fill_state
+            .max_length_policy
+            .lock()
+            .map_err(|e| e.to_string())?,
+    };
+
+    let latest = snapshot_state.latest.lock().map_err(|e| e.to_string())?;
+    match &*latest {
+        Some(snapshot)
+            if fill::canonicalize_domain(&snapshot.domain)
+                == fill::canonicalize_domain(&command.target_domain) =>
+        {
+            Ok(fill::check_max_lengths(&command, &snapshot.fields, policy))
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Queue several fill commands, usually for different sites, as one group
+/// whose progress can be followed and which can be cancelled together
+///
+/// Every command is checked before any is queued, as with a bridge batch.
+/// Each is then queued or held for consent on its own.
+#[tauri::command]
+fn fill_command_create_group(
+    mut commands: Vec<FillCommandJson>,
+    snapshot_state: State<FormSnapshotState>,
+    fill_state: State<FillCommandState>,
+    consent_state: State<ConsentState>,
+    app: tauri::AppHandle,
+) -> Result<shared::Revised<groups::GroupCreatedJson>, String> {
+    let policy = *fill_state
+        .max_length_policy
+        .lock()
+        .map_err(|e| e.to_string())?;
+    let snapshot = snapshot_state.latest.latest()?;
+    let warnings = fill::validate_batch(&mut commands, snapshot.as_ref(), policy).map_err(
+        |refused| {
+            refused
+                .iter()
+                .map(|r| format!("{}: {}", r.id, r.problems.join(", ")))
+                .collect::<Vec<_>>()
+                .join("; ")
+        },
+    )?;
+
+    let group_id = fill_state.groups.create(&commands, fill_state.clock.now());
+    let emit = move |event: &str, payload: serde_json::Value| {
+        let _ = app.emit(event, payload);
+    };
+    let results = commands
+        .into_iter()
+        .zip(warnings)
+        .map(|(command, warnings)| {
+            let id = command.id.clone();
+            let authorized =
+                queue_fill_command(command, &consent_state.store, &fill_state.commands, &emit)?;
+            Ok(fill::BatchResultJson {
+                id,
+                status: if authorized { "ok" } else { "awaiting_consent" }.to_string(),
+                warnings,
+            })
+        })
+        .collect::<Result<_, String>>()?;
+    println!("[Asterisk Fill] Queued fill command group {}", group_id);
+    let created = groups::GroupCreatedJson { group_id, results };
+    Ok(shared::Revised::new(created, &fill_state.commands))
+}
+
+/// Combined progress of a fill command group
+#[tauri::command]
+fn fill_command_group_status(
+    group_id: String,
+    state: State<FillCommandState>,
+) -> Result<groups::GroupStatusJson, String> {
+    state.groups.status(&group_id, state.clock.now())
+}
+
+/// Cancel every member of a group not yet applied: queued ones are
+/// withdrawn from the extension and held ones dropped
+#[tauri::command]
+fn fill_command_cancel_group(
+    group_id: String,
+    state: State<FillCommandState>,
+    consent_state: State<ConsentState>,
+) -> Result<shared::Revised<groups::GroupStatusJson>, String> {
+    let cancelled = state.groups.cancel(&group_id)?;
+    state
+        .commands
+        .lock()
+        .map_err(|e| e.to_string())?
+        .retain(|c| !cancelled.contains(&c.id));
+    consent_state.store.lock()?.cancel(&cancelled);
+    let status = state.groups.status(&group_id, state.clock.now())?;
+    Ok(shared::Revised::new(status, &state.commands))
+}
+
+/// Set how over-long fill values are handled when commands are posted
+#[tauri::command]
+fn set_max_length_policy(
+    policy: fill::MaxLengthPolicy,
+    state: State<FillCommandState>,
+) -> Result<(), String> {
+    let mut current = state.max_length_policy.lock().map_err(|e| e.to_string())?;
+    *current = policy;
+    Ok(())
+}
+
+// ============================================================================
+// Tauri Commands - Consent
+// ============================================================================
+
+/// Allow fills on a domain and release its pending commands
+///
+/// Returns the number of commands released to the extension.
+#[tauri::command]
+fn consent_grant(
+    domain: String,
+    scope: consent::ConsentScope,
+    state: State<ConsentState>,
+    fill_state: State<FillCommandState>,
+) -> Result<usize, String> {
+    let released = state.store.lock()?.grant(&domain, scope)?;
+    let count = released.len();
+
+    let mut commands = fill_state.commands.lock().map_err(|e| e.to_string())?;
+    for command in released {
+        commands.retain(|c| c.id != command.id);
+        commands.push(command);
+    }
+    Ok(count)
+}
+
+/// Refuse fills on a domain, cancelling its pending commands
+///
+/// Returns the number of commands cancelled.
+#[tauri::command]
+fn consent_deny(domain: String, state: State<ConsentState>) -> Result<usize, String> {
+    let mut store = state.store.lock()?;
+    Ok(store.deny(&domain).len())
+}
+
+/// Withdraw a previously granted consent
+#[tauri::command]
+fn consent_revoke(domain: String, state: State<ConsentState>) -> Result<(), String> {
+    let mut store = state.store.lock()?;
+    store.revoke(&domain)
+}
+
+/// List current consent grants
+#[tauri::command]
+fn consent_list(state: State<ConsentState>) -> Result<Vec<consent::ConsentGrantJson>, String> {
+    let store = state.store.lock()?;
+    Ok(store.grants().to_vec())
+}
+
+/// Consent status for a single domain
+#[tauri::command]
+fn consent_status(
+    domain: String,
+    state: State<ConsentState>,
+) -> Result<consent::ConsentStatusJson, String> {
+    let store = state.store.lock()?;
+    Ok(store.status(&domain))
+}
+
+// ============================================================================
+// Tauri Commands - Recall
+// ============================================================================
+
+/// Turn "remember filled values" on or off for a domain
+#[tauri::command]
+fn recall_set_enabled(
+    domain: String,
+    enabled: bool,
+    state: State<RecallState>,
+) -> Result<(), String> {
+    let mut store = state.store.lock().map_err(|e| e.to_string())?;
+    store.set_enabled(&domain, enabled)
+}
+
+/// Domains with recall turned on
+#[tauri::command]
+fn recall_domains(state: State<RecallState>) -> Result<Vec<String>, String> {
+    let store = state.store.lock().map_err(|e| e.to_string())?;
+    Ok(store.enabled_domains())
+}
+
+/// Search remembered values for a domain by label or value
+#[tauri::command]
+fn recall_search(
+    domain: String,
+    query: String,
+    state: State<RecallState>,
+) -> Result<Vec<recall::RecallRecordJson>, String> {
+    let store = state.store.lock().map_err(|e| e.to_string())?;
+    store.search(&domain, &query)
+}
+
+/// Delete every remembered value for a domain
+///
+/// Returns the number of records removed.
+#[tauri::command]
+fn recall_purge(domain: String, state: State<RecallState>) -> Result<usize, String> {
+    let mut store = state.store.lock().map_err(|e| e.to_string())?;
+    store.purge(&domain)
+}
+
+// ============================================================================
+// Tauri Commands - Audit Log
+// ============================================================================
+
+/// Append a new audit entry to the log file; a retried entry id is a no-op
+#[tauri::command]
+fn audit_append(
+    mut entry: AuditEntryJson,
+    state: State<AuditState>,
+    recall_state: State<RecallState>,
+    acceptance_state: State<AcceptanceState>,
+    snapshot_state: State<FormSnapshotState>,
+) -> Result<(), String> {
+    // A retry of an append that already landed; don't record it twice
+    if state.log.contains(&entry.id)? {
+        return Ok(());
+    }
+
+    entry.recall_recorded = recall_state
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .is_enabled(&entry.domain);
+
+    let mut accepted = None;
+    if let Some(acceptance_id) = &entry.acceptance_id {
+        let acceptances = acceptance_state.store.lock().map_err(|e| e.to_string())?;
+        entry.acceptance_hash =
+            Some(acceptances.verify(acceptance_id, entry.acceptance_hash.as_deref())?);
+        if entry.fill_command_id.is_none() {
+            entry.fill_command_id = acceptances.command_id(acceptance_id).map(String::from);
+        }
+        accepted = acceptances.get(acceptance_id).cloned();
+        entry.plan_hash = accepted.as_ref().map(|a| a.plan_hash.clone());
+    }
+
+    if state.telemetry.is_enabled() {
+        let fields = snapshot_state
+            .latest
+            .lock()
+            .map_err(|e| e.to_string())?
+            .as_ref()
+            .filter(|s| s.fingerprint.hash == entry.fingerprint)
+            .map(|s| s.fields.clone())
+            .unwrap_or_default();
+        if let Err(e) = state.telemetry.record_plan(&entry, accepted.as_ref(), &fields) {
+            eprintln!("[Asterisk Telemetry] {}", e);
+        }
+    }
+
+    state.log.append(entry).map(|_| ())
+}
+
+/// Undo a fill recorded in the audit log, restoring each field's prior value
+///
+/// Only works for fills whose command set `captureUndo`, within
+/// `undo::UNDO_WINDOW_MINUTES` of the fill. The reverse command is checked
+/// and queued like any other fill command, so it is held when the domain no
+/// longer has consent.
+#[tauri::command]
+fn undo_fill(
+    audit_id: String,
+    state: State<AuditState>,
+    undo_state: State<UndoState>,
+    fill_state: State<FillCommandState>,
+    consent_state: State<ConsentState>,
+    app: tauri::AppHandle,
+) -> Result<shared::Revised<FillCommandCreatedJson>, String> {
+    let entry = state
+        .log
+        .get(&audit_id)?
+        .ok_or_else(|| format!("Audit entry {} not found", audit_id))?;
+    let command_id = entry
+        .fill_command_id
+        .ok_or_else(|| "This fill did not capture prior values, so it can't be undone".to_string())?;
+
+    let command = undo_state
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .undo_command(&command_id, fill_state.clock.now())?;
+    fill::validate_command(&command).map_err(|problems| {
+        problems.iter().map(i18n::Message::render).collect::<Vec<_>>().join("; ")
+    })?;
+
+    fill::prune_store(&fill_state.commands, fill_state.clock.now());
+    let emit = move |event: &str, payload: serde_json::Value| {
+        let _ = app.emit(event, payload);
+    };
+    let authorized = queue_fill_command(
+        command.clone(),
+        &consent_state.store,
+        &fill_state.commands,
+        &emit,
+    )?;
+    println!(
+        "[Asterisk Undo] {} undo of {} ({} fields)",
+        if authorized { "Queued" } else { "Holding" },
+        command_id,
+        command.fills.len()
+    );
+    let created = FillCommandCreatedJson {
+        command,
+        status: if authorized { "ok" } else { "awaiting_consent" }.to_string(),
+        warnings: Vec::new(),
+    };
+    Ok(shared::Revised::new(created, &fill_state.commands))
+}
+
+/// List audit entries with optional pagination
+#[tauri::command]
+fn audit_list(
+    limit: Option<u32>,
+    cursor: Option<u32>,
+    state: State<AuditState>,
+) -> Result<AuditListResponse, String> {
+    let limit = limit.unwrap_or(50).min(100) as usize;
+    let start = cursor.unwrap_or(0) as usize;
+
+    let mut entries = state.log.entries()?;
+
+    // Sort by createdAt descending (newest first)
+    entries.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+    // Apply pagination
+    let total = entries.len();
+    let page: Vec<AuditEntryJson> = entries.into_iter().skip(start).take(limit).collect();
+
+    let next_cursor = if start + page.len() < total {
+        Some((start + page.len()) as u32)
+    } else {
+        None
+    };
+
+    Ok(AuditListResponse {
+        items: page,
+        next_cursor,
+    })
+}
+
+/// Get a single audit entry by ID
+#[tauri::command]
+fn audit_get(id: String, state: State<AuditState>) -> Result<Option<AuditEntryJson>, String> {
+    Ok(state
+        .log
+        .entries()?
+        .into_iter()
+        .find(|entry| entry.id == id))
+}
+
+/// Clear all audit log entries for the active profile (deletes the file)
+#[tauri::command]
+fn audit_clear(state: State<AuditState>) -> Result<(), String> {
+    state.log.clear()
+}
+
+/// How much of each fill the audit log keeps
+#[tauri::command]
+fn audit_detail_get(state: State<AuditState>) -> Result<audit::AuditDetail, String> {
+    state.log.detail()
+}
+
+/// Change how much of each fill the audit log keeps from now on; entries
+/// already logged keep their level
+#[tauri::command]
+fn audit_detail_set(detail: audit::AuditDetail, state: State<AuditState>) -> Result<(), String> {
+    state.log.set_detail(detail)?;
+    println!("[Asterisk Audit] Detail level set to {:?}", detail);
+    Ok(())
+}
+
+/// The active profile, which selects the audit log in use
+#[tauri::command]
+fn profile_get_active(state: State<AuditState>) -> Result<String, String> {
+    state.log.active_profile()
+}
+
+/// Switch profile; audit reads and writes move to that profile's log
+///
+/// The extension's cached vault keys are dropped for a full resync.
+#[tauri::command]
+fn profile_switch(
+    profile: String,
+    state: State<AuditState>,
+    app_state: State<AppState>,
+) -> Result<(), String> {
+    state.log.switch_profile(&profile)?;
+    app_state.changes.reset(&app_state.vault)?;
+    println!("[Asterisk Audit] Switched to profile {}", profile);
+    Ok(())
+}
+
+/// Remove audit entries older than `older_than_days`
+///
+/// With `dry_run` nothing is removed; the report lists what would be.
+#[tauri::command]
+fn audit_prune(
+    older_than_days: u32,
+    dry_run: bool,
+    state: State<AuditState>,
+    maintenance_state: State<MaintenanceState>,
+) -> Result<maintenance::MaintenanceReportJson, String> {
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(i64::from(older_than_days));
+    let report = state.log.prune(cutoff, dry_run)?;
+    maintenance_state.ledger.record(&report)?;
+    Ok(report)
+}
+
+/// Fill activity between two local dates (YYYY-MM-DD, inclusive) in day or
+/// week buckets, for the dashboard heatmap
+///
+/// `offset_minutes` is the viewer's UTC offset (east positive, default 0);
+/// empty buckets are included.
+#[tauri::command]
+fn usage_heatmap(
+    from: String,
+    to: String,
+    bucket: audit::HeatmapBucket,
+    offset_minutes: Option<i32>,
+    state: State<AuditState>,
+) -> Result<Vec<audit::HeatmapBucketJson>, String> {
+    let parse = |date: &str| {
+        chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid date {}: {}", date, e))
+    };
+    state
+        .log
+        .usage_heatmap(parse(&from)?, parse(&to)?, bucket, offset_minutes.unwrap_or(0))
+}
+
+/// Export the audit entries from `from` to `to` (YYYY-MM-DD, UTC,
+/// inclusive) as a signed evidence bundle at `path`
+///
+/// The manifest is signed with this install's evidence key; check a bundle
+/// with `verify-bundle BUNDLE KEY` and the manifest's `publicKey`.
+#[tauri::command]
+fn audit_export_signed(
+    path: String,
+    from: String,
+    to: String,
+    state: State<AuditState>,
+) -> Result<evidence::EvidenceManifestJson, String> {
+    let parse = |date: &str| {
+        chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid date {}: {}", date, e))
+    };
+    let (from, to) = (parse(&from)?, parse(&to)?);
+    if from > to {
+        return Err(format!("Export range starts after it ends: {} > {}", from, to));
+    }
+    let key = evidence::load_or_create_key(&state.evidence_key)?;
+    let entries = evidence::entries_in_range(state.log.entries()?, from, to);
+    let file = fs::File::create(&path).map_err(|e| format!("Failed to create bundle: {}", e))?;
+    let manifest = evidence::write_bundle(file, &entries, (from, to), &key, chrono::Utc::now())?;
+    println!(
+        "[Asterisk Audit] Exported {} entries as evidence to {}",
+        manifest.entry_count, path
+    );
+    Ok(manifest)
+}
+
+/// Sites where Asterisk has filled forms: each distinct audit log domain
+/// with its fill count and last fill, most recent first
+#[tauri::command]
+fn audit_domains(state: State<AuditState>) -> Result<Vec<audit::AuditDomainJson>, String> {
+    state.log.domains()
+}
+
+/// Vault keys that never provided an applied field in the active
+/// profile's audit log: data set up but not yet needed
+#[tauri::command]
+fn vault_never_filled(
+    state: State<AppState>,
+    audit_state: State<AuditState>,
+) -> Result<Vec<String>, String> {
+    let mut keys = Vec::new();
+    state
+        .vault
+        .lock()?
+        .for_each(&mut |item| {
+            keys.push(item.key.clone());
+            Ok(())
+        })
+        .map_err(|e| e.to_string())?;
+    audit_state.log.never_filled(keys)
+}
+
+/// Remove fill commands that expired without being picked up
+#[tauri::command]
+fn fill_commands_purge_expired(
+    dry_run: bool,
+    state: State<FillCommandState>,
+    maintenance_state: State<MaintenanceState>,
+) -> Result<shared::Revised<maintenance::MaintenanceReportJson>, String> {
+    let mut commands = state.commands.lock().map_err(|e| e.to_string())?;
+    let report = maintenance::purge_expired_commands(&mut commands, state.clock.now(), dry_run);
+    drop(commands);
+    maintenance_state.ledger.record(&report)?;
+    Ok(shared::Revised::new(report, &state.commands))
+}
+
+/// Move the fill command clock forward by `seconds`, so expiry and cleanup
+/// can be tested without waiting; returns the clock's new time
+///
+/// Needs the test-helpers feature.
+#[tauri::command]
+fn fill_commands_advance_clock(
+    seconds: u32,
+    state: State<FillCommandState>,
+) -> Result<String, features::CommandError> {
+    features::require("test-helpers")?;
+    state
+        .clock
+        .advance(chrono::Duration::seconds(i64::from(seconds)));
+    Ok(state.clock.now().to_rfc3339())
+}
+
+/// List past (non-dry) maintenance runs, oldest first
+#[tauri::command]
+fn maintenance_history(
+    state: State<MaintenanceState>,
+) -> Result<Vec<maintenance::MaintenanceReportJson>, String> {
+    state.ledger.list()
+}
+
+/// Turn access logging of sensitive vault reads on or off
+#[tauri::command]
+fn access_log_set_enabled(
+    enabled: bool,
+    state: State<AuditState>,
+    storage_state: State<StorageState>,
+) -> Result<(), String> {
+    if enabled && storage_state.status.mode == storage::StorageMode::Memory {
+        return Err("Access logging needs a writable data directory".to_string());
+    }
+    state.access_log.set_enabled(enabled);
+    Ok(())
+}
+
+/// List recorded reads of sensitive vault items, oldest first
+#[tauri::command]
+fn access_log_list(state: State<AuditState>) -> Result<Vec<access::AccessRecordJson>, String> {
+    state.access_log.list()
+}
+
+/// Turn value-free matching telemetry (`matching.jsonl`) on or off
+#[tauri::command]
+fn matching_telemetry_set_enabled(
+    enabled: bool,
+    state: State<AuditState>,
+    storage_state: State<StorageState>,
+) -> Result<(), String> {
+    if enabled && storage_state.status.mode == storage::StorageMode::Memory {
+        return Err("Matching telemetry needs a writable data directory".to_string());
+    }
+    state.telemetry.set_enabled(enabled);
+    Ok(())
+}
+
+/// Per-tier acceptance and fill success rates from matching telemetry
+#[tauri::command]
+fn matching_metrics_summary(
+    state: State<AuditState>,
+) -> Result<telemetry::MatchingMetricsSummaryJson, String> {
+    state.telemetry.summary()
+}
+
+/// Report where data is being stored and whether storage is degraded
+#[tauri::command]
+fn storage_status(state: State<StorageState>) -> storage::StorageStatusJson {
+    state.status.clone()
+}
+
+/// Data files that failed to load at startup, with the subsystems running
+/// without them and the actions that would fix each one
+#[tauri::command]
+fn startup_problems(state: State<StartupState>) -> Result<startup::StartupPlanJson, String> {
+    Ok(state.plan.lock().map_err(|e| e.to_string())?.clone())
+}
+
+/// Carry out the chosen fix for the corrupt file at `path`; takes effect
+/// on the next launch
+#[tauri::command]
+fn startup_resolve(
+    path: String,
+    action: startup::StartupAction,
+    state: State<StartupState>,
+) -> Result<startup::StartupPlanJson, String> {
+    let mut plan = state.plan.lock().map_err(|e| e.to_string())?;
+    startup::resolve(&mut plan, &path, action)?;
+    Ok(plan.clone())
+}
+
+/// Disk used by each kind of Asterisk data, with item counts and caps
+#[tauri::command]
+fn storage_report(
+    state: State<AppState>,
+    storage_state: State<StorageState>,
+    audit_state: State<AuditState>,
+    template_state: State<TemplateState>,
+    cache_state: State<LlmCacheState>,
+    maintenance_state: State<MaintenanceState>,
+) -> Result<disk_usage::StorageReportJson, String> {
+    let context = disk_usage::StorageContext {
+        audit: &audit_state.log,
+        llm_cache: &cache_state.cache,
+        ledger: &maintenance_state.ledger,
+        vault_items: state.vault.lock()?.len(),
+        templates: template_state.store.lock().map_err(|e| e.to_string())?.list().len(),
+    };
+    let data_dir = storage_state.status.active_dir.as_deref().map(std::path::Path::new);
+    disk_usage::report(data_dir, &context)
+}
+
+/// Bytes Asterisk uses on disk, by category (audit, vault, caches,
+/// templates, backups, other)
+#[tauri::command]
+fn storage_footprint(storage_state: State<StorageState>) -> disk_usage::StorageFootprintJson {
+    let data_dir = storage_state.status.active_dir.as_deref().map(std::path::Path::new);
+    disk_usage::footprint(data_dir)
+}
+
+/// Prune the given artifacts with their own mechanisms
+///
+/// Audit entries older than `audit_retention_days` (default 90) are
+/// removed; the LLM answer cache is emptied. Returns bytes reclaimed per
+/// target.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn storage_trim(
+    targets: Vec<disk_usage::StorageArtifact>,
+    audit_retention_days: Option<u32>,
+    state: State<AppState>,
+    storage_state: State<StorageState>,
+    audit_state: State<AuditState>,
+    template_state: State<TemplateState>,
+    cache_state: State<LlmCacheState>,
+    maintenance_state: State<MaintenanceState>,
+) -> Result<Vec<disk_usage::StorageTrimJson>, String> {
+    let context = disk_usage::StorageContext {
+        audit: &audit_state.log,
+        llm_cache: &cache_state.cache,
+        ledger: &maintenance_state.ledger,
+        vault_items: state.vault.lock()?.len(),
+        templates: template_state.store.lock().map_err(|e| e.to_string())?.list().len(),
+    };
+    let data_dir = storage_state.status.active_dir.as_deref().map(std::path::Path::new);
+    disk_usage::trim(
+        data_dir,
+        &targets,
+        audit_retention_days.unwrap_or(disk_usage::DEFAULT_AUDIT_RETENTION_DAYS),
+        &context,
+    )
+}
+
+/// Get the file path of the audit log
+#[tauri::command]
+fn audit_path(state: State<AuditState>) -> Result<String, String> {
+    let Some(path) = state.log.path()? else {
+        return Err("Audit log is kept in memory (data directory is not writable)".to_string());
+    };
+    path.to_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| "Invalid audit path".to_string())
+}
+
+// ============================================================================
+// LLM Integration Commands
+// ============================================================================
+
+/// Analyze a field using LLM (Claude API)
+///
+/// When `ASTERISK_LLM_PROVIDER` is set the provider and its key come from the
+/// environment; otherwise the Claude key configured in Settings is used.
+#[tauri::command]
+async fn llm_analyze_field(
+    request: llm::AnalyzeFieldRequest,
+    api_key_state: State<'_, ApiKeyState>,
+    budget_state: State<'_, LlmBudgetState>,
+    redaction_state: State<'_, LlmRedactionState>,
+    state: State<'_, AppState>,
+) -> Result<llm::AnalyzeFieldResponse, String> {
+    let (_, redaction) = llm_redaction(&redaction_state, &state)?;
+    let provider = llm_provider(&api_key_state, &budget_state)?;
+
+    // Call LLM analysis
+    llm::analyze_field(provider.as_ref(), request, &redaction)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Analyze several fields in one LLM call
+///
+/// Fields whose answer in the reply is missing or malformed come back as no
+/// match with the reason, flagged `parsed: false`; the rest keep their
+/// answers.
+#[tauri::command]
+async fn llm_analyze_batch(
+    requests: Vec<llm::BatchFieldRequest>,
+    api_key_state: State<'_, ApiKeyState>,
+    budget_state: State<'_, LlmBudgetState>,
+    redaction_state: State<'_, LlmRedactionState>,
+    state: State<'_, AppState>,
+) -> Result<Vec<llm::BatchFieldResultJson>, String> {
+    let (_, redaction) = llm_redaction(&redaction_state, &state)?;
+    let provider = llm_provider(&api_key_state, &budget_state)?;
+
+    llm::analyze_batch(provider.as_ref(), &requests, &redaction)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Analyze the latest snapshot's unmatched fields using LLM
+///
+/// `field_ids` are the fields the user left selected; when omitted every
+/// field the local tiers couldn't match is analyzed. Deselected fields come
+/// back as skipped by user and are never sent.
+///
+/// A later call for the same domain and `tab_id` supersedes this one: its
+/// LLM calls are cancelled, it returns an error, and an
+/// `analysis-superseded` event reports the work lost and kept.
+///
+/// With `deadline_ms`, the whole call — local matching, then the LLM — is
+/// budgeted from when it starts: fields the LLM hasn't answered by then
+/// come back as deadline exceeded rather than holding up the rest.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn llm_analyze_snapshot(
+    field_ids: Option<Vec<String>>,
+    tab_id: Option<String>,
+    deadline_ms: Option<u64>,
+    app: tauri::AppHandle,
+    api_key_state: State<'_, ApiKeyState>,
+    budget_state: State<'_, LlmBudgetState>,
+    redaction_state: State<'_, LlmRedactionState>,
+    snapshot_state: State<'_, FormSnapshotState>,
+    template_state: State<'_, TemplateState>,
+    override_state: State<'_, OverrideState>,
+    cache_state: State<'_, LlmCacheState>,
+    eager_state: State<'_, EagerAnalysisState>,
+    state: State<'_, AppState>,
+) -> Result<Vec<llm::FieldAnalysisJson>, String> {
+    // Answers a background job already got are in the cache
+    eager_state.analysis.cancel();
+    let deadline = deadline_ms
+        .map(|ms| tokio::time::Instant::now() + Duration::from_millis(ms));
+    let snapshot = snapshot_state
+        .latest
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clone()
+        .ok_or_else(|| "No form snapshot to analyze".to_string())?;
+    let items = state
+        .vault
+        .lock()
+        .map_err(|e| e.to_string())?
+        .list()
+        .map_err(|e| e.to_string())?;
+    // Overridden fields are never sent for analysis
+    let plan = local_fill_plan(
+        &snapshot,
+        &items,
+        &snapshot_state.sessions,
+        &template_state,
+        &override_state,
+    )?;
+    cache_state
+        .cache
+        .observe_labels(items.iter().map(|item| (item.key.clone(), item.label.clone())));
+
+    let (keys, redaction) = llm_redaction(&redaction_state, &state)?;
+    let provider = llm_provider(&api_key_state, &budget_state)?;
+
+    // A newer snapshot from the same tab cancels this analysis
+    let sessions = &cache_state.sessions;
+    let session = sessions
+        .begin(
+            llm::SessionKey {
+                domain: snapshot.domain.clone(),
+                tab_id,
+            },
+            chrono::Utc::now(),
+        )
+        .with_deadline(deadline);
+    let results = llm::analyze_snapshot_in_session(
+        provider.as_ref(),
+        &snapshot,
+        &plan.unmatched_fields,
+        field_ids.as_deref(),
+        &keys,
+        &redaction,
+        &cache_state.cache,
+        &session,
+    )
+    .await;
+    match results {
+        Some(results) if sessions.finish(&session) => Ok(results),
+        _ => {
+            if let Some(report) = session.superseded_report() {
+                let _ = app.emit(llm::ANALYSIS_SUPERSEDED_EVENT, report);
+            }
+            Err("Analysis superseded by a newer snapshot".to_string())
+        }
+    }
+}
+
+/// Match `snapshot` field by field, emitting each match on
+/// `MATCH_STREAM_EVENT` as it is found: local matches first, then LLM
+/// answers as they come. Without an API key, fields the local tiers left
+/// unmatched are emitted as unresolved.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn suggest_matches_stream(
+    snapshot: FormSnapshotJson,
+    stream_id: Option<String>,
+    tab_id: Option<String>,
+    app: tauri::AppHandle,
+    api_key_state: State<'_, ApiKeyState>,
+    budget_state: State<'_, LlmBudgetState>,
+    redaction_state: State<'_, LlmRedactionState>,
+    snapshot_state: State<'_, FormSnapshotState>,
+    template_state: State<'_, TemplateState>,
+    override_state: State<'_, OverrideState>,
+    cache_state: State<'_, LlmCacheState>,
+    state: State<'_, AppState>,
+) -> Result<match_stream::MatchStreamSummaryJson, String> {
+    let stream_id = stream_id
+        .unwrap_or_else(|| format!("match-{}", chrono::Utc::now().timestamp_millis()));
+    let items = state
+        .vault
+        .lock()
+        .map_err(|e| e.to_string())?
+        .list()
+        .map_err(|e| e.to_string())?;
+    let plan = local_fill_plan(
+        &snapshot,
+        &items,
+        &snapshot_state.sessions,
+        &template_state,
+        &override_state,
+    )?;
+    let (keys, redaction) = llm_redaction(&redaction_state, &state)?;
+    // No key configured: the local stage still streams
+    let provider = llm_provider(&api_key_state, &budget_state).ok();
+
+    let sessions = &cache_state.sessions;
+    let session = sessions.begin(
+        llm::SessionKey {
+            domain: snapshot.domain.clone(),
+            tab_id,
+        },
+        chrono::Utc::now(),
+    );
+    let stage = provider.as_deref().map(|provider| match_stream::LlmStage {
+        provider,
+        available_keys: &keys,
+        redaction: &redaction,
+        cache: &cache_state.cache,
+        session: &session,
+    });
+    let summary = match_stream::stream_matches(&stream_id, &snapshot, &plan, stage, &mut |json| {
+        let _ = app.emit(match_stream::MATCH_STREAM_EVENT, json);
+    })
+    .await;
+    if !sessions.finish(&session) {
+        if let Some(report) = session.superseded_report() {
+            let _ = app.emit(llm::ANALYSIS_SUPERSEDED_EVENT, report);
+        }
+    }
+    Ok(summary)
+}
+
+/// Hit, miss and revalidation counts for the LLM analysis cache
+#[tauri::command]
+fn llm_cache_stats(cache_state: State<LlmCacheState>) -> llm::AnalysisCacheStatsJson {
+    cache_state.cache.stats()
+}
+
+/// Start planning a snapshot that just arrived in the background, if eager
+/// analysis applies to it
+fn eager_analyze(app: tauri::AppHandle, snapshot: FormSnapshotJson, incognito: bool) {
+    let eager = Arc::clone(&app.state::<EagerAnalysisState>().analysis);
+    if !eager.is_enabled() {
+        return;
+    }
+    let now = chrono::Utc::now();
+    let domain = fill::canonicalize_domain(&snapshot.domain);
+    let inputs = eager::EagerInputs {
+        enabled: true,
+        incognito,
+        vault_locked: app.state::<VaultLockState>().lock.check(now).is_err(),
+        over_budget: app.state::<LlmBudgetState>().usage.check(now).is_err(),
+        has_fill_history: app.state::<AuditState>().log.domains().is_ok_and(|domains| {
+            domains
+                .iter()
+                .any(|d| fill::canonicalize_domain(&d.domain) == domain)
+        }),
+        has_template: app
+            .state::<TemplateState>()
+            .store
+            .lock()
+            .is_ok_and(|store| {
+                store
+                    .list()
+                    .iter()
+                    .any(|t| fill::canonicalize_domain(&t.domain) == domain)
+            }),
+    };
+    if let Err(reason) = eager::eligibility(&inputs) {
+        eager.skipped(reason);
+        return;
+    }
+
+    let session = eager.begin(now);
+    tauri::async_runtime::spawn(async move {
+        match eager_plan(&app, &snapshot, &session).await {
+            Ok((vault_revision, plan)) => {
+                if eager.finish(&session, &snapshot, vault_revision, plan) {
+                    println!("[Eager] Plan ready for {}", snapshot.domain);
+                }
+            }
             Err(e) => {
-                eprintln!("[Asterisk HTTP] Failed to start server: {}", e);
-                return;
+                eprintln!("[Eager] {}", e);
+                eager.abandon(&session);
             }
-        };
+        }
+    });
+}
+
+/// Local plan for `snapshot`, with the fields it leaves open analyzed into
+/// the LLM cache when a provider is configured
+async fn eager_plan(
+    app: &tauri::AppHandle,
+    snapshot: &FormSnapshotJson,
+    session: &llm::AnalysisSession,
+) -> Result<(u64, matching::FillPlanJson), String> {
+    let state = app.state::<AppState>();
+    let cache_state = app.state::<LlmCacheState>();
+    let (vault_revision, items) = {
+        let vault = state.vault.lock().map_err(|e| e.to_string())?;
+        (
+            state.vault.revision(),
+            vault.list().map_err(|e| e.to_string())?,
+        )
+    };
+    let plan = local_fill_plan(
+        snapshot,
+        &items,
+        &app.state::<FormSnapshotState>().sessions,
+        &app.state::<TemplateState>(),
+        &app.state::<OverrideState>(),
+    )?;
+    if plan.unmatched_fields.is_empty() {
+        return Ok((vault_revision, plan));
+    }
+    cache_state
+        .cache
+        .observe_labels(items.iter().map(|item| (item.key.clone(), item.label.clone())));
+    // Without an API key the local plan is still worth keeping
+    let Ok(provider) = llm_provider(&app.state::<ApiKeyState>(), &app.state::<LlmBudgetState>())
+    else {
+        return Ok((vault_revision, plan));
+    };
+    let (keys, redaction) = llm_redaction(&app.state::<LlmRedactionState>(), &state)?;
+    // A superseded job's plan is dropped by `finish`
+    llm::analyze_snapshot_in_session(
+        provider.as_ref(),
+        snapshot,
+        &plan.unmatched_fields,
+        None,
+        &keys,
+        &redaction,
+        &cache_state.cache,
+        session,
+    )
+    .await;
+    Ok((vault_revision, plan))
+}
+
+/// Turn background planning of arriving snapshots on or off
+#[tauri::command]
+fn eager_analysis_set_enabled(enabled: bool, eager_state: State<EagerAnalysisState>) {
+    eager_state.analysis.set_enabled(enabled);
+}
+
+/// Whether eager analysis is on, and what it has done
+#[tauri::command]
+fn eager_analysis_status(eager_state: State<EagerAnalysisState>) -> eager::EagerStatusJson {
+    eager_state.analysis.status()
+}
+
+/// LLM usage in the current budget window
+#[tauri::command]
+fn llm_budget_status(budget_state: State<LlmBudgetState>) -> llm::LlmBudgetStatusJson {
+    budget_state.usage.status(chrono::Utc::now())
+}
+
+/// Replace the LLM budget; usage so far in the window still counts
+#[tauri::command]
+fn llm_budget_configure(
+    budget: llm::LlmBudgetJson,
+    budget_state: State<LlmBudgetState>,
+) -> Result<llm::LlmBudgetStatusJson, String> {
+    budget_state.usage.configure(budget)?;
+    Ok(budget_state.usage.status(chrono::Utc::now()))
+}
+
+/// Rebuild the template entries of the analysis cache from saved templates
+///
+/// Returns how many field mappings were loaded.
+#[tauri::command]
+fn cache_warm_from_templates(
+    template_state: State<TemplateState>,
+    cache_state: State<LlmCacheState>,
+) -> Result<usize, String> {
+    let store = template_state.store.lock().map_err(|e| e.to_string())?;
+    Ok(cache_state.cache.warm_from_templates(store.list()))
+}
+
+/// The configured LLM provider
+///
+/// When `ASTERISK_LLM_PROVIDER` is set the provider and its key come from the
+/// environment; otherwise the Claude key configured in Settings is used.
+///
+/// Calls are counted against the LLM budget; once it is used up they fail
+/// until the window rolls over.
+fn llm_provider(
+    api_key_state: &ApiKeyState,
+    budget_state: &LlmBudgetState,
+) -> Result<Box<dyn llm::LlmProvider>, String> {
+    let inner = llm_inner_provider(api_key_state)?;
+    Ok(Box::new(llm::BudgetedProvider::new(
+        inner,
+        Arc::clone(&budget_state.usage),
+    )))
+}
+
+fn llm_inner_provider(api_key_state: &ApiKeyState) -> Result<Box<dyn llm::LlmProvider>, String> {
+    if std::env::var_os(llm::PROVIDER_ENV).is_some() {
+        return llm::provider_from_env().map_err(|e| e.to_string());
+    }
+    // Get API key from state
+    let api_key = api_key_state
+        .claude_api_key
+        .lock()
+        .map_err(|e| format!("Failed to lock API key: {}", e))?
+        .clone()
+        .ok_or_else(|| "No API key configured. Please set your Claude API key in Settings.".to_string())?;
+    let retry = llm::LlmConfig::from_env().map_err(|e| e.to_string())?;
+    let primary = llm::AnthropicProvider::new(api_key).with_retry_config(retry);
+    llm::with_fallbacks_from_env(Box::new(primary)).map_err(|e| e.to_string())
+}
+
+/// Vault keys plus the redaction applied to LLM prompts: configured tokens
+/// and the values of sensitive vault items
+fn llm_redaction(
+    redaction_state: &LlmRedactionState,
+    state: &AppState,
+) -> Result<(Vec<String>, llm::PromptRedaction), String> {
+    let mut tokens = redaction_state
+        .tokens
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clone();
+    let items = state
+        .vault
+        .lock()
+        .map_err(|e| e.to_string())?
+        .list()
+        .map_err(|e| e.to_string())?;
+    // Rejected items are never matched, so the LLM isn't offered them
+    let keys = items
+        .iter()
+        .filter(|item| item.review_status != ReviewStatus::Rejected)
+        .map(|item| item.key.clone())
+        .collect();
+    tokens.extend(items.into_iter().filter(|item| item.sensitive).map(|item| item.value));
+    Ok((keys, llm::PromptRedaction::new(&tokens)))
+}
+
+/// Show the prompts LLM analysis would send for each field of `snapshot`,
+/// after redaction. Nothing is sent.
+#[tauri::command]
+fn preview_llm_input(
+    snapshot: FormSnapshotJson,
+    redaction_state: State<LlmRedactionState>,
+    state: State<AppState>,
+) -> Result<Vec<String>, String> {
+    let (keys, redaction) = llm_redaction(&redaction_state, &state)?;
+    Ok(llm::preview_llm_input(&snapshot, &keys, &redaction))
+}
+
+/// Replace the extra tokens redacted from LLM prompts
+#[tauri::command]
+fn set_llm_redaction_tokens(
+    tokens: Vec<String>,
+    redaction_state: State<LlmRedactionState>,
+) -> Result<(), String> {
+    *redaction_state.tokens.lock().map_err(|e| e.to_string())? = tokens;
+    Ok(())
+}
+
+/// Extra tokens redacted from LLM prompts
+#[tauri::command]
+fn get_llm_redaction_tokens(
+    redaction_state: State<LlmRedactionState>,
+) -> Result<Vec<String>, String> {
+    Ok(redaction_state.tokens.lock().map_err(|e| e.to_string())?.clone())
+}
+
+/// Set the Claude API key
+///
+/// Surrounding whitespace is trimmed; a key that still doesn't look like an
+/// Anthropic key is refused here rather than failing later with a 401.
+#[tauri::command]
+fn set_api_key(
+    api_key: String,
+    state: State<ApiKeyState>,
+) -> Result<(), String> {
+    let api_key = api_key.trim().to_string();
+    llm::validate_api_key_format("anthropic", &api_key)?;
+
+    let mut key_store = state
+        .claude_api_key
+        .lock()
+        .map_err(|e| format!("Failed to lock API key: {}", e))?;
+
+    *key_store = Some(api_key);
+    Ok(())
+}
+
+/// Check a provider API key's format without storing it
+#[tauri::command]
+fn validate_api_key_format(provider: String, api_key: String) -> Result<(), String> {
+    llm::validate_api_key_format(&provider, api_key.trim())
+}
+
+/// Check if API key is configured
+#[tauri::command]
+fn has_api_key(state: State<ApiKeyState>) -> Result<bool, String> {
+    let key_store = state
+        .claude_api_key
+        .lock()
+        .map_err(|e| format!("Failed to lock API key: {}", e))?;
+
+    Ok(key_store.is_some())
+}
+
+/// Clear the API key
+#[tauri::command]
+fn clear_api_key(state: State<ApiKeyState>) -> Result<(), String> {
+    let mut key_store = state
+        .claude_api_key
+        .lock()
+        .map_err(|e| format!("Failed to lock API key: {}", e))?;
+
+    *key_store = None;
+    Ok(())
+}
+
+// ============================================================================
+// Tauri Commands - Onboarding
+// ============================================================================
+
+/// First-run setup progress, re-derived from the stores
+///
+/// Emits `onboarding-step-completed` for each step completed since the last
+/// check.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn onboarding_status(
+    state: State<AppState>,
+    api_key_state: State<ApiKeyState>,
+    client_state: State<BridgeClientState>,
+    snapshot_state: State<FormSnapshotState>,
+    audit_state: State<AuditState>,
+    onboarding_state: State<OnboardingState>,
+    app: tauri::AppHandle,
+) -> Result<onboarding::OnboardingStatusJson, String> {
+    let inputs = onboarding::OnboardingInputs {
+        vault_items: state.vault.lock()?.len(),
+        llm_key: llm_key_check(&api_key_state),
+        last_client: client_state.tracker.last(),
+        has_snapshot: snapshot_state.latest.latest()?.is_some(),
+        applied_fills: audit_state.log.stats()?.applied,
+    };
+    let (status, completed) = onboarding_state
+        .store
+        .lock()?
+        .evaluate(&inputs, chrono::Utc::now())?;
+    for step in completed {
+        let _ = app.emit(
+            onboarding::STEP_COMPLETED_EVENT,
+            serde_json::to_value(&step).unwrap_or_default(),
+        );
+    }
+    Ok(status)
+}
+
+/// Whether LLM features have a usable key, or why not
+fn llm_key_check(api_key_state: &ApiKeyState) -> Result<(), String> {
+    if std::env::var_os(llm::PROVIDER_ENV).is_some() {
+        return llm::provider_from_env().map(|_| ()).map_err(|e| e.to_string());
+    }
+    let key = api_key_state
+        .claude_api_key
+        .lock()
+        .map_err(|e| format!("Failed to lock API key: {}", e))?
+        .clone()
+        .ok_or_else(|| "No API key configured. Set your Claude API key in Settings.".to_string())?;
+    llm::validate_api_key_format("anthropic", &key)
+}
+
+// ============================================================================
+// Tauri Commands - Bridge Clients
+// ============================================================================
+
+/// The extension version last seen on the bridge and whether it is still supported
+#[tauri::command]
+fn bridge_client_status(
+    state: State<BridgeClientState>,
+) -> Result<Option<compat::SeenClientJson>, String> {
+    Ok(state.tracker.last())
+}
+
+/// Mint a named bridge token limited to `scopes`; the token value is only
+/// returned here
+#[tauri::command]
+fn bridge_token_create(
+    name: String,
+    scopes: Vec<tokens::Scope>,
+    state: State<BridgeTokenState>,
+) -> Result<tokens::CreatedTokenJson, String> {
+    let mut store = state.store.lock().map_err(|e| e.to_string())?;
+    store.create(&name, &scopes)
+}
+
+/// The pairing token, for the UI to hand to the extension
+#[tauri::command]
+fn bridge_token(state: State<BridgeTokenState>) -> String {
+    state.pairing_token.clone()
+}
+
+/// Revoke a bridge token; returns false if it didn't exist
+#[tauri::command]
+fn bridge_token_revoke(id: String, state: State<BridgeTokenState>) -> Result<bool, String> {
+    let mut store = state.store.lock().map_err(|e| e.to_string())?;
+    store.revoke(&id)
+}
+
+/// List bridge tokens with their scopes and last use (never the token values)
+#[tauri::command]
+fn bridge_token_list(state: State<BridgeTokenState>) -> Result<Vec<tokens::BridgeTokenJson>, String> {
+    let store = state.store.lock().map_err(|e| e.to_string())?;
+    Ok(store.list())
+}
+
+/// Pause or resume one bridge capability (`capture`, `vault-read`,
+/// `fill-delivery` or `audit-write`); returns every capability's state
+#[tauri::command]
+fn bridge_set_capability(
+    name: String,
+    enabled: bool,
+    state: State<BridgePauseState>,
+    app: tauri::AppHandle,
+) -> Result<Vec<pause::CapabilityStateJson>, String> {
+    let capability = pause::Capability::parse(&name)
+        .ok_or_else(|| format!("Unknown bridge capability: {}", name))?;
+    if state.pause.set(capability, enabled) {
+        println!(
+            "[Asterisk HTTP] {} {}",
+            if enabled { "Resumed" } else { "Paused" },
+            name
+        );
+        let _ = app.emit(
+            pause::CAPABILITY_CHANGED_EVENT,
+            pause::CapabilityStateJson {
+                name: capability,
+                enabled,
+            },
+        );
+    }
+    Ok(state.pause.states())
+}
+
+/// Every bridge capability and whether it is paused
+#[tauri::command]
+fn bridge_capabilities(state: State<BridgePauseState>) -> Vec<pause::CapabilityStateJson> {
+    state.pause.states()
+}
+
+// ============================================================================
+// HTTP Server for Extension Bridge
+// ============================================================================
+
+/// Shared state the extension bridge serves from
+struct BridgeContext {
+    snapshot_store: shared::SnapshotStore,
+    form_sessions: sessions::FormSessionStore,
+    plan_activity: Arc<Mutex<Option<polling::PlanActivity>>>,
+    recapture_queue: Arc<Mutex<recapture::RecaptureQueue>>,
+    vault_store: shared::SharedVault,
+    vault_history: Arc<history::VaultHistory>,
+    vault_changes: Arc<changes::ChangeJournal>,
+    fill_command_store: shared::FillCommandStore,
+    fill_clock: Arc<clock::Clock>,
+    fill_groups: Arc<groups::FillCommandGroups>,
+    max_length_policy: Arc<Mutex<fill::MaxLengthPolicy>>,
+    access_log: Arc<access::AccessLog>,
+    consent_store: consent::SharedConsentStore,
+    recall_store: Arc<Mutex<recall::RecallStore>>,
+    undo_store: Arc<Mutex<undo::UndoStore>>,
+    client_tracker: Arc<compat::ClientTracker>,
+    audit_log: Arc<audit::AuditLog>,
+    token_store: Arc<Mutex<tokens::TokenStore>>,
+    bridge_pause: Arc<pause::BridgePause>,
+    onboarding: onboarding::OnboardingStore,
+    cors: cors::CorsPolicy,
+    vault_lock: Arc<vault_lock::VaultLock>,
+    events: EventSink,
+    snapshot_hook: eager::SnapshotHook,
+}
+
+/// Start the bridge on `port`, or one of the ports after it if that is
+/// taken; returns the port bound
+fn start_http_server(context: BridgeContext, port: u16) -> Result<u16, String> {
+    let (server, port) = bridge_port::bind(port)?;
+    println!("[Asterisk HTTP] Server listening on http://127.0.0.1:{}", port);
+    spawn_bridge(server, context);
+    Ok(port)
+}
+
+/// Whether the store `url` reads from reaches revision `min` within
+/// `timeout`; routes without a store have nothing to wait for
+type RevisionWait = Arc<dyn Fn(&str, u64, Duration) -> Result<(), String> + Send + Sync>;
+
+/// Accept bridge requests from `server`, holding back those whose
+/// `minRevision` isn't reached yet
+///
+/// A held-back request waits on a thread of its own, so it never stalls the
+/// requests behind it, and is passed on once its store catches up or
+/// `MAX_REVISION_WAIT` runs out. Routing then finds the revision reached or
+/// answers 503.
+fn accept_requests(server: Server, wait: RevisionWait) -> mpsc::Receiver<Request> {
+    let (sender, receiver) = mpsc::channel();
+    let waiters = Arc::new(AtomicUsize::new(0));
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let behind = shared::take_min_revision(request.url())
+                .ok()
+                .and_then(|(url, min)| min.map(|min| (url, min)))
+                .filter(|(url, min)| wait(url, *min, Duration::ZERO).is_err());
+            let Some((url, min)) = behind else {
+                if sender.send(request).is_err() {
+                    break;
+                }
+                continue;
+            };
+            if waiters.fetch_add(1, Ordering::SeqCst) >= shared::MAX_REVISION_WAITERS {
+                waiters.fetch_sub(1, Ordering::SeqCst);
+                let _ = sender.send(request);
+                continue;
+            }
+            let (sender, waiters, wait) = (sender.clone(), Arc::clone(&waiters), Arc::clone(&wait));
+            thread::spawn(move || {
+                let _ = wait(&url, min, shared::MAX_REVISION_WAIT);
+                waiters.fetch_sub(1, Ordering::SeqCst);
+                let _ = sender.send(request);
+            });
+        }
+    });
+    receiver
+}
+
+/// Serve bridge requests from `server` on a background thread
+fn spawn_bridge(server: Server, context: BridgeContext) -> thread::JoinHandle<()> {
+    let BridgeContext {
+        snapshot_store,
+        form_sessions,
+        plan_activity,
+        recapture_queue,
+        vault_store,
+        vault_history,
+        vault_changes,
+        fill_command_store,
+        fill_clock,
+        fill_groups,
+        max_length_policy,
+        access_log,
+        consent_store,
+        recall_store,
+        undo_store,
+        client_tracker,
+        audit_log,
+        token_store,
+        bridge_pause,
+        onboarding,
+        cors,
+        vault_lock,
+        events,
+        snapshot_hook,
+    } = context;
+
+    // Poll hint for the extension from the current bridge state
+    let poll_hint = {
+        let snapshot_store = snapshot_store.clone();
+        let plan_activity = Arc::clone(&plan_activity);
+        move |domain: Option<&str>, pending_commands: usize| {
+            let captured_at = snapshot_store
+                .lock()
+                .ok()
+                .and_then(|s| s.as_ref().map(|s| s.captured_at.clone()));
+            let last_plan = plan_activity.lock().ok().and_then(|p| p.clone());
+            polling::poll_hint_ms(
+                &polling::PollInputs {
+                    domain,
+                    pending_commands,
+                    last_plan: last_plan.as_ref(),
+                    snapshot_captured_at: captured_at.as_deref(),
+                },
+                chrono::Utc::now(),
+            )
+        }
+    };
+
+    // Onboarding steps the bridge witnesses, announced as they first happen
+    let onboarding_step = {
+        let events = Arc::clone(&events);
+        move |step: onboarding::OnboardingStep| {
+            let recorded = onboarding
+                .lock()
+                .and_then(|mut progress| progress.record(step, chrono::Utc::now()));
+            match recorded {
+                Ok(Some(step)) => events(
+                    onboarding::STEP_COMPLETED_EVENT,
+                    serde_json::to_value(&step).unwrap_or_default(),
+                ),
+                Ok(None) => {}
+                Err(e) => eprintln!("[Onboarding] {}", e),
+            }
+        }
+    };
 
-        println!("[Asterisk HTTP] Server listening on http://127.0.0.1:17373");
+    let revision_wait: RevisionWait = {
+        let vault_store = vault_store.clone();
+        let fill_command_store = fill_command_store.clone();
+        let snapshot_store = snapshot_store.clone();
+        Arc::new(move |url: &str, min: u64, timeout: Duration| {
+            if url.starts_with("/v1/vault") {
+                vault_store.wait_for(min, timeout).map(drop)
+            } else if url.starts_with("/v1/fill-commands") {
+                fill_command_store.wait_for(min, timeout).map(drop)
+            } else if url.starts_with("/v1/form-snapshots") {
+                snapshot_store.wait_for(min, timeout).map(drop)
+            } else {
+                Ok(())
+            }
+        })
+    };
+    let requests = accept_requests(server, Arc::clone(&revision_wait));
 
-        for mut request in server.incoming_requests() {
+    thread::spawn(move || {
+        for mut request in requests {
             let url = request.url().to_string();
             let method = request.method().to_string();
 
-            // CORS headers for extension requests
-            let cors_headers = vec![
-                Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap(),
-                Header::from_bytes(
-                    &b"Access-Control-Allow-Methods"[..],
-                    &b"GET, POST, DELETE, OPTIONS"[..],
-                )
-                .unwrap(),
-                Header::from_bytes(
-                    &b"Access-Control-Allow-Headers"[..],
-                    &b"Content-Type"[..],
-                )
-                .unwrap(),
-            ];
+            // CORS headers for extension requests (none when CORS is disabled)
+            let mut cors_headers = cors.headers();
 
             // Handle CORS preflight
             if method == "OPTIONS" {
-                let mut response = Response::empty(204);
+                let status = if cors == cors::CorsPolicy::Disabled { 403 } else { 204 };
+                let mut response = Response::empty(status);
+                for header in cors_headers {
+                    response.add_header(header);
+                }
+                let _ = request.respond(response);
+                continue;
+            }
+
+            // With CORS disabled, pages on other origins are refused outright
+            let origin = request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv("Origin"))
+                .map(|h| h.value.as_str().to_string());
+            let host = request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv("Host"))
+                .map(|h| h.value.as_str().to_string());
+            if !cors.allows(origin.as_deref(), host.as_deref()) {
+                let body = serde_json::json!({ "error": "Cross-origin requests are disabled" });
+                let mut response = Response::from_string(body.to_string()).with_status_code(403);
+                response.add_header(
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap(),
+                );
+                let _ = request.respond(response);
+                continue;
+            }
+
+            // Client version check, before any route: refuse unsupported
+            // extensions, and mark deprecated ones on every response
+            let client = request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv(compat::CLIENT_HEADER))
+                .map(|h| h.value.as_str().to_string());
+            let compatibility = compat::evaluate(&compat::POLICY, client.as_deref());
+            if let Some(client) = &client {
+                client_tracker.observe(client, compatibility, chrono::Utc::now());
+                if matches!(
+                    compatibility,
+                    compat::Compatibility::Supported | compat::Compatibility::Deprecated
+                ) {
+                    onboarding_step(onboarding::OnboardingStep::ExtensionConnected);
+                }
+            }
+            if compatibility == compat::Compatibility::Unsupported && !compat::is_exempt(&url) {
+                let body = compat::upgrade_required_body(&compat::POLICY, client.as_deref().unwrap_or(""));
+                let mut response = Response::from_string(body.to_string()).with_status_code(426);
+                response.add_header(
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap(),
+                );
+                for header in cors_headers {
+                    response.add_header(header);
+                }
+                let _ = request.respond(response);
+                continue;
+            }
+            for (name, value) in compat::response_headers(&compat::POLICY, compatibility) {
+                cors_headers.push(Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap());
+            }
+
+            // Scoped tokens: a presented token must be known and carry the
+            // route's scope; once paired, `/v1/` routes need one
+            let token = request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv(tokens::TOKEN_HEADER))
+                .map(|h| h.value.as_str().to_string());
+            let access = match (token, token_store.lock()) {
+                (Some(token), Ok(mut store)) => {
+                    let required = tokens::required_scopes(&method, &url);
+                    Some(store.check(&token, required, chrono::Utc::now()))
+                }
+                (Some(_), Err(_)) => Some(tokens::Access::UnknownToken),
+                (None, store) => store
+                    .map_or(tokens::route_needs_token(&url), |store| store.requires_token(&url))
+                    .then_some(tokens::Access::MissingToken),
+            };
+            if let Some(access) = access {
+                let refusal = match access {
+                    tokens::Access::Allowed => None,
+                    tokens::Access::MissingToken => Some((
+                        401,
+                        serde_json::json!({ "error": "Missing bridge token" }),
+                    )),
+                    tokens::Access::UnknownToken => {
+                        Some((401, serde_json::json!({ "error": "Unknown bridge token" })))
+                    }
+                    tokens::Access::MissingScope(scope) => Some((
+                        403,
+                        serde_json::json!({
+                            "error": "Token does not allow this route",
+                            "requiredScope": scope,
+                        }),
+                    )),
+                };
+                if let Some((status_code, body)) = refusal {
+                    let mut response =
+                        Response::from_string(body.to_string()).with_status_code(status_code);
+                    response.add_header(
+                        Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap(),
+                    );
+                    for header in cors_headers {
+                        response.add_header(header);
+                    }
+                    let _ = request.respond(response);
+                    continue;
+                }
+            }
+
+            // Paused capabilities refuse their routes
+            if let Err(paused) = bridge_pause.check(&method, &url) {
+                let body = serde_json::to_string(&paused).unwrap_or_default();
+                let mut response = Response::from_string(body).with_status_code(503);
+                response.add_header(
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap(),
+                );
+                for header in cors_headers {
+                    response.add_header(header);
+                }
+                let _ = request.respond(response);
+                continue;
+            }
+
+            // A locked vault serves nothing until the user unlocks it; a
+            // locked store was locked by hand
+            if !compat::is_exempt(&url) {
+                let locked = vault_lock.check(chrono::Utc::now()).err().or_else(|| {
+                    vault_store
+                        .lock()
+                        .is_ok_and(|vault| vault.is_locked())
+                        .then_some(vault_lock::LockReason::Manual)
+                });
+                if let Some(reason) = locked {
+                    let body = serde_json::json!({ "error": "Vault is locked", "reason": reason });
+                    let mut response = Response::from_string(body.to_string()).with_status_code(423);
+                    response.add_header(
+                        Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap(),
+                    );
+                    for header in cors_headers {
+                        response.add_header(header);
+                    }
+                    let _ = request.respond(response);
+                    continue;
+                }
+            }
+
+            // Read-your-writes: `minRevision` must be reached by now (the
+            // request was held back while it could still be), then routes as
+            // if the parameter were absent
+            let routed = shared::take_min_revision(&url)
+                .map_err(|e| (400, e))
+                .and_then(|(url, min_revision)| {
+                    let reached = min_revision.map_or(Ok(()), |min| {
+                        revision_wait(&url, min, Duration::ZERO)
+                    });
+                    reached.map(|()| url).map_err(|e| (503, e))
+                });
+            let url = match routed {
+                Ok(url) => url,
+                Err((status_code, e)) => {
+                    let body = serde_json::json!({ "error": e });
+                    let mut response =
+                        Response::from_string(body.to_string()).with_status_code(status_code);
+                    response.add_header(
+                        Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap(),
+                    );
+                    for header in cors_headers {
+                        response.add_header(header);
+                    }
+                    let _ = request.respond(response);
+                    continue;
+                }
+            };
+
+            // Route: GET / (status page for browser visits; JSON on request)
+            if method == "GET" && url == "/" {
+                let status = status_page::BridgeStatusJson::new(
+                    client_tracker.last().as_ref(),
+                    chrono::Utc::now(),
+                );
+                let accept = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Accept"))
+                    .map(|h| h.value.as_str().to_string());
+                let (body, content_type) = if status_page::wants_json(accept.as_deref()) {
+                    (serde_json::to_string(&status).unwrap_or_default(), "application/json")
+                } else {
+                    (status_page::render(&status), "text/html; charset=utf-8")
+                };
+                let mut response = Response::from_string(body);
+                response.add_header(
+                    Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()).unwrap(),
+                );
                 for header in cors_headers {
                     response.add_header(header);
                 }
@@ -673,7 +3727,30 @@ fn start_http_server(
 
             // Route: GET /health
             if method == "GET" && url == "/health" {
+                let pending = fill_command_store.lock().map(|s| s.len()).unwrap_or(0);
+                let hint = poll_hint(None, pending).to_string();
                 let mut response = Response::from_string("OK");
+                response.add_header(
+                    Header::from_bytes(&b"X-Poll-Hint-Ms"[..], hint.as_bytes()).unwrap(),
+                );
+                for header in cors_headers {
+                    response.add_header(header);
+                }
+                let _ = request.respond(response);
+                continue;
+            }
+
+            // Route: GET /v1/capabilities (optional features in this build, paused capabilities)
+            if method == "GET" && url == "/v1/capabilities" {
+                let body = serde_json::json!({
+                    "features": features::FEATURES,
+                    "capabilities": bridge_pause.states(),
+                });
+                let mut response = Response::from_string(body.to_string());
+                response.add_header(
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                        .unwrap(),
+                );
                 for header in cors_headers {
                     response.add_header(header);
                 }
@@ -683,14 +3760,15 @@ fn start_http_server(
 
             // Route: GET /v1/form-snapshots (for browser fallback)
             if method == "GET" && url == "/v1/form-snapshots" {
-                let json_response = match snapshot_store.lock() {
-                    Ok(store) => match &*store {
-                        Some(snapshot) => serde_json::to_string(snapshot).unwrap_or_else(|_| "null".to_string()),
-                        None => "null".to_string(),
-                    },
-                    Err(_) => "null".to_string(),
+                let (status_code, body) = match snapshot_store.latest() {
+                    Ok(snapshot) => (200, serde_json::to_value(snapshot).unwrap_or_default()),
+                    Err(e) => {
+                        eprintln!("[Asterisk HTTP] ERROR: Failed to read form snapshot: {}", e);
+                        (500, serde_json::json!({ "error": e }))
+                    }
                 };
-                let mut response = Response::from_string(json_response);
+                let mut response =
+                    Response::from_string(body.to_string()).with_status_code(status_code);
                 response.add_header(
                     Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
                         .unwrap(),
@@ -704,6 +3782,10 @@ fn start_http_server(
 
             // Route: POST /v1/form-snapshots
             if method == "POST" && url == "/v1/form-snapshots" {
+                let incognito = request
+                    .headers()
+                    .iter()
+                    .any(|h| h.field.equiv(eager::INCOGNITO_HEADER) && h.value.as_str() == "1");
                 let mut body = String::new();
                 if let Err(e) = request.as_reader().read_to_string(&mut body) {
                     eprintln!("[Asterisk HTTP] Failed to read body: {}", e);
@@ -716,7 +3798,8 @@ fn start_http_server(
                 }
 
                 match serde_json::from_str::<FormSnapshotJson>(&body) {
-                    Ok(snapshot) => {
+                    Ok(mut snapshot) => {
+                        semantic::canonicalize_fields(&mut snapshot.fields);
                         println!(
                             "[Asterisk HTTP] Received form snapshot: {} ({} fields)",
                             snapshot.domain,
@@ -738,12 +3821,36 @@ fn start_http_server(
                             continue;
                         }
 
-                        // Store the snapshot
-                        if let Ok(mut store) = snapshot_store.lock() {
-                            *store = Some(snapshot);
+                        // A fresh snapshot answers any re-capture request for its domain
+                        if let Ok(mut queue) = recapture_queue.lock() {
+                            queue.snapshot_arrived(&snapshot.domain);
                         }
 
-                        let mut response = Response::from_string(r#"{"status":"ok"}"#);
+                        // Group steps of a multi-step form, then store the snapshot
+                        let stored = form_sessions
+                            .lock()
+                            .map(|mut sessions| sessions.record(&snapshot))
+                            .and_then(|()| snapshot_store.replace(snapshot.clone()));
+                        let (status_code, body) = match stored {
+                            Ok(()) => {
+                                onboarding_step(onboarding::OnboardingStep::FirstSnapshot);
+                                snapshot_hook(&snapshot, incognito);
+                                (
+                                    200,
+                                    serde_json::json!({
+                                        "status": "ok",
+                                        "revision": snapshot_store.revision(),
+                                    }),
+                                )
+                            }
+                            Err(e) => {
+                                eprintln!("[Asterisk HTTP] ERROR: Failed to store form snapshot: {}", e);
+                                (500, serde_json::json!({ "error": e }))
+                            }
+                        };
+
+                        let mut response =
+                            Response::from_string(body.to_string()).with_status_code(status_code);
                         response.add_header(
                             Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
                                 .unwrap(),
@@ -771,20 +3878,113 @@ fn start_http_server(
                 continue;
             }
 
-            // Route: GET /v1/vault (list all vault items)
-            if method == "GET" && url == "/v1/vault" {
-                let json_response = match vault_store.lock() {
-                    Ok(vault) => match vault.list() {
-                        Ok(items) => {
-                            let json_items: Vec<VaultItemJson> =
-                                items.into_iter().map(VaultItemJson::from).collect();
-                            serde_json::to_string(&json_items).unwrap_or_else(|_| "[]".to_string())
+            // Route: GET /v1/vault?category=xxx (list vault items, all or in one category)
+            if method == "GET" && (url == "/v1/vault" || url.starts_with("/v1/vault?category=")) {
+                let category = match url.strip_prefix("/v1/vault?category=") {
+                    Some(category) => {
+                        parse_category(&urlencoding::decode(category).unwrap_or_default())
+                            .map(Some)
+                            .map_err(|e| (400, e))
+                    }
+                    None => Ok(None),
+                };
+                let listed = category.and_then(|category| {
+                    vault_store
+                        .lock()
+                        .and_then(|vault| {
+                            match category {
+                                Some(category) => vault.list_by_category(category),
+                                None => vault.list(),
+                            }
+                            .map_err(|e| e.to_string())
+                        })
+                        .map_err(|e| (500, e))
+                });
+                let (status_code, body) = match listed {
+                    Ok(items) => {
+                        // Rejected items are never filled, so their values stay in the vault
+                        let items: Vec<VaultItem> = items
+                            .into_iter()
+                            .filter(|item| item.review_status != ReviewStatus::Rejected)
+                            .collect();
+                        for item in &items {
+                            if let Err(e) = access_log.record_read(item, "bridge:GET /v1/vault") {
+                                eprintln!("[Asterisk HTTP] {}", e);
+                            }
                         }
-                        Err(_) => "[]".to_string(),
-                    },
-                    Err(_) => "[]".to_string(),
+                        let json_items: Vec<VaultItemJson> =
+                            items.into_iter().map(VaultItemJson::from).collect();
+                        (200, serde_json::to_value(&json_items).unwrap_or_default())
+                    }
+                    Err((400, e)) => (400, serde_json::json!({ "error": e })),
+                    Err((status_code, e)) => {
+                        eprintln!("[Asterisk HTTP] ERROR: Failed to list vault: {}", e);
+                        (status_code, serde_json::json!({ "error": e }))
+                    }
+                };
+                let mut response =
+                    Response::from_string(body.to_string()).with_status_code(status_code);
+                response.add_header(
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                        .unwrap(),
+                );
+                for header in cors_headers {
+                    response.add_header(header);
+                }
+                let _ = request.respond(response);
+                continue;
+            }
+
+            // Route: GET /v1/vault/keys (keys, labels and categories, never values)
+            if method == "GET" && url == "/v1/vault/keys" {
+                // Rejected items are never filled, so there is no point offering them
+                let listed = vault_store
+                    .lock()
+                    .and_then(|vault| vault.list().map_err(|e| e.to_string()));
+                let (status_code, body) = match listed {
+                    Ok(items) => {
+                        let keys: Vec<VaultKeyJson> = items
+                            .into_iter()
+                            .filter(|item| item.review_status != ReviewStatus::Rejected)
+                            .map(VaultKeyJson::from)
+                            .collect();
+                        (200, serde_json::to_value(&keys).unwrap_or_default())
+                    }
+                    Err(e) => {
+                        eprintln!("[Asterisk HTTP] ERROR: Failed to list vault keys: {}", e);
+                        (500, serde_json::json!({ "error": e }))
+                    }
+                };
+                let mut response =
+                    Response::from_string(body.to_string()).with_status_code(status_code);
+                response.add_header(
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                        .unwrap(),
+                );
+                for header in cors_headers {
+                    response.add_header(header);
+                }
+                let _ = request.respond(response);
+                continue;
+            }
+
+            // Route: GET /v1/vault/changes?since=N (keys changed since revision N)
+            if method == "GET" && url.starts_with("/v1/vault/changes?since=") {
+                let since = url.strip_prefix("/v1/vault/changes?since=").unwrap_or("");
+                let changed = since
+                    .parse::<u64>()
+                    .map_err(|_| (400, format!("since must be a revision number: {}", since)))
+                    .and_then(|since| {
+                        vault_changes
+                            .since(&vault_store, since)
+                            .map_err(|e| (500, e))
+                    });
+                let (status_code, body) = match changed {
+                    Ok(changes) => (200, serde_json::to_value(&changes).unwrap_or_default()),
+                    Err((status_code, e)) => (status_code, serde_json::json!({ "error": e })),
                 };
-                let mut response = Response::from_string(json_response);
+                let mut response =
+                    Response::from_string(body.to_string()).with_status_code(status_code);
                 response.add_header(
                     Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
                         .unwrap(),
@@ -812,12 +4012,309 @@ fn start_http_server(
                 match serde_json::from_str::<VaultItemJson>(&body) {
                     Ok(item_json) => {
                         let key = item_json.key.clone();
-                        match VaultItem::try_from(item_json) {
-                            Ok(vault_item) => {
-                                if let Ok(mut vault) = vault_store.lock() {
-                                    let _ = vault.set(key, vault_item);
+                        match VaultItem::try_from_lossy(item_json) {
+                            Ok((mut vault_item, warnings)) => {
+                                let stored = vault_store.lock().and_then(|mut vault| {
+                                    let stored = vault.get(&key).map_err(|e| e.to_string())?;
+                                    if let Some(stored) = &stored {
+                                        vault_item.keep_usage_of(stored);
+                                    }
+                                    vault
+                                        .set(key, vault_item.clone())
+                                        .map_err(|e| e.to_string())?;
+                                    Ok(stored.is_some())
+                                });
+                                for warning in &warnings {
+                                    eprintln!("[Asterisk HTTP] {}", warning);
+                                }
+                                let (status_code, body) = match stored {
+                                    Ok(existed) => {
+                                        if let Err(e) =
+                                            vault_history.record_set(&vault_item, existed, "bridge")
+                                        {
+                                            eprintln!("[Asterisk HTTP] {}", e);
+                                        }
+                                        (
+                                            200,
+                                            serde_json::json!({
+                                                "status": "ok",
+                                                "warnings": warnings,
+                                                "revision": vault_store.revision(),
+                                            }),
+                                        )
+                                    }
+                                    Err(e) => {
+                                        eprintln!("[Asterisk HTTP] ERROR: Failed to store vault item: {}", e);
+                                        (500, serde_json::json!({ "error": e }))
+                                    }
+                                };
+                                let mut response = Response::from_string(body.to_string())
+                                    .with_status_code(status_code);
+                                response.add_header(
+                                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                                        .unwrap(),
+                                );
+                                for header in cors_headers {
+                                    response.add_header(header);
+                                }
+                                let _ = request.respond(response);
+                            }
+                            Err(e) => {
+                                let mut response =
+                                    Response::from_string(format!(r#"{{"error":"{}"}}"#, e))
+                                        .with_status_code(400);
+                                response.add_header(
+                                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                                        .unwrap(),
+                                );
+                                for header in cors_headers {
+                                    response.add_header(header);
+                                }
+                                let _ = request.respond(response);
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        let mut response =
+                            Response::from_string(format!(r#"{{"error":"{}"}}"#, e))
+                                .with_status_code(400);
+                        response.add_header(
+                            Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                                .unwrap(),
+                        );
+                        for header in cors_headers {
+                            response.add_header(header);
+                        }
+                        let _ = request.respond(response);
+                    }
+                }
+                continue;
+            }
+
+            // Route: DELETE /v1/vault?key=xxx (delete a vault item)
+            if method == "DELETE" && url.starts_with("/v1/vault?key=") {
+                let key = url.strip_prefix("/v1/vault?key=").unwrap_or("");
+                let key = urlencoding::decode(key).unwrap_or_default().to_string();
+
+                // Deleting a missing key is not an error
+                let deleted = vault_store.lock().and_then(|mut vault| match vault.delete(&key) {
+                    Ok(()) => Ok(true),
+                    Err(asterisk_vault::VaultError::NotFound(_)) => Ok(false),
+                    Err(e) => Err(e.to_string()),
+                });
+                let (status_code, body) = match deleted {
+                    Ok(deleted) => {
+                        if deleted {
+                            if let Err(e) = vault_history.record_delete(&key, "bridge") {
+                                eprintln!("[Asterisk HTTP] {}", e);
+                            }
+                        }
+                        (
+                            200,
+                            serde_json::json!({ "status": "ok", "revision": vault_store.revision() }),
+                        )
+                    }
+                    Err(e) => {
+                        eprintln!("[Asterisk HTTP] ERROR: Failed to delete vault item: {}", e);
+                        (500, serde_json::json!({ "error": e }))
+                    }
+                };
+                let mut response =
+                    Response::from_string(body.to_string()).with_status_code(status_code);
+                response.add_header(
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                        .unwrap(),
+                );
+                for header in cors_headers {
+                    response.add_header(header);
+                }
+                let _ = request.respond(response);
+                continue;
+            }
+
+            // Route: POST /v1/fill-commands/batch (several commands, all or nothing)
+            if method == "POST" && url == "/v1/fill-commands/batch" {
+                let mut body = String::new();
+                let parsed = request
+                    .as_reader()
+                    .read_to_string(&mut body)
+                    .map_err(|e| e.to_string())
+                    .and_then(|_| {
+                        serde_json::from_str::<Vec<FillCommandJson>>(&body).map_err(|e| e.to_string())
+                    });
+
+                let (status_code, body) = match parsed {
+                    Err(e) => {
+                        eprintln!("[Asterisk HTTP] Invalid fill command batch: {}", e);
+                        (400, serde_json::json!({ "error": e }))
+                    }
+                    Ok(mut commands) => {
+                        // Validate every command before committing any of them
+                        let policy = max_length_policy.lock().map(|p| *p).unwrap_or_default();
+                        let validated = snapshot_store.lock().map(|store| {
+                            fill::validate_batch(&mut commands, store.as_ref(), policy)
+                        });
+                        match validated {
+                            Err(e) => {
+                                eprintln!("[Asterisk HTTP] ERROR: Failed to read form snapshot: {}", e);
+                                (500, serde_json::json!({ "error": e }))
+                            }
+                            Ok(Err(problems)) => {
+                                eprintln!(
+                                    "[Asterisk HTTP] Rejected fill command batch: {} invalid",
+                                    problems.len()
+                                );
+                                (
+                                    422,
+                                    serde_json::json!({
+                                        "error": "Invalid fill command batch",
+                                        "problems": problems,
+                                    }),
+                                )
+                            }
+                            Ok(Ok(warnings)) => {
+                                // Clear out expired commands before queueing more
+                                fill::prune_store(&fill_command_store, fill_clock.now());
+
+                                match queue_fill_commands(
+                                    &commands,
+                                    &consent_store,
+                                    &fill_command_store,
+                                    events.as_ref(),
+                                ) {
+                                    Err(e) => {
+                                        eprintln!(
+                                            "[Asterisk HTTP] ERROR: Failed to queue fill command batch: {}",
+                                            e
+                                        );
+                                        (500, serde_json::json!({ "error": e }))
+                                    }
+                                    Ok(queued) => {
+                                        let results: Vec<fill::BatchResultJson> = commands
+                                            .iter()
+                                            .zip(warnings)
+                                            .zip(&queued)
+                                            .map(|((command, warnings), &ok)| {
+                                                fill::BatchResultJson {
+                                                    id: command.id.clone(),
+                                                    status: if ok {
+                                                        "ok"
+                                                    } else {
+                                                        "awaiting_consent"
+                                                    }
+                                                    .to_string(),
+                                                    warnings,
+                                                }
+                                            })
+                                            .collect();
+                                        let held = queued.iter().filter(|&&ok| !ok).count();
+                                        println!(
+                                            "[Asterisk HTTP] Received fill command batch: {} commands, {} awaiting consent",
+                                            results.len(),
+                                            held
+                                        );
+                                        let all_authorized = held == 0;
+                                        let ids: Vec<&str> =
+                                            results.iter().map(|r| r.id.as_str()).collect();
+                                        let status = if all_authorized {
+                                            "ok"
+                                        } else {
+                                            "awaiting_consent"
+                                        };
+                                        (
+                                            if all_authorized { 200 } else { 202 },
+                                            serde_json::json!({
+                                                "status": status,
+                                                "revision": fill_command_store.revision(),
+                                                "ids": ids,
+                                                "results": results,
+                                            }),
+                                        )
+                                    }
+                                }
+                            }
+                        }
+                    }
+                };
+
+                let mut response = Response::from_string(body.to_string()).with_status_code(status_code);
+                response.add_header(
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap(),
+                );
+                for header in cors_headers {
+                    response.add_header(header);
+                }
+                let _ = request.respond(response);
+                continue;
+            }
+
+            // Route: POST /v1/fill-commands (desktop sends a fill command)
+            if method == "POST" && url == "/v1/fill-commands" {
+                let mut body = String::new();
+                if let Err(e) = request.as_reader().read_to_string(&mut body) {
+                    eprintln!("[Asterisk HTTP] Failed to read body: {}", e);
+                    let mut response = Response::from_string("Bad Request").with_status_code(400);
+                    for header in cors_headers {
+                        response.add_header(header);
+                    }
+                    let _ = request.respond(response);
+                    continue;
+                }
+
+                match serde_json::from_str::<FillCommandJson>(&body) {
+                    Ok(mut command) => {
+                        println!(
+                            "[Asterisk HTTP] Received fill command: {} -> {} fields",
+                            command.target_domain,
+                            command.fills.len()
+                        );
+
+                        // Reject malformed commands before anything else looks at them
+                        command.target_domain = fill::canonicalize_domain(&command.target_domain);
+                        if let Err(problems) = fill::validate_command(&command) {
+                            let messages: Vec<i18n::MessageJson> =
+                                problems.into_iter().map(i18n::MessageJson::from).collect();
+                            let problems: Vec<&str> =
+                                messages.iter().map(|m| m.message.as_str()).collect();
+                            eprintln!(
+                                "[Asterisk HTTP] Rejected fill command {}: {:?}",
+                                command.id, problems
+                            );
+                            let body = serde_json::json!({
+                                "error": "Invalid fill command",
+                                "problems": problems,
+                                "messages": messages,
+                            });
+                            let mut response =
+                                Response::from_string(body.to_string()).with_status_code(422);
+                            response.add_header(
+                                Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                                    .unwrap(),
+                            );
+                            for header in cors_headers {
+                                response.add_header(header);
+                            }
+                            let _ = request.respond(response);
+                            continue;
+                        }
+
+                        // Check values against the snapshot's maxLength limits
+                        let policy = max_length_policy.lock().map(|p| *p).unwrap_or_default();
+                        let length_issues = match snapshot_store.lock() {
+                            Ok(store) => match &*store {
+                                Some(snapshot)
+                                    if fill::canonicalize_domain(&snapshot.domain)
+                                        == command.target_domain =>
+                                {
+                                    fill::check_max_lengths(&command, &snapshot.fields, policy)
                                 }
-                                let mut response = Response::from_string(r#"{"status":"ok"}"#);
+                                _ => Vec::new(),
+                            },
+                            Err(e) => {
+                                eprintln!("[Asterisk HTTP] ERROR: Failed to read form snapshot: {}", e);
+                                let body = serde_json::json!({ "error": e });
+                                let mut response =
+                                    Response::from_string(body.to_string()).with_status_code(500);
                                 response.add_header(
                                     Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
                                         .unwrap(),
@@ -826,11 +4323,51 @@ fn start_http_server(
                                     response.add_header(header);
                                 }
                                 let _ = request.respond(response);
+                                continue;
+                            }
+                        };
+
+                        if length_issues.iter().any(|issue| issue.rejected) {
+                            eprintln!(
+                                "[Asterisk HTTP] Rejected fill command {}: values exceed maxLength",
+                                command.id
+                            );
+                            let body = serde_json::json!({
+                                "error": "Fill values exceed field maxLength",
+                                "problems": length_issues,
+                            });
+                            let mut response =
+                                Response::from_string(body.to_string()).with_status_code(422);
+                            response.add_header(
+                                Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                                    .unwrap(),
+                            );
+                            for header in cors_headers {
+                                response.add_header(header);
                             }
+                            let _ = request.respond(response);
+                            continue;
+                        }
+
+                        // Clear out expired commands before queueing another
+                        fill::prune_store(&fill_command_store, fill_clock.now());
+
+                        // Hold the command back until the user consents to this domain
+                        let authorized = match queue_fill_command(
+                            command,
+                            &consent_store,
+                            &fill_command_store,
+                            events.as_ref(),
+                        ) {
+                            Ok(authorized) => authorized,
                             Err(e) => {
+                                eprintln!(
+                                    "[Asterisk HTTP] ERROR: Failed to queue fill command: {}",
+                                    e
+                                );
+                                let body = serde_json::json!({ "error": e });
                                 let mut response =
-                                    Response::from_string(format!(r#"{{"error":"{}"}}"#, e))
-                                        .with_status_code(400);
+                                    Response::from_string(body.to_string()).with_status_code(500);
                                 response.add_header(
                                     Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
                                         .unwrap(),
@@ -839,10 +4376,35 @@ fn start_http_server(
                                     response.add_header(header);
                                 }
                                 let _ = request.respond(response);
+                                continue;
                             }
+                        };
+                        let status = if authorized { "ok" } else { "awaiting_consent" };
+
+                        let revision = fill_command_store.revision();
+                        let body = if length_issues.is_empty() {
+                            serde_json::json!({ "status": status, "revision": revision })
+                        } else {
+                            serde_json::json!({
+                                "status": status,
+                                "revision": revision,
+                                "warnings": length_issues,
+                            })
+                        };
+                        let status_code = if authorized { 200 } else { 202 };
+                        let mut response =
+                            Response::from_string(body.to_string()).with_status_code(status_code);
+                        response.add_header(
+                            Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                                .unwrap(),
+                        );
+                        for header in cors_headers {
+                            response.add_header(header);
                         }
+                        let _ = request.respond(response);
                     }
                     Err(e) => {
+                        eprintln!("[Asterisk HTTP] Invalid fill command JSON: {}", e);
                         let mut response =
                             Response::from_string(format!(r#"{{"error":"{}"}}"#, e))
                                 .with_status_code(400);
@@ -859,15 +4421,50 @@ fn start_http_server(
                 continue;
             }
 
-            // Route: DELETE /v1/vault?key=xxx (delete a vault item)
-            if method == "DELETE" && url.starts_with("/v1/vault?key=") {
-                let key = url.strip_prefix("/v1/vault?key=").unwrap_or("");
-                let key = urlencoding::decode(key).unwrap_or_default().to_string();
+            // Route: GET /v1/fill-commands?domain=xxx (extension polls for commands)
+            if method == "GET" && url.starts_with("/v1/fill-commands") {
+                let domain = if url.contains("?domain=") {
+                    url.split("?domain=").nth(1).map(|s| {
+                        fill::canonicalize_domain(&urlencoding::decode(s).unwrap_or_default())
+                    })
+                } else {
+                    None
+                };
 
-                if let Ok(mut vault) = vault_store.lock() {
-                    let _ = vault.delete(&key);
-                }
-                let mut response = Response::from_string(r#"{"status":"ok"}"#);
+                let (status_code, body) = match fill_command_store.lock() {
+                    Ok(store) => {
+                        // Filter by domain if specified, also filter out expired commands
+                        let now = fill_clock.now();
+                        let commands: Vec<FillCommandJson> = store
+                            .iter()
+                            .filter(|c| !fill::is_expired(c, now))
+                            .filter(|c| domain.as_ref().is_none_or(|d| &c.target_domain == d))
+                            .cloned()
+                            .collect();
+                        drop(store);
+                        fill_groups.mark_delivered(commands.iter().map(|c| c.id.as_str()));
+                        let recaptures = recapture_queue
+                            .lock()
+                            .map(|mut queue| queue.pending(domain.as_deref(), chrono::Utc::now()))
+                            .unwrap_or_default();
+                        // A pending re-capture wants answering as soon as a command would
+                        let pending = commands.len() + recaptures.len();
+                        (
+                            200,
+                            serde_json::json!({
+                                "pollHintMs": poll_hint(domain.as_deref(), pending),
+                                "commands": commands,
+                                "recaptureRequests": recaptures,
+                            }),
+                        )
+                    }
+                    Err(e) => {
+                        eprintln!("[Asterisk HTTP] ERROR: Failed to read fill commands: {}", e);
+                        (500, serde_json::json!({ "error": e }))
+                    }
+                };
+                let mut response =
+                    Response::from_string(body.to_string()).with_status_code(status_code);
                 response.add_header(
                     Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
                         .unwrap(),
@@ -879,8 +4476,8 @@ fn start_http_server(
                 continue;
             }
 
-            // Route: POST /v1/fill-commands (desktop sends a fill command)
-            if method == "POST" && url == "/v1/fill-commands" {
+            // Route: POST /v1/fill-commands/prior-values (extension reports pre-fill values for undo)
+            if method == "POST" && url == "/v1/fill-commands/prior-values" {
                 let mut body = String::new();
                 if let Err(e) = request.as_reader().read_to_string(&mut body) {
                     eprintln!("[Asterisk HTTP] Failed to read body: {}", e);
@@ -892,36 +4489,73 @@ fn start_http_server(
                     continue;
                 }
 
-                match serde_json::from_str::<FillCommandJson>(&body) {
-                    Ok(command) => {
-                        println!(
-                            "[Asterisk HTTP] Received fill command: {} -> {} fields",
-                            command.target_domain,
-                            command.fills.len()
-                        );
-
-                        // Store the command
-                        if let Ok(mut store) = fill_command_store.lock() {
-                            // Remove any existing command with same ID
-                            store.retain(|c| c.id != command.id);
-                            store.push(command);
-                        }
-
-                        let mut response = Response::from_string(r#"{"status":"ok"}"#);
-                        response.add_header(
-                            Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
-                                .unwrap(),
-                        );
-                        for header in cors_headers {
-                            response.add_header(header);
+                let (status, body) = match serde_json::from_str::<undo::PriorValuesJson>(&body) {
+                    Ok(prior) => {
+                        let command = fill_command_store.lock().map(|store| {
+                            store.iter().find(|c| c.id == prior.command_id).cloned()
+                        });
+                        match command {
+                            Err(e) => {
+                                eprintln!("[Asterisk HTTP] ERROR: Failed to read fill commands: {}", e);
+                                (500, serde_json::json!({ "error": e }))
+                            }
+                            Ok(Some(command)) => {
+                                let captured = undo_store.lock().map_err(|e| e.to_string()).and_then(
+                                    |mut store| {
+                                        store.capture(
+                                            &command,
+                                            prior.prior_values,
+                                            chrono::Utc::now(),
+                                        )
+                                    },
+                                );
+                                match captured {
+                                    Ok(count) => (
+                                        200,
+                                        serde_json::json!({ "status": "ok", "captured": count }),
+                                    ),
+                                    Err(e) => (409, serde_json::json!({ "error": e })),
+                                }
+                            }
+                            Ok(None) => (
+                                404,
+                                serde_json::json!({ "error": "Unknown fill command" }),
+                            ),
                         }
-                        let _ = request.respond(response);
                     }
                     Err(e) => {
-                        eprintln!("[Asterisk HTTP] Invalid fill command JSON: {}", e);
+                        eprintln!("[Asterisk HTTP] Failed to parse prior values: {}", e);
+                        (400, serde_json::json!({ "error": "Invalid prior values" }))
+                    }
+                };
+                let mut response = Response::from_string(body.to_string()).with_status_code(status);
+                response.add_header(
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                        .unwrap(),
+                );
+                for header in cors_headers {
+                    response.add_header(header);
+                }
+                let _ = request.respond(response);
+                continue;
+            }
+
+            // Route: DELETE /v1/fill-commands?id=xxx (extension acknowledges command completion)
+            if method == "DELETE" && url.starts_with("/v1/fill-commands?id=") {
+                let id = url.strip_prefix("/v1/fill-commands?id=").unwrap_or("");
+                let id = urlencoding::decode(id).unwrap_or_default().to_string();
+
+                let completed = fill_command_store.lock().map(|mut store| {
+                    let index = store.iter().position(|c| c.id == id)?;
+                    Some(store.remove(index))
+                });
+                let completed = match completed {
+                    Ok(completed) => completed,
+                    Err(e) => {
+                        eprintln!("[Asterisk HTTP] ERROR: Failed to complete fill command: {}", e);
+                        let body = serde_json::json!({ "error": e });
                         let mut response =
-                            Response::from_string(format!(r#"{{"error":"{}"}}"#, e))
-                                .with_status_code(400);
+                            Response::from_string(body.to_string()).with_status_code(500);
                         response.add_header(
                             Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
                                 .unwrap(),
@@ -930,35 +4564,72 @@ fn start_http_server(
                             response.add_header(header);
                         }
                         let _ = request.respond(response);
+                        continue;
+                    }
+                };
+                println!("[Asterisk HTTP] Fill command completed: {}", id);
+
+                // Keep the filled values if the user opted in to recall for this domain
+                if let Some(command) = completed {
+                    fill_groups.mark_applied(&command.id);
+                    if let Ok(mut plan) = plan_activity.lock() {
+                        if plan
+                            .as_ref()
+                            .is_some_and(|p| p.domain == command.target_domain)
+                        {
+                            *plan = None;
+                        }
+                    }
+
+                    let fields = snapshot_store
+                        .lock()
+                        .ok()
+                        .and_then(|s| s.clone())
+                        .map(|s| s.fields)
+                        .unwrap_or_default();
+                    let fills = recall::labelled_fills(&command, &fields);
+                    if let Ok(mut store) = recall_store.lock() {
+                        if let Err(e) = store.record(&command.target_domain, &fills) {
+                            eprintln!("[Asterisk Recall] {}", e);
+                        }
                     }
                 }
+                let body = serde_json::json!({
+                    "status": "ok",
+                    "revision": fill_command_store.revision(),
+                });
+                let mut response = Response::from_string(body.to_string());
+                response.add_header(
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                        .unwrap(),
+                );
+                for header in cors_headers {
+                    response.add_header(header);
+                }
+                let _ = request.respond(response);
                 continue;
             }
 
-            // Route: GET /v1/fill-commands?domain=xxx (extension polls for commands)
-            if method == "GET" && url.starts_with("/v1/fill-commands") {
-                let domain = if url.contains("?domain=") {
-                    url.split("?domain=").nth(1).map(|s| {
-                        urlencoding::decode(s).unwrap_or_default().to_string()
-                    })
-                } else {
-                    None
-                };
+            // Route: GET /v1/site-info?domain=xxx (extension asks what it may do on a site)
+            if method == "GET" && url.starts_with("/v1/site-info?domain=") {
+                let domain = url.strip_prefix("/v1/site-info?domain=").unwrap_or("");
+                let domain = urlencoding::decode(domain).unwrap_or_default().to_string();
 
-                let json_response = match fill_command_store.lock() {
+                let (status_code, body) = match consent_store.lock() {
                     Ok(store) => {
-                        // Filter by domain if specified, also filter out expired commands
-                        let now = chrono::Utc::now().to_rfc3339();
-                        let commands: Vec<&FillCommandJson> = store
-                            .iter()
-                            .filter(|c| c.expires_at > now)
-                            .filter(|c| domain.as_ref().map_or(true, |d| &c.target_domain == d))
-                            .collect();
-                        serde_json::to_string(&commands).unwrap_or_else(|_| "[]".to_string())
+                        let status = store.status(&domain);
+                        (
+                            200,
+                            serde_json::json!({
+                                "domain": status.domain,
+                                "consent": status,
+                            }),
+                        )
                     }
-                    Err(_) => "[]".to_string(),
+                    Err(e) => (500, serde_json::json!({ "error": e })),
                 };
-                let mut response = Response::from_string(json_response);
+                let mut response =
+                    Response::from_string(body.to_string()).with_status_code(status_code);
                 response.add_header(
                     Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
                         .unwrap(),
@@ -970,19 +4641,15 @@ fn start_http_server(
                 continue;
             }
 
-            // Route: DELETE /v1/fill-commands?id=xxx (extension acknowledges command completion)
-            if method == "DELETE" && url.starts_with("/v1/fill-commands?id=") {
-                let id = url.strip_prefix("/v1/fill-commands?id=").unwrap_or("");
-                let id = urlencoding::decode(id).unwrap_or_default().to_string();
-
-                if let Ok(mut store) = fill_command_store.lock() {
-                    store.retain(|c| c.id != id);
-                }
-                println!("[Asterisk HTTP] Fill command completed: {}", id);
-                let mut response = Response::from_string(r#"{"status":"ok"}"#);
+            // Route: GET /v1/audit/stats (fill totals for the active profile, no values)
+            if method == "GET" && url == "/v1/audit/stats" {
+                let (status_code, body) = match audit_log.stats() {
+                    Ok(stats) => (200, serde_json::to_value(&stats).unwrap_or_default()),
+                    Err(e) => (500, serde_json::json!({ "error": e })),
+                };
+                let mut response = Response::from_string(body.to_string()).with_status_code(status_code);
                 response.add_header(
-                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
-                        .unwrap(),
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap(),
                 );
                 for header in cors_headers {
                     response.add_header(header);
@@ -998,7 +4665,7 @@ fn start_http_server(
             }
             let _ = request.respond(response);
         }
-    });
+    })
 }
 
 // ============================================================================
@@ -1007,62 +4674,798 @@ fn start_http_server(
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // Initialize vault store (in-memory for now)
-    let vault_store: Arc<Mutex<Box<dyn VaultStore>>> =
-        Arc::new(Mutex::new(Box::new(InMemoryStore::new())));
+    // Initialize vault store (in-memory for now), journaling changed keys
+    let vault_changes = Arc::new(changes::ChangeJournal::default());
+    let vault: Box<dyn VaultStore> = Box::new(changes::JournaledStore::new(
+        Box::new(InMemoryStore::new()),
+        Arc::clone(&vault_changes),
+    ));
+    let vault_store = shared::SharedVault::new("vault", vault);
 
     // Initialize form snapshot store (separate from vault)
-    let snapshot_store: Arc<Mutex<Option<FormSnapshotJson>>> = Arc::new(Mutex::new(None));
+    let snapshot_store = shared::SnapshotStore::new("form snapshot", None);
+    let form_sessions = sessions::FormSessionStore::new("form session", sessions::FormSessions::new());
+    let plan_activity = Arc::new(Mutex::new(None));
+    let recapture_queue = Arc::new(Mutex::new(recapture::RecaptureQueue::default()));
 
     // Initialize fill command store (desktop → extension)
-    let fill_command_store: Arc<Mutex<Vec<FillCommandJson>>> = Arc::new(Mutex::new(Vec::new()));
+    let fill_command_store = shared::FillCommandStore::new("fill command", Vec::new());
+    let fill_clock = Arc::new(clock::Clock::system());
+    let fill_groups = Arc::new(groups::FillCommandGroups::new());
+    let max_length_policy = Arc::new(Mutex::new(fill::MaxLengthPolicy::default()));
 
     // Initialize audit log path (in app data directory)
-    let audit_log_path = dirs::data_local_dir()
+    let requested_dir = dirs::data_local_dir()
         .unwrap_or_else(|| PathBuf::from("."))
-        .join("asterisk")
-        .join("audit.jsonl");
+        .join("asterisk");
+
+    // Probe the data directory once; fall back to temp or memory if read-only
+    let (active_dir, data_dir_status) =
+        storage::resolve_data_dir(&requested_dir, &std::env::temp_dir().join("asterisk"));
+    let in_memory = active_dir.is_none();
+    let data_dir = active_dir.unwrap_or(requested_dir);
+
+    // Files that fail to load are left alone and their subsystem disabled
+    let mut startup_loads = Vec::new();
+    if !in_memory {
+        let audit_path = data_dir.join(audit::log_file_name(audit::DEFAULT_PROFILE));
+        startup_loads.push(startup::SubsystemLoad {
+            subsystem: startup::Subsystem::AuditLog,
+            result: startup::check_audit_log(&audit_path),
+            path: audit_path,
+        });
+    }
+    let audit_disabled = startup::plan(&startup_loads).is_disabled(startup::Subsystem::AuditLog);
+
+    let audit_log = Arc::new(if in_memory || audit_disabled {
+        audit::AuditLog::in_memory()
+    } else {
+        audit::AuditLog::new(&data_dir)
+    });
+    if let Err(e) = audit::AuditDetail::from_env().and_then(|detail| audit_log.set_detail(detail)) {
+        eprintln!("[Asterisk Audit] {}", e);
+    }
+    let access_log = Arc::new(access::AccessLog::new(data_dir.join("access.jsonl")));
+    let matching_telemetry = Arc::new(telemetry::MatchingTelemetry::new(
+        data_dir.join("matching.jsonl"),
+        &data_dir.join("telemetry.salt"),
+    ));
+    let change_history = Arc::new(history::VaultHistory::new(
+        data_dir.join("vault-history.jsonl"),
+    ));
+    if in_memory {
+        change_history.set_enabled(false);
+    }
+    let maintenance_ledger = if in_memory {
+        maintenance::MaintenanceLedger::in_memory()
+    } else {
+        maintenance::MaintenanceLedger::new(data_dir.join("maintenance.jsonl"))
+    };
+
+    // Developers can keep the vault in a plain JSON file across restarts
+    if backend::json_vault_from_env() {
+        let opened = if in_memory {
+            Err("No writable data directory for the JSON vault".to_string())
+        } else {
+            backend::open_backend(
+                backend::VaultBackendKind::Json,
+                &Default::default(),
+                Some(&data_dir),
+            )
+        };
+        let swapped = opened.and_then(|store| {
+            let store = Box::new(changes::JournaledStore::new(store, Arc::clone(&vault_changes)));
+            let summary = backend::swap_in(&vault_store, backend::VaultBackendKind::Json, store)?;
+            vault_changes.reset(&vault_store)?;
+            Ok(summary)
+        });
+        match swapped {
+            Ok(summary) => println!(
+                "[Vault] Using the JSON file vault in {} ({} items)",
+                data_dir.display(),
+                summary.items
+            ),
+            // Keep the in-memory vault rather than start over a file we can't read
+            Err(e) => eprintln!("[Vault] JSON file vault unavailable: {}", e),
+        }
+    } else if !in_memory {
+        // The encrypted vault's master key lives in the OS keychain
+        let encrypted_path = data_dir.join(backend::KEYCHAIN_VAULT_FILE);
+        let existed = encrypted_path.exists();
+        let keyring = Arc::new(asterisk_vault::OsKeyring);
+        let swapped =
+            backend::open_keychain_vault(keyring, &data_dir).and_then(|(store, created)| {
+                let store = Box::new(changes::JournaledStore::new(
+                    store,
+                    Arc::clone(&vault_changes),
+                ));
+                let summary =
+                    backend::swap_in(&vault_store, backend::VaultBackendKind::Encrypted, store)?;
+                vault_changes.reset(&vault_store)?;
+                Ok((summary, created))
+            });
+        // A vault file that won't open disables the vault instead of
+        // quietly running on an empty one
+        startup_loads.push(startup::SubsystemLoad::opened(
+            startup::Subsystem::Vault,
+            &encrypted_path,
+            existed,
+            &swapped,
+        ));
+        match swapped {
+            Ok((summary, created)) => println!(
+                "[Vault] Using the encrypted vault in {} ({} items{})",
+                data_dir.display(),
+                summary.items,
+                if created { ", new master key" } else { "" }
+            ),
+            Err(e) => eprintln!(
+                "[Vault] WARNING: encrypted vault unavailable, vault is in memory only and \
+                 will be lost on exit: {}",
+                e
+            ),
+        }
+    }
+
+    // Load saved form templates (fall back to an empty store on a bad file)
+    let template_store = if in_memory {
+        templates::TemplateStore::in_memory()
+    } else {
+        let path = data_dir.join("templates.json");
+        let loaded = templates::TemplateStore::load(&path);
+        startup_loads.push(startup::SubsystemLoad::new(
+            startup::Subsystem::Settings,
+            &path,
+            &loaded,
+        ));
+        loaded.unwrap_or_else(|e| {
+            eprintln!("[Templates] {}", e);
+            templates::TemplateStore::in_memory()
+        })
+    };
+
+    // Load saved field overrides
+    let override_store = if in_memory {
+        overrides::OverrideStore::in_memory()
+    } else {
+        let path = data_dir.join("field-overrides.json");
+        let loaded = overrides::OverrideStore::load(&path);
+        startup_loads.push(startup::SubsystemLoad::new(
+            startup::Subsystem::Settings,
+            &path,
+            &loaded,
+        ));
+        loaded.unwrap_or_else(|e| {
+            eprintln!("[Overrides] {}", e);
+            overrides::OverrideStore::in_memory()
+        })
+    };
+
+    let fill_rules = if in_memory {
+        Vec::new()
+    } else {
+        let path = data_dir.join(rules::RULES_FILE);
+        let loaded = rules::load(&path);
+        startup_loads.push(startup::SubsystemLoad::new(
+            startup::Subsystem::Settings,
+            &path,
+            &loaded,
+        ));
+        loaded.unwrap_or_else(|e| {
+            eprintln!("[Fill Rules] {}", e);
+            Vec::new()
+        })
+    };
+
+    // LLM spend limits from the environment
+    let llm_budget = llm::LlmBudgetJson::from_env().unwrap_or_else(|e| {
+        eprintln!("[LLM] {}", e);
+        llm::LlmBudgetJson::default()
+    });
+
+    // Seed the analysis cache with template mappings so known forms skip the LLM
+    let analysis_cache = Arc::new(llm::AnalysisCache::new());
+    analysis_cache.warm_from_templates(template_store.list());
+
+    // Load per-domain fill consent
+    let consent_store = if in_memory {
+        consent::ConsentStore::in_memory()
+    } else {
+        let path = data_dir.join("consent.json");
+        let loaded = consent::ConsentStore::load(&path);
+        startup_loads.push(startup::SubsystemLoad::new(
+            startup::Subsystem::Settings,
+            &path,
+            &loaded,
+        ));
+        loaded.unwrap_or_else(|e| {
+            eprintln!("[Consent] {}", e);
+            consent::ConsentStore::in_memory()
+        })
+    };
+    let consent_store = consent::SharedConsentStore::new("consent", consent_store);
+
+    // Load the recall store, which has its own key file separate from the vault
+    let recall_store = if in_memory {
+        recall::RecallStore::in_memory()
+    } else {
+        let path = data_dir.join("recall.json");
+        let loaded = recall::RecallStore::load(&path, &data_dir.join("recall.key"));
+        startup_loads.push(startup::SubsystemLoad::new(
+            startup::Subsystem::Settings,
+            &path,
+            &loaded,
+        ));
+        loaded.unwrap_or_else(|e| {
+            eprintln!("[Recall] {}", e);
+            recall::RecallStore::in_memory()
+        })
+    };
+    let recall_store = Arc::new(Mutex::new(recall_store));
+
+    // Prior values for undo never touch disk
+    let undo_store = Arc::new(Mutex::new(undo::UndoStore::new()));
+
+    let client_tracker = Arc::new(compat::ClientTracker::new());
+
+    // Load scoped bridge tokens
+    let token_store = if in_memory {
+        tokens::TokenStore::in_memory()
+    } else {
+        let path = data_dir.join("tokens.json");
+        let loaded = tokens::TokenStore::load(&path);
+        startup_loads.push(startup::SubsystemLoad::new(
+            startup::Subsystem::Settings,
+            &path,
+            &loaded,
+        ));
+        loaded.unwrap_or_else(|e| {
+            eprintln!("[Tokens] {}", e);
+            tokens::TokenStore::in_memory()
+        })
+    };
+    let mut token_store = token_store;
+    match tokens::configured_from_env() {
+        Ok(configured) => token_store.configure(configured),
+        Err(e) => eprintln!("[Tokens] {}", e),
+    }
+    let pairing_path = data_dir.join(tokens::PAIRING_TOKEN_FILE);
+    let pairing_token =
+        tokens::load_or_create_pairing_token((!in_memory).then_some(pairing_path.as_path()))
+            .unwrap_or_else(|e| {
+                eprintln!("[Tokens] {}; pairing is needed again after a restart", e);
+                tokens::load_or_create_pairing_token(None).unwrap_or_default()
+            });
+    token_store.set_pairing_token(&pairing_token);
+    let token_store = Arc::new(Mutex::new(token_store));
+
+    let bridge_pause = Arc::new(pause::BridgePause::new());
+
+    // Load first-run onboarding progress
+    let onboarding = if in_memory {
+        onboarding::Onboarding::in_memory()
+    } else {
+        let path = data_dir.join("onboarding.json");
+        let loaded = onboarding::Onboarding::load(&path);
+        startup_loads.push(startup::SubsystemLoad::new(
+            startup::Subsystem::Settings,
+            &path,
+            &loaded,
+        ));
+        loaded.unwrap_or_else(|e| {
+            eprintln!("[Onboarding] {}", e);
+            onboarding::Onboarding::in_memory()
+        })
+    };
+    let onboarding = onboarding::OnboardingStore::new("onboarding", onboarding);
+
+    let startup_plan = startup::plan(&startup_loads);
+    for problem in &startup_plan.problems {
+        eprintln!(
+            "[Startup] {:?} disabled: {}: {}",
+            problem.subsystem, problem.path, problem.reason
+        );
+    }
+
+    // Bridge events reach the UI once the app handle exists
+    let app_handle: Arc<OnceLock<tauri::AppHandle>> = Arc::new(OnceLock::new());
+    let events: EventSink = {
+        let app_handle = Arc::clone(&app_handle);
+        Arc::new(move |event, payload| {
+            if let Some(handle) = app_handle.get() {
+                let _ = handle.emit(event, payload);
+            }
+        })
+    };
+
+    // Stored snapshots may start a plan in the background
+    let snapshot_hook: eager::SnapshotHook = {
+        let app_handle = Arc::clone(&app_handle);
+        Arc::new(move |snapshot, incognito| {
+            if let Some(handle) = app_handle.get() {
+                eager_analyze(handle.clone(), snapshot.clone(), incognito);
+            }
+        })
+    };
+
+    // Lock the vault on sleep and after idling, as configured
+    let lock_settings = vault_lock::LockSettingsJson::from_env().unwrap_or_else(|e| {
+        eprintln!("[Vault Lock] {}", e);
+        vault_lock::LockSettingsJson::default()
+    });
+    let lock = Arc::new(vault_lock::VaultLock::new(
+        lock_settings.into(),
+        chrono::Utc::now(),
+        Arc::clone(&events),
+    ));
+    power::watch(power::ClockJumpSource::default(), Arc::clone(&lock));
+    vault_lock::spawn_idle_lock(&lock, vault_store.clone());
+    fill::spawn_pruner(fill_command_store.clone(), Arc::clone(&fill_clock));
 
     // Start HTTP server for extension bridge
-    start_http_server(
-        Arc::clone(&snapshot_store),
-        Arc::clone(&vault_store),
-        Arc::clone(&fill_command_store),
-    );
+    let requested_port = bridge_port::port_from_env().unwrap_or_else(|e| {
+        eprintln!("[Asterisk HTTP] {}", e);
+        bridge_port::DEFAULT_PORT
+    });
+    let bridge_context = BridgeContext {
+        snapshot_store: snapshot_store.clone(),
+        form_sessions: form_sessions.clone(),
+        plan_activity: Arc::clone(&plan_activity),
+        recapture_queue: Arc::clone(&recapture_queue),
+        vault_store: vault_store.clone(),
+        vault_history: Arc::clone(&change_history),
+        vault_changes: Arc::clone(&vault_changes),
+        fill_command_store: fill_command_store.clone(),
+        fill_clock: Arc::clone(&fill_clock),
+        fill_groups: Arc::clone(&fill_groups),
+        max_length_policy: Arc::clone(&max_length_policy),
+        access_log: Arc::clone(&access_log),
+        consent_store: consent_store.clone(),
+        recall_store: Arc::clone(&recall_store),
+        undo_store: Arc::clone(&undo_store),
+        client_tracker: Arc::clone(&client_tracker),
+        audit_log: Arc::clone(&audit_log),
+        token_store: Arc::clone(&token_store),
+        bridge_pause: Arc::clone(&bridge_pause),
+        onboarding: onboarding.clone(),
+        cors: cors::CorsPolicy::from_env(),
+        vault_lock: Arc::clone(&lock),
+        events,
+        snapshot_hook,
+    };
+    let bound_port = start_http_server(bridge_context, requested_port)
+        .map_err(|e| eprintln!("[Asterisk HTTP] Failed to start server: {}", e))
+        .ok();
+    if let Err(e) = bridge_port::write_port_file(&data_dir, bound_port) {
+        eprintln!("[Asterisk HTTP] {}", e);
+    }
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .manage(AppState {
-            vault: Arc::clone(&vault_store),
+            vault: vault_store.clone(),
+            history: change_history,
+            changes: vault_changes,
+        })
+        .manage(VaultBackendState {
+            data_dir: (!in_memory).then(|| data_dir.clone()),
+        })
+        .manage(FindReplaceState {
+            previews: Mutex::new(find_replace::FindReplaceStore::new()),
         })
         .manage(FormSnapshotState {
             latest: snapshot_store,
+            sessions: form_sessions,
+            plan_activity,
+            recapture: recapture_queue,
         })
         .manage(FillCommandState {
             commands: fill_command_store,
+            clock: fill_clock,
+            max_length_policy,
+            groups: fill_groups,
         })
         .manage(AuditState {
-            log_path: audit_log_path,
+            log: audit_log,
+            access_log,
+            telemetry: matching_telemetry,
+            evidence_key: data_dir.join("evidence.key"),
+        })
+        .manage(StorageState {
+            status: data_dir_status,
+        })
+        .manage(StartupState {
+            plan: Mutex::new(startup_plan.clone()),
+        })
+        .manage(MaintenanceState {
+            ledger: maintenance_ledger,
+        })
+        .manage(TemplateState {
+            store: Arc::new(Mutex::new(template_store)),
+        })
+        .manage(OverrideState {
+            store: Mutex::new(override_store),
+        })
+        .manage(FillRulesState { rules: fill_rules })
+        .manage(AcceptanceState {
+            store: Mutex::new(acceptance::AcceptanceStore::new()),
         })
+        .manage(ConsentState {
+            store: consent_store,
+        })
+        .manage(RecallState {
+            store: recall_store,
+        })
+        .manage(UndoState { store: undo_store })
+        .manage(BridgeClientState {
+            tracker: client_tracker,
+        })
+        .manage(BridgeTokenState {
+            store: token_store,
+            pairing_token,
+        })
+        .manage(BridgePortState { port: bound_port })
+        .manage(BridgePauseState {
+            pause: bridge_pause,
+        })
+        .manage(OnboardingState { store: onboarding })
         .manage(ApiKeyState {
             claude_api_key: Arc::new(Mutex::new(None)),
         })
+        .manage(LlmRedactionState {
+            tokens: Arc::new(Mutex::new(Vec::new())),
+        })
+        .manage(LlmCacheState {
+            cache: analysis_cache,
+            sessions: Arc::new(llm::AnalysisSessions::new()),
+        })
+        .manage(VaultLockState { lock })
+        .manage(EagerAnalysisState {
+            analysis: Arc::new(eager::EagerAnalysis::new()),
+        })
+        .manage(LlmBudgetState {
+            usage: Arc::new(llm::LlmUsageStats::new(llm_budget, chrono::Utc::now())),
+        })
+        .setup(move |app| {
+            let _ = app_handle.set(app.handle().clone());
+            if startup_plan.safe_mode() {
+                let _ = app.emit(startup::STARTUP_PROBLEMS_EVENT, &startup_plan);
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             vault_set,
+            vault_set_many,
             vault_get,
+            vault_get_many,
             vault_list,
+            vault_list_by_category,
+            vault_search,
+            vault_needs_review,
             vault_delete,
+            vault_history,
+            vault_history_set_enabled,
+            vault_scan_pii,
+            vault_find_replace_preview,
+            vault_find_replace_apply,
+            vault_export_jsonl,
+            vault_import_jsonl,
+            vault_import_simple,
+            vault_export_profile,
+            vault_import_profile,
+            vault_review_queue,
+            vault_review_resolve,
+            vault_lock_status,
+            vault_lock_configure,
+            vault_lock,
+            vault_unlock,
+            vault_status,
+            vault_activity,
+            settings_set,
+            vault_switch_backend,
+            vault_key_status,
+            features_available,
+            locale_get,
+            locale_set,
+            time_sanity_check,
+            bridge_port,
+            bridge_conformance_run,
+            import_analyze_columns,
             get_latest_form_snapshot,
+            request_recapture,
+            generate_fill_plan,
+            reconcile_field_types,
+            check_fill_rules,
+            match_explanations,
+            fill_plan_find_by_hash,
+            fill_plan_set_value,
+            fill_plan_exclude,
+            fill_plan_include,
+            fill_plan_reset_field,
+            fill_plan_accept,
+            benchmark_matching,
+            template_save,
+            template_list,
+            template_report_fill_result,
+            template_health,
+            template_verify,
+            template_repair,
+            set_field_override,
+            remove_field_override,
+            list_field_overrides,
+            fill_command_check_lengths,
+            fill_command_create,
+            set_max_length_policy,
+            consent_grant,
+            consent_deny,
+            consent_revoke,
+            consent_list,
+            consent_status,
+            recall_set_enabled,
+            recall_domains,
+            recall_search,
+            recall_purge,
             audit_append,
             audit_list,
             audit_get,
+            undo_fill,
             audit_clear,
             audit_path,
+            audit_detail_get,
+            audit_detail_set,
+            profile_get_active,
+            profile_switch,
+            audit_prune,
+            usage_heatmap,
+            audit_domains,
+            vault_never_filled,
+            audit_export_signed,
+            fill_commands_purge_expired,
+            fill_commands_advance_clock,
+            fill_command_create_group,
+            fill_command_group_status,
+            fill_command_cancel_group,
+            maintenance_history,
+            storage_status,
+            startup_problems,
+            startup_resolve,
+            storage_report,
+            storage_footprint,
+            storage_trim,
+            access_log_set_enabled,
+            access_log_list,
+            matching_telemetry_set_enabled,
+            matching_metrics_summary,
+            bridge_client_status,
+            bridge_token,
+            bridge_token_create,
+            bridge_token_revoke,
+            bridge_token_list,
+            bridge_set_capability,
+            bridge_capabilities,
             llm_analyze_field,
+            llm_analyze_batch,
+            llm_analyze_snapshot,
+            suggest_matches_stream,
+            llm_cache_stats,
+            eager_analysis_set_enabled,
+            eager_analysis_status,
+            llm_budget_status,
+            llm_budget_configure,
+            cache_warm_from_templates,
+            preview_llm_input,
+            set_llm_redaction_tokens,
+            get_llm_redaction_tokens,
             set_api_key,
+            validate_api_key_format,
             has_api_key,
             clear_api_key,
+            onboarding_status,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GOOD: &str = "2026-01-01T00:00:00Z";
+    const BAD: &str = "2026-13-45 noon";
+
+    /// Fields that hold a timestamp, in bit order for `item_json`
+    const TIMESTAMP_FIELDS: [&str; 4] = ["provenance", "created", "updated", "last_used"];
+
+    /// An item whose timestamp fields are malformed where `bad_mask` has a bit set
+    fn item_json(bad_mask: u8) -> VaultItemJson {
+        let pick = |bit: u8| if bad_mask & (1 << bit) != 0 { BAD } else { GOOD }.to_string();
+        VaultItemJson {
+            key: "email".to_string(),
+            value: "ada@example.com".to_string(),
+            label: "Email".to_string(),
+            category: "contact".to_string(),
+            provenance: ProvenanceJson {
+                source: "imported".to_string(),
+                timestamp: pick(0),
+                confidence: 1.0,
+                origin: None,
+            },
+            metadata: VaultMetadataJson {
+                created: pick(1),
+                updated: pick(2),
+                last_used: Some(pick(3)),
+                usage_count: 0,
+            },
+            sensitive: false,
+            review_status: ReviewStatus::Approved,
+            review_after: None,
+        }
+    }
+
+    #[test]
+    fn test_review_after_round_trips_and_is_checked() {
+        let mut json = item_json(0);
+        json.review_after = Some(GOOD.to_string());
+        let item = VaultItem::try_from(json).unwrap();
+        assert_eq!(item.review_after, Some(GOOD.parse().unwrap()));
+        assert!(item.needs_review(chrono::Utc::now()));
+        let back = VaultItemJson::from(item);
+        assert_eq!(back.review_after.as_deref(), Some("2026-01-01T00:00:00+00:00"));
+
+        let mut json = item_json(0);
+        json.review_after = Some(BAD.to_string());
+        assert!(VaultItem::try_from(json).is_err());
+    }
+
+    #[test]
+    fn test_strict_conversion_rejects_any_bad_timestamp() {
+        for mask in 0..16u8 {
+            let result = VaultItem::try_from(item_json(mask));
+            assert_eq!(result.is_ok(), mask == 0, "mask {:04b}: {:?}", mask, result.err());
+        }
+    }
+
+    #[test]
+    fn test_lossy_conversion_falls_back_per_field() {
+        let good: chrono::DateTime<chrono::Utc> = GOOD.parse().unwrap();
+        for mask in 0..16u8 {
+            let before = chrono::Utc::now();
+            let (item, warnings) = VaultItem::try_from_lossy(item_json(mask))
+                .unwrap_or_else(|e| panic!("mask {:04b}: {}", mask, e));
+
+            assert_eq!(warnings.len(), mask.count_ones() as usize, "mask {:04b}", mask);
+            for (bit, field) in TIMESTAMP_FIELDS.iter().enumerate() {
+                let bad = mask & (1 << bit) != 0;
+                assert_eq!(
+                    warnings.iter().any(|w| w.contains(&format!("invalid {} timestamp", field))),
+                    bad,
+                    "mask {:04b} field {}",
+                    mask,
+                    field
+                );
+            }
+
+            // created/updated/provenance fall back to now, last_used to None
+            let expect = |bit: u8, value: chrono::DateTime<chrono::Utc>| {
+                if mask & (1 << bit) != 0 {
+                    assert!(value >= before, "mask {:04b} bit {}", mask, bit);
+                } else {
+                    assert_eq!(value, good, "mask {:04b} bit {}", mask, bit);
+                }
+            };
+            expect(0, item.provenance.timestamp);
+            expect(1, item.metadata.created);
+            expect(2, item.metadata.updated);
+            let expected_last_used = if mask & 0b1000 != 0 { None } else { Some(good) };
+            assert_eq!(item.metadata.last_used, expected_last_used, "mask {:04b}", mask);
+        }
+    }
+
+    #[test]
+    fn test_lossy_conversion_still_rejects_bad_category_and_source() {
+        let mut json = item_json(0b1111);
+        json.category = "misc".to_string();
+        assert!(VaultItem::try_from_lossy(json)
+            .unwrap_err()
+            .contains("Invalid category"));
+
+        let mut json = item_json(0);
+        json.provenance.source = "scraped".to_string();
+        assert!(VaultItem::try_from_lossy(json)
+            .unwrap_err()
+            .contains("Invalid source"));
+    }
+
+    #[test]
+    fn test_missing_last_used_is_not_a_warning() {
+        let mut json = item_json(0);
+        json.metadata.last_used = None;
+        let (item, warnings) = VaultItem::try_from_lossy(json).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(item.metadata.last_used, None);
+    }
+
+    #[test]
+    fn test_vault_list_filters_by_category_and_search() {
+        let item = |key: &str, label: &str, category: &str| {
+            let mut json = item_json(0);
+            json.key = key.to_string();
+            json.label = label.to_string();
+            json.category = category.to_string();
+            VaultItem::try_from(json).unwrap()
+        };
+        let items = vec![
+            item("phone", "Phone", "contact"),
+            item("email", "Email", "contact"),
+            item("street", "Home street", "address"),
+        ];
+
+        let contact = parse_category("contact").unwrap();
+        let keys = |items: Vec<VaultItem>| items.into_iter().map(|i| i.key).collect::<Vec<_>>();
+        assert_eq!(
+            keys(filter_vault_items(items.clone(), Some(&contact), None)),
+            ["email", "phone"]
+        );
+        // Search covers key, label and value, ignoring case
+        assert_eq!(
+            keys(filter_vault_items(items.clone(), None, Some("HOME"))),
+            ["street"]
+        );
+        assert_eq!(
+            keys(filter_vault_items(items.clone(), None, Some("ada@"))),
+            ["email", "phone", "street"]
+        );
+        assert!(filter_vault_items(items, Some(&contact), Some("street")).is_empty());
+        assert_eq!(parse_category("misc").unwrap_err(), "Invalid category: misc");
+    }
+
+    #[test]
+    fn test_queue_fill_commands_decides_consent_once_per_domain() {
+        let command = |id: &str, domain: &str| FillCommandJson {
+            id: id.to_string(),
+            target_domain: domain.to_string(),
+            target_url: None,
+            fills: vec![],
+            created_at: GOOD.to_string(),
+            expires_at: GOOD.to_string(),
+            capture_undo: false,
+            plan_hash: None,
+        };
+        let consent_store = shared::SharedStore::new("consent", consent::ConsentStore::in_memory());
+        consent_store
+            .lock()
+            .unwrap()
+            .grant("example.com", consent::ConsentScope::Once)
+            .unwrap();
+        let fill_command_store = shared::SharedStore::new("fill command", Vec::new());
+        let requested = Mutex::new(Vec::new());
+        let events = |event: &str, payload: serde_json::Value| {
+            assert_eq!(event, consent::CONSENT_REQUIRED_EVENT);
+            requested.lock().unwrap().push(payload["commandId"].clone());
+        };
+
+        let commands = [
+            command("a", "example.com"),
+            command("b", "other.com"),
+            command("c", "example.com"),
+        ];
+        let queued =
+            queue_fill_commands(&commands, &consent_store, &fill_command_store, &events).unwrap();
+
+        // The "once" grant covers both example.com commands
+        assert_eq!(queued, [true, false, true]);
+        let ids: Vec<String> = fill_command_store
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|c| c.id.clone())
+            .collect();
+        assert_eq!(ids, ["a", "c"]);
+        assert_eq!(*requested.lock().unwrap(), [serde_json::json!("b")]);
+        assert!(!consent_store.lock().unwrap().authorize("example.com"));
+    }
+}
@@ -0,0 +1,413 @@
+/*!
+ * Batch Field Analysis
+ *
+ * Analyzes several fields in one LLM call. The model answers with a JSON
+ * array holding one object per field, each echoing the field's number from
+ * the prompt. Field ids and page text beyond the redacted prompt are never
+ * sent.
+ *
+ * Models sometimes get one element of the array wrong (a missing comma, an
+ * unquoted key, a string cut off) while the rest are fine. Rather than
+ * failing the whole batch, the array is read element by element: well-formed
+ * objects are matched to their field by echoed number, or else by position,
+ * and every field left without a usable answer comes back as no match with
+ * the reason. The call only fails when nothing in the reply can be used.
+ */
+
+use serde::{Deserialize, Serialize};
+
+use super::{response_from_value, AnalyzeFieldRequest, AnalyzeFieldResponse};
+use super::{LlmError, LlmProvider, PromptRedaction};
+use crate::matching::LabelSource;
+
+/// One field of a batch request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchFieldRequest {
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    #[serde(flatten)]
+    pub request: AnalyzeFieldRequest,
+}
+
+/// Outcome for one field of a batch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchFieldResultJson {
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    pub result: AnalyzeFieldResponse,
+    /// False when the model's answer for this field was missing or malformed;
+    /// `result` is then a no match carrying the reason
+    pub parsed: bool,
+}
+
+/// Analyze `requests` in a single provider call
+///
+/// Results follow request order. The prompt is redacted with `redaction`
+/// before it is sent.
+pub async fn analyze_batch(
+    provider: &dyn LlmProvider,
+    requests: &[BatchFieldRequest],
+    redaction: &PromptRedaction,
+) -> Result<Vec<BatchFieldResultJson>, LlmError> {
+    if requests.is_empty() {
+        return Ok(Vec::new());
+    }
+    println!(
+        "[LLM] Analyzing {} fields in one batch with {}",
+        requests.len(),
+        provider.name()
+    );
+
+    let redacted: Vec<AnalyzeFieldRequest> = requests
+        .iter()
+        .map(|r| r.request.redacted(redaction))
+        .collect();
+    let prompt = build_batch_prompt(&redacted);
+    let (text, served) = provider.complete_with_source(&prompt).await.map_err(|e| {
+        eprintln!("[LLM] {}", e);
+        e
+    })?;
+
+    let mut results = parse_batch_response(&text, requests)?;
+    for (result, request) in results.iter_mut().zip(requests) {
+        result.result.provider = Some(served.name.to_string());
+        if !result.parsed {
+            eprintln!(
+                "[LLM] Batch answer for field {} unusable: {}",
+                result.field_id, result.result.reasoning
+            );
+            continue;
+        }
+        // Answers based on weak label context deserve a little less trust
+        if let Some((source, _)) = request.request.label_context().best() {
+            result.result.confidence *= source.confidence_factor();
+        }
+    }
+    Ok(results)
+}
+
+/// Build the prompt for a batch of fields, numbered from 1
+fn build_batch_prompt(requests: &[AnalyzeFieldRequest]) -> String {
+    let mut available_keys: Vec<&str> = Vec::new();
+    for key in requests.iter().flat_map(|r| &r.available_keys) {
+        if !available_keys.contains(&key.as_str()) {
+            available_keys.push(key);
+        }
+    }
+
+    let fields: Vec<String> = requests
+        .iter()
+        .enumerate()
+        .map(|(i, request)| {
+            let (label_source, label) = request
+                .label_context()
+                .best()
+                .unwrap_or((LabelSource::Label, ""));
+            format!(
+                "Field {}:\n- Label: \"{}\" (from {})\n- Name attribute: \"{}\"\n- Input type: \"{}\"\n- Placeholder: {}\n- Semantic hint: {}",
+                i + 1,
+                label,
+                label_source.description(),
+                request.name,
+                request.field_type,
+                request.placeholder.as_deref().unwrap_or("(none)"),
+                request.semantic.as_deref().unwrap_or("unknown"),
+            )
+        })
+        .collect();
+
+    format!(
+        r#"You are analyzing form fields to determine which user data each one expects.
+
+{}
+
+Available vault data keys:
+{}
+
+Task: For each field, determine which vault key (if any) should be used to fill it.
+
+Respond ONLY with a valid JSON array, one object per field in field order, one object per line:
+[
+{{"field": 1, "vaultKey": "keyName", "confidence": 0.85, "reasoning": "explanation"}},
+{{"field": 2, "vaultKey": null, "confidence": 0.0, "reasoning": "explanation"}}
+]
+
+Confidence scale:
+- 0.80-0.90: Strong semantic match
+- 0.60-0.80: Likely match but some ambiguity
+- 0.40-0.60: Possible match, low confidence
+- 0.0-0.40: No clear match
+
+If no vault key matches, set vaultKey to null. Be conservative with confidence scores."#,
+        fields.join("\n\n"),
+        available_keys.join(", ")
+    )
+}
+
+/// Split the first JSON array in `text` into its top-level elements
+///
+/// Elements are returned as raw text, well-formed or not. A string that runs
+/// to the end of a line is treated as closed there (JSON strings can't hold
+/// raw newlines), so one broken element doesn't swallow the rest. Returns
+/// None if there is no array.
+fn split_array_elements(text: &str) -> Option<Vec<&str>> {
+    let start = text.find('[')? + 1;
+    let body = &text[start..];
+
+    let mut elements = Vec::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut element_start = 0;
+    for (i, c) in body.char_indices() {
+        if in_string && c != '\n' {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            ']' if depth > 0 => depth -= 1,
+            ']' => {
+                elements.push(&body[element_start..i]);
+                return Some(elements);
+            }
+            ',' if depth == 0 => {
+                elements.push(&body[element_start..i]);
+                element_start = i + 1;
+            }
+            '\n' => {
+                in_string = false;
+                escaped = false;
+                // An element left open by a broken line ends where the next
+                // element (or the array) starts
+                let next = body[i + 1..].trim_start().chars().next();
+                if depth > 0 && matches!(next, Some('{') | Some(']')) {
+                    depth = 0;
+                }
+                // A line that closes an object ends the element even when the
+                // separating comma is missing
+                if depth == 0 && !body[element_start..i].trim().is_empty() {
+                    elements.push(&body[element_start..i]);
+                    element_start = i + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    // Reply cut off before the closing bracket
+    elements.push(&body[element_start..]);
+    Some(elements)
+}
+
+/// Parse a batch reply, keeping every well-formed answer
+///
+/// Fails only when the reply holds no usable answer at all.
+fn parse_batch_response(
+    text: &str,
+    requests: &[BatchFieldRequest],
+) -> Result<Vec<BatchFieldResultJson>, LlmError> {
+    let elements = split_array_elements(text).ok_or_else(|| {
+        LlmError::BadResponse("Batch response contains no JSON array".to_string())
+    })?;
+    let elements: Vec<&str> = elements
+        .into_iter()
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .collect();
+
+    let mut answers: Vec<Option<AnalyzeFieldResponse>> = vec![None; requests.len()];
+    let mut problems: Vec<Option<String>> = vec![None; requests.len()];
+    for (position, element) in elements.iter().enumerate() {
+        let parsed = serde_json::from_str::<serde_json::Value>(element)
+            .map_err(|e| e.to_string())
+            .and_then(|value| match value.is_object() {
+                true => Ok(value),
+                false => Err("not an object".to_string()),
+            });
+        let value = match parsed {
+            Ok(value) => value,
+            Err(e) => {
+                if let Some(problem) = problems.get_mut(position) {
+                    *problem = Some(format!("Malformed answer in batch response: {}", e));
+                }
+                continue;
+            }
+        };
+
+        // Echoed field number first, then position
+        let echoed = value
+            .get("field")
+            .and_then(|v| v.as_u64())
+            .and_then(|n| usize::try_from(n).ok()?.checked_sub(1))
+            .filter(|&i| i < requests.len() && answers[i].is_none());
+        let Some(index) =
+            echoed.or(Some(position).filter(|&i| i < requests.len() && answers[i].is_none()))
+        else {
+            continue;
+        };
+        answers[index] = Some(response_from_value(
+            &value,
+            &requests[index].request.available_keys,
+        ));
+    }
+
+    if answers.iter().all(Option::is_none) {
+        return Err(LlmError::BadResponse(
+            "Batch response contains no usable answers".to_string(),
+        ));
+    }
+
+    Ok(requests
+        .iter()
+        .zip(answers.into_iter().zip(problems))
+        .map(|(request, (answer, problem))| match answer {
+            Some(result) => BatchFieldResultJson {
+                field_id: request.field_id.clone(),
+                result,
+                parsed: true,
+            },
+            None => BatchFieldResultJson {
+                field_id: request.field_id.clone(),
+                result: AnalyzeFieldResponse {
+                    vault_key: None,
+                    confidence: 0.0,
+                    reasoning: problem.unwrap_or_else(|| {
+                        "No answer for this field in batch response".to_string()
+                    }),
+                    provider: None,
+                },
+                parsed: false,
+            },
+        })
+        .collect())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::MockProvider;
+
+    fn request(field_id: &str, label: &str) -> BatchFieldRequest {
+        BatchFieldRequest {
+            field_id: field_id.to_string(),
+            request: AnalyzeFieldRequest {
+                label: label.to_string(),
+                name: String::new(),
+                field_type: "text".to_string(),
+                placeholder: None,
+                semantic: None,
+                available_keys: vec![
+                    "email".to_string(),
+                    "phone".to_string(),
+                    "company".to_string(),
+                ],
+                aria_label: None,
+                described_by: None,
+                section_heading: None,
+                nearby_text: None,
+            },
+        }
+    }
+
+    fn requests() -> Vec<BatchFieldRequest> {
+        vec![
+            request("f-email", "Email"),
+            request("f-phone", "Phone"),
+            request("f-org", "Organization"),
+        ]
+    }
+
+    #[test]
+    fn test_malformed_element_keeps_the_others() {
+        // Second element is missing a comma and has an unterminated string
+        let reply = r#"[
+{"field": 1, "vaultKey": "email", "confidence": 0.9, "reasoning": "Email label"},
+{"field": 2, "vaultKey": "phone" "confidence": 0.8, "reasoning": "Phone label},
+{"field": 3, "vaultKey": "company", "confidence": 0.7, "reasoning": "Employer"}
+]"#;
+        let results = parse_batch_response(reply, &requests()).unwrap();
+        let [email, phone, org] = results.as_slice() else {
+            panic!("expected three results: {:?}", results);
+        };
+
+        assert!(email.parsed);
+        assert_eq!(email.result.vault_key.as_deref(), Some("email"));
+        assert!(org.parsed);
+        assert_eq!(org.field_id, "f-org");
+        assert_eq!(org.result.vault_key.as_deref(), Some("company"));
+
+        assert!(!phone.parsed);
+        assert_eq!(phone.field_id, "f-phone");
+        assert_eq!(phone.result.vault_key, None);
+        assert!(
+            phone.result.reasoning.contains("Malformed"),
+            "{}",
+            phone.result.reasoning
+        );
+    }
+
+    #[test]
+    fn test_echoed_number_wins_over_position() {
+        let reply = r#"[
+{"field": 3, "vaultKey": "company", "confidence": 0.7, "reasoning": "Employer"},
+{"field": 1, "vaultKey": "email", "confidence": 0.9, "reasoning": "Email label"}
+]"#;
+        let results = parse_batch_response(reply, &requests()).unwrap();
+        assert_eq!(results[0].result.vault_key.as_deref(), Some("email"));
+        assert_eq!(results[2].result.vault_key.as_deref(), Some("company"));
+        assert!(!results[1].parsed);
+        assert!(results[1].result.reasoning.contains("No answer"));
+    }
+
+    #[test]
+    fn test_truncated_reply_keeps_complete_elements() {
+        let reply = r#"[{"vaultKey": "email", "confidence": 0.9, "reasoning": "a"},
+{"vaultKey": "phone", "confidence": 0.8, "reasoning": "b"},
+{"vaultKey": "comp"#;
+        let results = parse_batch_response(reply, &requests()).unwrap();
+        assert_eq!(results[0].result.vault_key.as_deref(), Some("email"));
+        assert_eq!(results[1].result.vault_key.as_deref(), Some("phone"));
+        assert!(!results[2].parsed);
+    }
+
+    #[test]
+    fn test_reply_without_usable_answers_fails() {
+        assert!(parse_batch_response("I can't help with that", &requests()).is_err());
+        assert!(parse_batch_response("[oops, nope]", &requests()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_batch_sends_one_numbered_prompt() {
+        let provider = MockProvider::new(vec![r#"[
+{"field": 1, "vaultKey": "email", "confidence": 0.9, "reasoning": "Email label"},
+{"field": 2, "vaultKey": "fax", "confidence": 0.8, "reasoning": "Not a vault key"},
+{"field": 3, "vaultKey": null, "confidence": 0.0, "reasoning": "No match"}
+]"#
+        .to_string()]);
+
+        let results = analyze_batch(&provider, &requests(), &PromptRedaction::default())
+            .await
+            .unwrap();
+        assert_eq!(provider.prompts().len(), 1);
+        let prompt = &provider.prompts()[0];
+        assert!(prompt.contains("Field 3:\n- Label: \"Organization\""));
+        assert!(!prompt.contains("f-org"));
+
+        assert_eq!(results[0].result.vault_key.as_deref(), Some("email"));
+        // Unknown keys are dropped like in single-field analysis
+        assert!(results[1].parsed);
+        assert_eq!(results[1].result.vault_key, None);
+        assert_eq!(results[2].result.vault_key, None);
+    }
+}
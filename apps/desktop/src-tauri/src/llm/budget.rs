@@ -0,0 +1,380 @@
+/*!
+ * LLM Spend Budget
+ *
+ * `LlmUsageStats` counts the tokens sent to and received from the provider
+ * in a fixed window (a day by default) and enforces an optional cap on
+ * tokens, dollars or both. Every provider the app builds is wrapped in a
+ * `BudgetedProvider`, so once the cap is hit no further call goes out from
+ * any LLM path; fields that needed one come back as budget exceeded and
+ * stay local-only until the window rolls over.
+ *
+ * Providers don't report token counts through `LlmProvider`, so usage is
+ * estimated at four characters per token of prompt and reply, and dollars
+ * from a configured price per million tokens. Usage lives in memory: a
+ * restart starts a fresh window.
+ *
+ * The cap is read from the environment at startup and can be changed with
+ * `llm_budget_configure`:
+ *
+ * - `ASTERISK_LLM_BUDGET_TOKENS`: most tokens per window
+ * - `ASTERISK_LLM_BUDGET_USD`: most estimated dollars per window
+ * - `ASTERISK_LLM_BUDGET_WINDOW_HOURS`: window length (default 24)
+ * - `ASTERISK_LLM_USD_PER_MTOK`: price used for the estimate (default 3.0)
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use super::{LlmError, LlmFuture, LlmProvider, SourcedFuture};
+
+pub const BUDGET_TOKENS_ENV: &str = "ASTERISK_LLM_BUDGET_TOKENS";
+pub const BUDGET_USD_ENV: &str = "ASTERISK_LLM_BUDGET_USD";
+pub const BUDGET_WINDOW_ENV: &str = "ASTERISK_LLM_BUDGET_WINDOW_HOURS";
+pub const USD_PER_MTOK_ENV: &str = "ASTERISK_LLM_USD_PER_MTOK";
+
+/// Default budget window: one day
+pub const DEFAULT_WINDOW_HOURS: u32 = 24;
+
+/// Default price for the dollar estimate, per million tokens
+pub const DEFAULT_USD_PER_MTOK: f64 = 3.0;
+
+/// Characters per token assumed by the estimate
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Estimated token count of `text`
+pub fn estimate_tokens(text: &str) -> u64 {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN) as u64
+}
+
+/// Spend cap and window; no cap means unlimited
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LlmBudgetJson {
+    #[serde(rename = "maxTokens", default)]
+    pub max_tokens: Option<u64>,
+    #[serde(rename = "maxUsd", default)]
+    pub max_usd: Option<f64>,
+    #[serde(rename = "windowHours")]
+    pub window_hours: u32,
+    #[serde(rename = "usdPerMillionTokens")]
+    pub usd_per_million_tokens: f64,
+}
+
+impl Default for LlmBudgetJson {
+    fn default() -> Self {
+        Self {
+            max_tokens: None,
+            max_usd: None,
+            window_hours: DEFAULT_WINDOW_HOURS,
+            usd_per_million_tokens: DEFAULT_USD_PER_MTOK,
+        }
+    }
+}
+
+impl LlmBudgetJson {
+    /// Read the budget from the `ASTERISK_LLM_BUDGET_*` variables
+    pub fn from_env() -> Result<Self, String> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        fn parse<T: std::str::FromStr>(
+            name: &str,
+            value: Option<String>,
+        ) -> Result<Option<T>, String> {
+            value
+                .map(|v| {
+                    v.trim()
+                        .parse()
+                        .map_err(|_| format!("{} must be a number: {}", name, v))
+                })
+                .transpose()
+        }
+        let defaults = Self::default();
+        Self {
+            max_tokens: parse(BUDGET_TOKENS_ENV, var(BUDGET_TOKENS_ENV))?,
+            max_usd: parse(BUDGET_USD_ENV, var(BUDGET_USD_ENV))?,
+            window_hours: parse(BUDGET_WINDOW_ENV, var(BUDGET_WINDOW_ENV))?
+                .unwrap_or(defaults.window_hours),
+            usd_per_million_tokens: parse(USD_PER_MTOK_ENV, var(USD_PER_MTOK_ENV))?
+                .unwrap_or(defaults.usd_per_million_tokens),
+        }
+        .validated()
+    }
+
+    /// Refuse a zero-length window or negative amounts
+    pub fn validated(self) -> Result<Self, String> {
+        if self.window_hours == 0 {
+            return Err("Budget window must be at least one hour".to_string());
+        }
+        let invalid = |amount: f64| amount.is_nan() || amount < 0.0;
+        if self.max_usd.is_some_and(invalid) || invalid(self.usd_per_million_tokens) {
+            return Err("Budget amounts must not be negative".to_string());
+        }
+        Ok(self)
+    }
+
+    fn cost_usd(&self, tokens: u64) -> f64 {
+        tokens as f64 * self.usd_per_million_tokens / 1_000_000.0
+    }
+}
+
+/// Where the current window stands
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LlmBudgetStatusJson {
+    pub budget: LlmBudgetJson,
+    #[serde(rename = "windowStartedAt")]
+    pub window_started_at: String,
+    #[serde(rename = "windowEndsAt")]
+    pub window_ends_at: String,
+    pub calls: u64,
+    #[serde(rename = "usedTokens")]
+    pub used_tokens: u64,
+    #[serde(rename = "usedUsd")]
+    pub used_usd: f64,
+    /// None without a token cap
+    #[serde(rename = "remainingTokens")]
+    pub remaining_tokens: Option<u64>,
+    /// None without a dollar cap
+    #[serde(rename = "remainingUsd")]
+    pub remaining_usd: Option<f64>,
+    /// Whether LLM calls are refused until the window ends
+    pub exceeded: bool,
+}
+
+#[derive(Debug)]
+struct Window {
+    started_at: DateTime<Utc>,
+    calls: u64,
+    tokens: u64,
+}
+
+/// Token usage in the current budget window, and the cap it's held to
+#[derive(Debug)]
+pub struct LlmUsageStats {
+    budget: Mutex<LlmBudgetJson>,
+    window: Mutex<Window>,
+}
+
+impl LlmUsageStats {
+    pub fn new(budget: LlmBudgetJson, now: DateTime<Utc>) -> Self {
+        Self {
+            budget: Mutex::new(budget),
+            window: Mutex::new(Window {
+                started_at: now,
+                calls: 0,
+                tokens: 0,
+            }),
+        }
+    }
+
+    fn budget(&self) -> LlmBudgetJson {
+        *self.budget.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The window, rolled over first if it has ended
+    fn window(&self, now: DateTime<Utc>) -> MutexGuard<'_, Window> {
+        let length = Duration::hours(i64::from(self.budget().window_hours));
+        let mut window = self.window.lock().unwrap_or_else(PoisonError::into_inner);
+        if now >= window.started_at + length {
+            *window = Window {
+                started_at: now,
+                calls: 0,
+                tokens: 0,
+            };
+        }
+        window
+    }
+
+    /// Change the cap; usage so far in the window still counts
+    pub fn configure(&self, budget: LlmBudgetJson) -> Result<(), String> {
+        *self.budget.lock().unwrap_or_else(PoisonError::into_inner) = budget.validated()?;
+        Ok(())
+    }
+
+    /// Count one call's tokens against the window
+    pub fn record(&self, tokens: u64, now: DateTime<Utc>) {
+        let mut window = self.window(now);
+        window.calls += 1;
+        window.tokens += tokens;
+    }
+
+    pub fn status(&self, now: DateTime<Utc>) -> LlmBudgetStatusJson {
+        let budget = self.budget();
+        let window = self.window(now);
+        let used_usd = budget.cost_usd(window.tokens);
+        let remaining_tokens = budget
+            .max_tokens
+            .map(|max| max.saturating_sub(window.tokens));
+        let remaining_usd = budget.max_usd.map(|max| (max - used_usd).max(0.0));
+        LlmBudgetStatusJson {
+            window_started_at: window.started_at.to_rfc3339(),
+            window_ends_at: (window.started_at + Duration::hours(i64::from(budget.window_hours)))
+                .to_rfc3339(),
+            calls: window.calls,
+            used_tokens: window.tokens,
+            used_usd,
+            remaining_tokens,
+            remaining_usd,
+            exceeded: remaining_tokens == Some(0) || remaining_usd.is_some_and(|usd| usd <= 0.0),
+            budget,
+        }
+    }
+
+    /// Fail with `BudgetExceeded` once the window's cap is used up
+    pub fn check(&self, now: DateTime<Utc>) -> Result<(), LlmError> {
+        let status = self.status(now);
+        if status.exceeded {
+            Err(LlmError::BudgetExceeded(status.window_ends_at))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A provider whose calls are checked against and counted in a budget
+pub struct BudgetedProvider {
+    inner: Box<dyn LlmProvider>,
+    usage: Arc<LlmUsageStats>,
+}
+
+impl BudgetedProvider {
+    pub fn new(inner: Box<dyn LlmProvider>, usage: Arc<LlmUsageStats>) -> Self {
+        Self { inner, usage }
+    }
+}
+
+impl LlmProvider for BudgetedProvider {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    /// The prompt is counted even when the call fails, since the provider
+    /// may have billed it
+    fn complete<'a>(&'a self, prompt: &'a str) -> LlmFuture<'a> {
+        Box::pin(async move {
+            self.complete_with_source(prompt)
+                .await
+                .map(|(text, _)| text)
+        })
+    }
+
+    fn complete_with_source<'a>(&'a self, prompt: &'a str) -> SourcedFuture<'a> {
+        Box::pin(async move {
+            self.usage.check(Utc::now())?;
+            let reply = self.inner.complete_with_source(prompt).await;
+            let reply_tokens = reply.as_ref().map_or(0, |(text, _)| estimate_tokens(text));
+            self.usage
+                .record(estimate_tokens(prompt) + reply_tokens, Utc::now());
+            reply
+        })
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::MockProvider;
+
+    fn at(hours: i64) -> DateTime<Utc> {
+        "2026-05-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::hours(hours)
+    }
+
+    fn capped(max_tokens: u64) -> LlmBudgetJson {
+        LlmBudgetJson {
+            max_tokens: Some(max_tokens),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_cap_blocks_until_window_rolls_over() {
+        let usage = LlmUsageStats::new(capped(100), at(0));
+        assert!(usage.check(at(0)).is_ok());
+
+        usage.record(60, at(1));
+        assert_eq!(usage.status(at(1)).remaining_tokens, Some(40));
+        assert!(usage.check(at(1)).is_ok());
+
+        usage.record(60, at(2));
+        let status = usage.status(at(2));
+        assert!(status.exceeded);
+        assert_eq!(status.remaining_tokens, Some(0));
+        assert!(matches!(
+            usage.check(at(23)),
+            Err(LlmError::BudgetExceeded(_))
+        ));
+
+        // A day after the window began, it starts over
+        assert!(usage.check(at(24)).is_ok());
+        assert_eq!(usage.status(at(24)).used_tokens, 0);
+    }
+
+    #[test]
+    fn test_dollar_cap_and_unlimited_budget() {
+        let budget = LlmBudgetJson {
+            max_usd: Some(0.01),
+            usd_per_million_tokens: 10.0,
+            ..Default::default()
+        };
+        let usage = LlmUsageStats::new(budget, at(0));
+        usage.record(999, at(0));
+        assert!(usage.check(at(0)).is_ok());
+        usage.record(1, at(0));
+        assert!(usage.check(at(0)).is_err());
+
+        let unlimited = LlmUsageStats::new(LlmBudgetJson::default(), at(0));
+        unlimited.record(u64::MAX / 2, at(0));
+        assert!(unlimited.check(at(0)).is_ok());
+        assert_eq!(unlimited.status(at(0)).remaining_tokens, None);
+    }
+
+    #[test]
+    fn test_budget_from_vars() {
+        let vars = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        assert_eq!(
+            LlmBudgetJson::from_vars(vars(&[])).unwrap(),
+            LlmBudgetJson::default()
+        );
+
+        let budget = LlmBudgetJson::from_vars(vars(&[
+            (BUDGET_TOKENS_ENV, "50000"),
+            (BUDGET_WINDOW_ENV, " 12 "),
+        ]))
+        .unwrap();
+        assert_eq!(budget.max_tokens, Some(50_000));
+        assert_eq!(budget.window_hours, 12);
+
+        assert!(LlmBudgetJson::from_vars(vars(&[(BUDGET_TOKENS_ENV, "lots")])).is_err());
+        assert!(LlmBudgetJson::from_vars(vars(&[(BUDGET_WINDOW_ENV, "0")])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_budgeted_provider_refuses_once_spent() {
+        let usage = Arc::new(LlmUsageStats::new(
+            capped(estimate_tokens("hello")),
+            Utc::now(),
+        ));
+        let provider =
+            BudgetedProvider::new(Box::new(MockProvider::new(vec![])), Arc::clone(&usage));
+
+        assert!(provider.complete("hello").await.is_ok());
+        assert!(usage.status(Utc::now()).exceeded);
+        assert!(matches!(
+            provider.complete("hello").await,
+            Err(LlmError::BudgetExceeded(_))
+        ));
+        assert_eq!(usage.status(Utc::now()).calls, 1);
+    }
+}
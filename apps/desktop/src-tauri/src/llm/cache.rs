@@ -0,0 +1,514 @@
+/*!
+ * Field Analysis Cache
+ *
+ * LLM answers are cached per field request (page context plus the available
+ * vault keys), so analyzing the same form again costs nothing.
+ *
+ * Each entry also remembers a hash of the labels of its vault keys. The
+ * answer was reasoned from those labels, so when one is renamed the entry is
+ * not evicted (a match by key is probably still right) but marked
+ * stale-label. A stale entry is still served, flagged as revalidating, and
+ * re-run opportunistically in the same batch; the next lookup gets the fresh
+ * answer.
+ *
+ * The cache can also be warmed from saved form templates. Those entries are
+ * keyed by form and field id rather than by request, carry the template's
+ * vault key as a synthetic high-confidence answer, and are tagged
+ * `source: template`; `warm_from_templates` rebuilds them all.
+ */
+
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use super::{AnalyzeFieldRequest, AnalyzeFieldResponse};
+use crate::matching::TEMPLATE_CONFIDENCE;
+use crate::templates::{template_id, FormTemplateJson};
+
+/// Entries kept before the oldest are dropped
+const DEFAULT_CAPACITY: usize = 2048;
+
+/// Where a cached answer came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheSource {
+    /// An earlier LLM answer
+    Llm,
+    /// A saved form template mapping
+    Template,
+}
+
+/// Result of looking a field up in the cache
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheLookup {
+    Fresh(AnalyzeFieldResponse),
+    /// Labels changed since this answer; serve it but re-run the field
+    StaleLabel(AnalyzeFieldResponse),
+    Miss,
+}
+
+/// Cache counters, for the diagnostics view
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisCacheStatsJson {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries currently marked stale-label
+    #[serde(rename = "staleLabel")]
+    pub stale_label: usize,
+    pub revalidations: u64,
+    /// Revalidations whose answer picked a different vault key
+    #[serde(rename = "revalidationsChanged")]
+    pub revalidations_changed: u64,
+    /// Field mappings loaded from templates
+    #[serde(rename = "templateEntries", default)]
+    pub template_entries: usize,
+    #[serde(rename = "templateHits", default)]
+    pub template_hits: u64,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    response: AnalyzeFieldResponse,
+    available_keys: Vec<String>,
+    labels_hash: u64,
+    stale_label: bool,
+}
+
+#[derive(Debug, Default)]
+struct CacheInner {
+    entries: HashMap<String, CacheEntry>,
+    /// Insertion order, oldest first
+    order: VecDeque<String>,
+    /// Current vault labels by key
+    labels: HashMap<String, String>,
+    /// Vault key by (template id, field id), from saved templates
+    templates: HashMap<(String, String), String>,
+    stats: AnalysisCacheStatsJson,
+}
+
+impl CacheInner {
+    fn labels_hash(&self, keys: &[String]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for key in keys {
+            key.hash(&mut hasher);
+            self.labels.get(key).hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    fn insert(
+        &mut self,
+        cache_key: String,
+        request: &AnalyzeFieldRequest,
+        response: AnalyzeFieldResponse,
+        capacity: usize,
+    ) {
+        let available_keys = sorted_keys(request);
+        let labels_hash = self.labels_hash(&available_keys);
+        let entry = CacheEntry {
+            response,
+            available_keys,
+            labels_hash,
+            stale_label: false,
+        };
+        if self.entries.insert(cache_key.clone(), entry).is_none() {
+            self.order.push_back(cache_key);
+        }
+        while self.entries.len() > capacity {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+fn sorted_keys(request: &AnalyzeFieldRequest) -> Vec<String> {
+    let mut keys = request.available_keys.clone();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+/// Cache key: the whole request, with the available keys in a stable order
+fn cache_key(request: &AnalyzeFieldRequest) -> String {
+    let mut normalized = request.clone();
+    normalized.available_keys = sorted_keys(request);
+    serde_json::to_string(&normalized).unwrap_or_default()
+}
+
+/// In-memory cache of LLM field answers
+#[derive(Debug)]
+pub struct AnalysisCache {
+    inner: Mutex<CacheInner>,
+    capacity: usize,
+}
+
+impl Default for AnalysisCache {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl AnalysisCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(CacheInner::default()),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// A poisoned lock only means a panic mid-update of counters or entries;
+    /// the cache is safe to keep using
+    fn inner(&self) -> MutexGuard<'_, CacheInner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Record the current vault labels, marking entries whose keys' labels
+    /// changed as stale-label. Returns how many entries were newly marked.
+    pub fn observe_labels<I>(&self, labels: I) -> usize
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut inner = self.inner();
+        inner.labels = labels.into_iter().collect();
+
+        let hashes: Vec<(String, u64)> = inner
+            .entries
+            .iter()
+            .filter(|(_, entry)| !entry.stale_label)
+            .map(|(key, entry)| (key.clone(), inner.labels_hash(&entry.available_keys)))
+            .collect();
+        let mut marked = 0;
+        for (key, hash) in hashes {
+            if let Some(entry) = inner.entries.get_mut(&key) {
+                if entry.labels_hash != hash {
+                    entry.stale_label = true;
+                    marked += 1;
+                }
+            }
+        }
+        marked
+    }
+
+    /// Look up the cached answer for `request`
+    pub fn lookup(&self, request: &AnalyzeFieldRequest) -> CacheLookup {
+        let mut inner = self.inner();
+        let found = inner.entries.get(&cache_key(request)).map(|entry| {
+            if entry.stale_label {
+                CacheLookup::StaleLabel(entry.response.clone())
+            } else {
+                CacheLookup::Fresh(entry.response.clone())
+            }
+        });
+        match found {
+            Some(lookup) => {
+                inner.stats.hits += 1;
+                lookup
+            }
+            None => {
+                inner.stats.misses += 1;
+                CacheLookup::Miss
+            }
+        }
+    }
+
+    /// Cache a fresh answer for `request`
+    pub fn store(&self, request: &AnalyzeFieldRequest, response: &AnalyzeFieldResponse) {
+        let capacity = self.capacity;
+        self.inner()
+            .insert(cache_key(request), request, response.clone(), capacity);
+    }
+
+    /// Replace a stale-label entry with a re-run answer
+    ///
+    /// Returns whether the answer picked a different vault key than before.
+    pub fn revalidated(
+        &self,
+        request: &AnalyzeFieldRequest,
+        response: &AnalyzeFieldResponse,
+    ) -> bool {
+        let capacity = self.capacity;
+        let key = cache_key(request);
+        let mut inner = self.inner();
+        let changed = inner
+            .entries
+            .get(&key)
+            .is_some_and(|entry| entry.response.vault_key != response.vault_key);
+        inner.stats.revalidations += 1;
+        if changed {
+            inner.stats.revalidations_changed += 1;
+        }
+        inner.insert(key, request, response.clone(), capacity);
+        changed
+    }
+
+    /// Replace the template entries with the healthy mappings of
+    /// `templates`; returns how many were loaded
+    pub fn warm_from_templates(&self, templates: &[FormTemplateJson]) -> usize {
+        let entries: HashMap<(String, String), String> = templates
+            .iter()
+            .flat_map(|template| {
+                template
+                    .mappings
+                    .iter()
+                    .filter(|m| !m.suspect)
+                    .map(move |m| {
+                        (
+                            (template.id.clone(), m.field_id.clone()),
+                            m.vault_key.clone(),
+                        )
+                    })
+            })
+            .collect();
+        let loaded = entries.len();
+        self.inner().templates = entries;
+        loaded
+    }
+
+    /// Template answer for a field of the form `domain` + `fingerprint`
+    ///
+    /// Only served when the mapped key is among `available_keys`, so a
+    /// template pointing at a deleted vault item falls through to analysis.
+    pub fn lookup_template(
+        &self,
+        domain: &str,
+        fingerprint: &str,
+        field_id: &str,
+        available_keys: &[String],
+    ) -> Option<AnalyzeFieldResponse> {
+        let mut inner = self.inner();
+        let key = (template_id(domain, fingerprint), field_id.to_string());
+        let vault_key = inner
+            .templates
+            .get(&key)
+            .filter(|k| available_keys.contains(k))?
+            .clone();
+        inner.stats.template_hits += 1;
+        Some(AnalyzeFieldResponse {
+            vault_key: Some(vault_key),
+            confidence: TEMPLATE_CONFIDENCE,
+            reasoning: "Mapped by a saved form template".to_string(),
+            provider: None,
+        })
+    }
+
+    /// Maximum number of cached answers
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Evict every cached answer, keeping template mappings and counters
+    ///
+    /// Returns how many answers were evicted.
+    pub fn evict_all(&self) -> usize {
+        let mut inner = self.inner();
+        let evicted = inner.entries.len();
+        inner.entries.clear();
+        inner.order.clear();
+        evicted
+    }
+
+    pub fn stats(&self) -> AnalysisCacheStatsJson {
+        let inner = self.inner();
+        AnalysisCacheStatsJson {
+            entries: inner.entries.len(),
+            stale_label: inner.entries.values().filter(|e| e.stale_label).count(),
+            template_entries: inner.templates.len(),
+            ..inner.stats.clone()
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(label: &str, keys: &[&str]) -> AnalyzeFieldRequest {
+        AnalyzeFieldRequest {
+            label: label.to_string(),
+            name: label.to_lowercase(),
+            field_type: "text".to_string(),
+            placeholder: None,
+            semantic: None,
+            available_keys: keys.iter().map(|k| k.to_string()).collect(),
+            aria_label: None,
+            described_by: None,
+            section_heading: None,
+            nearby_text: None,
+        }
+    }
+
+    fn answer(key: Option<&str>) -> AnalyzeFieldResponse {
+        AnalyzeFieldResponse {
+            vault_key: key.map(str::to_string),
+            confidence: 0.8,
+            reasoning: "test".to_string(),
+            provider: None,
+        }
+    }
+
+    fn labels(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, l)| (k.to_string(), l.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_hit_ignores_key_order() {
+        let cache = AnalysisCache::new();
+        cache.store(
+            &request("Employer", &["company", "email"]),
+            &answer(Some("company")),
+        );
+
+        assert_eq!(
+            cache.lookup(&request("Employer", &["email", "company"])),
+            CacheLookup::Fresh(answer(Some("company")))
+        );
+        assert_eq!(
+            cache.lookup(&request("Employer", &["email"])),
+            CacheLookup::Miss
+        );
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+    }
+
+    #[test]
+    fn test_label_change_marks_only_affected_entries() {
+        let cache = AnalysisCache::new();
+        cache.observe_labels(labels(&[("company", "Company"), ("email", "Email")]));
+        cache.store(&request("Employer", &["company"]), &answer(Some("company")));
+        cache.store(&request("Contact", &["email"]), &answer(Some("email")));
+
+        // Same labels again: nothing changes
+        assert_eq!(
+            cache.observe_labels(labels(&[("company", "Company"), ("email", "Email")])),
+            0
+        );
+
+        let marked =
+            cache.observe_labels(labels(&[("company", "Employer name"), ("email", "Email")]));
+        assert_eq!(marked, 1);
+        assert_eq!(
+            cache.lookup(&request("Employer", &["company"])),
+            CacheLookup::StaleLabel(answer(Some("company")))
+        );
+        assert_eq!(
+            cache.lookup(&request("Contact", &["email"])),
+            CacheLookup::Fresh(answer(Some("email")))
+        );
+        assert_eq!(cache.stats().stale_label, 1);
+    }
+
+    #[test]
+    fn test_revalidation_clears_stale_and_counts_changes() {
+        let cache = AnalysisCache::new();
+        let employer = request("Employer", &["company", "school"]);
+        cache.observe_labels(labels(&[("company", "Company"), ("school", "School")]));
+        cache.store(&employer, &answer(Some("company")));
+        cache.observe_labels(labels(&[("company", "Company"), ("school", "Employer")]));
+
+        assert!(!cache.revalidated(&employer, &answer(Some("company"))));
+        cache.observe_labels(labels(&[("company", "Company"), ("school", "Workplace")]));
+        assert!(cache.revalidated(&employer, &answer(Some("school"))));
+
+        assert_eq!(
+            cache.lookup(&employer),
+            CacheLookup::Fresh(answer(Some("school")))
+        );
+        let stats = cache.stats();
+        assert_eq!(stats.revalidations, 2);
+        assert_eq!(stats.revalidations_changed, 1);
+        assert_eq!(stats.stale_label, 0);
+    }
+
+    fn template(
+        domain: &str,
+        fingerprint: &str,
+        mappings: &[(&str, &str, bool)],
+    ) -> FormTemplateJson {
+        FormTemplateJson {
+            id: template_id(domain, fingerprint),
+            domain: domain.to_string(),
+            fingerprint: fingerprint.to_string(),
+            mappings: mappings
+                .iter()
+                .map(
+                    |(field_id, vault_key, suspect)| crate::templates::TemplateMappingJson {
+                        field_id: field_id.to_string(),
+                        vault_key: vault_key.to_string(),
+                        suspect: *suspect,
+                    },
+                )
+                .collect(),
+            healed_count: 0,
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            updated_at: "2026-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_warm_from_templates() {
+        let cache = AnalysisCache::new();
+        let keys = vec!["company".to_string(), "email".to_string()];
+        let loaded = cache.warm_from_templates(&[
+            template(
+                "example.com",
+                "fp1",
+                &[("org", "company", false), ("mail", "email", true)],
+            ),
+            template("other.org", "fp2", &[("org", "phone", false)]),
+        ]);
+        assert_eq!(loaded, 2);
+
+        let hit = cache
+            .lookup_template("example.com", "fp1", "org", &keys)
+            .unwrap();
+        assert_eq!(hit.vault_key.as_deref(), Some("company"));
+        assert_eq!(hit.confidence, TEMPLATE_CONFIDENCE);
+        // Suspect mappings, other forms and keys no longer in the vault miss
+        assert!(cache
+            .lookup_template("example.com", "fp1", "mail", &keys)
+            .is_none());
+        assert!(cache
+            .lookup_template("example.com", "fp2", "org", &keys)
+            .is_none());
+        assert!(cache
+            .lookup_template("other.org", "fp2", "org", &keys)
+            .is_none());
+
+        let stats = cache.stats();
+        assert_eq!((stats.template_entries, stats.template_hits), (2, 1));
+
+        // Warming again replaces, rather than adds to, the template entries
+        assert_eq!(cache.warm_from_templates(&[]), 0);
+        assert!(cache
+            .lookup_template("example.com", "fp1", "org", &keys)
+            .is_none());
+    }
+
+    #[test]
+    fn test_capacity_drops_oldest() {
+        let cache = AnalysisCache::with_capacity(2);
+        cache.store(&request("A", &[]), &answer(None));
+        cache.store(&request("B", &[]), &answer(None));
+        cache.store(&request("C", &[]), &answer(None));
+
+        assert_eq!(cache.lookup(&request("A", &[])), CacheLookup::Miss);
+        assert_ne!(cache.lookup(&request("C", &[])), CacheLookup::Miss);
+        assert_eq!(cache.stats().entries, 2);
+    }
+}
@@ -0,0 +1,199 @@
+/*!
+ * Provider Fallback
+ *
+ * `FallbackProvider` tries an ordered list of providers until one answers,
+ * so an outage or a revoked key at the primary doesn't take field analysis
+ * down with it. Only failures another provider might not share move on to
+ * the next one (see `LlmError::allows_fallback`); a request the primary
+ * rejected as malformed would be just as malformed anywhere, so its error
+ * is returned as is. When every provider fails, the last error is returned.
+ * The provider that answered is named on each field's analysis result.
+ *
+ * The chain is configured with `ASTERISK_LLM_FALLBACK`, a comma-separated
+ * list of provider names tried after the primary (`openai,mock`).
+ */
+
+use super::{LlmError, LlmFuture, LlmProvider, ServedBy, SourcedFuture};
+
+/// Environment variable listing the providers tried after the primary
+pub const FALLBACK_ENV: &str = "ASTERISK_LLM_FALLBACK";
+
+impl LlmError {
+    /// Whether another provider might succeed where this one failed: the
+    /// request never got an answer, the provider is down or overloaded, it
+    /// refused our credentials, or its reply was unusable
+    pub fn allows_fallback(&self) -> bool {
+        match self {
+            LlmError::MissingApiKey(_) | LlmError::Request(_) | LlmError::BadResponse(_) => true,
+            LlmError::Api { status, .. } => matches!(status, 401 | 403 | 408 | 429 | 500..=599),
+            LlmError::UnknownProvider(_) | LlmError::BudgetExceeded(_) => false,
+        }
+    }
+}
+
+/// Providers tried in order until one answers
+pub struct FallbackProvider {
+    providers: Vec<Box<dyn LlmProvider>>,
+}
+
+impl FallbackProvider {
+    /// A chain trying `providers` in the order given; the first is the primary
+    pub fn new(providers: Vec<Box<dyn LlmProvider>>) -> Self {
+        Self { providers }
+    }
+
+    async fn first_answer(&self, prompt: &str) -> Result<(String, ServedBy), LlmError> {
+        let mut last_error =
+            LlmError::Request("No LLM provider is configured in the fallback chain".to_string());
+        for (index, provider) in self.providers.iter().enumerate() {
+            match provider.complete(prompt).await {
+                Ok(text) => {
+                    let served = ServedBy {
+                        index,
+                        name: provider.name(),
+                    };
+                    if index > 0 {
+                        println!("[LLM] Answered by fallback provider {}", served.name);
+                    }
+                    return Ok((text, served));
+                }
+                Err(e) if e.allows_fallback() => {
+                    eprintln!(
+                        "[LLM] {} failed, trying the next provider: {}",
+                        provider.name(),
+                        e
+                    );
+                    last_error = e;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error)
+    }
+}
+
+impl LlmProvider for FallbackProvider {
+    fn name(&self) -> &'static str {
+        "fallback"
+    }
+
+    fn complete<'a>(&'a self, prompt: &'a str) -> LlmFuture<'a> {
+        Box::pin(async move { self.first_answer(prompt).await.map(|(text, _)| text) })
+    }
+
+    fn complete_with_source<'a>(&'a self, prompt: &'a str) -> SourcedFuture<'a> {
+        Box::pin(self.first_answer(prompt))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::MockProvider;
+    use std::sync::Arc;
+
+    /// Shares a mock so the test can still read its prompts
+    struct Shared(Arc<MockProvider>);
+
+    impl LlmProvider for Shared {
+        fn name(&self) -> &'static str {
+            self.0.name()
+        }
+
+        fn complete<'a>(&'a self, prompt: &'a str) -> LlmFuture<'a> {
+            self.0.complete(prompt)
+        }
+    }
+
+    fn failing(error: LlmError) -> Arc<MockProvider> {
+        Arc::new(MockProvider::with_results(vec![Err(error)]))
+    }
+
+    fn api(status: u16) -> LlmError {
+        LlmError::Api {
+            status,
+            body: String::new(),
+        }
+    }
+
+    fn chain(providers: &[&Arc<MockProvider>]) -> FallbackProvider {
+        FallbackProvider::new(
+            providers
+                .iter()
+                .map(|p| Box::new(Shared(Arc::clone(p))) as Box<dyn LlmProvider>)
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_failing_primary_falls_through_to_secondary() {
+        for error in [
+            api(503),
+            api(401),
+            LlmError::Request("connection refused".to_string()),
+        ] {
+            let primary = failing(error.clone());
+            let secondary = Arc::new(MockProvider::new(vec!["answer".to_string()]));
+            let fallback = chain(&[&primary, &secondary]);
+
+            let (text, served) = fallback.complete_with_source("prompt").await.unwrap();
+            assert_eq!(text, "answer", "{:?}", error);
+            assert_eq!(
+                served,
+                ServedBy {
+                    index: 1,
+                    name: "mock"
+                }
+            );
+            assert_eq!(primary.prompts(), vec!["prompt"]);
+            assert_eq!(secondary.prompts(), vec!["prompt"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_budgeted_chain_reports_the_answering_provider() {
+        use crate::llm::{BudgetedProvider, LlmUsageStats};
+
+        let primary = failing(api(503));
+        let secondary = Arc::new(MockProvider::new(vec!["answer".to_string()]));
+        let usage = Arc::new(LlmUsageStats::new(Default::default(), chrono::Utc::now()));
+        let budgeted = BudgetedProvider::new(Box::new(chain(&[&primary, &secondary])), usage);
+
+        let (text, served) = budgeted.complete_with_source("prompt").await.unwrap();
+        assert_eq!(text, "answer");
+        assert_eq!(
+            served,
+            ServedBy {
+                index: 1,
+                name: "mock"
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bad_request_does_not_fall_back() {
+        let primary = failing(api(400));
+        let secondary = Arc::new(MockProvider::new(vec!["answer".to_string()]));
+        let fallback = chain(&[&primary, &secondary]);
+
+        assert_eq!(fallback.complete("prompt").await.unwrap_err(), api(400));
+        assert!(secondary.prompts().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_all_failing_returns_the_last_error() {
+        let primary = failing(api(500));
+        let secondary = failing(api(429));
+        let fallback = chain(&[&primary, &secondary]);
+
+        assert_eq!(fallback.complete("prompt").await.unwrap_err(), api(429));
+        assert!(FallbackProvider::new(Vec::new())
+            .complete("prompt")
+            .await
+            .is_err());
+    }
+}
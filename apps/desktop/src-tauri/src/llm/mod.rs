@@ -0,0 +1,1106 @@
+/*!
+ * LLM Integration for Field Analysis
+ *
+ * Uses an LLM provider (Claude by default) to analyze ambiguous form fields
+ * and suggest vault matches.
+ */
+
+mod batch;
+mod budget;
+mod cache;
+mod fallback;
+mod provider;
+mod redaction;
+mod session;
+
+pub use batch::{analyze_batch, BatchFieldRequest, BatchFieldResultJson};
+pub use budget::{BudgetedProvider, LlmBudgetJson, LlmBudgetStatusJson, LlmUsageStats};
+pub use cache::{AnalysisCache, AnalysisCacheStatsJson, CacheLookup, CacheSource};
+pub use provider::*;
+pub use redaction::PromptRedaction;
+pub use session::{
+    AnalysisSession, AnalysisSessions, CancellationToken, SessionKey, ANALYSIS_SUPERSEDED_EVENT,
+};
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::matching::{LabelContext, LabelSource};
+use crate::{FieldNodeJson, FormSnapshotJson};
+
+/// Request for LLM field analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyzeFieldRequest {
+    pub label: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: String,
+    pub placeholder: Option<String>,
+    pub semantic: Option<String>,
+    pub available_keys: Vec<String>,
+    /// Fallback label context, used when the field has no proper label
+    #[serde(default)]
+    pub aria_label: Option<String>,
+    #[serde(default)]
+    pub described_by: Option<String>,
+    #[serde(default)]
+    pub section_heading: Option<String>,
+    #[serde(default)]
+    pub nearby_text: Option<String>,
+}
+
+impl AnalyzeFieldRequest {
+    /// Request for a captured field
+    pub fn from_field(field: &FieldNodeJson, available_keys: Vec<String>) -> Self {
+        Self {
+            label: field.label.clone(),
+            name: field.name.clone(),
+            field_type: field.field_type.clone(),
+            placeholder: field.placeholder.clone(),
+            semantic: Some(field.semantic.clone()),
+            available_keys,
+            aria_label: field.aria_label.clone(),
+            described_by: field.described_by.clone(),
+            section_heading: field.section_heading.clone(),
+            nearby_text: field.nearby_text.clone(),
+        }
+    }
+
+    /// Copy with page-derived text redacted (vault keys are kept)
+    fn redacted(&self, redaction: &PromptRedaction) -> Self {
+        Self {
+            label: redaction.redact(&self.label),
+            name: redaction.redact(&self.name),
+            field_type: self.field_type.clone(),
+            placeholder: redaction.redact_opt(&self.placeholder),
+            semantic: self.semantic.clone(),
+            available_keys: self.available_keys.clone(),
+            aria_label: redaction.redact_opt(&self.aria_label),
+            described_by: redaction.redact_opt(&self.described_by),
+            section_heading: redaction.redact_opt(&self.section_heading),
+            nearby_text: redaction.redact_opt(&self.nearby_text),
+        }
+    }
+
+    /// Ranked label context for this field
+    fn label_context(&self) -> LabelContext<'_> {
+        LabelContext {
+            label: Some(self.label.as_str()),
+            aria_label: self.aria_label.as_deref(),
+            placeholder: self.placeholder.as_deref(),
+            described_by: self.described_by.as_deref(),
+            nearby_text: self.nearby_text.as_deref(),
+            name: Some(self.name.as_str()),
+        }
+    }
+}
+
+/// Response from LLM field analysis
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzeFieldResponse {
+    pub vault_key: Option<String>,
+    pub confidence: f64,
+    pub reasoning: String,
+    /// Provider that wrote the answer; None for saved-template answers
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub provider: Option<String>,
+}
+
+/// Analyze a field with the given provider
+///
+/// The prompt is redacted with `redaction` before it is sent.
+pub async fn analyze_field(
+    provider: &dyn LlmProvider,
+    request: AnalyzeFieldRequest,
+    redaction: &PromptRedaction,
+) -> Result<AnalyzeFieldResponse, LlmError> {
+    println!(
+        "[LLM] Analyzing field with {}: label='{}', name='{}', type='{}'",
+        provider.name(),
+        request.label,
+        request.name,
+        request.field_type
+    );
+    println!("[LLM] Available vault keys: {:?}", request.available_keys);
+
+    // Build the prompt
+    let prompt = build_prompt(&request.redacted(redaction));
+    println!("[LLM] Prompt length: {} chars", prompt.len());
+
+    let (text, served) = provider.complete_with_source(&prompt).await.map_err(|e| {
+        eprintln!("[LLM] {}", e);
+        e
+    })?;
+    println!("[LLM] Provider response from {}: {}", served.name, text);
+
+    let mut result = parse_llm_response(&text, &request.available_keys)?;
+    result.provider = Some(served.name.to_string());
+
+    // Answers based on weak label context deserve a little less trust
+    if let Some((source, _)) = request.label_context().best() {
+        result.confidence *= source.confidence_factor();
+    }
+    println!(
+        "[LLM] Match result: vault_key={:?}, confidence={:.2}, reasoning='{}'",
+        result.vault_key, result.confidence, result.reasoning
+    );
+
+    Ok(result)
+}
+
+/// What happened to one field during snapshot analysis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldAnalysisStatus {
+    Analyzed,
+    /// Deselected before analysis; nothing about it was sent
+    SkippedByUser,
+    Failed,
+    /// The session's deadline passed before the LLM answered
+    DeadlineExceeded,
+    /// The LLM spend budget was used up; the field stays local-only
+    BudgetExceeded,
+}
+
+/// Analysis outcome for one field of a snapshot
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldAnalysisJson {
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    pub status: FieldAnalysisStatus,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub result: Option<AnalyzeFieldResponse>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error: Option<String>,
+    /// Answer served from the analysis cache
+    #[serde(default)]
+    pub cached: bool,
+    /// Where the cached answer came from
+    #[serde(
+        rename = "cacheSource",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub cache_source: Option<CacheSource>,
+    /// Cached answer reasoned from vault labels that have since changed;
+    /// the field is being re-run and the next analysis gets the new answer
+    #[serde(default)]
+    pub revalidating: bool,
+}
+
+/// Analyze the fields of `snapshot` the local tiers left unmatched
+///
+/// `include` lists the field ids the user kept selected; None means every
+/// unmatched field. Unmatched fields that were deselected are reported as
+/// skipped by user. An included field the local tiers did match is analyzed
+/// too, since the user asked for it. Results follow snapshot order.
+///
+/// Answers come from `cache` when possible. Stale-label entries are served
+/// as they are, flagged as revalidating, and re-run once the rest of the
+/// batch is done.
+pub async fn analyze_snapshot(
+    provider: &dyn LlmProvider,
+    snapshot: &FormSnapshotJson,
+    unmatched: &[String],
+    include: Option<&[String]>,
+    available_keys: &[String],
+    redaction: &PromptRedaction,
+    cache: &AnalysisCache,
+) -> Vec<FieldAnalysisJson> {
+    analyze_snapshot_in_session(
+        provider,
+        snapshot,
+        unmatched,
+        include,
+        available_keys,
+        redaction,
+        cache,
+        &AnalysisSession::detached(),
+    )
+    .await
+    .unwrap_or_default()
+}
+
+/// `analyze_snapshot` within an analysis session
+///
+/// Returns None once the session is superseded: the in-flight LLM call is
+/// dropped and nothing more is sent. Answers already received stay cached.
+/// Once the session's deadline passes, cached answers are still served but
+/// fields that need the LLM come back as deadline exceeded.
+#[allow(clippy::too_many_arguments)]
+pub async fn analyze_snapshot_in_session(
+    provider: &dyn LlmProvider,
+    snapshot: &FormSnapshotJson,
+    unmatched: &[String],
+    include: Option<&[String]>,
+    available_keys: &[String],
+    redaction: &PromptRedaction,
+    cache: &AnalysisCache,
+    session: &AnalysisSession,
+) -> Option<Vec<FieldAnalysisJson>> {
+    let mut results = Vec::new();
+    analyze_snapshot_streaming(
+        provider,
+        snapshot,
+        unmatched,
+        include,
+        available_keys,
+        redaction,
+        cache,
+        session,
+        &mut |analysis| results.push(analysis),
+    )
+    .await?;
+    Some(results)
+}
+
+/// `analyze_snapshot_in_session`, handing each field's outcome to
+/// `on_result` as soon as it is known instead of collecting them
+///
+/// Outcomes arrive in snapshot order. Returns None once the session is
+/// superseded; outcomes already handed over stand.
+#[allow(clippy::too_many_arguments)]
+pub async fn analyze_snapshot_streaming(
+    provider: &dyn LlmProvider,
+    snapshot: &FormSnapshotJson,
+    unmatched: &[String],
+    include: Option<&[String]>,
+    available_keys: &[String],
+    redaction: &PromptRedaction,
+    cache: &AnalysisCache,
+    session: &AnalysisSession,
+    on_result: &mut (dyn FnMut(FieldAnalysisJson) + Send),
+) -> Option<()> {
+    let unmatched: HashSet<&str> = unmatched.iter().map(String::as_str).collect();
+    let include: Option<HashSet<&str>> =
+        include.map(|ids| ids.iter().map(String::as_str).collect());
+    let selected = |id: &str| match &include {
+        Some(include) => include.contains(id),
+        None => unmatched.contains(id),
+    };
+    let outcome = |field_id: &str, status| FieldAnalysisJson {
+        field_id: field_id.to_string(),
+        status,
+        result: None,
+        error: None,
+        cached: false,
+        cache_source: None,
+        revalidating: false,
+    };
+    let deadline_exceeded = |field_id: &str| FieldAnalysisJson {
+        error: Some("Deadline exceeded before the LLM answered".to_string()),
+        ..outcome(field_id, FieldAnalysisStatus::DeadlineExceeded)
+    };
+    let token = session.token();
+    let mut remaining = snapshot.fields.iter().filter(|f| selected(&f.id)).count();
+
+    let mut stale = Vec::new();
+    for field in &snapshot.fields {
+        if !selected(&field.id) {
+            if unmatched.contains(field.id.as_str()) {
+                on_result(outcome(&field.id, FieldAnalysisStatus::SkippedByUser));
+            }
+            continue;
+        }
+        if token.is_cancelled() {
+            session.record(|work| work.unstarted += remaining);
+            return None;
+        }
+        remaining -= 1;
+
+        if let Some(response) = cache.lookup_template(
+            &snapshot.domain,
+            &snapshot.fingerprint.hash,
+            &field.id,
+            available_keys,
+        ) {
+            session.record(|work| work.cache_hits += 1);
+            on_result(FieldAnalysisJson {
+                result: Some(response),
+                cached: true,
+                cache_source: Some(CacheSource::Template),
+                ..outcome(&field.id, FieldAnalysisStatus::Analyzed)
+            });
+            continue;
+        }
+
+        let request = AnalyzeFieldRequest::from_field(field, available_keys.to_vec());
+        let analysis = match cache.lookup(&request) {
+            CacheLookup::Fresh(response) => {
+                session.record(|work| work.cache_hits += 1);
+                FieldAnalysisJson {
+                    result: Some(response),
+                    cached: true,
+                    cache_source: Some(CacheSource::Llm),
+                    ..outcome(&field.id, FieldAnalysisStatus::Analyzed)
+                }
+            }
+            CacheLookup::StaleLabel(response) => {
+                session.record(|work| work.cache_hits += 1);
+                stale.push(request);
+                FieldAnalysisJson {
+                    result: Some(response),
+                    cached: true,
+                    cache_source: Some(CacheSource::Llm),
+                    revalidating: true,
+                    ..outcome(&field.id, FieldAnalysisStatus::Analyzed)
+                }
+            }
+            CacheLookup::Miss if session.past_deadline() => deadline_exceeded(&field.id),
+            CacheLookup::Miss => {
+                let call =
+                    token.run_until_cancelled(analyze_field(provider, request.clone(), redaction));
+                let analyzed = match session.deadline() {
+                    Some(deadline) => match tokio::time::timeout_at(deadline, call).await {
+                        Ok(analyzed) => analyzed,
+                        Err(_) => {
+                            on_result(deadline_exceeded(&field.id));
+                            continue;
+                        }
+                    },
+                    None => call.await,
+                };
+                match analyzed {
+                    None => {
+                        session.record(|work| {
+                            work.cancelled_calls += 1;
+                            work.unstarted += remaining;
+                        });
+                        return None;
+                    }
+                    Some(Ok(response)) => {
+                        cache.store(&request, &response);
+                        session.record(|work| work.llm_answers += 1);
+                        FieldAnalysisJson {
+                            result: Some(response),
+                            ..outcome(&field.id, FieldAnalysisStatus::Analyzed)
+                        }
+                    }
+                    Some(Err(e @ LlmError::BudgetExceeded(_))) => FieldAnalysisJson {
+                        error: Some(e.to_string()),
+                        ..outcome(&field.id, FieldAnalysisStatus::BudgetExceeded)
+                    },
+                    Some(Err(e)) => FieldAnalysisJson {
+                        error: Some(e.to_string()),
+                        ..outcome(&field.id, FieldAnalysisStatus::Failed)
+                    },
+                }
+            }
+        };
+        on_result(analysis);
+    }
+
+    // Refresh stale answers for next time; a failure leaves them stale
+    for request in stale {
+        if session.past_deadline() {
+            break;
+        }
+        let call = token.run_until_cancelled(analyze_field(provider, request.clone(), redaction));
+        let revalidated = match session.deadline() {
+            Some(deadline) => match tokio::time::timeout_at(deadline, call).await {
+                Ok(revalidated) => revalidated,
+                Err(_) => break,
+            },
+            None => call.await,
+        };
+        match revalidated {
+            None => {
+                session.record(|work| work.cancelled_calls += 1);
+                return None;
+            }
+            Some(Ok(response)) => {
+                if cache.revalidated(&request, &response) {
+                    println!(
+                        "[LLM] Revalidation changed the answer for '{}'",
+                        request.label
+                    );
+                }
+            }
+            Some(Err(_)) => {}
+        }
+    }
+    if token.is_cancelled() {
+        return None;
+    }
+    Some(())
+}
+
+/// The prompts that analyzing each field of `snapshot` would send, exactly
+/// as redacted; makes no network calls
+pub fn preview_llm_input(
+    snapshot: &FormSnapshotJson,
+    available_keys: &[String],
+    redaction: &PromptRedaction,
+) -> Vec<String> {
+    snapshot
+        .fields
+        .iter()
+        .map(|field| {
+            let request = AnalyzeFieldRequest::from_field(field, available_keys.to_vec());
+            build_prompt(&request.redacted(redaction))
+        })
+        .collect()
+}
+
+/// Build the prompt for field analysis
+fn build_prompt(request: &AnalyzeFieldRequest) -> String {
+    let available_keys = request.available_keys.join(", ");
+    let (label_source, label) = request
+        .label_context()
+        .best()
+        .unwrap_or((LabelSource::Label, ""));
+
+    format!(
+        r#"You are analyzing a form field to determine which user data it expects.
+
+Field information:
+- Label: "{}" (from {})
+- Name attribute: "{}"
+- Input type: "{}"
+- Placeholder: {}
+- Semantic hint: {}
+- Description: {}
+- Nearby text: {}
+- Section heading: {}
+
+Available vault data keys:
+{}
+
+Task: Determine which vault key (if any) should be used to fill this field.
+
+Respond ONLY with valid JSON in this exact format:
+{{"vaultKey": "keyName", "confidence": 0.85, "reasoning": "explanation"}}
+
+Or if no match:
+{{"vaultKey": null, "confidence": 0.0, "reasoning": "explanation"}}
+
+Confidence scale:
+- 0.80-0.90: Strong semantic match
+- 0.60-0.80: Likely match but some ambiguity
+- 0.40-0.60: Possible match, low confidence
+- 0.0-0.40: No clear match
+
+If no vault key matches, set vaultKey to null. Be conservative with confidence scores."#,
+        label,
+        label_source.description(),
+        request.name,
+        request.field_type,
+        request.placeholder.as_deref().unwrap_or("(none)"),
+        request.semantic.as_deref().unwrap_or("unknown"),
+        request.described_by.as_deref().unwrap_or("(none)"),
+        request.nearby_text.as_deref().unwrap_or("(none)"),
+        request.section_heading.as_deref().unwrap_or("(none)"),
+        available_keys
+    )
+}
+
+/// Parse LLM response into structured data
+fn parse_llm_response(
+    text: &str,
+    available_keys: &[String],
+) -> Result<AnalyzeFieldResponse, LlmError> {
+    // Try to parse as JSON
+    let parsed: serde_json::Value = serde_json::from_str(text.trim()).map_err(|e| {
+        LlmError::BadResponse(format!("Failed to parse LLM response as JSON: {}", e))
+    })?;
+    Ok(response_from_value(&parsed, available_keys))
+}
+
+/// Read one answer object; unknown vault keys become no match
+fn response_from_value(
+    parsed: &serde_json::Value,
+    available_keys: &[String],
+) -> AnalyzeFieldResponse {
+    let vault_key = parsed
+        .get("vaultKey")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    // Validate vault_key exists in available keys
+    let vault_key = if let Some(key) = vault_key {
+        if available_keys.contains(&key) {
+            Some(key)
+        } else {
+            eprintln!(
+                "LLM suggested key '{}' not in available keys: {:?}",
+                key, available_keys
+            );
+            None
+        }
+    } else {
+        None
+    };
+
+    let confidence = parsed
+        .get("confidence")
+        .and_then(|v| v.as_f64())
+        .unwrap_or(0.0);
+
+    let reasoning = parsed
+        .get("reasoning")
+        .and_then(|v| v.as_str())
+        .unwrap_or("No reasoning provided")
+        .to_string();
+
+    AnalyzeFieldResponse {
+        vault_key,
+        confidence,
+        reasoning,
+        provider: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_prompt() {
+        let request = AnalyzeFieldRequest {
+            label: "Company Name".to_string(),
+            name: "company".to_string(),
+            field_type: "text".to_string(),
+            placeholder: Some("e.g., Acme Corp".to_string()),
+            semantic: Some("unknown".to_string()),
+            available_keys: vec!["firstName".to_string(), "company".to_string()],
+            aria_label: None,
+            described_by: None,
+            section_heading: None,
+            nearby_text: None,
+        };
+
+        let prompt = build_prompt(&request);
+        assert!(prompt.contains("Company Name"));
+        assert!(prompt.contains("firstName, company"));
+    }
+
+    #[test]
+    fn test_build_prompt_uses_ranked_fallback() {
+        let request = AnalyzeFieldRequest {
+            label: "".to_string(),
+            name: "fld_17".to_string(),
+            field_type: "text".to_string(),
+            placeholder: None,
+            semantic: None,
+            available_keys: vec!["company".to_string()],
+            aria_label: None,
+            described_by: Some("Your employer's legal name".to_string()),
+            section_heading: Some("Employment".to_string()),
+            nearby_text: Some("Company".to_string()),
+        };
+
+        let prompt = build_prompt(&request);
+        assert!(
+            prompt.contains(r#"Label: "Your employer's legal name" (from aria-describedby text)"#)
+        );
+        assert!(prompt.contains("Section heading: Employment"));
+    }
+
+    #[test]
+    fn test_parse_llm_response_with_match() {
+        let json = r#"{"vaultKey": "email", "confidence": 0.85, "reasoning": "Field label indicates email address"}"#;
+        let available_keys = vec!["email".to_string(), "phone".to_string()];
+
+        let result = parse_llm_response(json, &available_keys).unwrap();
+        assert_eq!(result.vault_key, Some("email".to_string()));
+        assert_eq!(result.confidence, 0.85);
+    }
+
+    #[test]
+    fn test_parse_llm_response_no_match() {
+        let json = r#"{"vaultKey": null, "confidence": 0.0, "reasoning": "No clear match"}"#;
+        let available_keys = vec!["email".to_string()];
+
+        let result = parse_llm_response(json, &available_keys).unwrap();
+        assert_eq!(result.vault_key, None);
+        assert_eq!(result.confidence, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_field_with_mock_provider() {
+        let provider = MockProvider::new(vec![
+            r#"{"vaultKey": "company", "confidence": 0.8, "reasoning": "Employer name"}"#
+                .to_string(),
+        ]);
+        let request = AnalyzeFieldRequest {
+            label: "Employer".to_string(),
+            name: "employer".to_string(),
+            field_type: "text".to_string(),
+            placeholder: None,
+            semantic: None,
+            available_keys: vec!["company".to_string()],
+            aria_label: None,
+            described_by: None,
+            section_heading: None,
+            nearby_text: None,
+        };
+
+        let result = analyze_field(&provider, request, &PromptRedaction::default())
+            .await
+            .unwrap();
+        assert_eq!(result.vault_key, Some("company".to_string()));
+        assert_eq!(result.provider.as_deref(), Some("mock"));
+        assert_eq!(provider.prompts().len(), 1);
+        assert!(provider.prompts()[0].contains("Employer"));
+    }
+
+    fn snapshot_with(fields: Vec<FieldNodeJson>) -> FormSnapshotJson {
+        FormSnapshotJson {
+            url: "https://example.com/signup".to_string(),
+            domain: "example.com".to_string(),
+            title: "Sign up".to_string(),
+            captured_at: "2026-01-01T00:00:00Z".to_string(),
+            fingerprint: crate::FormFingerprintJson {
+                field_count: fields.len() as u32,
+                field_types: vec![],
+                required_count: 0,
+                hash: "fp".to_string(),
+            },
+            fields,
+            session: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_preview_redacts_sensitive_tokens() {
+        let snapshot = snapshot_with(vec![
+            FieldNodeJson {
+                id: "email".to_string(),
+                name: "email".to_string(),
+                label: "Email".to_string(),
+                field_type: "email".to_string(),
+                semantic: "email".to_string(),
+                nearby_text: Some("Signed in as ada@example.com".to_string()),
+                ..Default::default()
+            },
+            FieldNodeJson {
+                id: "ref".to_string(),
+                name: "ref".to_string(),
+                label: "Reference for Ada Lovelace".to_string(),
+                field_type: "text".to_string(),
+                semantic: "unknown".to_string(),
+                placeholder: Some("ADA@EXAMPLE.COM".to_string()),
+                ..Default::default()
+            },
+        ]);
+        let keys = vec!["email".to_string(), "fullName".to_string()];
+        let redaction = PromptRedaction::new(&["ada@example.com", "Ada Lovelace"]);
+
+        let prompts = preview_llm_input(&snapshot, &keys, &redaction);
+        assert_eq!(prompts.len(), 2);
+        for prompt in &prompts {
+            let lower = prompt.to_lowercase();
+            assert!(!lower.contains("ada@example.com"));
+            assert!(!lower.contains("ada lovelace"));
+            assert!(prompt.contains("email, fullName"));
+        }
+        assert!(prompts[0].contains("Signed in as [REDACTED]"));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_field_sends_redacted_prompt() {
+        let provider = MockProvider::new(vec![]);
+        let mut request = AnalyzeFieldRequest::from_field(
+            &FieldNodeJson {
+                label: "Phone".to_string(),
+                nearby_text: Some("We'll call 555-0100".to_string()),
+                ..Default::default()
+            },
+            vec!["phone".to_string()],
+        );
+        request.semantic = None;
+
+        analyze_field(&provider, request, &PromptRedaction::new(&["555-0100"]))
+            .await
+            .unwrap();
+        assert!(!provider.prompts()[0].contains("555-0100"));
+        assert!(provider.prompts()[0].contains("phone"));
+    }
+
+    fn labeled(id: &str, label: &str) -> FieldNodeJson {
+        FieldNodeJson {
+            id: id.to_string(),
+            name: id.to_string(),
+            label: label.to_string(),
+            field_type: "text".to_string(),
+            semantic: "unknown".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_analyze_snapshot_sends_only_selected_fields() {
+        let snapshot = snapshot_with(vec![
+            labeled("email", "Email"),
+            labeled("employer", "Employer"),
+            labeled("motto", "Personal motto"),
+            labeled("nickname", "Nickname"),
+        ]);
+        let unmatched = vec![
+            "employer".to_string(),
+            "motto".to_string(),
+            "nickname".to_string(),
+        ];
+        let include = vec!["employer".to_string(), "nickname".to_string()];
+        let provider = MockProvider::new(vec![]);
+
+        let results = analyze_snapshot(
+            &provider,
+            &snapshot,
+            &unmatched,
+            Some(&include),
+            &["company".to_string()],
+            &PromptRedaction::default(),
+            &AnalysisCache::new(),
+        )
+        .await;
+
+        let prompts = provider.prompts();
+        assert_eq!(prompts.len(), 2);
+        assert!(prompts[0].contains("Employer"));
+        assert!(prompts[1].contains("Nickname"));
+        assert!(prompts.iter().all(|p| !p.contains("Personal motto")));
+
+        let statuses: Vec<(&str, FieldAnalysisStatus)> = results
+            .iter()
+            .map(|r| (r.field_id.as_str(), r.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("employer", FieldAnalysisStatus::Analyzed),
+                ("motto", FieldAnalysisStatus::SkippedByUser),
+                ("nickname", FieldAnalysisStatus::Analyzed),
+            ]
+        );
+        assert!(results[1].result.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_snapshot_defaults_to_all_unmatched() {
+        let snapshot = snapshot_with(vec![labeled("email", "Email"), labeled("motto", "Motto")]);
+        let provider = MockProvider::new(vec![]);
+
+        let results = analyze_snapshot(
+            &provider,
+            &snapshot,
+            &["motto".to_string()],
+            None,
+            &[],
+            &PromptRedaction::default(),
+            &AnalysisCache::new(),
+        )
+        .await;
+
+        assert_eq!(provider.prompts().len(), 1);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].field_id, "motto");
+        assert_eq!(results[0].status, FieldAnalysisStatus::Analyzed);
+    }
+
+    #[tokio::test]
+    async fn test_warmed_template_resolves_without_provider() {
+        let snapshot = snapshot_with(vec![
+            labeled("employer", "Employer"),
+            labeled("motto", "Motto"),
+        ]);
+        let cache = AnalysisCache::new();
+        cache.warm_from_templates(&[crate::templates::FormTemplateJson {
+            id: crate::templates::template_id("example.com", "fp"),
+            domain: "example.com".to_string(),
+            fingerprint: "fp".to_string(),
+            mappings: vec![crate::templates::TemplateMappingJson {
+                field_id: "employer".to_string(),
+                vault_key: "company".to_string(),
+                suspect: false,
+            }],
+            healed_count: 0,
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            updated_at: "2026-01-01T00:00:00Z".to_string(),
+        }]);
+        let provider = MockProvider::new(vec![]);
+
+        let results = analyze_snapshot(
+            &provider,
+            &snapshot,
+            &["employer".to_string(), "motto".to_string()],
+            None,
+            &["company".to_string()],
+            &PromptRedaction::default(),
+            &cache,
+        )
+        .await;
+
+        // Only the field without a template reaches the provider
+        let prompts = provider.prompts();
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains("Motto"));
+
+        let employer = &results[0];
+        assert!(employer.cached);
+        assert_eq!(employer.cache_source, Some(CacheSource::Template));
+        let result = employer.result.as_ref().unwrap();
+        assert_eq!(result.vault_key.as_deref(), Some("company"));
+        assert_eq!(result.confidence, crate::matching::TEMPLATE_CONFIDENCE);
+        assert!(!results[1].cached);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_snapshot_reports_provider_failures_per_field() {
+        let snapshot = snapshot_with(vec![labeled("a", "A"), labeled("b", "B")]);
+        let provider = MockProvider::with_results(vec![
+            Err(LlmError::Request("offline".to_string())),
+            Ok(MockProvider::NO_MATCH.to_string()),
+        ]);
+
+        let results = analyze_snapshot(
+            &provider,
+            &snapshot,
+            &["a".to_string(), "b".to_string()],
+            None,
+            &[],
+            &PromptRedaction::default(),
+            &AnalysisCache::new(),
+        )
+        .await;
+
+        assert_eq!(results[0].status, FieldAnalysisStatus::Failed);
+        assert!(results[0].error.as_deref().unwrap().contains("offline"));
+        assert_eq!(results[1].status, FieldAnalysisStatus::Analyzed);
+    }
+
+    #[tokio::test]
+    async fn test_budget_blocks_analysis_until_window_rolls_over() {
+        use std::sync::Arc;
+
+        let snapshot = snapshot_with(vec![labeled("a", "A"), labeled("b", "B")]);
+        let fields = ["a".to_string(), "b".to_string()];
+        let (snapshot, fields) = (&snapshot, &fields);
+        let budget = LlmBudgetJson {
+            max_tokens: Some(1),
+            ..Default::default()
+        };
+        let analyze = |usage: &Arc<LlmUsageStats>| {
+            let provider =
+                BudgetedProvider::new(Box::new(MockProvider::new(vec![])), Arc::clone(usage));
+            async move {
+                analyze_snapshot(
+                    &provider,
+                    snapshot,
+                    fields,
+                    None,
+                    &[],
+                    &PromptRedaction::default(),
+                    &AnalysisCache::new(),
+                )
+                .await
+                .iter()
+                .map(|r| r.status)
+                .collect::<Vec<_>>()
+            }
+        };
+
+        // Under budget the first call goes out; it spends the cap
+        let usage = Arc::new(LlmUsageStats::new(budget, chrono::Utc::now()));
+        assert_eq!(
+            analyze(&usage).await,
+            vec![
+                FieldAnalysisStatus::Analyzed,
+                FieldAnalysisStatus::BudgetExceeded
+            ]
+        );
+        assert_eq!(
+            analyze(&usage).await,
+            vec![FieldAnalysisStatus::BudgetExceeded; 2]
+        );
+        assert_eq!(usage.status(chrono::Utc::now()).calls, 1);
+
+        // Spend from a window that has ended doesn't count
+        let earlier = chrono::Utc::now() - chrono::Duration::hours(25);
+        let usage = Arc::new(LlmUsageStats::new(budget, earlier));
+        usage.record(1_000, earlier);
+        assert_eq!(analyze(&usage).await[0], FieldAnalysisStatus::Analyzed);
+    }
+
+    #[tokio::test]
+    async fn test_stale_label_served_then_refreshed() {
+        let snapshot = snapshot_with(vec![labeled("employer", "Employer")]);
+        let unmatched = vec!["employer".to_string()];
+        let keys = vec!["company".to_string(), "school".to_string()];
+        let provider = MockProvider::new(vec![
+            r#"{"vaultKey": "company", "confidence": 0.8, "reasoning": "Company"}"#.to_string(),
+            r#"{"vaultKey": "school", "confidence": 0.8, "reasoning": "Workplace"}"#.to_string(),
+        ]);
+        let cache = AnalysisCache::new();
+        let redaction = PromptRedaction::default();
+        let vault_labels = |school: &str| {
+            vec![
+                ("company".to_string(), "Company".to_string()),
+                ("school".to_string(), school.to_string()),
+            ]
+        };
+        let analyze = || {
+            analyze_snapshot(
+                &provider, &snapshot, &unmatched, None, &keys, &redaction, &cache,
+            )
+        };
+
+        cache.observe_labels(vault_labels("School"));
+        let first = analyze().await;
+        assert!(!first[0].cached);
+
+        // Unchanged labels: served from cache, provider untouched
+        let second = analyze().await;
+        assert!(second[0].cached && !second[0].revalidating);
+        assert_eq!(provider.prompts().len(), 1);
+
+        // Renamed label: old answer served this time, re-run in the same batch
+        assert_eq!(cache.observe_labels(vault_labels("Workplace")), 1);
+        let third = analyze().await;
+        assert!(third[0].cached && third[0].revalidating);
+        assert_eq!(
+            third[0].result.as_ref().unwrap().vault_key.as_deref(),
+            Some("company")
+        );
+        assert_eq!(provider.prompts().len(), 2);
+
+        // The refreshed answer is served next time, and the change is counted
+        let fourth = analyze().await;
+        assert!(fourth[0].cached && !fourth[0].revalidating);
+        assert_eq!(
+            fourth[0].result.as_ref().unwrap().vault_key.as_deref(),
+            Some("school")
+        );
+        assert_eq!(provider.prompts().len(), 2);
+        let stats = cache.stats();
+        assert_eq!(stats.revalidations, 1);
+        assert_eq!(stats.revalidations_changed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_analysis_not_cached() {
+        let snapshot = snapshot_with(vec![labeled("a", "A")]);
+        let provider = MockProvider::with_results(vec![
+            Err(LlmError::Request("offline".to_string())),
+            Ok(MockProvider::NO_MATCH.to_string()),
+        ]);
+        let cache = AnalysisCache::new();
+        let unmatched = vec!["a".to_string()];
+
+        for _ in 0..2 {
+            analyze_snapshot(
+                &provider,
+                &snapshot,
+                &unmatched,
+                None,
+                &[],
+                &PromptRedaction::default(),
+                &cache,
+            )
+            .await;
+        }
+        assert_eq!(provider.prompts().len(), 2);
+        assert_eq!(cache.stats().entries, 1);
+    }
+
+    #[test]
+    fn test_parse_llm_response_invalid_key() {
+        let json = r#"{"vaultKey": "nonexistent", "confidence": 0.85, "reasoning": "Test"}"#;
+        let available_keys = vec!["email".to_string()];
+
+        let result = parse_llm_response(json, &available_keys).unwrap();
+        // Should reject invalid key
+        assert_eq!(result.vault_key, None);
+    }
+
+    /// Answers like `MockProvider`, after a delay
+    struct SlowProvider {
+        delay: std::time::Duration,
+        calls: std::sync::atomic::AtomicUsize,
+        inner: MockProvider,
+    }
+
+    impl LlmProvider for SlowProvider {
+        fn name(&self) -> &'static str {
+            "slow"
+        }
+
+        fn complete<'a>(&'a self, prompt: &'a str) -> LlmFuture<'a> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Box::pin(async move {
+                tokio::time::sleep(self.delay).await;
+                self.inner.complete(prompt).await
+            })
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_deadline_cuts_off_slow_llm_but_not_local_matches() {
+        use crate::matching::generate_fill_plan;
+        use asterisk_vault::{Provenance, ProvenanceSource, VaultCategory, VaultItem};
+        use std::time::Duration;
+
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(150);
+        let snapshot = snapshot_with(vec![
+            FieldNodeJson {
+                field_type: "email".to_string(),
+                ..labeled("email", "Email address")
+            },
+            labeled("employer", "Employer"),
+            labeled("motto", "Personal motto"),
+            labeled("nickname", "Nickname"),
+        ]);
+        let items = vec![VaultItem::new(
+            "email",
+            "ada@example.com",
+            "Email",
+            VaultCategory::Contact,
+            Provenance {
+                source: ProvenanceSource::UserEntered,
+                timestamp: chrono::Utc::now(),
+                confidence: 1.0,
+                origin: None,
+            },
+        )];
+        let plan = generate_fill_plan(&snapshot, &items, None);
+        let provider = SlowProvider {
+            delay: Duration::from_millis(100),
+            calls: Default::default(),
+            inner: MockProvider::new(vec![]),
+        };
+
+        let results = analyze_snapshot_in_session(
+            &provider,
+            &snapshot,
+            &plan.unmatched_fields,
+            None,
+            &["company".to_string()],
+            &PromptRedaction::default(),
+            &AnalysisCache::new(),
+            &AnalysisSession::detached().with_deadline(Some(deadline)),
+        )
+        .await
+        .unwrap();
+
+        // The local match resolves regardless of the LLM
+        assert_eq!(plan.recommendations.len(), 1);
+        assert_eq!(plan.recommendations[0].field_id, "email");
+
+        // "employer" answers in time, "motto" is cut off in flight and
+        // "nickname" is never sent
+        let statuses: Vec<(&str, FieldAnalysisStatus)> = results
+            .iter()
+            .map(|r| (r.field_id.as_str(), r.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("employer", FieldAnalysisStatus::Analyzed),
+                ("motto", FieldAnalysisStatus::DeadlineExceeded),
+                ("nickname", FieldAnalysisStatus::DeadlineExceeded),
+            ]
+        );
+        assert_eq!(provider.calls.into_inner(), 2);
+        assert!(tokio::time::Instant::now() <= deadline + Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,1061 @@
+/*!
+ * LLM Providers
+ *
+ * Puts the completion API behind `LlmProvider` so field analysis doesn't care
+ * which vendor (or mock) answers. `provider_from_env` lets CI and dev builds
+ * pick the provider without code changes.
+ */
+
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::Duration;
+use thiserror::Error;
+
+use super::fallback::{FallbackProvider, FALLBACK_ENV};
+
+/// Environment variable selecting the provider (`anthropic`, `openai`, `mock`)
+pub const PROVIDER_ENV: &str = "ASTERISK_LLM_PROVIDER";
+
+/// Environment variable holding the Anthropic API key
+pub const ANTHROPIC_KEY_ENV: &str = "ANTHROPIC_API_KEY";
+
+/// Environment variable holding the OpenAI API key
+pub const OPENAI_KEY_ENV: &str = "OPENAI_API_KEY";
+
+/// Environment variable overriding the response body cap, in bytes
+pub const MAX_BODY_ENV: &str = "ASTERISK_LLM_MAX_BODY_BYTES";
+
+/// Environment variable overriding the per-request timeout, in seconds
+pub const TIMEOUT_ENV: &str = "ASTERISK_LLM_TIMEOUT_SECS";
+
+/// Environment variable overriding how often a failed request is retried
+pub const MAX_RETRIES_ENV: &str = "ASTERISK_LLM_MAX_RETRIES";
+
+/// Environment variable overriding the first retry's delay, in milliseconds
+pub const RETRY_BASE_DELAY_ENV: &str = "ASTERISK_LLM_RETRY_BASE_MS";
+
+/// Largest response body we'll read from a provider (a few hundred KB is far
+/// more than a 256-token reply needs)
+pub const DEFAULT_MAX_BODY_BYTES: usize = 512 * 1024;
+
+/// Longest one request may take, body included, before it is abandoned (a
+/// server that sends headers and then stalls would otherwise hang forever)
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Longest connecting to a provider may take, within the request timeout
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// ============================================================================
+// Error Types
+// ============================================================================
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum LlmError {
+    #[error("Unknown LLM provider: {0}")]
+    UnknownProvider(String),
+
+    #[error("No API key configured: set {0}")]
+    MissingApiKey(String),
+
+    #[error("API request failed: {0}")]
+    Request(String),
+
+    #[error("API returned {status}: {body}")]
+    Api { status: u16, body: String },
+
+    #[error("Bad API response: {0}")]
+    BadResponse(String),
+
+    /// The spend budget is used up until the given time
+    #[error("LLM budget exceeded, local-only until {0}")]
+    BudgetExceeded(String),
+}
+
+// ============================================================================
+// Provider Trait
+// ============================================================================
+
+/// Boxed future returned by providers (keeps the trait object-safe)
+pub type LlmFuture<'a> = Pin<Box<dyn Future<Output = Result<String, LlmError>> + Send + 'a>>;
+
+/// Like `LlmFuture`, with the provider that answered
+pub type SourcedFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<(String, ServedBy), LlmError>> + Send + 'a>>;
+
+/// Which provider answered a prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServedBy {
+    /// Position in a fallback chain; 0 is the primary, or the only provider
+    pub index: usize,
+    pub name: &'static str,
+}
+
+/// A completion backend that answers a single-turn prompt with text
+pub trait LlmProvider: Send + Sync {
+    /// Short provider name ("anthropic", "openai", "mock")
+    fn name(&self) -> &'static str;
+
+    /// Send a prompt and return the model's text reply
+    fn complete<'a>(&'a self, prompt: &'a str) -> LlmFuture<'a>;
+
+    /// `complete`, also saying which provider answered; only chains and
+    /// wrappers around them answer as someone other than themselves
+    fn complete_with_source<'a>(&'a self, prompt: &'a str) -> SourcedFuture<'a> {
+        Box::pin(async move {
+            let text = self.complete(prompt).await?;
+            Ok((
+                text,
+                ServedBy {
+                    index: 0,
+                    name: self.name(),
+                },
+            ))
+        })
+    }
+}
+
+// ============================================================================
+// Bounded Body Reads
+// ============================================================================
+
+/// Read a response body, refusing to buffer more than `max_bytes`
+///
+/// `response.json()`/`text()` would buffer whatever the server (or a proxy)
+/// sends, so bodies are read chunk by chunk and abandoned past the cap.
+async fn read_body_limited(
+    mut response: reqwest::Response,
+    max_bytes: usize,
+) -> Result<Vec<u8>, LlmError> {
+    let too_large = || LlmError::BadResponse(format!("Response body exceeds {} bytes", max_bytes));
+    if response
+        .content_length()
+        .is_some_and(|len| len > max_bytes as u64)
+    {
+        return Err(too_large());
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| LlmError::Request(e.to_string()))?
+    {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// HTTP client abandoning requests after `timeout`
+fn http_client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT.min(timeout))
+        .timeout(timeout)
+        .build()
+        .unwrap_or_default()
+}
+
+/// Error for a non-success status; the body is only kept if it fits the cap
+async fn api_error(response: reqwest::Response, max_bytes: usize) -> LlmError {
+    let status = response.status().as_u16();
+    let body = read_body_limited(response, max_bytes)
+        .await
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .unwrap_or_else(|e| e.to_string());
+    LlmError::Api { status, body }
+}
+
+// ============================================================================
+// Retries
+// ============================================================================
+
+/// Longest wait between two attempts, whatever the backoff says
+const MAX_RETRY_DELAY_MS: u64 = 30_000;
+
+/// How a provider retries transient API failures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LlmConfig {
+    /// Retries after the first attempt; 0 fails on the first error
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for each one after it
+    pub base_delay_ms: u64,
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay_ms: 500,
+        }
+    }
+}
+
+impl LlmConfig {
+    /// The default policy with `ASTERISK_LLM_MAX_RETRIES` and
+    /// `ASTERISK_LLM_RETRY_BASE_MS` applied
+    pub fn from_env() -> Result<Self, LlmError> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, LlmError> {
+        let number = |name: &str| {
+            var(name)
+                .map(|value| {
+                    value.trim().parse::<u64>().map_err(|_| {
+                        LlmError::Request(format!("{} must be a number: {}", name, value))
+                    })
+                })
+                .transpose()
+        };
+        let defaults = Self::default();
+        Ok(Self {
+            max_retries: match number(MAX_RETRIES_ENV)? {
+                Some(n) => n.min(u32::MAX as u64) as u32,
+                None => defaults.max_retries,
+            },
+            base_delay_ms: number(RETRY_BASE_DELAY_ENV)?.unwrap_or(defaults.base_delay_ms),
+        })
+    }
+
+    /// Wait before retry `retry` (0 for the first): the exponential delay
+    /// plus up to half of it again at random, so clients that failed
+    /// together don't retry together
+    fn backoff(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay_ms
+            .saturating_mul(1u64 << retry.min(20))
+            .min(MAX_RETRY_DELAY_MS);
+        let jitter = match delay / 2 {
+            0 => 0,
+            half => OsRng.next_u64() % (half + 1),
+        };
+        Duration::from_millis(delay + jitter)
+    }
+}
+
+impl LlmError {
+    /// Whether the same request may succeed if sent again shortly: the
+    /// connection failed, or the API was rate limited or overloaded
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LlmError::Request(_) => true,
+            LlmError::Api { status, .. } => matches!(status, 429 | 500 | 502 | 503 | 529),
+            _ => false,
+        }
+    }
+}
+
+/// Run `send` until it succeeds, fails for good, or `config` runs out of
+/// retries; returns the last outcome
+async fn with_retries<F, Fut>(config: LlmConfig, api: &str, mut send: F) -> Result<String, LlmError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<String, LlmError>>,
+{
+    let mut retry = 0;
+    loop {
+        match send().await {
+            Err(e) if e.is_retryable() && retry < config.max_retries => {
+                let delay = config.backoff(retry);
+                retry += 1;
+                eprintln!(
+                    "[LLM] {} request failed ({}); retry {}/{} in {} ms",
+                    api,
+                    e,
+                    retry,
+                    config.max_retries,
+                    delay.as_millis()
+                );
+                tokio::time::sleep(delay).await;
+            }
+            outcome => return outcome,
+        }
+    }
+}
+
+// ============================================================================
+// Anthropic
+// ============================================================================
+
+/// Claude API message structure
+#[derive(Debug, Serialize, Deserialize)]
+struct ClaudeMessage {
+    role: String,
+    content: String,
+}
+
+/// Claude API request body
+#[derive(Debug, Serialize, Deserialize)]
+struct ClaudeRequest {
+    model: String,
+    max_tokens: u32,
+    messages: Vec<ClaudeMessage>,
+}
+
+/// Claude API response
+#[derive(Debug, Serialize, Deserialize)]
+struct ClaudeResponse {
+    content: Vec<ClaudeContent>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ClaudeContent {
+    #[serde(rename = "type")]
+    content_type: String,
+    text: String,
+}
+
+/// Anthropic Messages API provider
+pub struct AnthropicProvider {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+    max_body_bytes: usize,
+    retry: LlmConfig,
+}
+
+impl AnthropicProvider {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            client: http_client(DEFAULT_TIMEOUT),
+            api_key: api_key.into(),
+            model: "claude-sonnet-4-20250514".to_string(),
+            base_url: "https://api.anthropic.com".to_string(),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            retry: LlmConfig::default(),
+        }
+    }
+
+    /// Send requests to a different endpoint (a local mock server in tests)
+    #[cfg(test)]
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Cap on the response body size
+    pub fn with_max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Time limit for each request, body included
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = http_client(timeout);
+        self
+    }
+
+    /// Retry policy for rate limits and outages
+    pub fn with_retry_config(mut self, retry: LlmConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// One request to the Messages API
+    async fn send(&self, prompt: &str) -> Result<String, LlmError> {
+        let claude_request = ClaudeRequest {
+            model: self.model.clone(),
+            max_tokens: 256,
+            messages: vec![ClaudeMessage {
+                role: "user".to_string(),
+                content: prompt.to_string(),
+            }],
+        };
+
+        println!("[LLM] Sending request to Claude API...");
+        let response = self
+            .client
+            .post(format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("content-type", "application/json")
+            .json(&claude_request)
+            .send()
+            .await
+            .map_err(|e| LlmError::Request(e.to_string()))?;
+
+        let status = response.status();
+        println!("[LLM] API response status: {}", status);
+
+        if !status.is_success() {
+            return Err(api_error(response, self.max_body_bytes).await);
+        }
+
+        let body = read_body_limited(response, self.max_body_bytes).await?;
+        let claude_response: ClaudeResponse =
+            serde_json::from_slice(&body).map_err(|e| LlmError::BadResponse(e.to_string()))?;
+
+        Ok(claude_response
+            .content
+            .into_iter()
+            .next()
+            .map(|c| c.text)
+            .unwrap_or_default())
+    }
+}
+
+impl LlmProvider for AnthropicProvider {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn complete<'a>(&'a self, prompt: &'a str) -> LlmFuture<'a> {
+        Box::pin(with_retries(self.retry, "Claude API", move || {
+            self.send(prompt)
+        }))
+    }
+}
+
+// ============================================================================
+// OpenAI
+// ============================================================================
+
+/// OpenAI Chat Completions API provider
+pub struct OpenAiProvider {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+    max_body_bytes: usize,
+    retry: LlmConfig,
+}
+
+impl OpenAiProvider {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            client: http_client(DEFAULT_TIMEOUT),
+            api_key: api_key.into(),
+            model: "gpt-4o-mini".to_string(),
+            base_url: "https://api.openai.com".to_string(),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            retry: LlmConfig::default(),
+        }
+    }
+
+    /// Send requests to a different endpoint (a local mock server in tests)
+    #[cfg(test)]
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Cap on the response body size
+    pub fn with_max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Time limit for each request, body included
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = http_client(timeout);
+        self
+    }
+
+    /// Retry policy for rate limits and outages
+    pub fn with_retry_config(mut self, retry: LlmConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// One request to the Chat Completions API
+    async fn send(&self, prompt: &str) -> Result<String, LlmError> {
+        let body = serde_json::json!({
+            "model": self.model,
+            "max_tokens": 256,
+            "messages": [{ "role": "user", "content": prompt }],
+        });
+
+        println!("[LLM] Sending request to OpenAI API...");
+        let response = self
+            .client
+            .post(format!("{}/v1/chat/completions", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| LlmError::Request(e.to_string()))?;
+
+        let status = response.status();
+        println!("[LLM] API response status: {}", status);
+
+        if !status.is_success() {
+            return Err(api_error(response, self.max_body_bytes).await);
+        }
+
+        let body = read_body_limited(response, self.max_body_bytes).await?;
+        let parsed: serde_json::Value =
+            serde_json::from_slice(&body).map_err(|e| LlmError::BadResponse(e.to_string()))?;
+
+        parsed
+            .pointer("/choices/0/message/content")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .ok_or_else(|| LlmError::BadResponse("Missing message content".to_string()))
+    }
+}
+
+impl LlmProvider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn complete<'a>(&'a self, prompt: &'a str) -> LlmFuture<'a> {
+        Box::pin(with_retries(self.retry, "OpenAI API", move || {
+            self.send(prompt)
+        }))
+    }
+}
+
+// ============================================================================
+// Mock
+// ============================================================================
+
+/// Provider returning canned responses, for tests and offline development
+///
+/// Responses are served in order; the last one repeats once the rest are used.
+pub struct MockProvider {
+    responses: Mutex<Vec<Result<String, LlmError>>>,
+    prompts: Mutex<Vec<String>>,
+}
+
+impl MockProvider {
+    /// Default reply: a well-formed "no match" answer
+    pub const NO_MATCH: &'static str =
+        r#"{"vaultKey": null, "confidence": 0.0, "reasoning": "Mock provider"}"#;
+
+    pub fn new(responses: Vec<String>) -> Self {
+        Self::with_results(responses.into_iter().map(Ok).collect())
+    }
+
+    /// Mock that can also fail, e.g. to exercise retry and fallback paths
+    pub fn with_results(mut responses: Vec<Result<String, LlmError>>) -> Self {
+        if responses.is_empty() {
+            responses.push(Ok(Self::NO_MATCH.to_string()));
+        }
+        responses.reverse();
+        Self {
+            responses: Mutex::new(responses),
+            prompts: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Prompts received so far, oldest first
+    #[cfg(test)]
+    pub fn prompts(&self) -> Vec<String> {
+        self.prompts.lock().map(|p| p.clone()).unwrap_or_default()
+    }
+}
+
+impl LlmProvider for MockProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn complete<'a>(&'a self, prompt: &'a str) -> LlmFuture<'a> {
+        if let Ok(mut prompts) = self.prompts.lock() {
+            prompts.push(prompt.to_string());
+        }
+        let reply = match self.responses.lock() {
+            Ok(mut responses) if responses.len() > 1 => responses.pop(),
+            Ok(responses) => responses.last().cloned(),
+            Err(_) => None,
+        };
+        Box::pin(async move {
+            reply.unwrap_or_else(|| Err(LlmError::Request("Mock provider poisoned".to_string())))
+        })
+    }
+}
+
+// ============================================================================
+// API Key Format
+// ============================================================================
+
+/// Check that `key` looks like an API key for `provider` (`anthropic` or
+/// `openai`), before anything is sent over the network
+///
+/// Only the shape is checked: non-empty, no whitespace anywhere, and the
+/// provider's prefix. A key for the other provider gets a pointed error,
+/// since that's the usual mistake. Callers should trim pasted keys first.
+pub fn validate_api_key_format(provider: &str, key: &str) -> Result<(), String> {
+    const ANTHROPIC_PREFIX: &str = "sk-ant-";
+    const OPENAI_PREFIX: &str = "sk-";
+
+    if key.is_empty() {
+        return Err("API key is empty".to_string());
+    }
+    if key.chars().any(char::is_whitespace) {
+        return Err("API key contains whitespace; check it was pasted in full".to_string());
+    }
+
+    match provider.trim().to_lowercase().as_str() {
+        "anthropic" => {
+            if key.starts_with(ANTHROPIC_PREFIX) {
+                Ok(())
+            } else if key.starts_with(OPENAI_PREFIX) {
+                Err("This looks like an OpenAI key; Anthropic keys start with sk-ant-".to_string())
+            } else {
+                Err("Anthropic API keys start with sk-ant-".to_string())
+            }
+        }
+        "openai" => {
+            if key.starts_with(ANTHROPIC_PREFIX) {
+                Err("This looks like an Anthropic key; OpenAI keys start with sk-".to_string())
+            } else if key.starts_with(OPENAI_PREFIX) && key.len() > OPENAI_PREFIX.len() {
+                Ok(())
+            } else {
+                Err("OpenAI API keys start with sk-".to_string())
+            }
+        }
+        _ => Err(LlmError::UnknownProvider(provider.to_string()).to_string()),
+    }
+}
+
+// ============================================================================
+// Environment-based Selection
+// ============================================================================
+
+/// Build the provider selected by `ASTERISK_LLM_PROVIDER` (default `anthropic`)
+///
+/// Keys come from `ANTHROPIC_API_KEY` / `OPENAI_API_KEY`. The mock provider
+/// needs no key and answers with `ASTERISK_LLM_MOCK_RESPONSE` if set.
+/// `ASTERISK_LLM_MAX_BODY_BYTES` overrides the response body cap,
+/// `ASTERISK_LLM_MAX_RETRIES` and `ASTERISK_LLM_RETRY_BASE_MS` the retry
+/// policy, and `ASTERISK_LLM_FALLBACK` adds providers to fall back on.
+pub fn provider_from_env() -> Result<Box<dyn LlmProvider>, LlmError> {
+    provider_from_vars(|name| std::env::var(name).ok())
+}
+
+/// `primary`, followed by the providers `ASTERISK_LLM_FALLBACK` lists
+pub fn with_fallbacks_from_env(
+    primary: Box<dyn LlmProvider>,
+) -> Result<Box<dyn LlmProvider>, LlmError> {
+    with_fallbacks_from_vars(primary, |name| std::env::var(name).ok())
+}
+
+fn provider_from_vars(
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<Box<dyn LlmProvider>, LlmError> {
+    let kind = var(PROVIDER_ENV).unwrap_or_else(|| "anthropic".to_string());
+    let primary = provider_of_kind(&kind, &var)?;
+    with_fallbacks_from_vars(primary, var)
+}
+
+fn with_fallbacks_from_vars(
+    primary: Box<dyn LlmProvider>,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<Box<dyn LlmProvider>, LlmError> {
+    let list = var(FALLBACK_ENV).unwrap_or_default();
+    let mut providers = vec![primary];
+    for kind in list.split(',').map(str::trim).filter(|k| !k.is_empty()) {
+        providers.push(provider_of_kind(kind, &var)?);
+    }
+    if providers.len() == 1 {
+        return Ok(providers.remove(0));
+    }
+    Ok(Box::new(FallbackProvider::new(providers)))
+}
+
+/// Build one provider by name, with its key and body cap from `var`
+fn provider_of_kind(
+    kind: &str,
+    var: &impl Fn(&str) -> Option<String>,
+) -> Result<Box<dyn LlmProvider>, LlmError> {
+    let require_key = |name: &str| {
+        var(name)
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| LlmError::MissingApiKey(name.to_string()))
+    };
+
+    let max_body_bytes = match var(MAX_BODY_ENV) {
+        Some(value) => value.trim().parse::<usize>().map_err(|_| {
+            LlmError::Request(format!("{} must be a byte count: {}", MAX_BODY_ENV, value))
+        })?,
+        None => DEFAULT_MAX_BODY_BYTES,
+    };
+
+    let timeout = match var(TIMEOUT_ENV) {
+        Some(value) => match value.trim().parse::<u64>() {
+            Ok(secs) if secs > 0 => Duration::from_secs(secs),
+            _ => {
+                return Err(LlmError::Request(format!(
+                    "{} must be a number of seconds: {}",
+                    TIMEOUT_ENV, value
+                )))
+            }
+        },
+        None => DEFAULT_TIMEOUT,
+    };
+
+    let retry = LlmConfig::from_vars(var)?;
+
+    match kind.trim().to_lowercase().as_str() {
+        "anthropic" => Ok(Box::new(
+            AnthropicProvider::new(require_key(ANTHROPIC_KEY_ENV)?)
+                .with_max_body_bytes(max_body_bytes)
+                .with_timeout(timeout)
+                .with_retry_config(retry),
+        )),
+        "openai" => Ok(Box::new(
+            OpenAiProvider::new(require_key(OPENAI_KEY_ENV)?)
+                .with_max_body_bytes(max_body_bytes)
+                .with_timeout(timeout)
+                .with_retry_config(retry),
+        )),
+        "mock" => Ok(Box::new(MockProvider::new(
+            var("ASTERISK_LLM_MOCK_RESPONSE").into_iter().collect(),
+        ))),
+        _ => Err(LlmError::UnknownProvider(kind.to_string())),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn from_vars(vars: &[(&str, &str)]) -> Result<Box<dyn LlmProvider>, LlmError> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        provider_from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_well_formed_keys_accepted() {
+        assert!(validate_api_key_format("anthropic", "sk-ant-api03-abcDEF_123").is_ok());
+        assert!(validate_api_key_format("Anthropic", "sk-ant-x").is_ok());
+        assert!(validate_api_key_format("openai", "sk-proj-abcDEF123").is_ok());
+        assert!(validate_api_key_format("openai", "sk-abc123").is_ok());
+    }
+
+    #[test]
+    fn test_malformed_anthropic_keys_rejected() {
+        for (key, expected) in [
+            ("", "empty"),
+            ("sk-ant-abc ", "whitespace"),
+            (" sk-ant-abc", "whitespace"),
+            ("sk-ant-ab\tc", "whitespace"),
+            ("sk-proj-abc", "OpenAI key"),
+            ("ant-abc", "start with sk-ant-"),
+        ] {
+            let err = validate_api_key_format("anthropic", key).unwrap_err();
+            assert!(err.contains(expected), "{:?}: {}", key, err);
+        }
+    }
+
+    #[test]
+    fn test_malformed_openai_keys_rejected() {
+        for (key, expected) in [
+            ("", "empty"),
+            ("sk-abc\n", "whitespace"),
+            ("sk-a bc", "whitespace"),
+            ("sk-ant-abc", "Anthropic key"),
+            ("sk-", "start with sk-"),
+            ("pk-abc", "start with sk-"),
+        ] {
+            let err = validate_api_key_format("openai", key).unwrap_err();
+            assert!(err.contains(expected), "{:?}: {}", key, err);
+        }
+    }
+
+    #[test]
+    fn test_unknown_provider_rejected() {
+        assert_eq!(
+            validate_api_key_format("gemini", "sk-abc").unwrap_err(),
+            "Unknown LLM provider: gemini"
+        );
+    }
+
+    #[test]
+    fn test_anthropic_from_env() {
+        let provider = from_vars(&[
+            (PROVIDER_ENV, "anthropic"),
+            (ANTHROPIC_KEY_ENV, "sk-ant-test"),
+        ])
+        .unwrap();
+        assert_eq!(provider.name(), "anthropic");
+    }
+
+    #[test]
+    fn test_anthropic_is_default() {
+        let provider = from_vars(&[(ANTHROPIC_KEY_ENV, "sk-ant-test")]).unwrap();
+        assert_eq!(provider.name(), "anthropic");
+    }
+
+    #[test]
+    fn test_openai_from_env() {
+        let provider = from_vars(&[(PROVIDER_ENV, "OpenAI"), (OPENAI_KEY_ENV, "sk-test")]).unwrap();
+        assert_eq!(provider.name(), "openai");
+    }
+
+    #[test]
+    fn test_mock_from_env_needs_no_key() {
+        let provider = from_vars(&[(PROVIDER_ENV, "mock")]).unwrap();
+        assert_eq!(provider.name(), "mock");
+    }
+
+    #[test]
+    fn test_missing_key_errors() {
+        let err = from_vars(&[(PROVIDER_ENV, "openai"), (ANTHROPIC_KEY_ENV, "sk-ant-x")])
+            .err()
+            .unwrap();
+        assert_eq!(err, LlmError::MissingApiKey(OPENAI_KEY_ENV.to_string()));
+
+        let err = from_vars(&[(PROVIDER_ENV, "anthropic"), (ANTHROPIC_KEY_ENV, "  ")])
+            .err()
+            .unwrap();
+        assert_eq!(err, LlmError::MissingApiKey(ANTHROPIC_KEY_ENV.to_string()));
+    }
+
+    #[test]
+    fn test_invalid_max_body_errors() {
+        let err = from_vars(&[(ANTHROPIC_KEY_ENV, "sk-ant-test"), (MAX_BODY_ENV, "lots")])
+            .err()
+            .unwrap();
+        assert!(matches!(err, LlmError::Request(msg) if msg.contains(MAX_BODY_ENV)));
+    }
+
+    #[test]
+    fn test_invalid_timeout_errors() {
+        for value in ["soon", "0"] {
+            let err = from_vars(&[(ANTHROPIC_KEY_ENV, "sk-ant-test"), (TIMEOUT_ENV, value)])
+                .err()
+                .unwrap();
+            assert!(matches!(err, LlmError::Request(msg) if msg.contains(TIMEOUT_ENV)));
+        }
+        assert!(from_vars(&[(ANTHROPIC_KEY_ENV, "sk-ant-test"), (TIMEOUT_ENV, "5")]).is_ok());
+    }
+
+    #[test]
+    fn test_fallback_chain_from_env() {
+        let provider = from_vars(&[
+            (ANTHROPIC_KEY_ENV, "sk-ant-test"),
+            (FALLBACK_ENV, " openai, mock ,"),
+            (OPENAI_KEY_ENV, "sk-test"),
+        ])
+        .unwrap();
+        assert_eq!(provider.name(), "fallback");
+
+        let err = from_vars(&[(ANTHROPIC_KEY_ENV, "sk-ant-test"), (FALLBACK_ENV, "openai")])
+            .err()
+            .unwrap();
+        assert_eq!(err, LlmError::MissingApiKey(OPENAI_KEY_ENV.to_string()));
+        let single = from_vars(&[(ANTHROPIC_KEY_ENV, "sk-ant-test"), (FALLBACK_ENV, "")]);
+        assert_eq!(single.unwrap().name(), "anthropic");
+    }
+
+    #[test]
+    fn test_unknown_provider_errors() {
+        let err = from_vars(&[(PROVIDER_ENV, "gemini")]).err().unwrap();
+        assert_eq!(err, LlmError::UnknownProvider("gemini".to_string()));
+    }
+
+    /// Serve `body` to every request on a local port; returns the base URL
+    fn serve(body: String, chunked: bool) -> String {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let data = body.clone().into_bytes();
+                // Without a length the body goes out chunked
+                let length = (!chunked).then_some(data.len());
+                let response = tiny_http::Response::new(
+                    tiny_http::StatusCode(200),
+                    vec![],
+                    std::io::Cursor::new(data),
+                    length,
+                    None,
+                );
+                let _ = request.respond(response);
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_rejected() {
+        let huge = format!(
+            r#"{{"content":[{{"type":"text","text":"{}"}}]}}"#,
+            "x".repeat(1024 * 1024)
+        );
+        for chunked in [false, true] {
+            let provider = AnthropicProvider::new("sk-ant-test")
+                .with_base_url(serve(huge.clone(), chunked))
+                .with_max_body_bytes(64 * 1024);
+            let err = provider.complete("hi").await.unwrap_err();
+            assert!(
+                matches!(&err, LlmError::BadResponse(msg) if msg.contains("exceeds")),
+                "chunked={} err={:?}",
+                chunked,
+                err
+            );
+        }
+    }
+
+    /// Send headers and the start of a body, then stall; returns the base URL
+    fn serve_stalled_body() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                use std::io::{Read, Write};
+                let _ = stream.read(&mut [0; 4096]);
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\n{\"content\":[");
+                let _ = stream.flush();
+                std::thread::sleep(Duration::from_secs(30));
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_stalled_body_times_out() {
+        let provider = AnthropicProvider::new("sk-ant-test")
+            .with_base_url(serve_stalled_body())
+            .with_timeout(Duration::from_millis(300))
+            .with_retry_config(LlmConfig {
+                max_retries: 0,
+                base_delay_ms: 1,
+            });
+        let started = std::time::Instant::now();
+        let err = provider.complete("hi").await.unwrap_err();
+        assert!(matches!(err, LlmError::Request(_)), "{:?}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_body_under_limit_is_read() {
+        let body = r#"{"choices":[{"message":{"content":"hello"}}]}"#.to_string();
+        let provider = OpenAiProvider::new("sk-test")
+            .with_base_url(serve(body, true))
+            .with_max_body_bytes(1024);
+        assert_eq!(provider.complete("hi").await.unwrap(), "hello");
+    }
+
+    /// Answer requests with `replies` (status and body) in order, the last
+    /// one repeating; returns the base URL and the request count
+    fn serve_replies(replies: Vec<(u16, &'static str)>) -> (String, Arc<AtomicUsize>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let count = Arc::new(AtomicUsize::new(0));
+        let served = Arc::clone(&count);
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let n = served.fetch_add(1, Ordering::SeqCst);
+                let (status, body) = replies[n.min(replies.len() - 1)];
+                let response = tiny_http::Response::from_string(body).with_status_code(status);
+                let _ = request.respond(response);
+            }
+        });
+        (url, count)
+    }
+
+    const CLAUDE_OK: &str = r#"{"content":[{"type":"text","text":"hello"}]}"#;
+
+    fn fast_retries(max_retries: u32) -> LlmConfig {
+        LlmConfig {
+            max_retries,
+            base_delay_ms: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_then_success_retries_once() {
+        let (url, count) = serve_replies(vec![(429, "slow down"), (200, CLAUDE_OK)]);
+        let provider = AnthropicProvider::new("sk-ant-test")
+            .with_base_url(url)
+            .with_retry_config(fast_retries(3));
+        assert_eq!(provider.complete("hi").await.unwrap(), "hello");
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_non_retryable_errors_fail_fast() {
+        for status in [400, 401, 403] {
+            let (url, count) = serve_replies(vec![(status, "no"), (200, CLAUDE_OK)]);
+            let provider = AnthropicProvider::new("sk-ant-test")
+                .with_base_url(url)
+                .with_retry_config(fast_retries(3));
+            let err = provider.complete("hi").await.unwrap_err();
+            assert!(matches!(err, LlmError::Api { status: s, .. } if s == status));
+            assert_eq!(count.load(Ordering::SeqCst), 1, "status {}", status);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_stop_at_the_limit() {
+        let (url, count) = serve_replies(vec![(529, "overloaded")]);
+        let provider = OpenAiProvider::new("sk-test")
+            .with_base_url(url)
+            .with_retry_config(fast_retries(2));
+        let err = provider.complete("hi").await.unwrap_err();
+        assert!(matches!(err, LlmError::Api { status: 529, .. }));
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_retry_config_from_env() {
+        let vars = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        assert_eq!(
+            LlmConfig::from_vars(vars(&[])).unwrap(),
+            LlmConfig::default()
+        );
+        let config = LlmConfig::from_vars(vars(&[
+            (MAX_RETRIES_ENV, "0"),
+            (RETRY_BASE_DELAY_ENV, " 50"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            config,
+            LlmConfig {
+                max_retries: 0,
+                base_delay_ms: 50
+            }
+        );
+        assert!(LlmConfig::from_vars(vars(&[(MAX_RETRIES_ENV, "many")])).is_err());
+    }
+
+    #[test]
+    fn test_backoff_doubles_with_bounded_jitter() {
+        let config = LlmConfig {
+            max_retries: 5,
+            base_delay_ms: 100,
+        };
+        for (retry, base) in [(0, 100), (1, 200), (3, 800)] {
+            let delay = config.backoff(retry).as_millis() as u64;
+            assert!((base..=base + base / 2).contains(&delay), "{}", delay);
+        }
+        assert!(config.backoff(30).as_millis() as u64 <= MAX_RETRY_DELAY_MS * 3 / 2);
+    }
+
+    #[tokio::test]
+    async fn test_mock_serves_responses_in_order() {
+        let mock = MockProvider::new(vec!["first".to_string(), "second".to_string()]);
+        assert_eq!(mock.complete("a").await.unwrap(), "first");
+        assert_eq!(mock.complete("b").await.unwrap(), "second");
+        assert_eq!(mock.complete("c").await.unwrap(), "second");
+        assert_eq!(mock.prompts(), vec!["a", "b", "c"]);
+    }
+}
@@ -0,0 +1,97 @@
+/*!
+ * LLM Input Redaction
+ *
+ * Page text around a field (labels, nearby text, placeholders) can contain
+ * the user's own data. Before anything goes to a provider, configured
+ * sensitive tokens and the values of sensitive vault items are replaced
+ * with a placeholder. Vault key names are never redacted; the model needs
+ * them to answer.
+ */
+
+use regex::{Regex, RegexBuilder};
+
+/// Replacement for redacted text
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Tokens that must not leave the machine
+#[derive(Debug, Clone, Default)]
+pub struct PromptRedaction {
+    pattern: Option<Regex>,
+}
+
+impl PromptRedaction {
+    /// Build a redaction for `tokens`; blank tokens are ignored and matching
+    /// is case-insensitive
+    pub fn new<S: AsRef<str>>(tokens: &[S]) -> Self {
+        let mut tokens: Vec<&str> = tokens
+            .iter()
+            .map(|t| t.as_ref().trim())
+            .filter(|t| !t.is_empty())
+            .collect();
+        if tokens.is_empty() {
+            return Self::default();
+        }
+        // Longest first, so a token containing another is redacted whole
+        tokens.sort_by_key(|t| std::cmp::Reverse(t.len()));
+        tokens.dedup();
+
+        let alternation = tokens
+            .iter()
+            .map(|t| regex::escape(t))
+            .collect::<Vec<_>>()
+            .join("|");
+        let pattern = RegexBuilder::new(&alternation)
+            .case_insensitive(true)
+            .build()
+            .ok();
+        Self { pattern }
+    }
+
+    /// Redact every token occurrence in `text`
+    pub fn redact(&self, text: &str) -> String {
+        match &self.pattern {
+            Some(pattern) => pattern.replace_all(text, REDACTED).into_owned(),
+            None => text.to_string(),
+        }
+    }
+
+    /// Redact an optional piece of text
+    pub fn redact_opt(&self, text: &Option<String>) -> Option<String> {
+        text.as_deref().map(|t| self.redact(t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_tokens_is_identity() {
+        let redaction = PromptRedaction::new::<&str>(&[]);
+        assert_eq!(
+            redaction.redact("Email: ada@example.com"),
+            "Email: ada@example.com"
+        );
+    }
+
+    #[test]
+    fn test_tokens_redacted_case_insensitively() {
+        let redaction = PromptRedaction::new(&["ada@example.com", "  ", "Lovelace"]);
+        assert_eq!(
+            redaction.redact("Signed in as ADA@example.com (Ada LOVELACE)"),
+            "Signed in as [REDACTED] (Ada [REDACTED])"
+        );
+    }
+
+    #[test]
+    fn test_longest_token_wins() {
+        let redaction = PromptRedaction::new(&["555", "555-0100"]);
+        assert_eq!(redaction.redact("Call 555-0100"), "Call [REDACTED]");
+    }
+
+    #[test]
+    fn test_regex_metacharacters_are_literal() {
+        let redaction = PromptRedaction::new(&["a.b"]);
+        assert_eq!(redaction.redact("axb a.b"), "axb [REDACTED]");
+    }
+}
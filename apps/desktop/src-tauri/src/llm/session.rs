@@ -0,0 +1,555 @@
+/*!
+ * Analysis Sessions
+ *
+ * A dynamic form sends snapshot after snapshot, and analyses started for
+ * each would otherwise overlap and race. Every snapshot analysis runs in a
+ * session keyed by domain and tab. Beginning a session for a key supersedes
+ * the previous one: its cancellation token fires, its in-flight LLM call is
+ * dropped, and its results are discarded. Answers it already got are in the
+ * analysis cache, so the new session reuses them instead of asking again.
+ *
+ * Only the current session for a key may produce a plan.
+ *
+ * A session may also carry a deadline. Past it no further LLM call starts,
+ * a call in flight is dropped, and the fields left over come back as
+ * deadline exceeded instead of holding up the answer.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::{poll_fn, Future};
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Poll, Waker};
+use tokio::time::Instant;
+
+/// Event emitted (with `AnalysisSupersededJson`) when a session is superseded
+pub const ANALYSIS_SUPERSEDED_EVENT: &str = "analysis-superseded";
+
+// ============================================================================
+// Cancellation Token
+// ============================================================================
+
+#[derive(Debug, Default)]
+struct TokenInner {
+    cancelled: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+/// Shared flag that stops futures run under it; clones share the flag
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<TokenInner>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn wakers(&self) -> MutexGuard<'_, Vec<Waker>> {
+        self.inner
+            .wakers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Cancel, waking everything waiting on this token
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        for waker in self.wakers().drain(..) {
+            waker.wake();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Drive `future` until it finishes (Some) or the token is cancelled
+    /// (None); on cancellation the future is dropped unfinished
+    pub async fn run_until_cancelled<F: Future>(&self, future: F) -> Option<F::Output> {
+        let mut future = pin!(future);
+        poll_fn(|cx| {
+            if self.is_cancelled() {
+                return Poll::Ready(None);
+            }
+            if let Poll::Ready(output) = future.as_mut().poll(cx) {
+                return Poll::Ready(Some(output));
+            }
+            let mut wakers = self.wakers();
+            if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+            drop(wakers);
+            // A cancel between the first check and registering would be lost
+            if self.is_cancelled() {
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Wait until the token is cancelled
+    pub async fn cancelled(&self) {
+        self.run_until_cancelled(std::future::pending::<()>()).await;
+    }
+}
+
+// ============================================================================
+// Sessions
+// ============================================================================
+
+/// What a session is keyed by: snapshots from the same domain and tab
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SessionKey {
+    pub domain: String,
+    pub tab_id: Option<String>,
+}
+
+/// Work a session did, counted as it goes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionWorkJson {
+    /// Fields answered from the analysis cache
+    #[serde(rename = "cacheHits")]
+    pub cache_hits: usize,
+    /// Fields the LLM answered; the answers were cached, so a successor
+    /// session reuses them
+    #[serde(rename = "llmAnswers")]
+    pub llm_answers: usize,
+    /// LLM calls dropped in flight by cancellation
+    #[serde(rename = "cancelledCalls")]
+    pub cancelled_calls: usize,
+    /// Selected fields the session never got to
+    pub unstarted: usize,
+}
+
+/// Payload of `ANALYSIS_SUPERSEDED_EVENT`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisSupersededJson {
+    pub domain: String,
+    #[serde(rename = "tabId")]
+    pub tab_id: Option<String>,
+    #[serde(rename = "sessionId")]
+    pub session_id: u64,
+    #[serde(rename = "supersededBy")]
+    pub superseded_by: u64,
+    #[serde(rename = "startedAt")]
+    pub started_at: String,
+    #[serde(rename = "supersededAt")]
+    pub superseded_at: String,
+    #[serde(flatten)]
+    pub work: SessionWorkJson,
+}
+
+#[derive(Debug, Default)]
+struct SessionState {
+    work: SessionWorkJson,
+    /// Successor id and when it began
+    superseded: Option<(u64, DateTime<Utc>)>,
+}
+
+/// Handle held by one running analysis
+#[derive(Debug)]
+pub struct AnalysisSession {
+    id: u64,
+    key: SessionKey,
+    started_at: DateTime<Utc>,
+    token: CancellationToken,
+    state: Arc<Mutex<SessionState>>,
+    /// When LLM calls must stop
+    deadline: Option<Instant>,
+}
+
+impl AnalysisSession {
+    fn new(id: u64, key: SessionKey, started_at: DateTime<Utc>) -> Self {
+        Self {
+            id,
+            key,
+            started_at,
+            token: CancellationToken::new(),
+            state: Arc::default(),
+            deadline: None,
+        }
+    }
+
+    /// This session, stopping its LLM calls at `deadline` if there is one
+    pub fn with_deadline(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Whether the deadline has passed, so no LLM call should start
+    pub fn past_deadline(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// A session outside any manager, which nothing can supersede
+    pub fn detached() -> Self {
+        Self::new(
+            0,
+            SessionKey {
+                domain: String::new(),
+                tab_id: None,
+            },
+            Utc::now(),
+        )
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    pub fn is_superseded(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    fn state(&self) -> MutexGuard<'_, SessionState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Update this session's work counters
+    pub fn record(&self, update: impl FnOnce(&mut SessionWorkJson)) {
+        update(&mut self.state().work);
+    }
+
+    pub fn work(&self) -> SessionWorkJson {
+        self.state().work.clone()
+    }
+
+    /// Event payload, once a newer session has superseded this one
+    pub fn superseded_report(&self) -> Option<AnalysisSupersededJson> {
+        let state = self.state();
+        let (superseded_by, superseded_at) = state.superseded?;
+        Some(AnalysisSupersededJson {
+            domain: self.key.domain.clone(),
+            tab_id: self.key.tab_id.clone(),
+            session_id: self.id,
+            superseded_by,
+            started_at: self.started_at.to_rfc3339(),
+            superseded_at: superseded_at.to_rfc3339(),
+            work: state.work.clone(),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct ActiveSession {
+    id: u64,
+    token: CancellationToken,
+    state: Arc<Mutex<SessionState>>,
+}
+
+/// The current analysis session per domain and tab
+#[derive(Debug, Default)]
+pub struct AnalysisSessions {
+    active: Mutex<HashMap<SessionKey, ActiveSession>>,
+    next_id: AtomicU64,
+}
+
+impl AnalysisSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn active(&self) -> MutexGuard<'_, HashMap<SessionKey, ActiveSession>> {
+        self.active.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Begin a session for `key`, superseding (and cancelling) the one
+    /// already running for it
+    pub fn begin(&self, key: SessionKey, now: DateTime<Utc>) -> AnalysisSession {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let session = AnalysisSession::new(id, key.clone(), now);
+        let previous = self.active().insert(
+            key,
+            ActiveSession {
+                id,
+                token: session.token.clone(),
+                state: Arc::clone(&session.state),
+            },
+        );
+        if let Some(previous) = previous {
+            previous
+                .state
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .superseded = Some((id, now));
+            previous.token.cancel();
+        }
+        session
+    }
+
+    /// Whether `session` is still the latest for its key
+    pub fn is_current(&self, session: &AnalysisSession) -> bool {
+        self.active()
+            .get(&session.key)
+            .is_some_and(|active| active.id == session.id)
+    }
+
+    /// End `session`; returns whether it was still current, i.e. whether
+    /// its results may be used
+    pub fn finish(&self, session: &AnalysisSession) -> bool {
+        let mut active = self.active();
+        let current = active.get(&session.key).is_some_and(|a| a.id == session.id);
+        if current {
+            active.remove(&session.key);
+        }
+        current
+    }
+
+    /// Sessions currently running
+    pub fn len(&self) -> usize {
+        self.active().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{
+        analyze_snapshot_in_session, AnalysisCache, FieldAnalysisStatus, LlmFuture, LlmProvider,
+        MockProvider, PromptRedaction,
+    };
+    use crate::{FieldNodeJson, FormFingerprintJson, FormSnapshotJson};
+    use chrono::Duration;
+    use std::sync::atomic::AtomicUsize;
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        "2026-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::seconds(seconds)
+    }
+
+    fn key(domain: &str, tab: &str) -> SessionKey {
+        SessionKey {
+            domain: domain.to_string(),
+            tab_id: Some(tab.to_string()),
+        }
+    }
+
+    fn snapshot(ids: &[&str]) -> FormSnapshotJson {
+        FormSnapshotJson {
+            url: "https://example.com/apply".to_string(),
+            domain: "example.com".to_string(),
+            title: "Apply".to_string(),
+            captured_at: "2026-01-01T00:00:00Z".to_string(),
+            fingerprint: FormFingerprintJson {
+                field_count: ids.len() as u32,
+                field_types: vec![],
+                required_count: 0,
+                hash: "fp".to_string(),
+            },
+            fields: ids
+                .iter()
+                .map(|id| FieldNodeJson {
+                    id: id.to_string(),
+                    name: id.to_string(),
+                    label: format!("Field {}", id),
+                    field_type: "text".to_string(),
+                    semantic: "unknown".to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            session: Default::default(),
+        }
+    }
+
+    /// Answers the first `immediate` calls at once; later calls wait for the
+    /// gate to open
+    struct GatedProvider {
+        immediate: usize,
+        gate: CancellationToken,
+        calls: AtomicUsize,
+        inner: MockProvider,
+    }
+
+    impl GatedProvider {
+        fn new(immediate: usize) -> Self {
+            Self {
+                immediate,
+                gate: CancellationToken::new(),
+                calls: AtomicUsize::new(0),
+                inner: MockProvider::new(vec![]),
+            }
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl LlmProvider for GatedProvider {
+        fn name(&self) -> &'static str {
+            "gated"
+        }
+
+        fn complete<'a>(&'a self, prompt: &'a str) -> LlmFuture<'a> {
+            let index = self.calls.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                if index >= self.immediate {
+                    self.gate.cancelled().await;
+                }
+                self.inner.complete(prompt).await
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_stops_pending_future() {
+        let token = CancellationToken::new();
+        let (result, _) = tokio::join!(
+            token.run_until_cancelled(std::future::pending::<()>()),
+            async {
+                tokio::task::yield_now().await;
+                token.cancel();
+            }
+        );
+        assert_eq!(result, None);
+        assert_eq!(token.run_until_cancelled(async { 1 }).await, None);
+        assert_eq!(
+            CancellationToken::new()
+                .run_until_cancelled(async { 1 })
+                .await,
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_new_session_supersedes_same_domain_and_tab_only() {
+        let sessions = AnalysisSessions::new();
+        let first = sessions.begin(key("example.com", "1"), at(0));
+        let other_tab = sessions.begin(key("example.com", "2"), at(1));
+        let other_domain = sessions.begin(key("other.org", "1"), at(2));
+        let second = sessions.begin(key("example.com", "1"), at(5));
+
+        assert!(first.is_superseded());
+        assert!(!sessions.is_current(&first));
+        assert!(!other_tab.is_superseded());
+        assert!(!other_domain.is_superseded());
+        assert!(sessions.is_current(&second));
+        assert_eq!(sessions.len(), 3);
+
+        let report = first.superseded_report().unwrap();
+        assert_eq!(report.session_id, first.id());
+        assert_eq!(report.superseded_by, second.id());
+        assert_eq!(report.started_at, at(0).to_rfc3339());
+        assert_eq!(report.superseded_at, at(5).to_rfc3339());
+        assert!(second.superseded_report().is_none());
+    }
+
+    #[test]
+    fn test_only_latest_session_may_finish() {
+        let sessions = AnalysisSessions::new();
+        let first = sessions.begin(key("example.com", "1"), at(0));
+        let second = sessions.begin(key("example.com", "1"), at(1));
+
+        assert!(!sessions.finish(&first));
+        assert!(sessions.finish(&second));
+        assert!(sessions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_superseded_analysis_is_cancelled_and_successor_reuses_answers() {
+        let sessions = AnalysisSessions::new();
+        let cache = AnalysisCache::new();
+        let redaction = PromptRedaction::default();
+        let fields = ["a", "b", "c"];
+        let snap = snapshot(&fields);
+        let unmatched: Vec<String> = fields.iter().map(|f| f.to_string()).collect();
+        let keys = vec!["email".to_string()];
+
+        // The first session gets an answer for "a", then hangs on "b"
+        let slow = GatedProvider::new(1);
+        let fast = MockProvider::new(vec![]);
+        let first = sessions.begin(key("example.com", "1"), at(0));
+
+        let (first_results, (second, second_results)) = tokio::join!(
+            analyze_snapshot_in_session(
+                &slow, &snap, &unmatched, None, &keys, &redaction, &cache, &first
+            ),
+            async {
+                while slow.calls() < 2 {
+                    tokio::task::yield_now().await;
+                }
+                let second = sessions.begin(key("example.com", "1"), at(3));
+                let results = analyze_snapshot_in_session(
+                    &fast, &snap, &unmatched, None, &keys, &redaction, &cache, &second,
+                )
+                .await;
+                (second, results)
+            }
+        );
+
+        // The superseded session produced nothing and accounted for its work
+        assert!(first_results.is_none());
+        assert!(!sessions.finish(&first));
+        let report = first.superseded_report().unwrap();
+        assert_eq!(report.superseded_by, second.id());
+        assert_eq!(
+            report.work,
+            SessionWorkJson {
+                cache_hits: 0,
+                llm_answers: 1,
+                cancelled_calls: 1,
+                unstarted: 1,
+            }
+        );
+        // "c" was never sent
+        assert_eq!(slow.calls(), 2);
+
+        // The successor reused "a" and asked only about "b" and "c"
+        let results = second_results.unwrap();
+        assert!(sessions.finish(&second));
+        let cached: Vec<(&str, bool)> = results
+            .iter()
+            .map(|r| (r.field_id.as_str(), r.cached))
+            .collect();
+        assert_eq!(cached, vec![("a", true), ("b", false), ("c", false)]);
+        assert!(results
+            .iter()
+            .all(|r| r.status == FieldAnalysisStatus::Analyzed));
+        assert_eq!(fast.prompts().len(), 2);
+        assert_eq!(second.work().cache_hits, 1);
+        assert_eq!(second.work().llm_answers, 2);
+    }
+
+    #[tokio::test]
+    async fn test_detached_session_runs_to_completion() {
+        let session = AnalysisSession::detached();
+        let provider = MockProvider::new(vec![]);
+        let results = analyze_snapshot_in_session(
+            &provider,
+            &snapshot(&["a"]),
+            &["a".to_string()],
+            None,
+            &[],
+            &PromptRedaction::default(),
+            &AnalysisCache::new(),
+            &session,
+        )
+        .await
+        .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(session.superseded_report().is_none());
+    }
+}
@@ -0,0 +1,38 @@
+# German messages
+#
+# Missing ids fall back to en.ftl. Keep the `{ $name }` placeholders of the
+# English text.
+
+## Command errors
+command-failed = { $detail }
+feature-disabled = Dieser Build von Asterisk wurde ohne die Funktion „{ $feature }“ kompiliert
+
+## Match reasons
+match-autocomplete = Zugeordnet über autocomplete={ $token }
+match-label = Die Beschriftung „{ $text }“ enthält „{ $pattern }“
+match-aria-label = Das aria-label „{ $text }“ enthält „{ $pattern }“
+match-placeholder = Der Platzhalter „{ $text }“ enthält „{ $pattern }“
+match-described-by = Der aria-describedby-Text „{ $text }“ enthält „{ $pattern }“
+match-nearby-text = Der Text in der Nähe „{ $text }“ enthält „{ $pattern }“
+match-name = Das name-Attribut „{ $text }“ enthält „{ $pattern }“
+match-override = Ihre gespeicherte Auswahl für dieses Feld
+match-template = Zugeordnet über eine gespeicherte Formularvorlage
+match-composed-address = Aus Adressbestandteilen zusammengesetzt (Format { $format })
+match-session-step = Dasselbe Feld wie in Schritt { $step } dieses Formulars
+match-session = Dasselbe Feld wie in einem früheren Schritt dieses Formulars
+
+## Fill command validation
+command-id-empty = Die Befehls-ID ist leer
+command-domain-empty = Die Zieldomain ist leer
+command-no-fills = Der Befehl enthält keine Felder zum Ausfüllen
+command-duplicate-fill = Das Feld „{ $field }“ wird mehrfach ausgefüllt
+command-expiry-order = expiresAt liegt nicht nach createdAt
+command-invalid-created-at = Ungültiges createdAt: { $value }
+command-invalid-expires-at = Ungültiges expiresAt: { $value }
+batch-empty = Der Stapel enthält keine Befehle
+batch-duplicate-id = Die Befehls-ID „{ $id }“ kommt im Stapel mehrfach vor
+field-problem = { $field }: { $problem }
+value-too-long = Der Wert hat { $length } Zeichen, das Feld erlaubt höchstens { $max }
+value-truncated = Der Wert wird von { $length } auf { $max } Zeichen gekürzt
+value-pattern-mismatch = Der Wert entspricht nicht dem Muster { $pattern } des Feldes
+value-type-mismatch = Der Wert von „{ $key }“ passt nicht zum Eingabetyp { $type }
@@ -0,0 +1,38 @@
+# English messages, the fallback for every other locale
+#
+# One `id = text` per line; `{ $name }` is replaced by the param `name`.
+# Ids are listed in `i18n::ids`.
+
+## Command errors
+command-failed = { $detail }
+feature-disabled = This build of Asterisk was compiled without the "{ $feature }" feature
+
+## Match reasons
+match-autocomplete = Matched by autocomplete={ $token }
+match-label = The label "{ $text }" contains "{ $pattern }"
+match-aria-label = The aria-label "{ $text }" contains "{ $pattern }"
+match-placeholder = The placeholder "{ $text }" contains "{ $pattern }"
+match-described-by = The aria-describedby text "{ $text }" contains "{ $pattern }"
+match-nearby-text = The nearby text "{ $text }" contains "{ $pattern }"
+match-name = The name attribute "{ $text }" contains "{ $pattern }"
+match-override = Your saved choice for this field
+match-template = Matched via saved form template
+match-composed-address = Composed from address components ({ $format } format)
+match-session-step = Same field as step { $step } of this form
+match-session = Same field as an earlier step of this form
+
+## Fill command validation
+command-id-empty = Command id is empty
+command-domain-empty = Target domain is empty
+command-no-fills = Command has no fills
+command-duplicate-fill = Duplicate fill for field "{ $field }"
+command-expiry-order = expiresAt is not after createdAt
+command-invalid-created-at = Invalid createdAt: { $value }
+command-invalid-expires-at = Invalid expiresAt: { $value }
+batch-empty = Batch has no commands
+batch-duplicate-id = Duplicate command id "{ $id }" in batch
+field-problem = { $field }: { $problem }
+value-too-long = Value is { $length } characters but the field allows at most { $max }
+value-truncated = Value will be truncated from { $length } to { $max } characters
+value-pattern-mismatch = Value does not match the field's pattern { $pattern }
+value-type-mismatch = The value of "{ $key }" doesn't look like input of type { $type }
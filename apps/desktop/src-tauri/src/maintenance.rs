@@ -0,0 +1,398 @@
+/*!
+ * Destructive Maintenance
+ *
+ * Every operation that removes data (audit pruning, expired fill command
+ * purge) takes a `dry_run` flag and returns a `MaintenanceReportJson`. A dry
+ * run computes exactly what would be removed without touching anything; a
+ * real run returns the same report shape for what was actually removed and
+ * is recorded in the maintenance ledger (`maintenance.jsonl`).
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::{AuditEntryJson, FillCommandJson};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// What a maintenance operation removed (or would remove, for a dry run)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MaintenanceReportJson {
+    /// Operation name (e.g. "audit_prune")
+    pub operation: String,
+    #[serde(rename = "dryRun")]
+    pub dry_run: bool,
+    /// ISO timestamp of the run
+    #[serde(rename = "ranAt")]
+    pub ran_at: String,
+    #[serde(rename = "removedCount")]
+    pub removed_count: usize,
+    /// Ids of the removed audit entries or fill commands
+    #[serde(rename = "removedIds")]
+    pub removed_ids: Vec<String>,
+    /// Approximate bytes freed, measured as serialized JSON
+    #[serde(rename = "reclaimedBytes")]
+    pub reclaimed_bytes: u64,
+}
+
+impl MaintenanceReportJson {
+    fn new(operation: &str, dry_run: bool) -> Self {
+        Self {
+            operation: operation.to_string(),
+            dry_run,
+            ran_at: Utc::now().to_rfc3339(),
+            removed_count: 0,
+            removed_ids: Vec::new(),
+            reclaimed_bytes: 0,
+        }
+    }
+
+    fn add(&mut self, id: &str, bytes: usize) {
+        self.removed_count += 1;
+        self.removed_ids.push(id.to_string());
+        self.reclaimed_bytes += bytes as u64;
+    }
+}
+
+pub const AUDIT_PRUNE: &str = "audit_prune";
+pub const FILL_COMMAND_PURGE: &str = "fill_command_purge";
+
+/// Entries with an unparseable timestamp are never treated as expired
+fn is_before(timestamp: &str, cutoff: DateTime<Utc>) -> bool {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.with_timezone(&Utc) < cutoff)
+        .unwrap_or(false)
+}
+
+// ============================================================================
+// Audit Pruning
+// ============================================================================
+
+/// Remove audit entries created before `cutoff` from the JSONL log at `path`
+///
+/// Lines that don't parse are kept untouched. The log is rewritten through a
+/// temp file so a crash can't leave it half-written.
+pub fn prune_audit_log(
+    path: &Path,
+    cutoff: DateTime<Utc>,
+    dry_run: bool,
+) -> Result<MaintenanceReportJson, String> {
+    let mut report = MaintenanceReportJson::new(AUDIT_PRUNE, dry_run);
+
+    let file = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+        Err(e) => return Err(format!("Failed to open audit log: {}", e)),
+    };
+
+    let mut kept = String::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<AuditEntryJson>(&line) {
+            Ok(entry) if is_before(&entry.created_at, cutoff) => {
+                report.add(&entry.id, line.len() + 1);
+            }
+            _ => {
+                kept.push_str(&line);
+                kept.push('\n');
+            }
+        }
+    }
+
+    if !dry_run && report.removed_count > 0 {
+        let tmp = path.with_extension("jsonl.tmp");
+        fs::write(&tmp, kept).map_err(|e| format!("Failed to write audit log: {}", e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Failed to replace audit log: {}", e))?;
+    }
+    Ok(report)
+}
+
+/// Remove audit entries created before `cutoff` from an in-memory log
+pub fn prune_audit_entries(
+    entries: &mut Vec<AuditEntryJson>,
+    cutoff: DateTime<Utc>,
+    dry_run: bool,
+) -> MaintenanceReportJson {
+    let mut report = MaintenanceReportJson::new(AUDIT_PRUNE, dry_run);
+    for entry in entries.iter() {
+        if is_before(&entry.created_at, cutoff) {
+            let bytes = serde_json::to_string(entry).map_or(0, |s| s.len() + 1);
+            report.add(&entry.id, bytes);
+        }
+    }
+    if !dry_run {
+        entries.retain(|entry| !is_before(&entry.created_at, cutoff));
+    }
+    report
+}
+
+// ============================================================================
+// Expired Fill Commands
+// ============================================================================
+
+/// Remove fill commands whose `expiresAt` is before `now`
+pub fn purge_expired_commands(
+    commands: &mut Vec<FillCommandJson>,
+    now: DateTime<Utc>,
+    dry_run: bool,
+) -> MaintenanceReportJson {
+    let mut report = MaintenanceReportJson::new(FILL_COMMAND_PURGE, dry_run);
+    for command in commands.iter() {
+        if is_before(&command.expires_at, now) {
+            let bytes = serde_json::to_string(command).map_or(0, |s| s.len());
+            report.add(&command.id, bytes);
+        }
+    }
+    if !dry_run {
+        commands.retain(|command| !is_before(&command.expires_at, now));
+    }
+    report
+}
+
+// ============================================================================
+// Maintenance Ledger
+// ============================================================================
+
+/// Append-only record of real (non-dry) maintenance runs
+///
+/// Without a path (memory storage mode) runs are kept for this session only.
+#[derive(Debug, Default)]
+pub struct MaintenanceLedger {
+    path: Option<PathBuf>,
+    memory: Mutex<Vec<MaintenanceReportJson>>,
+}
+
+impl MaintenanceLedger {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            memory: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Record a run; dry runs are not recorded
+    pub fn record(&self, report: &MaintenanceReportJson) -> Result<(), String> {
+        if report.dry_run {
+            return Ok(());
+        }
+        let Some(path) = &self.path else {
+            self.memory
+                .lock()
+                .map_err(|e| e.to_string())?
+                .push(report.clone());
+            return Ok(());
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create maintenance directory: {}", e))?;
+        }
+        let line = serde_json::to_string(report)
+            .map_err(|e| format!("Failed to serialize maintenance report: {}", e))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open maintenance ledger: {}", e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Failed to write maintenance ledger: {}", e))
+    }
+
+    /// Past runs, oldest first
+    pub fn list(&self) -> Result<Vec<MaintenanceReportJson>, String> {
+        let Some(path) = &self.path else {
+            return Ok(self.memory.lock().map_err(|e| e.to_string())?.clone());
+        };
+
+        let file = match fs::File::open(path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to open maintenance ledger: {}", e)),
+        };
+        let mut reports = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(report) => reports.push(report),
+                Err(e) => eprintln!("[Asterisk Maintenance] Skipping malformed entry: {}", e),
+            }
+        }
+        Ok(reports)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AuditSummaryJson;
+
+    fn entry(id: &str, created_at: &str) -> AuditEntryJson {
+        AuditEntryJson {
+            id: id.to_string(),
+            created_at: created_at.to_string(),
+            url: "https://example.com/form".to_string(),
+            domain: "example.com".to_string(),
+            fingerprint: "fp".to_string(),
+            summary: AuditSummaryJson {
+                planned_count: 1,
+                applied_count: 1,
+                blocked_count: 0,
+                reviewed_count: 0,
+            },
+            items: vec![],
+            recall_recorded: false,
+            fill_command_id: None,
+            acceptance_id: None,
+            acceptance_hash: None,
+            plan_hash: None,
+            detail: Default::default(),
+        }
+    }
+
+    fn command(id: &str, expires_at: &str) -> FillCommandJson {
+        FillCommandJson {
+            id: id.to_string(),
+            target_domain: "example.com".to_string(),
+            target_url: None,
+            fills: vec![],
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            expires_at: expires_at.to_string(),
+            capture_undo: false,
+            plan_hash: None,
+        }
+    }
+
+    fn cutoff() -> DateTime<Utc> {
+        "2026-03-01T00:00:00Z".parse().unwrap()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "asterisk-maintenance-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_log(path: &Path) -> String {
+        let mut contents = String::new();
+        for e in [
+            entry("old-1", "2026-01-10T00:00:00Z"),
+            entry("new-1", "2026-04-01T00:00:00Z"),
+            entry("old-2", "2026-02-20T00:00:00Z"),
+        ] {
+            contents.push_str(&serde_json::to_string(&e).unwrap());
+            contents.push('\n');
+        }
+        contents.push_str("not json\n");
+        fs::write(path, &contents).unwrap();
+        contents
+    }
+
+    #[test]
+    fn test_audit_dry_run_never_mutates() {
+        let dir = temp_dir("dry");
+        let path = dir.join("audit.jsonl");
+        let before = write_log(&path);
+
+        let report = prune_audit_log(&path, cutoff(), true).unwrap();
+        assert!(report.dry_run);
+        assert_eq!(report.removed_ids, vec!["old-1", "old-2"]);
+        assert!(report.reclaimed_bytes > 0);
+        assert_eq!(fs::read_to_string(&path).unwrap(), before);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_audit_real_run_matches_dry_run() {
+        let dir = temp_dir("real");
+        let path = dir.join("audit.jsonl");
+        let before = write_log(&path);
+
+        let dry = prune_audit_log(&path, cutoff(), true).unwrap();
+        let real = prune_audit_log(&path, cutoff(), false).unwrap();
+        assert!(!real.dry_run);
+        assert_eq!(real.removed_ids, dry.removed_ids);
+        assert_eq!(real.reclaimed_bytes, dry.reclaimed_bytes);
+
+        let after = fs::read_to_string(&path).unwrap();
+        assert_eq!((before.len() - after.len()) as u64, real.reclaimed_bytes);
+        assert!(after.contains("new-1"));
+        assert!(after.contains("not json"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_in_memory_audit_prune() {
+        let mut entries = vec![
+            entry("old", "2026-01-10T00:00:00Z"),
+            entry("new", "2026-04-01T00:00:00Z"),
+        ];
+        let dry = prune_audit_entries(&mut entries, cutoff(), true);
+        assert_eq!(entries.len(), 2);
+
+        let real = prune_audit_entries(&mut entries, cutoff(), false);
+        assert_eq!(real.removed_ids, dry.removed_ids);
+        assert_eq!(real.reclaimed_bytes, dry.reclaimed_bytes);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, "new");
+    }
+
+    #[test]
+    fn test_expired_command_purge() {
+        let mut commands = vec![
+            command("expired", "2026-02-01T00:00:00Z"),
+            command("live", "2026-05-01T00:00:00Z"),
+        ];
+        let dry = purge_expired_commands(&mut commands, cutoff(), true);
+        assert_eq!(dry.removed_ids, vec!["expired"]);
+        assert_eq!(commands.len(), 2);
+
+        let real = purge_expired_commands(&mut commands, cutoff(), false);
+        assert_eq!(real.removed_ids, dry.removed_ids);
+        assert_eq!(real.reclaimed_bytes, dry.reclaimed_bytes);
+        assert_eq!(commands.len(), 1);
+    }
+
+    #[test]
+    fn test_ledger_records_real_runs_only() {
+        let dir = temp_dir("ledger");
+        let ledger = MaintenanceLedger::new(dir.join("maintenance.jsonl"));
+
+        let mut commands = vec![command("expired", "2026-02-01T00:00:00Z")];
+        ledger
+            .record(&purge_expired_commands(&mut commands, cutoff(), true))
+            .unwrap();
+        ledger
+            .record(&purge_expired_commands(&mut commands, cutoff(), false))
+            .unwrap();
+
+        let history = ledger.list().unwrap();
+        assert_eq!(history.len(), 1);
+        assert!(!history[0].dry_run);
+        assert_eq!(history[0].operation, FILL_COMMAND_PURGE);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
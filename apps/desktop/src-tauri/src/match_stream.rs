@@ -0,0 +1,301 @@
+/*!
+ * Streamed Matching
+ *
+ * `suggest_matches_stream` shows matches as they are found instead of after
+ * the slowest field. Each field of the snapshot is emitted exactly once, as
+ * a `StreamedMatchJson` carrying the stream id, in three stages:
+ * - `local`: every field the local tiers decided (matched, or not fillable),
+ *   all at once and in form order, since they cost nothing
+ * - `llm`: fields the local tiers left unmatched, each as soon as its
+ *   analysis answers
+ * - `unresolved`: whatever the LLM stage didn't get to (no provider
+ *   configured, or the session was superseded by a newer snapshot)
+ *
+ * LLM fields are analyzed one at a time through the usual session, so a
+ * newer snapshot from the same tab stops the stream between fields.
+ */
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::llm::{
+    self, AnalysisCache, AnalysisSession, FieldAnalysisJson, FieldAnalysisStatus, LlmProvider,
+    PromptRedaction,
+};
+use crate::matching::{FillPlanJson, MatchTier};
+use crate::FormSnapshotJson;
+
+/// Event raised for every field as its match is found
+pub const MATCH_STREAM_EVENT: &str = "match-stream-result";
+
+/// Which stage produced a streamed match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchSource {
+    /// A local rule, override, template or earlier step
+    Local,
+    /// The LLM's analysis
+    Llm,
+    /// Left unmatched locally and never analyzed
+    Unresolved,
+}
+
+/// One field's match, as emitted on `MATCH_STREAM_EVENT`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StreamedMatchJson {
+    #[serde(rename = "streamId")]
+    pub stream_id: String,
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    /// Absent when nothing matched
+    #[serde(rename = "vaultKey", skip_serializing_if = "Option::is_none")]
+    pub vault_key: Option<String>,
+    pub confidence: f64,
+    #[serde(rename = "matchTier", skip_serializing_if = "Option::is_none")]
+    pub match_tier: Option<MatchTier>,
+    pub source: MatchSource,
+}
+
+/// Result of `suggest_matches_stream`, once every field has been emitted
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MatchStreamSummaryJson {
+    #[serde(rename = "streamId")]
+    pub stream_id: String,
+    pub local: usize,
+    pub llm: usize,
+    pub unresolved: usize,
+    /// The LLM stage was cut short by a newer snapshot
+    pub superseded: bool,
+}
+
+/// What the LLM stage needs; absent when no provider is configured
+pub struct LlmStage<'a> {
+    pub provider: &'a dyn LlmProvider,
+    pub available_keys: &'a [String],
+    pub redaction: &'a PromptRedaction,
+    pub cache: &'a AnalysisCache,
+    pub session: &'a AnalysisSession,
+}
+
+/// Emit a match for every field of `snapshot`, local ones first
+pub async fn stream_matches(
+    stream_id: &str,
+    snapshot: &FormSnapshotJson,
+    plan: &FillPlanJson,
+    llm_stage: Option<LlmStage<'_>>,
+    emit: &mut (dyn FnMut(StreamedMatchJson) + Send),
+) -> MatchStreamSummaryJson {
+    let mut summary = MatchStreamSummaryJson {
+        stream_id: stream_id.to_string(),
+        ..Default::default()
+    };
+    let unmatched: HashSet<&str> = plan.unmatched_fields.iter().map(String::as_str).collect();
+    let mut emitted: HashSet<String> = HashSet::new();
+    let mut send = |json: StreamedMatchJson, summary: &mut MatchStreamSummaryJson| {
+        if !emitted.insert(json.field_id.clone()) {
+            return;
+        }
+        match json.source {
+            MatchSource::Local => summary.local += 1,
+            MatchSource::Llm => summary.llm += 1,
+            MatchSource::Unresolved => summary.unresolved += 1,
+        }
+        emit(json);
+    };
+    let unmatched_json = |field_id: &str, source: MatchSource| StreamedMatchJson {
+        stream_id: stream_id.to_string(),
+        field_id: field_id.to_string(),
+        vault_key: None,
+        confidence: 0.0,
+        match_tier: None,
+        source,
+    };
+
+    for field in &snapshot.fields {
+        if unmatched.contains(field.id.as_str()) {
+            continue;
+        }
+        let json = match plan.recommendations.iter().find(|r| r.field_id == field.id) {
+            Some(rec) => StreamedMatchJson {
+                stream_id: stream_id.to_string(),
+                field_id: field.id.clone(),
+                vault_key: Some(rec.vault_key.clone()),
+                confidence: rec.confidence,
+                match_tier: Some(rec.match_tier),
+                source: MatchSource::Local,
+            },
+            // Not fillable (passwords, checkboxes)
+            None => unmatched_json(&field.id, MatchSource::Local),
+        };
+        send(json, &mut summary);
+    }
+
+    if let Some(stage) = llm_stage {
+        let finished = {
+            let mut on_result = |analysis: FieldAnalysisJson| {
+                let mut json = unmatched_json(&analysis.field_id, MatchSource::Llm);
+                if let (FieldAnalysisStatus::Analyzed, Some(result)) =
+                    (analysis.status, &analysis.result)
+                {
+                    json.vault_key = result.vault_key.clone();
+                    json.confidence = result.confidence;
+                    json.match_tier = result.vault_key.is_some().then_some(MatchTier::Llm);
+                }
+                send(json, &mut summary);
+            };
+            llm::analyze_snapshot_streaming(
+                stage.provider,
+                snapshot,
+                &plan.unmatched_fields,
+                None,
+                stage.available_keys,
+                stage.redaction,
+                stage.cache,
+                stage.session,
+                &mut on_result,
+            )
+            .await
+        };
+        summary.superseded = finished.is_none();
+    }
+
+    // Already emitted fields are skipped by `send`
+    for field in &snapshot.fields {
+        send(
+            unmatched_json(&field.id, MatchSource::Unresolved),
+            &mut summary,
+        );
+    }
+    summary
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::MockProvider;
+    use crate::matching::generate_fill_plan;
+    use crate::{FieldNodeJson, FormFingerprintJson};
+    use asterisk_vault::{test_util, VaultCategory, VaultItem};
+    use chrono::Utc;
+
+    fn field(id: &str, label: &str, field_type: &str) -> FieldNodeJson {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": id,
+            "label": label,
+            "type": field_type,
+            "semantic": "",
+            "required": false,
+        }))
+        .unwrap()
+    }
+
+    fn item(key: &str, category: VaultCategory) -> VaultItem {
+        test_util::item(key, "value", category)
+    }
+
+    fn snapshot(fields: Vec<FieldNodeJson>) -> FormSnapshotJson {
+        FormSnapshotJson {
+            url: "https://example.com/form".to_string(),
+            domain: "example.com".to_string(),
+            title: "Form".to_string(),
+            captured_at: Utc::now().to_rfc3339(),
+            fingerprint: FormFingerprintJson {
+                field_count: fields.len() as u32,
+                field_types: vec![],
+                required_count: 0,
+                hash: "h".to_string(),
+            },
+            fields,
+            session: Default::default(),
+        }
+    }
+
+    fn form() -> FormSnapshotJson {
+        snapshot(vec![
+            field("work", "Where do you work?", "text"),
+            field("mail", "Email address", "email"),
+            field("pw", "Password", "password"),
+            field("pet", "Pet's name", "text"),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_every_field_emits_once_local_before_llm() {
+        let items = vec![
+            item("email", VaultCategory::Contact),
+            item("employer", VaultCategory::Custom),
+        ];
+        let form = form();
+        let plan = generate_fill_plan(&form, &items, None);
+        let provider = MockProvider::new(vec![
+            r#"{"vaultKey": "employer", "confidence": 0.9, "reasoning": "x"}"#.to_string(),
+            MockProvider::NO_MATCH.to_string(),
+        ]);
+        let keys = vec!["email".to_string(), "employer".to_string()];
+        let (redaction, cache) = (PromptRedaction::default(), AnalysisCache::new());
+        let session = AnalysisSession::detached();
+        let stage = LlmStage {
+            provider: &provider,
+            available_keys: &keys,
+            redaction: &redaction,
+            cache: &cache,
+            session: &session,
+        };
+
+        let mut emitted = Vec::new();
+        let summary =
+            stream_matches("s1", &form, &plan, Some(stage), &mut |m| emitted.push(m)).await;
+
+        let order: Vec<(&str, MatchSource)> = emitted
+            .iter()
+            .map(|m| (m.field_id.as_str(), m.source))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("mail", MatchSource::Local),
+                ("pw", MatchSource::Local),
+                ("work", MatchSource::Llm),
+                ("pet", MatchSource::Llm),
+            ]
+        );
+        assert!(emitted.iter().all(|m| m.stream_id == "s1"));
+        assert_eq!(emitted[0].vault_key.as_deref(), Some("email"));
+        assert_eq!(emitted[1].vault_key, None);
+        assert_eq!(emitted[2].vault_key.as_deref(), Some("employer"));
+        assert_eq!(emitted[2].match_tier, Some(MatchTier::Llm));
+        assert_eq!(emitted[3].vault_key, None);
+        assert_eq!((summary.local, summary.llm, summary.unresolved), (2, 2, 0));
+        assert!(!summary.superseded);
+    }
+
+    #[tokio::test]
+    async fn test_without_llm_unmatched_fields_are_unresolved() {
+        let form = form();
+        let plan = generate_fill_plan(&form, &[item("email", VaultCategory::Contact)], None);
+
+        let mut emitted = Vec::new();
+        let summary = stream_matches("s2", &form, &plan, None, &mut |m| emitted.push(m)).await;
+
+        let mut ids: Vec<&str> = emitted.iter().map(|m| m.field_id.as_str()).collect();
+        assert_eq!(ids.len(), form.fields.len());
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), form.fields.len());
+        assert_eq!(
+            emitted.iter().map(|m| m.source).collect::<Vec<_>>(),
+            vec![
+                MatchSource::Local,
+                MatchSource::Local,
+                MatchSource::Unresolved,
+                MatchSource::Unresolved,
+            ]
+        );
+        assert_eq!((summary.local, summary.llm, summary.unresolved), (2, 0, 2));
+    }
+}
@@ -0,0 +1,1352 @@
+/*!
+ * Local Form-to-Vault Matching
+ *
+ * Rust port of the tiered matcher in `@asterisk/core`:
+ * - The user's saved field overrides win outright (see the `overrides` module)
+ * - Tier 1: Autocomplete attributes (highest confidence)
+ * - Tier 2: Pattern matching on the field's label context (medium confidence)
+ * - In multi-step forms, fields still unmatched can reuse what the same
+ *   field was matched to in an earlier step (see the `sessions` module)
+ * - Fields asked again in another section are resolved by section context
+ *   (see the `duplicates` module)
+ *
+ * Fields left unmatched here are candidates for LLM analysis (Tier 3).
+ */
+
+use asterisk_vault::{ReviewStatus, VaultCategory, VaultItem};
+use serde::{Deserialize, Serialize};
+
+use crate::address;
+use crate::duplicates;
+use crate::i18n::{ids, Message, MessageJson};
+use crate::overrides::FieldOverrideJson;
+use crate::semantic::{canonicalize_semantic, SemanticType};
+use crate::templates::FormTemplateJson;
+use crate::{FieldNodeJson, FormSnapshotJson};
+
+// ============================================================================
+// Label Context Ranking
+// ============================================================================
+
+/// Where the text describing a field came from, in ranked order
+///
+/// Extensions can't always find a proper `<label>`; the fallbacks are
+/// progressively weaker evidence of what the field is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LabelSource {
+    Label,
+    AriaLabel,
+    Placeholder,
+    DescribedBy,
+    NearbyText,
+    Name,
+}
+
+impl LabelSource {
+    /// Multiplier applied to a match's confidence based on its label source
+    pub fn confidence_factor(self) -> f64 {
+        match self {
+            LabelSource::Label => 1.0,
+            LabelSource::AriaLabel => 0.98,
+            LabelSource::Placeholder => 0.96,
+            LabelSource::DescribedBy => 0.93,
+            LabelSource::NearbyText => 0.90,
+            LabelSource::Name => 0.88,
+        }
+    }
+
+    /// Human-readable name used in explanations and prompts
+    pub fn description(self) -> &'static str {
+        match self {
+            LabelSource::Label => "label",
+            LabelSource::AriaLabel => "aria-label",
+            LabelSource::Placeholder => "placeholder",
+            LabelSource::DescribedBy => "aria-describedby text",
+            LabelSource::NearbyText => "nearby text",
+            LabelSource::Name => "name attribute",
+        }
+    }
+
+    /// Catalog id of the reason given for a pattern match on this source
+    pub fn reason_id(self) -> &'static str {
+        match self {
+            LabelSource::Label => ids::MATCH_LABEL,
+            LabelSource::AriaLabel => ids::MATCH_ARIA_LABEL,
+            LabelSource::Placeholder => ids::MATCH_PLACEHOLDER,
+            LabelSource::DescribedBy => ids::MATCH_DESCRIBED_BY,
+            LabelSource::NearbyText => ids::MATCH_NEARBY_TEXT,
+            LabelSource::Name => ids::MATCH_NAME,
+        }
+    }
+}
+
+/// The text fragments available for describing a field
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LabelContext<'a> {
+    pub label: Option<&'a str>,
+    pub aria_label: Option<&'a str>,
+    pub placeholder: Option<&'a str>,
+    pub described_by: Option<&'a str>,
+    pub nearby_text: Option<&'a str>,
+    pub name: Option<&'a str>,
+}
+
+impl<'a> LabelContext<'a> {
+    /// Build the label context for a snapshot field
+    pub fn from_field(field: &'a FieldNodeJson) -> Self {
+        Self {
+            label: Some(field.label.as_str()),
+            aria_label: field.aria_label.as_deref(),
+            placeholder: field.placeholder.as_deref(),
+            described_by: field.described_by.as_deref(),
+            nearby_text: field.nearby_text.as_deref(),
+            name: Some(field.name.as_str()),
+        }
+    }
+
+    /// Non-empty context fragments, best source first
+    pub fn ranked(&self) -> Vec<(LabelSource, &'a str)> {
+        [
+            (LabelSource::Label, self.label),
+            (LabelSource::AriaLabel, self.aria_label),
+            (LabelSource::Placeholder, self.placeholder),
+            (LabelSource::DescribedBy, self.described_by),
+            (LabelSource::NearbyText, self.nearby_text),
+            (LabelSource::Name, self.name),
+        ]
+        .into_iter()
+        .filter_map(|(source, text)| {
+            let text = text?.trim();
+            (!text.is_empty()).then_some((source, text))
+        })
+        .collect()
+    }
+
+    /// The best available description of the field, if any
+    pub fn best(&self) -> Option<(LabelSource, &'a str)> {
+        self.ranked().into_iter().next()
+    }
+}
+
+// ============================================================================
+// Matching Rules (mirror AUTOCOMPLETE_MAPPINGS / PATTERN_RULES in types.ts)
+// ============================================================================
+
+/// Mapping from an HTML autocomplete token to vault data
+struct AutocompleteMapping {
+    token: &'static str,
+    category: VaultCategory,
+    key_pattern: &'static str,
+    confidence: f64,
+}
+
+const fn ac(
+    token: &'static str,
+    category: VaultCategory,
+    key_pattern: &'static str,
+    confidence: f64,
+) -> AutocompleteMapping {
+    AutocompleteMapping {
+        token,
+        category,
+        key_pattern,
+        confidence,
+    }
+}
+
+const AUTOCOMPLETE_MAPPINGS: &[AutocompleteMapping] = &[
+    // Identity
+    ac("given-name", VaultCategory::Identity, "firstName", 0.95),
+    ac("family-name", VaultCategory::Identity, "lastName", 0.95),
+    ac("name", VaultCategory::Identity, "name", 0.90),
+    ac("honorific-prefix", VaultCategory::Identity, "prefix", 0.95),
+    ac("honorific-suffix", VaultCategory::Identity, "suffix", 0.95),
+    ac("nickname", VaultCategory::Identity, "nickname", 0.95),
+    ac("bday", VaultCategory::Identity, "birthday", 0.95),
+    ac("sex", VaultCategory::Identity, "gender", 0.95),
+    // Contact
+    ac("email", VaultCategory::Contact, "email", 0.95),
+    ac("tel", VaultCategory::Contact, "phone", 0.95),
+    ac("tel-national", VaultCategory::Contact, "phone", 0.95),
+    ac("url", VaultCategory::Contact, "website", 0.90),
+    // Address
+    ac("street-address", VaultCategory::Address, "street", 0.95),
+    ac("address-line1", VaultCategory::Address, "address1", 0.95),
+    ac("address-line2", VaultCategory::Address, "address2", 0.95),
+    ac("address-level1", VaultCategory::Address, "state", 0.95),
+    ac("address-level2", VaultCategory::Address, "city", 0.95),
+    ac("postal-code", VaultCategory::Address, "zip", 0.95),
+    ac("country", VaultCategory::Address, "country", 0.95),
+    ac("country-name", VaultCategory::Address, "country", 0.95),
+    // Financial
+    ac("cc-name", VaultCategory::Financial, "cardName", 0.95),
+    ac("cc-number", VaultCategory::Financial, "cardNumber", 0.95),
+    ac("cc-exp", VaultCategory::Financial, "cardExpiry", 0.95),
+    ac(
+        "cc-exp-month",
+        VaultCategory::Financial,
+        "expiryMonth",
+        0.95,
+    ),
+    ac("cc-exp-year", VaultCategory::Financial, "expiryYear", 0.95),
+    ac("cc-csc", VaultCategory::Financial, "cvv", 0.95),
+    ac("cc-type", VaultCategory::Financial, "cardType", 0.95),
+    // Organization
+    ac("organization", VaultCategory::Identity, "company", 0.90),
+    ac(
+        "organization-title",
+        VaultCategory::Identity,
+        "jobTitle",
+        0.90,
+    ),
+];
+
+/// Pattern-based rule for Tier 2 matching
+struct PatternRule {
+    label_patterns: &'static [&'static str],
+    input_type: Option<&'static str>,
+    category: VaultCategory,
+    key_pattern: &'static str,
+    confidence: f64,
+}
+
+const PATTERN_RULES: &[PatternRule] = &[
+    // Identity
+    PatternRule {
+        label_patterns: &["first name", "firstname", "given name"],
+        input_type: None,
+        category: VaultCategory::Identity,
+        key_pattern: "firstName",
+        confidence: 0.85,
+    },
+    PatternRule {
+        label_patterns: &["last name", "lastname", "family name", "surname"],
+        input_type: None,
+        category: VaultCategory::Identity,
+        key_pattern: "lastName",
+        confidence: 0.85,
+    },
+    PatternRule {
+        label_patterns: &["full name", "your name"],
+        input_type: None,
+        category: VaultCategory::Identity,
+        key_pattern: "name",
+        confidence: 0.80,
+    },
+    // Contact
+    PatternRule {
+        label_patterns: &["email", "e-mail"],
+        input_type: Some("email"),
+        category: VaultCategory::Contact,
+        key_pattern: "email",
+        confidence: 0.90,
+    },
+    PatternRule {
+        label_patterns: &["phone", "mobile", "cell", "telephone"],
+        input_type: Some("tel"),
+        category: VaultCategory::Contact,
+        key_pattern: "phone",
+        confidence: 0.85,
+    },
+    // Address
+    PatternRule {
+        label_patterns: &["street", "address line"],
+        input_type: None,
+        category: VaultCategory::Address,
+        key_pattern: "street",
+        confidence: 0.80,
+    },
+    PatternRule {
+        label_patterns: &["city", "town"],
+        input_type: None,
+        category: VaultCategory::Address,
+        key_pattern: "city",
+        confidence: 0.85,
+    },
+    PatternRule {
+        label_patterns: &["state", "province", "region"],
+        input_type: None,
+        category: VaultCategory::Address,
+        key_pattern: "state",
+        confidence: 0.85,
+    },
+    PatternRule {
+        label_patterns: &["zip", "postal", "postcode"],
+        input_type: None,
+        category: VaultCategory::Address,
+        key_pattern: "zip",
+        confidence: 0.85,
+    },
+    PatternRule {
+        label_patterns: &["country"],
+        input_type: None,
+        category: VaultCategory::Address,
+        key_pattern: "country",
+        confidence: 0.85,
+    },
+    // Organization
+    PatternRule {
+        label_patterns: &["company", "organization", "employer"],
+        input_type: None,
+        category: VaultCategory::Identity,
+        key_pattern: "company",
+        confidence: 0.80,
+    },
+    PatternRule {
+        label_patterns: &["job title", "position", "role"],
+        input_type: None,
+        category: VaultCategory::Identity,
+        key_pattern: "jobTitle",
+        confidence: 0.80,
+    },
+];
+
+// ============================================================================
+// Match Types (mirror FillRecommendation / FillPlan in types.ts)
+// ============================================================================
+
+/// How a match was determined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchTier {
+    /// The user's saved override for this field
+    Override,
+    /// Resolved from a saved form template
+    Template,
+    Autocomplete,
+    Pattern,
+    /// Carried over from an earlier step of the same multi-step form
+    Session,
+    Llm,
+}
+
+/// A recommendation for filling a specific field
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FillRecommendationJson {
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    #[serde(rename = "vaultKey")]
+    pub vault_key: String,
+    pub confidence: f64,
+    /// Human-readable explanation
+    pub reason: String,
+    /// `reason` as a catalog message; absent for LLM reasoning
+    #[serde(
+        rename = "reasonMessage",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub reason_message: Option<MessageJson>,
+    pub required: bool,
+    #[serde(rename = "matchTier")]
+    pub match_tier: MatchTier,
+    /// Which piece of field context produced a pattern match
+    #[serde(rename = "labelSource", skip_serializing_if = "Option::is_none")]
+    pub label_source: Option<LabelSource>,
+    /// Value composed from several vault items, used instead of the value of
+    /// `vault_key` (e.g. a whole address for a single address field)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub value: Option<String>,
+}
+
+/// Fields at or above this confidence are applied without review
+/// (mirrors `SAFE_AUTO_THRESHOLD` in fillplan/confidence.ts)
+pub const SAFE_AUTO_THRESHOLD: f64 = 0.98;
+
+/// Fields below this confidence are blocked by default
+pub const REVIEW_THRESHOLD: f64 = 0.90;
+
+/// Highest confidence a match to an unreviewed vault item can have, so it
+/// is at most Review and never applied without the user seeing it
+pub const UNREVIEWED_CONFIDENCE_CAP: f64 = 0.95;
+
+/// A complete plan for filling a form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FillPlanJson {
+    /// Set when the plan is kept for acceptance (see the `acceptance` module)
+    #[serde(rename = "planId", skip_serializing_if = "Option::is_none", default)]
+    pub plan_id: Option<String>,
+    /// Content hash, the same for identical plans however often they are
+    /// regenerated; set alongside `plan_id` (see `acceptance::plan_hash`)
+    #[serde(rename = "planHash", skip_serializing_if = "Option::is_none", default)]
+    pub plan_hash: Option<String>,
+    #[serde(rename = "formFingerprint")]
+    pub form_fingerprint: String,
+    #[serde(rename = "formId")]
+    pub form_id: String,
+    pub recommendations: Vec<FillRecommendationJson>,
+    #[serde(rename = "unmatchedFields")]
+    pub unmatched_fields: Vec<String>,
+    #[serde(rename = "overallConfidence")]
+    pub overall_confidence: f64,
+    #[serde(rename = "generatedAt")]
+    pub generated_at: String,
+    #[serde(rename = "requiredFieldsCovered")]
+    pub required_fields_covered: u32,
+    #[serde(rename = "totalRequiredFields")]
+    pub total_required_fields: u32,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub warnings: Vec<String>,
+}
+
+// ============================================================================
+// Matching
+// ============================================================================
+
+/// Confidence assigned to matches resolved from a saved template
+pub const TEMPLATE_CONFIDENCE: f64 = 0.97;
+
+/// Field types that should never be autofilled
+const SKIP_TYPES: &[&str] = &["password", "checkbox", "radio"];
+
+/// Find a vault item in `category` whose key (or failing that, label) contains `key_pattern`
+fn find_vault_item<'a>(
+    vault_items: &'a [VaultItem],
+    category: &VaultCategory,
+    key_pattern: &str,
+) -> Option<&'a VaultItem> {
+    let pattern = key_pattern.to_lowercase();
+    vault_items
+        .iter()
+        .find(|item| &item.category == category && item.key.to_lowercase().contains(&pattern))
+        .or_else(|| {
+            vault_items.iter().find(|item| {
+                &item.category == category && item.label.to_lowercase().contains(&pattern)
+            })
+        })
+}
+
+/// Normalize text for pattern matching
+fn normalize_text(text: &str) -> String {
+    text.to_lowercase()
+        .replace(['_', '-'], " ")
+        .trim()
+        .to_string()
+}
+
+/// Match a field using its autocomplete attribute (Tier 1)
+pub fn match_by_autocomplete(
+    field: &FieldNodeJson,
+    vault_items: &[VaultItem],
+) -> Option<FillRecommendationJson> {
+    let autocomplete = field.autocomplete.as_deref()?;
+
+    // Autocomplete can be space-separated tokens ("shipping given-name"); take the last
+    let token = autocomplete.split_whitespace().last()?.to_lowercase();
+    let mapping = AUTOCOMPLETE_MAPPINGS.iter().find(|m| m.token == token)?;
+    let item = find_vault_item(vault_items, &mapping.category, mapping.key_pattern)?;
+    let reason = Message::new(ids::MATCH_AUTOCOMPLETE)
+        .with("token", &token)
+        .to_json();
+
+    Some(FillRecommendationJson {
+        field_id: field.id.clone(),
+        vault_key: item.key.clone(),
+        confidence: mapping.confidence,
+        reason: reason.message.clone(),
+        reason_message: Some(reason),
+        required: field.required,
+        match_tier: MatchTier::Autocomplete,
+        label_source: None,
+        value: None,
+    })
+}
+
+/// Match a field using its label context (Tier 2)
+///
+/// Context is tried in ranked order (label > aria-label > placeholder >
+/// describedby > nearby text > name); the first fragment that matches a rule
+/// wins and its confidence is discounted by how weak that source is.
+pub fn match_by_pattern(
+    field: &FieldNodeJson,
+    vault_items: &[VaultItem],
+) -> Option<FillRecommendationJson> {
+    for (source, text) in LabelContext::from_field(field).ranked() {
+        let normalized = normalize_text(text);
+
+        for rule in PATTERN_RULES {
+            let Some(pattern) = rule.label_patterns.iter().find(|p| normalized.contains(*p)) else {
+                continue;
+            };
+            if rule.input_type.is_some_and(|t| t != field.field_type) {
+                continue;
+            }
+            let Some(item) = find_vault_item(vault_items, &rule.category, rule.key_pattern) else {
+                continue;
+            };
+
+            let reason = Message::new(source.reason_id())
+                .with("text", text)
+                .with("pattern", pattern)
+                .to_json();
+            return Some(FillRecommendationJson {
+                field_id: field.id.clone(),
+                vault_key: item.key.clone(),
+                confidence: rule.confidence * source.confidence_factor(),
+                reason: reason.message.clone(),
+                reason_message: Some(reason),
+                required: field.required,
+                match_tier: MatchTier::Pattern,
+                label_source: Some(source),
+                value: None,
+            });
+        }
+    }
+
+    None
+}
+
+/// Vault keys a bare name (an import column header, say) suggests, best first
+///
+/// Applies the Tier 1 autocomplete tokens as exact names and the Tier 2
+/// label patterns. Input-type constraints are ignored, since a bare name has
+/// no input type.
+pub fn keys_for_name(name: &str) -> Vec<(&'static str, VaultCategory, f64)> {
+    let normalized = normalize_text(name);
+    let mut keys: Vec<(&'static str, VaultCategory, f64)> = Vec::new();
+    let exact = AUTOCOMPLETE_MAPPINGS
+        .iter()
+        .filter(|m| normalize_text(m.token) == normalized)
+        .map(|m| (m.key_pattern, m.category.clone(), m.confidence));
+    let patterns = PATTERN_RULES
+        .iter()
+        .filter(|r| r.label_patterns.iter().any(|p| normalized.contains(p)))
+        .map(|r| (r.key_pattern, r.category.clone(), r.confidence));
+    for (key, category, confidence) in exact.chain(patterns) {
+        if !keys.iter().any(|(k, _, _)| *k == key) {
+            keys.push((key, category, confidence));
+        }
+    }
+    keys.sort_by(|a, b| b.2.total_cmp(&a.2));
+    keys
+}
+
+/// Classify a single field against the vault using the local tiers
+pub fn classify_field(
+    field: &FieldNodeJson,
+    vault_items: &[VaultItem],
+) -> Option<FillRecommendationJson> {
+    match_by_autocomplete(field, vault_items).or_else(|| match_by_pattern(field, vault_items))
+}
+
+/// Confidence of a match forced by a user's field override
+pub const OVERRIDE_CONFIDENCE: f64 = 1.0;
+
+/// Resolve a field from the user's override for it, if the key still exists
+fn match_by_override(
+    field: &FieldNodeJson,
+    vault_items: &[VaultItem],
+    overrides: &[FieldOverrideJson],
+) -> Option<FillRecommendationJson> {
+    let entry = overrides.iter().find(|o| o.field_id == field.id)?;
+    let item = vault_items.iter().find(|i| i.key == entry.vault_key)?;
+    let reason = Message::new(ids::MATCH_OVERRIDE).to_json();
+
+    Some(FillRecommendationJson {
+        field_id: field.id.clone(),
+        vault_key: item.key.clone(),
+        confidence: OVERRIDE_CONFIDENCE,
+        reason: reason.message.clone(),
+        reason_message: Some(reason),
+        required: field.required,
+        match_tier: MatchTier::Override,
+        label_source: None,
+        value: None,
+    })
+}
+
+/// Resolve a field from a saved template mapping
+///
+/// Suspect mappings and mappings to keys no longer in the vault are skipped,
+/// so the field falls through to the regular tiers.
+fn match_by_template(
+    field: &FieldNodeJson,
+    vault_items: &[VaultItem],
+    template: &FormTemplateJson,
+) -> Option<FillRecommendationJson> {
+    let mapping = template.mapping_for(&field.id)?;
+    let item = vault_items.iter().find(|i| i.key == mapping.vault_key)?;
+    let reason = Message::new(ids::MATCH_TEMPLATE).to_json();
+
+    Some(FillRecommendationJson {
+        field_id: field.id.clone(),
+        vault_key: item.key.clone(),
+        confidence: TEMPLATE_CONFIDENCE,
+        reason: reason.message.clone(),
+        reason_message: Some(reason),
+        required: field.required,
+        match_tier: MatchTier::Template,
+        label_source: None,
+        value: None,
+    })
+}
+
+/// Confidence assigned to addresses composed from vault components
+const COMPOSED_ADDRESS_CONFIDENCE: f64 = 0.85;
+
+/// Whether a field asks for the whole address rather than one component
+fn wants_full_address(field: &FieldNodeJson) -> bool {
+    canonicalize_semantic(&field.semantic) == SemanticType::FullAddress
+}
+
+/// Whether a field is the form's country field
+fn is_country_field(field: &FieldNodeJson) -> bool {
+    canonicalize_semantic(&field.semantic) == SemanticType::Country
+        || field
+            .autocomplete
+            .as_deref()
+            .and_then(|a| a.split_whitespace().last())
+            .is_some_and(|t| t == "country" || t == "country-name")
+}
+
+/// Fill a whole-address field from the vault's address components
+///
+/// The components are ordered for the vault's country, or failing that the
+/// country already chosen in the form's country select. When the form has
+/// its own country field the country is left out of the address.
+fn match_full_address(
+    field: &FieldNodeJson,
+    snapshot: &FormSnapshotJson,
+    vault_items: &[VaultItem],
+) -> Option<FillRecommendationJson> {
+    if !wants_full_address(field) {
+        return None;
+    }
+    let (street_key, mut components) = address::vault_components(vault_items)?;
+
+    let country_field = snapshot.fields.iter().find(|f| is_country_field(f));
+    let form_country = country_field.and_then(|f| {
+        let selected = f.selected_value.as_deref()?;
+        // Prefer whichever of value or option label names a known country
+        let label = f
+            .options
+            .iter()
+            .flatten()
+            .find(|o| o.value == selected)
+            .map(|o| o.label.as_str());
+        [Some(selected), label]
+            .into_iter()
+            .flatten()
+            .find(|c| address::country_code(c).is_some())
+            .or(Some(selected))
+            .map(str::to_string)
+    });
+    let country = components
+        .country
+        .clone()
+        .or(form_country)
+        .unwrap_or_default();
+    if country_field.is_some() {
+        components.country = None;
+    }
+
+    let text = if field.field_type == "textarea" {
+        address::address_lines(&components, &country).join("\n")
+    } else {
+        address::format_address(&components, &country)
+    };
+    let locale = address::country_code(&country).unwrap_or("generic");
+    let reason = Message::new(ids::MATCH_COMPOSED_ADDRESS)
+        .with("format", locale)
+        .to_json();
+
+    Some(FillRecommendationJson {
+        field_id: field.id.clone(),
+        vault_key: street_key,
+        confidence: COMPOSED_ADDRESS_CONFIDENCE,
+        reason: reason.message.clone(),
+        reason_message: Some(reason),
+        required: field.required,
+        match_tier: MatchTier::Pattern,
+        label_source: None,
+        value: Some(text),
+    })
+}
+
+/// Whether local matching considers `field` at all
+pub fn is_fillable(field: &FieldNodeJson) -> bool {
+    !SKIP_TYPES.contains(&field.field_type.as_str())
+}
+
+/// Run one field through the local tiers: template, composed address, then
+/// autocomplete and pattern classification
+pub fn match_field(
+    field: &FieldNodeJson,
+    snapshot: &FormSnapshotJson,
+    vault_items: &[VaultItem],
+    template: Option<&FormTemplateJson>,
+) -> Option<FillRecommendationJson> {
+    template
+        .and_then(|t| match_by_template(field, vault_items, t))
+        .or_else(|| match_full_address(field, snapshot, vault_items))
+        .or_else(|| classify_field(field, vault_items))
+}
+
+/// Generate a fill plan for a snapshot using the local tiers
+///
+/// When a template is saved for the form, its healthy mappings take
+/// precedence over the matching tiers.
+pub fn generate_fill_plan(
+    snapshot: &FormSnapshotJson,
+    vault_items: &[VaultItem],
+    template: Option<&FormTemplateJson>,
+) -> FillPlanJson {
+    generate_fill_plan_with_overrides(snapshot, vault_items, template, &[])
+}
+
+/// Confidence of a match carried over from an earlier step
+pub const SESSION_CONFIDENCE: f64 = 0.85;
+
+/// An earlier step of a multi-step form and the plan generated for it
+pub struct EarlierStep<'a> {
+    pub snapshot: &'a FormSnapshotJson,
+    pub plan: &'a FillPlanJson,
+}
+
+/// Whether two fields ask for the same thing: same name, label or
+/// specific autocomplete token
+fn same_question(a: &FieldNodeJson, b: &FieldNodeJson) -> bool {
+    let same = |x: &str, y: &str| {
+        let x = normalize_text(x);
+        !x.is_empty() && x == normalize_text(y)
+    };
+    let specific = |token: &Option<String>| {
+        token
+            .as_deref()
+            .filter(|t| !matches!(normalize_text(t).as_str(), "on" | "off"))
+            .map(str::to_string)
+    };
+    same(&a.name, &b.name)
+        || same(&a.label, &b.label)
+        || matches!(
+            (specific(&a.autocomplete), specific(&b.autocomplete)),
+            (Some(x), Some(y)) if same(&x, &y)
+        )
+}
+
+/// Resolve a field the way the same field was resolved in an earlier step
+fn match_by_session(
+    field: &FieldNodeJson,
+    vault_items: &[VaultItem],
+    earlier_steps: &[EarlierStep<'_>],
+) -> Option<FillRecommendationJson> {
+    // Most recent step first
+    let (step, earlier) = earlier_steps.iter().rev().find_map(|earlier| {
+        earlier.plan.recommendations.iter().find_map(|r| {
+            let earlier_field = earlier
+                .snapshot
+                .fields
+                .iter()
+                .find(|f| f.id == r.field_id)?;
+            same_question(field, earlier_field).then_some((earlier.snapshot.session.step, r))
+        })
+    })?;
+    let item = vault_items.iter().find(|i| i.key == earlier.vault_key)?;
+    let reason = match step {
+        Some(step) => Message::new(ids::MATCH_SESSION_STEP).with("step", step),
+        None => Message::new(ids::MATCH_SESSION),
+    }
+    .to_json();
+
+    Some(FillRecommendationJson {
+        field_id: field.id.clone(),
+        vault_key: item.key.clone(),
+        confidence: SESSION_CONFIDENCE.min(earlier.confidence),
+        reason: reason.message.clone(),
+        reason_message: Some(reason),
+        required: field.required,
+        match_tier: MatchTier::Session,
+        label_source: None,
+        value: None,
+    })
+}
+
+/// Generate a fill plan, resolving fields with a saved override first
+///
+/// `overrides` are the user's overrides for this form; an override whose
+/// vault key no longer exists is ignored.
+pub fn generate_fill_plan_with_overrides(
+    snapshot: &FormSnapshotJson,
+    vault_items: &[VaultItem],
+    template: Option<&FormTemplateJson>,
+    overrides: &[FieldOverrideJson],
+) -> FillPlanJson {
+    generate_session_fill_plan(snapshot, vault_items, template, overrides, &[])
+}
+
+/// Generate a fill plan for one step of a multi-step form
+///
+/// Fields the local tiers leave unmatched are resolved like the same field
+/// in `earlier_steps`, so a question answered in step 1 isn't left open
+/// when a later step asks it again.
+pub fn generate_session_fill_plan(
+    snapshot: &FormSnapshotJson,
+    vault_items: &[VaultItem],
+    template: Option<&FormTemplateJson>,
+    overrides: &[FieldOverrideJson],
+    earlier_steps: &[EarlierStep<'_>],
+) -> FillPlanJson {
+    // Rejected items are never matched
+    let eligible: Vec<VaultItem>;
+    let vault_items = if vault_items
+        .iter()
+        .any(|i| i.review_status == ReviewStatus::Rejected)
+    {
+        eligible = vault_items
+            .iter()
+            .filter(|i| i.review_status != ReviewStatus::Rejected)
+            .cloned()
+            .collect();
+        eligible.as_slice()
+    } else {
+        vault_items
+    };
+
+    let mut recommendations = Vec::new();
+    let mut unmatched_fields = Vec::new();
+
+    let fillable: Vec<&FieldNodeJson> = snapshot.fields.iter().filter(|f| is_fillable(f)).collect();
+
+    for field in &fillable {
+        let matched = match_by_override(field, vault_items, overrides)
+            .or_else(|| match_field(field, snapshot, vault_items, template))
+            .or_else(|| match_by_session(field, vault_items, earlier_steps));
+        match matched {
+            Some(recommendation) => recommendations.push(recommendation),
+            None => unmatched_fields.push(field.id.clone()),
+        }
+    }
+    duplicates::resolve_duplicates(&snapshot.fields, vault_items, &mut recommendations);
+    for recommendation in &mut recommendations {
+        let unreviewed = vault_items.iter().any(|i| {
+            i.key == recommendation.vault_key && i.review_status == ReviewStatus::Unreviewed
+        });
+        if unreviewed {
+            recommendation.confidence = recommendation.confidence.min(UNREVIEWED_CONFIDENCE_CAP);
+        }
+    }
+
+    let required: Vec<&&FieldNodeJson> = fillable.iter().filter(|f| f.required).collect();
+    let required_fields_covered = required
+        .iter()
+        .filter(|f| recommendations.iter().any(|r| r.field_id == f.id))
+        .count() as u32;
+    let total_required_fields = required.len() as u32;
+
+    let overall_confidence = if recommendations.is_empty() {
+        0.0
+    } else {
+        recommendations.iter().map(|r| r.confidence).sum::<f64>() / recommendations.len() as f64
+    };
+
+    let mut warnings = Vec::new();
+    if required_fields_covered < total_required_fields {
+        warnings.push(format!(
+            "{} required field(s) could not be matched",
+            total_required_fields - required_fields_covered
+        ));
+    }
+    if vault_items.is_empty() {
+        warnings.push("No vault items available for matching".to_string());
+    }
+
+    FillPlanJson {
+        plan_id: None,
+        plan_hash: None,
+        form_fingerprint: snapshot.fingerprint.hash.clone(),
+        form_id: snapshot.url.clone(),
+        recommendations,
+        unmatched_fields,
+        overall_confidence,
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        required_fields_covered,
+        total_required_fields,
+        warnings,
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asterisk_vault::test_util;
+    use chrono::Utc;
+
+    fn item(key: &str, category: VaultCategory) -> VaultItem {
+        test_util::item(key, "value", category)
+    }
+
+    fn vault() -> Vec<VaultItem> {
+        vec![
+            item("firstName", VaultCategory::Identity),
+            item("email", VaultCategory::Contact),
+            item("phone", VaultCategory::Contact),
+            item("city", VaultCategory::Address),
+        ]
+    }
+
+    fn field(id: &str, field_type: &str) -> FieldNodeJson {
+        FieldNodeJson {
+            id: id.to_string(),
+            name: String::new(),
+            label: String::new(),
+            field_type: field_type.to_string(),
+            semantic: "unknown".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_autocomplete_match() {
+        let mut f = field("f1", "text");
+        f.autocomplete = Some("shipping given-name".to_string());
+        let rec = match_by_autocomplete(&f, &vault()).unwrap();
+        assert_eq!(rec.vault_key, "firstName");
+        assert_eq!(rec.match_tier, MatchTier::Autocomplete);
+        assert_eq!(rec.confidence, 0.95);
+    }
+
+    #[test]
+    fn test_pattern_respects_input_type() {
+        let mut f = field("f1", "text");
+        f.label = "Email".to_string();
+        assert!(match_by_pattern(&f, &vault()).is_none());
+
+        f.field_type = "email".to_string();
+        assert_eq!(match_by_pattern(&f, &vault()).unwrap().vault_key, "email");
+    }
+
+    #[test]
+    fn test_fallback_label() {
+        let mut f = field("f1", "text");
+        f.label = "City".to_string();
+        f.aria_label = Some("First name".to_string());
+        let rec = match_by_pattern(&f, &vault()).unwrap();
+        assert_eq!(rec.vault_key, "city");
+        assert_eq!(rec.label_source, Some(LabelSource::Label));
+        assert_eq!(rec.confidence, 0.85);
+    }
+
+    #[test]
+    fn test_fallback_aria_label() {
+        let mut f = field("f1", "text");
+        f.aria_label = Some("City".to_string());
+        f.placeholder = Some("First name".to_string());
+        let rec = match_by_pattern(&f, &vault()).unwrap();
+        assert_eq!(rec.vault_key, "city");
+        assert_eq!(rec.label_source, Some(LabelSource::AriaLabel));
+        assert!(rec.reason.contains("aria-label"));
+    }
+
+    #[test]
+    fn test_fallback_placeholder() {
+        let mut f = field("f1", "text");
+        f.placeholder = Some("Town".to_string());
+        f.described_by = Some("First name".to_string());
+        let rec = match_by_pattern(&f, &vault()).unwrap();
+        assert_eq!(rec.vault_key, "city");
+        assert_eq!(rec.label_source, Some(LabelSource::Placeholder));
+    }
+
+    #[test]
+    fn test_fallback_described_by() {
+        let mut f = field("f1", "text");
+        f.described_by = Some("Enter your city".to_string());
+        f.nearby_text = Some("First name".to_string());
+        let rec = match_by_pattern(&f, &vault()).unwrap();
+        assert_eq!(rec.vault_key, "city");
+        assert_eq!(rec.label_source, Some(LabelSource::DescribedBy));
+    }
+
+    #[test]
+    fn test_fallback_nearby_text() {
+        let mut f = field("f1", "text");
+        f.nearby_text = Some("City".to_string());
+        f.name = "given_name".to_string();
+        let rec = match_by_pattern(&f, &vault()).unwrap();
+        assert_eq!(rec.vault_key, "city");
+        assert_eq!(rec.label_source, Some(LabelSource::NearbyText));
+    }
+
+    #[test]
+    fn test_fallback_name() {
+        let mut f = field("f1", "text");
+        f.name = "first_name".to_string();
+        let rec = match_by_pattern(&f, &vault()).unwrap();
+        assert_eq!(rec.vault_key, "firstName");
+        assert_eq!(rec.label_source, Some(LabelSource::Name));
+        assert!(rec.reason.contains("name attribute"));
+    }
+
+    #[test]
+    fn test_each_heuristic_explains_itself() {
+        let mut by_autocomplete = field("f1", "text");
+        by_autocomplete.autocomplete = Some("shipping tel".to_string());
+        let mut by_label = field("f2", "text");
+        by_label.label = "Mobile number".to_string();
+        by_label.field_type = "tel".to_string();
+        let mut by_placeholder = field("f3", "email");
+        by_placeholder.placeholder = Some("Your email".to_string());
+        let mut by_name = field("f4", "text");
+        by_name.name = "home_town".to_string();
+
+        let reasons: Vec<String> = [&by_autocomplete, &by_label, &by_placeholder, &by_name]
+            .into_iter()
+            .map(|f| classify_field(f, &vault()).unwrap().reason)
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                "Matched by autocomplete=tel",
+                "The label \"Mobile number\" contains \"mobile\"",
+                "The placeholder \"Your email\" contains \"email\"",
+                "The name attribute \"home_town\" contains \"town\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_low_ranked_context_discounted() {
+        let mut by_label = field("f1", "text");
+        by_label.label = "City".to_string();
+        let mut by_nearby = field("f2", "text");
+        by_nearby.nearby_text = Some("City".to_string());
+
+        let label_conf = match_by_pattern(&by_label, &vault()).unwrap().confidence;
+        let nearby_conf = match_by_pattern(&by_nearby, &vault()).unwrap().confidence;
+        assert!(nearby_conf < label_conf);
+    }
+
+    #[test]
+    fn test_blank_context_skipped() {
+        let mut f = field("f1", "text");
+        f.label = "   ".to_string();
+        f.aria_label = Some("City".to_string());
+        let ctx = LabelContext::from_field(&f);
+        assert_eq!(ctx.best(), Some((LabelSource::AriaLabel, "City")));
+    }
+
+    #[test]
+    fn test_old_snapshot_fields_still_parse() {
+        let json = r#"{"id":"f1","name":"email","label":"Email","type":"email","semantic":"email","required":true}"#;
+        let f: FieldNodeJson = serde_json::from_str(json).unwrap();
+        assert!(f.aria_label.is_none());
+        assert!(f.nearby_text.is_none());
+    }
+
+    #[test]
+    fn test_generate_fill_plan() {
+        let mut email = field("email", "email");
+        email.label = "Email".to_string();
+        email.required = true;
+        let mut password = field("pw", "password");
+        password.label = "Password".to_string();
+        let mut other = field("other", "text");
+        other.label = "Favourite colour".to_string();
+        other.required = true;
+
+        let snapshot = FormSnapshotJson {
+            url: "https://example.com/signup".to_string(),
+            domain: "example.com".to_string(),
+            title: "Sign up".to_string(),
+            captured_at: Utc::now().to_rfc3339(),
+            fingerprint: crate::FormFingerprintJson {
+                field_count: 3,
+                field_types: vec![],
+                required_count: 2,
+                hash: "abc".to_string(),
+            },
+            fields: vec![email, password, other],
+            session: Default::default(),
+        };
+
+        let plan = generate_fill_plan(&snapshot, &vault(), None);
+        assert_eq!(plan.recommendations.len(), 1);
+        assert_eq!(plan.unmatched_fields, vec!["other".to_string()]);
+        assert_eq!(plan.required_fields_covered, 1);
+        assert_eq!(plan.total_required_fields, 2);
+        assert_eq!(plan.warnings.len(), 1);
+    }
+
+    fn address_item(key: &str, value: &str) -> VaultItem {
+        let mut item = item(key, VaultCategory::Address);
+        item.value = value.to_string();
+        item
+    }
+
+    fn snapshot_with(fields: Vec<FieldNodeJson>) -> FormSnapshotJson {
+        FormSnapshotJson {
+            url: "https://example.com/checkout".to_string(),
+            domain: "example.com".to_string(),
+            title: "Checkout".to_string(),
+            captured_at: Utc::now().to_rfc3339(),
+            fingerprint: crate::FormFingerprintJson {
+                field_count: fields.len() as u32,
+                field_types: vec![],
+                required_count: 0,
+                hash: "abc".to_string(),
+            },
+            fields,
+            session: Default::default(),
+        }
+    }
+
+    fn address_field(field_type: &str) -> FieldNodeJson {
+        let mut f = field("addr", field_type);
+        f.label = "Address".to_string();
+        f.semantic = "full-address".to_string();
+        f
+    }
+
+    fn german_vault() -> Vec<VaultItem> {
+        vec![
+            address_item("street", "Hauptstr. 5"),
+            address_item("city", "Berlin"),
+            address_item("zip", "10115"),
+        ]
+    }
+
+    #[test]
+    fn test_full_address_composed_from_components() {
+        let mut items = german_vault();
+        items.push(address_item("country", "Germany"));
+        let plan = generate_fill_plan(&snapshot_with(vec![address_field("text")]), &items, None);
+
+        let rec = &plan.recommendations[0];
+        assert_eq!(rec.vault_key, "street");
+        assert_eq!(
+            rec.value.as_deref(),
+            Some("Hauptstr. 5, 10115 Berlin, Germany")
+        );
+        assert!(rec.reason.contains("DE"));
+    }
+
+    #[test]
+    fn test_full_address_textarea_gets_lines() {
+        let mut items = german_vault();
+        items.push(address_item("country", "Germany"));
+        let plan = generate_fill_plan(
+            &snapshot_with(vec![address_field("textarea")]),
+            &items,
+            None,
+        );
+        assert_eq!(
+            plan.recommendations[0].value.as_deref(),
+            Some("Hauptstr. 5\n10115 Berlin\nGermany")
+        );
+    }
+
+    #[test]
+    fn test_full_address_uses_form_country_select() {
+        let mut country = field("country", "select");
+        country.semantic = "country".to_string();
+        country.options = Some(vec![crate::SelectOptionJson {
+            value: "de".to_string(),
+            label: "Deutschland".to_string(),
+        }]);
+        country.selected_value = Some("de".to_string());
+
+        let snapshot = snapshot_with(vec![address_field("text"), country]);
+        let plan = generate_fill_plan(&snapshot, &german_vault(), None);
+        let rec = plan
+            .recommendations
+            .iter()
+            .find(|r| r.field_id == "addr")
+            .unwrap();
+        // German order, and no country line since the form has its own field
+        assert_eq!(rec.value.as_deref(), Some("Hauptstr. 5, 10115 Berlin"));
+    }
+
+    #[test]
+    fn test_full_address_not_composed_when_vault_has_one() {
+        let mut items = german_vault();
+        items.push(address_item("fullAddress", "Hauptstr. 5, 10115 Berlin"));
+        let plan = generate_fill_plan(&snapshot_with(vec![address_field("text")]), &items, None);
+        assert!(plan.recommendations.iter().all(|r| r.value.is_none()));
+    }
+
+    #[test]
+    fn test_component_fields_unaffected() {
+        let mut city = field("city", "text");
+        city.label = "City".to_string();
+        city.semantic = "city".to_string();
+        let plan = generate_fill_plan(&snapshot_with(vec![city]), &german_vault(), None);
+        assert_eq!(plan.recommendations[0].vault_key, "city");
+        assert!(plan.recommendations[0].value.is_none());
+    }
+
+    #[test]
+    fn test_review_status_caps_or_excludes_matches() {
+        use crate::Disposition;
+
+        // An override is certain enough to be Safe on its own
+        let snapshot = snapshot_with(vec![field("contact", "text")]);
+        let overrides = vec![override_for("contact", "email")];
+        let disposition = |items: &[VaultItem]| {
+            let plan = generate_fill_plan_with_overrides(&snapshot, items, None, &overrides);
+            plan.recommendations
+                .first()
+                .map(|r| Disposition::for_confidence(r.confidence))
+        };
+
+        let mut items = vault();
+        assert_eq!(disposition(&items), Some(Disposition::Safe));
+
+        let email = items.iter_mut().find(|i| i.key == "email").unwrap();
+        email.review_status = ReviewStatus::Unreviewed;
+        assert_eq!(disposition(&items), Some(Disposition::Review));
+
+        let email = items.iter_mut().find(|i| i.key == "email").unwrap();
+        email.review_status = ReviewStatus::Rejected;
+        assert_eq!(disposition(&items), None);
+        let plan = generate_fill_plan_with_overrides(&snapshot, &items, None, &overrides);
+        assert_eq!(plan.unmatched_fields, vec!["contact"]);
+    }
+
+    fn override_for(field_id: &str, vault_key: &str) -> FieldOverrideJson {
+        FieldOverrideJson {
+            domain: "example.com".to_string(),
+            fingerprint: "abc".to_string(),
+            field_id: field_id.to_string(),
+            vault_key: vault_key.to_string(),
+            updated_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    #[test]
+    fn test_override_forces_key_over_classifier() {
+        let mut f = field("contact", "email");
+        f.autocomplete = Some("email".to_string());
+        f.label = "Email".to_string();
+        let snapshot = snapshot_with(vec![f]);
+
+        let plan = generate_fill_plan(&snapshot, &vault(), None);
+        assert_eq!(plan.recommendations[0].vault_key, "email");
+
+        let overrides = vec![override_for("contact", "phone")];
+        let plan = generate_fill_plan_with_overrides(&snapshot, &vault(), None, &overrides);
+        let rec = &plan.recommendations[0];
+        assert_eq!(rec.vault_key, "phone");
+        assert_eq!(rec.match_tier, MatchTier::Override);
+        assert_eq!(rec.confidence, OVERRIDE_CONFIDENCE);
+
+        // Removing the override reverts to the classifier's match
+        let plan = generate_fill_plan_with_overrides(&snapshot, &vault(), None, &[]);
+        assert_eq!(plan.recommendations[0].vault_key, "email");
+        assert_eq!(plan.recommendations[0].match_tier, MatchTier::Autocomplete);
+    }
+
+    #[test]
+    fn test_overridden_field_is_not_left_for_llm() {
+        // Nothing in the classifier matches this field, so without the
+        // override it would be sent for LLM analysis
+        let f = field("q7", "text");
+        let snapshot = snapshot_with(vec![f]);
+
+        let plan = generate_fill_plan(&snapshot, &vault(), None);
+        assert_eq!(plan.unmatched_fields, vec!["q7".to_string()]);
+
+        let overrides = vec![override_for("q7", "city")];
+        let plan = generate_fill_plan_with_overrides(&snapshot, &vault(), None, &overrides);
+        assert!(plan.unmatched_fields.is_empty());
+        assert_eq!(plan.recommendations[0].vault_key, "city");
+    }
+
+    #[test]
+    fn test_override_for_missing_vault_key_is_ignored() {
+        let mut f = field("contact", "email");
+        f.autocomplete = Some("email".to_string());
+        let overrides = vec![override_for("contact", "deleted")];
+        let plan =
+            generate_fill_plan_with_overrides(&snapshot_with(vec![f]), &vault(), None, &overrides);
+        assert_eq!(plan.recommendations[0].vault_key, "email");
+        assert_eq!(plan.recommendations[0].match_tier, MatchTier::Autocomplete);
+    }
+
+    fn wizard_step(n: u32, fields: Vec<FieldNodeJson>) -> FormSnapshotJson {
+        let mut snapshot = snapshot_with(fields);
+        snapshot.fingerprint.hash = format!("step{}", n);
+        snapshot.session = crate::sessions::FormStepJson {
+            form_session_id: Some("wizard".to_string()),
+            step: Some(n),
+            total_steps: Some(3),
+        };
+        snapshot
+    }
+
+    fn contact_field(id: &str) -> FieldNodeJson {
+        // Nothing the local tiers recognise
+        let mut f = field(id, "text");
+        f.name = "applicant_contact".to_string();
+        f.label = "Contact".to_string();
+        f
+    }
+
+    #[test]
+    fn test_earlier_step_match_is_reused_in_later_step() {
+        // Step 1's field was resolved by the user's override
+        let step1 = wizard_step(1, vec![contact_field("s1-contact")]);
+        let plan1 = generate_fill_plan_with_overrides(
+            &step1,
+            &vault(),
+            None,
+            &[override_for("s1-contact", "email")],
+        );
+        assert_eq!(plan1.recommendations[0].vault_key, "email");
+
+        let mut phone = field("s3-phone", "tel");
+        phone.autocomplete = Some("tel".to_string());
+        let step3 = wizard_step(3, vec![contact_field("s3-contact"), phone]);
+        let alone = generate_fill_plan(&step3, &vault(), None);
+        assert_eq!(alone.unmatched_fields, vec!["s3-contact".to_string()]);
+
+        let earlier = [EarlierStep {
+            snapshot: &step1,
+            plan: &plan1,
+        }];
+        let plan = generate_session_fill_plan(&step3, &vault(), None, &[], &earlier);
+        assert!(plan.unmatched_fields.is_empty());
+        let contact = plan
+            .recommendations
+            .iter()
+            .find(|r| r.field_id == "s3-contact")
+            .unwrap();
+        assert_eq!(contact.vault_key, "email");
+        assert_eq!(contact.match_tier, MatchTier::Session);
+        assert_eq!(contact.reason, "Same field as step 1 of this form");
+        let phone = plan
+            .recommendations
+            .iter()
+            .find(|r| r.field_id == "s3-phone")
+            .unwrap();
+        assert_eq!(phone.match_tier, MatchTier::Autocomplete);
+    }
+
+    #[test]
+    fn test_local_tiers_win_over_earlier_steps() {
+        let step1 = wizard_step(1, vec![contact_field("s1-contact")]);
+        let plan1 = generate_fill_plan_with_overrides(
+            &step1,
+            &vault(),
+            None,
+            &[override_for("s1-contact", "phone")],
+        );
+
+        // Same name, but this step's label is recognised on its own
+        let mut email = contact_field("s2-contact");
+        email.field_type = "email".to_string();
+        email.label = "Email".to_string();
+        let step2 = wizard_step(2, vec![email]);
+        let earlier = [EarlierStep {
+            snapshot: &step1,
+            plan: &plan1,
+        }];
+        let plan = generate_session_fill_plan(&step2, &vault(), None, &[], &earlier);
+        assert_eq!(plan.recommendations[0].vault_key, "email");
+        assert_eq!(plan.recommendations[0].match_tier, MatchTier::Pattern);
+    }
+}
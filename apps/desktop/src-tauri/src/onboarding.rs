@@ -0,0 +1,480 @@
+/*!
+ * First-Run Onboarding
+ *
+ * A new install needs a few things before Asterisk is useful: something in
+ * the vault, an LLM key, the extension talking to the bridge, a captured
+ * form and a first fill. Each is a step whose completion is derived from
+ * the real stores every time status is computed, so a step that regresses
+ * (the API key is cleared, the vault emptied, the extension goes quiet)
+ * shows as incomplete again.
+ *
+ * Two kinds of step:
+ *
+ * - state steps (vault, key, extension) are complete only while their
+ *   condition holds
+ * - milestones (first snapshot, first fill) stay complete once reached,
+ *   since the evidence for them (an in-memory snapshot, a cleared audit
+ *   log) can legitimately disappear afterwards
+ *
+ * When each step was completed is persisted in `onboarding.json`; a step
+ * that isn't recorded there yet has just been completed, which is when the
+ * `onboarding-step-completed` event fires.
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::compat::{Compatibility, SeenClientJson};
+use crate::shared::SharedStore;
+
+/// Event emitted with an `OnboardingStepJson` when a step completes
+pub const STEP_COMPLETED_EVENT: &str = "onboarding-step-completed";
+
+/// How recently the extension must have been heard from to count as connected
+pub const HEARTBEAT_WINDOW_SECS: i64 = 120;
+
+/// Onboarding progress, as shared by commands and the bridge
+pub type OnboardingStore = SharedStore<Onboarding>;
+
+/// A step of first-run setup, in the order the UI presents them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStep {
+    /// The vault holds at least one item
+    VaultSeeded,
+    /// An LLM key is configured and passes its format check
+    LlmKeyConfigured,
+    /// A supported extension has been heard from recently
+    ExtensionConnected,
+    /// The extension has sent a form snapshot
+    FirstSnapshot,
+    /// A fill has been applied
+    FirstFill,
+}
+
+impl OnboardingStep {
+    pub const ALL: &'static [OnboardingStep] = &[
+        OnboardingStep::VaultSeeded,
+        OnboardingStep::LlmKeyConfigured,
+        OnboardingStep::ExtensionConnected,
+        OnboardingStep::FirstSnapshot,
+        OnboardingStep::FirstFill,
+    ];
+
+    /// Milestones stay complete once reached
+    pub fn is_milestone(self) -> bool {
+        matches!(
+            self,
+            OnboardingStep::FirstSnapshot | OnboardingStep::FirstFill
+        )
+    }
+}
+
+/// What the stores currently say, gathered by the caller
+#[derive(Debug, Clone)]
+pub struct OnboardingInputs {
+    pub vault_items: usize,
+    /// Ok when a usable key is configured, otherwise why not
+    pub llm_key: Result<(), String>,
+    pub last_client: Option<SeenClientJson>,
+    /// A form snapshot is currently held
+    pub has_snapshot: bool,
+    /// Applied fields in the audit log
+    pub applied_fills: u64,
+}
+
+/// One step's state
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OnboardingStepJson {
+    pub step: OnboardingStep,
+    pub complete: bool,
+    #[serde(rename = "completedAt", skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+    /// Why an incomplete step isn't done yet
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+/// Result of `onboarding_status`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OnboardingStatusJson {
+    pub steps: Vec<OnboardingStepJson>,
+    pub complete: bool,
+    /// First incomplete step
+    #[serde(rename = "nextStep", skip_serializing_if = "Option::is_none")]
+    pub next_step: Option<OnboardingStep>,
+}
+
+/// Completion times of onboarding steps, optionally persisted
+#[derive(Debug, Default)]
+pub struct Onboarding {
+    path: Option<PathBuf>,
+    completed: BTreeMap<OnboardingStep, String>,
+}
+
+impl Onboarding {
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Load progress from `path` (a missing file means a fresh install)
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let completed = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("Failed to parse onboarding progress: {}", e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(format!("Failed to read onboarding progress: {}", e)),
+        };
+        Ok(Self {
+            path: Some(path),
+            completed,
+        })
+    }
+
+    /// Compute every step from `inputs`
+    ///
+    /// Returns the status and the steps that completed since the last
+    /// evaluation; state steps that no longer hold are forgotten so that
+    /// completing them again is reported again.
+    pub fn evaluate(
+        &mut self,
+        inputs: &OnboardingInputs,
+        now: DateTime<Utc>,
+    ) -> Result<(OnboardingStatusJson, Vec<OnboardingStepJson>), String> {
+        let mut changed = false;
+        let mut steps = Vec::new();
+        let mut newly_completed = Vec::new();
+
+        for &step in OnboardingStep::ALL {
+            let live = check(step, inputs, now);
+            let complete =
+                live.is_ok() || (step.is_milestone() && self.completed.contains_key(&step));
+            let newly = complete && !self.completed.contains_key(&step);
+            if newly {
+                self.completed.insert(step, now.to_rfc3339());
+                changed = true;
+            } else if !complete && self.completed.remove(&step).is_some() {
+                changed = true;
+            }
+
+            let json = OnboardingStepJson {
+                step,
+                complete,
+                completed_at: self.completed.get(&step).cloned(),
+                hint: live.err().filter(|_| !complete),
+            };
+            if newly {
+                newly_completed.push(json.clone());
+            }
+            steps.push(json);
+        }
+
+        if changed {
+            self.persist()?;
+        }
+        let next_step = steps.iter().find(|s| !s.complete).map(|s| s.step);
+        Ok((
+            OnboardingStatusJson {
+                complete: next_step.is_none(),
+                next_step,
+                steps,
+            },
+            newly_completed,
+        ))
+    }
+
+    /// Mark a step completed as it happens (the bridge seeing a heartbeat
+    /// or a snapshot); returns the step if it wasn't already complete
+    ///
+    /// The next `evaluate` still re-derives it, so a state step recorded
+    /// here is dropped again once its condition stops holding.
+    pub fn record(
+        &mut self,
+        step: OnboardingStep,
+        now: DateTime<Utc>,
+    ) -> Result<Option<OnboardingStepJson>, String> {
+        if self.completed.contains_key(&step) {
+            return Ok(None);
+        }
+        let completed_at = now.to_rfc3339();
+        self.completed.insert(step, completed_at.clone());
+        self.persist()?;
+        Ok(Some(OnboardingStepJson {
+            step,
+            complete: true,
+            completed_at: Some(completed_at),
+            hint: None,
+        }))
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create onboarding directory: {}", e))?;
+        }
+        let json = serde_json::to_string_pretty(&self.completed)
+            .map_err(|e| format!("Failed to serialize onboarding progress: {}", e))?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(|e| format!("Failed to write onboarding progress: {}", e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Failed to write onboarding progress: {}", e))
+    }
+}
+
+/// Whether the stores currently satisfy `step`, or why not
+fn check(
+    step: OnboardingStep,
+    inputs: &OnboardingInputs,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    match step {
+        OnboardingStep::VaultSeeded => (inputs.vault_items > 0)
+            .then_some(())
+            .ok_or_else(|| "Add at least one item to the vault".to_string()),
+        OnboardingStep::LlmKeyConfigured => inputs.llm_key.clone(),
+        OnboardingStep::ExtensionConnected => {
+            let client = inputs
+                .last_client
+                .as_ref()
+                .ok_or_else(|| "Install the browser extension and open a page".to_string())?;
+            if client.compatibility == Compatibility::Unsupported {
+                return Err(format!(
+                    "Extension {} is too old; update it to connect",
+                    client.version
+                ));
+            }
+            let seen = DateTime::parse_from_rfc3339(&client.seen_at)
+                .map_err(|e| format!("Unreadable heartbeat time: {}", e))?;
+            if now - seen.with_timezone(&Utc) > Duration::seconds(HEARTBEAT_WINDOW_SECS) {
+                return Err("The extension hasn't been heard from recently".to_string());
+            }
+            Ok(())
+        }
+        OnboardingStep::FirstSnapshot => inputs
+            .has_snapshot
+            .then_some(())
+            .ok_or_else(|| "Open a page with a form so the extension can capture it".to_string()),
+        OnboardingStep::FirstFill => (inputs.applied_fills > 0)
+            .then_some(())
+            .ok_or_else(|| "Apply a fill plan to a form".to_string()),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn fresh() -> OnboardingInputs {
+        OnboardingInputs {
+            vault_items: 0,
+            llm_key: Err("No API key configured".to_string()),
+            last_client: None,
+            has_snapshot: false,
+            applied_fills: 0,
+        }
+    }
+
+    fn heartbeat(seen_at: &str) -> Option<SeenClientJson> {
+        Some(SeenClientJson {
+            client: Some("asterisk-extension".to_string()),
+            version: "0.5.0".to_string(),
+            compatibility: Compatibility::Supported,
+            seen_at: seen_at.to_string(),
+        })
+    }
+
+    fn completed(status: &OnboardingStatusJson) -> Vec<OnboardingStep> {
+        status
+            .steps
+            .iter()
+            .filter(|s| s.complete)
+            .map(|s| s.step)
+            .collect()
+    }
+
+    fn names(steps: &[OnboardingStepJson]) -> Vec<OnboardingStep> {
+        steps.iter().map(|s| s.step).collect()
+    }
+
+    #[test]
+    fn test_fresh_install_starts_at_vault() {
+        let mut onboarding = Onboarding::in_memory();
+        let (status, new) = onboarding
+            .evaluate(&fresh(), at("2026-10-16T09:00:00Z"))
+            .unwrap();
+        assert!(!status.complete);
+        assert_eq!(status.next_step, Some(OnboardingStep::VaultSeeded));
+        assert!(new.is_empty());
+        assert!(status.steps.iter().all(|s| s.hint.is_some()));
+    }
+
+    #[test]
+    fn test_out_of_order_completion() {
+        let mut onboarding = Onboarding::in_memory();
+        let mut inputs = fresh();
+
+        // The extension connects and captures a form before anything else
+        inputs.last_client = heartbeat("2026-10-16T09:00:00Z");
+        inputs.has_snapshot = true;
+        let (status, new) = onboarding
+            .evaluate(&inputs, at("2026-10-16T09:00:30Z"))
+            .unwrap();
+        assert_eq!(
+            names(&new),
+            vec![
+                OnboardingStep::ExtensionConnected,
+                OnboardingStep::FirstSnapshot
+            ]
+        );
+        assert_eq!(status.next_step, Some(OnboardingStep::VaultSeeded));
+
+        // Nothing new on the next poll
+        let (_, new) = onboarding
+            .evaluate(&inputs, at("2026-10-16T09:00:40Z"))
+            .unwrap();
+        assert!(new.is_empty());
+
+        inputs.llm_key = Ok(());
+        inputs.applied_fills = 3;
+        inputs.vault_items = 2;
+        let (status, new) = onboarding
+            .evaluate(&inputs, at("2026-10-16T09:01:00Z"))
+            .unwrap();
+        assert_eq!(
+            names(&new),
+            vec![
+                OnboardingStep::VaultSeeded,
+                OnboardingStep::LlmKeyConfigured,
+                OnboardingStep::FirstFill
+            ]
+        );
+        assert!(status.complete);
+        assert_eq!(status.next_step, None);
+    }
+
+    #[test]
+    fn test_state_steps_regress_but_milestones_stay() {
+        let mut onboarding = Onboarding::in_memory();
+        let mut inputs = OnboardingInputs {
+            vault_items: 1,
+            llm_key: Ok(()),
+            last_client: heartbeat("2026-10-16T09:00:00Z"),
+            has_snapshot: true,
+            applied_fills: 1,
+        };
+        let (status, _) = onboarding
+            .evaluate(&inputs, at("2026-10-16T09:00:10Z"))
+            .unwrap();
+        assert!(status.complete);
+
+        // Key deleted, snapshot gone (restart), audit cleared, extension quiet
+        inputs.llm_key = Err("No API key configured".to_string());
+        inputs.has_snapshot = false;
+        inputs.applied_fills = 0;
+        let (status, new) = onboarding
+            .evaluate(&inputs, at("2026-10-16T09:10:00Z"))
+            .unwrap();
+        assert!(new.is_empty());
+        assert_eq!(
+            completed(&status),
+            vec![
+                OnboardingStep::VaultSeeded,
+                OnboardingStep::FirstSnapshot,
+                OnboardingStep::FirstFill
+            ]
+        );
+        assert_eq!(status.next_step, Some(OnboardingStep::LlmKeyConfigured));
+        let key = &status.steps[1];
+        assert_eq!(key.hint.as_deref(), Some("No API key configured"));
+        assert!(key.completed_at.is_none());
+
+        // Setting the key again completes it again
+        inputs.llm_key = Ok(());
+        let (_, new) = onboarding
+            .evaluate(&inputs, at("2026-10-16T09:11:00Z"))
+            .unwrap();
+        assert_eq!(names(&new), vec![OnboardingStep::LlmKeyConfigured]);
+    }
+
+    #[test]
+    fn test_recorded_heartbeat_is_rederived() {
+        let mut onboarding = Onboarding::in_memory();
+        let recorded = onboarding
+            .record(
+                OnboardingStep::ExtensionConnected,
+                at("2026-10-16T09:00:00Z"),
+            )
+            .unwrap();
+        assert!(recorded.is_some());
+        assert!(onboarding
+            .record(
+                OnboardingStep::ExtensionConnected,
+                at("2026-10-16T09:00:05Z")
+            )
+            .unwrap()
+            .is_none());
+
+        // The evaluation agrees while the heartbeat is fresh...
+        let mut inputs = fresh();
+        inputs.last_client = heartbeat("2026-10-16T09:00:00Z");
+        let (status, new) = onboarding
+            .evaluate(&inputs, at("2026-10-16T09:01:00Z"))
+            .unwrap();
+        assert!(new.is_empty());
+        assert!(status.steps[2].complete);
+
+        // ...and drops the step once it's stale
+        let (status, _) = onboarding
+            .evaluate(&inputs, at("2026-10-16T09:10:00Z"))
+            .unwrap();
+        assert!(!status.steps[2].complete);
+        assert!(onboarding
+            .record(
+                OnboardingStep::ExtensionConnected,
+                at("2026-10-16T09:10:01Z")
+            )
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_progress_persists() {
+        let dir = std::env::temp_dir().join(format!("asterisk-onboarding-{}", std::process::id()));
+        let path = dir.join("onboarding.json");
+        let _ = fs::remove_file(&path);
+
+        let mut onboarding = Onboarding::load(&path).unwrap();
+        onboarding
+            .record(OnboardingStep::FirstSnapshot, at("2026-10-16T09:00:00Z"))
+            .unwrap();
+
+        let mut reloaded = Onboarding::load(&path).unwrap();
+        let (status, new) = reloaded
+            .evaluate(&fresh(), at("2026-10-17T09:00:00Z"))
+            .unwrap();
+        assert!(new.is_empty());
+        let snapshot = &status.steps[3];
+        assert!(snapshot.complete);
+        assert_eq!(
+            snapshot.completed_at.as_deref(),
+            Some("2026-10-16T09:00:00+00:00")
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,208 @@
+/*!
+ * Field Overrides
+ *
+ * When a user keeps correcting the match for one field on one site, the
+ * correction is saved as an override: (domain, form fingerprint, field id)
+ * → vault key. Overrides are consulted before every matching tier, including
+ * templates and the LLM, and resolve with full confidence.
+ *
+ * Unlike templates, which cover a whole form and heal themselves, an
+ * override is a single explicit user choice and stays until removed.
+ */
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::fill::canonicalize_domain;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// A user's fixed choice of vault key for one field of one form
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FieldOverrideJson {
+    /// Canonical domain (see `fill::canonicalize_domain`)
+    pub domain: String,
+    /// Form fingerprint hash
+    pub fingerprint: String,
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    #[serde(rename = "vaultKey")]
+    pub vault_key: String,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
+
+// ============================================================================
+// Override Store
+// ============================================================================
+
+/// Field overrides persisted as a single JSON file
+#[derive(Debug, Default)]
+pub struct OverrideStore {
+    path: Option<PathBuf>,
+    overrides: Vec<FieldOverrideJson>,
+}
+
+impl OverrideStore {
+    /// Create a store that is never written to disk
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Load overrides from `path`; a missing file is an empty store
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let overrides = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("Failed to parse field overrides: {}", e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("Failed to read field overrides: {}", e)),
+        };
+        Ok(Self {
+            path: Some(path),
+            overrides,
+        })
+    }
+
+    pub fn list(&self) -> &[FieldOverrideJson] {
+        &self.overrides
+    }
+
+    /// Overrides for one form
+    pub fn for_form(&self, domain: &str, fingerprint: &str) -> Vec<FieldOverrideJson> {
+        let domain = canonicalize_domain(domain);
+        self.overrides
+            .iter()
+            .filter(|o| o.domain == domain && o.fingerprint == fingerprint)
+            .cloned()
+            .collect()
+    }
+
+    /// Set (or replace) the override for a field
+    pub fn set(
+        &mut self,
+        domain: &str,
+        fingerprint: &str,
+        field_id: &str,
+        vault_key: &str,
+    ) -> Result<FieldOverrideJson, String> {
+        let domain = canonicalize_domain(domain);
+        if domain.is_empty() || fingerprint.is_empty() || field_id.is_empty() {
+            return Err("Override needs a domain, fingerprint and field id".to_string());
+        }
+        if vault_key.is_empty() {
+            return Err("Override needs a vault key".to_string());
+        }
+
+        let entry = FieldOverrideJson {
+            domain,
+            fingerprint: fingerprint.to_string(),
+            field_id: field_id.to_string(),
+            vault_key: vault_key.to_string(),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        };
+        self.overrides.retain(|o| {
+            !(o.domain == entry.domain
+                && o.fingerprint == entry.fingerprint
+                && o.field_id == entry.field_id)
+        });
+        self.overrides.push(entry.clone());
+        self.persist()?;
+        Ok(entry)
+    }
+
+    /// Remove the override for a field; returns whether there was one
+    pub fn remove(
+        &mut self,
+        domain: &str,
+        fingerprint: &str,
+        field_id: &str,
+    ) -> Result<bool, String> {
+        let domain = canonicalize_domain(domain);
+        let before = self.overrides.len();
+        self.overrides.retain(|o| {
+            !(o.domain == domain && o.fingerprint == fingerprint && o.field_id == field_id)
+        });
+        if self.overrides.len() == before {
+            return Ok(false);
+        }
+        self.persist()?;
+        Ok(true)
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create override directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(&self.overrides)
+            .map_err(|e| format!("Failed to serialize field overrides: {}", e))?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(|e| format!("Failed to write field overrides: {}", e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Failed to write field overrides: {}", e))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_survive_reload() {
+        let dir = std::env::temp_dir().join(format!("asterisk-overrides-{}", std::process::id()));
+        let path = dir.join("field-overrides.json");
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut store = OverrideStore::load(&path).unwrap();
+        store
+            .set("https://Example.com/", "abc", "contact", "phone")
+            .unwrap();
+        store
+            .set("example.com", "abc", "name", "firstName")
+            .unwrap();
+
+        let reloaded = OverrideStore::load(&path).unwrap();
+        let form = reloaded.for_form("example.com", "abc");
+        assert_eq!(form.len(), 2);
+        assert!(form
+            .iter()
+            .any(|o| o.field_id == "contact" && o.vault_key == "phone"));
+        assert!(reloaded.for_form("example.com", "other").is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_set_replaces_and_remove_reverts() {
+        let mut store = OverrideStore::in_memory();
+        store.set("example.com", "abc", "contact", "phone").unwrap();
+        store.set("example.com", "abc", "contact", "email").unwrap();
+
+        let form = store.for_form("example.com", "abc");
+        assert_eq!(form.len(), 1);
+        assert_eq!(form[0].vault_key, "email");
+
+        assert!(store.remove("example.com", "abc", "contact").unwrap());
+        assert!(!store.remove("example.com", "abc", "contact").unwrap());
+        assert!(store.for_form("example.com", "abc").is_empty());
+    }
+
+    #[test]
+    fn test_set_rejects_incomplete_override() {
+        let mut store = OverrideStore::in_memory();
+        assert!(store.set("example.com", "", "contact", "phone").is_err());
+        assert!(store.set("example.com", "abc", "contact", "").is_err());
+        assert!(store.list().is_empty());
+    }
+}
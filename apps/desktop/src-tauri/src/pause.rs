@@ -0,0 +1,267 @@
+/*!
+ * Bridge Capability Pause
+ *
+ * Pausing the whole bridge is too blunt: the user may want to stop capture
+ * while a fill in progress still completes. Each bridge route belongs to
+ * exactly one capability in `ROUTE_CAPABILITIES`, and each capability can
+ * be paused on its own with `bridge_set_capability`. A request to a paused
+ * capability gets 503 with `code: capability_paused` naming it, before
+ * routing.
+ *
+ * `/health` and `/v1/capabilities` are never paused, so the extension can
+ * always see what is paused and adapt its UI; nor is the `/` status page. Pauses live in memory; a
+ * restart resumes everything.
+ */
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Event raised when a capability is paused or resumed
+pub const CAPABILITY_CHANGED_EVENT: &str = "bridge-capability-changed";
+
+/// A group of bridge routes that can be paused together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Capability {
+    /// Snapshots and vault changes coming in from pages
+    Capture,
+    /// Vault items and site info going out to the extension
+    VaultRead,
+    /// Fill commands queued for and polled by the extension
+    FillDelivery,
+    /// Fill completion and undo reports, and the audit routes
+    AuditWrite,
+}
+
+impl Capability {
+    pub const ALL: &'static [Capability] = &[
+        Capability::Capture,
+        Capability::VaultRead,
+        Capability::FillDelivery,
+        Capability::AuditWrite,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Capability::Capture => "capture",
+            Capability::VaultRead => "vault-read",
+            Capability::FillDelivery => "fill-delivery",
+            Capability::AuditWrite => "audit-write",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Capability> {
+        Self::ALL.iter().copied().find(|c| c.as_str() == name)
+    }
+}
+
+/// Which capability each bridge route belongs to, by method and path
+pub const ROUTE_CAPABILITIES: &[(&str, &str, Capability)] = &[
+    ("GET", "/v1/form-snapshots", Capability::Capture),
+    ("POST", "/v1/form-snapshots", Capability::Capture),
+    ("POST", "/v1/vault", Capability::Capture),
+    ("DELETE", "/v1/vault", Capability::Capture),
+    ("GET", "/v1/vault", Capability::VaultRead),
+    ("GET", "/v1/vault/keys", Capability::VaultRead),
+    ("GET", "/v1/vault/changes", Capability::VaultRead),
+    ("GET", "/v1/site-info", Capability::VaultRead),
+    ("GET", "/v1/fill-commands", Capability::FillDelivery),
+    ("POST", "/v1/fill-commands", Capability::FillDelivery),
+    ("POST", "/v1/fill-commands/batch", Capability::FillDelivery),
+    ("DELETE", "/v1/fill-commands", Capability::AuditWrite),
+    (
+        "POST",
+        "/v1/fill-commands/prior-values",
+        Capability::AuditWrite,
+    ),
+    ("GET", "/v1/audit/stats", Capability::AuditWrite),
+];
+
+/// The capability a request needs, if its route has one
+pub fn capability_for(method: &str, url: &str) -> Option<Capability> {
+    let path = url.split('?').next().unwrap_or(url);
+    ROUTE_CAPABILITIES
+        .iter()
+        .find(|(m, p, _)| *m == method && *p == path)
+        .map(|&(_, _, capability)| capability)
+}
+
+/// One capability and whether it is on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapabilityStateJson {
+    pub name: Capability,
+    pub enabled: bool,
+}
+
+/// Payload of the 503 for a paused capability
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapabilityPausedJson {
+    pub error: String,
+    /// Always `capability_paused`
+    pub code: String,
+    pub capability: Capability,
+}
+
+impl CapabilityPausedJson {
+    pub fn new(capability: Capability) -> Self {
+        CapabilityPausedJson {
+            error: format!("The bridge's {} capability is paused", capability.as_str()),
+            code: "capability_paused".to_string(),
+            capability,
+        }
+    }
+}
+
+/// The paused capabilities, shared with the bridge
+#[derive(Debug, Default)]
+pub struct BridgePause {
+    paused: Mutex<BTreeSet<Capability>>,
+}
+
+impl BridgePause {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn paused(&self) -> MutexGuard<'_, BTreeSet<Capability>> {
+        self.paused.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Pause or resume `capability`; returns whether that changed anything
+    pub fn set(&self, capability: Capability, enabled: bool) -> bool {
+        let mut paused = self.paused();
+        if enabled {
+            paused.remove(&capability)
+        } else {
+            paused.insert(capability)
+        }
+    }
+
+    pub fn is_paused(&self, capability: Capability) -> bool {
+        self.paused().contains(&capability)
+    }
+
+    /// The refusal for a request, if its route's capability is paused
+    pub fn check(&self, method: &str, url: &str) -> Result<(), CapabilityPausedJson> {
+        match capability_for(method, url) {
+            Some(capability) if self.is_paused(capability) => {
+                Err(CapabilityPausedJson::new(capability))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Every capability and whether it is on
+    pub fn states(&self) -> Vec<CapabilityStateJson> {
+        let paused = self.paused();
+        Capability::ALL
+            .iter()
+            .map(|&name| CapabilityStateJson {
+                name,
+                enabled: !paused.contains(&name),
+            })
+            .collect()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Routes that stay up whatever is paused
+    const UNPAUSABLE_ROUTES: &[(&str, &str)] = &[
+        ("GET", "/"),
+        ("GET", "/health"),
+        ("GET", "/v1/capabilities"),
+    ];
+
+    /// Method and path of every `// Route:` the bridge serves
+    fn registered_routes() -> Vec<(String, String)> {
+        include_str!("lib.rs")
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("// Route: "))
+            .map(|route| {
+                let mut parts = route.split_whitespace();
+                let method = parts.next().unwrap().to_string();
+                let target = parts.next().unwrap();
+                let path = target.split('?').next().unwrap().to_string();
+                (method, path)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_every_route_maps_to_exactly_one_capability() {
+        let routes = registered_routes();
+        assert!(routes.len() >= 15, "{:?}", routes);
+        for (method, path) in &routes {
+            let mapped = ROUTE_CAPABILITIES
+                .iter()
+                .filter(|(m, p, _)| m == method && p == path)
+                .count();
+            let unpausable = UNPAUSABLE_ROUTES.contains(&(method.as_str(), path.as_str()));
+            assert_eq!(
+                mapped + usize::from(unpausable),
+                1,
+                "{} {} must map to exactly one capability",
+                method,
+                path
+            );
+        }
+        // And the table names no route the bridge doesn't serve
+        for (method, path, _) in ROUTE_CAPABILITIES {
+            assert!(
+                routes.iter().any(|(m, p)| m == method && p == path),
+                "{} {} is not a bridge route",
+                method,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_paused_capability_refuses_only_its_routes() {
+        let pause = BridgePause::new();
+        assert!(pause.check("POST", "/v1/form-snapshots").is_ok());
+
+        assert!(pause.set(Capability::Capture, false));
+        assert!(!pause.set(Capability::Capture, false));
+        let refusal = pause.check("POST", "/v1/form-snapshots").unwrap_err();
+        assert_eq!(refusal.capability, Capability::Capture);
+        let json = serde_json::to_value(&refusal).unwrap();
+        assert_eq!(json["code"], "capability_paused");
+        assert_eq!(json["capability"], "capture");
+
+        // A fill in progress still completes
+        assert!(pause.check("GET", "/v1/fill-commands?domain=x").is_ok());
+        assert!(pause.check("DELETE", "/v1/fill-commands?id=x").is_ok());
+        assert!(pause.check("GET", "/health").is_ok());
+
+        assert!(pause.set(Capability::Capture, true));
+        assert!(pause.check("POST", "/v1/form-snapshots").is_ok());
+    }
+
+    #[test]
+    fn test_states_and_names() {
+        let pause = BridgePause::new();
+        pause.set(Capability::FillDelivery, false);
+        let states = pause.states();
+        assert_eq!(states.len(), Capability::ALL.len());
+        assert!(states
+            .iter()
+            .all(|s| s.enabled == (s.name != Capability::FillDelivery)));
+        for &capability in Capability::ALL {
+            assert_eq!(Capability::parse(capability.as_str()), Some(capability));
+            assert_eq!(
+                serde_json::to_value(capability).unwrap(),
+                capability.as_str()
+            );
+        }
+        assert_eq!(Capability::parse("everything"), None);
+    }
+}
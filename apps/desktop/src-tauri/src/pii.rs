@@ -0,0 +1,227 @@
+/*!
+ * PII Detection
+ *
+ * Flags vault values that look like highly sensitive data (social security
+ * numbers, payment cards, bank account numbers) stored outside the
+ * `Financial` category, so the UI can suggest moving or marking them.
+ *
+ * Findings carry only the key and what was detected; values are never
+ * returned or logged.
+ */
+
+use asterisk_vault::{VaultCategory, VaultItem};
+use serde::{Deserialize, Serialize};
+
+/// What a value looks like
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    /// US social security number
+    Ssn,
+    /// Luhn-valid payment card number
+    CreditCard,
+    /// International bank account number with a valid checksum
+    Iban,
+}
+
+/// A vault item whose value looks like sensitive data in the wrong category
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PiiFindingJson {
+    pub key: String,
+    pub category: VaultCategory,
+    pub kind: PiiKind,
+}
+
+/// Digits of a value made only of digits and the given separators
+fn digits_only(value: &str, separators: &[char]) -> Option<Vec<u32>> {
+    value
+        .trim()
+        .chars()
+        .filter(|c| !separators.contains(c))
+        .map(|c| c.to_digit(10))
+        .collect()
+}
+
+fn luhn_valid(digits: &[u32]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| {
+            if i % 2 == 1 {
+                let doubled = d * 2;
+                if doubled > 9 {
+                    doubled - 9
+                } else {
+                    doubled
+                }
+            } else {
+                d
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+fn is_credit_card(value: &str) -> bool {
+    let Some(digits) = digits_only(value, &[' ', '-']) else {
+        return false;
+    };
+    (13..=19).contains(&digits.len()) && digits.iter().any(|&d| d != 0) && luhn_valid(&digits)
+}
+
+/// "123-45-6789" or "123 45 6789", excluding numbers never issued
+fn is_ssn(value: &str) -> bool {
+    let value = value.trim();
+    let groups: Vec<&str> = value.split(['-', ' ']).collect();
+    let [area, group, serial] = groups.as_slice() else {
+        return false;
+    };
+    let shaped = [(area, 3), (group, 2), (serial, 4)]
+        .iter()
+        .all(|(part, len)| part.len() == *len && part.chars().all(|c| c.is_ascii_digit()));
+    shaped
+        && *area != "000"
+        && *area != "666"
+        && !area.starts_with('9')
+        && *group != "00"
+        && *serial != "0000"
+}
+
+/// Two letters, two check digits, then 11-30 letters or digits (mod-97 check)
+fn is_iban(value: &str) -> bool {
+    let compact: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_uppercase();
+    if !(15..=34).contains(&compact.len()) || !compact.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return false;
+    }
+    let (head, rest) = compact.split_at(4);
+    let mut head_chars = head.chars();
+    let country_ok = head_chars.by_ref().take(2).all(|c| c.is_ascii_alphabetic());
+    if !country_ok || !head_chars.all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+
+    // Move the first four characters to the end; letters count as 10..35
+    let remainder = rest.chars().chain(head.chars()).fold(0u32, |acc, c| {
+        let n = c.to_digit(36).unwrap_or(0);
+        if n >= 10 {
+            (acc * 100 + n) % 97
+        } else {
+            (acc * 10 + n) % 97
+        }
+    });
+    remainder == 1
+}
+
+/// What sensitive data, if any, a value looks like
+pub fn detect(value: &str) -> Option<PiiKind> {
+    if is_ssn(value) {
+        Some(PiiKind::Ssn)
+    } else if is_credit_card(value) {
+        Some(PiiKind::CreditCard)
+    } else if is_iban(value) {
+        Some(PiiKind::Iban)
+    } else {
+        None
+    }
+}
+
+/// Flag items outside `Financial` whose values look like sensitive data
+pub fn scan(items: &[VaultItem]) -> Vec<PiiFindingJson> {
+    items
+        .iter()
+        .filter(|item| item.category != VaultCategory::Financial)
+        .filter_map(|item| {
+            Some(PiiFindingJson {
+                key: item.key.clone(),
+                category: item.category.clone(),
+                kind: detect(&item.value)?,
+            })
+        })
+        .collect()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asterisk_vault::test_util;
+
+    fn item(key: &str, value: &str, category: VaultCategory) -> VaultItem {
+        test_util::item(key, value, category)
+    }
+
+    #[test]
+    fn test_card_under_contact_flagged() {
+        let findings = scan(&[item(
+            "backupNumber",
+            "4111 1111 1111 1111",
+            VaultCategory::Contact,
+        )]);
+        assert_eq!(
+            findings,
+            vec![PiiFindingJson {
+                key: "backupNumber".to_string(),
+                category: VaultCategory::Contact,
+                kind: PiiKind::CreditCard,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_phone_not_flagged_as_card() {
+        for phone in ["555-010-0199", "+1 (555) 010-0199", "+44 20 7946 0958"] {
+            assert_eq!(detect(phone), None, "{}", phone);
+        }
+    }
+
+    #[test]
+    fn test_card_requires_luhn() {
+        assert_eq!(detect("4111-1111-1111-1111"), Some(PiiKind::CreditCard));
+        assert_eq!(detect("4111-1111-1111-1112"), None);
+        assert_eq!(detect("0000 0000 0000 0000"), None);
+    }
+
+    #[test]
+    fn test_ssn() {
+        assert_eq!(detect("123-45-6789"), Some(PiiKind::Ssn));
+        assert_eq!(detect("123 45 6789"), Some(PiiKind::Ssn));
+        assert_eq!(detect("000-45-6789"), None);
+        assert_eq!(detect("666-45-6789"), None);
+        assert_eq!(detect("123-00-6789"), None);
+        assert_eq!(detect("12-345-6789"), None);
+    }
+
+    #[test]
+    fn test_iban() {
+        assert_eq!(detect("GB82 WEST 1234 5698 7654 32"), Some(PiiKind::Iban));
+        assert_eq!(detect("DE89370400440532013000"), Some(PiiKind::Iban));
+        assert_eq!(detect("GB82 WEST 1234 5698 7654 33"), None);
+    }
+
+    #[test]
+    fn test_financial_category_not_flagged() {
+        let findings = scan(&[
+            item("cardNumber", "4111111111111111", VaultCategory::Financial),
+            item("ssn", "123-45-6789", VaultCategory::Identity),
+            item("email", "a@b.c", VaultCategory::Contact),
+        ]);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].key, "ssn");
+        assert_eq!(findings[0].kind, PiiKind::Ssn);
+    }
+
+    #[test]
+    fn test_findings_never_carry_values() {
+        let findings = scan(&[item("x", "4111111111111111", VaultCategory::Custom)]);
+        let json = serde_json::to_string(&findings).unwrap();
+        assert!(!json.contains("4111"));
+    }
+}
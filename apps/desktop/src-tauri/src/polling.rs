@@ -0,0 +1,152 @@
+/*!
+ * Adaptive Poll Hints
+ *
+ * The extension polls for fill commands. Instead of a fixed interval the
+ * bridge tells it how soon to poll again (`pollHintMs`): fast while a fill
+ * is imminent, slower while the user is looking at a form, and slow when
+ * nothing is happening.
+ */
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Poll interval while a fill is imminent
+pub const FAST_POLL_MS: u64 = 250;
+/// Poll interval while a fresh snapshot is being worked on
+pub const MEDIUM_POLL_MS: u64 = 2_000;
+/// Poll interval when idle
+pub const IDLE_POLL_MS: u64 = 10_000;
+
+/// How long after a plan is generated we expect its fill command
+const PLAN_FRESH_SECS: i64 = 30;
+/// How long a captured snapshot counts as being worked on
+const SNAPSHOT_FRESH_SECS: i64 = 120;
+
+/// The most recently generated fill plan that hasn't been applied yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanActivity {
+    pub domain: String,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Server state the hint is computed from
+#[derive(Debug, Default)]
+pub struct PollInputs<'a> {
+    /// Domain the extension is polling for (None = any)
+    pub domain: Option<&'a str>,
+    /// Unexpired commands waiting for that domain
+    pub pending_commands: usize,
+    pub last_plan: Option<&'a PlanActivity>,
+    /// `capturedAt` of the latest snapshot
+    pub snapshot_captured_at: Option<&'a str>,
+}
+
+/// How long the extension should wait before polling again
+pub fn poll_hint_ms(inputs: &PollInputs, now: DateTime<Utc>) -> u64 {
+    if inputs.pending_commands > 0 {
+        return FAST_POLL_MS;
+    }
+
+    let plan_pending = inputs.last_plan.is_some_and(|plan| {
+        inputs.domain.is_none_or(|d| d == plan.domain)
+            && now - plan.generated_at < Duration::seconds(PLAN_FRESH_SECS)
+    });
+    if plan_pending {
+        return FAST_POLL_MS;
+    }
+
+    if inputs
+        .snapshot_captured_at
+        .is_some_and(|t| snapshot_is_fresh(t, now))
+    {
+        return MEDIUM_POLL_MS;
+    }
+
+    IDLE_POLL_MS
+}
+
+/// Whether a snapshot captured at `captured_at` is recent enough to trust
+///
+/// An unparseable time counts as stale.
+pub fn snapshot_is_fresh(captured_at: &str, now: DateTime<Utc>) -> bool {
+    DateTime::parse_from_rfc3339(captured_at)
+        .is_ok_and(|t| now - t.with_timezone(&Utc) < Duration::seconds(SNAPSHOT_FRESH_SECS))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        "2026-05-01T12:00:00Z".parse().unwrap()
+    }
+
+    fn plan(domain: &str, secs_ago: i64) -> PlanActivity {
+        PlanActivity {
+            domain: domain.to_string(),
+            generated_at: now() - Duration::seconds(secs_ago),
+        }
+    }
+
+    #[test]
+    fn test_idle() {
+        assert_eq!(poll_hint_ms(&PollInputs::default(), now()), IDLE_POLL_MS);
+    }
+
+    #[test]
+    fn test_pending_command_is_fast() {
+        let inputs = PollInputs {
+            pending_commands: 1,
+            ..Default::default()
+        };
+        assert_eq!(poll_hint_ms(&inputs, now()), FAST_POLL_MS);
+    }
+
+    #[test]
+    fn test_fresh_plan_is_fast() {
+        let plan = plan("example.com", 5);
+        let inputs = PollInputs {
+            domain: Some("example.com"),
+            last_plan: Some(&plan),
+            ..Default::default()
+        };
+        assert_eq!(poll_hint_ms(&inputs, now()), FAST_POLL_MS);
+    }
+
+    #[test]
+    fn test_plan_for_other_domain_or_stale_is_not_fast() {
+        let other = plan("other.org", 5);
+        let inputs = PollInputs {
+            domain: Some("example.com"),
+            last_plan: Some(&other),
+            ..Default::default()
+        };
+        assert_eq!(poll_hint_ms(&inputs, now()), IDLE_POLL_MS);
+
+        let stale = plan("example.com", 300);
+        let inputs = PollInputs {
+            domain: Some("example.com"),
+            last_plan: Some(&stale),
+            ..Default::default()
+        };
+        assert_eq!(poll_hint_ms(&inputs, now()), IDLE_POLL_MS);
+    }
+
+    #[test]
+    fn test_fresh_snapshot_is_medium() {
+        let inputs = PollInputs {
+            snapshot_captured_at: Some("2026-05-01T11:59:30.000Z"),
+            ..Default::default()
+        };
+        assert_eq!(poll_hint_ms(&inputs, now()), MEDIUM_POLL_MS);
+
+        let inputs = PollInputs {
+            snapshot_captured_at: Some("2026-05-01T10:00:00Z"),
+            ..Default::default()
+        };
+        assert_eq!(poll_hint_ms(&inputs, now()), IDLE_POLL_MS);
+    }
+}
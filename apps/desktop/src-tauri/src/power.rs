@@ -0,0 +1,176 @@
+/*!
+ * System Sleep and Resume
+ *
+ * A `PowerSource` reports when the machine went to sleep and woke up again;
+ * `watch` feeds its events to the vault lock on a background thread.
+ *
+ * Tauri has no sleep/resume notifications, so the built-in source,
+ * `ClockJumpSource`, infers suspension instead: it ticks every few seconds
+ * and compares how far the wall clock moved with how far the monotonic
+ * clock did. The monotonic clock stops while the machine is suspended
+ * (Linux, macOS), so a wall-clock jump well beyond the tick means the
+ * process was asleep in between. On a platform whose monotonic clock keeps
+ * running through suspend nothing is reported, and the lock falls back to
+ * its idle timer. Setting the wall clock forward by hand looks like a
+ * sleep too, which only ever locks early.
+ */
+
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::vault_lock::VaultLock;
+
+/// How often `ClockJumpSource` compares the clocks
+pub const TICK: Duration = Duration::from_secs(5);
+
+/// Wall-clock gain over the monotonic clock that counts as a sleep
+pub const SLEEP_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// A change in the machine's power state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerEvent {
+    Sleep { at: DateTime<Utc> },
+    Resume { at: DateTime<Utc> },
+}
+
+/// Where sleep and resume notifications come from
+pub trait PowerSource: Send {
+    /// Block until the next event; None when the source has ended
+    fn next_event(&mut self) -> Option<PowerEvent>;
+}
+
+/// Infers sleep from wall-clock jumps the monotonic clock didn't see
+pub struct ClockJumpSource {
+    tick: Duration,
+    threshold: Duration,
+    last: Option<(Instant, DateTime<Utc>)>,
+    pending_resume: Option<PowerEvent>,
+}
+
+impl ClockJumpSource {
+    pub fn new(tick: Duration, threshold: Duration) -> Self {
+        ClockJumpSource {
+            tick,
+            threshold,
+            last: None,
+            pending_resume: None,
+        }
+    }
+}
+
+impl Default for ClockJumpSource {
+    fn default() -> Self {
+        ClockJumpSource::new(TICK, SLEEP_THRESHOLD)
+    }
+}
+
+impl PowerSource for ClockJumpSource {
+    fn next_event(&mut self) -> Option<PowerEvent> {
+        if let Some(resume) = self.pending_resume.take() {
+            return Some(resume);
+        }
+        loop {
+            thread::sleep(self.tick);
+            let now = (Instant::now(), Utc::now());
+            let last = self.last.replace(now);
+            let Some((last_instant, last_wall)) = last else {
+                continue;
+            };
+            let awake = now.0.duration_since(last_instant);
+            if let Some((slept, woke)) = detect_sleep(last_wall, awake, now.1, self.threshold) {
+                self.pending_resume = Some(PowerEvent::Resume { at: woke });
+                return Some(PowerEvent::Sleep { at: slept });
+            }
+        }
+    }
+}
+
+/// When the machine slept and woke between two clock readings, if it did
+///
+/// `awake` is the monotonic time between the readings. The sleep is placed
+/// right after the process last ran: the earlier reading plus `awake`.
+pub fn detect_sleep(
+    last_wall: DateTime<Utc>,
+    awake: Duration,
+    now_wall: DateTime<Utc>,
+    threshold: Duration,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let awake = chrono::Duration::from_std(awake).ok()?;
+    let threshold = chrono::Duration::from_std(threshold).ok()?;
+    let slept_at = last_wall + awake;
+    (now_wall - slept_at > threshold).then_some((slept_at, now_wall))
+}
+
+/// Feed `source`'s events to `lock` until the source ends
+pub fn watch(
+    mut source: impl PowerSource + 'static,
+    lock: Arc<VaultLock>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        while let Some(event) = source.next_event() {
+            lock.power_event(event);
+        }
+    })
+}
+
+/// A scripted power source for tests
+#[cfg(test)]
+pub struct MockPowerSource {
+    events: std::collections::VecDeque<PowerEvent>,
+}
+
+#[cfg(test)]
+impl MockPowerSource {
+    pub fn new(events: impl IntoIterator<Item = PowerEvent>) -> Self {
+        MockPowerSource {
+            events: events.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl PowerSource for MockPowerSource {
+    fn next_event(&mut self) -> Option<PowerEvent> {
+        self.events.pop_front()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        "2026-05-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap() + chrono::Duration::seconds(secs)
+    }
+
+    #[test]
+    fn test_detect_sleep_from_clock_jump() {
+        let threshold = Duration::from_secs(30);
+        // Both clocks moved by the tick: no sleep
+        assert_eq!(
+            detect_sleep(at(0), Duration::from_secs(5), at(5), threshold),
+            None
+        );
+        // A small wall-clock correction isn't a sleep
+        assert_eq!(
+            detect_sleep(at(0), Duration::from_secs(5), at(20), threshold),
+            None
+        );
+        // Nor is the wall clock going backwards
+        assert_eq!(
+            detect_sleep(at(0), Duration::from_secs(5), at(-3600), threshold),
+            None
+        );
+        // Three hours of wall time in five seconds of running
+        assert_eq!(
+            detect_sleep(at(0), Duration::from_secs(5), at(3 * 3600), threshold),
+            Some((at(5), at(3 * 3600)))
+        );
+    }
+}
@@ -0,0 +1,396 @@
+/*!
+ * Filled Value Recall
+ *
+ * The audit log only keeps redacted values, so it can't answer "what did I
+ * put in the PO Number field on vendor.com last quarter?". Recall is an
+ * opt-in, per-domain record of filled label + value pairs for exactly that.
+ *
+ * Records are encrypted (ChaCha20-Poly1305, as in the vault) with a dedicated
+ * key kept in its own file, separate from the vault, and live in
+ * `recall.json`. Vault exports never read this store. Recording is off for
+ * every domain by default.
+ */
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::fill::canonicalize_domain;
+use crate::{FieldNodeJson, FillCommandJson};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// A recalled fill, decrypted
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecallRecordJson {
+    pub domain: String,
+    /// Label of the filled field
+    pub label: String,
+    pub value: String,
+    #[serde(rename = "recordedAt")]
+    pub recorded_at: String,
+}
+
+/// A record as stored: the domain stays in the clear so purges don't need
+/// to decrypt anything
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SealedRecord {
+    domain: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RecallFile {
+    #[serde(rename = "enabledDomains", default)]
+    enabled_domains: BTreeSet<String>,
+    #[serde(default)]
+    records: Vec<SealedRecord>,
+}
+
+/// The label/value/time triple that gets encrypted
+#[derive(Serialize, Deserialize)]
+struct RecallPayload {
+    label: String,
+    value: String,
+    recorded_at: String,
+}
+
+// ============================================================================
+// Recall Store
+// ============================================================================
+
+/// Encrypted store of filled values for domains that opted in
+pub struct RecallStore {
+    path: Option<PathBuf>,
+    cipher: ChaCha20Poly1305,
+    data: RecallFile,
+}
+
+impl RecallStore {
+    /// Create a store with a throwaway key that is never written to disk
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            cipher: ChaCha20Poly1305::new(&ChaCha20Poly1305::generate_key(OsRng)),
+            data: RecallFile::default(),
+        }
+    }
+
+    /// Load records from `path`, with the key at `key_path`
+    ///
+    /// A missing key file gets a fresh key; a missing record file means no
+    /// records and recall off everywhere.
+    pub fn load(path: impl Into<PathBuf>, key_path: &Path) -> Result<Self, String> {
+        let path = path.into();
+        let cipher = ChaCha20Poly1305::new(&load_or_create_key(key_path)?);
+        let data = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("Failed to parse recall store: {}", e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => RecallFile::default(),
+            Err(e) => return Err(format!("Failed to read recall store: {}", e)),
+        };
+        Ok(Self {
+            path: Some(path),
+            cipher,
+            data,
+        })
+    }
+
+    pub fn is_enabled(&self, domain: &str) -> bool {
+        self.data
+            .enabled_domains
+            .contains(&canonicalize_domain(domain))
+    }
+
+    /// Domains with recall turned on
+    pub fn enabled_domains(&self) -> Vec<String> {
+        self.data.enabled_domains.iter().cloned().collect()
+    }
+
+    /// Turn recall on or off for a domain; existing records are kept
+    pub fn set_enabled(&mut self, domain: &str, enabled: bool) -> Result<(), String> {
+        let domain = canonicalize_domain(domain);
+        if enabled {
+            self.data.enabled_domains.insert(domain);
+        } else {
+            self.data.enabled_domains.remove(&domain);
+        }
+        self.persist()
+    }
+
+    /// Record filled `(label, value)` pairs for a domain
+    ///
+    /// Returns false without recording anything when recall is off for it.
+    pub fn record(&mut self, domain: &str, fills: &[(String, String)]) -> Result<bool, String> {
+        if !self.is_enabled(domain) {
+            return Ok(false);
+        }
+        let domain = canonicalize_domain(domain);
+        let recorded_at = chrono::Utc::now().to_rfc3339();
+        for (label, value) in fills {
+            let sealed = self.seal(
+                &domain,
+                &RecallPayload {
+                    label: label.clone(),
+                    value: value.clone(),
+                    recorded_at: recorded_at.clone(),
+                },
+            )?;
+            self.data.records.push(sealed);
+        }
+        self.persist()?;
+        Ok(true)
+    }
+
+    /// Records for a domain whose label or value contains `query`
+    /// (case-insensitive), newest first; an empty query matches everything
+    pub fn search(&self, domain: &str, query: &str) -> Result<Vec<RecallRecordJson>, String> {
+        let domain = canonicalize_domain(domain);
+        let query = query.trim().to_lowercase();
+
+        let mut results = Vec::new();
+        for sealed in self.data.records.iter().filter(|r| r.domain == domain) {
+            let payload = self.open(sealed)?;
+            if query.is_empty()
+                || payload.label.to_lowercase().contains(&query)
+                || payload.value.to_lowercase().contains(&query)
+            {
+                results.push(RecallRecordJson {
+                    domain: domain.clone(),
+                    label: payload.label,
+                    value: payload.value,
+                    recorded_at: payload.recorded_at,
+                });
+            }
+        }
+        results.reverse();
+        Ok(results)
+    }
+
+    /// Delete every record for a domain; returns how many were removed
+    pub fn purge(&mut self, domain: &str) -> Result<usize, String> {
+        let domain = canonicalize_domain(domain);
+        let before = self.data.records.len();
+        self.data.records.retain(|r| r.domain != domain);
+        let removed = before - self.data.records.len();
+        self.persist()?;
+        Ok(removed)
+    }
+
+    fn seal(&self, domain: &str, payload: &RecallPayload) -> Result<SealedRecord, String> {
+        let plaintext = serde_json::to_vec(payload)
+            .map_err(|e| format!("Failed to serialize recall record: {}", e))?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| "Failed to encrypt recall record".to_string())?;
+        Ok(SealedRecord {
+            domain: domain.to_string(),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        })
+    }
+
+    fn open(&self, sealed: &SealedRecord) -> Result<RecallPayload, String> {
+        let nonce = BASE64
+            .decode(&sealed.nonce)
+            .map_err(|e| format!("Corrupt recall record: {}", e))?;
+        if nonce.len() != 12 {
+            return Err("Corrupt recall record: bad nonce".to_string());
+        }
+        let ciphertext = BASE64
+            .decode(&sealed.ciphertext)
+            .map_err(|e| format!("Corrupt recall record: {}", e))?;
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| "Failed to decrypt recall record (wrong key?)".to_string())?;
+        serde_json::from_slice(&plaintext).map_err(|e| format!("Corrupt recall record: {}", e))
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create recall directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(&self.data)
+            .map_err(|e| format!("Failed to serialize recall store: {}", e))?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(|e| format!("Failed to write recall store: {}", e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Failed to write recall store: {}", e))
+    }
+}
+
+/// Pair each fill in a completed command with its field's label
+///
+/// Falls back to the field name, then the field id, when the snapshot has
+/// no label for it.
+pub fn labelled_fills(
+    command: &FillCommandJson,
+    fields: &[FieldNodeJson],
+) -> Vec<(String, String)> {
+    command
+        .fills
+        .iter()
+        .map(|fill| {
+            let field = fields.iter().find(|f| f.id == fill.field_id);
+            let label = field
+                .map(|f| {
+                    if f.label.is_empty() {
+                        &f.name
+                    } else {
+                        &f.label
+                    }
+                })
+                .filter(|l| !l.is_empty())
+                .unwrap_or(&fill.field_id);
+            (label.clone(), fill.value.clone())
+        })
+        .collect()
+}
+
+/// Read the recall key, generating and saving one on first use
+fn load_or_create_key(key_path: &Path) -> Result<Key, String> {
+    match fs::read(key_path) {
+        Ok(bytes) if bytes.len() == 32 => return Ok(*Key::from_slice(&bytes)),
+        Ok(_) => return Err("Recall key file is corrupt".to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to read recall key: {}", e)),
+    }
+
+    if let Some(parent) = key_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create key directory: {}", e))?;
+    }
+    let key = ChaCha20Poly1305::generate_key(OsRng);
+    write_private(key_path, key.as_slice())
+        .map_err(|e| format!("Failed to write recall key: {}", e))?;
+    Ok(key)
+}
+
+/// Create `path` readable only by the owner and write `bytes` to it
+#[cfg(unix)]
+pub(crate) fn write_private(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(bytes)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn write_private(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    fs::write(path, bytes)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("asterisk-recall-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn fills(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(l, v)| (l.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_off_by_default() {
+        let mut store = RecallStore::in_memory();
+        assert!(!store.is_enabled("vendor.com"));
+        assert!(!store
+            .record("vendor.com", &fills(&[("PO Number", "PO-1")]))
+            .unwrap());
+        assert!(store.search("vendor.com", "").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_encryption_round_trip() {
+        let dir = temp_dir("roundtrip");
+        let path = dir.join("recall.json");
+        let key_path = dir.join("recall.key");
+
+        let mut store = RecallStore::load(&path, &key_path).unwrap();
+        store.set_enabled("Vendor.com", true).unwrap();
+        store
+            .record("vendor.com", &fills(&[("PO Number", "PO-12345")]))
+            .unwrap();
+
+        // Nothing readable on disk
+        let on_disk = fs::read_to_string(&path).unwrap();
+        assert!(!on_disk.contains("PO-12345"));
+        assert!(!on_disk.contains("PO Number"));
+
+        let reloaded = RecallStore::load(&path, &key_path).unwrap();
+        let found = reloaded.search("vendor.com", "po number").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].value, "PO-12345");
+
+        // A different key can't read the records
+        fs::remove_file(&key_path).unwrap();
+        let wrong_key = RecallStore::load(&path, &key_path).unwrap();
+        assert!(wrong_key.search("vendor.com", "").is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_per_domain_isolation() {
+        let mut store = RecallStore::in_memory();
+        store.set_enabled("vendor.com", true).unwrap();
+        store.set_enabled("other.org", true).unwrap();
+        store
+            .record("vendor.com", &fills(&[("PO Number", "PO-1")]))
+            .unwrap();
+        store
+            .record("other.org", &fills(&[("PO Number", "PO-2")]))
+            .unwrap();
+
+        let vendor = store.search("vendor.com", "").unwrap();
+        assert_eq!(vendor.len(), 1);
+        assert_eq!(vendor[0].value, "PO-1");
+        assert!(store.search("unknown.net", "").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_purge_domain() {
+        let mut store = RecallStore::in_memory();
+        store.set_enabled("vendor.com", true).unwrap();
+        store.set_enabled("other.org", true).unwrap();
+        store
+            .record("vendor.com", &fills(&[("A", "1"), ("B", "2")]))
+            .unwrap();
+        store.record("other.org", &fills(&[("A", "3")])).unwrap();
+
+        assert_eq!(store.purge("vendor.com").unwrap(), 2);
+        assert!(store.search("vendor.com", "").unwrap().is_empty());
+        assert_eq!(store.search("other.org", "").unwrap().len(), 1);
+    }
+}
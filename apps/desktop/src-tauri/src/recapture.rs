@@ -0,0 +1,215 @@
+/*!
+ * Re-capture Requests
+ *
+ * When the desktop knows its snapshot of a page is out of date it asks the
+ * extension to scrape the page again instead of waiting for the user to
+ * reload it. Requests queue here, next to fill commands, and reach the
+ * extension in the fill command poll response as `recaptureRequests`.
+ *
+ * There is at most one request per domain and form fingerprint; asking
+ * again while one is pending returns the pending one. A request lapses
+ * after `RECAPTURE_TTL_SECS`, and a fresh snapshot from its domain answers
+ * it.
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::fill::canonicalize_domain;
+
+/// How long a re-capture request stays pending
+pub const RECAPTURE_TTL_SECS: i64 = 60;
+
+/// Why the desktop wants a fresh snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecaptureReason {
+    /// Asked for from the UI
+    Requested,
+    /// A plan was generated from a snapshot too old to trust
+    StaleSnapshot,
+}
+
+/// A request for the extension to re-scrape a page
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecaptureRequestJson {
+    pub id: String,
+    pub domain: String,
+    /// Page the stale snapshot came from, to pick the tab
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub url: Option<String>,
+    /// Fingerprint of the stale form, when one is known
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fingerprint: Option<String>,
+    pub reason: RecaptureReason,
+    #[serde(rename = "requestedAt")]
+    pub requested_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+/// Pending re-capture requests
+#[derive(Debug, Default)]
+pub struct RecaptureQueue {
+    requests: Vec<(DateTime<Utc>, RecaptureRequestJson)>,
+    next_id: u64,
+}
+
+impl RecaptureQueue {
+    /// Ask for a fresh snapshot of `domain`
+    ///
+    /// Returns the request and whether it is new; a pending request for the
+    /// same domain and fingerprint is returned as is.
+    pub fn request(
+        &mut self,
+        domain: &str,
+        url: Option<&str>,
+        fingerprint: Option<&str>,
+        reason: RecaptureReason,
+        now: DateTime<Utc>,
+    ) -> (RecaptureRequestJson, bool) {
+        self.expire(now);
+        let domain = canonicalize_domain(domain);
+        let pending = self
+            .requests
+            .iter()
+            .find(|(_, r)| r.domain == domain && r.fingerprint.as_deref() == fingerprint);
+        if let Some((_, request)) = pending {
+            return (request.clone(), false);
+        }
+
+        self.next_id += 1;
+        let expires = now + Duration::seconds(RECAPTURE_TTL_SECS);
+        let request = RecaptureRequestJson {
+            id: format!("recapture-{}", self.next_id),
+            domain,
+            url: url.map(str::to_string),
+            fingerprint: fingerprint.map(str::to_string),
+            reason,
+            requested_at: now.to_rfc3339(),
+            expires_at: expires.to_rfc3339(),
+        };
+        self.requests.push((expires, request.clone()));
+        (request, true)
+    }
+
+    /// Unexpired requests, for one domain or all
+    pub fn pending(
+        &mut self,
+        domain: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Vec<RecaptureRequestJson> {
+        self.expire(now);
+        self.requests
+            .iter()
+            .filter(|(_, r)| domain.is_none_or(|d| r.domain == d))
+            .map(|(_, r)| r.clone())
+            .collect()
+    }
+
+    /// A snapshot of `domain` arrived; drop its requests and return how many
+    pub fn snapshot_arrived(&mut self, domain: &str) -> usize {
+        let domain = canonicalize_domain(domain);
+        let before = self.requests.len();
+        self.requests.retain(|(_, r)| r.domain != domain);
+        before - self.requests.len()
+    }
+
+    fn expire(&mut self, now: DateTime<Utc>) {
+        self.requests.retain(|(expires, _)| *expires > now);
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        "2026-05-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::seconds(secs)
+    }
+
+    #[test]
+    fn test_repeat_requests_are_deduplicated() {
+        let mut queue = RecaptureQueue::default();
+        let (first, new) = queue.request(
+            "Example.com",
+            Some("https://example.com/signup"),
+            Some("fp1"),
+            RecaptureReason::StaleSnapshot,
+            at(0),
+        );
+        assert!(new);
+        assert_eq!(first.domain, "example.com");
+
+        let (again, new) = queue.request(
+            "example.com",
+            None,
+            Some("fp1"),
+            RecaptureReason::Requested,
+            at(10),
+        );
+        assert!(!new);
+        assert_eq!(again, first);
+
+        let (_, new) = queue.request(
+            "example.com",
+            None,
+            Some("fp2"),
+            RecaptureReason::Requested,
+            at(10),
+        );
+        assert!(new);
+        assert_eq!(queue.pending(Some("example.com"), at(10)).len(), 2);
+        assert!(queue.pending(Some("other.org"), at(10)).is_empty());
+    }
+
+    #[test]
+    fn test_requests_expire() {
+        let mut queue = RecaptureQueue::default();
+        let (first, _) =
+            queue.request("example.com", None, None, RecaptureReason::Requested, at(0));
+        assert_eq!(queue.pending(None, at(RECAPTURE_TTL_SECS - 1)).len(), 1);
+        assert!(queue.pending(None, at(RECAPTURE_TTL_SECS)).is_empty());
+
+        // Once lapsed, asking again makes a new request
+        let (second, new) = queue.request(
+            "example.com",
+            None,
+            None,
+            RecaptureReason::Requested,
+            at(RECAPTURE_TTL_SECS),
+        );
+        assert!(new);
+        assert_ne!(second.id, first.id);
+    }
+
+    #[test]
+    fn test_snapshot_clears_its_domain() {
+        let mut queue = RecaptureQueue::default();
+        queue.request(
+            "example.com",
+            None,
+            Some("fp1"),
+            RecaptureReason::Requested,
+            at(0),
+        );
+        queue.request(
+            "example.com",
+            None,
+            Some("fp2"),
+            RecaptureReason::Requested,
+            at(0),
+        );
+        queue.request("other.org", None, None, RecaptureReason::Requested, at(0));
+
+        assert_eq!(queue.snapshot_arrived("EXAMPLE.com"), 2);
+        let pending = queue.pending(None, at(1));
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].domain, "other.org");
+        assert_eq!(queue.snapshot_arrived("example.com"), 0);
+    }
+}
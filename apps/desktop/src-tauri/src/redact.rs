@@ -0,0 +1,83 @@
+/*!
+ * Value Redaction
+ *
+ * Rust port of `redactValue` in `src/types/audit.ts`, used wherever a vault
+ * value has to be shown or logged without revealing it. `value_hash` stands
+ * in for a value where only its identity matters, as in plan hashes.
+ */
+
+use sha2::{Digest, Sha256};
+
+use crate::RedactionLevel;
+
+/// Longest redacted output, matching the TypeScript default
+const MAX_REDACTED_LENGTH: usize = 64;
+
+/// Redact a value for logs and previews
+///
+/// Sensitive values are fully masked; values of four characters or fewer are
+/// kept; anything else keeps its first and last two characters.
+pub fn redact_value(value: &str, sensitive: bool) -> (String, RedactionLevel) {
+    if value.is_empty() {
+        return (String::new(), RedactionLevel::None);
+    }
+    if sensitive {
+        return ("••••••".to_string(), RedactionLevel::Masked);
+    }
+
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() <= 4 {
+        return (value.to_string(), RedactionLevel::None);
+    }
+
+    let first: String = chars[..2].iter().collect();
+    let last: String = chars[chars.len() - 2..].iter().collect();
+    let middle = (chars.len() - 4).min(MAX_REDACTED_LENGTH - 4);
+    (
+        format!("{}{}{}", first, "•".repeat(middle), last),
+        RedactionLevel::Partial,
+    )
+}
+
+/// Stable hex digest of a value, the same on every run and machine
+pub fn value_hash(value: &str) -> String {
+    let digest = Sha256::new()
+        .chain_update(b"asterisk-value\0")
+        .chain_update(value.as_bytes())
+        .finalize();
+    digest[..16].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_redaction() {
+        let (redacted, level) = redact_value("alice@example.com", false);
+        assert_eq!(redacted, "al•••••••••••••om");
+        assert!(matches!(level, RedactionLevel::Partial));
+    }
+
+    #[test]
+    fn test_sensitive_fully_masked() {
+        let (redacted, level) = redact_value("123-45-6789", true);
+        assert_eq!(redacted, "••••••");
+        assert!(matches!(level, RedactionLevel::Masked));
+    }
+
+    #[test]
+    fn test_short_and_empty_values() {
+        assert_eq!(redact_value("abc", false).0, "abc");
+        assert_eq!(redact_value("", true).0, "");
+    }
+
+    #[test]
+    fn test_value_hash_is_stable_and_hides_the_value() {
+        let hash = value_hash("alice@example.com");
+        assert_eq!(hash, value_hash("alice@example.com"));
+        assert_eq!(hash.len(), 32);
+        assert!(!hash.contains("alice"));
+        assert_ne!(hash, value_hash("alice@example.co"));
+    }
+}
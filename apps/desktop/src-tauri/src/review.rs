@@ -0,0 +1,105 @@
+/*!
+ * Vault Review Queue
+ *
+ * Items Asterisk didn't get from the user directly, imported with low
+ * provenance confidence, start out `Unreviewed` and wait here until the
+ * user approves or rejects them. Until then matching still offers them but
+ * never as Safe (see `matching::UNREVIEWED_CONFIDENCE_CAP`); a rejected item
+ * stays in the vault but is never matched.
+ */
+
+use asterisk_vault::{ReviewStatus, VaultItem, VaultStore};
+use serde::{Deserialize, Serialize};
+
+use crate::history::VaultHistory;
+
+/// The user's verdict on a queued item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewDecision {
+    Approve,
+    Reject,
+}
+
+/// Items waiting for review, oldest first
+pub fn queue(store: &dyn VaultStore) -> Result<Vec<VaultItem>, String> {
+    let mut items: Vec<VaultItem> = store
+        .list()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|item| item.review_status == ReviewStatus::Unreviewed)
+        .collect();
+    items.sort_by(|a, b| (a.metadata.created, &a.key).cmp(&(b.metadata.created, &b.key)));
+    Ok(items)
+}
+
+/// Approve or reject `key`, returning the item as stored
+///
+/// Items already resolved can be resolved again, so a rejection can be
+/// undone by approving.
+pub fn resolve(
+    store: &mut dyn VaultStore,
+    history: &VaultHistory,
+    key: &str,
+    decision: ReviewDecision,
+) -> Result<VaultItem, String> {
+    let mut item = store
+        .get(key)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No vault item {}", key))?;
+    item.resolve_review(decision == ReviewDecision::Approve);
+    store
+        .set(key.to_string(), item.clone())
+        .map_err(|e| e.to_string())?;
+    history.record_set(&item, true, "vault_review_resolve")?;
+    Ok(item)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asterisk_vault::{test_util, InMemoryStore, Provenance, ProvenanceSource, VaultCategory};
+
+    fn item(key: &str, source: ProvenanceSource, confidence: f64) -> VaultItem {
+        let provenance = Provenance {
+            source,
+            confidence,
+            ..test_util::user_entered()
+        };
+        VaultItem::new(key, "value", key, VaultCategory::Contact, provenance)
+    }
+
+    #[test]
+    fn test_queue_lifecycle() {
+        let mut store = InMemoryStore::with_items(vec![
+            item("email", ProvenanceSource::UserEntered, 0.5),
+            item("phone", ProvenanceSource::Imported, 0.6),
+            item("city", ProvenanceSource::Imported, 0.95),
+            item("company", ProvenanceSource::Autofilled, 0.4),
+        ]);
+        let history = VaultHistory::new(std::env::temp_dir().join("asterisk-review-unused.jsonl"));
+        history.set_enabled(false);
+        let keys = |store: &InMemoryStore| -> Vec<String> {
+            let mut keys: Vec<String> = queue(store).unwrap().into_iter().map(|i| i.key).collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(keys(&store), vec!["company", "phone"]);
+
+        let approved = resolve(&mut store, &history, "phone", ReviewDecision::Approve).unwrap();
+        assert_eq!(approved.review_status, ReviewStatus::Approved);
+        assert_eq!(approved.provenance.confidence, 1.0);
+        resolve(&mut store, &history, "company", ReviewDecision::Reject).unwrap();
+        assert!(keys(&store).is_empty());
+        assert_eq!(
+            store.get("company").unwrap().unwrap().review_status,
+            ReviewStatus::Rejected
+        );
+
+        assert!(resolve(&mut store, &history, "missing", ReviewDecision::Approve).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+/*!
+ * Fill Rules
+ *
+ * User-defined guardrails checked against each planned fill, such as "never
+ * fill financial fields on non-HTTPS pages" or "confirm anything on
+ * *.gov". A rule is a condition and an action (allow, block, or
+ * require-confirm). Every condition a rule sets must hold for it to match;
+ * conditions it leaves out match anything.
+ *
+ * Rules are checked in order and the first match decides, so an `allow`
+ * rule placed first carves an exception out of the rules after it. A fill
+ * no rule matches is allowed.
+ *
+ * Rules live in `fill-rules.json` in the data directory, a JSON array:
+ *
+ * ```json
+ * [
+ *   { "name": "No cards over HTTP",
+ *     "when": { "category": "financial", "https": false },
+ *     "action": "block" },
+ *   { "name": "Confirm on government sites",
+ *     "when": { "domain": "*.gov" },
+ *     "action": "require-confirm" }
+ * ]
+ * ```
+ */
+
+use asterisk_vault::VaultCategory;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::fill::canonicalize_domain;
+use crate::matching::FillPlanJson;
+use crate::FormSnapshotJson;
+
+/// File in the data directory holding the rules
+pub const RULES_FILE: &str = "fill-rules.json";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// What a matching rule does to a fill
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleAction {
+    #[default]
+    Allow,
+    Block,
+    RequireConfirm,
+}
+
+/// When a rule applies; unset conditions match anything
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuleConditionJson {
+    /// Page domain: `example.com` exactly, or `*.example.com` for it and
+    /// its subdomains
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    /// Whether the page was served over HTTPS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub https: Option<bool>,
+    /// Category of the vault item being filled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<VaultCategory>,
+    /// Input type of the field, e.g. `password`
+    #[serde(rename = "fieldType", default, skip_serializing_if = "Option::is_none")]
+    pub field_type: Option<String>,
+}
+
+/// A user-defined fill rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FillRuleJson {
+    pub name: String,
+    #[serde(default)]
+    pub when: RuleConditionJson,
+    pub action: RuleAction,
+}
+
+/// What is known about one planned fill
+#[derive(Debug, Clone, Copy)]
+pub struct RuleContext<'a> {
+    /// URL of the page the form is on
+    pub url: &'a str,
+    /// Input type of the field; empty if unknown
+    pub field_type: &'a str,
+    /// Category of the vault item filled; None if it is no longer there
+    pub category: Option<&'a VaultCategory>,
+}
+
+/// The outcome for one fill, and the rule that decided it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleVerdict {
+    pub action: RuleAction,
+    /// Name of the first matching rule; None when no rule matched
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub rule: Option<String>,
+}
+
+/// A verdict for one field of a plan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldVerdictJson {
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    #[serde(flatten)]
+    pub verdict: RuleVerdict,
+}
+
+// ============================================================================
+// Evaluation
+// ============================================================================
+
+/// Whether `domain` (canonical) matches `pattern`
+fn domain_matches(pattern: &str, domain: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(parent) => {
+            let parent = canonicalize_domain(parent);
+            domain == parent || domain.ends_with(&format!(".{}", parent))
+        }
+        None => domain == canonicalize_domain(pattern),
+    }
+}
+
+impl RuleConditionJson {
+    fn matches(&self, context: &RuleContext) -> bool {
+        let https = context
+            .url
+            .trim()
+            .get(..8)
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case("https://"));
+        self.domain
+            .as_deref()
+            .is_none_or(|pattern| domain_matches(pattern, &canonicalize_domain(context.url)))
+            && self.https.is_none_or(|wanted| wanted == https)
+            && self
+                .category
+                .as_ref()
+                .is_none_or(|wanted| context.category == Some(wanted))
+            && self
+                .field_type
+                .as_deref()
+                .is_none_or(|wanted| wanted.eq_ignore_ascii_case(context.field_type))
+    }
+}
+
+/// The verdict of the first rule matching `context`; allow if none does
+pub fn evaluate_rules(rules: &[FillRuleJson], context: &RuleContext) -> RuleVerdict {
+    rules
+        .iter()
+        .find(|rule| rule.when.matches(context))
+        .map_or_else(
+            || RuleVerdict {
+                action: RuleAction::Allow,
+                rule: None,
+            },
+            |rule| RuleVerdict {
+                action: rule.action,
+                rule: Some(rule.name.clone()),
+            },
+        )
+}
+
+/// A verdict for every fill of `plan` on the page of `snapshot`
+/// (`category_of` looks up a vault key's category)
+pub fn check_plan(
+    rules: &[FillRuleJson],
+    plan: &FillPlanJson,
+    snapshot: &FormSnapshotJson,
+    category_of: impl Fn(&str) -> Option<VaultCategory>,
+) -> Vec<FieldVerdictJson> {
+    plan.recommendations
+        .iter()
+        .map(|rec| {
+            let field_type = snapshot
+                .fields
+                .iter()
+                .find(|field| field.id == rec.field_id)
+                .map_or("", |field| field.field_type.as_str());
+            let category = category_of(&rec.vault_key);
+            let context = RuleContext {
+                url: &snapshot.url,
+                field_type,
+                category: category.as_ref(),
+            };
+            FieldVerdictJson {
+                field_id: rec.field_id.clone(),
+                verdict: evaluate_rules(rules, &context),
+            }
+        })
+        .collect()
+}
+
+/// Load rules from `path`; a missing file means no rules
+pub fn load(path: &Path) -> Result<Vec<FillRuleJson>, String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse fill rules: {}", e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("Failed to read fill rules: {}", e)),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> Vec<FillRuleJson> {
+        serde_json::from_str(
+            r#"[
+                { "name": "No cards over HTTP",
+                  "when": { "category": "financial", "https": false },
+                  "action": "block" },
+                { "name": "Confirm on government sites",
+                  "when": { "domain": "*.gov" },
+                  "action": "require-confirm" }
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    fn context<'a>(url: &'a str, category: Option<&'a VaultCategory>) -> RuleContext<'a> {
+        RuleContext {
+            url,
+            field_type: "text",
+            category,
+        }
+    }
+
+    #[test]
+    fn test_block_financial_fill_on_non_https() {
+        let rules = rules();
+        let financial = VaultCategory::Financial;
+
+        let verdict = evaluate_rules(
+            &rules,
+            &context("http://shop.example/pay", Some(&financial)),
+        );
+        assert_eq!(verdict.action, RuleAction::Block);
+        assert_eq!(verdict.rule.as_deref(), Some("No cards over HTTP"));
+
+        // Over HTTPS, or for another category, the rule doesn't apply
+        let secure = evaluate_rules(
+            &rules,
+            &context("HTTPS://shop.example/pay", Some(&financial)),
+        );
+        assert_eq!(
+            secure,
+            RuleVerdict {
+                action: RuleAction::Allow,
+                rule: None
+            }
+        );
+        let contact = VaultCategory::Contact;
+        let verdict = evaluate_rules(&rules, &context("http://shop.example/pay", Some(&contact)));
+        assert_eq!(verdict.action, RuleAction::Allow);
+    }
+
+    #[test]
+    fn test_require_confirm_on_domain_pattern() {
+        let rules = rules();
+        for url in [
+            "https://irs.gov/form",
+            "https://www.irs.gov:443/form",
+            "https://gov",
+        ] {
+            assert_eq!(
+                evaluate_rules(&rules, &context(url, None)).action,
+                RuleAction::RequireConfirm,
+                "{}",
+                url
+            );
+        }
+        for url in [
+            "https://gov.example.com",
+            "https://notgov/form",
+            "https://irs.gov.evil.example",
+        ] {
+            assert_eq!(
+                evaluate_rules(&rules, &context(url, None)).action,
+                RuleAction::Allow,
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_first_matching_rule_decides() {
+        let mut rules = rules();
+        rules.insert(
+            0,
+            FillRuleJson {
+                name: "Trusted intranet".to_string(),
+                when: RuleConditionJson {
+                    domain: Some("intranet.example".to_string()),
+                    ..Default::default()
+                },
+                action: RuleAction::Allow,
+            },
+        );
+        let financial = VaultCategory::Financial;
+        let verdict = evaluate_rules(
+            &rules,
+            &context("http://intranet.example", Some(&financial)),
+        );
+        assert_eq!(verdict.action, RuleAction::Allow);
+        assert_eq!(verdict.rule.as_deref(), Some("Trusted intranet"));
+    }
+}
@@ -0,0 +1,598 @@
+/*!
+ * Form Scenario Fixtures
+ *
+ * Regression coverage for matching quality. The corpus under
+ * `fixtures/forms/` holds realistic (anonymized) signup, checkout,
+ * job-application and government forms. Each fixture is one JSON file:
+ *
+ * - `snapshot`: the captured form, as the extension sends it
+ * - `vault`: name of a persona vault in `fixtures/forms/vaults/`
+ * - `llm`: what the mock LLM answers for a field, by field id
+ * - `expected`: per field, the right vault key (or null for "leave empty"),
+ *   a minimum confidence and the disposition the review UI should show
+ *
+ * The harness runs `generate_fill_plan`, sends whatever is left unmatched to
+ * the mock LLM, and diffs the result against the expectations. Aggregate
+ * precision and recall are reported so a matching change shows its quality
+ * impact. Expectations describe the right answer, not today's answer; a
+ * field the matcher is known to get wrong is marked `knownGap` so it counts
+ * against the score without failing the run.
+ *
+ * New fixtures can be started from a captured snapshot with the `fixture`
+ * binary (dev-tools feature), then corrected by hand.
+ */
+
+use asterisk_vault::{Provenance, ProvenanceSource, VaultCategory, VaultItem};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::llm::{self, AnalysisCache, MockProvider, PromptRedaction};
+use crate::matching::{self, FillRecommendationJson, MatchTier};
+use crate::{Disposition, FormSnapshotJson};
+
+/// Confidence the mock LLM reports for its answers
+const MOCK_LLM_CONFIDENCE: f64 = 0.9;
+
+// ============================================================================
+// Fixture Format
+// ============================================================================
+
+/// A vault item in a persona file (values are made up)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureVaultItemJson {
+    pub key: String,
+    pub label: String,
+    pub category: VaultCategory,
+    pub value: String,
+}
+
+impl FixtureVaultItemJson {
+    fn to_item(&self) -> VaultItem {
+        VaultItem::new(
+            &self.key,
+            &self.value,
+            &self.label,
+            self.category.clone(),
+            Provenance {
+                source: ProvenanceSource::UserEntered,
+                timestamp: chrono::DateTime::UNIX_EPOCH,
+                confidence: 1.0,
+                origin: None,
+            },
+        )
+    }
+}
+
+/// What the plan should say about one field
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExpectedFieldJson {
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    /// None: the field should stay empty
+    #[serde(rename = "vaultKey")]
+    pub vault_key: Option<String>,
+    #[serde(rename = "minConfidence", default)]
+    pub min_confidence: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disposition: Option<Disposition>,
+    /// The matcher is known to get this field wrong today
+    #[serde(
+        rename = "knownGap",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub known_gap: bool,
+}
+
+/// One scenario: a form, who is filling it, and the right plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormFixtureJson {
+    pub name: String,
+    pub description: String,
+    /// Persona vault file name, without `.json`
+    pub vault: String,
+    pub snapshot: FormSnapshotJson,
+    /// Mock LLM answers (vault key) for fields the local tiers leave unmatched
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub llm: HashMap<String, String>,
+    pub expected: Vec<ExpectedFieldJson>,
+}
+
+/// A fixture with its persona vault loaded
+#[derive(Debug, Clone)]
+pub struct LoadedFixture {
+    pub path: PathBuf,
+    pub fixture: FormFixtureJson,
+    pub vault: Vec<VaultItem>,
+}
+
+/// Directory holding the corpus, relative to the crate root
+pub fn corpus_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/forms")
+}
+
+/// Load a persona vault from `<dir>/vaults/<name>.json`
+pub fn load_vault(dir: &Path, name: &str) -> Result<Vec<VaultItem>, String> {
+    let path = dir.join("vaults").join(format!("{}.json", name));
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let items: Vec<FixtureVaultItemJson> = serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid vault {}: {}", path.display(), e))?;
+    Ok(items.iter().map(FixtureVaultItemJson::to_item).collect())
+}
+
+/// Load every fixture in `dir`, sorted by file name
+pub fn load_corpus(dir: &Path) -> Result<Vec<LoadedFixture>, String> {
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let fixture: FormFixtureJson = serde_json::from_str(&text)
+                .map_err(|e| format!("Invalid fixture {}: {}", path.display(), e))?;
+            let vault = load_vault(dir, &fixture.vault)?;
+            Ok(LoadedFixture {
+                path,
+                fixture,
+                vault,
+            })
+        })
+        .collect()
+}
+
+// ============================================================================
+// Running
+// ============================================================================
+
+/// Local plan plus mock LLM answers for what it left unmatched
+pub async fn run_plan(
+    fixture: &FormFixtureJson,
+    vault: &[VaultItem],
+) -> Vec<FillRecommendationJson> {
+    let plan = matching::generate_fill_plan(&fixture.snapshot, vault, None);
+    let mut recommendations = plan.recommendations;
+
+    // The mock serves replies in order; line them up with the fields that
+    // will be analyzed, which follow snapshot order
+    let replies: Vec<String> = fixture
+        .snapshot
+        .fields
+        .iter()
+        .filter(|f| plan.unmatched_fields.contains(&f.id))
+        .map(|f| match fixture.llm.get(&f.id) {
+            Some(key) => serde_json::json!({
+                "vaultKey": key,
+                "confidence": MOCK_LLM_CONFIDENCE,
+                "reasoning": "Fixture answer",
+            })
+            .to_string(),
+            None => MockProvider::NO_MATCH.to_string(),
+        })
+        .collect();
+    if replies.is_empty() {
+        return recommendations;
+    }
+
+    let keys: Vec<String> = vault.iter().map(|item| item.key.clone()).collect();
+    let provider = MockProvider::new(replies);
+    let analyses = llm::analyze_snapshot(
+        &provider,
+        &fixture.snapshot,
+        &plan.unmatched_fields,
+        None,
+        &keys,
+        &PromptRedaction::default(),
+        &AnalysisCache::new(),
+    )
+    .await;
+    for analysis in analyses {
+        let Some(response) = analysis.result else {
+            continue;
+        };
+        let Some(vault_key) = response.vault_key else {
+            continue;
+        };
+        let required = fixture
+            .snapshot
+            .fields
+            .iter()
+            .any(|f| f.id == analysis.field_id && f.required);
+        recommendations.push(FillRecommendationJson {
+            field_id: analysis.field_id,
+            vault_key,
+            confidence: response.confidence,
+            reason: response.reasoning,
+            reason_message: None,
+            required,
+            match_tier: MatchTier::Llm,
+            label_source: None,
+            value: None,
+        });
+    }
+    recommendations
+}
+
+/// A field whose plan differs from its expectation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    pub fixture: String,
+    pub field_id: String,
+    pub problem: String,
+    pub known_gap: bool,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let gap = if self.known_gap { " (known gap)" } else { "" };
+        write!(
+            f,
+            "{} / {}: {}{}",
+            self.fixture, self.field_id, self.problem, gap
+        )
+    }
+}
+
+/// Matching quality across fixtures
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Score {
+    /// Fields filled with the expected key
+    pub correct: u32,
+    /// Fields filled with the wrong key, or filled when they should stay empty
+    pub wrong: u32,
+    /// Fields left empty that should have been filled
+    pub missed: u32,
+}
+
+impl Score {
+    /// Share of filled fields that were filled correctly
+    pub fn precision(&self) -> f64 {
+        let filled = self.correct + self.wrong;
+        if filled == 0 {
+            1.0
+        } else {
+            self.correct as f64 / filled as f64
+        }
+    }
+
+    /// Share of fillable fields that were filled correctly
+    pub fn recall(&self) -> f64 {
+        // A wrong key for a fillable field is also a miss of the right one
+        let fillable = self.correct + self.missed;
+        if fillable == 0 {
+            1.0
+        } else {
+            self.correct as f64 / fillable as f64
+        }
+    }
+
+    fn add(&mut self, other: Score) {
+        self.correct += other.correct;
+        self.wrong += other.wrong;
+        self.missed += other.missed;
+    }
+}
+
+/// Compare a plan against a fixture's expectations
+pub fn diff(
+    fixture: &FormFixtureJson,
+    recommendations: &[FillRecommendationJson],
+) -> (Score, Vec<Mismatch>) {
+    let mut score = Score::default();
+    let mut mismatches = Vec::new();
+
+    for expected in &fixture.expected {
+        let actual = recommendations
+            .iter()
+            .find(|r| r.field_id == expected.field_id);
+        let mut problems = Vec::new();
+
+        match (&expected.vault_key, actual) {
+            (Some(key), Some(actual)) if *key == actual.vault_key => {
+                score.correct += 1;
+                if actual.confidence < expected.min_confidence {
+                    problems.push(format!(
+                        "confidence {:.2} below {:.2}",
+                        actual.confidence, expected.min_confidence
+                    ));
+                }
+                let disposition = Disposition::for_confidence(actual.confidence);
+                if expected.disposition.is_some_and(|d| d != disposition) {
+                    problems.push(format!(
+                        "disposition {:?}, expected {:?}",
+                        disposition,
+                        expected.disposition.unwrap_or(disposition)
+                    ));
+                }
+            }
+            (Some(key), Some(actual)) => {
+                score.wrong += 1;
+                score.missed += 1;
+                problems.push(format!(
+                    "filled with {}, expected {}",
+                    actual.vault_key, key
+                ));
+            }
+            (Some(key), None) => {
+                score.missed += 1;
+                problems.push(format!("left empty, expected {}", key));
+            }
+            (None, Some(actual)) => {
+                score.wrong += 1;
+                problems.push(format!("filled with {}, expected empty", actual.vault_key));
+            }
+            (None, None) => {}
+        }
+
+        mismatches.extend(problems.into_iter().map(|problem| Mismatch {
+            fixture: fixture.name.clone(),
+            field_id: expected.field_id.clone(),
+            problem,
+            known_gap: expected.known_gap,
+        }));
+    }
+
+    // Every captured field needs an expectation, or it goes unchecked
+    for field in &fixture.snapshot.fields {
+        if !fixture.expected.iter().any(|e| e.field_id == field.id) {
+            mismatches.push(Mismatch {
+                fixture: fixture.name.clone(),
+                field_id: field.id.clone(),
+                problem: "no expectation".to_string(),
+                known_gap: false,
+            });
+        }
+    }
+
+    (score, mismatches)
+}
+
+/// Outcome of running the whole corpus
+#[derive(Debug, Clone, Default)]
+pub struct CorpusReport {
+    pub fixtures: usize,
+    pub score: Score,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl CorpusReport {
+    /// Mismatches not marked as known gaps
+    pub fn regressions(&self) -> Vec<&Mismatch> {
+        self.mismatches.iter().filter(|m| !m.known_gap).collect()
+    }
+}
+
+impl fmt::Display for CorpusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} fixtures: precision {:.1}%, recall {:.1}% ({} correct, {} wrong, {} missed)",
+            self.fixtures,
+            self.score.precision() * 100.0,
+            self.score.recall() * 100.0,
+            self.score.correct,
+            self.score.wrong,
+            self.score.missed
+        )?;
+        for mismatch in &self.mismatches {
+            writeln!(f, "  {}", mismatch)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run every fixture and collect the score and mismatches
+pub async fn run_corpus(fixtures: &[LoadedFixture]) -> CorpusReport {
+    let mut report = CorpusReport {
+        fixtures: fixtures.len(),
+        ..Default::default()
+    };
+    for loaded in fixtures {
+        let recommendations = run_plan(&loaded.fixture, &loaded.vault).await;
+        let (score, mismatches) = diff(&loaded.fixture, &recommendations);
+        report.score.add(score);
+        report.mismatches.extend(mismatches);
+    }
+    report
+}
+
+// ============================================================================
+// Authoring
+// ============================================================================
+
+/// Start a fixture from a captured snapshot
+///
+/// Expectations are filled in from today's local plan and must be reviewed
+/// by hand: fix wrong keys, set the right key for fields left empty (adding
+/// an `llm` answer if only the LLM tier can get it), and mark fields the
+/// matcher can't handle yet as `knownGap`.
+pub fn fixture_from_snapshot(
+    name: &str,
+    persona: &str,
+    snapshot: FormSnapshotJson,
+    vault: &[VaultItem],
+) -> FormFixtureJson {
+    let plan = matching::generate_fill_plan(&snapshot, vault, None);
+    let expected = snapshot
+        .fields
+        .iter()
+        .map(|field| {
+            let recommendation = plan.recommendations.iter().find(|r| r.field_id == field.id);
+            ExpectedFieldJson {
+                field_id: field.id.clone(),
+                vault_key: recommendation.map(|r| r.vault_key.clone()),
+                min_confidence: recommendation.map_or(0.0, |r| r.confidence),
+                disposition: recommendation.map(|r| Disposition::for_confidence(r.confidence)),
+                known_gap: false,
+            }
+        })
+        .collect();
+
+    FormFixtureJson {
+        name: name.to_string(),
+        description: format!("{} ({})", snapshot.title, snapshot.domain),
+        vault: persona.to_string(),
+        snapshot,
+        llm: HashMap::new(),
+        expected,
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_form_corpus() {
+        let fixtures = load_corpus(&corpus_dir()).unwrap();
+        assert!(
+            fixtures.len() >= 25,
+            "corpus has {} fixtures",
+            fixtures.len()
+        );
+
+        let report = run_corpus(&fixtures).await;
+        println!("{}", report);
+        let regressions = report.regressions();
+        assert!(
+            regressions.is_empty(),
+            "{} unexpected mismatch(es):\n{}",
+            regressions.len(),
+            report
+        );
+    }
+
+    #[test]
+    fn test_fixture_names_match_files() {
+        for loaded in load_corpus(&corpus_dir()).unwrap() {
+            let stem = loaded.path.file_stem().unwrap().to_string_lossy();
+            assert_eq!(loaded.fixture.name, stem);
+        }
+    }
+
+    fn recommendation(field_id: &str, key: &str, confidence: f64) -> FillRecommendationJson {
+        FillRecommendationJson {
+            field_id: field_id.to_string(),
+            vault_key: key.to_string(),
+            confidence,
+            reason: String::new(),
+            reason_message: None,
+            required: false,
+            match_tier: MatchTier::Pattern,
+            label_source: None,
+            value: None,
+        }
+    }
+
+    fn expect(field_id: &str, key: Option<&str>) -> ExpectedFieldJson {
+        ExpectedFieldJson {
+            field_id: field_id.to_string(),
+            vault_key: key.map(str::to_string),
+            min_confidence: 0.0,
+            disposition: None,
+            known_gap: false,
+        }
+    }
+
+    fn fixture(expected: Vec<ExpectedFieldJson>) -> FormFixtureJson {
+        let fields = expected
+            .iter()
+            .map(|e| crate::FieldNodeJson {
+                id: e.field_id.clone(),
+                ..Default::default()
+            })
+            .collect();
+        FormFixtureJson {
+            name: "unit".to_string(),
+            description: String::new(),
+            vault: String::new(),
+            snapshot: FormSnapshotJson {
+                url: String::new(),
+                domain: String::new(),
+                title: String::new(),
+                captured_at: String::new(),
+                fingerprint: crate::FormFingerprintJson {
+                    field_count: 0,
+                    field_types: vec![],
+                    required_count: 0,
+                    hash: String::new(),
+                },
+                fields,
+                session: Default::default(),
+            },
+            llm: HashMap::new(),
+            expected,
+        }
+    }
+
+    #[test]
+    fn test_diff_scores_each_outcome() {
+        let fixture = fixture(vec![
+            expect("a", Some("email")),
+            expect("b", Some("phone")),
+            expect("c", Some("city")),
+            expect("d", None),
+            expect("e", None),
+        ]);
+        let plan = vec![
+            recommendation("a", "email", 0.95),
+            recommendation("b", "email", 0.9),
+            recommendation("d", "zip", 0.85),
+        ];
+
+        let (score, mismatches) = diff(&fixture, &plan);
+        assert_eq!(
+            score,
+            Score {
+                correct: 1,
+                wrong: 2,
+                missed: 2
+            }
+        );
+        assert!((score.precision() - 1.0 / 3.0).abs() < 1e-9);
+        assert!((score.recall() - 1.0 / 3.0).abs() < 1e-9);
+        let fields: Vec<&str> = mismatches.iter().map(|m| m.field_id.as_str()).collect();
+        assert_eq!(fields, vec!["b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_diff_checks_confidence_and_disposition() {
+        let mut expected = expect("a", Some("email"));
+        expected.min_confidence = 0.9;
+        expected.disposition = Some(Disposition::Review);
+        let fixture = fixture(vec![expected]);
+
+        let (_, ok) = diff(&fixture, &[recommendation("a", "email", 0.95)]);
+        assert!(ok.is_empty());
+        let (score, low) = diff(&fixture, &[recommendation("a", "email", 0.85)]);
+        assert_eq!(score.correct, 1);
+        assert_eq!(low.len(), 2);
+    }
+
+    #[test]
+    fn test_known_gaps_are_not_regressions() {
+        let mut gap = expect("a", Some("email"));
+        gap.known_gap = true;
+        let (score, mismatches) = diff(&fixture(vec![gap]), &[]);
+        let report = CorpusReport {
+            fixtures: 1,
+            score,
+            mismatches,
+        };
+        assert_eq!(report.mismatches.len(), 1);
+        assert!(report.regressions().is_empty());
+    }
+}
@@ -0,0 +1,282 @@
+/*!
+ * Semantic Types
+ *
+ * The extension labels each field with a semantic hint, but hints also come
+ * from older extension builds, page `autocomplete` tokens and third-party
+ * capture tools, each spelling them its own way: `email`, `e-mail`,
+ * `email_address`, `postal-code`, `zip`. Snapshots are canonicalized on
+ * ingest so matching and LLM prompts only ever see the spellings of the
+ * extension's `FieldSemantic` (e.g. `zipCode`).
+ *
+ * Hints are compared with case, spaces and `-`/`_`/`.` ignored, so `E-Mail`
+ * and `e_mail` need no alias of their own. Anything it doesn't know becomes
+ * `unknown`.
+ */
+
+use serde::{Deserialize, Serialize};
+
+use crate::FieldNodeJson;
+
+/// What a field asks for, as the extension's `FieldSemantic` names it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SemanticType {
+    FirstName,
+    LastName,
+    FullName,
+    Email,
+    Phone,
+    Street,
+    City,
+    State,
+    ZipCode,
+    Country,
+    /// The whole postal address in one field
+    FullAddress,
+    CreditCard,
+    Cvv,
+    ExpiryDate,
+    Username,
+    Password,
+    DateOfBirth,
+    Company,
+    JobTitle,
+    #[default]
+    Unknown,
+}
+
+impl SemanticType {
+    /// The canonical spelling, as serialized
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SemanticType::FirstName => "firstName",
+            SemanticType::LastName => "lastName",
+            SemanticType::FullName => "fullName",
+            SemanticType::Email => "email",
+            SemanticType::Phone => "phone",
+            SemanticType::Street => "street",
+            SemanticType::City => "city",
+            SemanticType::State => "state",
+            SemanticType::ZipCode => "zipCode",
+            SemanticType::Country => "country",
+            SemanticType::FullAddress => "fullAddress",
+            SemanticType::CreditCard => "creditCard",
+            SemanticType::Cvv => "cvv",
+            SemanticType::ExpiryDate => "expiryDate",
+            SemanticType::Username => "username",
+            SemanticType::Password => "password",
+            SemanticType::DateOfBirth => "dateOfBirth",
+            SemanticType::Company => "company",
+            SemanticType::JobTitle => "jobTitle",
+            SemanticType::Unknown => "unknown",
+        }
+    }
+}
+
+/// Known spellings of each semantic type, normalized (lowercase, no
+/// separators)
+const ALIASES: &[(SemanticType, &[&str])] = &[
+    (
+        SemanticType::FirstName,
+        &["firstname", "givenname", "fname", "forename", "first"],
+    ),
+    (
+        SemanticType::LastName,
+        &["lastname", "familyname", "surname", "lname", "last"],
+    ),
+    (SemanticType::FullName, &["fullname", "name", "yourname"]),
+    (
+        SemanticType::Email,
+        &["email", "emailaddress", "mail", "emailaddr"],
+    ),
+    (
+        SemanticType::Phone,
+        &[
+            "phone",
+            "tel",
+            "telephone",
+            "phonenumber",
+            "telnational",
+            "mobile",
+            "mobilephone",
+            "cellphone",
+        ],
+    ),
+    (
+        SemanticType::Street,
+        &[
+            "street",
+            "addressline1",
+            "address1",
+            "streetline1",
+            "streetname",
+        ],
+    ),
+    (
+        SemanticType::City,
+        &["city", "addresslevel2", "town", "locality"],
+    ),
+    (
+        SemanticType::State,
+        &["state", "addresslevel1", "province", "region", "county"],
+    ),
+    (
+        SemanticType::ZipCode,
+        &["zipcode", "zip", "postalcode", "postcode", "postal", "plz"],
+    ),
+    (
+        SemanticType::Country,
+        &["country", "countryname", "countrycode"],
+    ),
+    (
+        SemanticType::FullAddress,
+        &["fulladdress", "streetaddress", "address", "postaladdress"],
+    ),
+    (
+        SemanticType::CreditCard,
+        &[
+            "creditcard",
+            "ccnumber",
+            "cardnumber",
+            "creditcardnumber",
+            "card",
+        ],
+    ),
+    (
+        SemanticType::Cvv,
+        &["cvv", "cvc", "csc", "cvv2", "ccv", "securitycode", "cccsc"],
+    ),
+    (
+        SemanticType::ExpiryDate,
+        &["expirydate", "expiry", "expirationdate", "ccexp", "expdate"],
+    ),
+    (SemanticType::Username, &["username", "login", "userid"]),
+    (
+        SemanticType::Password,
+        &[
+            "password",
+            "currentpassword",
+            "newpassword",
+            "passwd",
+            "pwd",
+        ],
+    ),
+    (
+        SemanticType::DateOfBirth,
+        &["dateofbirth", "dob", "birthday", "bday", "birthdate"],
+    ),
+    (
+        SemanticType::Company,
+        &[
+            "company",
+            "organization",
+            "organisation",
+            "employer",
+            "companyname",
+        ],
+    ),
+    (
+        SemanticType::JobTitle,
+        &["jobtitle", "organizationtitle", "position"],
+    ),
+];
+
+/// The semantic type a raw hint stands for; `Unknown` if none
+pub fn canonicalize_semantic(raw: &str) -> SemanticType {
+    let normalized: String = raw
+        .chars()
+        .filter(|c| !matches!(c, '-' | '_' | ' ' | '.'))
+        .flat_map(char::to_lowercase)
+        .collect();
+    ALIASES
+        .iter()
+        .find(|(_, aliases)| aliases.contains(&normalized.as_str()))
+        .map_or(SemanticType::Unknown, |(semantic, _)| *semantic)
+}
+
+/// Rewrite each field's hint to its canonical spelling
+pub fn canonicalize_fields(fields: &mut [FieldNodeJson]) {
+    for field in fields {
+        field.semantic = canonicalize_semantic(&field.semantic).as_str().to_string();
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_real_world_variants_map_to_one_type() {
+        let cases = [
+            (
+                SemanticType::Email,
+                &[
+                    "email",
+                    "E-Mail",
+                    "email-address",
+                    "email_address",
+                    "Email Address",
+                ][..],
+            ),
+            (
+                SemanticType::ZipCode,
+                &["zipCode", "zip", "postal-code", "PostCode", "postal_code"],
+            ),
+            (
+                SemanticType::FirstName,
+                &["firstName", "given-name", "first_name", "fname"],
+            ),
+            (
+                SemanticType::Phone,
+                &["tel", "phone-number", "Mobile", "tel-national"],
+            ),
+            (
+                SemanticType::CreditCard,
+                &["creditCard", "cc-number", "card_number"],
+            ),
+            (
+                SemanticType::FullAddress,
+                &["street-address", "full-address", "fullAddress"],
+            ),
+            (SemanticType::Street, &["address-line1", "street"]),
+            (
+                SemanticType::DateOfBirth,
+                &["dateOfBirth", "dob", "birth-date", "bday"],
+            ),
+        ];
+        for (expected, variants) in cases {
+            for raw in variants {
+                assert_eq!(canonicalize_semantic(raw), expected, "{}", raw);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unknown_hints_map_to_unknown() {
+        for raw in ["", "unknown", "favourite-colour", "emailx", "🙂"] {
+            assert_eq!(canonicalize_semantic(raw), SemanticType::Unknown, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn test_canonical_spellings_round_trip() {
+        for (semantic, _) in ALIASES {
+            assert_eq!(canonicalize_semantic(semantic.as_str()), *semantic);
+            assert_eq!(
+                serde_json::to_value(semantic).unwrap(),
+                serde_json::json!(semantic.as_str())
+            );
+        }
+
+        let mut fields = vec![FieldNodeJson {
+            semantic: "e-mail".to_string(),
+            ..Default::default()
+        }];
+        canonicalize_fields(&mut fields);
+        assert_eq!(fields[0].semantic, "email");
+    }
+}
@@ -0,0 +1,222 @@
+/*!
+ * Multi-Step Form Sessions
+ *
+ * Wizard-style forms post one snapshot per step. The extension tags each
+ * with a `formSessionId` and its `step`/`totalSteps`; the bridge groups the
+ * snapshots of a session here so that matching a later step can use what
+ * was matched in the earlier ones (see `matching::generate_session_fill_plan`).
+ *
+ * Sessions live in memory only and are bounded: at most `MAX_SESSIONS`
+ * sessions of `MAX_STEPS` steps each, the least recently updated session
+ * being dropped first. Posting a step again replaces the earlier capture of
+ * that step.
+ */
+
+use serde::{Deserialize, Serialize};
+
+use crate::shared::SharedStore;
+use crate::FormSnapshotJson;
+
+/// Sessions kept at once
+pub const MAX_SESSIONS: usize = 16;
+
+/// Steps kept per session
+pub const MAX_STEPS: usize = 20;
+
+/// Grouped multi-step sessions, as shared by commands and the bridge
+pub type FormSessionStore = SharedStore<FormSessions>;
+
+/// Where a snapshot sits in a multi-step form
+///
+/// Flattened into `FormSnapshotJson`; all fields are absent for ordinary
+/// single-page forms.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FormStepJson {
+    #[serde(
+        rename = "formSessionId",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub form_session_id: Option<String>,
+    /// 1-based step number
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<u32>,
+    #[serde(
+        rename = "totalSteps",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub total_steps: Option<u32>,
+}
+
+struct FormSession {
+    id: String,
+    /// Ordered by step
+    steps: Vec<FormSnapshotJson>,
+}
+
+/// Snapshots of recent multi-step sessions, grouped by session id
+#[derive(Default)]
+pub struct FormSessions {
+    /// Least recently updated first
+    sessions: Vec<FormSession>,
+}
+
+impl FormSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a snapshot to its session
+    ///
+    /// Snapshots without a session id or step number are not part of a
+    /// multi-step form and are ignored.
+    pub fn record(&mut self, snapshot: &FormSnapshotJson) {
+        let (Some(id), Some(step)) = (&snapshot.session.form_session_id, snapshot.session.step)
+        else {
+            return;
+        };
+
+        let mut session = match self.sessions.iter().position(|s| &s.id == id) {
+            Some(index) => self.sessions.remove(index),
+            None => FormSession {
+                id: id.clone(),
+                steps: Vec::new(),
+            },
+        };
+        session.steps.retain(|s| s.session.step != Some(step));
+        let at = session
+            .steps
+            .partition_point(|s| s.session.step.is_some_and(|n| n < step));
+        session.steps.insert(at, snapshot.clone());
+        if session.steps.len() > MAX_STEPS {
+            // Keep the steps nearest the one being filled
+            let excess = session.steps.len() - MAX_STEPS;
+            if at >= excess {
+                session.steps.drain(..excess);
+            } else {
+                session.steps.truncate(MAX_STEPS);
+            }
+        }
+
+        self.sessions.push(session);
+        if self.sessions.len() > MAX_SESSIONS {
+            self.sessions.remove(0);
+        }
+    }
+
+    /// Steps of the snapshot's session that come before it, in step order
+    pub fn earlier_steps(&self, snapshot: &FormSnapshotJson) -> Vec<FormSnapshotJson> {
+        let (Some(id), Some(step)) = (&snapshot.session.form_session_id, snapshot.session.step)
+        else {
+            return Vec::new();
+        };
+        self.sessions
+            .iter()
+            .find(|s| &s.id == id)
+            .map(|s| {
+                s.steps
+                    .iter()
+                    .filter(|earlier| earlier.session.step.is_some_and(|n| n < step))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Number of steps recorded for a session
+    pub fn step_count(&self, session_id: &str) -> usize {
+        self.sessions
+            .iter()
+            .find(|s| s.id == session_id)
+            .map_or(0, |s| s.steps.len())
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(session: &str, n: u32, title: &str) -> FormSnapshotJson {
+        FormSnapshotJson {
+            url: "https://wizard.example/apply".to_string(),
+            domain: "wizard.example".to_string(),
+            title: title.to_string(),
+            captured_at: chrono::Utc::now().to_rfc3339(),
+            fingerprint: crate::FormFingerprintJson {
+                field_count: 0,
+                field_types: vec![],
+                required_count: 0,
+                hash: format!("step{}", n),
+            },
+            fields: vec![],
+            session: FormStepJson {
+                form_session_id: Some(session.to_string()),
+                step: Some(n),
+                total_steps: Some(3),
+            },
+        }
+    }
+
+    #[test]
+    fn test_steps_are_grouped_and_ordered() {
+        let mut sessions = FormSessions::new();
+        sessions.record(&step("a", 2, "two"));
+        sessions.record(&step("b", 1, "other session"));
+        sessions.record(&step("a", 1, "one"));
+        sessions.record(&step("a", 1, "one again"));
+
+        let earlier = sessions.earlier_steps(&step("a", 3, "three"));
+        let titles: Vec<&str> = earlier.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, vec!["one again", "two"]);
+        assert!(sessions.earlier_steps(&step("a", 1, "one")).is_empty());
+        assert_eq!(sessions.step_count("b"), 1);
+    }
+
+    #[test]
+    fn test_snapshots_without_session_are_ignored() {
+        let mut sessions = FormSessions::new();
+        let mut single = step("a", 1, "one");
+        single.session = FormStepJson::default();
+        sessions.record(&single);
+        assert_eq!(sessions.step_count("a"), 0);
+        assert!(sessions.earlier_steps(&single).is_empty());
+    }
+
+    #[test]
+    fn test_sessions_and_steps_are_bounded() {
+        let mut sessions = FormSessions::new();
+        for i in 0..MAX_SESSIONS + 1 {
+            sessions.record(&step(&format!("s{}", i), 1, "one"));
+        }
+        assert_eq!(sessions.step_count("s0"), 0);
+        assert_eq!(sessions.step_count(&format!("s{}", MAX_SESSIONS)), 1);
+
+        for n in 1..=(MAX_STEPS as u32 + 5) {
+            sessions.record(&step("long", n, "step"));
+        }
+        assert_eq!(sessions.step_count("long"), MAX_STEPS);
+        let latest = step("long", MAX_STEPS as u32 + 6, "next");
+        assert_eq!(sessions.earlier_steps(&latest).len(), MAX_STEPS);
+    }
+
+    #[test]
+    fn test_step_fields_round_trip_through_snapshot_json() {
+        let json = serde_json::to_value(step("a", 2, "two")).unwrap();
+        assert_eq!(json["formSessionId"], "a");
+        assert_eq!(json["step"], 2);
+        assert_eq!(json["totalSteps"], 3);
+
+        let mut plain = json.clone();
+        let object = plain.as_object_mut().unwrap();
+        object.remove("formSessionId");
+        object.remove("step");
+        object.remove("totalSteps");
+        let parsed: FormSnapshotJson = serde_json::from_value(plain).unwrap();
+        assert_eq!(parsed.session, FormStepJson::default());
+    }
+}
@@ -0,0 +1,476 @@
+/*!
+ * Shared Stores
+ *
+ * The latest form snapshot, the pending fill commands and the vault are each
+ * shared between Tauri commands and the extension bridge thread. A panic
+ * while one side holds the lock poisons a plain `Mutex`, after which every
+ * `lock()` fails; the bridge used to treat that as "no snapshot" or an empty
+ * vault, so the extension saw fake empty results and the real bug was
+ * hidden.
+ *
+ * `SharedStore` recovers from poisoning explicitly: the panic is logged at
+ * error level once, the poison is cleared and the value is handed out as
+ * the panicking thread left it. Every store here is replaced or appended to
+ * in single statements, so a half-finished write is not observable. Failures
+ * of the stores themselves (a vault that can't be read) are returned as
+ * errors for callers to report, not swallowed.
+ *
+ * Each store also carries a revision, bumped whenever a lock is released
+ * after mutable access. Mutating commands and routes return it, and reads
+ * can name a `min_revision` to wait (briefly) for, so a caller that knows
+ * about a write made on the other side never reads the store from before
+ * it.
+ */
+
+use serde::Serialize;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use asterisk_vault::VaultStore;
+
+use crate::{FillCommandJson, FormSnapshotJson};
+
+/// The latest form snapshot from the extension
+pub type SnapshotStore = SharedStore<Option<FormSnapshotJson>>;
+
+/// Fill commands waiting for the extension to pick up
+pub type FillCommandStore = SharedStore<Vec<FillCommandJson>>;
+
+/// The vault, as shared by commands and the bridge
+pub type SharedVault = SharedStore<Box<dyn VaultStore>>;
+
+// ============================================================================
+// Shared Store
+// ============================================================================
+
+/// Longest a read waits for a store to reach a requested revision
+pub const MAX_REVISION_WAIT: Duration = Duration::from_secs(2);
+
+/// Most bridge requests waiting for a revision at once; any more are
+/// answered (503) without waiting
+pub const MAX_REVISION_WAITERS: usize = 16;
+
+/// A named value behind a mutex that recovers from poisoning
+pub struct SharedStore<T: ?Sized> {
+    name: &'static str,
+    revision: Arc<Revision>,
+    inner: Arc<Mutex<T>>,
+}
+
+impl<T: ?Sized> Clone for SharedStore<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            revision: Arc::clone(&self.revision),
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> SharedStore<T> {
+    /// `name` identifies the store in error logs
+    pub fn new(name: &'static str, value: T) -> Self {
+        Self {
+            name,
+            revision: Arc::new(Revision::default()),
+            inner: Arc::new(Mutex::new(value)),
+        }
+    }
+}
+
+impl<T: ?Sized> SharedStore<T> {
+    /// Lock the store, recovering it if another thread panicked holding it
+    ///
+    /// Mutable access through the guard bumps the revision when it is
+    /// released.
+    pub fn lock(&self) -> Result<StoreGuard<'_, T>, String> {
+        let guard = match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                eprintln!(
+                    "[Asterisk] ERROR: {} store was poisoned by a panic in another thread; recovering",
+                    self.name
+                );
+                self.inner.clear_poison();
+                poisoned.into_inner()
+            }
+        };
+        Ok(StoreGuard {
+            guard,
+            revision: &self.revision,
+            written: false,
+        })
+    }
+
+    /// Lock the store once it has reached `min_revision`
+    ///
+    /// Waits at most `MAX_REVISION_WAIT`; a store still behind after that
+    /// is an error rather than a silently stale read.
+    pub fn lock_at(&self, min_revision: Option<u64>) -> Result<StoreGuard<'_, T>, String> {
+        if let Some(min_revision) = min_revision {
+            self.wait_for(min_revision, MAX_REVISION_WAIT)?;
+        }
+        self.lock()
+    }
+
+    /// The number of writes the store has seen
+    pub fn revision(&self) -> u64 {
+        *self.revision.current()
+    }
+
+    /// Block until the store reaches `min_revision` or `timeout` passes
+    pub fn wait_for(&self, min_revision: u64, timeout: Duration) -> Result<u64, String> {
+        let deadline = Instant::now() + timeout;
+        let mut current = self.revision.current();
+        while *current < min_revision {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(format!(
+                    "The {} store is at revision {}, not yet {}",
+                    self.name, *current, min_revision
+                ));
+            }
+            current = match self.revision.advanced.wait_timeout(current, remaining) {
+                Ok((current, _)) => current,
+                Err(poisoned) => poisoned.into_inner().0,
+            };
+        }
+        Ok(*current)
+    }
+}
+
+/// Monotonic write counter of a store
+#[derive(Default)]
+struct Revision {
+    current: Mutex<u64>,
+    advanced: Condvar,
+}
+
+impl Revision {
+    fn current(&self) -> MutexGuard<'_, u64> {
+        self.current
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn bump(&self) {
+        *self.current() += 1;
+        self.advanced.notify_all();
+    }
+}
+
+/// Access to a locked store; see `SharedStore::lock`
+pub struct StoreGuard<'a, T: ?Sized> {
+    guard: MutexGuard<'a, T>,
+    revision: &'a Revision,
+    written: bool,
+}
+
+impl<T: ?Sized> Deref for StoreGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T: ?Sized> DerefMut for StoreGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.written = true;
+        &mut self.guard
+    }
+}
+
+impl<T: ?Sized> StoreGuard<'_, T> {
+    /// Bump the revision on release even though nothing was written, so
+    /// readers treat the value as changed
+    pub fn mark_written(&mut self) {
+        self.written = true;
+    }
+}
+
+impl<T: ?Sized> Drop for StoreGuard<'_, T> {
+    fn drop(&mut self) {
+        // Bumped while the value is still locked: a reader woken by the new
+        // revision then waits on the lock and sees the finished write
+        if self.written {
+            self.revision.bump();
+        }
+    }
+}
+
+/// A command result together with the revision of the store it changed
+#[derive(Debug, Clone, Serialize)]
+pub struct Revised<T> {
+    #[serde(flatten)]
+    pub value: T,
+    pub revision: u64,
+}
+
+impl<T> Revised<T> {
+    /// `value`, stamped with `store`'s current revision
+    pub fn new<S: ?Sized>(value: T, store: &SharedStore<S>) -> Self {
+        Self {
+            value,
+            revision: store.revision(),
+        }
+    }
+}
+
+/// Bridge query parameter naming the revision a read must reflect
+pub const MIN_REVISION_PARAM: &str = "minRevision";
+
+/// Split `minRevision=N` off a bridge URL, leaving the rest for routing
+pub fn take_min_revision(url: &str) -> Result<(String, Option<u64>), String> {
+    let Some((path, query)) = url.split_once('?') else {
+        return Ok((url.to_string(), None));
+    };
+    let mut min_revision = None;
+    let mut rest = Vec::new();
+    for pair in query.split('&') {
+        match pair
+            .strip_prefix(MIN_REVISION_PARAM)
+            .and_then(|v| v.strip_prefix('='))
+        {
+            Some(value) => {
+                min_revision = Some(value.parse().map_err(|_| {
+                    format!(
+                        "{} must be a revision number: {}",
+                        MIN_REVISION_PARAM, value
+                    )
+                })?);
+            }
+            None => rest.push(pair),
+        }
+    }
+    let url = if rest.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}?{}", path, rest.join("&"))
+    };
+    Ok((url, min_revision))
+}
+
+impl SnapshotStore {
+    /// A copy of the latest snapshot, if any
+    pub fn latest(&self) -> Result<Option<FormSnapshotJson>, String> {
+        Ok(self.lock()?.clone())
+    }
+
+    /// Replace the latest snapshot
+    pub fn replace(&self, snapshot: FormSnapshotJson) -> Result<(), String> {
+        *self.lock()? = Some(snapshot);
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asterisk_vault::{InMemoryStore, Provenance, ProvenanceSource, VaultCategory, VaultItem};
+    use std::thread;
+
+    fn snapshot(domain: &str) -> FormSnapshotJson {
+        FormSnapshotJson {
+            url: format!("https://{}/", domain),
+            domain: domain.to_string(),
+            title: String::new(),
+            captured_at: chrono::Utc::now().to_rfc3339(),
+            fingerprint: crate::FormFingerprintJson {
+                field_count: 0,
+                field_types: vec![],
+                required_count: 0,
+                hash: "abc".to_string(),
+            },
+            fields: vec![],
+            session: Default::default(),
+        }
+    }
+
+    /// Panic on another thread while holding the store's lock
+    fn poison_with<T: Send + ?Sized + 'static>(
+        store: &SharedStore<T>,
+        write: impl FnOnce(&mut T) + Send + 'static,
+    ) {
+        let writer = store.clone();
+        let result = thread::spawn(move || {
+            let mut guard = writer.inner.lock().unwrap();
+            write(&mut guard);
+            panic!("writer failed");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(store.inner.is_poisoned());
+    }
+
+    #[test]
+    fn test_snapshot_readers_recover_after_writer_panics() {
+        let store = SnapshotStore::new("snapshot", None);
+        store.replace(snapshot("before.example")).unwrap();
+        poison_with(&store, |latest| *latest = Some(snapshot("after.example")));
+
+        // Readers see what the writer left, not a fake "no snapshot"
+        let latest = store.latest().unwrap().unwrap();
+        assert_eq!(latest.domain, "after.example");
+        assert!(!store.inner.is_poisoned());
+
+        store.replace(snapshot("next.example")).unwrap();
+        assert_eq!(store.latest().unwrap().unwrap().domain, "next.example");
+    }
+
+    #[test]
+    fn test_fill_commands_recover_after_writer_panics() {
+        let store = FillCommandStore::new("fill command", Vec::new());
+        poison_with(&store, |_| {});
+        assert!(store.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_vault_recovers_after_writer_panics() {
+        let vault: Box<dyn VaultStore> = Box::new(InMemoryStore::new());
+        let store = SharedVault::new("vault", vault);
+        poison_with(&store, |vault| {
+            let item = VaultItem::new(
+                "email",
+                "me@example.com",
+                "Email",
+                VaultCategory::Contact,
+                Provenance {
+                    source: ProvenanceSource::UserEntered,
+                    timestamp: chrono::Utc::now(),
+                    confidence: 1.0,
+                    origin: None,
+                },
+            );
+            vault.set("email".to_string(), item).unwrap();
+        });
+
+        let items = store.lock().unwrap().list().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].key, "email");
+    }
+
+    fn fill_command(id: &str) -> FillCommandJson {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "targetDomain": "example.com",
+            "fills": [],
+            "createdAt": "2026-10-16T09:00:00Z",
+            "expiresAt": "2099-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_revision_bumps_on_mutable_access_only() {
+        let store = FillCommandStore::new("fill command", Vec::new());
+        assert_eq!(store.revision(), 0);
+        assert!(store.lock().unwrap().is_empty());
+        assert_eq!(store.revision(), 0);
+
+        store.lock().unwrap().push(fill_command("a"));
+        assert_eq!(store.revision(), 1);
+        let revised = Revised::new((), &store);
+        assert_eq!(serde_json::to_value(&revised).unwrap()["revision"], 1);
+    }
+
+    #[test]
+    fn test_read_waits_for_pending_write() {
+        let store = FillCommandStore::new("fill command", Vec::new());
+        let expected = store.revision() + 1;
+
+        // The reader knows about a write that hasn't happened yet
+        let reader = {
+            let store = store.clone();
+            thread::spawn(move || {
+                let commands = store.lock_at(Some(expected)).unwrap();
+                commands.iter().map(|c| c.id.clone()).collect::<Vec<_>>()
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        store.lock().unwrap().push(fill_command("late"));
+
+        assert_eq!(reader.join().unwrap(), vec!["late"]);
+    }
+
+    #[test]
+    fn test_wait_is_bounded() {
+        let store = SnapshotStore::new("snapshot", None);
+        let started = Instant::now();
+        let err = store.wait_for(3, Duration::from_millis(30)).unwrap_err();
+        assert!(err.contains("not yet 3"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_no_stale_reads_across_threads() {
+        let vault: Box<dyn VaultStore> = Box::new(InMemoryStore::new());
+        let store = SharedVault::new("vault", vault);
+        let (sender, receiver) = std::sync::mpsc::channel();
+
+        let writers: Vec<_> = (0..4)
+            .map(|w| {
+                let store = store.clone();
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    for i in 0..25 {
+                        let key = format!("w{}-{}", w, i);
+                        let item = VaultItem::new(
+                            &key,
+                            "value",
+                            &key,
+                            VaultCategory::Custom,
+                            Provenance {
+                                source: ProvenanceSource::UserEntered,
+                                timestamp: chrono::Utc::now(),
+                                confidence: 1.0,
+                                origin: None,
+                            },
+                        );
+                        store.lock().unwrap().set(key.clone(), item).unwrap();
+                        let revised = Revised::new(key, &store);
+                        sender.send((revised.value, revised.revision)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+
+        let reader = {
+            let store = store.clone();
+            thread::spawn(move || {
+                for (key, revision) in receiver {
+                    let vault = store.lock_at(Some(revision)).unwrap();
+                    assert!(vault.get(&key).unwrap().is_some(), "stale read of {}", key);
+                }
+            })
+        };
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        reader.join().unwrap();
+        assert_eq!(store.revision(), 100);
+    }
+
+    #[test]
+    fn test_take_min_revision() {
+        assert_eq!(
+            take_min_revision("/v1/vault?minRevision=7").unwrap(),
+            ("/v1/vault".to_string(), Some(7))
+        );
+        assert_eq!(
+            take_min_revision("/v1/fill-commands?domain=a.com&minRevision=2").unwrap(),
+            ("/v1/fill-commands?domain=a.com".to_string(), Some(2))
+        );
+        assert_eq!(
+            take_min_revision("/v1/vault?key=x").unwrap(),
+            ("/v1/vault?key=x".to_string(), None)
+        );
+        assert!(take_min_revision("/v1/vault?minRevision=soon").is_err());
+    }
+}
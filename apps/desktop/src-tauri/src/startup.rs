@@ -0,0 +1,366 @@
+/*!
+ * Safe-Mode Startup
+ *
+ * A corrupt data file must neither crash startup nor quietly start over
+ * with empty state and later overwrite what was there. Each subsystem's
+ * load at startup is recorded as healthy, missing or corrupt, and `plan`
+ * decides from those results alone which subsystems to disable.
+ *
+ * A disabled subsystem runs without its file (in memory), so the corrupt
+ * file is left exactly as it was. The UI gets a `startup-problems` event
+ * and can list the problems with `startup_problems`, each with the file
+ * and the actions that would fix it. Only `reset` is carried out here: it
+ * moves the file aside rather than deleting it, and the subsystem starts
+ * fresh on the next launch.
+ */
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::AuditEntryJson;
+
+/// Event raised when startup found problems
+pub const STARTUP_PROBLEMS_EVENT: &str = "startup-problems";
+
+/// A part of the app that loads a data file at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    /// Templates, overrides, consent, recall, tokens and onboarding
+    Settings,
+    /// The keychain-encrypted vault opened at startup
+    Vault,
+    /// The audit log and the id index built from it
+    AuditLog,
+}
+
+/// How loading one file went
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadResult {
+    Healthy,
+    /// No file yet: a fresh start, not a problem
+    Missing,
+    Corrupt(String),
+}
+
+/// One file's load result
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubsystemLoad {
+    pub subsystem: Subsystem,
+    pub path: PathBuf,
+    pub result: LoadResult,
+}
+
+impl SubsystemLoad {
+    /// Classify a store's `load` of `path`
+    pub fn new<T>(subsystem: Subsystem, path: &Path, loaded: &Result<T, String>) -> Self {
+        let result = match loaded {
+            Err(e) => LoadResult::Corrupt(e.clone()),
+            Ok(_) if path.exists() => LoadResult::Healthy,
+            Ok(_) => LoadResult::Missing,
+        };
+        SubsystemLoad {
+            subsystem,
+            path: path.to_path_buf(),
+            result,
+        }
+    }
+
+    /// Classify opening `path`, which may create it; `existed` is whether
+    /// it was there before. A failed open with no file there loses nothing,
+    /// so it counts as missing.
+    pub fn opened<T>(
+        subsystem: Subsystem,
+        path: &Path,
+        existed: bool,
+        opened: &Result<T, String>,
+    ) -> Self {
+        let result = match (opened, existed) {
+            (Err(e), true) => LoadResult::Corrupt(e.clone()),
+            (Ok(_), true) => LoadResult::Healthy,
+            (_, false) => LoadResult::Missing,
+        };
+        SubsystemLoad {
+            subsystem,
+            path: path.to_path_buf(),
+            result,
+        }
+    }
+}
+
+/// A fix the user can choose for a corrupt file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupAction {
+    /// Replace the file with a backup copy, then restart
+    RestoreBackup,
+    /// Keep what can still be read, then restart
+    Repair,
+    /// Move the file aside and start this subsystem empty
+    Reset,
+}
+
+/// A file that failed to load
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StartupProblemJson {
+    pub subsystem: Subsystem,
+    pub path: String,
+    pub reason: String,
+    /// Suggested fixes, most conservative first
+    pub actions: Vec<StartupAction>,
+}
+
+/// What startup decided
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct StartupPlanJson {
+    /// Subsystems running without their files
+    pub disabled: Vec<Subsystem>,
+    pub problems: Vec<StartupProblemJson>,
+}
+
+impl StartupPlanJson {
+    /// Whether any subsystem is disabled
+    pub fn safe_mode(&self) -> bool {
+        !self.disabled.is_empty()
+    }
+
+    pub fn is_disabled(&self, subsystem: Subsystem) -> bool {
+        self.disabled.contains(&subsystem)
+    }
+
+    /// Whether `path` failed to load
+    pub fn has_problem(&self, path: &Path) -> bool {
+        let path = path.display().to_string();
+        self.problems.iter().any(|problem| problem.path == path)
+    }
+}
+
+/// Disable every subsystem with a corrupt file
+///
+/// Missing files are a fresh start. A subsystem is disabled once however
+/// many of its files are corrupt, and each of those files is a problem.
+pub fn plan(loads: &[SubsystemLoad]) -> StartupPlanJson {
+    let mut plan = StartupPlanJson::default();
+    for load in loads {
+        let LoadResult::Corrupt(reason) = &load.result else {
+            continue;
+        };
+        if !plan.is_disabled(load.subsystem) {
+            plan.disabled.push(load.subsystem);
+        }
+        plan.problems.push(StartupProblemJson {
+            subsystem: load.subsystem,
+            path: load.path.display().to_string(),
+            reason: reason.clone(),
+            actions: actions_for(load.subsystem),
+        });
+    }
+    plan
+}
+
+fn actions_for(subsystem: Subsystem) -> Vec<StartupAction> {
+    match subsystem {
+        Subsystem::Settings => vec![StartupAction::RestoreBackup, StartupAction::Reset],
+        Subsystem::Vault => vec![
+            StartupAction::RestoreBackup,
+            StartupAction::Repair,
+            StartupAction::Reset,
+        ],
+        // Entries are independent lines, so dropping the bad ones is safe
+        Subsystem::AuditLog => vec![StartupAction::Repair, StartupAction::Reset],
+    }
+}
+
+/// Check that every line of an audit log parses
+pub fn check_audit_log(path: &Path) -> LoadResult {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return LoadResult::Missing,
+        Err(e) => return LoadResult::Corrupt(format!("Failed to open audit log: {}", e)),
+    };
+    let mut malformed = 0;
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                return LoadResult::Corrupt(format!("line {}: {}", index + 1, e));
+            }
+        };
+        if !line.trim().is_empty() && serde_json::from_str::<AuditEntryJson>(&line).is_err() {
+            malformed += 1;
+        }
+    }
+    if malformed == 0 {
+        LoadResult::Healthy
+    } else {
+        LoadResult::Corrupt(format!("{} malformed audit entries", malformed))
+    }
+}
+
+/// Carry out `action` for the problem with `path`, dropping the problem
+///
+/// The subsystem stays disabled until the next launch, when it loads the
+/// fixed (or fresh) file.
+pub fn resolve(
+    plan: &mut StartupPlanJson,
+    path: &str,
+    action: StartupAction,
+) -> Result<(), String> {
+    let index = plan
+        .problems
+        .iter()
+        .position(|problem| problem.path == path)
+        .ok_or_else(|| format!("No startup problem with {}", path))?;
+    if !plan.problems[index].actions.contains(&action) {
+        return Err(format!("{:?} doesn't apply to {}", action, path));
+    }
+    match action {
+        StartupAction::Reset => {
+            let aside = format!("{}.corrupt-{}", path, chrono::Utc::now().timestamp());
+            fs::rename(path, &aside)
+                .map_err(|e| format!("Failed to move {} aside: {}", path, e))?;
+            println!("[Startup] Moved corrupt {} to {}", path, aside);
+        }
+        StartupAction::RestoreBackup | StartupAction::Repair => {
+            return Err(format!(
+                "{:?} has to be done by hand: fix {} and restart",
+                action, path
+            ));
+        }
+    }
+    plan.problems.remove(index);
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(subsystem: Subsystem, name: &str, result: LoadResult) -> SubsystemLoad {
+        SubsystemLoad {
+            subsystem,
+            path: PathBuf::from(name),
+            result,
+        }
+    }
+
+    fn corrupt() -> LoadResult {
+        LoadResult::Corrupt("bad".to_string())
+    }
+
+    #[test]
+    fn test_plan_for_every_combination() {
+        let results = || [LoadResult::Healthy, LoadResult::Missing, corrupt()];
+        for settings in results() {
+            for vault in results() {
+                for audit in results() {
+                    let loads = [
+                        load(Subsystem::Settings, "consent.json", settings.clone()),
+                        load(Subsystem::Vault, "vault.json", vault.clone()),
+                        load(Subsystem::AuditLog, "audit.jsonl", audit.clone()),
+                    ];
+                    let plan = plan(&loads);
+                    for load in &loads {
+                        let is_corrupt = load.result == corrupt();
+                        assert_eq!(plan.is_disabled(load.subsystem), is_corrupt, "{:?}", loads);
+                        assert_eq!(
+                            plan.problems
+                                .iter()
+                                .any(|p| p.path == load.path.display().to_string()),
+                            is_corrupt
+                        );
+                    }
+                    assert_eq!(
+                        plan.safe_mode(),
+                        loads.iter().any(|l| l.result == corrupt())
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_subsystem_with_several_corrupt_files_disabled_once() {
+        let plan = plan(&[
+            load(Subsystem::Settings, "consent.json", corrupt()),
+            load(Subsystem::Settings, "tokens.json", LoadResult::Healthy),
+            load(Subsystem::Settings, "templates.json", corrupt()),
+        ]);
+        assert_eq!(plan.disabled, vec![Subsystem::Settings]);
+        assert_eq!(plan.problems.len(), 2);
+        assert_eq!(
+            plan.problems[0].actions,
+            vec![StartupAction::RestoreBackup, StartupAction::Reset]
+        );
+    }
+
+    #[test]
+    fn test_file_checks_and_reset() {
+        let dir = std::env::temp_dir().join(format!("asterisk-startup-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let vault = dir.join("vault.keychain.enc");
+        let audit = dir.join("audit.jsonl");
+
+        assert_eq!(check_audit_log(&audit), LoadResult::Missing);
+        fs::write(&audit, "\n").unwrap();
+        assert_eq!(check_audit_log(&audit), LoadResult::Healthy);
+
+        fs::write(&vault, "[{\"key\":").unwrap();
+        fs::write(&audit, "{}\nnot json\n").unwrap();
+        let failed: Result<(), String> = Err("Corrupt vault file".to_string());
+        let mut plan = plan(&[
+            SubsystemLoad::opened(Subsystem::Vault, &vault, true, &failed),
+            SubsystemLoad {
+                subsystem: Subsystem::AuditLog,
+                path: audit.clone(),
+                result: check_audit_log(&audit),
+            },
+        ]);
+        assert_eq!(plan.problems[1].reason, "2 malformed audit entries");
+
+        // Nothing touches the file until an action is chosen
+        assert!(plan.has_problem(&vault));
+        assert!(!plan.has_problem(&dir.join("vault.json")));
+        let vault_path = vault.display().to_string();
+        assert!(resolve(&mut plan, &vault_path, StartupAction::Repair).is_err());
+        assert_eq!(fs::read_to_string(&vault).unwrap(), "[{\"key\":");
+        resolve(&mut plan, &vault_path, StartupAction::Reset).unwrap();
+        assert!(!vault.exists());
+        assert_eq!(plan.problems.len(), 1);
+        // The corrupt data is kept beside it
+        let kept = fs::read_dir(&dir).unwrap().filter_map(|e| e.ok()).any(|e| {
+            e.file_name()
+                .to_string_lossy()
+                .starts_with("vault.keychain.enc.corrupt-")
+        });
+        assert!(kept);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_failed_open_of_an_existing_file_disables_its_subsystem() {
+        let path = Path::new("vault.enc");
+        let failed: Result<(), String> = Err("no master key for it".to_string());
+        let load = SubsystemLoad::opened(Subsystem::Vault, path, true, &failed);
+        assert_eq!(
+            load.result,
+            LoadResult::Corrupt("no master key for it".to_string())
+        );
+        assert!(plan(&[load]).is_disabled(Subsystem::Vault));
+
+        // Nothing on disk to protect
+        let load = SubsystemLoad::opened(Subsystem::Vault, path, false, &failed);
+        assert_eq!(load.result, LoadResult::Missing);
+        let load = SubsystemLoad::opened(Subsystem::Vault, path, true, &Ok(()));
+        assert_eq!(load.result, LoadResult::Healthy);
+        let load = SubsystemLoad::opened(Subsystem::Vault, path, false, &Ok(()));
+        assert_eq!(load.result, LoadResult::Missing);
+    }
+}
@@ -0,0 +1,157 @@
+/*!
+ * Bridge Status Page
+ *
+ * People open the bridge address in a browser to check the app is running.
+ * `GET /` answers them with a small self-contained HTML page (inline styles,
+ * no scripts or external assets): that Asterisk is running, the app
+ * version, whether an extension has connected recently, and a pointer to
+ * the desktop app. A request that accepts `application/json` gets the same
+ * facts as JSON instead.
+ *
+ * The route needs no token, so the page shows nothing about the vault,
+ * audit log or pending fills, not even counts.
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::compat::SeenClientJson;
+
+/// How recently an extension must have been seen to count as connected
+const RECENT_MINUTES: i64 = 5;
+
+/// Public facts about the running bridge
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BridgeStatusJson {
+    pub status: &'static str,
+    pub app: &'static str,
+    pub version: &'static str,
+    #[serde(rename = "extensionConnected")]
+    pub extension_connected: bool,
+}
+
+impl BridgeStatusJson {
+    /// The status given the last client seen by the bridge
+    pub fn new(last_client: Option<&SeenClientJson>, now: DateTime<Utc>) -> Self {
+        let extension_connected = last_client
+            .and_then(|client| DateTime::parse_from_rfc3339(&client.seen_at).ok())
+            .is_some_and(|seen| {
+                now - seen.with_timezone(&Utc) <= Duration::minutes(RECENT_MINUTES)
+            });
+        BridgeStatusJson {
+            status: "ok",
+            app: "Asterisk",
+            version: env!("CARGO_PKG_VERSION"),
+            extension_connected,
+        }
+    }
+}
+
+/// Whether an `Accept` header prefers JSON over the HTML page
+pub fn wants_json(accept: Option<&str>) -> bool {
+    accept.is_some_and(|accept| {
+        accept
+            .split(',')
+            .filter_map(|range| range.split(';').next())
+            .any(|media| media.trim().eq_ignore_ascii_case("application/json"))
+    })
+}
+
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Asterisk is running</title>
+<style>
+body { font-family: system-ui, sans-serif; max-width: 32rem; margin: 4rem auto; padding: 0 1rem; color: #222; }
+h1 { font-size: 1.5rem; }
+.dot { display: inline-block; width: .6rem; height: .6rem; border-radius: 50%; background: #2a9d4b; margin-right: .4rem; }
+dl { display: grid; grid-template-columns: auto 1fr; gap: .4rem 1rem; }
+dt { color: #666; }
+dd { margin: 0; }
+</style>
+</head>
+<body>
+<h1><span class="dot"></span>Asterisk is running</h1>
+<dl>
+<dt>Version</dt><dd>{{version}}</dd>
+<dt>Browser extension</dt><dd>{{extension}}</dd>
+</dl>
+<p>This address is for the Asterisk browser extension. To manage your data and settings, open the Asterisk desktop app.</p>
+</body>
+</html>
+"#;
+
+/// The status page for `status`
+pub fn render(status: &BridgeStatusJson) -> String {
+    let extension = if status.extension_connected {
+        "Connected recently"
+    } else {
+        "Not connected recently"
+    };
+    TEMPLATE
+        .replace("{{version}}", &escape(status.version))
+        .replace("{{extension}}", extension)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compat::Compatibility;
+
+    fn seen_at(at: DateTime<Utc>) -> SeenClientJson {
+        SeenClientJson {
+            client: Some("asterisk-extension".to_string()),
+            version: "0.5.0".to_string(),
+            compatibility: Compatibility::Supported,
+            seen_at: at.to_rfc3339(),
+        }
+    }
+
+    #[test]
+    fn test_extension_connected_only_when_seen_recently() {
+        let now = Utc::now();
+        assert!(!BridgeStatusJson::new(None, now).extension_connected);
+        assert!(
+            BridgeStatusJson::new(Some(&seen_at(now - Duration::minutes(1))), now)
+                .extension_connected
+        );
+        assert!(
+            !BridgeStatusJson::new(Some(&seen_at(now - Duration::hours(1))), now)
+                .extension_connected
+        );
+    }
+
+    #[test]
+    fn test_wants_json() {
+        assert!(!wants_json(None));
+        assert!(!wants_json(Some(
+            "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"
+        )));
+        assert!(wants_json(Some("application/json")));
+        assert!(wants_json(Some("Application/JSON; charset=utf-8, */*")));
+    }
+
+    #[test]
+    fn test_page_is_self_contained() {
+        let page = render(&BridgeStatusJson::new(None, Utc::now()));
+        assert!(page.contains(env!("CARGO_PKG_VERSION")));
+        assert!(page.contains("Not connected recently"));
+        assert!(!page.contains("{{"));
+        for external in ["<script", "<link", "src=", "http"] {
+            assert!(!page.contains(external), "{}", external);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+/*!
+ * Data Directory Selection
+ *
+ * Probes the data directory once at startup instead of letting every write
+ * fail with an IO error. On a read-only data directory (locked-down
+ * machines) we fall back to a temp directory, and failing that keep the
+ * audit log in memory. Either way the UI is told via `storage_status`.
+ */
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where persistent data is going
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageMode {
+    /// The normal app data directory
+    Normal,
+    /// A temp directory; data may not survive a reboot
+    TempDir,
+    /// Nothing is written to disk
+    Memory,
+}
+
+/// Storage health reported to the UI
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StorageStatusJson {
+    pub mode: StorageMode,
+    /// True whenever we're not writing to the normal data directory
+    pub degraded: bool,
+    /// The directory we wanted to use
+    #[serde(rename = "requestedDir")]
+    pub requested_dir: String,
+    /// The directory actually in use (absent in memory mode)
+    #[serde(rename = "activeDir", skip_serializing_if = "Option::is_none")]
+    pub active_dir: Option<String>,
+    /// Prominent warning for the UI when degraded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+/// Check that `dir` exists (creating it if needed) and accepts writes
+pub fn probe_writable(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    let probe = dir.join(".asterisk-write-probe");
+    fs::write(&probe, b"ok").map_err(|e| format!("Cannot write to {}: {}", dir.display(), e))?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+/// Pick the data directory, falling back to `fallback` and then to memory
+///
+/// Returns the directory to use (None in memory mode) and the status to
+/// report.
+pub fn resolve_data_dir(preferred: &Path, fallback: &Path) -> (Option<PathBuf>, StorageStatusJson) {
+    let requested_dir = preferred.display().to_string();
+
+    let preferred_error = match probe_writable(preferred) {
+        Ok(()) => {
+            return (
+                Some(preferred.to_path_buf()),
+                StorageStatusJson {
+                    mode: StorageMode::Normal,
+                    degraded: false,
+                    requested_dir,
+                    active_dir: Some(preferred.display().to_string()),
+                    warning: None,
+                },
+            );
+        }
+        Err(e) => e,
+    };
+    eprintln!("[Asterisk Storage] {}", preferred_error);
+
+    match probe_writable(fallback) {
+        Ok(()) => (
+            Some(fallback.to_path_buf()),
+            StorageStatusJson {
+                mode: StorageMode::TempDir,
+                degraded: true,
+                requested_dir,
+                active_dir: Some(fallback.display().to_string()),
+                warning: Some(format!(
+                    "Data directory is not writable ({}). Using a temporary directory; \
+                     audit history may be lost on reboot.",
+                    preferred_error
+                )),
+            },
+        ),
+        Err(fallback_error) => {
+            eprintln!("[Asterisk Storage] {}", fallback_error);
+            (
+                None,
+                StorageStatusJson {
+                    mode: StorageMode::Memory,
+                    degraded: true,
+                    requested_dir,
+                    active_dir: None,
+                    warning: Some(format!(
+                        "Data directory is not writable ({}). The audit log is kept in \
+                         memory and will be lost when Asterisk quits.",
+                        preferred_error
+                    )),
+                },
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A path under a regular file can never be created, even as root
+    fn unwritable(name: &str) -> PathBuf {
+        let blocker =
+            std::env::temp_dir().join(format!("asterisk-storage-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&blocker);
+        fs::write(&blocker, b"not a directory").unwrap();
+        blocker.join("asterisk")
+    }
+
+    fn writable(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "asterisk-storage-ok-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_writable_dir_is_normal() {
+        let preferred = writable("normal");
+        let (dir, status) = resolve_data_dir(&preferred, &unwritable("normal-fallback"));
+        assert_eq!(dir, Some(preferred));
+        assert_eq!(status.mode, StorageMode::Normal);
+        assert!(!status.degraded);
+        assert!(status.warning.is_none());
+    }
+
+    #[test]
+    fn test_read_only_dir_falls_back_to_temp() {
+        let fallback = writable("fallback");
+        let (dir, status) = resolve_data_dir(&unwritable("temp"), &fallback);
+        assert_eq!(dir, Some(fallback));
+        assert_eq!(status.mode, StorageMode::TempDir);
+        assert!(status.degraded);
+        assert!(status.warning.is_some());
+    }
+
+    #[test]
+    fn test_nothing_writable_uses_memory() {
+        let (dir, status) = resolve_data_dir(&unwritable("memory"), &unwritable("memory-2"));
+        assert!(dir.is_none());
+        assert_eq!(status.mode, StorageMode::Memory);
+        assert!(status.degraded);
+        assert!(status.active_dir.is_none());
+    }
+}
@@ -0,0 +1,591 @@
+/*!
+ * Matching Telemetry
+ *
+ * Opt-in, value-free record of how the matching tiers fared, for offline
+ * quality analysis. Per filled plan, each field gets its semantic class, the
+ * tier that matched it, its confidence and disposition, and whether the user
+ * kept or changed it; fill results reported later say whether the fill
+ * itself worked.
+ *
+ * Nothing identifying is written: no labels, values, vault keys or field
+ * ids. Domains, forms and fields appear only as hashes salted with a random
+ * per-install salt kept next to the log, so records from one install can be
+ * joined with each other but not with anyone else's.
+ *
+ * Off by default, like the access log.
+ */
+
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::acceptance::AcceptedPlanJson;
+use crate::fill::canonicalize_domain;
+use crate::matching::MatchTier;
+use crate::templates::FieldFillResultJson;
+use crate::{AuditEntryJson, Disposition, FieldNodeJson};
+
+/// Semantic classes longer than this, or with other characters, are
+/// recorded as "other" so free text can't slip in through the classifier
+const MAX_SEMANTIC_LEN: usize = 32;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// One field's outcome within a filled plan
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FieldOutcomeJson {
+    /// Salted hash of the form fingerprint and field id
+    pub field: String,
+    pub semantic: String,
+    /// Tier that matched the field; absent when the fill didn't come from an
+    /// accepted backend plan
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tier: Option<MatchTier>,
+    pub confidence: f64,
+    pub disposition: Disposition,
+    /// Whether the field was filled rather than dropped in review
+    pub kept: bool,
+    /// Whether the user replaced the suggested value
+    #[serde(rename = "userChanged")]
+    pub user_changed: bool,
+}
+
+/// A line of `matching.jsonl`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum TelemetryRecordJson {
+    /// Outcomes of a plan the user applied
+    Plan {
+        #[serde(rename = "recordedAt")]
+        recorded_at: String,
+        /// Salted hash of the domain
+        domain: String,
+        fields: Vec<FieldOutcomeJson>,
+    },
+    /// Whether filling one field worked, as reported after the fill
+    FillResult {
+        #[serde(rename = "recordedAt")]
+        recorded_at: String,
+        domain: String,
+        field: String,
+        succeeded: bool,
+    },
+}
+
+/// Acceptance and fill success for one tier
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TierMetricsJson {
+    /// None for fields whose tier wasn't known
+    pub tier: Option<MatchTier>,
+    pub fields: u64,
+    pub kept: u64,
+    pub changed: u64,
+    /// Share of fields kept without change
+    #[serde(rename = "acceptanceRate")]
+    pub acceptance_rate: f64,
+    #[serde(rename = "fillResults")]
+    pub fill_results: u64,
+    #[serde(rename = "fillSucceeded")]
+    pub fill_succeeded: u64,
+    /// Share of reported fills that worked; None without reports
+    #[serde(rename = "fillSuccessRate")]
+    pub fill_success_rate: Option<f64>,
+}
+
+/// Per-tier summary of `matching.jsonl`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MatchingMetricsSummaryJson {
+    pub plans: u64,
+    pub tiers: Vec<TierMetricsJson>,
+}
+
+fn tier_order(tier: Option<MatchTier>) -> usize {
+    match tier {
+        Some(MatchTier::Override) => 0,
+        Some(MatchTier::Template) => 1,
+        Some(MatchTier::Autocomplete) => 2,
+        Some(MatchTier::Pattern) => 3,
+        Some(MatchTier::Session) => 4,
+        Some(MatchTier::Llm) => 5,
+        None => 6,
+    }
+}
+
+fn semantic_class(semantic: &str) -> String {
+    let ok = !semantic.is_empty()
+        && semantic.len() <= MAX_SEMANTIC_LEN
+        && semantic
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+    if ok {
+        semantic.to_string()
+    } else {
+        "other".to_string()
+    }
+}
+
+fn rate(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+// ============================================================================
+// Telemetry Log
+// ============================================================================
+
+/// Append-only JSONL log of matching outcomes
+#[derive(Debug)]
+pub struct MatchingTelemetry {
+    path: PathBuf,
+    salt: String,
+    enabled: AtomicBool,
+}
+
+impl MatchingTelemetry {
+    /// Create a disabled log writing to `path`, salted with the salt in
+    /// `salt_path` (created on first use)
+    ///
+    /// If the salt can't be read or saved, a salt for this run only is used;
+    /// records then won't join across restarts, but stay pseudonymous.
+    pub fn new(path: impl Into<PathBuf>, salt_path: &Path) -> Self {
+        let salt = load_or_create_salt(salt_path).unwrap_or_else(|e| {
+            eprintln!("[Asterisk Telemetry] {}", e);
+            random_salt()
+        });
+        Self {
+            path: path.into(),
+            salt,
+            enabled: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Salted hash standing in for `value`
+    fn pseudonym(&self, kind: &str, value: &str) -> String {
+        let digest = Sha256::new()
+            .chain_update(self.salt.as_bytes())
+            .chain_update([0])
+            .chain_update(kind.as_bytes())
+            .chain_update([0])
+            .chain_update(value.as_bytes())
+            .finalize();
+        digest[..12].iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn field_hash(&self, fingerprint: &str, field_id: &str) -> String {
+        self.pseudonym("field", &format!("{}\u{0}{}", fingerprint, field_id))
+    }
+
+    /// Record the outcome of an applied plan from its audit entry
+    ///
+    /// `accepted` supplies each field's tier and whether the user overrode
+    /// it; `fields` (the form's snapshot) supplies semantic classes.
+    pub fn record_plan(
+        &self,
+        entry: &AuditEntryJson,
+        accepted: Option<&AcceptedPlanJson>,
+        fields: &[FieldNodeJson],
+    ) -> Result<(), String> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let outcomes = entry
+            .items
+            .iter()
+            .map(|item| {
+                let accepted_field =
+                    accepted.and_then(|a| a.fields.iter().find(|f| f.field_id == item.field_id));
+                let semantic = fields
+                    .iter()
+                    .find(|f| f.id == item.field_id)
+                    .map_or("unknown", |f| f.semantic.as_str());
+                FieldOutcomeJson {
+                    field: self.field_hash(&entry.fingerprint, &item.field_id),
+                    semantic: semantic_class(semantic),
+                    tier: accepted_field.and_then(|f| f.match_tier),
+                    confidence: item.confidence,
+                    disposition: item.disposition,
+                    kept: item.applied,
+                    user_changed: accepted_field.is_some_and(|f| f.overridden),
+                }
+            })
+            .collect();
+        self.append(&TelemetryRecordJson::Plan {
+            recorded_at: chrono::Utc::now().to_rfc3339(),
+            domain: self.pseudonym("domain", &canonicalize_domain(&entry.domain)),
+            fields: outcomes,
+        })
+    }
+
+    /// Record fill results reported for a form
+    pub fn record_fill_results(
+        &self,
+        domain: &str,
+        fingerprint: &str,
+        results: &[FieldFillResultJson],
+    ) -> Result<(), String> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let domain = self.pseudonym("domain", &canonicalize_domain(domain));
+        let recorded_at = chrono::Utc::now().to_rfc3339();
+        for result in results {
+            self.append(&TelemetryRecordJson::FillResult {
+                recorded_at: recorded_at.clone(),
+                domain: domain.clone(),
+                field: self.field_hash(fingerprint, &result.field_id),
+                succeeded: result.success,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn append(&self, record: &TelemetryRecordJson) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create telemetry directory: {}", e))?;
+        }
+        let line = serde_json::to_string(record)
+            .map_err(|e| format!("Failed to serialize telemetry record: {}", e))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("Failed to open telemetry log: {}", e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Failed to write telemetry record: {}", e))
+    }
+
+    /// Per-tier acceptance and fill success rates over the whole log
+    ///
+    /// A fill result counts toward the tier of the most recent plan outcome
+    /// for the same field; results with no earlier plan are ignored.
+    pub fn summary(&self) -> Result<MatchingMetricsSummaryJson, String> {
+        let file = match fs::File::open(&self.path) {
+            Ok(f) => Some(f),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("Failed to open telemetry log: {}", e)),
+        };
+
+        let mut plans = 0;
+        let mut tiers: HashMap<usize, TierMetricsJson> = HashMap::new();
+        let mut last_tier: HashMap<(String, String), Option<MatchTier>> = HashMap::new();
+
+        for line in file.into_iter().flat_map(|f| BufReader::new(f).lines()) {
+            let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = match serde_json::from_str::<TelemetryRecordJson>(&line) {
+                Ok(record) => record,
+                Err(e) => {
+                    eprintln!("[Asterisk Telemetry] Skipping malformed record: {}", e);
+                    continue;
+                }
+            };
+            match record {
+                TelemetryRecordJson::Plan { domain, fields, .. } => {
+                    plans += 1;
+                    for field in fields {
+                        let metrics = tiers
+                            .entry(tier_order(field.tier))
+                            .or_insert_with(|| empty_metrics(field.tier));
+                        metrics.fields += 1;
+                        metrics.kept += u64::from(field.kept);
+                        metrics.changed += u64::from(field.user_changed);
+                        last_tier.insert((domain.clone(), field.field), field.tier);
+                    }
+                }
+                TelemetryRecordJson::FillResult {
+                    domain,
+                    field,
+                    succeeded,
+                    ..
+                } => {
+                    let Some(&tier) = last_tier.get(&(domain, field)) else {
+                        continue;
+                    };
+                    let metrics = tiers
+                        .entry(tier_order(tier))
+                        .or_insert_with(|| empty_metrics(tier));
+                    metrics.fill_results += 1;
+                    metrics.fill_succeeded += u64::from(succeeded);
+                }
+            }
+        }
+
+        let mut tiers: Vec<(usize, TierMetricsJson)> = tiers.into_iter().collect();
+        tiers.sort_by_key(|(order, _)| *order);
+        Ok(MatchingMetricsSummaryJson {
+            plans,
+            tiers: tiers
+                .into_iter()
+                .map(|(_, mut m)| {
+                    m.acceptance_rate = rate(m.kept.saturating_sub(m.changed), m.fields);
+                    m.fill_success_rate =
+                        (m.fill_results > 0).then(|| rate(m.fill_succeeded, m.fill_results));
+                    m
+                })
+                .collect(),
+        })
+    }
+}
+
+fn empty_metrics(tier: Option<MatchTier>) -> TierMetricsJson {
+    TierMetricsJson {
+        tier,
+        fields: 0,
+        kept: 0,
+        changed: 0,
+        acceptance_rate: 0.0,
+        fill_results: 0,
+        fill_succeeded: 0,
+        fill_success_rate: None,
+    }
+}
+
+fn random_salt() -> String {
+    let mut buf = [0u8; 16];
+    OsRng.fill_bytes(&mut buf);
+    buf.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn load_or_create_salt(path: &Path) -> Result<String, String> {
+    match fs::read_to_string(path) {
+        Ok(salt) if !salt.trim().is_empty() => return Ok(salt.trim().to_string()),
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to read telemetry salt: {}", e)),
+    }
+    let salt = random_salt();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create telemetry directory: {}", e))?;
+    }
+    fs::write(path, &salt).map_err(|e| format!("Failed to save telemetry salt: {}", e))?;
+    Ok(salt)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acceptance::AcceptedFieldJson;
+    use crate::{AuditItemJson, AuditSummaryJson, RedactionLevel};
+
+    fn telemetry(name: &str) -> MatchingTelemetry {
+        let dir = std::env::temp_dir().join(format!(
+            "asterisk-telemetry-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let telemetry =
+            MatchingTelemetry::new(dir.join("matching.jsonl"), &dir.join("telemetry.salt"));
+        telemetry.set_enabled(true);
+        telemetry
+    }
+
+    fn item(field_id: &str, label: &str, applied: bool) -> AuditItemJson {
+        AuditItemJson {
+            field_id: field_id.to_string(),
+            label: label.to_string(),
+            kind: "text".to_string(),
+            confidence: 0.95,
+            disposition: Disposition::Review,
+            applied,
+            source: "employerName".to_string(),
+            old_value_redacted: "Old Employer Ltd".to_string(),
+            new_value_redacted: "Analytical Engines".to_string(),
+            redaction: RedactionLevel::Masked,
+            user_confirmed: true,
+            notes: Some("user override".to_string()),
+        }
+    }
+
+    fn entry() -> AuditEntryJson {
+        AuditEntryJson {
+            id: "audit-1".to_string(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            url: "https://careers.example.com/apply?ref=ada".to_string(),
+            domain: "careers.example.com".to_string(),
+            fingerprint: "fp-abc".to_string(),
+            summary: AuditSummaryJson {
+                planned_count: 2,
+                applied_count: 1,
+                blocked_count: 0,
+                reviewed_count: 1,
+            },
+            items: vec![
+                item("employer-input", "Current employer", true),
+                item("motto-input", "Personal motto", false),
+            ],
+            recall_recorded: false,
+            fill_command_id: None,
+            acceptance_id: None,
+            acceptance_hash: None,
+            plan_hash: None,
+            detail: Default::default(),
+        }
+    }
+
+    fn accepted(tier: MatchTier, overridden: bool) -> AcceptedPlanJson {
+        AcceptedPlanJson {
+            id: "acc-1".to_string(),
+            plan_id: "plan-1".to_string(),
+            plan_hash: "ph".to_string(),
+            domain: "careers.example.com".to_string(),
+            url: "https://careers.example.com/apply?ref=ada".to_string(),
+            form_fingerprint: "fp-abc".to_string(),
+            fields: vec![AcceptedFieldJson {
+                field_id: "employer-input".to_string(),
+                vault_key: Some("employerName".to_string()),
+                value: "Analytical Engines".to_string(),
+                match_tier: Some(tier),
+                confidence: Some(0.95),
+                overridden,
+            }],
+            accepted_at: "2026-01-01T00:00:00Z".to_string(),
+            content_hash: "hash".to_string(),
+        }
+    }
+
+    fn snapshot_fields() -> Vec<FieldNodeJson> {
+        vec![FieldNodeJson {
+            id: "employer-input".to_string(),
+            name: "employer".to_string(),
+            label: "Current employer".to_string(),
+            field_type: "text".to_string(),
+            semantic: "organization".to_string(),
+            ..Default::default()
+        }]
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let telemetry = telemetry("disabled");
+        telemetry.set_enabled(false);
+        telemetry.record_plan(&entry(), None, &[]).unwrap();
+        assert!(!telemetry.path.exists());
+    }
+
+    #[test]
+    fn test_records_hold_no_labels_values_or_ids() {
+        let telemetry = telemetry("value-free");
+        telemetry
+            .record_plan(
+                &entry(),
+                Some(&accepted(MatchTier::Pattern, true)),
+                &snapshot_fields(),
+            )
+            .unwrap();
+        telemetry
+            .record_fill_results(
+                "careers.example.com",
+                "fp-abc",
+                &[FieldFillResultJson {
+                    field_id: "employer-input".to_string(),
+                    vault_key: "employerName".to_string(),
+                    success: true,
+                }],
+            )
+            .unwrap();
+
+        let raw = fs::read_to_string(&telemetry.path).unwrap();
+        for needle in [
+            "employer-input",
+            "motto-input",
+            "Current employer",
+            "Personal motto",
+            "employerName",
+            "Analytical Engines",
+            "Old Employer",
+            "example.com",
+            "fp-abc",
+            "ada",
+            "user override",
+        ] {
+            assert!(!raw.contains(needle), "{} leaked into {}", needle, raw);
+        }
+        assert!(raw.contains("organization"));
+    }
+
+    #[test]
+    fn test_pseudonyms_stable_per_salt() {
+        let a = telemetry("salt-a");
+        let b = MatchingTelemetry::new(a.path.clone(), &a.path.with_file_name("telemetry.salt"));
+        assert_eq!(
+            a.pseudonym("domain", "example.com"),
+            b.pseudonym("domain", "example.com")
+        );
+
+        let other = telemetry("salt-b");
+        assert_ne!(
+            a.pseudonym("domain", "example.com"),
+            other.pseudonym("domain", "example.com")
+        );
+    }
+
+    #[test]
+    fn test_summary_per_tier_rates() {
+        let telemetry = telemetry("summary");
+        assert_eq!(telemetry.summary().unwrap().plans, 0);
+
+        telemetry
+            .record_plan(&entry(), Some(&accepted(MatchTier::Pattern, false)), &[])
+            .unwrap();
+        telemetry
+            .record_plan(&entry(), Some(&accepted(MatchTier::Pattern, true)), &[])
+            .unwrap();
+        let result = |success| FieldFillResultJson {
+            field_id: "employer-input".to_string(),
+            vault_key: "employerName".to_string(),
+            success,
+        };
+        telemetry
+            .record_fill_results("careers.example.com", "fp-abc", &[result(true)])
+            .unwrap();
+        telemetry
+            .record_fill_results("careers.example.com", "fp-abc", &[result(false)])
+            .unwrap();
+        // No plan outcome for this form; ignored
+        telemetry
+            .record_fill_results("other.test", "fp-abc", &[result(true)])
+            .unwrap();
+
+        let summary = telemetry.summary().unwrap();
+        assert_eq!(summary.plans, 2);
+        let [pattern, unknown] = summary.tiers.as_slice() else {
+            panic!("expected two tiers, got {:?}", summary.tiers);
+        };
+        // Kept twice, changed once; one of two reported fills worked
+        assert_eq!(pattern.tier, Some(MatchTier::Pattern));
+        assert_eq!((pattern.fields, pattern.kept, pattern.changed), (2, 2, 1));
+        assert_eq!(pattern.acceptance_rate, 0.5);
+        assert_eq!(pattern.fill_success_rate, Some(0.5));
+        // The motto field was dropped in review
+        assert_eq!(unknown.tier, None);
+        assert_eq!((unknown.fields, unknown.kept), (2, 0));
+        assert_eq!(unknown.acceptance_rate, 0.0);
+        assert_eq!(unknown.fill_success_rate, None);
+    }
+}
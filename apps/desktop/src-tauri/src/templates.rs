@@ -0,0 +1,523 @@
+/*!
+ * Form Templates
+ *
+ * A template remembers which vault key filled which field on a given form
+ * (domain + fingerprint), so repeat visits resolve without re-matching.
+ *
+ * Templates heal themselves: a mapping whose fill fails is marked suspect and
+ * bypassed on the next plan; when the re-matched key then fills successfully,
+ * the mapping is corrected in place.
+ *
+ * Vault items can be deleted out from under a template. `verify` reports
+ * mappings whose vault key is gone; `repair` drops them, and removes a
+ * template once it has no mappings left.
+ */
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// One field → vault key mapping in a template
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TemplateMappingJson {
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    #[serde(rename = "vaultKey")]
+    pub vault_key: String,
+    /// Set when a fill through this mapping failed; bypassed until healed
+    #[serde(default)]
+    pub suspect: bool,
+}
+
+/// A saved field mapping for one form
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FormTemplateJson {
+    /// Stable ID derived from domain and fingerprint
+    pub id: String,
+    pub domain: String,
+    /// Form fingerprint hash
+    pub fingerprint: String,
+    pub mappings: Vec<TemplateMappingJson>,
+    /// How many mappings have been corrected automatically
+    #[serde(rename = "healedCount", default)]
+    pub healed_count: u32,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
+
+impl FormTemplateJson {
+    /// Usable (non-suspect) mapping for a field, if any
+    pub fn mapping_for(&self, field_id: &str) -> Option<&TemplateMappingJson> {
+        self.mappings
+            .iter()
+            .find(|m| m.field_id == field_id && !m.suspect)
+    }
+}
+
+/// Outcome of filling one field, reported after a fill completes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldFillResultJson {
+    #[serde(rename = "fieldId")]
+    pub field_id: String,
+    /// The vault key whose value was filled
+    #[serde(rename = "vaultKey")]
+    pub vault_key: String,
+    pub success: bool,
+}
+
+/// Health summary for a template
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TemplateHealthJson {
+    #[serde(rename = "templateId")]
+    pub template_id: String,
+    #[serde(rename = "healedCount")]
+    pub healed_count: u32,
+    /// Mappings currently bypassed because their last fill failed
+    pub suspect: Vec<TemplateMappingJson>,
+}
+
+/// A template with mappings to vault keys that no longer exist
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TemplateProblemJson {
+    #[serde(rename = "templateId")]
+    pub template_id: String,
+    pub domain: String,
+    /// Mappings whose vault key was deleted
+    #[serde(rename = "invalidMappings")]
+    pub invalid_mappings: Vec<TemplateMappingJson>,
+    /// Whether no usable mapping would remain, so repair removes the template
+    #[serde(rename = "removeTemplate")]
+    pub remove_template: bool,
+}
+
+/// Build the stable template ID for a form
+pub fn template_id(domain: &str, fingerprint: &str) -> String {
+    format!("{}#{}", domain, fingerprint)
+}
+
+// ============================================================================
+// Template Store
+// ============================================================================
+
+/// Templates persisted as a single JSON file
+#[derive(Debug, Default)]
+pub struct TemplateStore {
+    path: Option<PathBuf>,
+    templates: Vec<FormTemplateJson>,
+}
+
+impl TemplateStore {
+    /// Create a store that is never written to disk
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Load templates from `path`; a missing file is an empty store
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let templates = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("Failed to parse templates: {}", e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("Failed to read templates: {}", e)),
+        };
+        Ok(Self {
+            path: Some(path),
+            templates,
+        })
+    }
+
+    /// Path of the backing file, if persistent
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn list(&self) -> &[FormTemplateJson] {
+        &self.templates
+    }
+
+    pub fn get(&self, id: &str) -> Option<&FormTemplateJson> {
+        self.templates.iter().find(|t| t.id == id)
+    }
+
+    /// Find the template for a form, if one was saved
+    pub fn find(&self, domain: &str, fingerprint: &str) -> Option<&FormTemplateJson> {
+        self.get(&template_id(domain, fingerprint))
+    }
+
+    /// Save (or replace) the template for a form
+    pub fn save(
+        &mut self,
+        domain: &str,
+        fingerprint: &str,
+        mappings: Vec<TemplateMappingJson>,
+    ) -> Result<FormTemplateJson, String> {
+        let id = template_id(domain, fingerprint);
+        let now = chrono::Utc::now().to_rfc3339();
+        let created_at = self
+            .get(&id)
+            .map(|t| t.created_at.clone())
+            .unwrap_or_else(|| now.clone());
+
+        let template = FormTemplateJson {
+            id: id.clone(),
+            domain: domain.to_string(),
+            fingerprint: fingerprint.to_string(),
+            mappings,
+            healed_count: self.get(&id).map(|t| t.healed_count).unwrap_or(0),
+            created_at,
+            updated_at: now,
+        };
+
+        self.templates.retain(|t| t.id != id);
+        self.templates.push(template.clone());
+        self.persist()?;
+        Ok(template)
+    }
+
+    /// Apply a fill result report to a template
+    ///
+    /// Failed fills through a template mapping mark it suspect. A successful
+    /// fill of a suspect field rewrites the mapping to the key that worked.
+    pub fn apply_fill_results(
+        &mut self,
+        id: &str,
+        results: &[FieldFillResultJson],
+    ) -> Result<TemplateHealthJson, String> {
+        let template = self
+            .templates
+            .iter_mut()
+            .find(|t| t.id == id)
+            .ok_or_else(|| format!("Template not found: {}", id))?;
+
+        let mut changed = false;
+        for result in results {
+            let Some(mapping) = template
+                .mappings
+                .iter_mut()
+                .find(|m| m.field_id == result.field_id)
+            else {
+                continue;
+            };
+
+            if mapping.suspect && result.success {
+                mapping.vault_key = result.vault_key.clone();
+                mapping.suspect = false;
+                template.healed_count += 1;
+                changed = true;
+            } else if !mapping.suspect && !result.success && mapping.vault_key == result.vault_key {
+                mapping.suspect = true;
+                changed = true;
+            }
+        }
+
+        if changed {
+            template.updated_at = chrono::Utc::now().to_rfc3339();
+            self.persist()?;
+        }
+        self.health(id)
+            .ok_or_else(|| format!("Template not found: {}", id))
+    }
+
+    /// Find templates whose mappings point at keys not in `vault_keys`
+    ///
+    /// Templates with no mappings at all are reported too, since they can
+    /// never resolve a field.
+    pub fn verify(&self, vault_keys: &HashSet<&str>) -> Vec<TemplateProblemJson> {
+        self.templates
+            .iter()
+            .filter_map(|t| {
+                let invalid_mappings: Vec<TemplateMappingJson> = t
+                    .mappings
+                    .iter()
+                    .filter(|m| !vault_keys.contains(m.vault_key.as_str()))
+                    .cloned()
+                    .collect();
+                let remove_template = invalid_mappings.len() == t.mappings.len();
+                (remove_template || !invalid_mappings.is_empty()).then(|| TemplateProblemJson {
+                    template_id: t.id.clone(),
+                    domain: t.domain.clone(),
+                    invalid_mappings,
+                    remove_template,
+                })
+            })
+            .collect()
+    }
+
+    /// Drop mappings to keys not in `vault_keys`, and templates left empty
+    ///
+    /// Returns what was changed, in the form `verify` reports it.
+    pub fn repair(
+        &mut self,
+        vault_keys: &HashSet<&str>,
+    ) -> Result<Vec<TemplateProblemJson>, String> {
+        let problems = self.verify(vault_keys);
+        if problems.is_empty() {
+            return Ok(problems);
+        }
+
+        let now = chrono::Utc::now().to_rfc3339();
+        for problem in &problems {
+            if problem.remove_template {
+                self.templates.retain(|t| t.id != problem.template_id);
+            } else if let Some(template) = self
+                .templates
+                .iter_mut()
+                .find(|t| t.id == problem.template_id)
+            {
+                template
+                    .mappings
+                    .retain(|m| vault_keys.contains(m.vault_key.as_str()));
+                template.updated_at = now.clone();
+            }
+        }
+        self.persist()?;
+        Ok(problems)
+    }
+
+    /// Health summary for a template
+    pub fn health(&self, id: &str) -> Option<TemplateHealthJson> {
+        self.get(id).map(|t| TemplateHealthJson {
+            template_id: t.id.clone(),
+            healed_count: t.healed_count,
+            suspect: t.mappings.iter().filter(|m| m.suspect).cloned().collect(),
+        })
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create template directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(&self.templates)
+            .map_err(|e| format!("Failed to serialize templates: {}", e))?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(|e| format!("Failed to write templates: {}", e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Failed to write templates: {}", e))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::{generate_fill_plan, MatchTier};
+    use crate::{FieldNodeJson, FormFingerprintJson, FormSnapshotJson};
+    use asterisk_vault::{test_util, VaultCategory, VaultItem};
+
+    fn mapping(field_id: &str, vault_key: &str) -> TemplateMappingJson {
+        TemplateMappingJson {
+            field_id: field_id.to_string(),
+            vault_key: vault_key.to_string(),
+            suspect: false,
+        }
+    }
+
+    fn result(field_id: &str, vault_key: &str, success: bool) -> FieldFillResultJson {
+        FieldFillResultJson {
+            field_id: field_id.to_string(),
+            vault_key: vault_key.to_string(),
+            success,
+        }
+    }
+
+    fn item(key: &str, category: VaultCategory) -> VaultItem {
+        test_util::item(key, "value", category)
+    }
+
+    fn snapshot() -> FormSnapshotJson {
+        FormSnapshotJson {
+            url: "https://vendor.example/signup".to_string(),
+            domain: "vendor.example".to_string(),
+            title: "Sign up".to_string(),
+            captured_at: chrono::Utc::now().to_rfc3339(),
+            fingerprint: FormFingerprintJson {
+                field_count: 2,
+                field_types: vec!["email".to_string(), "text".to_string()],
+                required_count: 0,
+                hash: "fp1".to_string(),
+            },
+            fields: vec![
+                FieldNodeJson {
+                    id: "contact".to_string(),
+                    label: "Email".to_string(),
+                    field_type: "email".to_string(),
+                    ..Default::default()
+                },
+                FieldNodeJson {
+                    id: "org".to_string(),
+                    label: "Company".to_string(),
+                    field_type: "text".to_string(),
+                    ..Default::default()
+                },
+            ],
+            session: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_save_and_find() {
+        let mut store = TemplateStore::in_memory();
+        store
+            .save("vendor.example", "fp1", vec![mapping("org", "company")])
+            .unwrap();
+        let template = store.find("vendor.example", "fp1").unwrap();
+        assert_eq!(template.mapping_for("org").unwrap().vault_key, "company");
+        assert!(store.find("vendor.example", "fp2").is_none());
+    }
+
+    #[test]
+    fn test_failure_marks_mapping_suspect() {
+        let mut store = TemplateStore::in_memory();
+        let template = store
+            .save(
+                "vendor.example",
+                "fp1",
+                vec![mapping("contact", "emailOld")],
+            )
+            .unwrap();
+
+        let health = store
+            .apply_fill_results(&template.id, &[result("contact", "emailOld", false)])
+            .unwrap();
+        assert_eq!(health.suspect.len(), 1);
+        assert!(store
+            .get(&template.id)
+            .unwrap()
+            .mapping_for("contact")
+            .is_none());
+    }
+
+    #[test]
+    fn test_rename_heal_cycle() {
+        let vault = vec![
+            item("email", VaultCategory::Contact),
+            item("company", VaultCategory::Identity),
+        ];
+        let mut store = TemplateStore::in_memory();
+        let template = store
+            .save(
+                "vendor.example",
+                "fp1",
+                vec![mapping("contact", "emailOld"), mapping("org", "company")],
+            )
+            .unwrap();
+
+        // The stale mapping fails to fill
+        store
+            .apply_fill_results(
+                &template.id,
+                &[
+                    result("contact", "emailOld", false),
+                    result("org", "company", true),
+                ],
+            )
+            .unwrap();
+
+        // Next plan bypasses the template for the suspect field only
+        let plan = generate_fill_plan(&snapshot(), &vault, store.get(&template.id));
+        let contact = plan
+            .recommendations
+            .iter()
+            .find(|r| r.field_id == "contact")
+            .unwrap();
+        assert_eq!(contact.match_tier, MatchTier::Pattern);
+        assert_eq!(contact.vault_key, "email");
+        let org = plan
+            .recommendations
+            .iter()
+            .find(|r| r.field_id == "org")
+            .unwrap();
+        assert_eq!(org.match_tier, MatchTier::Template);
+
+        // The re-matched value fills successfully and the template heals
+        let health = store
+            .apply_fill_results(&template.id, &[result("contact", "email", true)])
+            .unwrap();
+        assert_eq!(health.healed_count, 1);
+        assert!(health.suspect.is_empty());
+
+        let plan = generate_fill_plan(&snapshot(), &vault, store.get(&template.id));
+        let contact = plan
+            .recommendations
+            .iter()
+            .find(|r| r.field_id == "contact")
+            .unwrap();
+        assert_eq!(contact.match_tier, MatchTier::Template);
+        assert_eq!(contact.vault_key, "email");
+    }
+
+    #[test]
+    fn test_verify_and_repair_deleted_keys() {
+        let mut store = TemplateStore::in_memory();
+        let kept = store
+            .save(
+                "vendor.example",
+                "fp1",
+                vec![mapping("contact", "email"), mapping("org", "company")],
+            )
+            .unwrap();
+        let gone = store
+            .save("other.example", "fp2", vec![mapping("org", "company")])
+            .unwrap();
+        store
+            .save("vendor.example", "fp3", vec![mapping("contact", "email")])
+            .unwrap();
+
+        // "company" has since been deleted from the vault
+        let keys = HashSet::from(["email"]);
+        let problems = store.verify(&keys);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].template_id, kept.id);
+        assert_eq!(
+            problems[0].invalid_mappings,
+            vec![mapping("org", "company")]
+        );
+        assert!(!problems[0].remove_template);
+        assert_eq!(problems[1].template_id, gone.id);
+        assert!(problems[1].remove_template);
+
+        let repaired = store.repair(&keys).unwrap();
+        assert_eq!(repaired, problems);
+        assert_eq!(store.list().len(), 2);
+        assert!(store.get(&gone.id).is_none());
+        assert_eq!(
+            store.get(&kept.id).unwrap().mappings,
+            vec![mapping("contact", "email")]
+        );
+
+        assert!(store.verify(&keys).is_empty());
+        assert!(store.repair(&keys).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_persists_across_loads() {
+        let dir = std::env::temp_dir().join(format!("asterisk-templates-{}", std::process::id()));
+        let path = dir.join("templates.json");
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut store = TemplateStore::load(&path).unwrap();
+        assert!(store.list().is_empty());
+        store
+            .save("vendor.example", "fp1", vec![mapping("org", "company")])
+            .unwrap();
+
+        let reloaded = TemplateStore::load(&path).unwrap();
+        assert_eq!(reloaded.list().len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
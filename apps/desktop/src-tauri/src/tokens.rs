@@ -0,0 +1,749 @@
+/*!
+ * Scoped Bridge Tokens
+ *
+ * Local integrations (scripts, CLIs) get named tokens limited to the scopes
+ * they need, e.g. a stats script with only `audit:read`. A request that
+ * presents a token in `X-Asterisk-Token` is checked before routing: an
+ * unknown token is refused with 401, a token without the route's scope
+ * with 403. A `/v1/` route with no scope assigned needs all of them.
+ *
+ * The bridge's own pairing token is a full-scope token (`Scope::ALL`) in
+ * this model. It is generated on first start, kept in `PAIRING_TOKEN_FILE`
+ * in the data directory and handed to the extension when pairing. Once the
+ * store has one, every `/v1/` route refuses requests without a token with
+ * 401, so a web page can't read the vault just by reaching localhost;
+ * `/health` and the `/` status page stay open.
+ *
+ * Besides tokens created at runtime, fixed tokens can be configured at
+ * startup in `ASTERISK_BRIDGE_TOKENS`, e.g. for a debugging CLI:
+ * `cli=ast_secret=vault:read,fill:read;ext=ast_other=fill:post`. These are
+ * listed with the rest but never written to disk or revocable; remove them
+ * from the environment instead.
+ *
+ * Only a SHA-256 hash of each token is stored; the value is shown once,
+ * when it is created.
+ */
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Request header carrying a bridge token
+pub const TOKEN_HEADER: &str = "X-Asterisk-Token";
+
+/// Tokens configured at startup: `name=token=scope,scope;...`
+pub const TOKENS_ENV: &str = "ASTERISK_BRIDGE_TOKENS";
+
+/// File in the data directory holding the pairing token
+pub const PAIRING_TOKEN_FILE: &str = "bridge-token";
+
+/// Listed id of the pairing token
+const PAIRING_TOKEN_ID: &str = "pairing";
+
+/// How stale a persisted last-used time may get before it is rewritten
+const LAST_USED_PERSIST_SECS: i64 = 60;
+
+/// What a token may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "vault:read")]
+    VaultRead,
+    #[serde(rename = "vault:write")]
+    VaultWrite,
+    #[serde(rename = "snapshot:read")]
+    SnapshotRead,
+    #[serde(rename = "snapshot:write")]
+    SnapshotWrite,
+    #[serde(rename = "fill:read")]
+    FillRead,
+    #[serde(rename = "fill:write", alias = "fill:post")]
+    FillWrite,
+    #[serde(rename = "audit:read")]
+    AuditRead,
+    #[serde(rename = "site:read")]
+    SiteRead,
+}
+
+impl Scope {
+    /// Every scope; what the pairing token carries
+    pub const ALL: &'static [Scope] = &[
+        Scope::VaultRead,
+        Scope::VaultWrite,
+        Scope::SnapshotRead,
+        Scope::SnapshotWrite,
+        Scope::FillRead,
+        Scope::FillWrite,
+        Scope::AuditRead,
+        Scope::SiteRead,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::VaultRead => "vault:read",
+            Scope::VaultWrite => "vault:write",
+            Scope::SnapshotRead => "snapshot:read",
+            Scope::SnapshotWrite => "snapshot:write",
+            Scope::FillRead => "fill:read",
+            Scope::FillWrite => "fill:write",
+            Scope::AuditRead => "audit:read",
+            Scope::SiteRead => "site:read",
+        }
+    }
+
+    /// Parse a scope name; `fill:post` is accepted for `fill:write`
+    pub fn parse(name: &str) -> Option<Scope> {
+        serde_json::from_value(serde_json::Value::String(name.to_string())).ok()
+    }
+}
+
+/// Whether a token with `scopes` may use a route that requires `required`
+pub fn token_allows(scopes: &[Scope], required: Scope) -> bool {
+    scopes.contains(&required)
+}
+
+/// The scopes a token needs for a bridge route
+///
+/// None for public routes and those open to any known token. A `/v1/`
+/// route not listed here needs every scope, so a route added without a
+/// scope is refused to scoped tokens rather than open to all of them.
+pub fn required_scopes(method: &str, url: &str) -> &'static [Scope] {
+    let path = url.split('?').next().unwrap_or(url);
+    match (method, path) {
+        ("GET", "/v1/form-snapshots") => &[Scope::SnapshotRead],
+        ("POST", "/v1/form-snapshots") => &[Scope::SnapshotWrite],
+        ("GET", "/v1/vault") | ("GET", "/v1/vault/keys") | ("GET", "/v1/vault/changes") => {
+            &[Scope::VaultRead]
+        }
+        ("POST", "/v1/vault") | ("DELETE", "/v1/vault") => &[Scope::VaultWrite],
+        ("GET", "/v1/fill-commands") => &[Scope::FillRead],
+        ("POST", "/v1/fill-commands")
+        | ("POST", "/v1/fill-commands/batch")
+        | ("POST", "/v1/fill-commands/prior-values")
+        | ("DELETE", "/v1/fill-commands") => &[Scope::FillWrite],
+        ("GET", "/v1/audit/stats") => &[Scope::AuditRead],
+        ("GET", "/v1/site-info") => &[Scope::SiteRead],
+        // Which optional features exist; nothing about the user
+        ("GET", "/v1/capabilities") => &[],
+        _ if route_needs_token(path) => Scope::ALL,
+        _ => &[],
+    }
+}
+
+/// Whether a route needs a token once a pairing token is set
+pub fn route_needs_token(url: &str) -> bool {
+    url.starts_with("/v1/")
+}
+
+/// A token as listed (never includes the token value)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BridgeTokenJson {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "lastUsedAt", default)]
+    pub last_used_at: Option<String>,
+}
+
+/// A freshly minted token; `token` is only ever returned here
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedTokenJson {
+    #[serde(flatten)]
+    pub info: BridgeTokenJson,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredToken {
+    #[serde(flatten)]
+    info: BridgeTokenJson,
+    /// Hex SHA-256 of the token value
+    hash: String,
+}
+
+/// Outcome of checking a request's token against a route
+#[derive(Debug, Clone, PartialEq)]
+pub enum Access {
+    Allowed,
+    /// 401: the route needs a token and none was given
+    MissingToken,
+    /// 401: the token is not known (or was revoked)
+    UnknownToken,
+    /// 403: the token lacks the route's scope
+    MissingScope(Scope),
+}
+
+fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn random_string(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buf);
+    URL_SAFE_NO_PAD.encode(buf)
+}
+
+fn new_token() -> String {
+    format!("ast_{}", random_string(32))
+}
+
+/// The pairing token kept at `path`, generated and saved there first if
+/// there is none; None for `path` gives a fresh token kept nowhere
+pub fn load_or_create_pairing_token(path: Option<&Path>) -> Result<String, String> {
+    let Some(path) = path else {
+        return Ok(new_token());
+    };
+    match fs::read_to_string(path) {
+        Ok(token) if !token.trim().is_empty() => return Ok(token.trim().to_string()),
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to read pairing token: {}", e)),
+    }
+    let token = new_token();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create token directory: {}", e))?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("Failed to write pairing token: {}", e))?;
+    std::io::Write::write_all(&mut file, token.as_bytes())
+        .map_err(|e| format!("Failed to write pairing token: {}", e))?;
+    Ok(token)
+}
+
+/// A token given in `TOKENS_ENV`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfiguredToken {
+    pub name: String,
+    pub token: String,
+    pub scopes: Vec<Scope>,
+}
+
+/// Tokens configured in the environment
+pub fn configured_from_env() -> Result<Vec<ConfiguredToken>, String> {
+    configured_from_vars(|name| std::env::var(name).ok())
+}
+
+fn configured_from_vars(
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<ConfiguredToken>, String> {
+    let Some(raw) = var(TOKENS_ENV) else {
+        return Ok(Vec::new());
+    };
+    raw.split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let invalid = || format!("Invalid {} entry: {}", TOKENS_ENV, entry);
+            let mut parts = entry.splitn(3, '=');
+            let (Some(name), Some(token), Some(scopes)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                return Err(invalid());
+            };
+            let scopes = scopes
+                .split(',')
+                .map(|scope| Scope::parse(scope.trim()).ok_or_else(invalid))
+                .collect::<Result<Vec<_>, _>>()?;
+            if name.trim().is_empty() || token.trim().is_empty() {
+                return Err(invalid());
+            }
+            Ok(ConfiguredToken {
+                name: name.trim().to_string(),
+                token: token.trim().to_string(),
+                scopes,
+            })
+        })
+        .collect()
+}
+
+/// Named, scoped bridge tokens, persisted as JSON
+#[derive(Debug, Default)]
+pub struct TokenStore {
+    path: Option<PathBuf>,
+    tokens: Vec<StoredToken>,
+    /// Startup tokens from `TOKENS_ENV`; kept out of the file
+    configured: Vec<StoredToken>,
+    /// The pairing token; while set, `/v1/` routes need a token
+    pairing: Option<StoredToken>,
+    /// When each token's last-used time was last written to disk
+    persisted_use: Vec<(String, DateTime<Utc>)>,
+}
+
+impl TokenStore {
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Load tokens from `path` (a missing file means no tokens)
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let tokens = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("Failed to parse bridge tokens: {}", e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("Failed to read bridge tokens: {}", e)),
+        };
+        Ok(Self {
+            path: Some(path),
+            tokens,
+            configured: Vec::new(),
+            pairing: None,
+            persisted_use: Vec::new(),
+        })
+    }
+
+    /// Accept the startup tokens, replacing any configured before
+    pub fn configure(&mut self, configured: Vec<ConfiguredToken>) {
+        let created_at = Utc::now().to_rfc3339();
+        self.configured = configured
+            .into_iter()
+            .map(|c| StoredToken {
+                info: BridgeTokenJson {
+                    id: format!("cfg_{}", c.name),
+                    name: c.name,
+                    scopes: c.scopes,
+                    created_at: created_at.clone(),
+                    last_used_at: None,
+                },
+                hash: hash_token(&c.token),
+            })
+            .collect();
+    }
+
+    /// Accept `token` with every scope, and from now on require a token on
+    /// `/v1/` routes
+    pub fn set_pairing_token(&mut self, token: &str) {
+        self.pairing = Some(StoredToken {
+            info: BridgeTokenJson {
+                id: PAIRING_TOKEN_ID.to_string(),
+                name: "Extension pairing".to_string(),
+                scopes: Scope::ALL.to_vec(),
+                created_at: Utc::now().to_rfc3339(),
+                last_used_at: None,
+            },
+            hash: hash_token(token),
+        });
+    }
+
+    /// Whether requests to `url` without a token are refused
+    pub fn requires_token(&self, url: &str) -> bool {
+        self.pairing.is_some() && route_needs_token(url)
+    }
+
+    /// Mint a token; the returned value is not stored and can't be shown again
+    pub fn create(&mut self, name: &str, scopes: &[Scope]) -> Result<CreatedTokenJson, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Token name is empty".to_string());
+        }
+        if scopes.is_empty() {
+            return Err("Token needs at least one scope".to_string());
+        }
+        let mut scopes = scopes.to_vec();
+        scopes.sort_by_key(|s| Scope::ALL.iter().position(|a| a == s));
+        scopes.dedup();
+
+        let token = new_token();
+        let info = BridgeTokenJson {
+            id: format!("tok_{}", random_string(6)),
+            name: name.to_string(),
+            scopes,
+            created_at: Utc::now().to_rfc3339(),
+            last_used_at: None,
+        };
+        self.tokens.push(StoredToken {
+            info: info.clone(),
+            hash: hash_token(&token),
+        });
+        self.persist()?;
+        Ok(CreatedTokenJson { info, token })
+    }
+
+    /// Revoke a token; false if there was no such token
+    pub fn revoke(&mut self, id: &str) -> Result<bool, String> {
+        let before = self.tokens.len();
+        self.tokens.retain(|t| t.info.id != id);
+        if self.tokens.len() == before {
+            return Ok(false);
+        }
+        self.persist()?;
+        Ok(true)
+    }
+
+    pub fn list(&self) -> Vec<BridgeTokenJson> {
+        self.tokens
+            .iter()
+            .chain(&self.configured)
+            .chain(&self.pairing)
+            .map(|t| t.info.clone())
+            .collect()
+    }
+
+    /// Check a presented token against the scope a route requires, recording
+    /// when a known token was used
+    pub fn check(&mut self, token: &str, required: &[Scope], now: DateTime<Utc>) -> Access {
+        let hash = hash_token(token);
+        let Some(stored) = self
+            .tokens
+            .iter_mut()
+            .chain(self.configured.iter_mut())
+            .chain(self.pairing.iter_mut())
+            .find(|t| t.hash == hash)
+        else {
+            return Access::UnknownToken;
+        };
+        stored.info.last_used_at = Some(now.to_rfc3339());
+        let id = stored.info.id.clone();
+        let missing = required
+            .iter()
+            .find(|&&scope| !token_allows(&stored.info.scopes, scope));
+        let access = match missing {
+            Some(&scope) => Access::MissingScope(scope),
+            None => Access::Allowed,
+        };
+        if id.starts_with("cfg_") || id == PAIRING_TOKEN_ID {
+            return access;
+        }
+
+        // Last-used times are kept in memory and written out now and then
+        let stale = self
+            .persisted_use
+            .iter()
+            .find(|(t, _)| *t == id)
+            .is_none_or(|(_, at)| now - *at >= Duration::seconds(LAST_USED_PERSIST_SECS));
+        if stale {
+            self.persisted_use.retain(|(t, _)| *t != id);
+            self.persisted_use.push((id, now));
+            if let Err(e) = self.persist() {
+                eprintln!("[Asterisk Tokens] {}", e);
+            }
+        }
+        access
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create token directory: {}", e))?;
+        }
+        let json = serde_json::to_string_pretty(&self.tokens)
+            .map_err(|e| format!("Failed to serialize bridge tokens: {}", e))?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(|e| format!("Failed to write bridge tokens: {}", e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Failed to write bridge tokens: {}", e))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every bridge route and the scope it needs
+    const ROUTES: &[(&str, &str, &[Scope])] = &[
+        ("GET", "/", &[]),
+        ("GET", "/health", &[]),
+        ("GET", "/v1/capabilities", &[]),
+        ("GET", "/v1/form-snapshots", &[Scope::SnapshotRead]),
+        ("POST", "/v1/form-snapshots", &[Scope::SnapshotWrite]),
+        ("GET", "/v1/vault", &[Scope::VaultRead]),
+        ("GET", "/v1/vault/keys", &[Scope::VaultRead]),
+        ("GET", "/v1/vault/changes?since=0", &[Scope::VaultRead]),
+        ("POST", "/v1/vault", &[Scope::VaultWrite]),
+        ("DELETE", "/v1/vault?key=x", &[Scope::VaultWrite]),
+        ("GET", "/v1/fill-commands?domain=x", &[Scope::FillRead]),
+        ("POST", "/v1/fill-commands", &[Scope::FillWrite]),
+        ("POST", "/v1/fill-commands/batch", &[Scope::FillWrite]),
+        (
+            "POST",
+            "/v1/fill-commands/prior-values",
+            &[Scope::FillWrite],
+        ),
+        ("DELETE", "/v1/fill-commands?id=x", &[Scope::FillWrite]),
+        ("GET", "/v1/audit/stats", &[Scope::AuditRead]),
+        ("GET", "/v1/site-info?domain=x", &[Scope::SiteRead]),
+    ];
+
+    #[test]
+    fn test_route_scope_matrix() {
+        let mut store = TokenStore::in_memory();
+        let now = Utc::now();
+        for &scope in Scope::ALL {
+            let token = store.create(scope.as_str(), &[scope]).unwrap().token;
+            for &(method, url, required) in ROUTES {
+                assert_eq!(required_scopes(method, url), required, "{} {}", method, url);
+                let expected = match required {
+                    [r] if *r != scope => Access::MissingScope(*r),
+                    _ => Access::Allowed,
+                };
+                assert_eq!(
+                    store.check(&token, required, now),
+                    expected,
+                    "{} token on {} {}",
+                    scope.as_str(),
+                    method,
+                    url
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_bridge_route_is_listed() {
+        let strip = |url: &str| url.split('?').next().unwrap_or(url).to_string();
+        let listed: Vec<(&str, String)> = ROUTES
+            .iter()
+            .map(|&(method, url, _)| (method, strip(url)))
+            .collect();
+        let routes: Vec<(String, String)> = include_str!("lib.rs")
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("// Route: "))
+            .map(|route| {
+                let mut parts = route.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                (method, strip(parts.next().unwrap_or_default()))
+            })
+            .collect();
+        assert!(routes.len() >= ROUTES.len(), "{:?}", routes);
+        for (method, path) in &routes {
+            assert!(
+                listed.contains(&(method.as_str(), path.clone())),
+                "{} {} has no entry in ROUTES",
+                method,
+                path
+            );
+            assert_ne!(
+                required_scopes(method, path),
+                Scope::ALL,
+                "{} {} has no scope",
+                method,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_unlisted_v1_routes_need_every_scope() {
+        assert_eq!(required_scopes("GET", "/v1/new-route"), Scope::ALL);
+        assert_eq!(required_scopes("PUT", "/v1/vault"), Scope::ALL);
+        assert!(required_scopes("GET", "/favicon.ico").is_empty());
+
+        let mut store = TokenStore::in_memory();
+        let scoped = store.create("reader", &[Scope::VaultRead]).unwrap().token;
+        let full = store.create("pairing", Scope::ALL).unwrap().token;
+        let required = required_scopes("GET", "/v1/new-route");
+        assert_eq!(
+            store.check(&scoped, required, Utc::now()),
+            Access::MissingScope(Scope::VaultWrite)
+        );
+        assert_eq!(store.check(&full, required, Utc::now()), Access::Allowed);
+    }
+
+    #[test]
+    fn test_token_allows() {
+        let read_only = [Scope::VaultRead, Scope::FillRead];
+        assert!(token_allows(&read_only, Scope::FillRead));
+        assert!(!token_allows(&read_only, Scope::VaultWrite));
+        assert!(!token_allows(&[], Scope::VaultRead));
+        assert!(Scope::ALL.iter().all(|&s| token_allows(Scope::ALL, s)));
+    }
+
+    #[test]
+    fn test_configured_tokens_from_vars() {
+        let vars =
+            |value: &'static str| move |name: &str| (name == TOKENS_ENV).then(|| value.to_string());
+        assert!(configured_from_vars(|_| None).unwrap().is_empty());
+
+        let configured = configured_from_vars(vars(
+            "cli=ast_a=vault:read, fill:read; ext=ast_b=fill:post;",
+        ))
+        .unwrap();
+        assert_eq!(
+            configured,
+            vec![
+                ConfiguredToken {
+                    name: "cli".to_string(),
+                    token: "ast_a".to_string(),
+                    scopes: vec![Scope::VaultRead, Scope::FillRead],
+                },
+                ConfiguredToken {
+                    name: "ext".to_string(),
+                    token: "ast_b".to_string(),
+                    scopes: vec![Scope::FillWrite],
+                },
+            ]
+        );
+        assert!(configured_from_vars(vars("cli=ast_a=vault:admin")).is_err());
+        assert!(configured_from_vars(vars("cli=ast_a")).is_err());
+
+        // Checked like any token, but never persisted or revoked
+        let path = std::env::temp_dir().join(format!(
+            "asterisk-tokens-configured-{}.json",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        let mut store = TokenStore::load(&path).unwrap();
+        store.configure(configured);
+        store.create("script", &[Scope::AuditRead]).unwrap();
+        assert_eq!(
+            store.check("ast_a", &[Scope::FillRead], Utc::now()),
+            Access::Allowed
+        );
+        assert_eq!(
+            store.check("ast_a", &[Scope::VaultWrite], Utc::now()),
+            Access::MissingScope(Scope::VaultWrite)
+        );
+        assert_eq!(store.list().len(), 3);
+        assert!(!store.revoke("cfg_cli").unwrap());
+        assert_eq!(TokenStore::load(&path).unwrap().list().len(), 1);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_full_scope_token_allowed_everywhere() {
+        let mut store = TokenStore::in_memory();
+        let token = store.create("pairing", Scope::ALL).unwrap().token;
+        for &(method, url, required) in ROUTES {
+            assert_eq!(
+                store.check(&token, required, Utc::now()),
+                Access::Allowed,
+                "{} {}",
+                method,
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_pairing_token_required_on_v1_routes() {
+        let mut store = TokenStore::in_memory();
+        assert!(!store.requires_token("/v1/vault"));
+
+        let path = std::env::temp_dir().join(format!(
+            "asterisk-pairing-{}/{}",
+            std::process::id(),
+            PAIRING_TOKEN_FILE
+        ));
+        let _ = fs::remove_file(&path);
+        let token = load_or_create_pairing_token(Some(&path)).unwrap();
+        assert!(token.starts_with("ast_"));
+        assert_eq!(load_or_create_pairing_token(Some(&path)).unwrap(), token);
+        assert_ne!(load_or_create_pairing_token(None).unwrap(), token);
+
+        store.set_pairing_token(&token);
+        for &(method, url, required) in ROUTES {
+            assert_eq!(
+                store.requires_token(url),
+                url.starts_with("/v1/"),
+                "{}",
+                url
+            );
+            assert_eq!(
+                store.check(&token, required, Utc::now()),
+                Access::Allowed,
+                "{} {}",
+                method,
+                url
+            );
+        }
+        assert!(!store.requires_token("/"));
+        assert_eq!(
+            store.check("ast_other", &[], Utc::now()),
+            Access::UnknownToken
+        );
+        assert!(!store.revoke(PAIRING_TOKEN_ID).unwrap());
+        assert!(!serde_json::to_string(&store.list())
+            .unwrap()
+            .contains(&token));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_unknown_and_revoked_tokens() {
+        let mut store = TokenStore::in_memory();
+        let created = store.create("script", &[Scope::AuditRead]).unwrap();
+        assert_eq!(
+            store.check("ast_nope", &[Scope::AuditRead], Utc::now()),
+            Access::UnknownToken
+        );
+
+        assert!(store.revoke(&created.info.id).unwrap());
+        assert!(!store.revoke(&created.info.id).unwrap());
+        assert_eq!(
+            store.check(&created.token, &[Scope::AuditRead], Utc::now()),
+            Access::UnknownToken
+        );
+    }
+
+    #[test]
+    fn test_list_has_last_used_but_never_the_token() {
+        let mut store = TokenStore::in_memory();
+        let created = store.create("script", &[Scope::AuditRead]).unwrap();
+        assert!(store.list()[0].last_used_at.is_none());
+
+        store.check(&created.token, &[], Utc::now());
+        let listed = store.list();
+        assert!(listed[0].last_used_at.is_some());
+        let json = serde_json::to_string(&listed).unwrap();
+        assert!(!json.contains(&created.token));
+    }
+
+    #[test]
+    fn test_create_validates() {
+        let mut store = TokenStore::in_memory();
+        assert!(store.create(" ", &[Scope::VaultRead]).is_err());
+        assert!(store.create("x", &[]).is_err());
+        let created = store
+            .create(
+                "x",
+                &[Scope::VaultWrite, Scope::VaultRead, Scope::VaultWrite],
+            )
+            .unwrap();
+        assert_eq!(
+            created.info.scopes,
+            vec![Scope::VaultRead, Scope::VaultWrite]
+        );
+    }
+
+    #[test]
+    fn test_tokens_persist_hashed() {
+        let path =
+            std::env::temp_dir().join(format!("asterisk-tokens-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let created = {
+            let mut store = TokenStore::load(&path).unwrap();
+            store.create("script", &[Scope::AuditRead]).unwrap()
+        };
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains(&created.token));
+
+        let mut reloaded = TokenStore::load(&path).unwrap();
+        assert_eq!(
+            reloaded.check(&created.token, &[Scope::AuditRead], Utc::now()),
+            Access::Allowed
+        );
+        let _ = fs::remove_file(&path);
+    }
+}
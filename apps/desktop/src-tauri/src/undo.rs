@@ -0,0 +1,297 @@
+/*!
+ * Fill Undo
+ *
+ * The audit log keeps only redacted values, so it can't put a field back the
+ * way it was. A fill command that sets `captureUndo` has the extension report
+ * each field's value from just before the fill; those prior values are kept
+ * here for a short window so `undo_fill` can send a reverse fill command.
+ *
+ * Captures are held in memory only, encrypted (ChaCha20-Poly1305) with a key
+ * that is generated per process and never written to disk. Each capture can be
+ * undone once, and is refused after `UNDO_WINDOW_MINUTES`.
+ */
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use chrono::{DateTime, Duration, Utc};
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::{FieldFillJson, FillCommandJson};
+
+/// How long prior values are kept after the fill
+pub const UNDO_WINDOW_MINUTES: i64 = 10;
+
+/// Lifetime of the reverse fill command, matching the desktop's own commands
+const UNDO_COMMAND_MINUTES: i64 = 5;
+
+/// Prior values reported by the extension for a command it just ran
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PriorValuesJson {
+    #[serde(rename = "commandId")]
+    pub command_id: String,
+    /// Each field's value from just before the fill
+    #[serde(rename = "priorValues")]
+    pub prior_values: Vec<FieldFillJson>,
+}
+
+/// A capture as held: the target stays in the clear, values are sealed
+struct Capture {
+    target_domain: String,
+    target_url: Option<String>,
+    captured_at: DateTime<Utc>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+// ============================================================================
+// Undo Store
+// ============================================================================
+
+/// Short-lived, encrypted prior values keyed by fill command id
+pub struct UndoStore {
+    cipher: ChaCha20Poly1305,
+    captures: HashMap<String, Capture>,
+}
+
+impl Default for UndoStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UndoStore {
+    pub fn new() -> Self {
+        Self {
+            cipher: ChaCha20Poly1305::new(&ChaCha20Poly1305::generate_key(OsRng)),
+            captures: HashMap::new(),
+        }
+    }
+
+    fn is_expired(capture: &Capture, now: DateTime<Utc>) -> bool {
+        now >= capture.captured_at + Duration::minutes(UNDO_WINDOW_MINUTES)
+    }
+
+    /// Drop captures whose undo window has passed
+    pub fn prune(&mut self, now: DateTime<Utc>) {
+        self.captures
+            .retain(|_, capture| !Self::is_expired(capture, now));
+    }
+
+    /// Keep the prior values for a command that opted in to undo
+    ///
+    /// Values for fields the command didn't fill are ignored. Returns how
+    /// many fields were captured.
+    pub fn capture(
+        &mut self,
+        command: &FillCommandJson,
+        prior_values: Vec<FieldFillJson>,
+        now: DateTime<Utc>,
+    ) -> Result<usize, String> {
+        if !command.capture_undo {
+            return Err(format!(
+                "Fill command {} did not ask for undo capture",
+                command.id
+            ));
+        }
+        self.prune(now);
+
+        let filled: HashSet<&str> = command.fills.iter().map(|f| f.field_id.as_str()).collect();
+        let mut seen = HashSet::new();
+        let prior_values: Vec<FieldFillJson> = prior_values
+            .into_iter()
+            .filter(|p| filled.contains(p.field_id.as_str()) && seen.insert(p.field_id.clone()))
+            .collect();
+
+        let plaintext = serde_json::to_vec(&prior_values)
+            .map_err(|e| format!("Failed to serialize prior values: {}", e))?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| "Failed to encrypt prior values".to_string())?;
+
+        self.captures.insert(
+            command.id.clone(),
+            Capture {
+                target_domain: command.target_domain.clone(),
+                target_url: command.target_url.clone(),
+                captured_at: now,
+                nonce: nonce.to_vec(),
+                ciphertext,
+            },
+        );
+        Ok(prior_values.len())
+    }
+
+    /// Whether `command_id` has a capture that can still be undone
+    pub fn can_undo(&self, command_id: &str, now: DateTime<Utc>) -> bool {
+        self.captures
+            .get(command_id)
+            .is_some_and(|capture| !Self::is_expired(capture, now))
+    }
+
+    /// Build the reverse fill command for `command_id`, consuming its capture
+    ///
+    /// The reverse command targets the same domain, URL and fields, with each
+    /// field set to its captured prior value. Expired captures are refused.
+    pub fn undo_command(
+        &mut self,
+        command_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<FillCommandJson, String> {
+        let capture = self
+            .captures
+            .remove(command_id)
+            .ok_or_else(|| format!("No prior values were captured for fill {}", command_id))?;
+        if Self::is_expired(&capture, now) {
+            return Err(format!(
+                "Undo for fill {} expired {} minutes after it was filled",
+                command_id, UNDO_WINDOW_MINUTES
+            ));
+        }
+
+        let plaintext = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(&capture.nonce),
+                capture.ciphertext.as_slice(),
+            )
+            .map_err(|_| "Failed to decrypt prior values".to_string())?;
+        let fills: Vec<FieldFillJson> = serde_json::from_slice(&plaintext)
+            .map_err(|e| format!("Failed to parse prior values: {}", e))?;
+        if fills.is_empty() {
+            return Err(format!(
+                "No prior values were captured for fill {}",
+                command_id
+            ));
+        }
+
+        Ok(FillCommandJson {
+            id: format!("undo-{}", command_id),
+            target_domain: capture.target_domain,
+            target_url: capture.target_url,
+            fills,
+            created_at: now.to_rfc3339(),
+            expires_at: (now + Duration::minutes(UNDO_COMMAND_MINUTES)).to_rfc3339(),
+            capture_undo: false,
+            plan_hash: None,
+        })
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(field_id: &str, value: &str) -> FieldFillJson {
+        FieldFillJson {
+            field_id: field_id.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn command(capture_undo: bool) -> FillCommandJson {
+        FillCommandJson {
+            id: "cmd-1".to_string(),
+            target_domain: "example.com".to_string(),
+            target_url: Some("https://example.com/signup".to_string()),
+            fills: vec![fill("email", "me@example.com"), fill("name", "Ada")],
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            expires_at: "2026-01-01T00:05:00Z".to_string(),
+            capture_undo,
+            plan_hash: None,
+        }
+    }
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        "2026-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::minutes(minutes)
+    }
+
+    #[test]
+    fn test_undo_restores_prior_values_for_same_fields() {
+        let mut store = UndoStore::new();
+        let captured = store
+            .capture(
+                &command(true),
+                vec![
+                    fill("email", "old@example.com"),
+                    fill("name", ""),
+                    // Not part of the fill; ignored
+                    fill("phone", "555"),
+                ],
+                at(0),
+            )
+            .unwrap();
+        assert_eq!(captured, 2);
+
+        let undo = store.undo_command("cmd-1", at(1)).unwrap();
+        assert_eq!(undo.id, "undo-cmd-1");
+        assert_eq!(undo.target_domain, "example.com");
+        assert_eq!(
+            undo.target_url.as_deref(),
+            Some("https://example.com/signup")
+        );
+        assert_eq!(
+            undo.fills,
+            vec![fill("email", "old@example.com"), fill("name", "")]
+        );
+        assert!(!undo.capture_undo);
+        assert!(crate::fill::validate_command(&undo).is_ok());
+
+        // One undo per capture
+        assert!(store.undo_command("cmd-1", at(1)).is_err());
+    }
+
+    #[test]
+    fn test_expired_capture_refuses_undo() {
+        let mut store = UndoStore::new();
+        store
+            .capture(
+                &command(true),
+                vec![fill("email", "old@example.com")],
+                at(0),
+            )
+            .unwrap();
+
+        assert!(store.can_undo("cmd-1", at(UNDO_WINDOW_MINUTES - 1)));
+        assert!(!store.can_undo("cmd-1", at(UNDO_WINDOW_MINUTES)));
+        let err = store
+            .undo_command("cmd-1", at(UNDO_WINDOW_MINUTES))
+            .unwrap_err();
+        assert!(err.contains("expired"), "{}", err);
+    }
+
+    #[test]
+    fn test_capture_requires_opt_in() {
+        let mut store = UndoStore::new();
+        assert!(store
+            .capture(&command(false), vec![fill("email", "old")], at(0))
+            .is_err());
+        assert!(!store.can_undo("cmd-1", at(0)));
+    }
+
+    #[test]
+    fn test_prior_values_are_not_held_in_the_clear() {
+        let mut store = UndoStore::new();
+        store
+            .capture(
+                &command(true),
+                vec![fill("email", "old@example.com")],
+                at(0),
+            )
+            .unwrap();
+
+        let capture = &store.captures["cmd-1"];
+        let needle = b"old@example.com";
+        assert!(!capture
+            .ciphertext
+            .windows(needle.len())
+            .any(|w| w == needle));
+    }
+}
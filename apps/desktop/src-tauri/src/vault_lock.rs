@@ -0,0 +1,676 @@
+/*!
+ * Vault Lock
+ *
+ * While the vault is locked the extension bridge refuses requests (423
+ * Locked) until the user unlocks it from the desktop app. The vault locks:
+ *
+ * - on request (`vault_lock`)
+ * - when the machine goes to sleep, if `lockOnSleep` is set (see `power`)
+ * - after `idleLockSecs` without user activity, if set
+ *
+ * Idle time is measured on the wall clock, not the monotonic one, so time
+ * spent suspended counts as idle: opening the lid hours later finds the
+ * vault locked even with `lockOnSleep` off. Only `touch` (user activity in
+ * the app, including vault reads and fill plan generation) resets the
+ * idle timer; extension polling doesn't. While locked, those reads fail
+ * too, whether or not the store itself can be locked. The thread from `spawn_idle_lock`
+ * locks when the timer runs out even if nothing asks, and after either
+ * automatic lock (idle or sleep) also locks a store that can be locked, so
+ * it forgets its key.
+ *
+ * Every transition to locked emits `VAULT_LOCKED_EVENT` with the lock
+ * status. Settings start from `ASTERISK_LOCK_ON_SLEEP` and
+ * `ASTERISK_IDLE_LOCK_SECS` and can be changed with `vault_lock_configure`;
+ * `settings_set("autoLockMinutes", n)` sets the idle timeout alone.
+ *
+ * `vault_lock` also locks an encrypted store itself: the store forgets its
+ * key and decrypted items, and every read fails until `vault_unlock`. A
+ * passphrase vault needs the passphrase to open again, even if the store
+ * hadn't locked yet; the keychain vault opened at startup reads its key
+ * from the keychain. The bridge treats a locked store as locked by hand.
+ * `VaultStatusJson` reports both.
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration as StdDuration;
+
+use asterisk_vault::VaultStore;
+
+use crate::power::PowerEvent;
+use crate::shared::SharedVault;
+use crate::EventSink;
+
+/// Event emitted when the vault locks
+pub const VAULT_LOCKED_EVENT: &str = "vault-locked";
+
+/// Environment variable enabling lock on sleep ("1" or "true")
+pub const LOCK_ON_SLEEP_ENV: &str = "ASTERISK_LOCK_ON_SLEEP";
+
+/// Environment variable setting the idle lock timeout in seconds
+pub const IDLE_LOCK_ENV: &str = "ASTERISK_IDLE_LOCK_SECS";
+
+/// Longest the idle lock thread sleeps before re-checking the wall clock
+const MAX_IDLE_WAIT: StdDuration = StdDuration::from_secs(1);
+
+/// Why the vault locked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockReason {
+    Manual,
+    Sleep,
+    Idle,
+}
+
+/// When the vault locks by itself, as configured and reported (the idle
+/// timeout in whole seconds)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockSettingsJson {
+    #[serde(rename = "lockOnSleep", default)]
+    pub lock_on_sleep: bool,
+    /// None never locks for idleness
+    #[serde(rename = "idleLockSecs", default)]
+    pub idle_lock_secs: Option<u64>,
+}
+
+impl LockSettingsJson {
+    /// Settings from the environment; unset means off
+    pub fn from_env() -> Result<Self, String> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let lock_on_sleep = match var(LOCK_ON_SLEEP_ENV).as_deref().map(str::trim) {
+            None | Some("") | Some("0") | Some("false") => false,
+            Some("1") | Some("true") => true,
+            Some(other) => return Err(format!("Invalid {}: {}", LOCK_ON_SLEEP_ENV, other)),
+        };
+        let idle_lock_secs = match var(IDLE_LOCK_ENV) {
+            None => None,
+            Some(secs) => Some(
+                secs.trim()
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|secs| *secs > 0)
+                    .ok_or_else(|| format!("Invalid {}: {}", IDLE_LOCK_ENV, secs))?,
+            ),
+        };
+        Ok(LockSettingsJson {
+            lock_on_sleep,
+            idle_lock_secs,
+        })
+    }
+}
+
+/// When the vault locks by itself
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LockSettings {
+    pub lock_on_sleep: bool,
+    /// None never locks for idleness
+    pub idle_timeout: Option<StdDuration>,
+}
+
+impl From<LockSettingsJson> for LockSettings {
+    fn from(settings: LockSettingsJson) -> Self {
+        LockSettings {
+            lock_on_sleep: settings.lock_on_sleep,
+            idle_timeout: settings.idle_lock_secs.map(StdDuration::from_secs),
+        }
+    }
+}
+
+impl From<LockSettings> for LockSettingsJson {
+    /// A timeout with a fraction of a second is rounded up
+    fn from(settings: LockSettings) -> Self {
+        LockSettingsJson {
+            lock_on_sleep: settings.lock_on_sleep,
+            idle_lock_secs: settings
+                .idle_timeout
+                .map(|timeout| timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0)),
+        }
+    }
+}
+
+/// Whether the vault is locked, and why
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VaultLockStatusJson {
+    pub locked: bool,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub reason: Option<LockReason>,
+    #[serde(rename = "lockedAt", skip_serializing_if = "Option::is_none", default)]
+    pub locked_at: Option<String>,
+    #[serde(rename = "lastActivityAt")]
+    pub last_activity_at: String,
+    pub settings: LockSettingsJson,
+}
+
+/// Result of `vault_status`: the lock above and the store's own lock
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VaultStatusJson {
+    /// Locked either way; nothing is served until both are unlocked
+    pub locked: bool,
+    /// The store has forgotten its key until it is unlocked
+    #[serde(rename = "storeLocked")]
+    pub store_locked: bool,
+    /// Locking forgets the store's key and decrypted items; when false,
+    /// locking only stops the bridge
+    #[serde(rename = "storeLockable")]
+    pub store_lockable: bool,
+    /// The store has a passphrase it can be locked behind
+    #[serde(rename = "passphraseLockable")]
+    pub passphrase_lockable: bool,
+    pub lock: VaultLockStatusJson,
+}
+
+impl VaultStatusJson {
+    pub fn new(lock: VaultLockStatusJson, store: &dyn VaultStore) -> Self {
+        VaultStatusJson {
+            locked: lock.locked || store.is_locked(),
+            store_locked: store.is_locked(),
+            store_lockable: store.is_lockable(),
+            passphrase_lockable: store.is_lockable() && store.unlock_needs_passphrase(),
+            lock,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockInner {
+    settings: LockSettings,
+    locked: Option<(LockReason, DateTime<Utc>)>,
+    last_activity: DateTime<Utc>,
+    /// Bumped whenever the idle lock thread should look again before its
+    /// wait is up: the settings changed or the vault locked
+    changes: u64,
+}
+
+impl LockInner {
+    fn status(&self) -> VaultLockStatusJson {
+        VaultLockStatusJson {
+            locked: self.locked.is_some(),
+            reason: self.locked.map(|(reason, _)| reason),
+            locked_at: self.locked.map(|(_, at)| at.to_rfc3339()),
+            last_activity_at: self.last_activity.to_rfc3339(),
+            settings: self.settings.into(),
+        }
+    }
+
+    /// When the idle timer runs out, if it has by `now`
+    fn idle_deadline_passed(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let deadline = self.idle_deadline()?;
+        (now >= deadline).then_some(deadline)
+    }
+
+    fn idle_deadline(&self) -> Option<DateTime<Utc>> {
+        let timeout = Duration::from_std(self.settings.idle_timeout?).ok()?;
+        Some(self.last_activity + timeout)
+    }
+
+    fn changed(&mut self, changed: &Condvar) {
+        self.changes += 1;
+        changed.notify_all();
+    }
+}
+
+/// The vault's lock state, shared by the app and the bridge
+pub struct VaultLock {
+    inner: Mutex<LockInner>,
+    /// Signalled with each bump of `LockInner::changes`
+    changed: Condvar,
+    events: EventSink,
+}
+
+impl VaultLock {
+    /// An unlocked vault with activity at `now`
+    pub fn new(settings: LockSettings, now: DateTime<Utc>, events: EventSink) -> Self {
+        VaultLock {
+            inner: Mutex::new(LockInner {
+                settings,
+                locked: None,
+                last_activity: now,
+                changes: 0,
+            }),
+            changed: Condvar::new(),
+            events,
+        }
+    }
+
+    fn inner(&self) -> MutexGuard<'_, LockInner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn configure(&self, settings: LockSettings) {
+        let mut inner = self.inner();
+        inner.settings = settings;
+        inner.changed(&self.changed);
+    }
+
+    /// Change only the idle timeout (None never locks for idleness)
+    pub fn set_idle_timeout(&self, timeout: Option<StdDuration>) {
+        let mut inner = self.inner();
+        inner.settings.idle_timeout = timeout;
+        inner.changed(&self.changed);
+    }
+
+    /// Lock state at `now`, locking first if the idle timer ran out
+    pub fn status(&self, now: DateTime<Utc>) -> VaultLockStatusJson {
+        let _ = self.check(now);
+        self.inner().status()
+    }
+
+    /// Ok while unlocked; locks first if the idle timer ran out by `now`
+    pub fn check(&self, now: DateTime<Utc>) -> Result<(), LockReason> {
+        let mut inner = self.inner();
+        if let Some((reason, _)) = inner.locked {
+            return Err(reason);
+        }
+        match inner.idle_deadline_passed(now) {
+            Some(deadline) => {
+                self.lock_inner(&mut inner, LockReason::Idle, deadline);
+                Err(LockReason::Idle)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Record user activity at `now`; refused while locked
+    pub fn touch(&self, now: DateTime<Utc>) -> Result<(), LockReason> {
+        self.check(now)?;
+        let mut inner = self.inner();
+        inner.last_activity = inner.last_activity.max(now);
+        Ok(())
+    }
+
+    pub fn lock(&self, reason: LockReason, now: DateTime<Utc>) {
+        let mut inner = self.inner();
+        self.lock_inner(&mut inner, reason, now);
+    }
+
+    /// Unlock, counting the unlock as activity
+    pub fn unlock(&self, now: DateTime<Utc>) -> VaultLockStatusJson {
+        let mut inner = self.inner();
+        inner.locked = None;
+        inner.last_activity = now;
+        inner.status()
+    }
+
+    /// Lock on sleep if configured; on resume, re-check the idle timer
+    /// against the wall clock so the suspension counts as idle time
+    pub fn power_event(&self, event: PowerEvent) {
+        let mut inner = self.inner();
+        match event {
+            PowerEvent::Sleep { at } => {
+                if inner.settings.lock_on_sleep {
+                    self.lock_inner(&mut inner, LockReason::Sleep, at);
+                }
+            }
+            PowerEvent::Resume { at } => {
+                if let Some(deadline) = inner.idle_deadline_passed(at) {
+                    self.lock_inner(&mut inner, LockReason::Idle, deadline);
+                }
+            }
+        }
+    }
+
+    /// How long the idle lock thread may sleep from `now`, locking first if
+    /// the idle timer ran out, and the changes seen so far
+    fn idle_wait(&self, now: DateTime<Utc>) -> (StdDuration, u64) {
+        let locked = self.check(now).is_err();
+        let inner = self.inner();
+        let wait = match inner.idle_deadline() {
+            Some(deadline) if !locked => (deadline - now)
+                .to_std()
+                .map_or(MAX_IDLE_WAIT, |remaining| remaining.min(MAX_IDLE_WAIT)),
+            _ => MAX_IDLE_WAIT,
+        };
+        (wait, inner.changes)
+    }
+
+    /// Sleep for up to `wait`, waking early on a change after `seen`
+    fn sleep_unless_changed(&self, wait: StdDuration, seen: u64) {
+        let inner = self.inner();
+        let _ = self
+            .changed
+            .wait_timeout_while(inner, wait, |inner| inner.changes == seen);
+    }
+
+    /// Lock unless already locked; emits the event on the transition
+    fn lock_inner(&self, inner: &mut LockInner, reason: LockReason, at: DateTime<Utc>) {
+        if inner.locked.is_some() {
+            return;
+        }
+        inner.locked = Some((reason, at));
+        inner.changed(&self.changed);
+        (self.events)(
+            VAULT_LOCKED_EVENT,
+            serde_json::to_value(inner.status()).unwrap_or_default(),
+        );
+    }
+}
+
+/// Start the thread that locks `lock` when its idle timer runs out, and
+/// `vault` too, if it can be locked, after an idle or sleep lock; it ends
+/// once the last `Arc` of the lock is dropped
+pub fn spawn_idle_lock(lock: &Arc<VaultLock>, vault: SharedVault) -> thread::JoinHandle<()> {
+    let weak = Arc::downgrade(lock);
+    thread::spawn(move || {
+        while let Some(lock) = weak.upgrade() {
+            let (wait, seen) = lock.idle_wait(Utc::now());
+            lock_store_if_idle(&lock, &vault);
+            lock.sleep_unless_changed(wait, seen);
+        }
+    })
+}
+
+fn lock_store_if_idle(lock: &VaultLock, vault: &SharedVault) {
+    // Checked under the store's guard, so an unlock in progress (which
+    // holds it across both locks) is never undone halfway
+    let mut store = match vault.lock() {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("[Vault Lock] Idle lock failed to lock the store: {}", e);
+            return;
+        }
+    };
+    if !store.is_lockable() || store.is_locked() {
+        return;
+    }
+    if let Err(reason @ (LockReason::Idle | LockReason::Sleep)) = lock.check(Utc::now()) {
+        match store.lock() {
+            Ok(()) => println!("[Vault Lock] Locked the store ({:?})", reason),
+            Err(e) => eprintln!("[Vault Lock] Failed to lock the store: {}", e),
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::power::{watch, MockPowerSource};
+    use std::sync::Arc;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        "2026-05-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::seconds(secs)
+    }
+
+    /// A lock whose emitted events are collected
+    fn lock_with(settings: LockSettings) -> (Arc<VaultLock>, Arc<Mutex<Vec<LockReason>>>) {
+        let emitted = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&emitted);
+        let events: EventSink = Arc::new(move |name, payload| {
+            assert_eq!(name, VAULT_LOCKED_EVENT);
+            let status: VaultLockStatusJson = serde_json::from_value(payload).unwrap();
+            sink.lock().unwrap().push(status.reason.unwrap());
+        });
+        (Arc::new(VaultLock::new(settings, at(0), events)), emitted)
+    }
+
+    fn run(lock: &Arc<VaultLock>, events: Vec<PowerEvent>) {
+        watch(MockPowerSource::new(events), Arc::clone(lock))
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_sleep_locks_when_enabled() {
+        let (lock, emitted) = lock_with(LockSettings {
+            lock_on_sleep: true,
+            idle_timeout: None,
+        });
+        run(
+            &lock,
+            vec![
+                PowerEvent::Sleep { at: at(60) },
+                PowerEvent::Resume { at: at(4 * 3600) },
+            ],
+        );
+        assert_eq!(lock.check(at(4 * 3600)), Err(LockReason::Sleep));
+        assert_eq!(lock.touch(at(4 * 3600)), Err(LockReason::Sleep));
+        assert_eq!(*emitted.lock().unwrap(), vec![LockReason::Sleep]);
+
+        let status = lock.unlock(at(4 * 3600 + 5));
+        assert!(!status.locked);
+        assert_eq!(lock.check(at(4 * 3600 + 6)), Ok(()));
+
+        // A second sleep locks again, and emits again
+        run(&lock, vec![PowerEvent::Sleep { at: at(5 * 3600) }]);
+        assert_eq!(emitted.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_sleep_alone_doesnt_lock_when_disabled() {
+        let (lock, emitted) = lock_with(LockSettings::default());
+        run(
+            &lock,
+            vec![
+                PowerEvent::Sleep { at: at(60) },
+                PowerEvent::Resume { at: at(4 * 3600) },
+            ],
+        );
+        assert_eq!(lock.check(at(4 * 3600)), Ok(()));
+        assert!(emitted.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_suspension_counts_as_idle_time() {
+        let (lock, emitted) = lock_with(LockSettings {
+            lock_on_sleep: false,
+            idle_timeout: Some(StdDuration::from_secs(600)),
+        });
+        lock.touch(at(100)).unwrap();
+
+        // Asleep from 2 minutes to 3 hours: far past the idle timeout,
+        // though the process itself only ran for a few minutes
+        run(
+            &lock,
+            vec![
+                PowerEvent::Sleep { at: at(120) },
+                PowerEvent::Resume { at: at(3 * 3600) },
+            ],
+        );
+        let status = lock.status(at(3 * 3600));
+        assert!(status.locked);
+        assert_eq!(status.reason, Some(LockReason::Idle));
+        assert_eq!(status.locked_at, Some(at(700).to_rfc3339()));
+        assert_eq!(*emitted.lock().unwrap(), vec![LockReason::Idle]);
+
+        // A short nap inside the timeout doesn't lock
+        lock.unlock(at(3 * 3600));
+        run(
+            &lock,
+            vec![
+                PowerEvent::Sleep {
+                    at: at(3 * 3600 + 60),
+                },
+                PowerEvent::Resume {
+                    at: at(3 * 3600 + 300),
+                },
+            ],
+        );
+        assert_eq!(lock.check(at(3 * 3600 + 300)), Ok(()));
+    }
+
+    #[test]
+    fn test_clock_jumps_on_activity() {
+        let (lock, _) = lock_with(LockSettings {
+            lock_on_sleep: false,
+            idle_timeout: Some(StdDuration::from_secs(600)),
+        });
+        lock.touch(at(500)).unwrap();
+        // The wall clock stepping back doesn't rewind the last activity
+        lock.touch(at(-3600)).unwrap();
+        assert_eq!(lock.check(at(1099)), Ok(()));
+        // Stepping forward past the timeout locks, resume event or not
+        assert_eq!(lock.touch(at(1100)), Err(LockReason::Idle));
+    }
+
+    #[test]
+    fn test_settings_from_vars() {
+        let vars = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        assert_eq!(
+            LockSettingsJson::from_vars(vars(&[])).unwrap(),
+            LockSettingsJson::default()
+        );
+        assert_eq!(
+            LockSettingsJson::from_vars(vars(&[
+                (LOCK_ON_SLEEP_ENV, "true"),
+                (IDLE_LOCK_ENV, "900")
+            ]))
+            .unwrap(),
+            LockSettingsJson {
+                lock_on_sleep: true,
+                idle_lock_secs: Some(900),
+            }
+        );
+        assert!(LockSettingsJson::from_vars(vars(&[(LOCK_ON_SLEEP_ENV, "sometimes")])).is_err());
+        assert!(LockSettingsJson::from_vars(vars(&[(IDLE_LOCK_ENV, "0")])).is_err());
+    }
+
+    #[test]
+    fn test_sub_second_timeout_reports_whole_seconds() {
+        let (lock, _) = lock_with(LockSettings {
+            lock_on_sleep: false,
+            idle_timeout: Some(StdDuration::from_millis(100)),
+        });
+        assert_eq!(lock.status(at(0)).settings.idle_lock_secs, Some(1));
+        assert_eq!(lock.check(at(0) + Duration::milliseconds(99)), Ok(()));
+        assert_eq!(
+            lock.check(at(0) + Duration::milliseconds(100)),
+            Err(LockReason::Idle)
+        );
+    }
+
+    /// A passphrase-locked vault watched by the idle lock thread
+    struct IdleFixture {
+        lock: Arc<VaultLock>,
+        vault: SharedVault,
+        emitted: Arc<Mutex<Vec<LockReason>>>,
+        dir: std::path::PathBuf,
+    }
+
+    impl IdleFixture {
+        fn new(name: &str, idle_timeout: Option<StdDuration>) -> Self {
+            use asterisk_vault::{EncryptedFileStore, KdfParams, LockableStore};
+            let dir = std::env::temp_dir().join(format!(
+                "asterisk-idle-lock-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&dir);
+            let cheap = KdfParams {
+                mem_kib: 64,
+                iterations: 1,
+                parallelism: 1,
+            };
+            let store =
+                EncryptedFileStore::create_with_kdf(dir.join("vault.enc"), "pass", cheap).unwrap();
+            let store: Box<dyn VaultStore> = Box::new(LockableStore::new(store));
+            let vault = SharedVault::new("vault", store);
+            let (lock, emitted) = lock_with(LockSettings {
+                lock_on_sleep: false,
+                idle_timeout,
+            });
+            // Activity now, on the wall clock the thread reads
+            lock.unlock(Utc::now());
+            spawn_idle_lock(&lock, vault.clone());
+            IdleFixture {
+                lock,
+                vault,
+                emitted,
+                dir,
+            }
+        }
+
+        fn store_locked(&self) -> bool {
+            self.vault.lock().unwrap().is_locked()
+        }
+
+        /// Whether the store locks within `within`
+        fn locks_within(&self, within: StdDuration) -> bool {
+            let start = std::time::Instant::now();
+            while start.elapsed() < within {
+                if self.store_locked() {
+                    return true;
+                }
+                thread::sleep(StdDuration::from_millis(10));
+            }
+            self.store_locked()
+        }
+    }
+
+    impl Drop for IdleFixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    const IDLE_TIMEOUT: StdDuration = StdDuration::from_millis(100);
+
+    #[test]
+    fn test_idle_thread_locks_the_store_without_a_request() {
+        let f = IdleFixture::new("fires", Some(IDLE_TIMEOUT));
+        assert!(f.locks_within(IDLE_TIMEOUT * 4));
+        assert_eq!(*f.emitted.lock().unwrap(), vec![LockReason::Idle]);
+        assert!(matches!(
+            f.vault.lock().unwrap().list(),
+            Err(asterisk_vault::VaultError::Locked)
+        ));
+    }
+
+    #[test]
+    fn test_touches_keep_the_idle_thread_off() {
+        let f = IdleFixture::new("activity", Some(IDLE_TIMEOUT));
+        let start = std::time::Instant::now();
+        while start.elapsed() < IDLE_TIMEOUT * 3 {
+            f.lock.touch(Utc::now()).unwrap();
+            thread::sleep(IDLE_TIMEOUT / 10);
+        }
+        assert!(!f.store_locked());
+
+        // Left alone, it locks
+        assert!(f.locks_within(IDLE_TIMEOUT * 4));
+    }
+
+    #[test]
+    fn test_new_idle_timeout_applies_without_restart() {
+        let f = IdleFixture::new("settings", None);
+        thread::sleep(IDLE_TIMEOUT * 2);
+        assert!(!f.store_locked());
+
+        // The thread wakes for the change rather than finishing its wait
+        f.lock.set_idle_timeout(Some(IDLE_TIMEOUT));
+        assert!(f.locks_within(IDLE_TIMEOUT * 4));
+        // A manual lock leaves a passphrase store alone
+        f.vault.lock().unwrap().unlock("pass").unwrap();
+        f.lock.unlock(Utc::now());
+        f.lock.set_idle_timeout(None);
+        f.lock.lock(LockReason::Manual, Utc::now());
+        thread::sleep(IDLE_TIMEOUT * 2);
+        assert!(!f.store_locked());
+    }
+
+    #[test]
+    fn test_sleep_lock_also_locks_the_store() {
+        let f = IdleFixture::new("sleep", None);
+        f.lock.configure(LockSettings {
+            lock_on_sleep: true,
+            idle_timeout: None,
+        });
+        f.lock.power_event(PowerEvent::Sleep { at: Utc::now() });
+        assert!(f.locks_within(IDLE_TIMEOUT * 4));
+        assert_eq!(*f.emitted.lock().unwrap(), vec![LockReason::Sleep]);
+    }
+}
@@ -3,6 +3,7 @@ import FormsTab from './FormsTab';
 import MatchingPanel from './MatchingPanel';
 import SettingsTab from './SettingsTab';
 import AuditTab from './AuditTab';
+import { bridgeFetch } from './bridge';
 import './App.css';
 
 // Check if we're running in Tauri context
@@ -58,9 +59,9 @@ function VaultTab() {
 
       if (isTauri) {
         const { invoke } = await import('@tauri-apps/api/core');
-        result = await invoke<VaultItem[]>('vault_list');
+        result = (await invoke<{ items: VaultItem[] }>('vault_list')).items;
       } else {
-        const response = await fetch('http://127.0.0.1:17373/v1/vault');
+        const response = await bridgeFetch('/v1/vault');
         if (response.ok) {
           result = await response.json();
         }
@@ -112,7 +113,7 @@ function VaultTab() {
         const { invoke } = await import('@tauri-apps/api/core');
         await invoke('vault_set', { key, item: newItem });
       } else {
-        await fetch('http://127.0.0.1:17373/v1/vault', {
+        await bridgeFetch('/v1/vault', {
           method: 'POST',
           headers: { 'Content-Type': 'application/json' },
           body: JSON.stringify(newItem),
@@ -147,7 +148,7 @@ function VaultTab() {
         const { invoke } = await import('@tauri-apps/api/core');
         await invoke('vault_delete', { key: itemKey });
       } else {
-        await fetch(`http://127.0.0.1:17373/v1/vault?key=${encodeURIComponent(itemKey)}`, {
+        await bridgeFetch(`/v1/vault?key=${encodeURIComponent(itemKey)}`, {
           method: 'DELETE',
         });
       }
@@ -1,4 +1,5 @@
 import { useEffect, useState, useCallback } from 'react';
+import { bridgeFetch } from './bridge';
 
 // Check if we're running in Tauri context
 const isTauri = typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
@@ -51,7 +52,7 @@ export function FormsTab() {
         result = await invoke<FormSnapshotJson | null>('get_latest_form_snapshot');
       } else {
         // Browser fallback: fetch from HTTP API
-        const response = await fetch('http://127.0.0.1:17373/v1/form-snapshots');
+        const response = await bridgeFetch('/v1/form-snapshots');
         if (response.ok) {
           result = await response.json();
         }
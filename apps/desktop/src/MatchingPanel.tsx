@@ -21,6 +21,7 @@ import {
   type VaultItemInfo,
 } from './components/fillplan';
 import type { LastAppliedOperation, AuditEntry } from './types/audit';
+import { bridgeFetch } from './bridge';
 
 // Check if we're running in Tauri context
 const isTauri = typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
@@ -99,6 +100,7 @@ interface VaultItemJson {
     last_used?: string;
     usage_count: number;
   };
+  review_status?: VaultItem['reviewStatus'];
 }
 
 // ============================================================================
@@ -134,6 +136,7 @@ function toVaultItems(json: VaultItemJson[]): VaultItem[] {
       lastUsed: item.metadata.last_used ? new Date(item.metadata.last_used) : undefined,
       usageCount: item.metadata.usage_count,
     },
+    reviewStatus: item.review_status,
   }));
 }
 
@@ -191,7 +194,7 @@ export function MatchingPanel() {
       if (isTauri) {
         result = await invoke<FormSnapshotJson | null>('get_latest_form_snapshot');
       } else {
-        const response = await fetch('http://127.0.0.1:17373/v1/form-snapshots');
+        const response = await bridgeFetch('/v1/form-snapshots');
         if (response.ok) {
           result = await response.json();
         }
@@ -211,9 +214,9 @@ export function MatchingPanel() {
       let result: VaultItemJson[] = [];
 
       if (isTauri) {
-        result = await invoke<VaultItemJson[]>('vault_list');
+        result = (await invoke<{ items: VaultItemJson[] }>('vault_list')).items;
       } else {
-        const response = await fetch('http://127.0.0.1:17373/v1/vault');
+        const response = await bridgeFetch('/v1/vault');
         if (response.ok) {
           result = await response.json();
         }
@@ -249,6 +252,15 @@ export function MatchingPanel() {
         return;
       }
 
+      if (isTauri) {
+        // Backend plans carry an id so the reviewed fields can be accepted
+        const plan = await invoke<(Omit<FillPlan, 'generatedAt'> & { generatedAt: string }) | null>(
+          'generate_fill_plan'
+        );
+        setFillPlan(plan ? { ...plan, generatedAt: new Date(plan.generatedAt) } : null);
+        return;
+      }
+
       // Convert to core types and generate plan
       const formSnapshot = toFormSnapshot(currentSnapshot);
       const items = toVaultItems(currentVaultItems);
@@ -380,6 +392,50 @@ export function MatchingPanel() {
         rec => selectedFieldIds.includes(rec.fieldId)
       );
 
+      if (isTauri && fillPlan.planId) {
+        // Freeze exactly what was approved; AI matches were added after the
+        // backend plan, so they come along as overrides
+        const accepted = await invoke<{ id: string; contentHash: string }>('fill_plan_accept', {
+          planId: fillPlan.planId,
+          acceptedFieldIds: selectedRecs.map(rec => rec.fieldId),
+          overrides: selectedRecs
+            .filter(rec => rec.matchTier === 'llm')
+            .map(rec => ({ fieldId: rec.fieldId, vaultKey: rec.vaultKey })),
+        });
+        const created = await invoke<{ command: FillCommand; status: string }>(
+          'fill_command_create',
+          { acceptanceId: accepted.id, captureUndo: true }
+        );
+        if (created.status === 'awaiting_consent') {
+          const allowed = window.confirm(`Allow Asterisk to fill forms on ${snapshot.domain}?`);
+          if (allowed) {
+            await invoke('consent_grant', { domain: snapshot.domain, scope: 'always' });
+          } else {
+            await invoke('consent_deny', { domain: snapshot.domain });
+            setError(`Fill cancelled: no consent for ${snapshot.domain}`);
+            return;
+          }
+        }
+        try {
+          await invoke('audit_append', {
+            entry: {
+              ...auditEntry,
+              acceptanceId: accepted.id,
+              acceptanceHash: accepted.contentHash,
+              fillCommandId: created.command.id,
+            },
+          });
+        } catch (auditErr) {
+          console.warn('Failed to store audit entry:', auditErr);
+        }
+        setLastApplied(null);
+        setSuccess(
+          `Fill command sent! ${created.command.fills.length} field(s) ready to fill on ${snapshot.domain}`
+        );
+        setTimeout(() => setSuccess(null), 5000);
+        return;
+      }
+
       // Convert recommendations to fills with actual values
       const fills: FieldFill[] = selectedRecs
         .map(rec => {
@@ -387,7 +443,7 @@ export function MatchingPanel() {
           if (!vaultItem) return null;
           return {
             fieldId: rec.fieldId,
-            value: vaultItem.value,
+            value: rec.value ?? vaultItem.value,
           };
         })
         .filter((f): f is FieldFill => f !== null);
@@ -411,7 +467,7 @@ export function MatchingPanel() {
       };
 
       // Send to backend
-      const response = await fetch('http://127.0.0.1:17373/v1/fill-commands', {
+      const response = await bridgeFetch('/v1/fill-commands', {
         method: 'POST',
         headers: { 'Content-Type': 'application/json' },
         body: JSON.stringify(command),
@@ -421,6 +477,19 @@ export function MatchingPanel() {
         throw new Error(`Failed to send fill command: ${response.statusText}`);
       }
 
+      // First fill on a domain waits for the user's consent
+      const result = await response.json();
+      if (result.status === 'awaiting_consent' && isTauri) {
+        const allowed = window.confirm(`Allow Asterisk to fill forms on ${snapshot.domain}?`);
+        if (allowed) {
+          await invoke('consent_grant', { domain: snapshot.domain, scope: 'always' });
+        } else {
+          await invoke('consent_deny', { domain: snapshot.domain });
+          setError(`Fill cancelled: no consent for ${snapshot.domain}`);
+          return;
+        }
+      }
+
       // Track for undo support
       const oldValues: Record<string, string> = {};
       const newValues: Record<string, string> = {};
@@ -479,7 +548,7 @@ export function MatchingPanel() {
         expiresAt: expiresAt.toISOString(),
       };
 
-      const response = await fetch('http://127.0.0.1:17373/v1/fill-commands', {
+      const response = await bridgeFetch('/v1/fill-commands', {
         method: 'POST',
         headers: { 'Content-Type': 'application/json' },
         body: JSON.stringify(command),
@@ -1,4 +1,5 @@
 import { useState, useEffect, useCallback } from 'react';
+import { pairingToken } from './bridge';
 
 // Check if we're running in Tauri context
 const isTauri = typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
@@ -95,6 +96,8 @@ export function SettingsTab() {
   const [error, setError] = useState<string | null>(null);
   const [success, setSuccess] = useState<string | null>(null);
   const [showApiKey, setShowApiKey] = useState(false);
+  const [bridgeToken, setBridgeToken] = useState<string | null>(null);
+  const [showBridgeToken, setShowBridgeToken] = useState(false);
 
   // Load settings on mount
   const load = useCallback(async () => {
@@ -114,6 +117,24 @@ export function SettingsTab() {
     load();
   }, [load]);
 
+  // The token the extension needs to reach the bridge
+  useEffect(() => {
+    pairingToken()
+      .then(setBridgeToken)
+      .catch(() => setBridgeToken(null));
+  }, []);
+
+  const handleCopyBridgeToken = async () => {
+    if (!bridgeToken) return;
+    try {
+      await navigator.clipboard.writeText(bridgeToken);
+      setSuccess('Pairing token copied. Paste it into the extension settings.');
+      setTimeout(() => setSuccess(null), 3000);
+    } catch (err) {
+      setError(err instanceof Error ? err.message : 'Failed to copy pairing token');
+    }
+  };
+
   // Save settings
   const handleSave = async () => {
     try {
@@ -251,6 +272,45 @@ export function SettingsTab() {
         </div>
       </section>
 
+      <section className="settings-section">
+        <h2>Browser Extension</h2>
+        <p className="settings-description">
+          The extension needs this pairing token to reach Asterisk. Copy it into the extension's
+          settings (the ⚙ button in its popup).
+        </p>
+
+        <div className="form-group">
+          <label htmlFor="bridgeToken">Pairing Token</label>
+          <div className="api-key-input">
+            <input
+              id="bridgeToken"
+              type={showBridgeToken ? 'text' : 'password'}
+              value={bridgeToken ?? ''}
+              placeholder="Not available"
+              readOnly
+            />
+            <button
+              type="button"
+              onClick={() => setShowBridgeToken(!showBridgeToken)}
+              className="toggle-visibility"
+              title={showBridgeToken ? 'Hide pairing token' : 'Show pairing token'}
+            >
+              {showBridgeToken ? '👁️' : '👁️‍🗨️'}
+            </button>
+          </div>
+        </div>
+
+        <div className="button-group">
+          <button
+            onClick={handleCopyBridgeToken}
+            disabled={!bridgeToken}
+            className="secondary-btn"
+          >
+            Copy Pairing Token
+          </button>
+        </div>
+      </section>
+
       <section className="settings-section">
         <h2>Matching Options</h2>
 
@@ -0,0 +1,83 @@
+/**
+ * Desktop Bridge Requests
+ *
+ * The UI's own HTTP calls to the bridge. Every /v1 route needs the pairing
+ * token: inside the desktop app it comes from the `bridge_token` command;
+ * in a plain browser the user is asked for it once and it is kept in
+ * localStorage.
+ *
+ * The bridge may not be on its default port when another process held it;
+ * the desktop app reports the port it bound through `bridge_port`.
+ */
+
+const isTauri = typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
+
+const DEFAULT_BRIDGE_PORT = 17373;
+
+const TOKEN_STORAGE_KEY = 'asterisk_bridge_token';
+
+let tauriToken: Promise<string> | null = null;
+let tauriPort: Promise<number> | null = null;
+
+/**
+ * Base URL of the bridge; a plain browser assumes the default port
+ */
+export async function bridgeUrl(): Promise<string> {
+  if (!isTauri) {
+    return `http://127.0.0.1:${DEFAULT_BRIDGE_PORT}`;
+  }
+  if (!tauriPort) {
+    tauriPort = import('@tauri-apps/api/core').then(({ invoke }) =>
+      invoke<number>('bridge_port')
+    );
+    // The bridge may not have started yet; ask again next time
+    tauriPort.catch(() => {
+      tauriPort = null;
+    });
+  }
+  return `http://127.0.0.1:${await tauriPort}`;
+}
+
+/**
+ * The pairing token, as the desktop app hands it to the extension
+ */
+export async function pairingToken(): Promise<string | null> {
+  if (isTauri) {
+    if (!tauriToken) {
+      tauriToken = import('@tauri-apps/api/core').then(({ invoke }) =>
+        invoke<string>('bridge_token')
+      );
+    }
+    return tauriToken;
+  }
+  return localStorage.getItem(TOKEN_STORAGE_KEY);
+}
+
+async function send(path: string, init: RequestInit, token: string | null): Promise<Response> {
+  const headers = new Headers(init.headers);
+  if (token) {
+    headers.set('X-Asterisk-Token', token);
+  }
+  return fetch(`${await bridgeUrl()}${path}`, { ...init, headers });
+}
+
+/**
+ * `fetch` against the bridge with the pairing token attached
+ *
+ * In a browser, a 401 asks for the token from the desktop app's Settings
+ * tab and retries once with it.
+ */
+export async function bridgeFetch(path: string, init: RequestInit = {}): Promise<Response> {
+  const response = await send(path, init, await pairingToken());
+  if (response.status !== 401 || isTauri) {
+    return response;
+  }
+  const entered = window
+    .prompt("Paste the pairing token from the Asterisk desktop app's Settings tab")
+    ?.trim();
+  if (!entered) {
+    return response;
+  }
+  localStorage.setItem(TOKEN_STORAGE_KEY, entered);
+  return send(path, init, entered);
+}
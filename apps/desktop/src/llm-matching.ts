@@ -20,6 +20,15 @@ import type {
 // Types
 // ============================================================================
 
+/** One field's answer from the backend */
+export interface LlmAnswer {
+  vault_key: string | null;
+  confidence: number;
+  reasoning: string;
+  /** Provider that wrote the answer; absent for saved-template answers */
+  provider?: string;
+}
+
 export interface FieldAnalysis {
   fieldId: string;
   semantic: FieldSemantic;
@@ -57,10 +66,7 @@ async function analyzeSingleField(
   };
 
   // Call Rust backend
-  const response = await invoke<{ vault_key: string | null; confidence: number; reasoning: string }>(
-    'llm_analyze_field',
-    { request }
-  );
+  const response = await invoke<LlmAnswer>('llm_analyze_field', { request });
 
   return {
     vaultKey: response.vault_key,
@@ -69,6 +75,169 @@ async function analyzeSingleField(
   };
 }
 
+export interface SnapshotFieldAnalysis {
+  fieldId: string;
+  status: 'analyzed' | 'skipped_by_user' | 'failed' | 'deadline_exceeded' | 'budget_exceeded';
+  result?: LlmAnswer;
+  error?: string;
+  cached: boolean;
+  /** Where a cached answer came from */
+  cacheSource?: 'llm' | 'template';
+  /** Cached answer based on since-renamed vault labels; being re-run */
+  revalidating: boolean;
+}
+
+/** Payload of the 'analysis-superseded' event */
+export interface AnalysisSuperseded {
+  domain: string;
+  tabId: string | null;
+  sessionId: number;
+  supersededBy: number;
+  startedAt: string;
+  supersededAt: string;
+  cacheHits: number;
+  /** Answers received before cancellation; cached, so the newer analysis reuses them */
+  llmAnswers: number;
+  cancelledCalls: number;
+  unstarted: number;
+}
+
+/**
+ * Analyze the latest snapshot's unmatched fields in the Tauri backend
+ *
+ * A later call for the same domain and tab supersedes this one, which then
+ * rejects and raises an 'analysis-superseded' event.
+ *
+ * @param fieldIds - Fields the user left selected; omit to analyze every
+ *   unmatched field. Deselected fields come back as 'skipped_by_user'.
+ * @param tabId - Browser tab the snapshot came from, if known
+ * @param deadlineMs - Time budget for the whole call; fields the LLM hasn't
+ *   answered by then come back as 'deadline_exceeded'
+ */
+export async function analyzeSnapshot(
+  fieldIds?: string[],
+  tabId?: string,
+  deadlineMs?: number
+): Promise<SnapshotFieldAnalysis[]> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<SnapshotFieldAnalysis[]>('llm_analyze_snapshot', {
+    fieldIds: fieldIds ?? null,
+    tabId: tabId ?? null,
+    deadlineMs: deadlineMs ?? null,
+  });
+}
+
+export interface BatchFieldResult {
+  fieldId: string;
+  result: LlmAnswer;
+  /** False when the model's answer for this field was missing or malformed */
+  parsed: boolean;
+}
+
+/**
+ * Analyze several fields in one LLM call
+ *
+ * A malformed answer for one field doesn't fail the others: that field comes
+ * back unparsed, as no match with the reason.
+ */
+export async function analyzeBatch(
+  fields: FieldNode[],
+  vaultItems: VaultItem[]
+): Promise<BatchFieldResult[]> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  const availableKeys = vaultItems.map(item => item.key);
+  const requests = fields.map(field => ({
+    fieldId: field.id,
+    label: field.label,
+    name: field.name,
+    type: field.type,
+    placeholder: field.placeholder || null,
+    semantic: field.semantic || null,
+    available_keys: availableKeys,
+  }));
+  return invoke<BatchFieldResult[]>('llm_analyze_batch', { requests });
+}
+
+/**
+ * Reload saved template mappings into the analysis cache
+ *
+ * @returns Number of field mappings loaded
+ */
+export async function warmCacheFromTemplates(): Promise<number> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<number>('cache_warm_from_templates');
+}
+
+export interface LlmBudget {
+  /** Most tokens per window; null for no cap */
+  maxTokens: number | null;
+  /** Most estimated dollars per window; null for no cap */
+  maxUsd: number | null;
+  windowHours: number;
+  usdPerMillionTokens: number;
+}
+
+export interface LlmBudgetStatus {
+  budget: LlmBudget;
+  windowStartedAt: string;
+  windowEndsAt: string;
+  calls: number;
+  usedTokens: number;
+  usedUsd: number;
+  remainingTokens: number | null;
+  remainingUsd: number | null;
+  /** LLM calls are refused until windowEndsAt */
+  exceeded: boolean;
+}
+
+/**
+ * LLM usage in the current budget window
+ */
+export async function getLlmBudgetStatus(): Promise<LlmBudgetStatus> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<LlmBudgetStatus>('llm_budget_status');
+}
+
+/**
+ * Replace the LLM budget; usage so far in the window still counts
+ */
+export async function configureLlmBudget(budget: LlmBudget): Promise<LlmBudgetStatus> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<LlmBudgetStatus>('llm_budget_configure', { budget });
+}
+
+export interface EagerAnalysisStatus {
+  enabled: boolean;
+  /** Background jobs started */
+  started: number;
+  /** Jobs whose plan was kept */
+  completed: number;
+  /** Jobs superseded by a newer snapshot or a user request */
+  cancelled: number;
+  /** Snapshots not analyzed (private window, locked vault, over budget, new domain) */
+  skipped: number;
+  /** Plan requests served from a kept plan */
+  hits: number;
+  /** Plans kept right now */
+  plans: number;
+}
+
+/**
+ * Turn background planning of arriving snapshots on or off
+ */
+export async function setEagerAnalysisEnabled(enabled: boolean): Promise<void> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke('eager_analysis_set_enabled', { enabled });
+}
+
+/**
+ * Whether eager analysis is on, and what it has done
+ */
+export async function getEagerAnalysisStatus(): Promise<EagerAnalysisStatus> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<EagerAnalysisStatus>('eager_analysis_status');
+}
+
 // ============================================================================
 // Matching Functions
 // ============================================================================
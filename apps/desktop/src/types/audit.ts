@@ -56,6 +56,12 @@ export interface AuditSummary {
   reviewedCount: number;
 }
 
+/**
+ * How much of each fill the audit log keeps: every field item (full), the
+ * entry without items (summary), or just time, domain and counts (minimal).
+ */
+export type AuditDetail = 'full' | 'summary' | 'minimal';
+
 /**
  * A single audit log entry representing one fill operation.
  */
@@ -74,6 +80,18 @@ export interface AuditEntry {
   summary: AuditSummary;
   /** Individual field items */
   items: AuditItem[];
+  /** Whether recall was on for the domain, so the filled values were kept */
+  recallRecorded?: boolean;
+  /** The fill command that performed this fill; needed for undo */
+  fillCommandId?: string;
+  /** The plan acceptance the fill was built from */
+  acceptanceId?: string;
+  /** Content hash of that acceptance (set by the backend) */
+  acceptanceHash?: string;
+  /** Content hash of the accepted plan (set by the backend) */
+  planHash?: string;
+  /** How much of the fill was kept (set by the backend); absent means full */
+  detail?: AuditDetail;
 }
 
 /**
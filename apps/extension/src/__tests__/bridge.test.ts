@@ -0,0 +1,87 @@
+/**
+ * Desktop Bridge Tests
+ *
+ * The desktop refuses /v1 requests without the pairing token, so every
+ * bridge request has to carry the token the user stored. When port 17373
+ * is taken the bridge moves to one of the next ports, and the extension
+ * has to find it there.
+ */
+
+import { describe, it, expect, vi, afterEach } from 'vitest';
+import {
+  bridgeHeaders,
+  bridgeUrl,
+  probeBridge,
+  savePairingToken,
+  BRIDGE_PORT_KEY,
+  BRIDGE_TOKEN_KEY,
+} from '../bridge';
+
+describe('Bridge headers', () => {
+  it('sends the stored pairing token', async () => {
+    chrome.storage.local.get = vi.fn().mockResolvedValue({ [BRIDGE_TOKEN_KEY]: 'ast_secret' });
+
+    const headers = await bridgeHeaders();
+    expect(headers['X-Asterisk-Token']).toBe('ast_secret');
+    expect(headers['X-Asterisk-Client']).toBe('asterisk-extension/0.2.0');
+  });
+
+  it('sends no token before pairing', async () => {
+    const headers = await bridgeHeaders();
+    expect(headers).not.toHaveProperty('X-Asterisk-Token');
+    expect(headers['X-Asterisk-Client']).toBe('asterisk-extension/0.2.0');
+  });
+
+  it('stores a pasted token trimmed and forgets an empty one', async () => {
+    await savePairingToken('  ast_secret \n');
+    expect(chrome.storage.local.set).toHaveBeenCalledWith({ [BRIDGE_TOKEN_KEY]: 'ast_secret' });
+
+    await savePairingToken('   ');
+    expect(chrome.storage.local.remove).toHaveBeenCalledWith(BRIDGE_TOKEN_KEY);
+  });
+});
+
+describe('Bridge discovery', () => {
+  afterEach(() => {
+    vi.unstubAllGlobals();
+  });
+
+  function health(bridge: boolean): Response {
+    return {
+      ok: true,
+      headers: { has: (name: string) => bridge && name === 'X-Poll-Hint-Ms', get: () => null },
+    } as unknown as Response;
+  }
+
+  it('finds the bridge on a fallback port and remembers it', async () => {
+    const fetchMock = vi.fn(async (url: string) => {
+      if (url.includes(':17373/')) return health(false); // some other server
+      if (url.includes(':17375/')) return health(true);
+      throw new TypeError('Failed to fetch');
+    });
+    vi.stubGlobal('fetch', fetchMock);
+
+    const response = await probeBridge();
+    expect(response?.headers.has('X-Poll-Hint-Ms')).toBe(true);
+    expect(fetchMock.mock.calls.map(([url]) => url)).toEqual([
+      'http://127.0.0.1:17373/health',
+      'http://127.0.0.1:17374/health',
+      'http://127.0.0.1:17375/health',
+    ]);
+    expect(chrome.storage.local.set).toHaveBeenCalledWith({ [BRIDGE_PORT_KEY]: 17375 });
+  });
+
+  it('sends requests to the remembered port', async () => {
+    chrome.storage.local.get = vi.fn().mockResolvedValue({ [BRIDGE_PORT_KEY]: 17375 });
+    expect(await bridgeUrl('/v1/vault')).toBe('http://127.0.0.1:17375/v1/vault');
+  });
+
+  it('reports no bridge when no port answers', async () => {
+    const fetchMock = vi.fn().mockRejectedValue(new TypeError('Failed to fetch'));
+    vi.stubGlobal('fetch', fetchMock);
+
+    expect(await probeBridge()).toBeNull();
+    expect(fetchMock).toHaveBeenCalledTimes(11);
+    expect(chrome.storage.local.set).not.toHaveBeenCalled();
+  });
+});
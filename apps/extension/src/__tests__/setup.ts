@@ -14,11 +14,13 @@ const chromeMock = {
   },
   runtime: {
     sendMessage: vi.fn(),
+    getManifest: vi.fn(() => ({ version: '0.2.0' })),
   },
   storage: {
     local: {
       get: vi.fn().mockResolvedValue({}), // Return empty object by default
       set: vi.fn().mockResolvedValue(undefined),
+      remove: vi.fn().mockResolvedValue(undefined),
     },
   },
 };
@@ -32,4 +34,6 @@ beforeEach(() => {
   // Re-apply default mock behaviors after clear
   chrome.storage.local.get = vi.fn().mockResolvedValue({});
   chrome.storage.local.set = vi.fn().mockResolvedValue(undefined);
+  chrome.storage.local.remove = vi.fn().mockResolvedValue(undefined);
+  chrome.runtime.getManifest = vi.fn(() => ({ version: '0.2.0' }) as chrome.runtime.Manifest);
 });
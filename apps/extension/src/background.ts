@@ -5,17 +5,19 @@
  * to the desktop app via localhost HTTP.
  */
 
-import type { FormSnapshot, FillCommand, FillPlan, VaultItem, FieldFill } from '@asterisk/core';
+import type { FormSnapshot, FillCommand, FillPlan, VaultItem, FieldFill, ReviewStatus } from '@asterisk/core';
 import { generateFillPlan } from '@asterisk/core';
+import { bridgeHeaders, bridgeUrl, probeBridge } from './bridge';
 
 // ============================================================================
 // Constants
 // ============================================================================
 
-const DESKTOP_API_URL = 'http://127.0.0.1:17373/v1/form-snapshots';
-const FILL_COMMANDS_URL = 'http://127.0.0.1:17373/v1/fill-commands';
+const FORM_SNAPSHOTS_PATH = '/v1/form-snapshots';
+const FILL_COMMANDS_PATH = '/v1/fill-commands';
 const DESKTOP_BRIDGE_MESSAGE = 'ASTERISK_FORM_SNAPSHOT';
 const FILL_COMMAND_MESSAGE = 'ASTERISK_FILL_COMMAND';
+const RECAPTURE_MESSAGE = 'ASTERISK_RECAPTURE';
 // Chrome Alarms API has 1-minute minimum, so we use that for persistent polling
 const HEALTH_CHECK_ALARM = 'asterisk-health-check';
 const FILL_POLL_ALARM = 'asterisk-fill-poll';
@@ -76,12 +78,15 @@ async function fetchVaultItems(): Promise<VaultItem[]> {
   }
 
   try {
-    const response = await fetch('http://127.0.0.1:17373/v1/vault', {
+    const response = await fetch(await bridgeUrl('/v1/vault'), {
       method: 'GET',
+      headers: await bridgeHeaders(),
     });
 
     if (response.ok) {
-      const items: VaultItem[] = await response.json();
+      // The bridge sends the review status as `review_status`
+      const json: Array<VaultItem & { review_status?: ReviewStatus }> = await response.json();
+      const items: VaultItem[] = json.map(item => ({ ...item, reviewStatus: item.review_status }));
       cachedVaultItems = items;
       cacheTimestamp = Date.now(); // Update cache timestamp
       isDesktopAvailable = true;
@@ -89,6 +94,7 @@ async function fetchVaultItems(): Promise<VaultItem[]> {
     }
 
     // HTTP error response - mark desktop as unavailable
+    warnIfUnpaired(response);
     isDesktopAvailable = false;
     return cachedVaultItems;
   } catch (error) {
@@ -122,7 +128,7 @@ async function generateLocalFillPlan(snapshot: FormSnapshot): Promise<FillPlan |
   }
 }
 
-async function sendToDesktop(snapshot: FormSnapshot): Promise<boolean> {
+async function sendToDesktop(snapshot: FormSnapshot, incognito = false): Promise<boolean> {
   // Rate limit connection attempts when desktop is unavailable
   const now = Date.now();
   if (!isDesktopAvailable && now - lastConnectionAttempt < CONNECTION_RETRY_INTERVAL) {
@@ -131,10 +137,13 @@ async function sendToDesktop(snapshot: FormSnapshot): Promise<boolean> {
   lastConnectionAttempt = now;
 
   try {
-    const response = await fetch(DESKTOP_API_URL, {
+    const response = await fetch(await bridgeUrl(FORM_SNAPSHOTS_PATH), {
       method: 'POST',
       headers: {
+        ...(await bridgeHeaders()),
         'Content-Type': 'application/json',
+        // Keeps the desktop app from analyzing private-window forms in the background
+        ...(incognito ? { 'X-Asterisk-Incognito': '1' } : {}),
       },
       body: JSON.stringify(snapshot),
     });
@@ -143,6 +152,8 @@ async function sendToDesktop(snapshot: FormSnapshot): Promise<boolean> {
       isDesktopAvailable = true;
       return true;
     }
+    warnIfUnpaired(response);
+    await warnIfUpgradeRequired(response);
 
     // Server returned an error
     console.debug('[Asterisk] Desktop returned error:', response.status);
@@ -159,11 +170,42 @@ async function sendToDesktop(snapshot: FormSnapshot): Promise<boolean> {
 // Health Check
 // ============================================================================
 
+/**
+ * The desktop answers 401 until the extension has its pairing token
+ */
+function warnIfUnpaired(response: Response): void {
+  if (response.status !== 401) return;
+  console.warn(
+    '[Asterisk] Not paired: copy the pairing token from the desktop app (Settings tab) into the extension settings'
+  );
+}
+
+/**
+ * The desktop answers 426 when this extension is too old to talk to it
+ */
+async function warnIfUpgradeRequired(response: Response): Promise<void> {
+  if (response.status !== 426) return;
+  try {
+    const body: { message?: string } = await response.json();
+    console.warn('[Asterisk]', body.message ?? 'Extension update required');
+  } catch {
+    console.warn('[Asterisk] Extension update required');
+  }
+}
+
 async function checkDesktopHealth(): Promise<boolean> {
   try {
-    const response = await fetch('http://127.0.0.1:17373/health', {
-      method: 'GET',
-    });
+    const response = await probeBridge();
+    if (!response) {
+      isDesktopAvailable = false;
+      return false;
+    }
+    if (response.headers.get('Deprecation')) {
+      console.warn(
+        '[Asterisk] This extension version is deprecated by the desktop app; support ends',
+        response.headers.get('Sunset') ?? 'soon'
+      );
+    }
     isDesktopAvailable = response.ok;
     return response.ok;
   } catch (error) {
@@ -181,17 +223,72 @@ async function checkDesktopHealth(): Promise<boolean> {
 // Track processed commands to avoid duplicates
 const processedCommands = new Set<string>();
 
+// Polls sooner than the alarm period are scheduled from the desktop's pollHintMs
+const MAX_HINTED_POLL_MS = 30_000;
+let hintedPollTimer: ReturnType<typeof setTimeout> | undefined;
+
+/**
+ * A desktop request to scrape a page again because its snapshot is stale.
+ * It is dropped once a fresh snapshot from the domain arrives, or after 60s.
+ */
+interface RecaptureRequest {
+  id: string;
+  domain: string;
+  url?: string;
+  fingerprint?: string;
+  reason: 'requested' | 'stale_snapshot';
+  requestedAt: string;
+  expiresAt: string;
+}
+
+// Re-capture requests already passed to a tab
+const handledRecaptures = new Set<string>();
+
+/**
+ * Fill command listing. Older desktop builds return a bare array.
+ */
+type FillCommandsResponse =
+  | FillCommand[]
+  | { commands: FillCommand[]; pollHintMs?: number; recaptureRequests?: RecaptureRequest[] };
+
+function scheduleHintedPoll(pollHintMs: number | undefined): void {
+  if (hintedPollTimer !== undefined) {
+    clearTimeout(hintedPollTimer);
+    hintedPollTimer = undefined;
+  }
+  // Longer hints are covered by the regular alarm
+  if (pollHintMs === undefined || pollHintMs > MAX_HINTED_POLL_MS) return;
+  hintedPollTimer = setTimeout(() => {
+    hintedPollTimer = undefined;
+    void pollFillCommands();
+  }, pollHintMs);
+}
+
 async function pollFillCommands(): Promise<void> {
   if (!isDesktopAvailable) return;
 
   try {
-    const response = await fetch(FILL_COMMANDS_URL, {
+    const response = await fetch(await bridgeUrl(FILL_COMMANDS_PATH), {
       method: 'GET',
+      headers: await bridgeHeaders(),
     });
 
-    if (!response.ok) return;
+    if (!response.ok) {
+      warnIfUnpaired(response);
+      await warnIfUpgradeRequired(response);
+      return;
+    }
 
-    const commands: FillCommand[] = await response.json();
+    const data: FillCommandsResponse = await response.json();
+    const commands = Array.isArray(data) ? data : data.commands;
+    scheduleHintedPoll(Array.isArray(data) ? undefined : data.pollHintMs);
+
+    const recaptures = Array.isArray(data) ? [] : data.recaptureRequests ?? [];
+    for (const request of recaptures) {
+      if (handledRecaptures.has(request.id)) continue;
+      handledRecaptures.add(request.id);
+      await sendRecaptureToTab(request);
+    }
 
     for (const command of commands) {
       // Skip already processed commands
@@ -205,10 +302,14 @@ async function pollFillCommands(): Promise<void> {
       }
 
       // Try to send to matching tab
-      const success = await sendFillCommandToTab(command);
+      const result = await sendFillCommandToTab(command);
 
-      if (success) {
+      if (result.success) {
         processedCommands.add(command.id);
+        // Prior values must reach the desktop before the command is acknowledged
+        if (result.priorValues) {
+          await reportPriorValues(command.id, result.priorValues);
+        }
         await acknowledgeFillCommand(command.id);
         console.debug('[Asterisk] Fill command executed:', command.id, command.fills.length, 'fields');
       }
@@ -219,13 +320,19 @@ async function pollFillCommands(): Promise<void> {
   }
 }
 
-async function sendFillCommandToTab(command: FillCommand): Promise<boolean> {
+interface TabFillResult {
+  success: boolean;
+  /** Pre-fill values, when the command asked for undo capture */
+  priorValues?: FieldFill[];
+}
+
+async function sendFillCommandToTab(command: FillCommand): Promise<TabFillResult> {
   // Find tabs matching the target domain
   const tabs = await chrome.tabs.query({ url: `*://${command.targetDomain}/*` });
 
   if (tabs.length === 0) {
     console.debug('[Asterisk] No tabs found for domain:', command.targetDomain);
-    return false;
+    return { success: false };
   }
 
   console.log('[Asterisk] Found', tabs.length, 'tabs for domain:', command.targetDomain, '- tabs:', tabs.map(t => ({ id: t.id, url: t.url })));
@@ -244,7 +351,7 @@ async function sendFillCommandToTab(command: FillCommand): Promise<boolean> {
       console.log('[Asterisk] Tab response:', response);
 
       if (response?.success) {
-        return true;
+        return { success: true, priorValues: response.priorValues };
       }
     } catch (error) {
       console.warn('[Asterisk] Failed to send to tab:', tab.id, tab.url, error);
@@ -254,13 +361,54 @@ async function sendFillCommandToTab(command: FillCommand): Promise<boolean> {
   }
 
   console.warn('[Asterisk] All tabs failed for domain:', command.targetDomain);
+  return { success: false };
+}
+
+async function sendRecaptureToTab(request: RecaptureRequest): Promise<boolean> {
+  const tabs = await chrome.tabs.query({ url: `*://${request.domain}/*` });
+  // The tab the stale snapshot came from goes first
+  tabs.sort((a, b) => Number(b.url === request.url) - Number(a.url === request.url));
+
+  for (const tab of tabs) {
+    if (!tab.id) continue;
+    try {
+      const response = await chrome.tabs.sendMessage(tab.id, { type: RECAPTURE_MESSAGE });
+      if (response?.success) {
+        console.debug('[Asterisk] Re-captured forms for', request.domain, 'in tab', tab.id);
+        return true;
+      }
+    } catch (error) {
+      // Tab may not have content script loaded, try next tab
+      console.debug('[Asterisk] Re-capture failed in tab:', tab.id, error);
+      continue;
+    }
+  }
+
+  console.debug('[Asterisk] No tab could re-capture', request.domain);
   return false;
 }
 
+async function reportPriorValues(commandId: string, priorValues: FieldFill[]): Promise<void> {
+  try {
+    await fetch(await bridgeUrl(`${FILL_COMMANDS_PATH}/prior-values`), {
+      method: 'POST',
+      headers: {
+        ...(await bridgeHeaders()),
+        'Content-Type': 'application/json',
+      },
+      body: JSON.stringify({ commandId, priorValues }),
+    });
+  } catch (error) {
+    // Undo just won't be available for this fill
+    console.debug('[Asterisk] Reporting prior values failed:', error instanceof Error ? error.message : 'Network error');
+  }
+}
+
 async function acknowledgeFillCommand(commandId: string): Promise<void> {
   try {
-    await fetch(`${FILL_COMMANDS_URL}?id=${encodeURIComponent(commandId)}`, {
+    await fetch(await bridgeUrl(`${FILL_COMMANDS_PATH}?id=${encodeURIComponent(commandId)}`), {
       method: 'DELETE',
+      headers: await bridgeHeaders(),
     });
   } catch (error) {
     // Expected: Acknowledge failed when desktop app disconnected
@@ -420,7 +568,7 @@ chrome.runtime.onMessage.addListener((message, sender, sendResponse) => {
     }
 
     // Fire and forget - don't block the content script
-    sendToDesktop(snapshot).then((success) => {
+    sendToDesktop(snapshot, sender.tab?.incognito ?? false).then((success) => {
       if (success) {
         console.debug('[Asterisk] Sent form snapshot:', snapshot.domain, snapshot.fingerprint.fieldCount, 'fields');
         // Piggyback: also poll for fill commands when we receive a form snapshot
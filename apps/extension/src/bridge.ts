@@ -0,0 +1,105 @@
+/**
+ * Desktop Bridge Requests
+ *
+ * Where the desktop bridge is and the headers sent with every request to
+ * it. Once the desktop app has a pairing token, every /v1 route refuses
+ * requests without it, so the token the user copies from the desktop app's
+ * Settings tab is kept in chrome.storage.local and sent in `X-Asterisk-Token`.
+ *
+ * The bridge listens on port 17373 unless another process holds it, in
+ * which case it takes the first free one of the next 10. The port found by
+ * probing `/health` is remembered in chrome.storage.local.
+ */
+
+// Pairing token from the desktop app, required on /v1 routes
+export const BRIDGE_TOKEN_KEY = 'bridgeToken';
+
+// Port the bridge was last found on
+export const BRIDGE_PORT_KEY = 'bridgePort';
+
+// Matches DEFAULT_PORT and FALLBACK_PORTS in the desktop's bridge_port.rs
+export const DEFAULT_BRIDGE_PORT = 17373;
+export const FALLBACK_PORTS = 10;
+
+// A port that doesn't answer this fast isn't the local bridge
+const PROBE_TIMEOUT_MS = 1000;
+
+async function rememberedPort(): Promise<number> {
+  const stored = await chrome.storage.local.get([BRIDGE_PORT_KEY]);
+  const port = stored?.[BRIDGE_PORT_KEY];
+  return typeof port === 'number' ? port : DEFAULT_BRIDGE_PORT;
+}
+
+/**
+ * URL of `path` on the bridge, at the port it was last found on
+ */
+export async function bridgeUrl(path: string): Promise<string> {
+  return `http://127.0.0.1:${await rememberedPort()}${path}`;
+}
+
+/**
+ * GET /health from the bridge, looking for it across the fallback ports
+ * when it isn't on the remembered one
+ *
+ * Only a response carrying the bridge's `X-Poll-Hint-Ms` header counts, so
+ * another local server on one of the ports isn't mistaken for it. Returns
+ * null when no port answers.
+ */
+export async function probeBridge(): Promise<Response | null> {
+  const remembered = await rememberedPort();
+  const candidates = [remembered];
+  for (let port = DEFAULT_BRIDGE_PORT; port <= DEFAULT_BRIDGE_PORT + FALLBACK_PORTS; port++) {
+    if (port !== remembered) candidates.push(port);
+  }
+
+  const headers = await bridgeHeaders();
+  for (const port of candidates) {
+    try {
+      const response = await fetch(`http://127.0.0.1:${port}/health`, {
+        method: 'GET',
+        headers,
+        signal: AbortSignal.timeout(PROBE_TIMEOUT_MS),
+      });
+      if (!response.headers.has('X-Poll-Hint-Ms')) continue;
+      if (port !== remembered) {
+        await chrome.storage.local.set({ [BRIDGE_PORT_KEY]: port });
+      }
+      return response;
+    } catch {
+      // Nothing listening on this port
+    }
+  }
+  return null;
+}
+
+/**
+ * Identifies this extension version to the desktop bridge
+ */
+export function clientHeaders(): Record<string, string> {
+  return {
+    'X-Asterisk-Client': `asterisk-extension/${chrome.runtime.getManifest().version}`,
+  };
+}
+
+/**
+ * Client headers plus the pairing token, when one has been stored
+ */
+export async function bridgeHeaders(): Promise<Record<string, string>> {
+  const stored = await chrome.storage.local.get([BRIDGE_TOKEN_KEY]);
+  const token = stored?.[BRIDGE_TOKEN_KEY];
+  return typeof token === 'string' && token
+    ? { ...clientHeaders(), 'X-Asterisk-Token': token }
+    : clientHeaders();
+}
+
+/**
+ * Store the token pasted from the desktop app; an empty one unpairs
+ */
+export async function savePairingToken(token: string): Promise<void> {
+  const trimmed = token.trim();
+  if (trimmed) {
+    await chrome.storage.local.set({ [BRIDGE_TOKEN_KEY]: trimmed });
+  } else {
+    await chrome.storage.local.remove(BRIDGE_TOKEN_KEY);
+  }
+}
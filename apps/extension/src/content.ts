@@ -19,7 +19,7 @@ if (IS_DESKTOP_APP) {
   console.log('[Asterisk Content] Skipping desktop app page - content script disabled');
 }
 
-import type { FieldNode, FieldType, FormFingerprint, FormSnapshot, SelectOption, FillCommand } from '@asterisk/core';
+import type { FieldNode, FieldType, FormFingerprint, FormSnapshot, SelectOption, FillCommand, FieldFill } from '@asterisk/core';
 import {
   inferFieldSemantic,
   findAllFields,
@@ -36,6 +36,7 @@ import {
 const DEBOUNCE_MS = 500;
 const DESKTOP_BRIDGE_MESSAGE = 'ASTERISK_FORM_SNAPSHOT';
 const FILL_COMMAND_MESSAGE = 'ASTERISK_FILL_COMMAND';
+const RECAPTURE_MESSAGE = 'ASTERISK_RECAPTURE';
 
 // ============================================================================
 // Type Guards and Helpers
@@ -237,6 +238,10 @@ function extractFieldNode(
   // Add options for select elements
   if (isSelectElement(element)) {
     fieldNode.options = extractSelectOptions(element);
+    // A chosen country decides how a whole address is formatted
+    if (semantic === 'country' && element.value) {
+      fieldNode.selectedValue = element.value;
+    }
   }
 
   return fieldNode;
@@ -586,11 +591,38 @@ function fillField(element: HTMLInputElement | HTMLSelectElement | HTMLTextAreaE
   }
 }
 
+/**
+ * Read a field's current value in the form fillField accepts, so it can be restored
+ */
+function currentFieldValue(element: HTMLInputElement | HTMLSelectElement | HTMLTextAreaElement): string {
+  if (isInputElement(element)) {
+    const type = element.type.toLowerCase();
+    if (type === 'checkbox') {
+      return element.checked ? 'true' : 'false';
+    }
+    if (type === 'radio') {
+      const checked = document.querySelector(
+        `input[type="radio"][name="${CSS.escape(element.name)}"]:checked`
+      ) as HTMLInputElement | null;
+      return checked?.value ?? '';
+    }
+  }
+  return element.value;
+}
+
 /**
  * Execute a fill command from the desktop app
+ *
+ * With command.captureUndo, each field's value from just before the fill is
+ * returned so the desktop can undo it.
  */
-function executeFillCommand(command: FillCommand): { success: boolean; filledCount: number } {
+function executeFillCommand(command: FillCommand): {
+  success: boolean;
+  filledCount: number;
+  priorValues?: FieldFill[];
+} {
   let filledCount = 0;
+  const priorValues: FieldFill[] = [];
 
   for (const fill of command.fills) {
     const element = findFieldElement(fill.fieldId);
@@ -600,7 +632,11 @@ function executeFillCommand(command: FillCommand): { success: boolean; filledCou
       continue;
     }
 
+    const prior = command.captureUndo ? currentFieldValue(element) : undefined;
     if (fillField(element, fill.value)) {
+      if (prior !== undefined) {
+        priorValues.push({ fieldId: fill.fieldId, value: prior });
+      }
       filledCount++;
       console.debug('[Asterisk] Filled field:', fill.fieldId);
     }
@@ -609,6 +645,7 @@ function executeFillCommand(command: FillCommand): { success: boolean; filledCou
   return {
     success: filledCount > 0,
     filledCount,
+    priorValues: command.captureUndo ? priorValues : undefined,
   };
 }
 
@@ -617,6 +654,16 @@ function executeFillCommand(command: FillCommand): { success: boolean; filledCou
 // ============================================================================
 
 chrome.runtime.onMessage.addListener((message, _sender, sendResponse) => {
+  // The desktop asked for a fresh snapshot; send it even if unchanged
+  if (message.type === RECAPTURE_MESSAGE) {
+    void scanPageForForms().then((snapshots) => {
+      lastFingerprint = snapshots.map((s) => s.fingerprint.hash).join(',');
+      sendSnapshotsToBackground(snapshots);
+      sendResponse({ success: snapshots.length > 0 });
+    });
+    return true;
+  }
+
   if (message.type === FILL_COMMAND_MESSAGE && message.payload) {
     const command = message.payload as FillCommand;
 
@@ -631,7 +678,11 @@ chrome.runtime.onMessage.addListener((message, _sender, sendResponse) => {
     const result = executeFillCommand(command);
     console.log('[Asterisk] Fill command executed:', result.filledCount, 'of', command.fills.length, 'fields filled');
 
-    sendResponse({ success: result.success, filledCount: result.filledCount });
+    sendResponse({
+      success: result.success,
+      filledCount: result.filledCount,
+      priorValues: result.priorValues,
+    });
   }
 
   return true;
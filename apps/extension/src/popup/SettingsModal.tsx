@@ -3,14 +3,17 @@
  *
  * Allows users to configure extension settings:
  * - Desktop API URL
+ * - Pairing token from the desktop app
  * - Auto-fill enabled toggle
  * - Link to full desktop settings
  */
 
 import { useEffect, useState } from 'react';
+import { BRIDGE_TOKEN_KEY, savePairingToken } from '../bridge';
 
 interface Settings {
   desktopApiUrl: string;
+  pairingToken: string;
   autoFillEnabled: boolean;
   autoCloseAfterFill: boolean;
   showKeyboardShortcuts: boolean;
@@ -22,6 +25,7 @@ interface SettingsModalProps {
 
 const DEFAULT_SETTINGS: Settings = {
   desktopApiUrl: 'http://localhost:1420',
+  pairingToken: '',
   autoFillEnabled: true,
   autoCloseAfterFill: true,
   showKeyboardShortcuts: true,
@@ -58,12 +62,14 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
     try {
       const result = await chrome.storage.local.get([
         'desktopApiUrl',
+        BRIDGE_TOKEN_KEY,
         'autoFillEnabled',
         'autoCloseAfterFill',
         'showKeyboardShortcuts',
       ]);
       setSettings({
         desktopApiUrl: result.desktopApiUrl || DEFAULT_SETTINGS.desktopApiUrl,
+        pairingToken: result[BRIDGE_TOKEN_KEY] || DEFAULT_SETTINGS.pairingToken,
         autoFillEnabled: result.autoFillEnabled ?? DEFAULT_SETTINGS.autoFillEnabled,
         autoCloseAfterFill: result.autoCloseAfterFill ?? DEFAULT_SETTINGS.autoCloseAfterFill,
         showKeyboardShortcuts: result.showKeyboardShortcuts ?? DEFAULT_SETTINGS.showKeyboardShortcuts,
@@ -91,6 +97,7 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
         autoCloseAfterFill: settings.autoCloseAfterFill,
         showKeyboardShortcuts: settings.showKeyboardShortcuts,
       });
+      await savePairingToken(settings.pairingToken);
 
       // Close modal after brief delay
       setTimeout(() => {
@@ -149,6 +156,18 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
             />
           </div>
 
+          <div className="form-group">
+            <label className="form-label">Pairing Token</label>
+            <input
+              type="password"
+              className="form-input"
+              value={settings.pairingToken}
+              onChange={(e) => setSettings({ ...settings, pairingToken: e.target.value })}
+              placeholder="Copy it from the desktop app's Settings tab"
+              autoComplete="off"
+            />
+          </div>
+
           <div className="form-group">
             <label className="form-checkbox">
               <input
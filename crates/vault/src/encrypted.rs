@@ -0,0 +1,588 @@
+/*!
+ * Encrypted File Store
+ *
+ * A persistent `VaultStore` that keeps the whole vault in one file,
+ * encrypted with ChaCha20-Poly1305 under a key derived from a passphrase
+ * (Argon2id), or directly under a random `MasterKey` kept elsewhere, such
+ * as the OS keychain. The file is a small JSON envelope: format version,
+ * the KDF salt and costs (`"kdf": "none"` for a master key), and the nonce
+ * and ciphertext of the item list. No key, label or value is readable
+ * without the passphrase or master key.
+ *
+ * Every mutation re-encrypts the vault with a fresh nonce and replaces the
+ * file by temp-and-rename, so a crash mid-write leaves the previous vault
+ * intact and at worst a stray temp file, which is ignored. A wrong
+ * passphrase and a tampered file both fail authentication and are
+ * reported as a `StorageError`, never as an empty vault.
+ */
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::journal::{storage_error, sync_parent_dir, tmp_path_for};
+use crate::{MasterKey, Result, VaultCategory, VaultError, VaultItem, VaultStore};
+
+/// Current file format
+pub const ENCRYPTED_FORMAT_VERSION: u32 = 1;
+
+const SALT_LEN: usize = 16;
+
+/// Highest Argon2id memory cost a vault file may ask for (1 GiB)
+///
+/// The costs are read before anything is authenticated, so an edited or
+/// corrupt file must not be able to exhaust memory or time when opened.
+const MAX_KDF_MEM_KIB: u32 = 1024 * 1024;
+
+/// Highest Argon2id iteration count a vault file may ask for
+const MAX_KDF_ITERATIONS: u32 = 10;
+
+/// Argon2id costs used to derive the file key
+///
+/// Stored in the file, so vaults created with other costs still open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory in KiB
+    #[serde(rename = "memKib")]
+    pub mem_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// Refuse costs above `MAX_KDF_MEM_KIB` / `MAX_KDF_ITERATIONS`
+    fn check_bounds(&self, path: &Path) -> Result<()> {
+        if self.mem_kib > MAX_KDF_MEM_KIB || self.iterations > MAX_KDF_ITERATIONS {
+            return Err(VaultError::SerializationError(format!(
+                "Corrupt vault file {}: key derivation costs out of range ({} KiB, {} iterations)",
+                path.display(),
+                self.mem_kib,
+                self.iterations
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            mem_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// How the file key was made
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Kdf {
+    /// Derived from a passphrase and the envelope's salt
+    Argon2id(KdfParams),
+    /// A master key used as is, stored as `"none"`
+    None(NoKdf),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum NoKdf {
+    #[serde(rename = "none")]
+    None,
+}
+
+/// The file as stored
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    version: u32,
+    kdf: Kdf,
+    /// Empty when there is no KDF
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Vault persisted to a single encrypted file
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    cipher: ChaCha20Poly1305,
+    kdf: Kdf,
+    salt: Vec<u8>,
+    items: HashMap<String, VaultItem>,
+}
+
+impl std::fmt::Debug for EncryptedFileStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedFileStore")
+            .field("path", &self.path)
+            .field("items", &self.items.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl EncryptedFileStore {
+    /// Create an empty vault at `path`, encrypted with `passphrase`
+    ///
+    /// Fails if the file already exists, so an existing vault is never
+    /// replaced by an empty one.
+    pub fn create(path: impl Into<PathBuf>, passphrase: &str) -> Result<Self> {
+        Self::create_with_kdf(path, passphrase, KdfParams::default())
+    }
+
+    /// `create` with explicit key derivation costs
+    pub fn create_with_kdf(
+        path: impl Into<PathBuf>,
+        passphrase: &str,
+        kdf: KdfParams,
+    ) -> Result<Self> {
+        let mut salt = vec![0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let cipher = derive_cipher(passphrase, &salt, kdf)?;
+        Self::create_file(path.into(), cipher, Kdf::Argon2id(kdf), salt)
+    }
+
+    /// Create an empty vault at `path`, encrypted directly with `key`
+    ///
+    /// No key derivation runs: the master key is already random and full
+    /// length. Fails if the file already exists, as `create` does.
+    pub fn create_with_key(path: impl Into<PathBuf>, key: &MasterKey) -> Result<Self> {
+        Self::create_file(
+            path.into(),
+            key_cipher(key),
+            Kdf::None(NoKdf::None),
+            Vec::new(),
+        )
+    }
+
+    fn create_file(
+        path: PathBuf,
+        cipher: ChaCha20Poly1305,
+        kdf: Kdf,
+        salt: Vec<u8>,
+    ) -> Result<Self> {
+        if path.exists() {
+            return Err(VaultError::StorageError(format!(
+                "Vault file {} already exists",
+                path.display()
+            )));
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| storage_error("Failed to create", parent, e))?;
+        }
+        let store = Self {
+            cipher,
+            path,
+            kdf,
+            salt,
+            items: HashMap::new(),
+        };
+        store.save()?;
+        Ok(store)
+    }
+
+    /// Open the existing vault at `path` with `passphrase`
+    pub fn open(path: impl Into<PathBuf>, passphrase: &str) -> Result<Self> {
+        let path = path.into();
+        let envelope = read_envelope(&path)?;
+        let Kdf::Argon2id(kdf) = envelope.kdf else {
+            return Err(VaultError::StorageError(format!(
+                "{} is encrypted with a master key, not a passphrase",
+                path.display()
+            )));
+        };
+        kdf.check_bounds(&path)?;
+        let salt = decode(&path, "salt", &envelope.salt)?;
+        let cipher = derive_cipher(passphrase, &salt, kdf)?;
+        let items = decrypt_items(&path, &cipher, &envelope, "Wrong passphrase")?;
+        Ok(Self {
+            path,
+            cipher,
+            kdf: envelope.kdf,
+            salt,
+            items,
+        })
+    }
+
+    /// Open the existing vault at `path` with the master key it was
+    /// created with
+    pub fn open_with_key(path: impl Into<PathBuf>, key: &MasterKey) -> Result<Self> {
+        let path = path.into();
+        let envelope = read_envelope(&path)?;
+        if envelope.kdf != Kdf::None(NoKdf::None) {
+            return Err(VaultError::StorageError(format!(
+                "{} is encrypted with a passphrase, not a master key",
+                path.display()
+            )));
+        }
+        let cipher = key_cipher(key);
+        let items = decrypt_items(&path, &cipher, &envelope, "Wrong master key")?;
+        Ok(Self {
+            path,
+            cipher,
+            kdf: envelope.kdf,
+            salt: Vec::new(),
+            items,
+        })
+    }
+
+    /// The vault file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Encrypt the vault and replace the file with it
+    fn save(&self) -> Result<()> {
+        let mut items: Vec<&VaultItem> = self.items.values().collect();
+        items.sort_by(|a, b| a.key.cmp(&b.key));
+        let plaintext = Zeroizing::new(
+            serde_json::to_vec(&items)
+                .map_err(|e| VaultError::SerializationError(e.to_string()))?,
+        );
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|e| VaultError::StorageError(format!("Failed to encrypt vault: {}", e)))?;
+        let envelope = Envelope {
+            version: ENCRYPTED_FORMAT_VERSION,
+            kdf: self.kdf,
+            salt: BASE64.encode(&self.salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        };
+        let json = serde_json::to_vec(&envelope)
+            .map_err(|e| VaultError::SerializationError(e.to_string()))?;
+
+        let tmp = tmp_path_for(&self.path);
+        let mut file =
+            File::create(&tmp).map_err(|e| storage_error("Failed to create", &tmp, e))?;
+        file.write_all(&json)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| storage_error("Failed to write", &tmp, e))?;
+        fs::rename(&tmp, &self.path)
+            .map_err(|e| storage_error("Failed to replace", &self.path, e))?;
+        sync_parent_dir(&self.path);
+        Ok(())
+    }
+}
+
+fn read_envelope(path: &Path) -> Result<Envelope> {
+    let bytes = fs::read(path).map_err(|e| storage_error("Failed to read", path, e))?;
+    let envelope: Envelope = serde_json::from_slice(&bytes).map_err(|e| {
+        VaultError::SerializationError(format!("Corrupt vault file {}: {}", path.display(), e))
+    })?;
+    if envelope.version != ENCRYPTED_FORMAT_VERSION {
+        return Err(VaultError::StorageError(format!(
+            "Unsupported vault file version {} in {}",
+            envelope.version,
+            path.display()
+        )));
+    }
+    Ok(envelope)
+}
+
+fn decode(path: &Path, field: &str, value: &str) -> Result<Vec<u8>> {
+    BASE64.decode(value).map_err(|e| {
+        VaultError::SerializationError(format!("Corrupt {} in {}: {}", field, path.display(), e))
+    })
+}
+
+/// The envelope's items, keyed; `wrong_key` names what to blame when the
+/// file doesn't authenticate
+fn decrypt_items(
+    path: &Path,
+    cipher: &ChaCha20Poly1305,
+    envelope: &Envelope,
+    wrong_key: &str,
+) -> Result<HashMap<String, VaultItem>> {
+    let nonce = decode(path, "nonce", &envelope.nonce)?;
+    let ciphertext = decode(path, "ciphertext", &envelope.ciphertext)?;
+    if nonce.len() != 12 {
+        return Err(VaultError::SerializationError(format!(
+            "Corrupt nonce in {}",
+            path.display()
+        )));
+    }
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map(Zeroizing::new)
+        .map_err(|_| {
+            VaultError::StorageError(format!(
+                "{} for {} (or the file was altered)",
+                wrong_key,
+                path.display()
+            ))
+        })?;
+    let items: Vec<VaultItem> = serde_json::from_slice(&plaintext)
+        .map_err(|e| VaultError::SerializationError(e.to_string()))?;
+    Ok(items
+        .into_iter()
+        .map(|item| (item.key.clone(), item))
+        .collect())
+}
+
+/// The cipher for a master key, which is used as the file key as is
+fn key_cipher(key: &MasterKey) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(Key::from_slice(key.as_bytes()))
+}
+
+fn derive_cipher(passphrase: &str, salt: &[u8], kdf: KdfParams) -> Result<ChaCha20Poly1305> {
+    let params = Params::new(kdf.mem_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| VaultError::StorageError(format!("Invalid key derivation costs: {}", e)))?;
+    // The cipher keeps its own copy, which it wipes on drop
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| VaultError::StorageError(format!("Failed to derive vault key: {}", e)))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(key.as_ref())))
+}
+
+impl Drop for EncryptedFileStore {
+    /// Wipes the decrypted values and labels before their memory is freed
+    fn drop(&mut self) {
+        for item in self.items.values_mut() {
+            item.value.zeroize();
+            item.label.zeroize();
+        }
+    }
+}
+
+impl VaultStore for EncryptedFileStore {
+    fn set(&mut self, key: String, mut item: VaultItem) -> Result<()> {
+        crate::check_key(&key)?;
+        item.key = key.clone();
+        let previous = self.items.insert(key.clone(), item);
+        // Memory must match the file, so a failed write is undone
+        self.save().inspect_err(|_| match previous {
+            Some(previous) => {
+                self.items.insert(key, previous);
+            }
+            None => {
+                self.items.remove(&key);
+            }
+        })
+    }
+
+    /// Checks every key, then writes the file once with the whole batch
+    fn set_many(&mut self, items: Vec<(String, VaultItem)>) -> Result<()> {
+        crate::check_keys(&items)?;
+        let previous = crate::insert_all(&mut self.items, items);
+        // As with `set`, a failed write is undone in memory too
+        self.save()
+            .inspect_err(|_| crate::restore(&mut self.items, previous))
+    }
+
+    fn get(&self, key: &str) -> Result<Option<VaultItem>> {
+        Ok(self.items.get(key).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<VaultItem>> {
+        Ok(self.items.values().cloned().collect())
+    }
+
+    fn list_by_category(&self, category: VaultCategory) -> Result<Vec<VaultItem>> {
+        Ok(crate::in_category(self.items.values(), &category))
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&VaultItem) -> Result<()>) -> Result<()> {
+        self.items.values().try_for_each(visit)
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        let removed = self
+            .items
+            .remove(key)
+            .ok_or_else(|| VaultError::NotFound(key.to_string()))?;
+        self.save().inspect_err(|_| {
+            self.items.insert(key.to_string(), removed);
+        })
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        let previous = std::mem::take(&mut self.items);
+        self.save().inspect_err(|_| self.items = previous)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util, VaultCategory};
+
+    /// Cheap costs so tests don't spend seconds deriving keys
+    const TEST_KDF: KdfParams = KdfParams {
+        mem_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    fn item(key: &str, category: VaultCategory) -> VaultItem {
+        test_util::item(key, &format!("{} value", key), category)
+    }
+
+    fn vault_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "asterisk-encrypted-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("vault.enc")
+    }
+
+    #[test]
+    fn test_round_trips_every_category() {
+        let path = vault_path("roundtrip");
+        let categories = [
+            VaultCategory::Identity,
+            VaultCategory::Contact,
+            VaultCategory::Address,
+            VaultCategory::Financial,
+            VaultCategory::Custom,
+        ];
+        let mut store = EncryptedFileStore::create_with_kdf(&path, "hunter2", TEST_KDF).unwrap();
+        for (i, category) in categories.iter().enumerate() {
+            let key = format!("key-{}", i);
+            store
+                .set(key.clone(), item(&key, category.clone()))
+                .unwrap();
+        }
+        store.delete("key-0").unwrap();
+        store
+            .set("key-0".into(), item("key-0", VaultCategory::Identity))
+            .unwrap();
+        drop(store);
+
+        // Values never appear in the file
+        let raw = fs::read_to_string(&path).unwrap();
+        assert!(!raw.contains("key-1 value"));
+
+        let store = EncryptedFileStore::open(&path, "hunter2").unwrap();
+        assert_eq!(store.len(), categories.len());
+        for (i, category) in categories.iter().enumerate() {
+            let stored = store.get(&format!("key-{}", i)).unwrap().unwrap();
+            assert_eq!(&stored.category, category);
+            assert_eq!(stored.value, format!("key-{} value", i));
+        }
+    }
+
+    #[test]
+    fn test_wrong_passphrase_is_a_storage_error() {
+        let path = vault_path("wrongkey");
+        let mut store = EncryptedFileStore::create_with_kdf(&path, "right", TEST_KDF).unwrap();
+        store
+            .set("email".into(), item("email", VaultCategory::Contact))
+            .unwrap();
+
+        let err = EncryptedFileStore::open(&path, "wrong").unwrap_err();
+        assert!(matches!(err, VaultError::StorageError(_)));
+        assert!(err.to_string().contains("Wrong passphrase"));
+        // The failed open left the vault alone
+        assert_eq!(EncryptedFileStore::open(&path, "right").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_out_of_range_kdf_costs_are_refused_before_deriving() {
+        let path = vault_path("kdfbounds");
+        EncryptedFileStore::create_with_kdf(&path, "pass", TEST_KDF).unwrap();
+        let original = fs::read(&path).unwrap();
+
+        for (field, value) in [("memKib", 64 * 1024 * 1024), ("iterations", 1_000_000)] {
+            let mut envelope: serde_json::Value = serde_json::from_slice(&original).unwrap();
+            envelope["kdf"][field] = serde_json::json!(value);
+            fs::write(&path, serde_json::to_vec(&envelope).unwrap()).unwrap();
+
+            let err = EncryptedFileStore::open(&path, "pass").unwrap_err();
+            assert!(matches!(err, VaultError::SerializationError(_)), "{}", err);
+            assert!(err.to_string().contains("out of range"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_master_key_vault_skips_the_kdf() {
+        let path = vault_path("masterkey");
+        let key = MasterKey::generate();
+        let mut store = EncryptedFileStore::create_with_key(&path, &key).unwrap();
+        store
+            .set("email".into(), item("email", VaultCategory::Contact))
+            .unwrap();
+        drop(store);
+
+        let envelope: serde_json::Value =
+            serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(envelope["kdf"], "none");
+        assert_eq!(envelope["salt"], "");
+
+        let store = EncryptedFileStore::open_with_key(&path, &key).unwrap();
+        assert_eq!(store.get("email").unwrap().unwrap().value, "email value");
+        let err = EncryptedFileStore::open_with_key(&path, &MasterKey::generate()).unwrap_err();
+        assert!(err.to_string().contains("Wrong master key"), "{}", err);
+        // Neither kind of vault opens as the other
+        let err = EncryptedFileStore::open(&path, &key.to_base64()).unwrap_err();
+        assert!(err.to_string().contains("master key"), "{}", err);
+        let passphrase_path = vault_path("masterkey-passphrase");
+        EncryptedFileStore::create_with_kdf(&passphrase_path, "pass", TEST_KDF).unwrap();
+        assert!(EncryptedFileStore::open_with_key(&passphrase_path, &key).is_err());
+    }
+
+    #[test]
+    fn test_create_refuses_existing_file() {
+        let path = vault_path("exists");
+        EncryptedFileStore::create_with_kdf(&path, "pass", TEST_KDF).unwrap();
+        assert!(EncryptedFileStore::create_with_kdf(&path, "pass", TEST_KDF).is_err());
+    }
+
+    #[test]
+    fn test_partial_write_keeps_last_good_vault() {
+        let path = vault_path("partial");
+        let mut store = EncryptedFileStore::create_with_kdf(&path, "pass", TEST_KDF).unwrap();
+        store
+            .set("email".into(), item("email", VaultCategory::Contact))
+            .unwrap();
+        drop(store);
+
+        // A save cut off before its rename
+        fs::write(tmp_path_for(&path), br#"{"version":1,"kdf":"#).unwrap();
+
+        let mut store = EncryptedFileStore::open(&path, "pass").unwrap();
+        assert_eq!(store.get("email").unwrap().unwrap().value, "email value");
+        // The next save replaces the stray temp file
+        store
+            .set("phone".into(), item("phone", VaultCategory::Contact))
+            .unwrap();
+        assert_eq!(EncryptedFileStore::open(&path, "pass").unwrap().len(), 2);
+
+        // A truncated vault file is reported, not read as empty
+        let raw = fs::read(&path).unwrap();
+        fs::write(&path, &raw[..raw.len() / 2]).unwrap();
+        assert!(EncryptedFileStore::open(&path, "pass").is_err());
+    }
+
+    #[test]
+    fn test_temp_file_is_not_shared_with_a_json_vault() {
+        let path = vault_path("sibling");
+        let json_path = path.with_file_name("vault.json");
+        assert_ne!(tmp_path_for(&path), tmp_path_for(&json_path));
+
+        let mut store = EncryptedFileStore::create_with_kdf(&path, "pass", TEST_KDF).unwrap();
+        // A JSON vault save in the same directory, caught before its rename
+        fs::write(tmp_path_for(&json_path), b"[]").unwrap();
+        store
+            .set("email".into(), item("email", VaultCategory::Contact))
+            .unwrap();
+
+        assert_eq!(fs::read(tmp_path_for(&json_path)).unwrap(), b"[]");
+        assert!(!tmp_path_for(&path).exists());
+    }
+}
@@ -0,0 +1,604 @@
+/*!
+ * Journaled File Store
+ *
+ * A persistent `VaultStore` that never rewrites the main file on the hot
+ * path. Each mutation is appended (and synced) to `vault.journal` as one
+ * compact JSON record; every N mutations, and on clean shutdown, the
+ * journal is folded into the main file with temp-and-rename and then
+ * emptied.
+ *
+ * On open, the last good main file is loaded and any journal left behind
+ * by a crash is replayed over it. Replay is idempotent, so a crash between
+ * the rename and the journal truncation is harmless. A torn or corrupt
+ * journal tail is cut off at the last valid record, so a failed append is
+ * cut off right away rather than left for replay to trip over. A failed
+ * checkpoint only leaves the journal to be folded in later.
+ *
+ * Nothing is logged here: the bytes cut off on open (`truncated_bytes`)
+ * and the last failed automatic checkpoint (`take_checkpoint_error`) are
+ * left for the caller to report.
+ */
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::{Result, VaultCategory, VaultError, VaultItem, VaultStore};
+
+/// Mutations between automatic checkpoints
+pub const DEFAULT_CHECKPOINT_INTERVAL: usize = 64;
+
+/// One journaled mutation
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum JournalRecord {
+    Set {
+        item: VaultItem,
+    },
+    /// A `set_many` batch, one record so a torn write loses all of it
+    SetMany {
+        items: Vec<VaultItem>,
+    },
+    Delete {
+        key: String,
+    },
+    Clear,
+}
+
+pub(crate) fn storage_error(context: &str, path: &Path, e: impl std::fmt::Display) -> VaultError {
+    VaultError::StorageError(format!("{} {}: {}", context, path.display(), e))
+}
+
+/// Vault persisted to a JSON file plus a write-ahead journal
+#[derive(Debug)]
+pub struct JournaledFileStore {
+    path: PathBuf,
+    journal_path: PathBuf,
+    items: HashMap<String, VaultItem>,
+    journal: File,
+    /// Records in the journal since the last checkpoint
+    pending: usize,
+    checkpoint_interval: usize,
+    /// Set when a failed append could not be cut back off the journal;
+    /// writes are refused until the store is reopened and replays it
+    broken: bool,
+    /// Corrupt journal bytes cut off when the store was opened
+    truncated_bytes: u64,
+    /// The last automatic checkpoint failure not yet taken
+    checkpoint_error: Option<VaultError>,
+    /// Test hook: fail the next append after writing this many bytes
+    #[cfg(test)]
+    fail_append_after: Option<usize>,
+}
+
+impl JournaledFileStore {
+    /// Open the store at `path`, replaying any journal left by a crash
+    ///
+    /// A missing main file is an empty vault; a corrupt one is an error
+    /// rather than a silent wipe.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let journal_path = journal_path_for(&path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| storage_error("Failed to create", parent, e))?;
+        }
+
+        let mut items = load_main_file(&path)?;
+        let (pending, truncated_bytes) = replay_journal(&journal_path, &mut items)?;
+        let journal = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&journal_path)
+            .map_err(|e| storage_error("Failed to open", &journal_path, e))?;
+
+        Ok(Self {
+            path,
+            journal_path,
+            items,
+            journal,
+            pending,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+            broken: false,
+            truncated_bytes,
+            checkpoint_error: None,
+            #[cfg(test)]
+            fail_append_after: None,
+        })
+    }
+
+    /// Checkpoint after every `interval` mutations (minimum 1)
+    pub fn with_checkpoint_interval(mut self, interval: usize) -> Self {
+        self.checkpoint_interval = interval.max(1);
+        self
+    }
+
+    /// Records waiting in the journal to be folded into the main file
+    pub fn pending_records(&self) -> usize {
+        self.pending
+    }
+
+    /// Bytes of a torn or corrupt journal tail cut off on open
+    pub fn truncated_bytes(&self) -> u64 {
+        self.truncated_bytes
+    }
+
+    /// The last automatic checkpoint failure since this was last called
+    ///
+    /// The write that triggered it succeeded all the same, so this is the
+    /// only place the failure shows.
+    pub fn take_checkpoint_error(&mut self) -> Option<VaultError> {
+        self.checkpoint_error.take()
+    }
+
+    /// Fold the journal into the main file and empty it
+    pub fn checkpoint(&mut self) -> Result<()> {
+        let mut items: Vec<&VaultItem> = self.items.values().collect();
+        items.sort_by(|a, b| a.key.cmp(&b.key));
+        let json = serde_json::to_vec_pretty(&items)
+            .map_err(|e| VaultError::SerializationError(e.to_string()))?;
+
+        let tmp = tmp_path_for(&self.path);
+        let mut file =
+            File::create(&tmp).map_err(|e| storage_error("Failed to create", &tmp, e))?;
+        file.write_all(&json)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| storage_error("Failed to write", &tmp, e))?;
+        fs::rename(&tmp, &self.path)
+            .map_err(|e| storage_error("Failed to replace", &self.path, e))?;
+        sync_parent_dir(&self.path);
+
+        // Only now is the journal redundant
+        self.journal
+            .set_len(0)
+            .and_then(|_| self.journal.sync_all())
+            .map_err(|e| storage_error("Failed to truncate", &self.journal_path, e))?;
+        self.pending = 0;
+        Ok(())
+    }
+
+    /// Append and sync one record, or leave the journal as it was
+    ///
+    /// A failed append is cut back off: left in place, a torn line would
+    /// make replay drop every record after it, and a whole but unsynced
+    /// one would be replayed though the caller was told it failed.
+    fn append(&mut self, record: &JournalRecord) -> Result<()> {
+        if self.broken {
+            return Err(VaultError::StorageError(format!(
+                "Journal {} may hold a failed write; reopen the vault to recover",
+                self.journal_path.display()
+            )));
+        }
+        let mut line = serde_json::to_vec(record)
+            .map_err(|e| VaultError::SerializationError(e.to_string()))?;
+        line.push(b'\n');
+        let len = self
+            .journal
+            .metadata()
+            .map_err(|e| storage_error("Failed to read", &self.journal_path, e))?
+            .len();
+        if let Err(e) = self.write_line(&line) {
+            if let Err(undo) = self.journal.set_len(len) {
+                self.broken = true;
+                return Err(VaultError::StorageError(format!(
+                    "Failed to append to {} ({}), and to cut the append off again: {}",
+                    self.journal_path.display(),
+                    e,
+                    undo
+                )));
+            }
+            return Err(storage_error("Failed to append to", &self.journal_path, e));
+        }
+        self.pending += 1;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> std::io::Result<()> {
+        #[cfg(test)]
+        if let Some(after) = self.fail_append_after.take() {
+            self.journal.write_all(&line[..after.min(line.len())])?;
+            return Err(std::io::Error::other("injected append failure"));
+        }
+        self.journal.write_all(line)?;
+        self.journal.sync_data()
+    }
+
+    /// Checkpoint if enough records are pending
+    ///
+    /// A failed checkpoint isn't the caller's failure: the record is in the
+    /// journal already, and the next checkpoint or open picks it up. The
+    /// failure is kept for `take_checkpoint_error`.
+    fn maybe_checkpoint(&mut self) {
+        if self.pending >= self.checkpoint_interval {
+            if let Err(e) = self.checkpoint() {
+                self.checkpoint_error = Some(e);
+            }
+        }
+    }
+}
+
+impl VaultStore for JournaledFileStore {
+    fn set(&mut self, key: String, mut item: VaultItem) -> Result<()> {
+        crate::check_key(&key)?;
+        item.key = key.clone();
+        self.append(&JournalRecord::Set { item: item.clone() })?;
+        self.items.insert(key, item);
+        self.maybe_checkpoint();
+        Ok(())
+    }
+
+    /// Checks every key, then journals the whole batch as one record
+    fn set_many(&mut self, items: Vec<(String, VaultItem)>) -> Result<()> {
+        crate::check_keys(&items)?;
+        let items: Vec<VaultItem> = items
+            .into_iter()
+            .map(|(key, mut item)| {
+                item.key = key;
+                item
+            })
+            .collect();
+        self.append(&JournalRecord::SetMany {
+            items: items.clone(),
+        })?;
+        self.items
+            .extend(items.into_iter().map(|item| (item.key.clone(), item)));
+        self.maybe_checkpoint();
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<VaultItem>> {
+        Ok(self.items.get(key).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<VaultItem>> {
+        Ok(self.items.values().cloned().collect())
+    }
+
+    fn list_by_category(&self, category: VaultCategory) -> Result<Vec<VaultItem>> {
+        Ok(crate::in_category(self.items.values(), &category))
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&VaultItem) -> Result<()>) -> Result<()> {
+        self.items.values().try_for_each(visit)
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        if !self.items.contains_key(key) {
+            return Err(VaultError::NotFound(key.to_string()));
+        }
+        self.append(&JournalRecord::Delete {
+            key: key.to_string(),
+        })?;
+        self.items.remove(key);
+        self.maybe_checkpoint();
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.append(&JournalRecord::Clear)?;
+        self.items.clear();
+        self.maybe_checkpoint();
+        Ok(())
+    }
+}
+
+impl Drop for JournaledFileStore {
+    /// Clean shutdown folds the journal in; if this fails the journal is
+    /// still there to be replayed on the next open
+    fn drop(&mut self) {
+        if self.pending > 0 {
+            let _ = self.checkpoint();
+        }
+    }
+}
+
+// ============================================================================
+// Recovery
+// ============================================================================
+
+/// `vault.json` journals to `vault.journal`
+fn journal_path_for(path: &Path) -> PathBuf {
+    path.with_extension("journal")
+}
+
+/// `vault.json` is replaced via `vault.json.tmp`
+///
+/// The whole file name is kept, so `vault.enc` and `vault.json` in the same
+/// directory never share a temp file.
+pub(crate) fn tmp_path_for(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".tmp");
+    PathBuf::from(name)
+}
+
+pub(crate) fn load_main_file(path: &Path) -> Result<HashMap<String, VaultItem>> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(storage_error("Failed to read", path, e)),
+    };
+    let items: Vec<VaultItem> = serde_json::from_slice(&bytes).map_err(|e| {
+        VaultError::SerializationError(format!("Corrupt vault file {}: {}", path.display(), e))
+    })?;
+    Ok(items
+        .into_iter()
+        .map(|item| (item.key.clone(), item))
+        .collect())
+}
+
+/// Apply journal records to `items`; returns how many were applied and
+/// how many bytes were cut off
+///
+/// Stops at the first incomplete or unparseable record and truncates the
+/// journal there, since nothing after a torn write can be trusted.
+fn replay_journal(path: &Path, items: &mut HashMap<String, VaultItem>) -> Result<(usize, u64)> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((0, 0)),
+        Err(e) => return Err(storage_error("Failed to read", path, e)),
+    };
+
+    let mut applied = 0;
+    let mut valid_len = 0;
+    while let Some(newline) = bytes[valid_len..].iter().position(|&b| b == b'\n') {
+        let line = &bytes[valid_len..valid_len + newline];
+        let Ok(record) = serde_json::from_slice::<JournalRecord>(line) else {
+            break;
+        };
+        match record {
+            JournalRecord::Set { item } => {
+                items.insert(item.key.clone(), item);
+            }
+            JournalRecord::SetMany { items: batch } => {
+                items.extend(batch.into_iter().map(|item| (item.key.clone(), item)));
+            }
+            JournalRecord::Delete { key } => {
+                items.remove(&key);
+            }
+            JournalRecord::Clear => items.clear(),
+        }
+        applied += 1;
+        valid_len += newline + 1;
+    }
+
+    if valid_len < bytes.len() {
+        let file = OpenOptions::new()
+            .write(true)
+            .open(path)
+            .map_err(|e| storage_error("Failed to open", path, e))?;
+        file.set_len(valid_len as u64)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| storage_error("Failed to truncate", path, e))?;
+    }
+    Ok((applied, (bytes.len() - valid_len) as u64))
+}
+
+/// Make a rename durable; not supported (or needed) everywhere
+pub(crate) fn sync_parent_dir(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util, VaultCategory};
+
+    fn item(key: &str, value: &str) -> VaultItem {
+        test_util::item(key, value, VaultCategory::Contact)
+    }
+
+    fn vault_path(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("asterisk-journal-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("vault.json")
+    }
+
+    fn value(store: &JournaledFileStore, key: &str) -> Option<String> {
+        store.get(key).unwrap().map(|i| i.value)
+    }
+
+    /// Drop without the clean-shutdown checkpoint
+    fn crash(store: JournaledFileStore) {
+        std::mem::forget(store);
+    }
+
+    #[test]
+    fn test_clean_shutdown_checkpoints() {
+        let path = vault_path("clean");
+        let mut store = JournaledFileStore::open(&path).unwrap();
+        store.set("email".into(), item("email", "a@b.c")).unwrap();
+        drop(store);
+
+        assert_eq!(fs::metadata(journal_path_for(&path)).unwrap().len(), 0);
+        let store = JournaledFileStore::open(&path).unwrap();
+        assert_eq!(value(&store, "email").as_deref(), Some("a@b.c"));
+        assert_eq!(store.pending_records(), 0);
+    }
+
+    #[test]
+    fn test_crash_before_checkpoint_replays_journal() {
+        let path = vault_path("precheckpoint");
+        let mut store = JournaledFileStore::open(&path).unwrap();
+        store.set("email".into(), item("email", "a@b.c")).unwrap();
+        store.set("phone".into(), item("phone", "555")).unwrap();
+        store.delete("phone").unwrap();
+        crash(store);
+
+        assert!(!path.exists());
+        let store = JournaledFileStore::open(&path).unwrap();
+        assert_eq!(value(&store, "email").as_deref(), Some("a@b.c"));
+        assert_eq!(value(&store, "phone"), None);
+        assert_eq!(store.pending_records(), 3);
+    }
+
+    #[test]
+    fn test_set_many_is_one_record_replayed_whole() {
+        let path = vault_path("setmany");
+        let mut store = JournaledFileStore::open(&path).unwrap();
+        store
+            .set_many(vec![
+                ("email".into(), item("email", "a@b.c")),
+                ("phone".into(), item("phone", "555")),
+            ])
+            .unwrap();
+        assert_eq!(store.pending_records(), 1);
+        crash(store);
+
+        let journal = journal_path_for(&path);
+        let good_len = fs::metadata(&journal).unwrap().len();
+        let mut file = OpenOptions::new().append(true).open(&journal).unwrap();
+        file.write_all(br#"{"op":"setmany","items":[{"key":"city"#)
+            .unwrap();
+        drop(file);
+
+        let store = JournaledFileStore::open(&path).unwrap();
+        assert_eq!(value(&store, "email").as_deref(), Some("a@b.c"));
+        assert_eq!(value(&store, "phone").as_deref(), Some("555"));
+        assert_eq!(value(&store, "city"), None);
+        assert_eq!(fs::metadata(&journal).unwrap().len(), good_len);
+    }
+
+    #[test]
+    fn test_failed_append_is_cut_off_and_later_writes_survive() {
+        let path = vault_path("failedappend");
+        let mut store = JournaledFileStore::open(&path).unwrap();
+        store.set("email".into(), item("email", "a@b.c")).unwrap();
+
+        // The write dies partway through the line
+        store.fail_append_after = Some(10);
+        assert!(store.set("phone".into(), item("phone", "555")).is_err());
+        assert_eq!(value(&store, "phone"), None);
+        store.set("city".into(), item("city", "Oslo")).unwrap();
+        crash(store);
+
+        let store = JournaledFileStore::open(&path).unwrap();
+        assert_eq!(value(&store, "email").as_deref(), Some("a@b.c"));
+        assert_eq!(value(&store, "phone"), None);
+        assert_eq!(value(&store, "city").as_deref(), Some("Oslo"));
+        assert_eq!(store.pending_records(), 2);
+    }
+
+    #[test]
+    fn test_failed_checkpoint_does_not_fail_the_write() {
+        let path = vault_path("failedcheckpoint");
+        let mut store = JournaledFileStore::open(&path)
+            .unwrap()
+            .with_checkpoint_interval(1);
+        // A non-empty directory where the main file goes makes the rename fail
+        fs::create_dir_all(path.join("blocker")).unwrap();
+
+        store.set("email".into(), item("email", "a@b.c")).unwrap();
+        assert_eq!(store.pending_records(), 1);
+        // Kept for the caller to report, once
+        assert!(store.take_checkpoint_error().is_some());
+        assert!(store.take_checkpoint_error().is_none());
+        crash(store);
+
+        fs::remove_dir_all(&path).unwrap();
+        let store = JournaledFileStore::open(&path).unwrap();
+        assert_eq!(value(&store, "email").as_deref(), Some("a@b.c"));
+    }
+
+    #[test]
+    fn test_torn_journal_tail_is_truncated() {
+        let path = vault_path("torn");
+        let mut store = JournaledFileStore::open(&path).unwrap();
+        store.set("email".into(), item("email", "a@b.c")).unwrap();
+        crash(store);
+
+        // A write cut off mid-record
+        let journal = journal_path_for(&path);
+        let good_len = fs::metadata(&journal).unwrap().len();
+        let torn = br#"{"op":"set","item":{"key":"pho"#;
+        let mut file = OpenOptions::new().append(true).open(&journal).unwrap();
+        file.write_all(torn).unwrap();
+        drop(file);
+
+        let mut store = JournaledFileStore::open(&path).unwrap();
+        assert_eq!(value(&store, "email").as_deref(), Some("a@b.c"));
+        assert_eq!(fs::metadata(&journal).unwrap().len(), good_len);
+        assert_eq!(store.truncated_bytes(), torn.len() as u64);
+
+        // New records go after the last valid one
+        store.set("phone".into(), item("phone", "555")).unwrap();
+        crash(store);
+        let store = JournaledFileStore::open(&path).unwrap();
+        assert_eq!(value(&store, "phone").as_deref(), Some("555"));
+        assert_eq!(store.truncated_bytes(), 0);
+    }
+
+    #[test]
+    fn test_crash_before_rename_keeps_last_good_file() {
+        let path = vault_path("prerename");
+        let mut store = JournaledFileStore::open(&path).unwrap();
+        store.set("email".into(), item("email", "old")).unwrap();
+        store.checkpoint().unwrap();
+        store.set("email".into(), item("email", "new")).unwrap();
+        crash(store);
+
+        // Half-written temp file from an interrupted checkpoint
+        fs::write(tmp_path_for(&path), b"[{\"key\":").unwrap();
+
+        let store = JournaledFileStore::open(&path).unwrap();
+        assert_eq!(value(&store, "email").as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn test_crash_after_rename_replay_is_idempotent() {
+        let path = vault_path("postrename");
+        let mut store = JournaledFileStore::open(&path).unwrap();
+        store.set("email".into(), item("email", "a@b.c")).unwrap();
+        store.set("phone".into(), item("phone", "555")).unwrap();
+        store.delete("phone").unwrap();
+        store.clear().unwrap();
+        store.set("name".into(), item("name", "Ada")).unwrap();
+
+        // Main file written, but the journal was never truncated
+        let journal = fs::read(journal_path_for(&path)).unwrap();
+        store.checkpoint().unwrap();
+        crash(store);
+        fs::write(journal_path_for(&path), journal).unwrap();
+
+        let store = JournaledFileStore::open(&path).unwrap();
+        assert_eq!(value(&store, "name").as_deref(), Some("Ada"));
+        assert_eq!(value(&store, "email"), None);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_checkpoint_interval() {
+        let path = vault_path("interval");
+        let mut store = JournaledFileStore::open(&path)
+            .unwrap()
+            .with_checkpoint_interval(2);
+        store.set("a".into(), item("a", "1")).unwrap();
+        assert_eq!(store.pending_records(), 1);
+        store.set("b".into(), item("b", "2")).unwrap();
+        assert_eq!(store.pending_records(), 0);
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn test_corrupt_main_file_is_an_error() {
+        let path = vault_path("corrupt");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, b"not json").unwrap();
+        assert!(matches!(
+            JournaledFileStore::open(&path),
+            Err(VaultError::SerializationError(_))
+        ));
+    }
+}
@@ -0,0 +1,188 @@
+/*!
+ * JSON File Store
+ *
+ * The simplest persistent `VaultStore`, for development: the whole vault
+ * is one pretty-printed JSON array, rewritten on every mutation. Nothing
+ * is encrypted, so it is only for test data.
+ *
+ * Each write goes to a temp file that is synced and then renamed over the
+ * vault file, so a crash leaves either the old vault or the new one, never
+ * a truncated file. The parent directory is created on the first write. A
+ * missing file opens as an empty vault; a corrupt one is an error rather
+ * than a silent wipe.
+ */
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::journal::{load_main_file, storage_error, sync_parent_dir, tmp_path_for};
+use crate::{Result, VaultCategory, VaultError, VaultItem, VaultStore};
+
+/// Vault persisted to a single plain JSON file
+#[derive(Debug)]
+pub struct JsonFileStore {
+    path: PathBuf,
+    items: HashMap<String, VaultItem>,
+}
+
+impl JsonFileStore {
+    /// Open the vault file at `path`; a missing file is an empty vault
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let items = load_main_file(&path)?;
+        Ok(Self { path, items })
+    }
+
+    /// The vault file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write the vault to a temp file and rename it over the vault file
+    fn save(&self) -> Result<()> {
+        let mut items: Vec<&VaultItem> = self.items.values().collect();
+        items.sort_by(|a, b| a.key.cmp(&b.key));
+        let json = serde_json::to_vec_pretty(&items)
+            .map_err(|e| VaultError::SerializationError(e.to_string()))?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| storage_error("Failed to create", parent, e))?;
+        }
+        let tmp = tmp_path_for(&self.path);
+        let mut file =
+            File::create(&tmp).map_err(|e| storage_error("Failed to create", &tmp, e))?;
+        file.write_all(&json)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| storage_error("Failed to write", &tmp, e))?;
+        fs::rename(&tmp, &self.path)
+            .map_err(|e| storage_error("Failed to replace", &self.path, e))?;
+        sync_parent_dir(&self.path);
+        Ok(())
+    }
+}
+
+impl VaultStore for JsonFileStore {
+    fn set(&mut self, key: String, mut item: VaultItem) -> Result<()> {
+        crate::check_key(&key)?;
+        item.key = key.clone();
+        let previous = self.items.insert(key.clone(), item);
+        // Memory must match the file, so a failed write is undone
+        self.save().inspect_err(|_| match previous {
+            Some(previous) => {
+                self.items.insert(key, previous);
+            }
+            None => {
+                self.items.remove(&key);
+            }
+        })
+    }
+
+    /// Checks every key, then writes the file once with the whole batch
+    fn set_many(&mut self, items: Vec<(String, VaultItem)>) -> Result<()> {
+        crate::check_keys(&items)?;
+        let previous = crate::insert_all(&mut self.items, items);
+        // As with `set`, a failed write is undone in memory too
+        self.save()
+            .inspect_err(|_| crate::restore(&mut self.items, previous))
+    }
+
+    fn get(&self, key: &str) -> Result<Option<VaultItem>> {
+        Ok(self.items.get(key).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<VaultItem>> {
+        Ok(self.items.values().cloned().collect())
+    }
+
+    fn list_by_category(&self, category: VaultCategory) -> Result<Vec<VaultItem>> {
+        Ok(crate::in_category(self.items.values(), &category))
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&VaultItem) -> Result<()>) -> Result<()> {
+        self.items.values().try_for_each(visit)
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        let removed = self
+            .items
+            .remove(key)
+            .ok_or_else(|| VaultError::NotFound(key.to_string()))?;
+        self.save().inspect_err(|_| {
+            self.items.insert(key.to_string(), removed);
+        })
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        let previous = std::mem::take(&mut self.items);
+        self.save().inspect_err(|_| self.items = previous)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util, VaultCategory};
+
+    fn item(key: &str) -> VaultItem {
+        test_util::item(key, &format!("{} value", key), VaultCategory::Custom)
+    }
+
+    fn vault_path(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("asterisk-json-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("nested").join("vault.json")
+    }
+
+    #[test]
+    fn test_persists_every_mutation() {
+        let path = vault_path("persist");
+        let mut store = JsonFileStore::open(&path).unwrap();
+        assert!(store.is_empty());
+        // Opening alone writes nothing; the first write creates the directory
+        assert!(!path.parent().unwrap().exists());
+
+        store.set("email".to_string(), item("email")).unwrap();
+        store.set("phone".to_string(), item("phone")).unwrap();
+        store.delete("phone").unwrap();
+        assert!(matches!(
+            store.delete("phone"),
+            Err(VaultError::NotFound(_))
+        ));
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains("\n  {"), "not pretty-printed: {}", text);
+        assert!(!tmp_path_for(&path).exists());
+
+        let reopened = JsonFileStore::open(&path).unwrap();
+        assert_eq!(reopened.len(), 1);
+        assert_eq!(reopened.get("email").unwrap().unwrap().value, "email value");
+
+        store.clear().unwrap();
+        assert!(JsonFileStore::open(&path).unwrap().is_empty());
+        let _ = fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
+    }
+
+    #[test]
+    fn test_corrupt_file_is_an_error_not_an_empty_vault() {
+        let path = vault_path("corrupt");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, b"[{\"key\": \"email\", \"val").unwrap();
+
+        let err = JsonFileStore::open(&path).unwrap_err();
+        assert!(
+            matches!(err, VaultError::SerializationError(_)),
+            "{:?}",
+            err
+        );
+        // The file is left as it was for the user to recover
+        assert_eq!(fs::read(&path).unwrap(), b"[{\"key\": \"email\", \"val");
+        let _ = fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
+    }
+}
@@ -0,0 +1,289 @@
+/*!
+ * Master Key Storage
+ *
+ * The encrypted vault's master key is a random 256-bit key kept in the OS
+ * keychain (Keychain on macOS, Credential Manager on Windows, the Secret
+ * Service on Linux) rather than in a file next to the vault. Keychains are
+ * reached through the `KeyringBackend` trait: `OsKeyring` (`keychain`
+ * feature) is the real one, `FileKeyring` keeps secrets in a plain JSON file
+ * so tests and CI run without a keychain.
+ *
+ * The key is stored base64-encoded under `KEYCHAIN_SERVICE` /
+ * `MASTER_KEY_ACCOUNT`. A stored value that doesn't decode to a key of the
+ * right length is an error, never silently replaced, since replacing it
+ * would lock the user out of the vault it encrypts.
+ */
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::journal::storage_error;
+use crate::{Result, VaultError};
+
+/// Keychain service the master key is filed under
+pub const KEYCHAIN_SERVICE: &str = "dev.asterisk.vault";
+
+/// Keychain account holding the master key
+pub const MASTER_KEY_ACCOUNT: &str = "master-key";
+
+/// Master key length in bytes
+pub const MASTER_KEY_LEN: usize = 32;
+
+/// The vault master key
+#[derive(Clone, PartialEq, Eq)]
+pub struct MasterKey([u8; MASTER_KEY_LEN]);
+
+impl std::fmt::Debug for MasterKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MasterKey(..)")
+    }
+}
+
+impl MasterKey {
+    /// A fresh random key
+    pub fn generate() -> Self {
+        let mut key = [0u8; MASTER_KEY_LEN];
+        OsRng.fill_bytes(&mut key);
+        MasterKey(key)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; MASTER_KEY_LEN] {
+        &self.0
+    }
+
+    /// The key as stored in the keychain
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(self.0)
+    }
+
+    pub fn from_base64(encoded: &str) -> Result<Self> {
+        let bytes = BASE64.decode(encoded.trim()).map_err(|e| {
+            VaultError::InvalidKey(format!("Stored master key is not base64: {}", e))
+        })?;
+        let key: [u8; MASTER_KEY_LEN] = bytes.try_into().map_err(|bytes: Vec<u8>| {
+            VaultError::InvalidKey(format!(
+                "Stored master key is {} bytes, expected {}",
+                bytes.len(),
+                MASTER_KEY_LEN
+            ))
+        })?;
+        Ok(MasterKey(key))
+    }
+}
+
+/// A place secrets are kept, addressed by service and account
+pub trait KeyringBackend: Send + Sync {
+    /// The secret, or None if there is none
+    fn get(&self, service: &str, account: &str) -> Result<Option<String>>;
+
+    /// Store or replace the secret
+    fn set(&self, service: &str, account: &str, secret: &str) -> Result<()>;
+
+    /// Remove the secret; returns whether there was one
+    fn delete(&self, service: &str, account: &str) -> Result<bool>;
+}
+
+/// Save `key` as the master key, replacing any stored one
+pub fn store_master_key(keyring: &dyn KeyringBackend, key: &MasterKey) -> Result<()> {
+    keyring.set(KEYCHAIN_SERVICE, MASTER_KEY_ACCOUNT, &key.to_base64())
+}
+
+/// The stored master key, or None if none is stored
+pub fn load_master_key(keyring: &dyn KeyringBackend) -> Result<Option<MasterKey>> {
+    keyring
+        .get(KEYCHAIN_SERVICE, MASTER_KEY_ACCOUNT)?
+        .map(|encoded| MasterKey::from_base64(&encoded))
+        .transpose()
+}
+
+/// Forget the master key; returns whether one was stored
+///
+/// Anything encrypted under it can no longer be opened.
+pub fn delete_master_key(keyring: &dyn KeyringBackend) -> Result<bool> {
+    keyring.delete(KEYCHAIN_SERVICE, MASTER_KEY_ACCOUNT)
+}
+
+// ============================================================================
+// OS Keychain
+// ============================================================================
+
+/// The platform keychain, through the `keyring` crate
+#[cfg(feature = "keychain")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsKeyring;
+
+#[cfg(feature = "keychain")]
+impl OsKeyring {
+    fn entry(service: &str, account: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(service, account).map_err(keychain_error)
+    }
+}
+
+#[cfg(feature = "keychain")]
+fn keychain_error(e: keyring::Error) -> VaultError {
+    VaultError::StorageError(format!("Keychain: {}", e))
+}
+
+#[cfg(feature = "keychain")]
+impl KeyringBackend for OsKeyring {
+    fn get(&self, service: &str, account: &str) -> Result<Option<String>> {
+        match Self::entry(service, account)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(keychain_error(e)),
+        }
+    }
+
+    fn set(&self, service: &str, account: &str, secret: &str) -> Result<()> {
+        Self::entry(service, account)?
+            .set_password(secret)
+            .map_err(keychain_error)
+    }
+
+    fn delete(&self, service: &str, account: &str) -> Result<bool> {
+        match Self::entry(service, account)?.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(keychain_error(e)),
+        }
+    }
+}
+
+// ============================================================================
+// File Keyring
+// ============================================================================
+
+/// Secrets in a plain JSON file, for tests and machines without a keychain
+///
+/// Nothing is protected beyond the file's permissions, so it is no safer
+/// than keeping the key next to the vault.
+#[derive(Debug, Clone)]
+pub struct FileKeyring {
+    path: PathBuf,
+}
+
+impl FileKeyring {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn entry_name(service: &str, account: &str) -> String {
+        format!("{}/{}", service, account)
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, String>> {
+        match fs::read(&self.path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| VaultError::SerializationError(e.to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(storage_error("Failed to read", &self.path, e)),
+        }
+    }
+
+    fn save(&self, entries: &BTreeMap<String, String>) -> Result<()> {
+        let json = serde_json::to_vec_pretty(entries)
+            .map_err(|e| VaultError::SerializationError(e.to_string()))?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| storage_error("Failed to create", parent, e))?;
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options
+            .open(&self.path)
+            .map_err(|e| storage_error("Failed to create", &self.path, e))?;
+        std::io::Write::write_all(&mut file, &json)
+            .map_err(|e| storage_error("Failed to write", &self.path, e))
+    }
+}
+
+impl KeyringBackend for FileKeyring {
+    fn get(&self, service: &str, account: &str) -> Result<Option<String>> {
+        Ok(self.load()?.remove(&Self::entry_name(service, account)))
+    }
+
+    fn set(&self, service: &str, account: &str, secret: &str) -> Result<()> {
+        let mut entries = self.load()?;
+        entries.insert(Self::entry_name(service, account), secret.to_string());
+        self.save(&entries)
+    }
+
+    fn delete(&self, service: &str, account: &str) -> Result<bool> {
+        let mut entries = self.load()?;
+        let removed = entries
+            .remove(&Self::entry_name(service, account))
+            .is_some();
+        if removed {
+            self.save(&entries)?;
+        }
+        Ok(removed)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyring(name: &str) -> FileKeyring {
+        let path = std::env::temp_dir().join(format!(
+            "asterisk-keyring-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        FileKeyring::new(path)
+    }
+
+    #[test]
+    fn test_master_key_round_trip() {
+        let keyring = keyring("round-trip");
+        assert_eq!(load_master_key(&keyring).unwrap(), None);
+
+        let key = MasterKey::generate();
+        store_master_key(&keyring, &key).unwrap();
+        // A second handle on the same file sees the same key
+        let reopened = FileKeyring::new(keyring.path());
+        assert_eq!(load_master_key(&reopened).unwrap(), Some(key.clone()));
+        assert_ne!(MasterKey::generate(), key);
+
+        assert!(delete_master_key(&keyring).unwrap());
+        assert!(!delete_master_key(&keyring).unwrap());
+        assert_eq!(load_master_key(&keyring).unwrap(), None);
+        let _ = fs::remove_file(keyring.path());
+    }
+
+    #[test]
+    fn test_malformed_stored_key_is_an_error_not_replaced() {
+        let keyring = keyring("malformed");
+        keyring
+            .set(
+                KEYCHAIN_SERVICE,
+                MASTER_KEY_ACCOUNT,
+                &BASE64.encode([1u8; 8]),
+            )
+            .unwrap();
+
+        let err = load_master_key(&keyring).unwrap_err();
+        assert!(matches!(err, VaultError::InvalidKey(_)), "{}", err);
+        // The stored value is left for the user to recover
+        assert_eq!(
+            keyring.get(KEYCHAIN_SERVICE, MASTER_KEY_ACCOUNT).unwrap(),
+            Some(BASE64.encode([1u8; 8]))
+        );
+        assert_eq!(format!("{:?}", MasterKey::generate()), "MasterKey(..)");
+        let _ = fs::remove_file(keyring.path());
+    }
+}
@@ -10,6 +10,31 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use thiserror::Error;
 
+mod encrypted;
+mod journal;
+mod json_file;
+mod keychain;
+mod lockable;
+mod search;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(any(test, feature = "test-helpers"))]
+pub mod test_util;
+
+pub use encrypted::{EncryptedFileStore, KdfParams};
+pub use journal::JournaledFileStore;
+pub use json_file::JsonFileStore;
+#[cfg(feature = "keychain")]
+pub use keychain::OsKeyring;
+pub use keychain::{
+    delete_master_key, load_master_key, store_master_key,
+    FileKeyring, KeyringBackend, MasterKey, KEYCHAIN_SERVICE, MASTER_KEY_ACCOUNT, MASTER_KEY_LEN,
+};
+pub use lockable::LockableStore;
+pub use search::SEARCH_LIMIT;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
 // ============================================================================
 // Error Types
 // ============================================================================
@@ -27,6 +52,9 @@ pub enum VaultError {
 
     #[error("Storage error: {0}")]
     StorageError(String),
+
+    #[error("Vault is locked")]
+    Locked,
 }
 
 pub type Result<T> = std::result::Result<T, VaultError>;
@@ -91,6 +119,37 @@ pub enum VaultCategory {
     Custom,
 }
 
+/// Items imported below this provenance confidence wait for review
+pub const REVIEW_CONFIDENCE_THRESHOLD: f64 = 0.9;
+
+/// Whether the user has vouched for an item's value
+///
+/// Items missing the field (stored before review existed) are approved.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewStatus {
+    /// Waiting in the review queue; matched, but never auto-filled
+    Unreviewed,
+    #[default]
+    Approved,
+    /// Kept in the vault but never matched
+    Rejected,
+}
+
+impl ReviewStatus {
+    /// Status for a newly acquired item: anything the user didn't enter
+    /// with confidence below `REVIEW_CONFIDENCE_THRESHOLD` needs review
+    pub fn initial(provenance: &Provenance) -> Self {
+        if provenance.source != ProvenanceSource::UserEntered
+            && provenance.confidence < REVIEW_CONFIDENCE_THRESHOLD
+        {
+            ReviewStatus::Unreviewed
+        } else {
+            ReviewStatus::Approved
+        }
+    }
+}
+
 /// A single item stored in the user's vault
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct VaultItem {
@@ -111,6 +170,20 @@ pub struct VaultItem {
 
     /// Storage metadata
     pub metadata: VaultMetadata,
+
+    /// Whether reads of this item are worth recording (e.g. SSN, card number)
+    #[serde(default)]
+    pub sensitive: bool,
+
+    /// Whether the item waits in, or was turned down by, the review queue
+    #[serde(default)]
+    pub review_status: ReviewStatus,
+
+    /// When the value is likely to have gone stale (a card's expiry, a
+    /// lease's end) and the user should check it again; the item is kept
+    /// and still filled after that, only flagged
+    #[serde(default)]
+    pub review_after: Option<DateTime<Utc>>,
 }
 
 impl VaultItem {
@@ -127,11 +200,19 @@ impl VaultItem {
             value: value.into(),
             label: label.into(),
             category,
+            review_status: ReviewStatus::initial(&provenance),
             provenance,
             metadata: VaultMetadata::default(),
+            sensitive: false,
+            review_after: None,
         }
     }
 
+    /// Whether the item's review date has come at `now`
+    pub fn needs_review(&self, now: DateTime<Utc>) -> bool {
+        self.review_after.is_some_and(|review_after| review_after <= now)
+    }
+
     /// Update the item's value and timestamp
     pub fn update_value(&mut self, new_value: impl Into<String>) {
         self.value = new_value.into();
@@ -139,10 +220,33 @@ impl VaultItem {
     }
 
     /// Mark the item as used
+    ///
+    /// For items already in a store, use `VaultStore::touch` so the bump
+    /// happens against the stored copy rather than a stale clone.
     pub fn mark_used(&mut self) {
         self.metadata.last_used = Some(Utc::now());
         self.metadata.usage_count += 1;
     }
+
+    /// Approve or reject the item; approving vouches for the value, so
+    /// its provenance confidence becomes 1.0
+    pub fn resolve_review(&mut self, approve: bool) {
+        if approve {
+            self.review_status = ReviewStatus::Approved;
+            self.provenance.confidence = 1.0;
+        } else {
+            self.review_status = ReviewStatus::Rejected;
+        }
+    }
+
+    /// Keep the usage counters of the stored copy when replacing an item
+    ///
+    /// Edits carry whatever usage the editor last saw; taking the stored
+    /// values instead means a `touch` that landed in between isn't undone.
+    pub fn keep_usage_of(&mut self, stored: &VaultItem) {
+        self.metadata.last_used = stored.metadata.last_used;
+        self.metadata.usage_count = stored.metadata.usage_count;
+    }
 }
 
 // ============================================================================
@@ -153,21 +257,108 @@ impl VaultItem {
 ///
 /// Implementations can provide different storage strategies:
 /// - InMemoryStore (current): Fast, volatile storage for development
-/// - EncryptedFileStore (future): Encrypted storage with OS keychain
+/// - JsonFileStore: Plain JSON file rewritten on every change, for development
+/// - JournaledFileStore: Plain JSON file with a crash-safe write-ahead journal
+/// - EncryptedFileStore: Single file encrypted under a passphrase
+/// - LockableStore: EncryptedFileStore that can be locked until its
+///   passphrase is given again
+/// - SqliteStore (`sqlite` feature): One row per item in a SQLite database
 /// - CloudStore (future): Encrypted cloud sync
 pub trait VaultStore: Send + Sync {
     /// Store or update a vault item
     fn set(&mut self, key: String, item: VaultItem) -> Result<()>;
 
+    /// Store or update several items, all or nothing
+    ///
+    /// Every key is checked before anything is written, so one invalid key
+    /// leaves the store as it was. The default then sets the items one by
+    /// one; persistent backends override it so that a failed write can't
+    /// leave part of the batch behind either.
+    fn set_many(&mut self, items: Vec<(String, VaultItem)>) -> Result<()> {
+        check_keys(&items)?;
+        for (key, item) in items {
+            self.set(key, item)?;
+        }
+        Ok(())
+    }
+
     /// Retrieve a vault item by key
     fn get(&self, key: &str) -> Result<Option<VaultItem>>;
 
+    /// Retrieve the items under `keys`, by key
+    ///
+    /// Keys with no item are left out of the map rather than being an
+    /// error. Goes through `get`, so only the requested items are loaded;
+    /// backends that can look several keys up at once should override it.
+    fn get_many(&self, keys: &[&str]) -> Result<HashMap<String, VaultItem>> {
+        let mut found = HashMap::with_capacity(keys.len());
+        for key in keys {
+            if let Some(item) = self.get(key)? {
+                found.insert(key.to_string(), item);
+            }
+        }
+        Ok(found)
+    }
+
     /// List all vault items
     fn list(&self) -> Result<Vec<VaultItem>>;
 
+    /// List the items in `category`
+    ///
+    /// Backends that can select by category without loading every item
+    /// should override this.
+    fn list_by_category(&self, category: VaultCategory) -> Result<Vec<VaultItem>> {
+        let mut items = self.list()?;
+        items.retain(|item| item.category == category);
+        Ok(items)
+    }
+
+    /// Find items by key, label or category, best match first, at most
+    /// `SEARCH_LIMIT`
+    ///
+    /// Ranked as the `search` module describes: exact and prefix matches
+    /// first, then substrings, typos and scattered letters, with ties going
+    /// to the item used most. Values are never searched, so a search can't
+    /// reveal a secret.
+    fn search(&self, query: &str) -> Result<Vec<VaultItem>> {
+        Ok(search::rank(self.list()?, query, SEARCH_LIMIT))
+    }
+
+    /// Visit every item without collecting them, stopping at the first error
+    ///
+    /// Backends that can iterate in place should override this so callers
+    /// like streaming export don't hold a second copy of the vault.
+    fn for_each(&self, visit: &mut dyn FnMut(&VaultItem) -> Result<()>) -> Result<()> {
+        for item in self.list()? {
+            visit(&item)?;
+        }
+        Ok(())
+    }
+
     /// Delete a vault item by key
     fn delete(&mut self, key: &str) -> Result<()>;
 
+    /// Record a use of an item: bump its usage count and last-used time
+    ///
+    /// This is the only sanctioned way to bump usage. The read-modify-write
+    /// happens in one call, so callers holding the store's lock can't lose
+    /// an increment to a concurrent edit.
+    fn touch(&mut self, key: &str) -> Result<()> {
+        self.get_and_touch(key)?
+            .map(|_| ())
+            .ok_or_else(|| VaultError::NotFound(key.to_string()))
+    }
+
+    /// Like `touch`, returning the item as updated; `None` if it doesn't exist
+    fn get_and_touch(&mut self, key: &str) -> Result<Option<VaultItem>> {
+        let Some(mut item) = self.get(key)? else {
+            return Ok(None);
+        };
+        item.mark_used();
+        self.set(key.to_string(), item.clone())?;
+        Ok(Some(item))
+    }
+
     /// Check if a key exists
     fn exists(&self, key: &str) -> bool {
         self.get(key).ok().flatten().is_some()
@@ -185,6 +376,91 @@ pub trait VaultStore: Send + Sync {
 
     /// Clear all items from the vault
     fn clear(&mut self) -> Result<()>;
+
+    /// Whether the store can be locked, forgetting its key
+    fn is_lockable(&self) -> bool {
+        false
+    }
+
+    /// Whether `unlock` needs the passphrase; a store whose key is kept
+    /// elsewhere, such as the OS keychain, unlocks without one
+    fn unlock_needs_passphrase(&self) -> bool {
+        false
+    }
+
+    /// Whether the store is locked; reads and writes fail with
+    /// `VaultError::Locked` until it is unlocked
+    fn is_locked(&self) -> bool {
+        false
+    }
+
+    /// Lock the store, forgetting its key
+    fn lock(&mut self) -> Result<()> {
+        Err(not_lockable())
+    }
+
+    /// Unlock the store with its passphrase, if it needs one
+    fn unlock(&mut self, _passphrase: &str) -> Result<()> {
+        Err(not_lockable())
+    }
+}
+
+/// Refuse a key no item can be stored under
+pub fn check_key(key: &str) -> Result<()> {
+    if key.is_empty() {
+        return Err(VaultError::InvalidKey("Key cannot be empty".to_string()));
+    }
+    Ok(())
+}
+
+/// Refuse a batch for `VaultStore::set_many` if any of its keys is invalid
+pub(crate) fn check_keys(items: &[(String, VaultItem)]) -> Result<()> {
+    items.iter().try_for_each(|(key, _)| check_key(key))
+}
+
+/// Insert a checked batch into an in-memory map, returning what each key
+/// held before so `restore` can undo it
+pub(crate) fn insert_all(
+    items: &mut HashMap<String, VaultItem>,
+    batch: Vec<(String, VaultItem)>,
+) -> Vec<(String, Option<VaultItem>)> {
+    batch
+        .into_iter()
+        .map(|(key, mut item)| {
+            item.key = key.clone();
+            let previous = items.insert(key.clone(), item);
+            (key, previous)
+        })
+        .collect()
+}
+
+/// Undo `insert_all`, latest insert first so a key set twice gets its
+/// original item back
+pub(crate) fn restore(
+    items: &mut HashMap<String, VaultItem>,
+    previous: Vec<(String, Option<VaultItem>)>,
+) {
+    for (key, item) in previous.into_iter().rev() {
+        match item {
+            Some(item) => items.insert(key, item),
+            None => items.remove(&key),
+        };
+    }
+}
+
+/// Copies of the items in `category`, for backends holding items in memory
+pub(crate) fn in_category<'a>(
+    items: impl Iterator<Item = &'a VaultItem>,
+    category: &VaultCategory,
+) -> Vec<VaultItem> {
+    items
+        .filter(|item| item.category == *category)
+        .cloned()
+        .collect()
+}
+
+fn not_lockable() -> VaultError {
+    VaultError::StorageError("This vault has no passphrase to lock it with".to_string())
 }
 
 // ============================================================================
@@ -225,9 +501,7 @@ impl InMemoryStore {
 
 impl VaultStore for InMemoryStore {
     fn set(&mut self, key: String, item: VaultItem) -> Result<()> {
-        if key.is_empty() {
-            return Err(VaultError::InvalidKey("Key cannot be empty".to_string()));
-        }
+        check_key(&key)?;
 
         self.items.insert(key, item);
         Ok(())
@@ -237,10 +511,26 @@ impl VaultStore for InMemoryStore {
         Ok(self.items.get(key).cloned())
     }
 
+    fn get_many(&self, keys: &[&str]) -> Result<HashMap<String, VaultItem>> {
+        Ok(keys
+            .iter()
+            .filter_map(|key| self.items.get_key_value(*key))
+            .map(|(key, item)| (key.clone(), item.clone()))
+            .collect())
+    }
+
     fn list(&self) -> Result<Vec<VaultItem>> {
         Ok(self.items.values().cloned().collect())
     }
 
+    fn list_by_category(&self, category: VaultCategory) -> Result<Vec<VaultItem>> {
+        Ok(in_category(self.items.values(), &category))
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&VaultItem) -> Result<()>) -> Result<()> {
+        self.items.values().try_for_each(visit)
+    }
+
     fn delete(&mut self, key: &str) -> Result<()> {
         match self.items.remove(key) {
             Some(_) => Ok(()),
@@ -248,12 +538,37 @@ impl VaultStore for InMemoryStore {
         }
     }
 
+    fn get_and_touch(&mut self, key: &str) -> Result<Option<VaultItem>> {
+        Ok(self.items.get_mut(key).map(|item| {
+            item.mark_used();
+            item.clone()
+        }))
+    }
+
     fn clear(&mut self) -> Result<()> {
         self.items.clear();
         Ok(())
     }
 }
 
+// ============================================================================
+// Migration
+// ============================================================================
+
+/// Copy every item of `from` into `to`, keeping keys, provenance and usage
+///
+/// Items already in `to` under the same key are replaced; others are left
+/// alone. Stops at the first error. Returns how many items were copied.
+pub fn migrate(from: &dyn VaultStore, to: &mut dyn VaultStore) -> Result<usize> {
+    let mut copied = 0;
+    from.for_each(&mut |item| {
+        to.set(item.key.clone(), item.clone())?;
+        copied += 1;
+        Ok(())
+    })?;
+    Ok(copied)
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -314,6 +629,259 @@ mod tests {
         assert_eq!(items.len(), 2);
     }
 
+    #[test]
+    fn test_list_by_category_across_backends() {
+        let dir = std::env::temp_dir().join(format!("asterisk-by-category-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut stores: Vec<Box<dyn VaultStore>> = vec![
+            Box::new(InMemoryStore::new()),
+            Box::new(JsonFileStore::open(dir.join("vault.json")).unwrap()),
+            Box::new(JournaledFileStore::open(dir.join("journaled.json")).unwrap()),
+        ];
+        for store in &mut stores {
+            store.set("email".to_string(), create_test_item("email")).unwrap();
+            store.set("phone".to_string(), create_test_item("phone")).unwrap();
+            let mut card = create_test_item("card");
+            card.category = VaultCategory::Financial;
+            store.set("card".to_string(), card).unwrap();
+
+            let mut contacts: Vec<String> = store
+                .list_by_category(VaultCategory::Contact)
+                .unwrap()
+                .into_iter()
+                .map(|item| item.key)
+                .collect();
+            contacts.sort();
+            assert_eq!(contacts, ["email", "phone"]);
+            let financial = store.list_by_category(VaultCategory::Financial).unwrap();
+            assert_eq!(financial.len(), 1);
+            assert_eq!(financial[0].key, "card");
+            assert!(store.list_by_category(VaultCategory::Address).unwrap().is_empty());
+        }
+        drop(stores);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_set_many_is_all_or_nothing_across_backends() {
+        let dir = std::env::temp_dir().join(format!("asterisk-set-many-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        type Open = fn(&std::path::Path) -> Box<dyn VaultStore>;
+        let reopen: Vec<Open> = vec![
+            |dir| Box::new(JsonFileStore::open(dir.join("vault.json")).unwrap()),
+            |dir| Box::new(JournaledFileStore::open(dir.join("journaled.json")).unwrap()),
+            #[cfg(feature = "sqlite")]
+            |dir| Box::new(SqliteStore::open(dir.join("vault.db")).unwrap()),
+        ];
+        let mut stores: Vec<Box<dyn VaultStore>> = vec![Box::new(InMemoryStore::new())];
+        stores.extend(reopen.iter().map(|open| open(&dir)));
+
+        for store in &mut stores {
+            store.set("email".to_string(), create_test_item("email")).unwrap();
+            let mut changed = create_test_item("email");
+            changed.value = "changed".to_string();
+
+            // One empty key refuses the whole batch, before anything is written
+            let result = store.set_many(vec![
+                ("email".to_string(), changed.clone()),
+                ("phone".to_string(), create_test_item("phone")),
+                ("".to_string(), create_test_item("")),
+            ]);
+            assert!(matches!(result, Err(VaultError::InvalidKey(_))));
+            assert_eq!(store.get("email").unwrap().unwrap().value, "test_value");
+            assert!(store.get("phone").unwrap().is_none());
+
+            store
+                .set_many(vec![
+                    ("email".to_string(), changed),
+                    ("phone".to_string(), create_test_item("phone")),
+                ])
+                .unwrap();
+            assert_eq!(store.get("email").unwrap().unwrap().value, "changed");
+            assert_eq!(store.get("phone").unwrap().unwrap().key, "phone");
+        }
+        drop(stores);
+
+        for open in &reopen {
+            let store = open(&dir);
+            assert_eq!(store.get("email").unwrap().unwrap().value, "changed");
+            assert!(store.get("phone").unwrap().is_some());
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn search_store() -> InMemoryStore {
+        let mut store = InMemoryStore::new();
+        for (key, label, value) in [
+            ("email", "Email", "me@example.com"),
+            ("emailWork", "Work Email", "me@work.example"),
+            ("emailPersonal", "Personal Email", "me@home.example"),
+            ("phoneMobile", "Mobile Phone", "+1 555 0100"),
+            ("phoneWork", "Work Phone", "+1 555 0199"),
+            ("apiToken", "API Token", "emailWork-secret"),
+        ] {
+            let mut item = create_test_item(key);
+            item.label = label.to_string();
+            item.value = value.to_string();
+            store.set(key.to_string(), item).unwrap();
+        }
+        store
+    }
+
+    fn search_keys(store: &InMemoryStore, query: &str) -> Vec<String> {
+        store
+            .search(query)
+            .unwrap()
+            .into_iter()
+            .map(|item| item.key)
+            .collect()
+    }
+
+    #[test]
+    fn test_search_ranks_exact_then_prefix_then_substring_then_fuzzy() {
+        let store = search_store();
+        // Exact key, then key prefixes; "Work Email" only contains it
+        assert_eq!(
+            search_keys(&store, "email"),
+            vec!["email", "emailPersonal", "emailWork"]
+        );
+        // Label prefix beats a match on each word
+        assert_eq!(search_keys(&store, "work"), vec!["emailWork", "phoneWork"]);
+        assert_eq!(search_keys(&store, "work email"), vec!["emailWork"]);
+        assert_eq!(search_keys(&store, "email work"), vec!["emailWork"]);
+        assert_eq!(search_keys(&store, "mobile"), vec!["phoneMobile"]);
+        // Typos, closest first
+        assert_eq!(search_keys(&store, "phne"), vec!["phoneMobile", "phoneWork"]);
+        assert_eq!(
+            search_keys(&store, "emial"),
+            vec!["email", "emailPersonal", "emailWork"]
+        );
+        // An exact key isn't beaten by the case-insensitive prefixes
+        assert_eq!(search_keys(&store, "emailWork")[0], "emailWork");
+        assert!(search_keys(&store, "   ").is_empty());
+        assert!(search_keys(&store, "xyz").is_empty());
+    }
+
+    #[test]
+    fn test_search_never_matches_values_and_is_capped() {
+        let store = search_store();
+        assert!(search_keys(&store, "me@work").is_empty());
+        assert!(search_keys(&store, "secret").is_empty());
+        assert_eq!(search_keys(&store, "emailWork-secret"), Vec::<String>::new());
+
+        let mut store = InMemoryStore::new();
+        for i in 0..30 {
+            let key = format!("email{:02}", i);
+            store.set(key.clone(), create_test_item(&key)).unwrap();
+        }
+        let found = search_keys(&store, "email");
+        assert_eq!(found.len(), SEARCH_LIMIT);
+        assert_eq!(found[0], "email00");
+    }
+
+    #[test]
+    fn test_get_many_leaves_out_missing_keys() {
+        let store = search_store();
+        let found = store
+            .get_many(&["emailWork", "fax", "phoneMobile", "emailWork", ""])
+            .unwrap();
+        let mut keys: Vec<&str> = found.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["emailWork", "phoneMobile"]);
+        assert_eq!(found["emailWork"].value, "me@work.example");
+        assert!(store.get_many(&[]).unwrap().is_empty());
+    }
+
+    /// A store that fails the test if anything loads the whole vault
+    struct NoListStore(InMemoryStore);
+
+    impl VaultStore for NoListStore {
+        fn set(&mut self, key: String, item: VaultItem) -> Result<()> {
+            self.0.set(key, item)
+        }
+        fn get(&self, key: &str) -> Result<Option<VaultItem>> {
+            self.0.get(key)
+        }
+        fn list(&self) -> Result<Vec<VaultItem>> {
+            panic!("list() clones the whole vault")
+        }
+        fn for_each(&self, _: &mut dyn FnMut(&VaultItem) -> Result<()>) -> Result<()> {
+            panic!("for_each() visits the whole vault")
+        }
+        fn delete(&mut self, key: &str) -> Result<()> {
+            self.0.delete(key)
+        }
+        fn clear(&mut self) -> Result<()> {
+            self.0.clear()
+        }
+    }
+
+    #[test]
+    fn test_get_many_loads_only_requested_items() {
+        let mut large = InMemoryStore::new();
+        for i in 0..10_000 {
+            let key = format!("item{:05}", i);
+            large.set(key.clone(), create_test_item(&key)).unwrap();
+        }
+        let keys = ["item00042", "item09999", "missing"];
+
+        // The default goes through `get`, never `list`
+        let store = NoListStore(large);
+        let found = store.get_many(&keys).unwrap();
+        assert_eq!(found.len(), 2);
+
+        // The in-memory override copies just the hits
+        let found = store.0.get_many(&keys).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found["item09999"].key, "item09999");
+    }
+
+    #[test]
+    fn test_needs_review_once_review_date_passes() {
+        let now = Utc::now();
+        let mut card = create_test_item("cardNumber");
+        assert!(!card.needs_review(now));
+
+        card.review_after = Some(now - chrono::Duration::days(1));
+        assert!(card.needs_review(now));
+        card.review_after = Some(now);
+        assert!(card.needs_review(now));
+        card.review_after = Some(now + chrono::Duration::days(30));
+        assert!(!card.needs_review(now));
+
+        // Items stored before review dates existed have none
+        let mut json = serde_json::to_value(create_test_item("email")).unwrap();
+        json.as_object_mut().unwrap().remove("review_after");
+        let stored: VaultItem = serde_json::from_value(json).unwrap();
+        assert_eq!(stored.review_after, None);
+    }
+
+    #[test]
+    fn test_for_each_stops_at_error() {
+        let store = InMemoryStore::with_items(vec![
+            create_test_item("a"),
+            create_test_item("b"),
+            create_test_item("c"),
+        ]);
+
+        let mut seen = 0;
+        store
+            .for_each(&mut |_| {
+                seen += 1;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(seen, 3);
+
+        let mut seen = 0;
+        let result = store.for_each(&mut |_| {
+            seen += 1;
+            Err(VaultError::StorageError("stop".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(seen, 1);
+    }
+
     #[test]
     fn test_delete_nonexistent() {
         let mut store = InMemoryStore::new();
@@ -338,6 +906,123 @@ mod tests {
         assert!(item.metadata.last_used.is_some());
     }
 
+    #[test]
+    fn test_touch_bumps_stored_item() {
+        let mut store = InMemoryStore::with_items(vec![create_test_item("email")]);
+
+        store.touch("email").unwrap();
+        let touched = store.get_and_touch("email").unwrap().unwrap();
+        assert_eq!(touched.metadata.usage_count, 2);
+        assert!(touched.metadata.last_used.is_some());
+        assert_eq!(store.get("email").unwrap().unwrap(), touched);
+
+        assert!(matches!(
+            store.touch("missing"),
+            Err(VaultError::NotFound(_))
+        ));
+        assert!(store.get_and_touch("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_concurrent_touches_are_not_lost() {
+        use std::sync::{Arc, Mutex};
+
+        const THREADS: usize = 8;
+        const TOUCHES: usize = 250;
+        let store = Arc::new(Mutex::new(InMemoryStore::with_items(vec![
+            create_test_item("email"),
+        ])));
+
+        let handles: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let store = Arc::clone(&store);
+                std::thread::spawn(move || {
+                    for _ in 0..TOUCHES {
+                        store.lock().unwrap().touch("email").unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let item = store.lock().unwrap().get("email").unwrap().unwrap();
+        assert_eq!(item.metadata.usage_count as usize, THREADS * TOUCHES);
+    }
+
+    #[test]
+    fn test_keep_usage_of_stored_copy() {
+        let mut stored = create_test_item("email");
+        stored.mark_used();
+        stored.mark_used();
+
+        let mut edited = create_test_item("email");
+        edited.update_value("new@example.com");
+        edited.keep_usage_of(&stored);
+        assert_eq!(edited.metadata.usage_count, 2);
+        assert_eq!(edited.metadata.last_used, stored.metadata.last_used);
+        assert_eq!(edited.value, "new@example.com");
+    }
+
+    #[test]
+    fn test_sensitive_defaults_off() {
+        let item = create_test_item("test");
+        assert!(!item.sensitive);
+
+        // Items serialized before the flag existed still deserialize
+        let mut json = serde_json::to_value(&item).unwrap();
+        json.as_object_mut().unwrap().remove("sensitive");
+        let parsed: VaultItem = serde_json::from_value(json).unwrap();
+        assert!(!parsed.sensitive);
+    }
+
+    #[test]
+    fn test_review_status_follows_provenance() {
+        let mut item = create_test_item("test");
+        assert_eq!(item.review_status, ReviewStatus::Approved);
+
+        item.provenance.source = ProvenanceSource::Imported;
+        item.provenance.confidence = 0.5;
+        assert_eq!(
+            ReviewStatus::initial(&item.provenance),
+            ReviewStatus::Unreviewed
+        );
+
+        item.resolve_review(true);
+        assert_eq!(item.review_status, ReviewStatus::Approved);
+        assert_eq!(item.provenance.confidence, 1.0);
+        assert_eq!(
+            ReviewStatus::initial(&item.provenance),
+            ReviewStatus::Approved
+        );
+
+        // Items serialized before review existed count as approved
+        item.resolve_review(false);
+        let mut json = serde_json::to_value(&item).unwrap();
+        assert_eq!(json["review_status"], "rejected");
+        json.as_object_mut().unwrap().remove("review_status");
+        let parsed: VaultItem = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.review_status, ReviewStatus::Approved);
+    }
+
+    #[test]
+    fn test_migrate_copies_items_with_usage() {
+        let mut from = InMemoryStore::new();
+        from.set("email".to_string(), create_test_item("email")).unwrap();
+        from.set("phone".to_string(), create_test_item("phone")).unwrap();
+        from.touch("email").unwrap();
+
+        let mut to = InMemoryStore::new();
+        to.set("city".to_string(), create_test_item("city")).unwrap();
+        assert_eq!(migrate(&from, &mut to).unwrap(), 2);
+
+        assert_eq!(to.len(), 3);
+        let email = to.get("email").unwrap().unwrap();
+        assert_eq!(email.metadata.usage_count, 1);
+        assert_eq!(from.len(), 2);
+    }
+
     #[test]
     fn test_vault_item_update() {
         let mut item = create_test_item("test");
@@ -0,0 +1,280 @@
+/*!
+ * Lockable Store
+ *
+ * `LockableStore` puts a passphrase lock on an `EncryptedFileStore`, the way
+ * a password manager does. While unlocked it behaves exactly like the
+ * store it wraps. Locking drops that store, which wipes the Argon2id-derived
+ * data key and the decrypted values and labels it held; every read and
+ * write then fails with `VaultError::Locked`. Copies already handed out by
+ * `get` or `list` belong to the caller and are not wiped, and the wipe is
+ * best effort: the allocator may have left older copies of a value behind
+ * when a string or map grew.
+ *
+ * Unlocking reopens the vault file with the passphrase. Every mutation is
+ * saved before it returns, so the file always matches what was in memory.
+ * A wrong passphrase fails the file's authentication check before anything
+ * is written, so the vault stays locked and the file untouched. The check
+ * costs the same either way: the key derivation always runs in full, and
+ * the authentication tag is compared in constant time.
+ *
+ * A vault under a master key in the keychain (`with_keychain`) locks the
+ * same way, but unlocking reads the key from the keychain again instead of
+ * taking a passphrase. Locking it still clears the key and every decrypted
+ * value from memory.
+ */
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::{
+    EncryptedFileStore, KeyringBackend, Result, VaultCategory, VaultError, VaultItem, VaultStore,
+};
+
+/// Where the key comes from on unlock
+enum KeySource {
+    Passphrase,
+    Keychain(Arc<dyn KeyringBackend>),
+}
+
+impl std::fmt::Debug for KeySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeySource::Passphrase => f.write_str("Passphrase"),
+            KeySource::Keychain(_) => f.write_str("Keychain"),
+        }
+    }
+}
+
+/// An encrypted vault that can be locked until its key is given again
+#[derive(Debug)]
+pub struct LockableStore {
+    path: PathBuf,
+    key_source: KeySource,
+    /// None while locked
+    store: Option<EncryptedFileStore>,
+}
+
+impl LockableStore {
+    /// Wrap an open (unlocked) passphrase store
+    pub fn new(store: EncryptedFileStore) -> Self {
+        Self {
+            path: store.path().to_path_buf(),
+            key_source: KeySource::Passphrase,
+            store: Some(store),
+        }
+    }
+
+    /// Wrap an open (unlocked) store under the master key in `keyring`;
+    /// `unlock` reads the key from there and ignores its passphrase
+    pub fn with_keychain(store: EncryptedFileStore, keyring: Arc<dyn KeyringBackend>) -> Self {
+        Self {
+            path: store.path().to_path_buf(),
+            key_source: KeySource::Keychain(keyring),
+            store: Some(store),
+        }
+    }
+
+    /// The existing passphrase vault at `path`, locked until `unlock`
+    pub fn locked(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            key_source: KeySource::Passphrase,
+            store: None,
+        }
+    }
+
+    /// The vault file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn store(&self) -> Result<&EncryptedFileStore> {
+        self.store.as_ref().ok_or(VaultError::Locked)
+    }
+
+    fn store_mut(&mut self) -> Result<&mut EncryptedFileStore> {
+        self.store.as_mut().ok_or(VaultError::Locked)
+    }
+}
+
+impl VaultStore for LockableStore {
+    fn set(&mut self, key: String, item: VaultItem) -> Result<()> {
+        self.store_mut()?.set(key, item)
+    }
+
+    fn set_many(&mut self, items: Vec<(String, VaultItem)>) -> Result<()> {
+        self.store_mut()?.set_many(items)
+    }
+
+    fn get(&self, key: &str) -> Result<Option<VaultItem>> {
+        self.store()?.get(key)
+    }
+
+    fn get_many(&self, keys: &[&str]) -> Result<HashMap<String, VaultItem>> {
+        self.store()?.get_many(keys)
+    }
+
+    fn list(&self) -> Result<Vec<VaultItem>> {
+        self.store()?.list()
+    }
+
+    fn list_by_category(&self, category: VaultCategory) -> Result<Vec<VaultItem>> {
+        self.store()?.list_by_category(category)
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&VaultItem) -> Result<()>) -> Result<()> {
+        self.store()?.for_each(visit)
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        self.store_mut()?.delete(key)
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.store_mut()?.clear()
+    }
+
+    fn is_lockable(&self) -> bool {
+        true
+    }
+
+    fn is_locked(&self) -> bool {
+        self.store.is_none()
+    }
+
+    fn unlock_needs_passphrase(&self) -> bool {
+        matches!(self.key_source, KeySource::Passphrase)
+    }
+
+    fn lock(&mut self) -> Result<()> {
+        self.store = None;
+        Ok(())
+    }
+
+    /// Reopens the file even when already unlocked, so a wrong passphrase
+    /// is always reported
+    fn unlock(&mut self, passphrase: &str) -> Result<()> {
+        let store = match &self.key_source {
+            KeySource::Passphrase => EncryptedFileStore::open(&self.path, passphrase)?,
+            KeySource::Keychain(keyring) => {
+                let key = crate::load_master_key(keyring.as_ref())?.ok_or_else(|| {
+                    VaultError::StorageError(format!(
+                        "The keychain holds no master key for {}",
+                        self.path.display()
+                    ))
+                })?;
+                EncryptedFileStore::open_with_key(&self.path, &key)?
+            }
+        };
+        self.store = Some(store);
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util, KdfParams, VaultCategory};
+    use std::fs;
+
+    /// Cheap costs so tests don't spend seconds deriving keys
+    const TEST_KDF: KdfParams = KdfParams {
+        mem_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    fn item(key: &str) -> VaultItem {
+        test_util::item(key, &format!("{} value", key), VaultCategory::Contact)
+    }
+
+    fn lockable(name: &str, passphrase: &str) -> LockableStore {
+        let dir =
+            std::env::temp_dir().join(format!("asterisk-lockable-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let store =
+            EncryptedFileStore::create_with_kdf(dir.join("vault.enc"), passphrase, TEST_KDF)
+                .unwrap();
+        LockableStore::new(store)
+    }
+
+    #[test]
+    fn test_locked_store_refuses_reads_and_writes() {
+        let mut store = lockable("refuses", "right");
+        store.set("email".into(), item("email")).unwrap();
+        assert!(store.is_lockable());
+        assert!(!store.is_locked());
+
+        store.lock().unwrap();
+        assert!(store.is_locked());
+        assert!(matches!(store.get("email"), Err(VaultError::Locked)));
+        assert!(matches!(store.list(), Err(VaultError::Locked)));
+        assert!(matches!(
+            store.set("phone".into(), item("phone")),
+            Err(VaultError::Locked)
+        ));
+        assert!(matches!(store.delete("email"), Err(VaultError::Locked)));
+        assert!(matches!(store.touch("email"), Err(VaultError::Locked)));
+        assert!(!store.exists("email"));
+        assert_eq!(store.len(), 0);
+
+        store.unlock("right").unwrap();
+        assert_eq!(store.get("email").unwrap().unwrap().value, "email value");
+        assert_eq!(store.len(), 1);
+        let _ = fs::remove_dir_all(store.path().parent().unwrap());
+    }
+
+    #[test]
+    fn test_wrong_passphrase_stays_locked_and_keeps_data() {
+        let mut store = lockable("wrong", "right");
+        store.set("email".into(), item("email")).unwrap();
+        let before = fs::read(store.path()).unwrap();
+
+        store.lock().unwrap();
+        let err = store.unlock("wrong").unwrap_err();
+        assert!(err.to_string().contains("Wrong passphrase"), "{}", err);
+        assert!(store.is_locked());
+        assert_eq!(fs::read(store.path()).unwrap(), before);
+
+        // Reopening a vault that starts locked works the same way
+        let mut reopened = LockableStore::locked(store.path());
+        assert!(reopened.is_locked());
+        assert!(reopened.unlock("").is_err());
+        reopened.unlock("right").unwrap();
+        assert_eq!(reopened.list().unwrap().len(), 1);
+        let _ = fs::remove_dir_all(store.path().parent().unwrap());
+    }
+
+    #[test]
+    fn test_keychain_store_unlocks_from_the_keychain() {
+        let dir =
+            std::env::temp_dir().join(format!("asterisk-lockable-keychain-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let keyring = Arc::new(crate::FileKeyring::new(dir.join("keyring.json")));
+        let key = crate::MasterKey::generate();
+        crate::store_master_key(keyring.as_ref(), &key).unwrap();
+        let store = EncryptedFileStore::create_with_key(dir.join("vault.enc"), &key).unwrap();
+        let mut store = LockableStore::with_keychain(store, keyring.clone());
+        store.set("email".into(), item("email")).unwrap();
+        assert!(store.is_lockable());
+        assert!(!store.unlock_needs_passphrase());
+
+        store.lock().unwrap();
+        assert!(matches!(store.get("email"), Err(VaultError::Locked)));
+        store.unlock("").unwrap();
+        assert_eq!(store.get("email").unwrap().unwrap().value, "email value");
+
+        // Without its key it stays locked
+        store.lock().unwrap();
+        crate::delete_master_key(keyring.as_ref()).unwrap();
+        let err = store.unlock("").unwrap_err();
+        assert!(err.to_string().contains("no master key"), "{}", err);
+        assert!(store.is_locked());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
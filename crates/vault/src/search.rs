@@ -0,0 +1,257 @@
+/*!
+ * Vault Search
+ *
+ * Ranks items against a search query for `VaultStore::search`, so the right
+ * key can be found without knowing its exact name. Each item gets a score
+ * from its key, label and category, the best of:
+ *
+ * - the exact key (1000), then key or label equal to the query ignoring
+ *   case (900), starting with it (800), or containing it or each of its
+ *   words (600)
+ * - key or label words within one or two typos of the query (400, 300)
+ * - the query's letters appearing in order in the key, label or one of
+ *   their words, scored by how much of it they cover (100 to 300)
+ * - the category name starting with the query (250)
+ *
+ * Items scoring under `MIN_SCORE` are left out. The rest are sorted by
+ * score, and ties go to the item used most. Values are never scored, so a
+ * search can't reveal a secret.
+ */
+
+use crate::{VaultCategory, VaultItem};
+
+/// Most items `VaultStore::search` returns
+pub const SEARCH_LIMIT: usize = 20;
+
+/// Lowest score an item needs to be returned
+pub const MIN_SCORE: u32 = 200;
+
+/// Most typos a fuzzy match may have
+const MAX_TYPOS: usize = 2;
+
+/// How well `item` matches `query`; 0 for not at all
+pub fn score(item: &VaultItem, query: &str) -> u32 {
+    let exact = query.trim();
+    let query = exact.to_lowercase();
+    if query.is_empty() {
+        return 0;
+    }
+    if item.key == exact {
+        return 1000;
+    }
+
+    let key = item.key.to_lowercase();
+    let label = item.label.to_lowercase();
+    let fields = [key.as_str(), label.as_str()];
+    if fields.contains(&query.as_str()) {
+        return 900;
+    }
+    if fields.iter().any(|field| field.starts_with(&query)) {
+        return 800;
+    }
+    let words: Vec<&str> = query.split_whitespace().collect();
+    if fields
+        .iter()
+        .any(|field| words.iter().all(|word| field.contains(word)))
+    {
+        return 600;
+    }
+
+    let mut candidates = vec![key.clone(), label.clone()];
+    candidates.extend(split_words(&item.key));
+    candidates.extend(label.split_whitespace().map(str::to_string));
+    let typo_score = typos(&query, &candidates).map_or(0, |typos| 500 - 100 * typos as u32);
+    let subsequence_score = candidates
+        .iter()
+        .filter_map(|candidate| subsequence(&query, candidate))
+        .max()
+        .unwrap_or(0);
+    let category_score = if category_name(&item.category).starts_with(&query) {
+        250
+    } else {
+        0
+    };
+    typo_score.max(subsequence_score).max(category_score)
+}
+
+/// The items scoring at least `MIN_SCORE` for `query`, best first, at most
+/// `limit` of them
+pub fn rank(items: Vec<VaultItem>, query: &str, limit: usize) -> Vec<VaultItem> {
+    let mut scored: Vec<(u32, VaultItem)> = items
+        .into_iter()
+        .map(|item| (score(&item, query), item))
+        .filter(|(score, _)| *score >= MIN_SCORE)
+        .collect();
+    scored.sort_by(|(score_a, a), (score_b, b)| {
+        score_b
+            .cmp(score_a)
+            .then_with(|| b.metadata.usage_count.cmp(&a.metadata.usage_count))
+            .then_with(|| a.key.cmp(&b.key))
+    });
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, item)| item)
+        .collect()
+}
+
+/// Fewest typos between `query` and any candidate, if within `MAX_TYPOS`
+///
+/// Typos are only allowed for a query long enough to still say something.
+fn typos(query: &str, candidates: &[String]) -> Option<usize> {
+    let max_typos = MAX_TYPOS.min(query.chars().count().saturating_sub(2));
+    if max_typos == 0 {
+        return None;
+    }
+    candidates
+        .iter()
+        .map(|candidate| levenshtein(query, candidate))
+        .min()
+        .filter(|typos| *typos <= max_typos)
+}
+
+/// Score for the letters of `query` appearing in order in `candidate`:
+/// 100 plus up to 200 for the share of `candidate` they cover
+fn subsequence(query: &str, candidate: &str) -> Option<u32> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut remaining = query.iter().peekable();
+    let mut length = 0;
+    for c in candidate.chars() {
+        length += 1;
+        if remaining.peek() == Some(&&c) {
+            remaining.next();
+        }
+    }
+    (remaining.peek().is_none() && length > 0).then(|| 100 + 200 * query.len() as u32 / length)
+}
+
+fn category_name(category: &VaultCategory) -> &'static str {
+    match category {
+        VaultCategory::Identity => "identity",
+        VaultCategory::Contact => "contact",
+        VaultCategory::Address => "address",
+        VaultCategory::Financial => "financial",
+        VaultCategory::Custom => "custom",
+    }
+}
+
+/// The lowercased words of a camelCase or snake_case key
+fn split_words(key: &str) -> Vec<String> {
+    let mut words = vec![String::new()];
+    for c in key.chars() {
+        if c == '_' || c == '-' || c.is_uppercase() {
+            words.push(String::new());
+        }
+        if c.is_alphanumeric() {
+            words.last_mut().unwrap().extend(c.to_lowercase());
+        }
+    }
+    words.retain(|word| !word.is_empty());
+    words
+}
+
+/// Edit distance between `a` and `b`, counted in chars
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    fn item(key: &str, label: &str, category: VaultCategory) -> VaultItem {
+        VaultItem::new(
+            key,
+            "secret value",
+            label,
+            category,
+            test_util::user_entered(),
+        )
+    }
+
+    fn keys(items: Vec<VaultItem>) -> Vec<String> {
+        items.into_iter().map(|item| item.key).collect()
+    }
+
+    #[test]
+    fn test_scores_fall_with_match_quality() {
+        let work = item("emailWork", "Work Email", VaultCategory::Contact);
+        let scores: Vec<u32> = [
+            "emailWork",
+            "emailwork",
+            "email",
+            "email work",
+            "emial",
+            "emlwrk",
+            "contact",
+        ]
+        .iter()
+        .map(|query| score(&work, query))
+        .collect();
+        assert_eq!(scores[..4], [1000, 900, 800, 600]);
+        // Two typos in the word "email"
+        assert_eq!(scores[4], 300);
+        // Six of the nine letters of "emailwork"
+        assert_eq!(scores[5], 100 + 200 * 6 / 9);
+        assert_eq!(scores[6], 250);
+        assert!(scores.windows(2).take(4).all(|pair| pair[0] > pair[1]));
+
+        assert_eq!(score(&work, ""), 0);
+        assert_eq!(score(&work, "fax"), 0);
+        assert_eq!(score(&work, "secret"), 0);
+    }
+
+    #[test]
+    fn test_sparse_matches_fall_under_threshold() {
+        let item = item(
+            "notes",
+            "Emergency contact instructions",
+            VaultCategory::Custom,
+        );
+        // "eci" appears in order in the label, but covers little of it
+        let sparse = score(&item, "eci");
+        assert!(sparse > 0 && sparse < MIN_SCORE, "{}", sparse);
+        assert!(rank(vec![item], "eci", SEARCH_LIMIT).is_empty());
+    }
+
+    #[test]
+    fn test_ties_go_to_the_most_used_item() {
+        let mut personal = item("emailPersonal", "Personal Email", VaultCategory::Contact);
+        let work = item("emailWork", "Work Email", VaultCategory::Contact);
+        let card = item("cardNumber", "Card number", VaultCategory::Financial);
+        personal.metadata.usage_count = 1;
+        let mut busy = work.clone();
+        busy.metadata.usage_count = 7;
+
+        let items = vec![personal.clone(), work, card.clone()];
+        assert_eq!(
+            keys(rank(items, "email", SEARCH_LIMIT)),
+            ["emailPersonal", "emailWork"]
+        );
+        let items = vec![personal, busy, card];
+        assert_eq!(
+            keys(rank(items.clone(), "email", SEARCH_LIMIT)),
+            ["emailWork", "emailPersonal"]
+        );
+        assert_eq!(keys(rank(items.clone(), "email", 1)), ["emailWork"]);
+        assert_eq!(keys(rank(items, "financial", SEARCH_LIMIT)), ["cardNumber"]);
+    }
+}
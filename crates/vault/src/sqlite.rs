@@ -0,0 +1,620 @@
+/*!
+ * SQLite Store
+ *
+ * A persistent `VaultStore` backed by a SQLite database, built with the
+ * `sqlite` feature. Each item is one row and each field its own column, so
+ * the database can be queried and indexed directly instead of holding one
+ * serialized blob. Timestamps are stored as fixed-width RFC 3339 text so
+ * they sort correctly; the enums are stored under their serde names.
+ *
+ * The schema is versioned with `PRAGMA user_version`. Opening a database
+ * applies whichever `MIGRATIONS` it hasn't seen yet, in one transaction, so
+ * a newer build can extend an older vault in place. Several handles may
+ * open the same file: SQLite serializes their writes, and a handle waits up
+ * to `BUSY_TIMEOUT` for another's write to finish rather than failing.
+ */
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use crate::journal::storage_error;
+use crate::{Provenance, Result, VaultCategory, VaultError, VaultItem, VaultMetadata, VaultStore};
+
+/// How long a handle waits for another handle's write to finish
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Schema changes in order; migration `n` takes `user_version` from `n` to `n + 1`
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE vault_items (
+        key TEXT PRIMARY KEY NOT NULL,
+        value TEXT NOT NULL,
+        label TEXT NOT NULL,
+        category TEXT NOT NULL,
+        provenance_source TEXT NOT NULL,
+        provenance_timestamp TEXT NOT NULL,
+        provenance_confidence REAL NOT NULL,
+        provenance_origin TEXT,
+        created TEXT NOT NULL,
+        updated TEXT NOT NULL,
+        last_used TEXT,
+        usage_count INTEGER NOT NULL DEFAULT 0,
+        sensitive INTEGER NOT NULL DEFAULT 0,
+        review_status TEXT NOT NULL DEFAULT 'approved'
+    );
+    CREATE INDEX vault_items_updated ON vault_items (updated DESC);",
+    "CREATE INDEX vault_items_category ON vault_items (category, updated DESC);",
+    "ALTER TABLE vault_items ADD COLUMN review_after TEXT;",
+];
+
+/// Columns in the order `RawRow::read` expects them
+const COLUMNS: &str = "key, value, label, category, provenance_source, provenance_timestamp,
+    provenance_confidence, provenance_origin, created, updated, last_used, usage_count,
+    sensitive, review_status, review_after";
+
+/// Vault persisted to a SQLite database
+pub struct SqliteStore {
+    path: Option<PathBuf>,
+    conn: Mutex<Connection>,
+}
+
+impl std::fmt::Debug for SqliteStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqliteStore")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SqliteStore {
+    /// Open the database at `path`, creating and migrating it as needed
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| storage_error("Failed to create", parent, e))?;
+        }
+        let conn =
+            Connection::open(&path).map_err(|e| storage_error("Failed to open", &path, e))?;
+        Self::with_connection(conn, Some(path))
+    }
+
+    /// A private database that lives as long as the store
+    pub fn in_memory() -> Result<Self> {
+        let conn = Connection::open_in_memory().map_err(sql_error)?;
+        Self::with_connection(conn, None)
+    }
+
+    fn with_connection(mut conn: Connection, path: Option<PathBuf>) -> Result<Self> {
+        conn.busy_timeout(BUSY_TIMEOUT).map_err(sql_error)?;
+        migrate(&mut conn)?;
+        Ok(Self {
+            path,
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// The database file; `None` for an in-memory store
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The schema version the database is at
+    pub fn schema_version(&self) -> Result<usize> {
+        user_version(&self.conn())
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Apply the migrations the database hasn't seen yet
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let tx = conn
+        .transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)
+        .map_err(sql_error)?;
+    let version = user_version(&tx)?;
+    if version > MIGRATIONS.len() {
+        return Err(VaultError::StorageError(format!(
+            "Vault database schema version {} is newer than this build supports ({})",
+            version,
+            MIGRATIONS.len()
+        )));
+    }
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        tx.execute_batch(migration).map_err(sql_error)?;
+        tx.pragma_update(None, "user_version", i + 1)
+            .map_err(sql_error)?;
+    }
+    tx.commit().map_err(sql_error)
+}
+
+/// Insert `item` under `key`, replacing what was there
+fn upsert(conn: &Connection, key: &str, item: &VaultItem) -> Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO vault_items ({})
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+                 ON CONFLICT (key) DO UPDATE SET
+                    value = excluded.value,
+                    label = excluded.label,
+                    category = excluded.category,
+                    provenance_source = excluded.provenance_source,
+                    provenance_timestamp = excluded.provenance_timestamp,
+                    provenance_confidence = excluded.provenance_confidence,
+                    provenance_origin = excluded.provenance_origin,
+                    created = excluded.created,
+                    updated = excluded.updated,
+                    last_used = excluded.last_used,
+                    usage_count = excluded.usage_count,
+                    sensitive = excluded.sensitive,
+                    review_status = excluded.review_status,
+                    review_after = excluded.review_after",
+            COLUMNS
+        ),
+        params![
+            key,
+            item.value,
+            item.label,
+            enum_text(&item.category)?,
+            enum_text(&item.provenance.source)?,
+            timestamp(&item.provenance.timestamp),
+            item.provenance.confidence,
+            item.provenance.origin,
+            timestamp(&item.metadata.created),
+            timestamp(&item.metadata.updated),
+            item.metadata.last_used.as_ref().map(timestamp),
+            item.metadata.usage_count,
+            item.sensitive,
+            enum_text(&item.review_status)?,
+            item.review_after.as_ref().map(timestamp),
+        ],
+    )
+    .map_err(sql_error)?;
+    Ok(())
+}
+
+fn user_version(conn: &Connection) -> Result<usize> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))
+        .map(|v| v as usize)
+        .map_err(sql_error)
+}
+
+fn sql_error(e: rusqlite::Error) -> VaultError {
+    VaultError::StorageError(format!("SQLite: {}", e))
+}
+
+fn timestamp(t: &DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
+
+/// An enum's serde name, e.g. `user_entered`
+fn enum_text<T: Serialize>(value: &T) -> Result<String> {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(s)) => Ok(s),
+        Ok(other) => Err(VaultError::SerializationError(format!(
+            "Expected a unit variant, got {}",
+            other
+        ))),
+        Err(e) => Err(VaultError::SerializationError(e.to_string())),
+    }
+}
+
+fn parse_enum<T: DeserializeOwned>(column: &str, text: &str) -> Result<T> {
+    serde_json::from_value(serde_json::Value::String(text.to_string()))
+        .map_err(|e| VaultError::SerializationError(format!("Bad {} '{}': {}", column, text, e)))
+}
+
+fn parse_timestamp(column: &str, text: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| VaultError::SerializationError(format!("Bad {} '{}': {}", column, text, e)))
+}
+
+/// The raw text and numbers of one row, before parsing
+struct RawRow {
+    key: String,
+    value: String,
+    label: String,
+    category: String,
+    source: String,
+    timestamp: String,
+    confidence: f64,
+    origin: Option<String>,
+    created: String,
+    updated: String,
+    last_used: Option<String>,
+    usage_count: i64,
+    sensitive: bool,
+    review_status: String,
+    review_after: Option<String>,
+}
+
+impl RawRow {
+    fn read(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(RawRow {
+            key: row.get(0)?,
+            value: row.get(1)?,
+            label: row.get(2)?,
+            category: row.get(3)?,
+            source: row.get(4)?,
+            timestamp: row.get(5)?,
+            confidence: row.get(6)?,
+            origin: row.get(7)?,
+            created: row.get(8)?,
+            updated: row.get(9)?,
+            last_used: row.get(10)?,
+            usage_count: row.get(11)?,
+            sensitive: row.get(12)?,
+            review_status: row.get(13)?,
+            review_after: row.get(14)?,
+        })
+    }
+
+    fn into_item(self) -> Result<VaultItem> {
+        Ok(VaultItem {
+            category: parse_enum("category", &self.category)?,
+            provenance: Provenance {
+                source: parse_enum("provenance_source", &self.source)?,
+                timestamp: parse_timestamp("provenance_timestamp", &self.timestamp)?,
+                confidence: self.confidence,
+                origin: self.origin,
+            },
+            metadata: VaultMetadata {
+                created: parse_timestamp("created", &self.created)?,
+                updated: parse_timestamp("updated", &self.updated)?,
+                last_used: self
+                    .last_used
+                    .map(|t| parse_timestamp("last_used", &t))
+                    .transpose()?,
+                usage_count: u32::try_from(self.usage_count).unwrap_or(u32::MAX),
+            },
+            sensitive: self.sensitive,
+            review_status: parse_enum("review_status", &self.review_status)?,
+            review_after: self
+                .review_after
+                .map(|t| parse_timestamp("review_after", &t))
+                .transpose()?,
+            key: self.key,
+            value: self.value,
+            label: self.label,
+        })
+    }
+}
+
+fn query_items(conn: &Connection, visit: &mut dyn FnMut(VaultItem) -> Result<()>) -> Result<()> {
+    query_items_where(conn, "", [], visit)
+}
+
+/// Items matching `filter` (a WHERE clause, or empty for all), most
+/// recently updated first
+fn query_items_where(
+    conn: &Connection,
+    filter: &str,
+    params: impl rusqlite::Params,
+    visit: &mut dyn FnMut(VaultItem) -> Result<()>,
+) -> Result<()> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM vault_items {} ORDER BY updated DESC, key",
+            COLUMNS, filter
+        ))
+        .map_err(sql_error)?;
+    let rows = stmt.query_map(params, RawRow::read).map_err(sql_error)?;
+    for row in rows {
+        visit(row.map_err(sql_error)?.into_item()?)?;
+    }
+    Ok(())
+}
+
+fn get_item(conn: &Connection, key: &str) -> Result<Option<VaultItem>> {
+    conn.query_row(
+        &format!("SELECT {} FROM vault_items WHERE key = ?1", COLUMNS),
+        [key],
+        RawRow::read,
+    )
+    .optional()
+    .map_err(sql_error)?
+    .map(RawRow::into_item)
+    .transpose()
+}
+
+impl VaultStore for SqliteStore {
+    /// Insert the item under `key`, or replace every column of the row
+    /// already there; the stored item's `key` is `key`
+    fn set(&mut self, key: String, item: VaultItem) -> Result<()> {
+        crate::check_key(&key)?;
+        upsert(&self.conn(), &key, &item)
+    }
+
+    /// Checks every key, then writes the batch in one transaction
+    fn set_many(&mut self, items: Vec<(String, VaultItem)>) -> Result<()> {
+        crate::check_keys(&items)?;
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(sql_error)?;
+        for (key, item) in &items {
+            upsert(&tx, key, item)?;
+        }
+        tx.commit().map_err(sql_error)
+    }
+
+    fn get(&self, key: &str) -> Result<Option<VaultItem>> {
+        get_item(&self.conn(), key)
+    }
+
+    /// Every item, most recently updated first
+    fn list(&self) -> Result<Vec<VaultItem>> {
+        let mut items = Vec::new();
+        query_items(&self.conn(), &mut |item| {
+            items.push(item);
+            Ok(())
+        })?;
+        Ok(items)
+    }
+
+    /// Items in `category`, most recently updated first; served from the
+    /// category index
+    fn list_by_category(&self, category: VaultCategory) -> Result<Vec<VaultItem>> {
+        let mut items = Vec::new();
+        query_items_where(
+            &self.conn(),
+            "WHERE category = ?1",
+            [enum_text(&category)?],
+            &mut |item| {
+                items.push(item);
+                Ok(())
+            },
+        )?;
+        Ok(items)
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&VaultItem) -> Result<()>) -> Result<()> {
+        query_items(&self.conn(), &mut |item| visit(&item))
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        let deleted = self
+            .conn()
+            .execute("DELETE FROM vault_items WHERE key = ?1", [key])
+            .map_err(sql_error)?;
+        if deleted == 0 {
+            return Err(VaultError::NotFound(key.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Bumps usage in one statement, so a touch through another handle on
+    /// the same file is never lost
+    fn get_and_touch(&mut self, key: &str) -> Result<Option<VaultItem>> {
+        let conn = self.conn();
+        let touched = conn
+            .execute(
+                "UPDATE vault_items
+                 SET usage_count = MIN(usage_count + 1, 4294967295), last_used = ?2
+                 WHERE key = ?1",
+                params![key, timestamp(&Utc::now())],
+            )
+            .map_err(sql_error)?;
+        if touched == 0 {
+            return Ok(None);
+        }
+        get_item(&conn, key)
+    }
+
+    fn exists(&self, key: &str) -> bool {
+        self.conn()
+            .query_row(
+                "SELECT 1 FROM vault_items WHERE key = ?1",
+                [key],
+                |_| Ok(()),
+            )
+            .optional()
+            .ok()
+            .flatten()
+            .is_some()
+    }
+
+    fn len(&self) -> usize {
+        self.conn()
+            .query_row("SELECT COUNT(*) FROM vault_items", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .map(|n| n as usize)
+            .unwrap_or(0)
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.conn()
+            .execute("DELETE FROM vault_items", [])
+            .map_err(sql_error)?;
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util, ProvenanceSource, ReviewStatus, VaultCategory};
+    use std::fs;
+
+    fn item(key: &str, category: VaultCategory) -> VaultItem {
+        test_util::item(key, &format!("{} value", key), category)
+    }
+
+    fn db_path(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("asterisk-sqlite-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("vault.db")
+    }
+
+    #[test]
+    fn test_round_trips_every_field() {
+        let path = db_path("roundtrip");
+        let mut imported = item("ssn", VaultCategory::Financial);
+        imported.provenance = Provenance {
+            source: ProvenanceSource::Imported,
+            timestamp: Utc::now(),
+            confidence: 0.4,
+            origin: Some("contacts.csv".to_string()),
+        };
+        imported.review_status = ReviewStatus::Unreviewed;
+        imported.sensitive = true;
+        imported.review_after = Some(Utc::now() + chrono::Duration::days(365));
+        imported.mark_used();
+        {
+            let mut store = SqliteStore::open(&path).unwrap();
+            store.set("ssn".to_string(), imported.clone()).unwrap();
+            store
+                .set("name".to_string(), item("name", VaultCategory::Identity))
+                .unwrap();
+        }
+
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.get("ssn").unwrap(), Some(imported));
+        assert_eq!(store.len(), 2);
+        assert!(store.exists("name"));
+        assert!(store.get("missing").unwrap().is_none());
+        assert_eq!(store.schema_version().unwrap(), MIGRATIONS.len());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_set_upserts_and_delete_reports_missing() {
+        let mut store = SqliteStore::in_memory().unwrap();
+        store
+            .set("email".to_string(), item("email", VaultCategory::Contact))
+            .unwrap();
+        let mut edited = item("email", VaultCategory::Contact);
+        edited.value = "new@example.com".to_string();
+        store.set("email".to_string(), edited).unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(
+            store.get("email").unwrap().unwrap().value,
+            "new@example.com"
+        );
+
+        assert!(matches!(
+            store.set(String::new(), item("", VaultCategory::Custom)),
+            Err(VaultError::InvalidKey(_))
+        ));
+        store.delete("email").unwrap();
+        assert!(matches!(
+            store.delete("email"),
+            Err(VaultError::NotFound(_))
+        ));
+        assert!(matches!(store.touch("email"), Err(VaultError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_lists_most_recently_updated_first() {
+        let mut store = SqliteStore::in_memory().unwrap();
+        let base = Utc::now();
+        for (i, key) in ["old", "newest", "middle"].iter().enumerate() {
+            let mut it = item(key, VaultCategory::Custom);
+            let age = [3, 1, 2][i];
+            it.metadata.updated = base - chrono::Duration::minutes(age);
+            store.set(key.to_string(), it).unwrap();
+        }
+        let keys: Vec<String> = store.list().unwrap().into_iter().map(|i| i.key).collect();
+        assert_eq!(keys, ["newest", "middle", "old"]);
+
+        let mut visited = Vec::new();
+        store
+            .for_each(&mut |item| {
+                visited.push(item.key.clone());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(visited, keys);
+
+        store.clear().unwrap();
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_list_by_category_selects_rows() {
+        let mut store = SqliteStore::in_memory().unwrap();
+        let base = Utc::now();
+        for (key, category, age) in [
+            ("email", VaultCategory::Contact, 2),
+            ("card", VaultCategory::Financial, 1),
+            ("phone", VaultCategory::Contact, 1),
+        ] {
+            let mut it = item(key, category);
+            it.metadata.updated = base - chrono::Duration::minutes(age);
+            store.set(key.to_string(), it).unwrap();
+        }
+
+        let keys = |category| -> Vec<String> {
+            store
+                .list_by_category(category)
+                .unwrap()
+                .into_iter()
+                .map(|i| i.key)
+                .collect()
+        };
+        assert_eq!(keys(VaultCategory::Contact), ["phone", "email"]);
+        assert_eq!(keys(VaultCategory::Financial), ["card"]);
+        assert!(keys(VaultCategory::Address).is_empty());
+    }
+
+    #[test]
+    fn test_two_handles_share_one_file() {
+        let path = db_path("shared");
+        let mut first = SqliteStore::open(&path).unwrap();
+        let mut second = SqliteStore::open(&path).unwrap();
+        first
+            .set("phone".to_string(), item("phone", VaultCategory::Contact))
+            .unwrap();
+        assert!(second.exists("phone"));
+
+        let writers: Vec<_> = [0, 1]
+            .into_iter()
+            .map(|n| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let mut store = SqliteStore::open(&path).unwrap();
+                    for i in 0..25 {
+                        store.touch("phone").unwrap();
+                        let key = format!("item-{}-{}", n, i);
+                        store
+                            .set(key.clone(), item(&key, VaultCategory::Custom))
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        // Every write from both threads landed, and no touch was lost
+        assert_eq!(first.len(), 51);
+        assert_eq!(
+            second
+                .get_and_touch("phone")
+                .unwrap()
+                .unwrap()
+                .metadata
+                .usage_count,
+            51
+        );
+        assert_eq!(
+            first.get("phone").unwrap().unwrap().metadata.usage_count,
+            51
+        );
+        second.delete("phone").unwrap();
+        assert!(!first.exists("phone"));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,25 @@
+/*!
+ * Test Helpers
+ *
+ * Item factories shared by the store tests here and, with the
+ * `test-helpers` feature, by the desktop app's tests.
+ */
+
+use chrono::Utc;
+
+use crate::{Provenance, ProvenanceSource, VaultCategory, VaultItem};
+
+/// Provenance of an item the user typed in just now
+pub fn user_entered() -> Provenance {
+    Provenance {
+        source: ProvenanceSource::UserEntered,
+        timestamp: Utc::now(),
+        confidence: 1.0,
+        origin: None,
+    }
+}
+
+/// A user-entered item labelled with its key
+pub fn item(key: &str, value: &str, category: VaultCategory) -> VaultItem {
+    VaultItem::new(key, value, key, category, user_entered())
+}
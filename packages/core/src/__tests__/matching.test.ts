@@ -3,6 +3,7 @@ import {
   matchByAutocomplete,
   matchByPattern,
   generateFillPlan,
+  UNREVIEWED_CONFIDENCE_CAP,
   type VaultItem,
   type FieldNode,
 } from '../matching';
@@ -360,4 +361,50 @@ describe('generateFillPlan', () => {
     expect(plan.recommendations).toHaveLength(0);
     expect(plan.unmatchedFields).toHaveLength(1);
   });
+
+  it('should skip rejected items and cap unreviewed ones at review', () => {
+    const formSnapshot = {
+      url: 'https://example.com/form',
+      title: 'Test Form',
+      domain: 'example.com',
+      fingerprint: {
+        fieldCount: 2,
+        fieldTypes: ['text', 'email'],
+        requiredCount: 0,
+        hash: 'test-form-789',
+      },
+      fields: [
+        {
+          id: 'field-1',
+          name: 'fname',
+          label: 'First Name',
+          type: 'text',
+          semantic: 'firstName',
+          required: false,
+          autocomplete: 'given-name',
+        },
+        {
+          id: 'field-2',
+          name: 'email',
+          label: 'Email',
+          type: 'email',
+          semantic: 'email',
+          required: false,
+          autocomplete: 'email',
+        },
+      ],
+      capturedAt: new Date().toISOString(),
+    };
+    const [firstName, email] = vaultItems;
+    const reviewed: VaultItem[] = [
+      { ...firstName, reviewStatus: 'rejected' },
+      { ...email, reviewStatus: 'unreviewed' },
+    ];
+
+    const plan = generateFillPlan(formSnapshot, reviewed);
+
+    expect(plan.recommendations.map(r => r.vaultKey)).toEqual(['email']);
+    expect(plan.recommendations[0].confidence).toBeLessThanOrEqual(UNREVIEWED_CONFIDENCE_CAP);
+    expect(plan.unmatchedFields).toEqual(['field-1']);
+  });
 });
@@ -12,6 +12,7 @@ export type {
   // Vault
   VaultItem,
   VaultCategory,
+  ReviewStatus,
   // Form Analysis
   FormBrief,
   FieldNode,
@@ -47,6 +48,7 @@ export {
   generateFillPlan,
   getMatchTierDescription,
   getConfidenceLevel,
+  UNREVIEWED_CONFIDENCE_CAP,
 } from './matching';
 
 // Export performance monitoring
@@ -19,6 +19,13 @@ import type {
 
 import { AUTOCOMPLETE_MAPPINGS, PATTERN_RULES } from './types';
 
+/**
+ * Highest confidence a match to an unreviewed vault item can have, so it is
+ * at most Review and never applied without the user seeing it
+ * (mirrors `UNREVIEWED_CONFIDENCE_CAP` in the desktop matcher)
+ */
+export const UNREVIEWED_CONFIDENCE_CAP = 0.95;
+
 // ============================================================================
 // Tier 1: Autocomplete-based Matching
 // ============================================================================
@@ -90,7 +97,7 @@ export function matchByAutocomplete(
     fieldId: field.id,
     vaultKey: vaultItem.key,
     confidence: mapping.confidence,
-    reason: `Matched via autocomplete="${field.autocomplete}"`,
+    reason: `Matched by autocomplete=${autocompleteValue}`,
     required: field.required,
     matchTier: 'autocomplete' as MatchTier,
   };
@@ -115,6 +122,28 @@ function containsPattern(text: string, patterns: string[]): boolean {
   return patterns.some(pattern => normalized.includes(pattern));
 }
 
+/**
+ * The field text containing one of the patterns, with where it came from
+ * (for the match explanation)
+ */
+function findPatternSource(
+  field: FieldNode,
+  patterns: string[]
+): { source: string; text: string; pattern: string } | undefined {
+  const sources: Array<[string, string | undefined]> = [
+    ['label', field.label],
+    ['name attribute', field.name],
+    ['placeholder', field.placeholder],
+  ];
+  for (const [source, text] of sources) {
+    if (!text) continue;
+    const normalized = normalizeText(text);
+    const pattern = patterns.find(p => normalized.includes(p));
+    if (pattern) return { source, text, pattern };
+  }
+  return undefined;
+}
+
 /**
  * Match a field using label/name patterns (Tier 2)
  *
@@ -145,11 +174,14 @@ export function matchByPattern(
 
     if (!vaultItem) continue;
 
+    const matched = findPatternSource(field, rule.labelPatterns);
     return {
       fieldId: field.id,
       vaultKey: vaultItem.key,
       confidence: rule.confidence,
-      reason: `Matched via pattern in "${field.label || field.name}"`,
+      reason: matched
+        ? `The ${matched.source} "${matched.text}" contains "${matched.pattern}"`
+        : `Matched via pattern in "${field.label || field.name}"`,
       required: field.required,
       matchTier: 'pattern' as MatchTier,
     };
@@ -246,11 +278,15 @@ export async function matchByLLM(
  * 1. Try autocomplete match (highest confidence)
  * 2. Try pattern match (medium confidence)
  * 3. Mark as unmatched (for LLM analysis later)
+ *
+ * Rejected vault items are never matched, and matches to unreviewed items
+ * are capped at `UNREVIEWED_CONFIDENCE_CAP`.
  */
 export function generateFillPlan(
   form: FormSnapshot,
-  vaultItems: VaultItem[]
+  allVaultItems: VaultItem[]
 ): FillPlan {
+  const vaultItems = allVaultItems.filter(item => item.reviewStatus !== 'rejected');
   const recommendations: FillRecommendation[] = [];
   const unmatchedFields: string[] = [];
   const warnings: string[] = [];
@@ -273,6 +309,16 @@ export function generateFillPlan(
     }
 
     if (recommendation) {
+      const vaultKey = recommendation.vaultKey;
+      const unreviewed = vaultItems.some(
+        item => item.key === vaultKey && item.reviewStatus === 'unreviewed'
+      );
+      if (unreviewed) {
+        recommendation.confidence = Math.min(
+          recommendation.confidence,
+          UNREVIEWED_CONFIDENCE_CAP
+        );
+      }
       recommendations.push(recommendation);
     } else {
       unmatchedFields.push(field.id);
@@ -325,10 +371,16 @@ export function generateFillPlan(
  */
 export function getMatchTierDescription(tier: MatchTier): string {
   switch (tier) {
+    case 'override':
+      return 'Your saved choice for this field';
+    case 'template':
+      return 'High confidence (saved form template)';
     case 'autocomplete':
       return 'High confidence (HTML autocomplete attribute)';
     case 'pattern':
       return 'Medium confidence (label/name pattern match)';
+    case 'session':
+      return 'Same field as an earlier step of this form';
     case 'llm':
       return 'AI-inferred (semantic analysis)';
   }
@@ -30,6 +30,14 @@ export interface Provenance {
 // Vault - User data storage
 // ============================================================================
 
+/**
+ * Where an item stands in the review queue
+ *
+ * Rejected items are never matched; unreviewed items are matched but never
+ * applied without review.
+ */
+export type ReviewStatus = 'unreviewed' | 'approved' | 'rejected';
+
 /**
  * A single item stored in the user's vault
  */
@@ -56,6 +64,15 @@ export interface VaultItem {
     lastUsed?: Date;
     usageCount?: number;
   };
+
+  /** Whether reads of this item are recorded in the access log */
+  sensitive?: boolean;
+
+  /** When the value may have gone stale and should be checked again */
+  reviewAfter?: Date;
+
+  /** Review queue status (missing means approved) */
+  reviewStatus?: ReviewStatus;
 }
 
 // ============================================================================
@@ -187,6 +204,9 @@ export interface FieldNode {
 
   /** Options for select/radio/checkbox fields */
   options?: SelectOption[];
+
+  /** Option already chosen in a country select (not captured for other fields) */
+  selectedValue?: string;
 }
 
 // ============================================================================
@@ -237,6 +257,15 @@ export interface FormSnapshot {
 
   /** All detected fields (without values) */
   fields: FieldNode[];
+
+  /** Groups the steps of a multi-step (wizard) form */
+  formSessionId?: string;
+
+  /** 1-based step within the multi-step form */
+  step?: number;
+
+  /** Number of steps in the multi-step form, if known */
+  totalSteps?: number;
 }
 
 // ============================================================================
@@ -246,7 +275,13 @@ export interface FormSnapshot {
 /**
  * How a match was determined
  */
-export type MatchTier = 'autocomplete' | 'pattern' | 'llm';
+export type MatchTier =
+  | 'override'
+  | 'template'
+  | 'autocomplete'
+  | 'pattern'
+  | 'session'
+  | 'llm';
 
 /**
  * A recommendation for filling a specific field
@@ -269,12 +304,21 @@ export interface FillRecommendation {
 
   /** How the match was determined */
   matchTier: MatchTier;
+
+  /** Value composed from several vault items (e.g. a whole address), used instead of the vault item's value */
+  value?: string;
 }
 
 /**
  * A complete plan for filling a form
  */
 export interface FillPlan {
+  /** Set on plans generated by the desktop backend, which can be accepted */
+  planId?: string;
+
+  /** Content hash, the same for identical plans across regenerations */
+  planHash?: string;
+
   /** Form fingerprint hash for identification */
   formFingerprint: string;
 
@@ -342,6 +386,12 @@ export interface FillCommand {
 
   /** Command expires after this time (ISO 8601) */
   expiresAt: string;
+
+  /** Report each field's pre-fill value to the desktop so the fill can be undone */
+  captureUndo?: boolean;
+
+  /** Content hash of the plan the command was accepted from */
+  planHash?: string;
 }
 
 // ============================================================================